@@ -0,0 +1,217 @@
+use crate::{
+    archive, auth, guild, health, i18n, invalid_name, normalize_name, save_profile,
+    save_writer::{self, StorageFailure},
+    PlayerProfile, PlayerStore,
+};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// 同期の回数を数える窓
+const METER_WINDOW_SECS: i64 = 60;
+// 「最近遊んだ」とみなす期間
+const ACTIVE_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+// 直近 1 分の同期の時刻と、起動してからの合計
+#[derive(Default)]
+pub struct SyncMeter {
+    recent: VecDeque<i64>,
+    total: u64,
+}
+
+impl SyncMeter {
+    pub fn record(&mut self, now: i64) {
+        self.recent.push_back(now);
+        self.total += 1;
+        self.expire(now);
+    }
+
+    fn expire(&mut self, now: i64) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&at| at <= now - METER_WINDOW_SECS)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    fn per_minute(&mut self, now: i64) -> usize {
+        self.expire(now);
+        self.recent.len()
+    }
+}
+
+#[derive(Serialize)]
+struct Metrics {
+    timestamp: i64,
+    uptime_secs: i64,
+    player_count: usize,
+    // 放置で書庫に移したプレイヤー（player_count には含めない）
+    archived_players: usize,
+    flagged_players: usize,
+    active_players_24h: usize,
+    guild_count: usize,
+    // WebSocket で接続中のクライアント
+    connected_clients: usize,
+    syncs_last_minute: usize,
+    syncs_total: u64,
+    max_players: Option<usize>,
+    maintenance: bool,
+    // 書けなくなっていれば保存先と理由
+    storage_failure: Option<StorageFailure>,
+}
+
+pub async fn metrics(req: HttpRequest, store: web::Data<PlayerStore>) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let now = Utc::now().timestamp();
+    let mut state = store.lock().unwrap();
+    let metrics = Metrics {
+        timestamp: now,
+        uptime_secs: (now - state.started_at).max(0),
+        player_count: state.players.len(),
+        archived_players: state.archived.len(),
+        flagged_players: state.players.values().filter(|p| p.flagged).count(),
+        active_players_24h: state
+            .players
+            .values()
+            .filter(|p| p.last_update > now - ACTIVE_WINDOW_SECS)
+            .count(),
+        guild_count: state.guilds.len(),
+        connected_clients: state.sockets.values().map(Vec::len).sum(),
+        syncs_last_minute: state.syncs.per_minute(now),
+        syncs_total: state.syncs.total,
+        max_players: health::max_players(),
+        maintenance: state.maintenance.is_enabled(),
+        storage_failure: save_writer::status(),
+    };
+    HttpResponse::Ok().json(metrics)
+}
+
+// 全プロファイルをまとめて返す（バックアップ・移行用）
+pub async fn export(req: HttpRequest, store: web::Data<PlayerStore>) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let state = store.lock().unwrap();
+    let mut profiles: Vec<&PlayerProfile> = state.players.values().collect();
+    profiles.sort_by(|a, b| a.player_id.cmp(&b.player_id));
+    HttpResponse::Ok().json(serde_json::json!({
+        "exported_at": Utc::now().timestamp(),
+        "players": profiles,
+    }))
+}
+
+// トークン・接続を消し、所属ギルドからも抜けさせる（レイドの貢献記録は残す）
+// プロファイルは data/deleted に移すだけなので、手作業で戻せる
+pub async fn delete_player(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let mut state = store.lock().unwrap();
+    let removed = state
+        .players
+        .remove(player_id.as_str())
+        .or_else(|| archive::take_archived(&mut state, &player_id));
+    let Some(profile) = removed else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    };
+    state
+        .name_index
+        .remove(&normalize_name(&profile.player_name));
+    state.sockets.remove(&profile.player_id);
+    state.tokens.revoke(&profile.player_id);
+    state.tokens.save();
+    let departure = profile
+        .guild_id
+        .as_deref()
+        .map(|guild_id| guild::depart(&mut state, guild_id, &profile.player_id));
+    drop(state);
+
+    archive::discard(&profile);
+    if let Some(departure) = departure {
+        departure.save();
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "deleted": profile.player_id }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameRequest {
+    player_name: String,
+}
+
+// 登録と同じ規則で名前を変える（大文字小文字だけの変更もできる）
+pub async fn rename_player(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    data: web::Json<RenameRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let name = data.player_name.trim();
+    if let Some(response) = invalid_name(&req, name) {
+        return response;
+    }
+    let lower_name = normalize_name(name);
+
+    let mut state = store.lock().unwrap();
+    let Some(old_name) = state
+        .players
+        .get(player_id.as_str())
+        .map(|p| p.player_name.clone())
+    else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    };
+    if state
+        .name_index
+        .get(&lower_name)
+        .is_some_and(|owner| owner != player_id.as_str())
+    {
+        return HttpResponse::Conflict().json(i18n::error(&req, "player_name_taken"));
+    }
+    state.name_index.remove(&normalize_name(&old_name));
+    state.name_index.insert(lower_name, player_id.to_string());
+    let profile = {
+        let profile = state.players.get_mut(player_id.as_str()).unwrap();
+        profile.player_name = name.to_string();
+        profile.clone()
+    };
+    drop(state);
+
+    save_profile(&profile);
+    HttpResponse::Ok().json(profile)
+}
+
+// トークン導入前のプレイヤーや、トークンをなくしたプレイヤーに新しく発行する（古いトークンは使えなくなる）
+// 本人確認は管理者が行い、返したトークンを本人に渡す
+pub async fn issue_token(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let mut state = store.lock().unwrap();
+    let known = state.players.contains_key(player_id.as_str())
+        || state.archived.contains(player_id.as_str());
+    if !known {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    }
+    let token = state.tokens.issue(&player_id);
+    state.sockets.remove(player_id.as_str());
+    state.tokens.save();
+    HttpResponse::Ok().json(serde_json::json!({
+        "player_id": player_id.as_str(),
+        "token": token,
+    }))
+}
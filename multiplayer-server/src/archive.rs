@@ -0,0 +1,183 @@
+use crate::{
+    data_root, profile_json, profile_path, save_writer, PlayerProfile, PlayerStore, ServerState,
+};
+use chrono::Utc;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// 最後の同期・ログインからこの日数がたったプロファイルを書庫に移す（0 なら移さない）
+pub const DEFAULT_ARCHIVE_AFTER_DAYS: u64 = 180;
+const DAY_SECS: i64 = 24 * 60 * 60;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// MULTIPLAYER_ARCHIVE_AFTER_DAYS（--archive-after-days から設定される）
+pub fn archive_after_days() -> Option<u64> {
+    let days = std::env::var("MULTIPLAYER_ARCHIVE_AFTER_DAYS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_ARCHIVE_AFTER_DAYS);
+    (days > 0).then_some(days)
+}
+
+// 放置で移したもの（ログインで戻る）
+fn archive_dir() -> PathBuf {
+    data_root().join("archive")
+}
+
+// 管理者が削除したもの（手作業で戻せるように残す）
+fn deleted_dir() -> PathBuf {
+    data_root().join("deleted")
+}
+
+fn archive_path(player_id: &str) -> PathBuf {
+    archive_dir().join(format!("{}.json", player_id))
+}
+
+fn read(path: &Path) -> Option<PlayerProfile> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write(dir: PathBuf, profile: &PlayerProfile) -> std::io::Result<()> {
+    fs::create_dir_all(&dir)?;
+    let json = serde_json::to_string_pretty(profile).unwrap_or_default();
+    fs::write(dir.join(format!("{}.json", profile.player_id)), json)
+}
+
+// 書庫の ID だけを覚え、名前は他の人に取られないよう予約したままにする
+pub fn load(state: &mut ServerState) {
+    let Ok(entries) = fs::read_dir(archive_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if let Some(profile) = read(&entry.path()) {
+            if state.players.contains_key(&profile.player_id) {
+                continue;
+            }
+            state.name_index.insert(
+                profile.player_name.to_lowercase(),
+                profile.player_id.clone(),
+            );
+            state.archived.insert(profile.player_id);
+        }
+    }
+}
+
+// 期限を過ぎたプロファイルを選ぶ。状態は変えず、書庫に書けてから settle で移す
+// WebSocket で接続中のプレイヤーは残す
+pub fn sweep(state: &ServerState, now: i64) -> Vec<PlayerProfile> {
+    let Some(days) = archive_after_days() else {
+        return Vec::new();
+    };
+    let cutoff = now.saturating_sub((days as i64).saturating_mul(DAY_SECS));
+    state
+        .players
+        .values()
+        .filter(|profile| profile.last_update.max(profile.last_seen) < cutoff)
+        .filter(|profile| !state.sockets.contains_key(&profile.player_id))
+        .cloned()
+        .collect()
+}
+
+// 書庫に書く（ロックの外で）。書けたものだけ返し、書けなかったものは通常のマップに残る
+pub fn persist(profiles: Vec<PlayerProfile>) -> Vec<PlayerProfile> {
+    profiles
+        .into_iter()
+        .filter(|profile| match write(archive_dir(), profile) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("Failed to archive profile: {}", err);
+                false
+            }
+        })
+        .collect()
+}
+
+// 書庫に書けたプロファイルを通常のマップ（一覧・ランキング）と保存先から外し、移した数を返す
+// 書いている間に同期・ログイン・接続したプレイヤーは移さず、書いた分を消す
+pub fn settle(state: &mut ServerState, written: Vec<PlayerProfile>) -> usize {
+    let mut moved = 0;
+    for profile in written {
+        let unchanged = state
+            .players
+            .get(&profile.player_id)
+            .is_some_and(|current| profile_json(current) == profile_json(&profile))
+            && !state.sockets.contains_key(&profile.player_id);
+        if !unchanged {
+            let _ = fs::remove_file(archive_path(&profile.player_id));
+            continue;
+        }
+        state.players.remove(&profile.player_id);
+        save_writer::remove(profile_path(&profile.player_id));
+        state.archived.insert(profile.player_id);
+        moved += 1;
+    }
+    moved
+}
+
+// 書庫から通常のマップに戻す。ファイルの移動は finish_restore で行う
+pub fn restore(state: &mut ServerState, player_id: &str) -> Option<PlayerProfile> {
+    if !state.archived.contains(player_id) {
+        return None;
+    }
+    let profile = read(&archive_path(player_id))?;
+    state.archived.remove(player_id);
+    state
+        .players
+        .insert(profile.player_id.clone(), profile.clone());
+    Some(profile)
+}
+
+// 通常の保存先に書けたのを待ってから書庫のファイルを消す（ブロックする）
+pub fn finish_restore(profile: &PlayerProfile) {
+    match save_writer::write(profile_path(&profile.player_id), profile_json(profile)) {
+        Ok(()) => {
+            let _ = fs::remove_file(archive_path(&profile.player_id));
+        }
+        Err(err) => eprintln!("Failed to restore profile: {}", err),
+    }
+}
+
+// 書庫にあるプレイヤーを削除するときに読み出す
+pub fn take_archived(state: &mut ServerState, player_id: &str) -> Option<PlayerProfile> {
+    if !state.archived.remove(player_id) {
+        return None;
+    }
+    read(&archive_path(player_id))
+}
+
+// 削除したプロファイルは消さずに deleted に移す
+pub fn discard(profile: &PlayerProfile) {
+    if let Err(err) = write(deleted_dir(), profile) {
+        eprintln!("Failed to keep deleted profile: {}", err);
+        return;
+    }
+    save_writer::remove(profile_path(&profile.player_id));
+    let _ = fs::remove_file(archive_path(&profile.player_id));
+}
+
+// 1 時間ごとに書庫へ移す（メンテナンス中は保存先に触らない）
+pub async fn run(store: PlayerStore) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = {
+            let state = store.lock().unwrap();
+            if state.maintenance.is_enabled() {
+                continue;
+            }
+            sweep(&state, Utc::now().timestamp())
+        };
+        if expired.is_empty() {
+            continue;
+        }
+        let written = persist(expired);
+        let moved = settle(&mut store.lock().unwrap(), written);
+        if moved > 0 {
+            println!("Archived {} inactive player profiles", moved);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{auth, i18n, save_profile, PlayerStore, UpgradesProgress};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+// 対戦用の軍勢。戦闘はクライアントが決定的に計算するので、サーバーは強化の段階だけを持つ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArmySnapshot {
+    upgrades: UpgradesProgress,
+    #[serde(default)]
+    prestige_attack: u32,
+    #[serde(default)]
+    prestige_hp: u32,
+    #[serde(default)]
+    created_at: i64,
+}
+
+#[derive(Serialize)]
+struct ArmyResponse<'a> {
+    player_id: &'a str,
+    player_name: &'a str,
+    #[serde(flatten)]
+    army: &'a ArmySnapshot,
+}
+
+// 同期済みの進行度を超える強化は受け付けない
+fn exceeds_progress(army: &ArmySnapshot, profile: &crate::PlayerProfile) -> bool {
+    let synced = profile.progress.upgrades.levels();
+    let prestige = profile
+        .progress
+        .prestige
+        .as_ref()
+        .map(|prestige| &prestige.upgrades);
+    army.upgrades
+        .levels()
+        .iter()
+        .zip(synced)
+        .any(|(&level, max)| level > max)
+        || army.prestige_attack > prestige.map_or(0, |upgrades| upgrades.attack)
+        || army.prestige_hp > prestige.map_or(0, |upgrades| upgrades.hp)
+}
+
+pub async fn upload(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    data: web::Json<ArmySnapshot>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let mut state = store.lock().unwrap();
+    if !state.players.contains_key(player_id.as_str()) {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    }
+    if !auth::is_authorized(&req, &state, &player_id) {
+        return auth::unauthorized(&req);
+    }
+    let profile = state.players.get_mut(player_id.as_str()).unwrap();
+    let mut army = data.into_inner();
+    if exceeds_progress(&army, profile) {
+        return HttpResponse::UnprocessableEntity()
+            .json(i18n::error(&req, "army_exceeds_progress"));
+    }
+    army.created_at = Utc::now().timestamp();
+    profile.army = Some(army.clone());
+    let profile = profile.clone();
+    drop(state);
+
+    save_profile(&profile);
+    HttpResponse::Ok().json(ArmyResponse {
+        player_id: &profile.player_id,
+        player_name: &profile.player_name,
+        army: &army,
+    })
+}
+
+// 対戦相手を選べるよう、トークン無しで誰でも取得できる
+pub async fn get(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let state = store.lock().unwrap();
+    match state
+        .players
+        .get(player_id.as_str())
+        .and_then(|profile| Some((profile, profile.army.as_ref()?)))
+    {
+        Some((profile, army)) => HttpResponse::Ok().json(ArmyResponse {
+            player_id: &profile.player_id,
+            player_name: &profile.player_name,
+            army,
+        }),
+        None => HttpResponse::NotFound().json(i18n::error(&req, "no_army")),
+    }
+}
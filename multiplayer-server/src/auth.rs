@@ -0,0 +1,169 @@
+use crate::{i18n, save_writer, ServerState};
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+// プレイヤーごとの認証トークン（プロファイルとは別に保存し、API の応答には含めない）
+#[derive(Default)]
+pub struct TokenStore {
+    tokens: HashMap<String, String>, // player_id -> token
+    owners: HashMap<String, String>, // token -> player_id（レート制限でリクエストの主を引く）
+}
+
+fn tokens_path() -> PathBuf {
+    let mut path = crate::data_root();
+    path.push("tokens.json");
+    path
+}
+
+impl TokenStore {
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(&tokens_path())
+    }
+
+    // 読めなければ新しい世代のバックアップから戻す。どれも読めなければ起動させない
+    // （空のまま始めると全員のトークンが無効になり、管理者に発行し直してもらうまで誰も同期できない）
+    fn load_from(path: &Path) -> io::Result<Self> {
+        let read = |path: &Path| -> Option<HashMap<String, String>> {
+            serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+        };
+        if let Some(tokens) = read(path) {
+            return Ok(Self::from_tokens(tokens));
+        }
+        let backups =
+            (1..=save_writer::BACKUPS).map(|generation| save_writer::backup_path(path, generation));
+        if !path.exists() && !backups.clone().any(|backup| backup.exists()) {
+            return Ok(Self::default());
+        }
+        for backup in backups {
+            if let Some(tokens) = read(&backup) {
+                eprintln!(
+                    "{} could not be read; restored the tokens from {}",
+                    path.display(),
+                    backup.display()
+                );
+                return Ok(Self::from_tokens(tokens));
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} and its backups could not be read; restore one of them, or remove them all to start without tokens",
+                path.display()
+            ),
+        ))
+    }
+
+    fn from_tokens(tokens: HashMap<String, String>) -> Self {
+        let owners = tokens
+            .iter()
+            .map(|(player_id, token)| (token.clone(), player_id.clone()))
+            .collect();
+        Self { tokens, owners }
+    }
+
+    // プロファイルと同じく書き込み用のスレッドで一時ファイルから置き換え、世代のバックアップも残す
+    pub fn save(&self) {
+        let json = serde_json::to_vec_pretty(&self.tokens).unwrap_or_default();
+        save_writer::submit(tokens_path(), json);
+    }
+
+    pub fn issue(&mut self, player_id: &str) -> String {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.revoke(player_id);
+        self.tokens.insert(player_id.to_string(), token.clone());
+        self.owners.insert(token.clone(), player_id.to_string());
+        token
+    }
+
+    pub fn revoke(&mut self, player_id: &str) {
+        if let Some(token) = self.tokens.remove(player_id) {
+            self.owners.remove(&token);
+        }
+    }
+
+    pub fn get(&self, player_id: &str) -> Option<&str> {
+        self.tokens.get(player_id).map(String::as_str)
+    }
+
+    // トークンを発行したプレイヤー（発行していないトークンなら None）
+    pub fn owner(&self, token: &str) -> Option<&str> {
+        self.owners.get(token).map(String::as_str)
+    }
+
+    // トークン未発行のプレイヤーは常に不一致（再登録で発行される）
+    pub fn verify(&self, player_id: &str, token: Option<&str>) -> bool {
+        match (self.get(player_id), token) {
+            (Some(expected), Some(token)) => constant_time_eq(expected, token),
+            _ => false,
+        }
+    }
+}
+
+// 比較にかかる時間から一致した桁数を推測されないようにする
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+// Authorization: Bearer <token>
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+pub fn is_authorized(req: &HttpRequest, state: &ServerState, player_id: &str) -> bool {
+    state.tokens.verify(player_id, bearer_token(req))
+}
+
+// 管理用 API は MULTIPLAYER_ADMIN_TOKEN を設定したときだけ使える
+pub fn is_admin(req: &HttpRequest) -> bool {
+    let Ok(expected) = std::env::var("MULTIPLAYER_ADMIN_TOKEN") else {
+        return false;
+    };
+    match bearer_token(req) {
+        Some(token) => !expected.is_empty() && constant_time_eq(&expected, token),
+        None => false,
+    }
+}
+
+pub fn unauthorized(req: &HttpRequest) -> HttpResponse {
+    HttpResponse::Unauthorized().json(i18n::error(req, "unauthorized"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_tokens_fall_back_to_a_backup_or_refuse_to_load() {
+        let dir = std::env::temp_dir().join(format!("kurikka-token-tests-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+
+        assert!(TokenStore::load_from(&path).unwrap().tokens.is_empty());
+
+        fs::write(&path, "{ truncated").unwrap();
+        assert!(matches!(
+            TokenStore::load_from(&path),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData
+        ));
+
+        fs::write(save_writer::backup_path(&path, 2), r#"{"player": "token"}"#).unwrap();
+        let store = TokenStore::load_from(&path).unwrap();
+        assert_eq!(store.owner("token"), Some("player"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+// 負荷試験: N 人のクライアントが登録 → 同期 → ポーリングを繰り返し、
+// 操作ごとのレイテンシ分布とエラー率を表示する
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+struct Options {
+    url: String,
+    clients: usize,
+    duration: Duration,
+    interval: Duration,
+}
+
+const USAGE: &str =
+    "Usage: server-loadtest [--url URL] [--clients N] [--duration SECS] [--interval-ms MS]
+
+  --url          target server (default http://localhost:8080)
+  --clients      concurrent simulated clients (default 50)
+  --duration     seconds to run after registration (default 30)
+  --interval-ms  pause between each client's sync/poll rounds (default 1000)
+
+Each client registers a real player named loadtest-<run>-<n> on the target.";
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        url: "http://localhost:8080".to_string(),
+        clients: 50,
+        duration: Duration::from_secs(30),
+        interval: Duration::from_millis(1000),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n\n{}", flag, USAGE))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{} expects a number, got {}", flag, value))
+        };
+        match flag.as_str() {
+            "--url" => options.url = value.trim_end_matches('/').to_string(),
+            "--clients" => options.clients = number()?.max(1) as usize,
+            "--duration" => options.duration = Duration::from_secs(number()?),
+            "--interval-ms" => options.interval = Duration::from_millis(number()?),
+            _ => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+// 操作ごとの計測結果
+#[derive(Default)]
+struct OpStats {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+type Stats = BTreeMap<&'static str, OpStats>;
+
+fn record(stats: &mut Stats, op: &'static str, started: Instant, ok: bool) {
+    let entry = stats.entry(op).or_default();
+    if ok {
+        entry.latencies.push(started.elapsed());
+    } else {
+        entry.errors += 1;
+    }
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Option<serde_json::Value> {
+    let response = request.send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+async fn run_client(
+    client: reqwest::Client,
+    options: &Options,
+    name: String,
+    deadline: Instant,
+) -> Stats {
+    let mut stats = Stats::new();
+
+    let started = Instant::now();
+    let registered = send(
+        client
+            .post(format!("{}/api/player/register", options.url))
+            .json(&json!({ "player_name": name })),
+    )
+    .await;
+    record(&mut stats, "register", started, registered.is_some());
+    let Some((player_id, token)) = registered.as_ref().and_then(|body| {
+        Some((
+            body["player_id"].as_str()?.to_string(),
+            body["token"].as_str()?.to_string(),
+        ))
+    }) else {
+        return stats;
+    };
+
+    let mut round: u64 = 0;
+    while Instant::now() < deadline {
+        round += 1;
+        let progress = json!({
+            "stage": 1 + round / 10,
+            "coins": round * 100,
+            "upgrades": {
+                "small_attack": 0, "medium_attack": 0, "large_attack": 0,
+                "small_hp": 0, "medium_hp": 0, "large_hp": 0,
+                "small_speed": 0, "medium_speed": 0, "large_speed": 0,
+                "coin_rate": 0, "base_hp": 0,
+            },
+            "max_player_base_hp": 1000.0,
+            "max_enemy_base_hp": 500.0,
+        });
+
+        let started = Instant::now();
+        let synced = send(
+            client
+                .post(format!("{}/api/player/{}/sync", options.url, player_id))
+                .bearer_auth(&token)
+                .json(&json!({ "progress": progress })),
+        )
+        .await;
+        record(&mut stats, "sync", started, synced.is_some());
+
+        let started = Instant::now();
+        let fetched = send(
+            client
+                .get(format!("{}/api/player/{}", options.url, player_id))
+                .bearer_auth(&token),
+        )
+        .await;
+        record(&mut stats, "get_player", started, fetched.is_some());
+
+        let started = Instant::now();
+        let board = send(client.get(format!("{}/api/leaderboard", options.url))).await;
+        record(&mut stats, "leaderboard", started, board.is_some());
+
+        tokio::time::sleep(options.interval).await;
+    }
+    stats
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((sorted.len() as f64 * p / 100.0).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+fn print_report(stats: Stats, elapsed: Duration) {
+    println!(
+        "{:<12} {:>8} {:>7} {:>8} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "operation",
+        "requests",
+        "errors",
+        "error%",
+        "req/s",
+        "p50 ms",
+        "p90 ms",
+        "p99 ms",
+        "max ms"
+    );
+    for (op, mut op_stats) in stats {
+        op_stats.latencies.sort();
+        let ok = op_stats.latencies.len() as u64;
+        let total = ok + op_stats.errors;
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        println!(
+            "{:<12} {:>8} {:>7} {:>7.2}% {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+            op,
+            total,
+            op_stats.errors,
+            op_stats.errors as f64 / total.max(1) as f64 * 100.0,
+            total as f64 / elapsed.as_secs_f64().max(0.001),
+            ms(percentile(&op_stats.latencies, 50.0)),
+            ms(percentile(&op_stats.latencies, 90.0)),
+            ms(percentile(&op_stats.latencies, 99.0)),
+            ms(op_stats.latencies.last().copied().unwrap_or_default()),
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .pool_max_idle_per_host(options.clients)
+        .build()
+        .expect("failed to build HTTP client");
+    let run_id = chrono::Utc::now().timestamp();
+    println!(
+        "Load testing {} with {} clients for {}s...",
+        options.url,
+        options.clients,
+        options.duration.as_secs()
+    );
+
+    let options = std::sync::Arc::new(options);
+    let started = Instant::now();
+    let deadline = started + options.duration;
+    let handles: Vec<_> = (0..options.clients)
+        .map(|index| {
+            let client = client.clone();
+            let options = std::sync::Arc::clone(&options);
+            let name = format!("loadtest-{}-{}", run_id, index);
+            tokio::spawn(async move { run_client(client, &options, name, deadline).await })
+        })
+        .collect();
+
+    let mut stats = Stats::new();
+    for handle in handles {
+        let Ok(client_stats) = handle.await else {
+            continue;
+        };
+        for (op, op_stats) in client_stats {
+            let entry = stats.entry(op).or_default();
+            entry.latencies.extend(op_stats.latencies);
+            entry.errors += op_stats.errors;
+        }
+    }
+    print_report(stats, started.elapsed());
+}
@@ -0,0 +1,52 @@
+use actix_web::http::header::{self, HttpDate};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 本文のハッシュを ETag、内容の最終更新時刻を Last-Modified として付け、
+// クライアントの手元にあるものと同じなら 304 で本文を省く
+pub fn conditional_json<T: Serialize>(
+    req: &HttpRequest,
+    body: &T,
+    last_modified: i64,
+) -> HttpResponse {
+    let json = serde_json::to_string(body).unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+    let etag = format!("\"{:x}\"", hasher.finish());
+    let modified = HttpDate::from(UNIX_EPOCH + Duration::from_secs(last_modified.max(0) as u64));
+
+    if is_not_modified(req, &etag, last_modified) {
+        return HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .insert_header((header::LAST_MODIFIED, modified.to_string()))
+            .finish();
+    }
+
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .insert_header((header::LAST_MODIFIED, modified.to_string()))
+        .content_type("application/json")
+        .body(json)
+}
+
+// If-None-Match があればそれを優先し、If-Modified-Since は無視する（RFC 9110）
+fn is_not_modified(req: &HttpRequest, etag: &str, last_modified: i64) -> bool {
+    let headers = req.headers();
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag == etag || tag.strip_prefix("W/") == Some(etag))
+        });
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<HttpDate>().ok())
+        .and_then(|since| SystemTime::from(since).duration_since(UNIX_EPOCH).ok())
+        .is_some_and(|since| last_modified <= since.as_secs() as i64)
+}
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+const DEFAULT_BIND: &str = "0.0.0.0:8080";
+const DEFAULT_PORT: u16 = 8080;
+
+const USAGE: &str = "Usage: server [--bind ADDR] [--port PORT] [--data-dir DIR] [--max-players N]
+              [--rate-limit N] [--player-rate-limit N] [--archive-after-days N]
+              [--season N] [--season-ends-at UNIX_TIME] [--season-days N]
+
+Rate limits are requests per minute per IP address / per player (0 disables).
+Profiles inactive for --archive-after-days are archived until the next login (0 disables).
+--season starts a new season when it is higher than the saved one. When a season
+ends its standings are archived and the next one lasts --season-days (0 = no end).
+Flags take precedence over the environment variables MULTIPLAYER_BIND,
+MULTIPLAYER_DATA_DIR, MULTIPLAYER_MAX_PLAYERS, MULTIPLAYER_RATE_LIMIT,
+MULTIPLAYER_PLAYER_RATE_LIMIT, MULTIPLAYER_ARCHIVE_AFTER_DAYS, MULTIPLAYER_SEASON,
+MULTIPLAYER_SEASON_ENDS_AT and MULTIPLAYER_SEASON_DAYS.
+The admin token is only read from MULTIPLAYER_ADMIN_TOKEN.";
+
+// 起動時の設定（フラグ > 環境変数 > 既定値）
+// 管理者トークンはプロセス一覧から見えないよう環境変数でだけ受け取る
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub data_dir: Option<PathBuf>,
+    // 0 なら無制限
+    pub max_players: Option<usize>,
+    // 1 分あたりのリクエスト数（IP ごと・プレイヤーごと、0 なら無制限）
+    pub rate_limit: Option<u32>,
+    pub player_rate_limit: Option<u32>,
+    // 放置されたプロファイルを書庫に移すまでの日数（0 なら移さない）
+    pub archive_after_days: Option<u64>,
+    // シーズン番号・終了時刻（UNIX 秒）・次のシーズンの長さ（日）
+    pub season: Option<u32>,
+    pub season_ends_at: Option<i64>,
+    pub season_days: Option<u64>,
+}
+
+pub enum Parsed {
+    Run(ServerConfig),
+    Help,
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+        Self {
+            bind: var("MULTIPLAYER_BIND").unwrap_or_else(|| DEFAULT_BIND.to_string()),
+            data_dir: var("MULTIPLAYER_DATA_DIR").map(PathBuf::from),
+            max_players: var("MULTIPLAYER_MAX_PLAYERS").and_then(|value| value.trim().parse().ok()),
+            rate_limit: var("MULTIPLAYER_RATE_LIMIT").and_then(|value| value.trim().parse().ok()),
+            player_rate_limit: var("MULTIPLAYER_PLAYER_RATE_LIMIT")
+                .and_then(|value| value.trim().parse().ok()),
+            archive_after_days: var("MULTIPLAYER_ARCHIVE_AFTER_DAYS")
+                .and_then(|value| value.trim().parse().ok()),
+            season: var("MULTIPLAYER_SEASON").and_then(|value| value.trim().parse().ok()),
+            season_ends_at: var("MULTIPLAYER_SEASON_ENDS_AT")
+                .and_then(|value| value.trim().parse().ok()),
+            season_days: var("MULTIPLAYER_SEASON_DAYS").and_then(|value| value.trim().parse().ok()),
+        }
+    }
+
+    // "--flag value" と "--flag=value" のどちらも受け付ける
+    pub fn parse(mut self, args: impl IntoIterator<Item = String>) -> Result<Parsed, String> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if flag == "-h" || flag == "--help" {
+                return Ok(Parsed::Help);
+            }
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match flag.as_str() {
+                "--bind" => self.bind = value()?,
+                "--port" => {
+                    let port: u16 = value()?
+                        .parse()
+                        .map_err(|_| "--port must be a number from 0 to 65535".to_string())?;
+                    let host = self
+                        .bind
+                        .rsplit_once(':')
+                        .map_or(self.bind.as_str(), |(host, _)| host);
+                    self.bind = format!("{}:{}", host, port);
+                }
+                "--data-dir" => self.data_dir = Some(PathBuf::from(value()?)),
+                "--max-players" => {
+                    self.max_players = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--max-players must be a number".to_string())?,
+                    )
+                }
+                "--rate-limit" => {
+                    self.rate_limit = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--rate-limit must be a number".to_string())?,
+                    )
+                }
+                "--player-rate-limit" => {
+                    self.player_rate_limit = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--player-rate-limit must be a number".to_string())?,
+                    )
+                }
+                "--archive-after-days" => {
+                    self.archive_after_days = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--archive-after-days must be a number".to_string())?,
+                    )
+                }
+                "--season" => {
+                    self.season = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--season must be a number".to_string())?,
+                    )
+                }
+                "--season-ends-at" => {
+                    self.season_ends_at = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--season-ends-at must be a UNIX time".to_string())?,
+                    )
+                }
+                "--season-days" => {
+                    self.season_days = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "--season-days must be a number".to_string())?,
+                    )
+                }
+                _ => return Err(format!("Unknown argument: {}\n\n{}", flag, USAGE)),
+            }
+        }
+        // ポートを省いたアドレスは既定のポートで待ち受ける
+        if !self.bind.contains(':') {
+            self.bind = format!("{}:{}", self.bind, DEFAULT_PORT);
+        }
+        Ok(Parsed::Run(self))
+    }
+
+    // 保存先・人数の上限・レート制限・書庫の期限・シーズンは各モジュールが環境変数から読むので、決まった値を書き戻す
+    pub fn apply(&self) {
+        if let Some(dir) = &self.data_dir {
+            std::env::set_var("MULTIPLAYER_DATA_DIR", dir);
+        }
+        if let Some(max) = self.max_players {
+            std::env::set_var("MULTIPLAYER_MAX_PLAYERS", max.to_string());
+        }
+        if let Some(limit) = self.rate_limit {
+            std::env::set_var("MULTIPLAYER_RATE_LIMIT", limit.to_string());
+        }
+        if let Some(limit) = self.player_rate_limit {
+            std::env::set_var("MULTIPLAYER_PLAYER_RATE_LIMIT", limit.to_string());
+        }
+        if let Some(days) = self.archive_after_days {
+            std::env::set_var("MULTIPLAYER_ARCHIVE_AFTER_DAYS", days.to_string());
+        }
+        if let Some(season) = self.season {
+            std::env::set_var("MULTIPLAYER_SEASON", season.to_string());
+        }
+        if let Some(ends_at) = self.season_ends_at {
+            std::env::set_var("MULTIPLAYER_SEASON_ENDS_AT", ends_at.to_string());
+        }
+        if let Some(days) = self.season_days {
+            std::env::set_var("MULTIPLAYER_SEASON_DAYS", days.to_string());
+        }
+    }
+}
+
+pub fn usage() -> &'static str {
+    USAGE
+}
@@ -0,0 +1,452 @@
+use crate::{
+    auth, cache, i18n, LeaderboardSort, PlayerProfile, PlayerStore, ServerState, DEFAULT_PAGE_SIZE,
+    MAX_NAME_LEN, MAX_PAGE_SIZE,
+};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+use uuid::Uuid;
+
+const JOIN_CODE_LEN: usize = 8;
+
+// メンバーはプロファイルの guild_id から求める（ここには持たない）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guild {
+    guild_id: String,
+    name: String,
+    // 参加に必要なコード（メンバーにだけ見せる）
+    join_code: String,
+    owner_id: String,
+    created_at: i64,
+}
+
+fn guilds_dir() -> PathBuf {
+    let mut dir = crate::data_root();
+    dir.push("guilds");
+    dir
+}
+
+pub fn load_guilds() -> HashMap<String, Guild> {
+    let mut guilds = HashMap::new();
+    if let Ok(entries) = fs::read_dir(guilds_dir()) {
+        for entry in entries.flatten() {
+            if let Ok(contents) = fs::read_to_string(entry.path()) {
+                if let Ok(guild) = serde_json::from_str::<Guild>(&contents) {
+                    guilds.insert(guild.guild_id.clone(), guild);
+                }
+            }
+        }
+    }
+    guilds
+}
+
+fn guild_path(guild_id: &str) -> PathBuf {
+    let mut path = guilds_dir();
+    path.push(format!("{}.json", guild_id));
+    path
+}
+
+fn save_guild(guild: &Guild) -> std::io::Result<()> {
+    fs::create_dir_all(guilds_dir())?;
+    let json = serde_json::to_string_pretty(guild).unwrap_or_default();
+    fs::write(guild_path(&guild.guild_id), json)
+}
+
+fn new_join_code(state: &ServerState) -> String {
+    loop {
+        let code: String = Uuid::new_v4()
+            .simple()
+            .to_string()
+            .to_uppercase()
+            .chars()
+            .take(JOIN_CODE_LEN)
+            .collect();
+        if !state.guilds.values().any(|g| g.join_code == code) {
+            return code;
+        }
+    }
+}
+
+fn members<'a>(state: &'a ServerState, guild_id: &str) -> Vec<&'a PlayerProfile> {
+    let mut members: Vec<&PlayerProfile> = state
+        .players
+        .values()
+        .filter(|profile| profile.guild_id.as_deref() == Some(guild_id))
+        .collect();
+    members.sort_by(|a, b| {
+        b.progress
+            .stage
+            .cmp(&a.progress.stage)
+            .then_with(|| a.player_name.cmp(&b.player_name))
+    });
+    members
+}
+
+#[derive(Serialize)]
+struct GuildMember {
+    player_id: String,
+    player_name: String,
+    stage: u32,
+    coins: u64,
+    flagged: bool,
+}
+
+#[derive(Serialize)]
+struct GuildDetails {
+    guild_id: String,
+    name: String,
+    owner_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    join_code: Option<String>,
+    members: Vec<GuildMember>,
+    total_stage: u64,
+    total_coins: u64,
+}
+
+// 不正の疑いがあるメンバーは合計に含めない
+fn totals(members: &[&PlayerProfile]) -> (u64, u64) {
+    members.iter().filter(|profile| !profile.flagged).fold(
+        (0u64, 0u64),
+        |(stage, coins), profile| {
+            (
+                stage.saturating_add(profile.progress.stage as u64),
+                coins.saturating_add(profile.progress.coins),
+            )
+        },
+    )
+}
+
+fn details(state: &ServerState, guild: &Guild, with_code: bool) -> GuildDetails {
+    let members = members(state, &guild.guild_id);
+    let (total_stage, total_coins) = totals(&members);
+    GuildDetails {
+        guild_id: guild.guild_id.clone(),
+        name: guild.name.clone(),
+        owner_id: guild.owner_id.clone(),
+        join_code: with_code.then(|| guild.join_code.clone()),
+        members: members
+            .iter()
+            .map(|profile| GuildMember {
+                player_id: profile.player_id.clone(),
+                player_name: profile.player_name.clone(),
+                stage: profile.progress.stage,
+                coins: profile.progress.coins,
+                flagged: profile.flagged,
+            })
+            .collect(),
+        total_stage,
+        total_coins,
+    }
+}
+
+// 参加者が存在しないかトークンが違えば、返すべきエラー応答
+fn reject_player(req: &HttpRequest, state: &ServerState, player_id: &str) -> Option<HttpResponse> {
+    if !state.players.contains_key(player_id) {
+        return Some(HttpResponse::NotFound().json(i18n::error(req, "player_not_found")));
+    }
+    if !auth::is_authorized(req, state, player_id) {
+        return Some(auth::unauthorized(req));
+    }
+    None
+}
+
+fn save_member(profile: Option<PlayerProfile>) {
+    if let Some(profile) = profile {
+        crate::save_profile(&profile);
+    }
+}
+
+// プロファイルの所属を書き換えて、保存用の複製を返す
+fn set_membership(
+    state: &mut ServerState,
+    player_id: &str,
+    guild_id: Option<String>,
+) -> Option<PlayerProfile> {
+    let profile = state.players.get_mut(player_id)?;
+    profile.guild_id = guild_id;
+    profile.last_update = Utc::now().timestamp();
+    Some(profile.clone())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGuildRequest {
+    player_id: String,
+    name: String,
+}
+
+pub async fn create(
+    req: HttpRequest,
+    data: web::Json<CreateGuildRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let name = data.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN || name.chars().any(char::is_control)
+    {
+        return HttpResponse::BadRequest().json(i18n::error(&req, "invalid_guild_name"));
+    }
+
+    let mut state = store.lock().unwrap();
+    if let Some(response) = reject_player(&req, &state, &data.player_id) {
+        return response;
+    }
+    if state.players[&data.player_id].guild_id.is_some() {
+        return HttpResponse::Conflict().json(i18n::error(&req, "already_in_guild"));
+    }
+    let lower_name = name.to_lowercase();
+    if state
+        .guilds
+        .values()
+        .any(|g| g.name.to_lowercase() == lower_name)
+    {
+        return HttpResponse::Conflict().json(i18n::error(&req, "guild_name_taken"));
+    }
+
+    let guild = Guild {
+        guild_id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        join_code: new_join_code(&state),
+        owner_id: data.player_id.clone(),
+        created_at: Utc::now().timestamp(),
+    };
+    state.guilds.insert(guild.guild_id.clone(), guild.clone());
+    let profile = set_membership(&mut state, &data.player_id, Some(guild.guild_id.clone()));
+    let response = details(&state, &guild, true);
+    drop(state);
+
+    if let Err(err) = save_guild(&guild) {
+        eprintln!("Failed to save guild: {}", err);
+    }
+    save_member(profile);
+
+    HttpResponse::Ok().json(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinGuildRequest {
+    player_id: String,
+    code: String,
+}
+
+pub async fn join(
+    req: HttpRequest,
+    data: web::Json<JoinGuildRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let mut state = store.lock().unwrap();
+    if let Some(response) = reject_player(&req, &state, &data.player_id) {
+        return response;
+    }
+    if state.players[&data.player_id].guild_id.is_some() {
+        return HttpResponse::Conflict().json(i18n::error(&req, "already_in_guild"));
+    }
+    let code = data.code.trim().to_uppercase();
+    let Some(guild) = state.guilds.values().find(|g| g.join_code == code).cloned() else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "unknown_guild_code"));
+    };
+
+    let profile = set_membership(&mut state, &data.player_id, Some(guild.guild_id.clone()));
+    let response = details(&state, &guild, true);
+    drop(state);
+
+    save_member(profile);
+    HttpResponse::Ok().json(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaveGuildRequest {
+    player_id: String,
+}
+
+// 最後のメンバーが抜けたギルドは解散し、リーダーが抜けたら最上位のメンバーに引き継ぐ
+pub async fn leave(
+    req: HttpRequest,
+    data: web::Json<LeaveGuildRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let mut state = store.lock().unwrap();
+    if let Some(response) = reject_player(&req, &state, &data.player_id) {
+        return response;
+    }
+    let Some(guild_id) = state.players[&data.player_id].guild_id.clone() else {
+        return HttpResponse::Conflict().json(i18n::error(&req, "not_in_guild"));
+    };
+    let profile = set_membership(&mut state, &data.player_id, None);
+    let departure = depart(&mut state, &guild_id, &data.player_id);
+    drop(state);
+
+    save_member(profile);
+    departure.save();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "guild_id": departure.guild_id,
+        "disbanded": departure.disbanded,
+    }))
+}
+
+// メンバーが抜けた（または削除された）後のギルド
+pub struct Departure {
+    guild_id: String,
+    disbanded: bool,
+    // リーダーが替わって保存し直すギルド
+    changed: Option<Guild>,
+}
+
+// メンバーから外した後に呼ぶ。最後のメンバーなら解散し、リーダーなら最上位のメンバーに引き継ぐ
+pub fn depart(state: &mut ServerState, guild_id: &str, player_id: &str) -> Departure {
+    let next_owner = members(state, guild_id)
+        .first()
+        .map(|profile| profile.player_id.clone());
+    let changed = match next_owner {
+        None => {
+            state.guilds.remove(guild_id);
+            None
+        }
+        Some(next_owner) => state.guilds.get_mut(guild_id).and_then(|guild| {
+            if guild.owner_id != player_id {
+                return None;
+            }
+            guild.owner_id = next_owner;
+            Some(guild.clone())
+        }),
+    };
+    Departure {
+        guild_id: guild_id.to_string(),
+        disbanded: !state.guilds.contains_key(guild_id),
+        changed,
+    }
+}
+
+impl Departure {
+    pub fn save(&self) {
+        if self.disbanded {
+            let _ = fs::remove_file(guild_path(&self.guild_id));
+        } else if let Some(guild) = &self.changed {
+            if let Err(err) = save_guild(guild) {
+                eprintln!("Failed to save guild: {}", err);
+            }
+        }
+    }
+}
+
+// 参加コードはトークン付きで問い合わせたメンバーにだけ返す
+#[derive(Debug, Deserialize)]
+pub struct GuildQuery {
+    player_id: Option<String>,
+}
+
+pub async fn get(
+    req: HttpRequest,
+    guild_id: web::Path<String>,
+    query: web::Query<GuildQuery>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let state = store.lock().unwrap();
+    let Some(guild) = state.guilds.get(guild_id.as_str()) else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "guild_not_found"));
+    };
+    let is_member = query.player_id.as_deref().is_some_and(|player_id| {
+        state
+            .players
+            .get(player_id)
+            .is_some_and(|p| p.guild_id.as_deref() == Some(guild.guild_id.as_str()))
+            && auth::is_authorized(&req, &state, player_id)
+    });
+    HttpResponse::Ok().json(details(&state, guild, is_member))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GuildLeaderboardQuery {
+    #[serde(default)]
+    sort: LeaderboardSort,
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GuildLeaderboardEntry {
+    rank: usize,
+    guild_id: String,
+    name: String,
+    member_count: usize,
+    total_stage: u64,
+    total_coins: u64,
+    last_update: i64,
+}
+
+#[derive(Serialize)]
+struct GuildLeaderboardPage {
+    entries: Vec<GuildLeaderboardEntry>,
+    page: usize,
+    page_size: usize,
+    total: usize,
+}
+
+// メンバーのステージ・コインの合計で並べる（page は 1 始まり）
+pub async fn leaderboard(
+    req: HttpRequest,
+    query: web::Query<GuildLeaderboardQuery>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let state = store.lock().unwrap();
+    let mut rows: Vec<GuildLeaderboardEntry> = state
+        .guilds
+        .values()
+        .filter_map(|guild| {
+            let members = members(&state, &guild.guild_id);
+            // メンバーが全員書庫に移ったギルドは載せない
+            if members.is_empty() {
+                return None;
+            }
+            let (total_stage, total_coins) = totals(&members);
+            Some(GuildLeaderboardEntry {
+                rank: 0,
+                guild_id: guild.guild_id.clone(),
+                name: guild.name.clone(),
+                member_count: members.len(),
+                total_stage,
+                total_coins,
+                last_update: members
+                    .iter()
+                    .map(|profile| profile.last_update)
+                    .max()
+                    .unwrap_or(guild.created_at),
+            })
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        let order = match query.sort {
+            LeaderboardSort::Stage => b.total_stage.cmp(&a.total_stage),
+            LeaderboardSort::Coins => b.total_coins.cmp(&a.total_coins),
+            LeaderboardSort::LastUpdate => b.last_update.cmp(&a.last_update),
+        };
+        order.then_with(|| a.name.cmp(&b.name))
+    });
+
+    let total = rows.len();
+    let offset = (page - 1).saturating_mul(page_size);
+    let entries = rows
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .take(page_size)
+        .map(|(index, entry)| GuildLeaderboardEntry {
+            rank: index + 1,
+            ..entry
+        })
+        .collect();
+
+    let page = GuildLeaderboardPage {
+        entries,
+        page,
+        page_size,
+        total,
+    };
+    cache::conditional_json(&req, &page, crate::latest_update(&state))
+}
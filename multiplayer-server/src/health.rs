@@ -0,0 +1,114 @@
+use crate::{
+    maintenance::Maintenance,
+    save_writer::{self, StorageFailure},
+    PlayerStore, ServerState,
+};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Serialize;
+use std::path::Path;
+
+// プロファイルは 1 人 1 ファイルの JSON で保存している
+const STORAGE_BACKEND: &str = "json_files";
+
+// 登録できるプレイヤー数の上限（MULTIPLAYER_MAX_PLAYERS、未設定や 0 なら無制限）
+pub fn max_players() -> Option<usize> {
+    std::env::var("MULTIPLAYER_MAX_PLAYERS")
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|&max| max > 0)
+}
+
+pub fn is_full(state: &ServerState) -> bool {
+    max_players().is_some_and(|max| state.players.len() >= max)
+}
+
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path は NUL 終端済みで、stat は書き込み先として有効
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[derive(Serialize)]
+struct StorageStatus {
+    backend: &'static str,
+    data_dir: String,
+    // 取得できない環境では省く
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_bytes: Option<u64>,
+    // 書けなくなっていれば保存先と理由（書けるようになるまで null にならない）
+    failure: Option<StorageFailure>,
+}
+
+#[derive(Serialize)]
+struct Capacity {
+    // None なら無制限
+    max_players: Option<usize>,
+    full: bool,
+}
+
+#[derive(Serialize)]
+struct HealthStatus {
+    status: &'static str,
+    version: &'static str,
+    timestamp: i64,
+    uptime_secs: i64,
+    player_count: usize,
+    storage: StorageStatus,
+    capacity: Capacity,
+    maintenance: Maintenance,
+}
+
+pub async fn health(store: web::Data<PlayerStore>) -> impl Responder {
+    let (player_count, started_at, full, maintenance) = {
+        let state = store.lock().unwrap();
+        (
+            state.players.len(),
+            state.started_at,
+            is_full(&state),
+            state.maintenance.clone(),
+        )
+    };
+    let now = Utc::now().timestamp();
+    let data_dir = crate::data_root();
+    // データディレクトリがまだ無いときは親ディレクトリの空き容量を見る
+    let free = free_bytes(&data_dir).or_else(|| data_dir.parent().and_then(free_bytes));
+    let failure = save_writer::status();
+
+    HttpResponse::Ok().json(HealthStatus {
+        status: if maintenance.is_enabled() {
+            "maintenance"
+        } else if failure.is_some() {
+            "storage_error"
+        } else {
+            "ok"
+        },
+        version: env!("CARGO_PKG_VERSION"),
+        timestamp: now,
+        uptime_secs: (now - started_at).max(0),
+        player_count,
+        storage: StorageStatus {
+            backend: STORAGE_BACKEND,
+            data_dir: data_dir.display().to_string(),
+            free_bytes: free,
+            failure,
+        },
+        capacity: Capacity {
+            max_players: max_players(),
+            full,
+        },
+        maintenance,
+    })
+}
@@ -0,0 +1,190 @@
+use actix_web::{http::header, HttpRequest};
+use serde_json::Value;
+use std::fmt::Display;
+
+// エラーの文言の言語（クライアントが Accept-Language で送る）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    English,
+    Japanese,
+}
+
+impl Language {
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::English => EN,
+            Self::Japanese => JA,
+        }
+    }
+}
+
+// "ja-JP,ja;q=0.9,en;q=0.8" のような指定を前から見て、最初に対応している言語を選ぶ（q 値は見ない）
+pub fn language(req: &HttpRequest) -> Language {
+    let accepted = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    for range in accepted.split(',') {
+        let tag = range.split(';').next().unwrap_or_default().trim();
+        match tag.split(['-', '_']).next().unwrap_or_default() {
+            "ja" => return Language::Japanese,
+            "en" => return Language::English,
+            _ => {}
+        }
+    }
+    Language::English
+}
+
+const EN: &[(&str, &str)] = &[
+    ("player_not_found", "Player not found"),
+    ("player_name_taken", "Player name is already taken"),
+    ("player_name_required", "Player name is required"),
+    (
+        "invalid_player_name",
+        "Player names are up to 32 letters, digits, spaces, '_', '-' or '.'",
+    ),
+    ("unauthorized", "Invalid or missing token"),
+    (
+        "token_not_issued",
+        "This account predates tokens; ask the server admin for a token",
+    ),
+    ("server_full", "Server is full"),
+    ("implausible_progress", "Implausible progress"),
+    ("batch_too_large", "At most {max} players per request"),
+    (
+        "army_exceeds_progress",
+        "Army upgrades exceed the synced progress",
+    ),
+    ("no_army", "No army uploaded"),
+    (
+        "invalid_guild_name",
+        "Guild names are 1-32 characters without control characters",
+    ),
+    ("already_in_guild", "Already in a guild"),
+    ("guild_name_taken", "Guild name is already taken"),
+    ("unknown_guild_code", "Unknown guild code"),
+    ("not_in_guild", "Not in a guild"),
+    ("guild_not_found", "Guild not found"),
+    ("too_many_requests", "Too many requests"),
+    ("body_too_large", "Request body is larger than {max} bytes"),
+    ("invalid_body", "{reason}"),
+    ("maintenance", "Server is under maintenance"),
+    (
+        "maintenance_message_too_long",
+        "Maintenance messages are up to {max} characters",
+    ),
+    ("invalid_raid_id", "Invalid raid id"),
+    ("use_raid_damage", "Use /api/raid/damage for the raid boss"),
+    ("raid_boss_alive", "Raid boss is still alive"),
+    ("no_raid_contribution", "No contribution to this raid"),
+    ("raid_not_found", "Raid not found"),
+    ("raid_exists", "A raid with this id already exists"),
+    ("raid_ends_in_past", "Raids must end in the future"),
+    ("raid_ended", "This raid has ended"),
+    ("raid_not_ended", "This raid has not ended yet"),
+    ("reward_already_claimed", "Reward already claimed"),
+    (
+        "season_archive_failed",
+        "Failed to write the season archive",
+    ),
+];
+
+const JA: &[(&str, &str)] = &[
+    ("player_not_found", "プレイヤーが見つかりません"),
+    (
+        "player_name_taken",
+        "そのプレイヤー名はすでに使われています",
+    ),
+    ("player_name_required", "プレイヤー名を入力してください"),
+    (
+        "invalid_player_name",
+        "プレイヤー名は英数字・空白・'_'・'-'・'.' で 32 文字までです",
+    ),
+    ("unauthorized", "トークンがないか、正しくありません"),
+    (
+        "token_not_issued",
+        "トークン導入前のアカウントです。サーバーの管理者にトークンの発行を依頼してください",
+    ),
+    ("server_full", "サーバーが満員です"),
+    ("implausible_progress", "進行状況の値があり得ません"),
+    (
+        "batch_too_large",
+        "1 回に問い合わせられるのは {max} 人までです",
+    ),
+    (
+        "army_exceeds_progress",
+        "部隊の強化が同期した進行状況を超えています",
+    ),
+    ("no_army", "部隊がアップロードされていません"),
+    (
+        "invalid_guild_name",
+        "ギルド名は制御文字を含まない 1〜32 文字です",
+    ),
+    ("already_in_guild", "すでにギルドに入っています"),
+    ("guild_name_taken", "そのギルド名はすでに使われています"),
+    ("unknown_guild_code", "そのギルドコードは見つかりません"),
+    ("not_in_guild", "ギルドに入っていません"),
+    ("guild_not_found", "ギルドが見つかりません"),
+    ("too_many_requests", "リクエストが多すぎます"),
+    (
+        "body_too_large",
+        "リクエストの本文が {max} バイトを超えています",
+    ),
+    (
+        "invalid_body",
+        "リクエストの本文が正しくありません: {reason}",
+    ),
+    ("maintenance", "サーバーはメンテナンス中です"),
+    (
+        "maintenance_message_too_long",
+        "メンテナンスのお知らせは {max} 文字までです",
+    ),
+    ("invalid_raid_id", "レイドの ID が正しくありません"),
+    (
+        "use_raid_damage",
+        "レイドボスへのダメージは /api/raid/damage で送ってください",
+    ),
+    ("raid_boss_alive", "レイドボスはまだ倒されていません"),
+    ("no_raid_contribution", "このレイドには参加していません"),
+    ("raid_not_found", "レイドが見つかりません"),
+    ("raid_exists", "その ID のレイドはすでにあります"),
+    ("raid_ends_in_past", "レイドの終了時刻は未来にしてください"),
+    ("raid_ended", "このレイドは終了しました"),
+    ("raid_not_ended", "このレイドはまだ終わっていません"),
+    ("reward_already_claimed", "報酬は受け取り済みです"),
+    (
+        "season_archive_failed",
+        "シーズンの記録を書き出せませんでした",
+    ),
+];
+
+// 訳がなければ英語、それもなければキーのまま
+fn template(language: Language, key: &str) -> &str {
+    [language.table(), EN]
+        .into_iter()
+        .find_map(|table| {
+            table
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, text)| *text)
+        })
+        .unwrap_or(key)
+}
+
+pub fn format(req: &HttpRequest, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = template(language(req), key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+// エラーの応答の本文。"code" は言語によらないので、クライアントはこちらで判定する
+pub fn error(req: &HttpRequest, key: &str) -> Value {
+    error_with(req, key, &[])
+}
+
+pub fn error_with(req: &HttpRequest, key: &str, args: &[(&str, &dyn Display)]) -> Value {
+    serde_json::json!({ "error": format(req, key, args), "code": key })
+}
@@ -0,0 +1,204 @@
+use crate::{auth, i18n, PlayerStore};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::{InternalError, JsonPayloadError},
+    http::header,
+    middleware::Next,
+    web, HttpRequest, HttpResponse,
+};
+use std::{collections::HashMap, hash::Hash, net::IpAddr, time::Instant};
+
+// 1 分あたりのリクエスト数の既定値（0 なら制限しない）
+pub const DEFAULT_IP_PER_MINUTE: u32 = 600;
+pub const DEFAULT_PLAYER_PER_MINUTE: u32 = 120;
+// 続けて送れるのは 1 分の上限の 1/4 まで
+const BURST_DIVISOR: u32 = 4;
+// バケットがこれより増えたら、満タンに戻ったものを捨てる
+const PRUNE_THRESHOLD: usize = 10_000;
+// 監視用に制限しないパス
+const EXEMPT_PATHS: [&str; 1] = ["/health"];
+// JSON の本文の上限（同期の本文は数 KB）
+const MAX_JSON_BYTES: usize = 64 * 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Clone, Copy)]
+struct Limit {
+    per_minute: u32,
+}
+
+impl Limit {
+    fn new(per_minute: u32) -> Option<Self> {
+        (per_minute > 0).then_some(Self { per_minute })
+    }
+
+    fn capacity(self) -> f64 {
+        (self.per_minute / BURST_DIVISOR).max(1) as f64
+    }
+
+    fn refill_per_sec(self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+
+    fn refill(self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec()).min(self.capacity());
+        bucket.updated = now;
+    }
+
+    // 1 回分を使えれば Ok、足りなければ次の 1 回までの秒数
+    fn take<K: Eq + Hash>(
+        self,
+        buckets: &mut HashMap<K, Bucket>,
+        key: K,
+        now: Instant,
+    ) -> Result<(), u64> {
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                self.refill(bucket, now);
+                bucket.tokens < self.capacity()
+            });
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity(),
+            updated: now,
+        });
+        self.refill(bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(((1.0 - bucket.tokens) / self.refill_per_sec())
+            .ceil()
+            .max(1.0) as u64)
+    }
+}
+
+// 接続元の IP ごとと、プレイヤーごとのトークンバケット
+pub struct RateLimiter {
+    per_ip: Option<Limit>,
+    per_player: Option<Limit>,
+    ips: HashMap<IpAddr, Bucket>,
+    players: HashMap<String, Bucket>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_IP_PER_MINUTE, DEFAULT_PLAYER_PER_MINUTE)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(ip_per_minute: u32, player_per_minute: u32) -> Self {
+        Self {
+            per_ip: Limit::new(ip_per_minute),
+            per_player: Limit::new(player_per_minute),
+            ips: HashMap::new(),
+            players: HashMap::new(),
+        }
+    }
+
+    // MULTIPLAYER_RATE_LIMIT / MULTIPLAYER_PLAYER_RATE_LIMIT（--rate-limit などのフラグから設定される）
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            var("MULTIPLAYER_RATE_LIMIT", DEFAULT_IP_PER_MINUTE),
+            var("MULTIPLAYER_PLAYER_RATE_LIMIT", DEFAULT_PLAYER_PER_MINUTE),
+        )
+    }
+
+    // 制限に掛かったら Retry-After に入れる秒数
+    pub fn check(
+        &mut self,
+        ip: Option<IpAddr>,
+        player: Option<&str>,
+        now: Instant,
+    ) -> Result<(), u64> {
+        if let (Some(limit), Some(ip)) = (self.per_ip, ip) {
+            limit.take(&mut self.ips, ip, now)?;
+        }
+        if let (Some(limit), Some(player)) = (self.per_player, player) {
+            limit.take(&mut self.players, player.to_string(), now)?;
+        }
+        Ok(())
+    }
+}
+
+// Bearer トークン（WebSocket は ?token= でも可）の持ち主。パスや本文の player_id は検証前なので使わない
+fn request_token(req: &ServiceRequest) -> Option<&str> {
+    auth::bearer_token(req.request()).or_else(|| {
+        req.query_string()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    })
+}
+
+fn too_many_requests(req: &HttpRequest, retry_after_secs: u64) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, retry_after_secs.to_string()))
+        .json(serde_json::json!({
+            "error": i18n::format(req, "too_many_requests", &[]),
+            "code": "too_many_requests",
+            "retry_after_secs": retry_after_secs,
+        }))
+}
+
+// 全ルートに掛けるミドルウェア。上限を超えたリクエストは 429 で断る
+pub async fn guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if !EXEMPT_PATHS.contains(&req.path()) {
+        let ip = req.peer_addr().map(|addr| addr.ip());
+        let rejection = req.app_data::<web::Data<PlayerStore>>().and_then(|store| {
+            let mut state = store.lock().unwrap();
+            // 発行済みのトークンを持つリクエストだけをプレイヤーごとに数え、それ以外は IP の制限だけ掛ける
+            let player = request_token(&req)
+                .and_then(|token| state.tokens.owner(token))
+                .map(str::to_string);
+            state
+                .limits
+                .check(ip, player.as_deref(), Instant::now())
+                .err()
+        });
+        if let Some(retry_after_secs) = rejection {
+            let response = too_many_requests(req.request(), retry_after_secs);
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+// 大きすぎる本文は 413、読めない本文は 400 を JSON で返す
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(MAX_JSON_BYTES)
+        .error_handler(|err, req: &HttpRequest| {
+            let response = match &err {
+                JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::OverflowKnownLength { .. } => HttpResponse::PayloadTooLarge()
+                    .json(i18n::error_with(
+                        req,
+                        "body_too_large",
+                        &[("max", &MAX_JSON_BYTES)],
+                    )),
+                _ => HttpResponse::BadRequest().json(i18n::error_with(
+                    req,
+                    "invalid_body",
+                    &[("reason", &err)],
+                )),
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
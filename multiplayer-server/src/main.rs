@@ -1,15 +1,45 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
     path::PathBuf,
     sync::{Arc, Mutex},
 };
 use uuid::Uuid;
 
+mod admin;
+mod archive;
+mod army;
+mod auth;
+mod cache;
+mod config;
+mod guild;
+mod health;
+mod i18n;
+mod limits;
+mod maintenance;
+mod raid;
+mod save_writer;
+mod season;
+mod shop;
+mod validation;
+mod ws;
+
+// クライアントから受け取る値の上限
+const MAX_STAGE: u32 = 1_000_000;
+const MAX_UPGRADE_LEVEL: u32 = 1_000_000;
+const MAX_BASE_HP: f32 = 1.0e12;
+const MAX_NAME_LEN: usize = 32;
+const MAX_TITLES: usize = 64;
+const MAX_TITLE_ID_LEN: usize = 32;
+const MAX_COSMETICS: usize = 64;
+// 使われている名前で登録したときに付ける "#1234" の桁数と、空きを探す回数
+const DISCRIMINATOR_DIGITS: usize = 4;
+const DISCRIMINATOR_ATTEMPTS: usize = 100;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct UpgradesProgress {
     small_attack: u32,
@@ -23,15 +53,174 @@ struct UpgradesProgress {
     large_speed: u32,
     coin_rate: u32,
     base_hp: u32,
+    #[serde(default)]
+    heal_radius: u32,
+    #[serde(default)]
+    heal_rate: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct PrestigeUpgradesProgress {
+    attack: u32,
+    hp: u32,
+    coin: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct PrestigeProgress {
+    points: u64,
+    total_points_earned: u64,
+    prestige_count: u32,
+    highest_stage: u32,
+    best_stage: u32,
+    upgrades: PrestigeUpgradesProgress,
+    // 転生ポイントのショップで買った品の ID
+    #[serde(default)]
+    shop_items: Vec<String>,
+}
+
+// 獲得した称号の ID と表示中の称号（中身はクライアントが決める）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct TitlesProgress {
+    #[serde(default)]
+    earned: Vec<String>,
+    #[serde(default)]
+    active: Option<String>,
+}
+
+// 称号・見た目の ID は英小文字・数字・_ の 32 文字以内
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TITLE_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// 形の正しい ID を重複なく max 個まで残す
+fn sanitize_ids(ids: &mut Vec<String>, max: usize) {
+    let mut seen = HashSet::new();
+    ids.retain(|id| is_valid_id(id) && seen.insert(id.clone()));
+    ids.truncate(max);
+}
+
+// 選んでいる ID が獲得済みの中になければ外す
+fn sanitize_selection(selected: &mut Option<String>, earned: &[String]) {
+    if !selected.as_ref().is_some_and(|id| earned.contains(id)) {
+        *selected = None;
+    }
+}
+
+impl TitlesProgress {
+    fn sanitize(&mut self) {
+        sanitize_ids(&mut self.earned, MAX_TITLES);
+        sanitize_selection(&mut self.active, &self.earned);
+    }
+}
+
+// 解除した見た目の ID と選んでいるアバター・ユニットの見た目（中身はクライアントが決める）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct CosmeticsProgress {
+    #[serde(default)]
+    unlocked: Vec<String>,
+    #[serde(default)]
+    avatar: Option<String>,
+    #[serde(default)]
+    skin: Option<String>,
+}
+
+impl CosmeticsProgress {
+    fn sanitize(&mut self) {
+        sanitize_ids(&mut self.unlocked, MAX_COSMETICS);
+        sanitize_selection(&mut self.avatar, &self.unlocked);
+        sanitize_selection(&mut self.skin, &self.unlocked);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PlayerProgress {
     stage: u32,
-    coins: u32,
+    coins: u64,
     upgrades: UpgradesProgress,
     max_player_base_hp: f32,
     max_enemy_base_hp: f32,
+    #[serde(default)]
+    prestige: Option<PrestigeProgress>,
+    #[serde(default)]
+    titles: TitlesProgress,
+    #[serde(default)]
+    cosmetics: CosmeticsProgress,
+}
+
+impl UpgradesProgress {
+    fn sanitize(&mut self) {
+        for level in [
+            &mut self.small_attack,
+            &mut self.medium_attack,
+            &mut self.large_attack,
+            &mut self.small_hp,
+            &mut self.medium_hp,
+            &mut self.large_hp,
+            &mut self.small_speed,
+            &mut self.medium_speed,
+            &mut self.large_speed,
+            &mut self.coin_rate,
+            &mut self.base_hp,
+            &mut self.heal_radius,
+            &mut self.heal_rate,
+        ] {
+            *level = (*level).min(MAX_UPGRADE_LEVEL);
+        }
+    }
+
+    fn levels(&self) -> [u32; 13] {
+        [
+            self.small_attack,
+            self.medium_attack,
+            self.large_attack,
+            self.small_hp,
+            self.medium_hp,
+            self.large_hp,
+            self.small_speed,
+            self.medium_speed,
+            self.large_speed,
+            self.coin_rate,
+            self.base_hp,
+            self.heal_radius,
+            self.heal_rate,
+        ]
+    }
+}
+
+// NaN・無限大・0 以下の HP は既定値に戻す（JSON に NaN は保存できない）
+fn sanitize_hp(value: f32, default: f32) -> f32 {
+    if value.is_finite() && value > 0.0 {
+        value.min(MAX_BASE_HP)
+    } else {
+        default
+    }
+}
+
+impl PlayerProgress {
+    // クライアントから届いた進行状況を保存できる範囲に収める
+    fn sanitize(&mut self) {
+        let defaults = PlayerProgress::default();
+        self.stage = self.stage.clamp(1, MAX_STAGE);
+        self.max_player_base_hp = sanitize_hp(self.max_player_base_hp, defaults.max_player_base_hp);
+        self.max_enemy_base_hp = sanitize_hp(self.max_enemy_base_hp, defaults.max_enemy_base_hp);
+        self.upgrades.sanitize();
+        if let Some(prestige) = &mut self.prestige {
+            prestige.highest_stage = prestige.highest_stage.min(MAX_STAGE);
+            prestige.best_stage = prestige.best_stage.min(MAX_STAGE);
+            prestige.total_points_earned = prestige.total_points_earned.max(prestige.points);
+            let mut seen = HashSet::new();
+            prestige
+                .shop_items
+                .retain(|id| shop::is_item(id) && seen.insert(id.clone()));
+        }
+        self.titles.sanitize();
+        self.cosmetics.sanitize();
+    }
 }
 
 impl Default for PlayerProgress {
@@ -42,6 +231,9 @@ impl Default for PlayerProgress {
             upgrades: UpgradesProgress::default(),
             max_player_base_hp: 1000.0,
             max_enemy_base_hp: 500.0,
+            prestige: None,
+            titles: TitlesProgress::default(),
+            cosmetics: CosmeticsProgress::default(),
         }
     }
 }
@@ -52,15 +244,46 @@ struct PlayerProfile {
     player_name: String,
     progress: PlayerProgress,
     last_update: i64,
+    // 最後に進行状況を同期した時刻（ギルドの加入などでは動かない。不自然な伸びはここからの経過時間で判定する）
+    #[serde(default)]
+    last_sync: i64,
+    // 不自然な進み方の同期があったプレイヤー（一覧から除外するかはクライアントが決める）
+    #[serde(default)]
+    flagged: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flag_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    guild_id: Option<String>,
+    // 最後にログインした時刻（同期しなくても書庫に移るまでの期限を延ばす）
+    #[serde(default)]
+    last_seen: i64,
+    // 対戦の相手として使われる軍勢（最後に挑んだときのもの）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    army: Option<army::ArmySnapshot>,
+    // 最後に同期したときのシーズン（クライアントはこれで切り替えに気付く）
+    #[serde(default)]
+    season: u32,
+    // 終わったシーズンごとの成績（古いものから）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    seasons: Vec<season::SeasonResult>,
 }
 
 impl PlayerProfile {
     fn new(player_name: &str) -> Self {
+        let now = Utc::now().timestamp();
         Self {
             player_id: Uuid::new_v4().to_string(),
             player_name: player_name.to_string(),
             progress: PlayerProgress::default(),
-            last_update: Utc::now().timestamp(),
+            last_update: now,
+            last_sync: now,
+            flagged: false,
+            flag_reason: None,
+            guild_id: None,
+            last_seen: 0,
+            army: None,
+            season: 0,
+            seasons: Vec::new(),
         }
     }
 }
@@ -69,23 +292,51 @@ impl PlayerProfile {
 struct ServerState {
     players: HashMap<String, PlayerProfile>,
     name_index: HashMap<String, String>, // lower_name -> player_id
+    sockets: HashMap<String, Vec<ws::Subscriber>>, // player_id -> WebSocket 接続
+    raids: HashMap<String, raid::RaidLedger>, // raid_id -> 貢献度
+    raid_boss: raid::RaidBoss,
+    guilds: HashMap<String, guild::Guild>, // guild_id -> ギルド
+    tokens: auth::TokenStore,
+    maintenance: maintenance::Maintenance,
+    // 起動時刻（稼働時間の表示用）
+    started_at: i64,
+    syncs: admin::SyncMeter,
+    limits: limits::RateLimiter,
+    // 放置で書庫に移したプレイヤーの ID（プロファイルはメモリに置かない）
+    archived: HashSet<String>,
+    season: season::SeasonState,
 }
 
 type PlayerStore = Arc<Mutex<ServerState>>;
 
-fn data_dir() -> PathBuf {
+// 保存先のルート（MULTIPLAYER_DATA_DIR で変更できる）
+fn data_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("MULTIPLAYER_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
     let mut dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
     dir.push("data");
+    dir
+}
+
+fn data_dir() -> PathBuf {
+    let mut dir = data_root();
     dir.push("players");
     dir
 }
 
-fn load_profiles() -> ServerState {
+// トークンが読めなければ起動しない（auth::TokenStore::load）
+fn load_profiles() -> std::io::Result<ServerState> {
     let mut state = ServerState::default();
     let dir = data_dir();
     if let Ok(entries) = fs::read_dir(&dir) {
+        // バックアップ（.bakN）や書きかけの一時ファイルは読まない
         for entry in entries.flatten() {
-            if let Ok(contents) = fs::read_to_string(entry.path()) {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            if let Ok(contents) = fs::read_to_string(path) {
                 if let Ok(profile) = serde_json::from_str::<PlayerProfile>(&contents) {
                     let lower = profile.player_name.to_lowercase();
                     state.name_index.insert(lower, profile.player_id.clone());
@@ -94,16 +345,33 @@ fn load_profiles() -> ServerState {
             }
         }
     }
-    state
+    archive::load(&mut state);
+    state.raids = raid::load_ledgers();
+    state.raid_boss = raid::load_boss();
+    state.guilds = guild::load_guilds();
+    state.tokens = auth::TokenStore::load()?;
+    state.maintenance = maintenance::load();
+    state.limits = limits::RateLimiter::from_env();
+    state.started_at = Utc::now().timestamp();
+    state.season = season::load(state.started_at);
+    Ok(state)
 }
 
-fn save_profile(profile: &PlayerProfile) -> std::io::Result<()> {
-    let dir = data_dir();
-    fs::create_dir_all(&dir)?;
-    let mut path = dir;
-    path.push(format!("{}.json", profile.player_id));
-    let json = serde_json::to_string_pretty(profile).unwrap_or_default();
-    fs::write(path, json)
+fn profile_path(player_id: &str) -> PathBuf {
+    let mut path = data_dir();
+    path.push(format!("{}.json", player_id));
+    path
+}
+
+// 書き込み用のスレッドに積んで待たずに戻る（一時ファイルから置き換え、直前の世代をバックアップに残す）
+fn save_profile(profile: &PlayerProfile) {
+    save_writer::submit(profile_path(&profile.player_id), profile_json(profile));
+}
+
+fn profile_json(profile: &PlayerProfile) -> Vec<u8> {
+    serde_json::to_string_pretty(profile)
+        .unwrap_or_default()
+        .into_bytes()
 }
 
 #[derive(Debug, Deserialize)]
@@ -118,46 +386,99 @@ struct RegisterResponse {
     message: String,
     progress: PlayerProgress,
     last_update: i64,
+    // 以降の同期・取得で Authorization: Bearer に付ける秘密のトークン
+    token: String,
 }
 
 fn normalize_name(name: &str) -> String {
     name.trim().to_lowercase()
 }
 
-fn build_register_response(profile: &PlayerProfile, message: &str) -> RegisterResponse {
+// 名前に使える文字（'#' は重複時の識別子に使う）
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.')
+}
+
+// 登録・名前の変更で使えない名前なら 400 の応答
+fn invalid_name(req: &HttpRequest, name: &str) -> Option<HttpResponse> {
+    if name.is_empty() {
+        return Some(HttpResponse::BadRequest().json(i18n::error(req, "player_name_required")));
+    }
+    if name.chars().count() > MAX_NAME_LEN || !name.chars().all(is_name_char) {
+        return Some(HttpResponse::BadRequest().json(i18n::error(req, "invalid_player_name")));
+    }
+    None
+}
+
+fn build_register_response(
+    profile: &PlayerProfile,
+    message: &str,
+    token: String,
+) -> RegisterResponse {
     RegisterResponse {
         player_id: profile.player_id.clone(),
         player_name: profile.player_name.clone(),
         message: message.to_string(),
         progress: profile.progress.clone(),
         last_update: profile.last_update,
+        token,
     }
 }
 
+// 空いている "名前#1234" を探す（長すぎる名前は識別子の分だけ削る）
+fn discriminated_name(state: &ServerState, name: &str) -> Option<String> {
+    let base: String = name
+        .chars()
+        .take(MAX_NAME_LEN - DISCRIMINATOR_DIGITS - 1)
+        .collect();
+    let base = base.trim_end();
+    (0..DISCRIMINATOR_ATTEMPTS)
+        .map(|_| {
+            let discriminator = Uuid::new_v4().as_u128() % 10u128.pow(DISCRIMINATOR_DIGITS as u32);
+            format!(
+                "{}#{:0width$}",
+                base,
+                discriminator,
+                width = DISCRIMINATOR_DIGITS
+            )
+        })
+        .find(|candidate| !state.name_index.contains_key(&normalize_name(candidate)))
+}
+
+// 登録は常に新しいプレイヤーを作る。使われている名前には識別子を付けて重複させない
+// （既存のプレイヤーに入り直すには login でトークンを使う）
 async fn register_player(
+    req: HttpRequest,
     data: web::Json<RegisterRequest>,
     store: web::Data<PlayerStore>,
 ) -> impl Responder {
     let requested_name = data.player_name.trim();
-    if requested_name.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Player name is required",
-        }));
+    if let Some(response) = invalid_name(&req, requested_name) {
+        return response;
     }
 
-    let lower_name = normalize_name(requested_name);
     let mut state = store.lock().unwrap();
-
-    if let Some(existing_id) = state.name_index.get(&lower_name).cloned() {
-        if let Some(profile) = state.players.get(&existing_id).cloned() {
-            return HttpResponse::Ok().json(build_register_response(
-                &profile,
-                "Welcome back! Progress loaded.",
-            ));
-        }
+    if health::is_full(&state) {
+        return HttpResponse::ServiceUnavailable().json(i18n::error(&req, "server_full"));
     }
 
-    let profile = PlayerProfile::new(requested_name);
+    let (name, message) = if state
+        .name_index
+        .contains_key(&normalize_name(requested_name))
+    {
+        let Some(name) = discriminated_name(&state, requested_name) else {
+            return HttpResponse::Conflict().json(i18n::error(&req, "player_name_taken"));
+        };
+        let message = format!("Name was taken; registered as {}", name);
+        (name, message)
+    } else {
+        (requested_name.to_string(), "Account created!".to_string())
+    };
+    let lower_name = normalize_name(&name);
+
+    let profile = PlayerProfile::new(&name);
+    let token = state.tokens.issue(&profile.player_id);
+    state.tokens.save();
     state
         .name_index
         .insert(lower_name, profile.player_id.clone());
@@ -167,46 +488,143 @@ async fn register_player(
     let profile_clone = profile.clone();
     drop(state);
 
-    if let Err(err) = save_profile(&profile_clone) {
-        eprintln!("Failed to save profile: {}", err);
-    }
+    save_profile(&profile_clone);
 
-    HttpResponse::Ok().json(build_register_response(&profile_clone, "Account created!"))
+    HttpResponse::Ok().json(build_register_response(&profile_clone, &message, token))
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    player_id: String,
+}
+
+// 登録時のトークンで既存のプレイヤーに入り直す（再起動後や別の端末から）
+async fn login_player(
+    req: HttpRequest,
+    data: web::Json<LoginRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let player_id = data.player_id.as_str();
+    // ロックは書庫からの復元を待つ前に外す
+    let (token, restored, profile) = {
+        let mut state = store.lock().unwrap();
+        let known = state.players.contains_key(player_id) || state.archived.contains(player_id);
+        if !known {
+            return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+        }
+        // トークン導入前に作られたプレイヤーは ID だけでは本人と確かめられない
+        // （ID は一覧で公開されている）ので、管理用 API で発行したトークンを使ってもらう
+        let token = match state.tokens.get(player_id) {
+            Some(token) if auth::is_authorized(&req, &state, player_id) => token.to_string(),
+            Some(_) => return auth::unauthorized(&req),
+            None => {
+                return HttpResponse::Unauthorized().json(i18n::error(&req, "token_not_issued"))
+            }
+        };
+        // 書庫に移っていたら戻す
+        let restored = archive::restore(&mut state, player_id).is_some();
+        let Some(profile) = state.players.get_mut(player_id) else {
+            return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+        };
+        profile.last_seen = Utc::now().timestamp();
+        (token, restored, profile.clone())
+    };
+
+    if restored {
+        let restored = profile.clone();
+        let _ = web::block(move || archive::finish_restore(&restored)).await;
+    } else {
+        save_profile(&profile);
+    }
+    let message = if restored {
+        "Welcome back! Your archived progress was restored."
+    } else {
+        "Welcome back! Progress loaded."
+    };
+    HttpResponse::Ok().json(build_register_response(&profile, message, token))
 }
 
 #[derive(Debug, Deserialize)]
 struct SyncRequest {
     progress: PlayerProgress,
+    // 送信元クライアント（WebSocket で本人に送り返さないため）
+    #[serde(default)]
+    client_id: Option<String>,
 }
 
 async fn sync_player(
+    req: HttpRequest,
     player_id: web::Path<String>,
     data: web::Json<SyncRequest>,
     store: web::Data<PlayerStore>,
 ) -> impl Responder {
+    let mut progress = data.progress.clone();
+    progress.sanitize();
+
     let mut state = store.lock().unwrap();
+    if state.players.contains_key(player_id.as_str())
+        && !auth::is_authorized(&req, &state, &player_id)
+    {
+        return auth::unauthorized(&req);
+    }
+    let season = state.season.current.number;
     if let Some(profile) = state.players.get_mut(player_id.as_str()) {
-        profile.progress = data.progress.clone();
-        profile.last_update = Utc::now().timestamp();
+        let now = Utc::now().timestamp();
+        // 同期の記録がない古いプロファイルは last_update から測る
+        let last_sync = if profile.last_sync > 0 {
+            profile.last_sync
+        } else {
+            profile.last_update
+        };
+        match validation::check(&profile.progress, &progress, now - last_sync) {
+            validation::Verdict::Plausible => {}
+            validation::Verdict::Suspicious(reason) => {
+                profile.flagged = true;
+                profile.flag_reason = Some(reason);
+            }
+            validation::Verdict::Rejected(reason) => {
+                profile.flagged = true;
+                profile.flag_reason = Some(reason.clone());
+                let profile_clone = profile.clone();
+                drop(state);
+                save_profile(&profile_clone);
+                return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+                    "error": i18n::format(&req, "implausible_progress", &[]),
+                    "code": "implausible_progress",
+                    "reason": reason,
+                }));
+            }
+        }
+        profile.progress = progress;
+        profile.last_update = now;
+        profile.last_sync = now;
+        profile.season = season;
         let profile_clone = profile.clone();
+        state.syncs.record(now);
         drop(state);
 
-        if let Err(err) = save_profile(&profile_clone) {
-            eprintln!("Failed to save profile: {}", err);
-        }
+        save_profile(&profile_clone);
+        ws::broadcast(&store, data.client_id.as_deref(), &profile_clone);
 
         return HttpResponse::Ok().json(profile_clone);
     }
 
-    HttpResponse::NotFound().json(serde_json::json!({ "error": "Player not found" }))
+    HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"))
 }
 
-async fn get_player(player_id: web::Path<String>, store: web::Data<PlayerStore>) -> impl Responder {
+async fn get_player(
+    req: HttpRequest,
+    player_id: web::Path<String>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
     let state = store.lock().unwrap();
     if let Some(profile) = state.players.get(player_id.as_str()) {
-        HttpResponse::Ok().json(profile.clone())
+        if !auth::is_authorized(&req, &state, &player_id) {
+            return auth::unauthorized(&req);
+        }
+        cache::conditional_json(&req, profile, profile.last_update)
     } else {
-        HttpResponse::NotFound().json(serde_json::json!({ "error": "Player not found" }))
+        HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"))
     }
 }
 
@@ -216,9 +634,26 @@ struct PlayerSummary {
     player_name: String,
     stage: u32,
     last_update: i64,
+    flagged: bool,
+    // 表示中の称号と獲得済みの称号
+    title: Option<String>,
+    badges: Vec<String>,
+    // 選んでいるアバターとユニットの見た目
+    avatar: Option<String>,
+    skin: Option<String>,
 }
 
-async fn list_players(store: web::Data<PlayerStore>) -> impl Responder {
+// 一覧の最終更新時刻（いずれかのプレイヤーが更新された時刻）
+fn latest_update(state: &ServerState) -> i64 {
+    state
+        .players
+        .values()
+        .map(|profile| profile.last_update)
+        .max()
+        .unwrap_or(0)
+}
+
+async fn list_players(req: HttpRequest, store: web::Data<PlayerStore>) -> impl Responder {
     let state = store.lock().unwrap();
     let players: Vec<PlayerSummary> = state
         .players
@@ -228,42 +663,1596 @@ async fn list_players(store: web::Data<PlayerStore>) -> impl Responder {
             player_name: profile.player_name.clone(),
             stage: profile.progress.stage,
             last_update: profile.last_update,
+            flagged: profile.flagged,
+            title: profile.progress.titles.active.clone(),
+            badges: profile.progress.titles.earned.clone(),
+            avatar: profile.progress.cosmetics.avatar.clone(),
+            skin: profile.progress.cosmetics.skin.clone(),
+        })
+        .collect();
+    cache::conditional_json(&req, &players, latest_update(&state))
+}
+
+// 一度にまとめて取得できるプレイヤー数
+const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    player_ids: Vec<String>,
+}
+
+// フレンドと進み具合を比べるための公開情報（アップグレードなどは含めない）
+#[derive(Serialize)]
+struct PlayerSnapshot {
+    player_id: String,
+    player_name: String,
+    stage: u32,
+    coins: u64,
+    best_stage: u32,
+    prestige_count: u32,
+    // 表示中の称号と獲得済みの称号
+    title: Option<String>,
+    badges: Vec<String>,
+    avatar: Option<String>,
+    skin: Option<String>,
+    last_update: i64,
+    flagged: bool,
+}
+
+// 要求した順に返し、存在しない ID は省く
+async fn batch_players(
+    req: HttpRequest,
+    data: web::Json<BatchRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if data.player_ids.len() > MAX_BATCH_SIZE {
+        return HttpResponse::BadRequest().json(i18n::error_with(
+            &req,
+            "batch_too_large",
+            &[("max", &MAX_BATCH_SIZE)],
+        ));
+    }
+
+    let state = store.lock().unwrap();
+    let mut seen = HashSet::new();
+    let players: Vec<PlayerSnapshot> = data
+        .player_ids
+        .iter()
+        .filter(|player_id| seen.insert(player_id.as_str()))
+        .filter_map(|player_id| state.players.get(player_id))
+        .map(|profile| {
+            let prestige = profile.progress.prestige.as_ref();
+            PlayerSnapshot {
+                player_id: profile.player_id.clone(),
+                player_name: profile.player_name.clone(),
+                stage: profile.progress.stage,
+                coins: profile.progress.coins,
+                best_stage: prestige
+                    .map_or(0, |p| p.best_stage)
+                    .max(profile.progress.stage),
+                prestige_count: prestige.map_or(0, |p| p.prestige_count),
+                last_update: profile.last_update,
+                flagged: profile.flagged,
+                title: profile.progress.titles.active.clone(),
+                badges: profile.progress.titles.earned.clone(),
+                avatar: profile.progress.cosmetics.avatar.clone(),
+                skin: profile.progress.cosmetics.skin.clone(),
+            }
         })
         .collect();
     HttpResponse::Ok().json(players)
 }
 
-async fn health(store: web::Data<PlayerStore>) -> impl Responder {
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum LeaderboardSort {
+    #[default]
+    Stage,
+    Coins,
+    LastUpdate,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    #[serde(default)]
+    sort: LeaderboardSort,
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct LeaderboardEntry {
+    rank: usize,
+    player_id: String,
+    player_name: String,
+    stage: u32,
+    coins: u64,
+    last_update: i64,
+    flagged: bool,
+    // 表示中の称号と獲得済みの称号
+    title: Option<String>,
+    badges: Vec<String>,
+    // 選んでいるアバターとユニットの見た目
+    avatar: Option<String>,
+    skin: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LeaderboardPage {
+    entries: Vec<LeaderboardEntry>,
+    page: usize,
+    page_size: usize,
+    total: usize,
+}
+
+// page は 1 始まり、いずれのキーも降順（同値なら名前順）
+async fn leaderboard(
+    req: HttpRequest,
+    query: web::Query<LeaderboardQuery>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
     let state = store.lock().unwrap();
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": "ok",
-        "timestamp": Utc::now().timestamp(),
-        "player_count": state.players.len(),
-    }))
+    let mut profiles: Vec<&PlayerProfile> = state.players.values().collect();
+    profiles.sort_by(|a, b| {
+        let order = match query.sort {
+            LeaderboardSort::Stage => b.progress.stage.cmp(&a.progress.stage),
+            LeaderboardSort::Coins => b.progress.coins.cmp(&a.progress.coins),
+            LeaderboardSort::LastUpdate => b.last_update.cmp(&a.last_update),
+        };
+        order.then_with(|| a.player_name.cmp(&b.player_name))
+    });
+
+    let total = profiles.len();
+    let offset = (page - 1).saturating_mul(page_size);
+    let entries = profiles
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .take(page_size)
+        .map(|(index, profile)| LeaderboardEntry {
+            rank: index + 1,
+            player_id: profile.player_id.clone(),
+            player_name: profile.player_name.clone(),
+            stage: profile.progress.stage,
+            coins: profile.progress.coins,
+            last_update: profile.last_update,
+            flagged: profile.flagged,
+            title: profile.progress.titles.active.clone(),
+            badges: profile.progress.titles.earned.clone(),
+            avatar: profile.progress.cosmetics.avatar.clone(),
+            skin: profile.progress.cosmetics.skin.clone(),
+        })
+        .collect();
+
+    let page = LeaderboardPage {
+        entries,
+        page,
+        page_size,
+        total,
+    };
+    cache::conditional_json(&req, &page, latest_update(&state))
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let config = match config::ServerConfig::from_env().parse(std::env::args().skip(1)) {
+        Ok(config::Parsed::Run(config)) => config,
+        Ok(config::Parsed::Help) => {
+            println!("{}", config::usage());
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    config.apply();
+
     println!("Starting Clicker Clicker Clicker Multiplayer Server...");
-    println!("Server will listen on http://0.0.0.0:8080");
+    println!("Server will listen on http://{}", config.bind);
+    println!("Data directory: {}", data_root().display());
 
-    let initial_state = load_profiles();
-    println!("Loaded {} player profiles", initial_state.players.len());
+    let initial_state = match load_profiles() {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Loaded {} player profiles ({} archived)",
+        initial_state.players.len(),
+        initial_state.archived.len()
+    );
+    println!("Season {}", initial_state.season.current.number);
     let player_store = Arc::new(Mutex::new(initial_state));
+    actix_web::rt::spawn(archive::run(player_store.clone()));
+    actix_web::rt::spawn(season::run(player_store.clone()));
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
 
         App::new()
+            .wrap(middleware::from_fn(maintenance::guard))
+            .wrap(middleware::from_fn(limits::guard))
             .wrap(cors)
             .app_data(web::Data::new(player_store.clone()))
-            .route("/health", web::get().to(health))
+            .app_data(limits::json_config())
+            .route("/health", web::get().to(health::health))
+            .route("/api/maintenance", web::get().to(maintenance::status))
+            .route(
+                "/api/admin/maintenance",
+                web::post().to(maintenance::update),
+            )
+            .route("/api/admin/metrics", web::get().to(admin::metrics))
+            .route("/api/admin/players/export", web::get().to(admin::export))
+            .route(
+                "/api/admin/player/{id}",
+                web::delete().to(admin::delete_player),
+            )
+            .route(
+                "/api/admin/player/{id}/rename",
+                web::post().to(admin::rename_player),
+            )
+            .route(
+                "/api/admin/player/{id}/token",
+                web::post().to(admin::issue_token),
+            )
+            .route("/api/admin/season/end", web::post().to(season::end))
+            .route("/api/admin/raid/{id}", web::post().to(raid::create))
             .route("/api/player/register", web::post().to(register_player))
+            .route("/api/player/login", web::post().to(login_player))
             .route("/api/player/{id}", web::get().to(get_player))
             .route("/api/player/{id}/sync", web::post().to(sync_player))
+            .route("/api/player/{id}/army", web::get().to(army::get))
+            .route("/api/player/{id}/army", web::post().to(army::upload))
             .route("/api/players", web::get().to(list_players))
+            .route("/api/players/batch", web::post().to(batch_players))
+            .route("/api/leaderboard", web::get().to(leaderboard))
+            .route("/api/season", web::get().to(season::info))
+            .route("/api/shop", web::get().to(shop::get))
+            .route("/api/raid", web::get().to(raid::status))
+            .route("/api/raid/damage", web::post().to(raid::damage))
+            .route(
+                "/api/raid/{id}/contribute",
+                web::post().to(raid::contribute),
+            )
+            .route(
+                "/api/raid/{id}/leaderboard",
+                web::get().to(raid::leaderboard),
+            )
+            .route("/api/raid/{id}/claim", web::post().to(raid::claim_reward))
+            .route("/api/guild", web::post().to(guild::create))
+            .route("/api/guild/join", web::post().to(guild::join))
+            .route("/api/guild/leave", web::post().to(guild::leave))
+            .route("/api/guild/{id}", web::get().to(guild::get))
+            .route("/api/guilds/leaderboard", web::get().to(guild::leaderboard))
+            .route("/ws/player/{id}", web::get().to(ws::player_socket))
     })
-    .bind(("0.0.0.0", 8080))?
+    .bind(config.bind.as_str())?
     .run()
-    .await
+    .await?;
+    // 積んだままのプロファイルを書き終えてから終了する
+    save_writer::flush();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test as actix_test};
+    use proptest::prelude::*;
+
+    // テストの保存先は一時ディレクトリに隔離する
+    fn test_store() -> PlayerStore {
+        let dir = std::env::temp_dir().join(format!("kurikka-server-tests-{}", std::process::id()));
+        std::env::set_var("MULTIPLAYER_DATA_DIR", dir);
+        Arc::new(Mutex::new(ServerState::default()))
+    }
+
+    fn assert_storable(progress: &PlayerProgress) {
+        assert!((1..=MAX_STAGE).contains(&progress.stage));
+        for hp in [progress.max_player_base_hp, progress.max_enemy_base_hp] {
+            assert!(hp.is_finite() && hp > 0.0 && hp <= MAX_BASE_HP, "hp {}", hp);
+        }
+        let json = serde_json::to_string(progress).unwrap();
+        serde_json::from_str::<PlayerProgress>(&json).unwrap();
+    }
+
+    fn any_hp() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            any::<f32>(),
+            Just(f32::NAN),
+            Just(f32::INFINITY),
+            Just(f32::NEG_INFINITY),
+            Just(0.0),
+            -1.0e6f32..1.0e6,
+        ]
+    }
+
+    prop_compose! {
+        fn any_progress()(
+            stage in any::<u32>(),
+            coins in any::<u64>(),
+            levels in proptest::collection::vec(any::<u32>(), 13),
+            max_player_base_hp in any_hp(),
+            max_enemy_base_hp in any_hp(),
+            prestige in proptest::option::of((any::<u64>(), any::<u64>(), any::<u32>(), any::<u32>())),
+        ) -> PlayerProgress {
+            PlayerProgress {
+                stage,
+                coins,
+                upgrades: UpgradesProgress {
+                    small_attack: levels[0],
+                    medium_attack: levels[1],
+                    large_attack: levels[2],
+                    small_hp: levels[3],
+                    medium_hp: levels[4],
+                    large_hp: levels[5],
+                    small_speed: levels[6],
+                    medium_speed: levels[7],
+                    large_speed: levels[8],
+                    coin_rate: levels[9],
+                    base_hp: levels[10],
+                    heal_radius: levels[11],
+                    heal_rate: levels[12],
+                },
+                max_player_base_hp,
+                max_enemy_base_hp,
+                prestige: prestige.map(|(points, earned, highest, best)| PrestigeProgress {
+                    points,
+                    total_points_earned: earned,
+                    prestige_count: 0,
+                    highest_stage: highest,
+                    best_stage: best,
+                    upgrades: PrestigeUpgradesProgress::default(),
+                    shop_items: Vec::new(),
+                }),
+                titles: TitlesProgress::default(),
+                cosmetics: CosmeticsProgress::default(),
+            }
+        }
+    }
+
+    // 任意の JSON 値（数値は f32 に収まらない大きさや負数も含む）
+    fn any_json() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<i64>().prop_map(serde_json::Value::from),
+            any::<f64>()
+                .prop_filter("JSON has no NaN/inf", |n| n.is_finite())
+                .prop_map(serde_json::Value::from),
+            ".{0,40}".prop_map(serde_json::Value::from),
+        ];
+        leaf.prop_recursive(3, 32, 8, |inner| {
+            prop_oneof![
+                proptest::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::from),
+                proptest::collection::hash_map("[a-z_]{1,20}", inner, 0..6)
+                    .prop_map(|map| serde_json::Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    // 正しい形の同期リクエストの一部を任意の値に差し替えたもの
+    fn mutated_sync_body() -> impl Strategy<Value = serde_json::Value> {
+        (
+            any_progress(),
+            proptest::sample::select(vec![
+                "stage",
+                "coins",
+                "max_player_base_hp",
+                "max_enemy_base_hp",
+                "upgrades",
+                "prestige",
+                "titles",
+                "cosmetics",
+            ]),
+            any_json(),
+            any::<f64>().prop_filter("JSON has no NaN/inf", |n| n.is_finite()),
+        )
+            .prop_map(|(mut progress, field, value, hp)| {
+                progress.sanitize();
+                let mut body = serde_json::json!({ "progress": progress });
+                body["progress"][field] = value;
+                body["progress"]["max_enemy_base_hp"] = serde_json::Value::from(hp);
+                body
+            })
+    }
+
+    async fn sync(store: &PlayerStore, player_id: &str, token: &str, body: Vec<u8>) -> StatusCode {
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}/sync", web::post().to(sync_player)),
+        )
+        .await;
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/player/{}/sync", player_id))
+            .insert_header(("content-type", "application/json"))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .set_payload(body)
+            .to_request();
+        actix_test::call_service(&app, req).await.status()
+    }
+
+    fn registered(store: &PlayerStore) -> (String, String) {
+        let profile = PlayerProfile::new("fuzz");
+        let id = profile.player_id.clone();
+        let mut state = store.lock().unwrap();
+        state.players.insert(id.clone(), profile);
+        let token = state.tokens.issue(&id);
+        (id, token)
+    }
+
+    async fn register(store: &PlayerStore, name: &str) -> StatusCode {
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/register", web::post().to(register_player)),
+        )
+        .await;
+        let req = actix_test::TestRequest::post()
+            .uri("/api/player/register")
+            .set_json(serde_json::json!({ "player_name": name }))
+            .to_request();
+        actix_test::call_service(&app, req).await.status()
+    }
+
+    #[actix_web::test]
+    async fn sync_requires_the_players_token() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let (_, other_token) = registered(&store);
+        let body =
+            serde_json::to_vec(&serde_json::json!({ "progress": PlayerProgress::default() }))
+                .unwrap();
+
+        assert_eq!(
+            sync(&store, &player_id, &other_token, body.clone()).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            sync(&store, &player_id, "", body.clone()).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(sync(&store, &player_id, &token, body).await, StatusCode::OK);
+    }
+
+    // ギルドの加入などで last_update が進んでも、不自然な伸びは前回の同期からの時間で判定する
+    #[actix_web::test]
+    async fn sync_validation_measures_time_since_the_last_sync() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        {
+            let mut state = store.lock().unwrap();
+            let profile = state.players.get_mut(&player_id).unwrap();
+            profile.last_sync -= 3600;
+        }
+
+        // 1 時間で 100 ステージはありえるが、last_update からの 0 秒なら拒否される量
+        let progress = PlayerProgress {
+            stage: 100,
+            ..PlayerProgress::default()
+        };
+        let body = serde_json::to_vec(&serde_json::json!({ "progress": progress })).unwrap();
+        assert_eq!(sync(&store, &player_id, &token, body).await, StatusCode::OK);
+        let state = store.lock().unwrap();
+        assert!(!state.players[&player_id].flagged);
+        assert_eq!(state.players[&player_id].progress.stage, 100);
+    }
+
+    // 報酬は claim でだけ受け取る（プロファイルのコインや更新時刻は変えない）
+    #[actix_web::test]
+    async fn defeating_the_raid_boss_pays_every_contributor() {
+        let store = test_store();
+        let (first, first_token) = registered(&store);
+        let (second, second_token) = registered(&store);
+        let updated = store.lock().unwrap().players[&first].last_update;
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/raid/damage", web::post().to(raid::damage))
+                .route("/api/raid/{id}/claim", web::post().to(raid::claim_reward)),
+        )
+        .await;
+        let mut statuses = Vec::new();
+        for (player_id, token, damage) in [
+            (&first, &first_token, 10),
+            (&second, &second_token, u64::MAX),
+        ] {
+            let req = actix_test::TestRequest::post()
+                .uri("/api/raid/damage")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "player_id": player_id, "damage": damage }))
+                .to_request();
+            let status: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+            statuses.push(status);
+        }
+
+        assert_eq!(statuses[0]["generation"], 1);
+        assert_eq!(statuses[1]["generation"], 2);
+        assert_eq!(
+            statuses[1]["pending_rewards"],
+            serde_json::json!(["boss-1"])
+        );
+        {
+            let state = store.lock().unwrap();
+            assert_eq!(state.players[&first].progress.coins, 0);
+            assert_eq!(state.players[&first].last_update, updated);
+        }
+
+        for (player_id, token, coins) in
+            [(&second, &second_token, 1000), (&first, &first_token, 300)]
+        {
+            let claim = || {
+                actix_test::TestRequest::post()
+                    .uri("/api/raid/boss-1/claim")
+                    .insert_header(("authorization", format!("Bearer {}", token)))
+                    .set_json(serde_json::json!({ "player_id": player_id }))
+                    .to_request()
+            };
+            let reward: serde_json::Value =
+                actix_test::call_and_read_body_json(&app, claim()).await;
+            assert_eq!(reward["coins"], coins);
+            let again = actix_test::call_service(&app, claim()).await;
+            assert_eq!(again.status(), StatusCode::CONFLICT);
+        }
+        let state = store.lock().unwrap();
+        assert_eq!(state.players[&second].progress.coins, 0);
+    }
+
+    // 個別のレイドは管理者が作ったものだけ。締め切りまでダメージを受け付け、報酬は締め切った後に受け取る
+    #[actix_web::test]
+    async fn raids_take_damage_until_they_end_and_pay_out_afterwards() {
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route(
+                    "/api/raid/{id}/contribute",
+                    web::post().to(raid::contribute),
+                )
+                .route(
+                    "/api/raid/{id}/leaderboard",
+                    web::get().to(raid::leaderboard),
+                )
+                .route("/api/raid/{id}/claim", web::post().to(raid::claim_reward))
+                .route("/api/admin/raid/{id}", web::post().to(raid::create)),
+        )
+        .await;
+        let hit = |damage: u64| {
+            actix_test::TestRequest::post()
+                .uri("/api/raid/festival/contribute")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "player_id": player_id, "damage": damage }))
+                .to_request()
+        };
+        let claim = || {
+            actix_test::TestRequest::post()
+                .uri("/api/raid/festival/claim")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "player_id": player_id }))
+                .to_request()
+        };
+        let create = |bearer: &str| {
+            actix_test::TestRequest::post()
+                .uri("/api/admin/raid/festival")
+                .insert_header(("authorization", format!("Bearer {}", bearer)))
+                .set_json(serde_json::json!({ "ends_at": Utc::now().timestamp() + 3600 }))
+                .to_request()
+        };
+
+        let resp = actix_test::call_service(&app, hit(100)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = actix_test::call_service(&app, create(&token)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(store.lock().unwrap().raids.is_empty());
+        let resp = actix_test::call_service(&app, create("admin-secret")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = actix_test::call_service(&app, create("admin-secret")).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let resp = actix_test::call_service(&app, hit(u64::MAX)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = actix_test::TestRequest::get()
+            .uri("/api/raid/festival/leaderboard")
+            .to_request();
+        let page: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page["total_damage"], 10_000_000);
+        let resp = actix_test::call_service(&app, claim()).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        {
+            let mut state = store.lock().unwrap();
+            let mut ledger = serde_json::to_value(&state.raids["festival"]).unwrap();
+            ledger["ends_at"] = serde_json::json!(Utc::now().timestamp() - 1);
+            state.raids.insert(
+                "festival".to_string(),
+                serde_json::from_value(ledger).unwrap(),
+            );
+        }
+        let resp = actix_test::call_service(&app, hit(100)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let reward: serde_json::Value = actix_test::call_and_read_body_json(&app, claim()).await;
+        assert_eq!(reward["coins"], 300);
+        assert_eq!(reward["rank"], 1);
+    }
+
+    #[actix_web::test]
+    async fn guild_members_add_up_on_the_team_leaderboard() {
+        let store = test_store();
+        let (owner, owner_token) = registered(&store);
+        let (member, member_token) = registered(&store);
+        for (player_id, stage) in [(&owner, 7), (&member, 5)] {
+            store
+                .lock()
+                .unwrap()
+                .players
+                .get_mut(player_id.as_str())
+                .unwrap()
+                .progress
+                .stage = stage;
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/guild", web::post().to(guild::create))
+                .route("/api/guild/join", web::post().to(guild::join))
+                .route("/api/guild/leave", web::post().to(guild::leave))
+                .route("/api/guild/{id}", web::get().to(guild::get))
+                .route("/api/guilds/leaderboard", web::get().to(guild::leaderboard)),
+        )
+        .await;
+        let post = |uri: &str, token: &str, body: serde_json::Value| {
+            actix_test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(body)
+                .to_request()
+        };
+
+        let created: serde_json::Value = actix_test::call_and_read_body_json(
+            &app,
+            post(
+                "/api/guild",
+                &owner_token,
+                serde_json::json!({ "player_id": owner, "name": "Clickers" }),
+            ),
+        )
+        .await;
+        let code = created["join_code"].as_str().unwrap().to_lowercase();
+        let wrong_token = post(
+            "/api/guild/join",
+            &owner_token,
+            serde_json::json!({ "player_id": member, "code": code }),
+        );
+        assert_eq!(
+            actix_test::call_service(&app, wrong_token).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let joined: serde_json::Value = actix_test::call_and_read_body_json(
+            &app,
+            post(
+                "/api/guild/join",
+                &member_token,
+                serde_json::json!({ "player_id": member, "code": code }),
+            ),
+        )
+        .await;
+        assert_eq!(joined["members"].as_array().unwrap().len(), 2);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/api/guilds/leaderboard?sort=stage")
+            .to_request();
+        let board: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(board["entries"][0]["total_stage"], 12);
+        assert_eq!(board["entries"][0]["member_count"], 2);
+
+        // リーダーが抜けると残ったメンバーが引き継ぎ、全員抜けると解散する
+        for (player_id, token) in [(&owner, &owner_token), (&member, &member_token)] {
+            let req = post(
+                "/api/guild/leave",
+                token,
+                serde_json::json!({ "player_id": player_id }),
+            );
+            assert_eq!(
+                actix_test::call_service(&app, req).await.status(),
+                StatusCode::OK
+            );
+            if player_id == &owner {
+                let req = actix_test::TestRequest::get()
+                    .uri(&format!(
+                        "/api/guild/{}",
+                        created["guild_id"].as_str().unwrap()
+                    ))
+                    .to_request();
+                let guild: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+                assert_eq!(guild["owner_id"], member.as_str());
+                assert!(guild.get("join_code").is_none());
+            }
+        }
+        assert!(store.lock().unwrap().guilds.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn batch_returns_known_players_in_request_order() {
+        let store = test_store();
+        let (first, _) = registered(&store);
+        let (second, _) = registered(&store);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/players/batch", web::post().to(batch_players)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/players/batch")
+            .set_json(serde_json::json!({ "player_ids": [second, "missing", first, second] }))
+            .to_request();
+        let players: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        let ids: Vec<&str> = players
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["player_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, [second.as_str(), first.as_str()]);
+
+        let too_many = vec![first; MAX_BATCH_SIZE + 1];
+        let req = actix_test::TestRequest::post()
+            .uri("/api/players/batch")
+            .set_json(serde_json::json!({ "player_ids": too_many }))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn maintenance_blocks_writes_but_not_reads() {
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(maintenance::guard))
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}", web::get().to(get_player))
+                .route("/api/player/{id}/sync", web::post().to(sync_player))
+                .route("/api/player/login", web::post().to(login_player))
+                .route("/api/players/batch", web::post().to(batch_players))
+                .route(
+                    "/api/admin/maintenance",
+                    web::post().to(maintenance::update),
+                ),
+        )
+        .await;
+        let toggle = |token: &str, enabled: bool| {
+            actix_test::TestRequest::post()
+                .uri("/api/admin/maintenance")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({
+                    "enabled": enabled,
+                    "message": "Upgrading storage",
+                    "retry_after_secs": 120,
+                }))
+                .to_request()
+        };
+        let sync = || {
+            actix_test::TestRequest::post()
+                .uri(&format!("/api/player/{}/sync", player_id))
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "progress": PlayerProgress::default() }))
+                .to_request()
+        };
+
+        // プレイヤーのトークンでは切り替えられない
+        let resp = actix_test::call_service(&app, toggle(&token, true)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = actix_test::call_service(&app, toggle("admin-secret", true)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = actix_test::call_service(&app, sync()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "120");
+        let body: serde_json::Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Upgrading storage");
+        // login も書庫から戻すなど書き込むので止める
+        let req = actix_test::TestRequest::post()
+            .uri("/api/player/login")
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "player_id": player_id }))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let req = actix_test::TestRequest::get()
+            .uri(&format!("/api/player/{}", player_id))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+        let req = actix_test::TestRequest::post()
+            .uri("/api/players/batch")
+            .set_json(serde_json::json!({ "player_ids": [player_id] }))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+
+        let resp = actix_test::call_service(&app, toggle("admin-secret", false)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            actix_test::call_service(&app, sync()).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[actix_web::test]
+    async fn admin_endpoints_manage_players() {
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let (other_id, _) = registered(&store);
+        store
+            .lock()
+            .unwrap()
+            .name_index
+            .insert("taken".to_string(), other_id);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}/sync", web::post().to(sync_player))
+                .route("/api/admin/metrics", web::get().to(admin::metrics))
+                .route("/api/admin/players/export", web::get().to(admin::export))
+                .route(
+                    "/api/admin/player/{id}",
+                    web::delete().to(admin::delete_player),
+                )
+                .route(
+                    "/api/admin/player/{id}/rename",
+                    web::post().to(admin::rename_player),
+                ),
+        )
+        .await;
+        let admin = |req: actix_test::TestRequest, token: &str| {
+            req.insert_header(("authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+        let rename = |name: &str| {
+            actix_test::TestRequest::post()
+                .uri(&format!("/api/admin/player/{}/rename", player_id))
+                .set_json(serde_json::json!({ "player_name": name }))
+        };
+
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/player/{}/sync", player_id))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "progress": PlayerProgress::default() }))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+        let req = admin(
+            actix_test::TestRequest::get().uri("/api/admin/metrics"),
+            &token,
+        );
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let req = admin(
+            actix_test::TestRequest::get().uri("/api/admin/metrics"),
+            "admin-secret",
+        );
+        let metrics: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(metrics["player_count"], 2);
+        assert_eq!(metrics["syncs_last_minute"], 1);
+
+        let resp = actix_test::call_service(&app, admin(rename("Taken"), "admin-secret")).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = actix_test::call_service(&app, admin(rename("Renamed"), "admin-secret")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            store.lock().unwrap().name_index.get("renamed"),
+            Some(&player_id)
+        );
+
+        let req = admin(
+            actix_test::TestRequest::get().uri("/api/admin/players/export"),
+            "admin-secret",
+        );
+        let export: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(export["players"].as_array().unwrap().len(), 2);
+
+        let delete = || {
+            admin(
+                actix_test::TestRequest::delete().uri(&format!("/api/admin/player/{}", player_id)),
+                "admin-secret",
+            )
+        };
+        assert_eq!(
+            actix_test::call_service(&app, delete()).await.status(),
+            StatusCode::OK
+        );
+        {
+            let state = store.lock().unwrap();
+            assert!(!state.players.contains_key(&player_id));
+            assert!(state.tokens.get(&player_id).is_none());
+            assert!(!state.name_index.contains_key("renamed"));
+        }
+        assert_eq!(
+            actix_test::call_service(&app, delete()).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn rate_limits_and_body_size_are_enforced() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        // プレイヤーごとに 1 分 8 回（続けて 2 回）まで
+        store.lock().unwrap().limits = limits::RateLimiter::new(0, 8);
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(limits::guard))
+                .app_data(web::Data::new(store.clone()))
+                .app_data(limits::json_config())
+                .route("/health", web::get().to(health::health))
+                .route("/api/player/{id}", web::get().to(get_player))
+                .route("/api/player/{id}/sync", web::post().to(sync_player)),
+        )
+        .await;
+        let get = |id: &str, token: &str| {
+            actix_test::TestRequest::get()
+                .uri(&format!("/api/player/{}", id))
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        for _ in 0..2 {
+            let resp = actix_test::call_service(&app, get(&player_id, &token)).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = actix_test::call_service(&app, get(&player_id, &token)).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "8");
+        // パスの ID を変えても同じトークンなら同じ枠で数える
+        let (other_id, other_token) = registered(&store);
+        let resp = actix_test::call_service(&app, get(&other_id, &token)).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        // 他のプレイヤー、トークンの合わないリクエスト、ヘルスチェックはこの枠で数えない
+        let resp = actix_test::call_service(&app, get(&other_id, &other_token)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = actix_test::call_service(&app, get(&player_id, "forged")).await;
+        assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let req = actix_test::TestRequest::get().uri("/health").to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+
+        store.lock().unwrap().limits = limits::RateLimiter::new(0, 0);
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/player/{}/sync", player_id))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .insert_header(("content-type", "application/json"))
+            .set_payload(vec![b' '; 128 * 1024])
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(register(&store, "<script>").await, StatusCode::BAD_REQUEST);
+        assert_eq!(register(&store, "Näme_1.2-3").await, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn inactive_profiles_are_archived_until_login() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let (active_id, _) = registered(&store);
+        let now = Utc::now().timestamp();
+        {
+            let mut state = store.lock().unwrap();
+            state.players.get_mut(&player_id).unwrap().last_update = now - 365 * 24 * 60 * 60;
+            let written = archive::persist(archive::sweep(&state, now));
+            assert_eq!(written.len(), 1);
+            // 書庫に書いている間に変わったプロファイルは移さない
+            state.players.get_mut(&player_id).unwrap().last_seen = now - 364 * 24 * 60 * 60;
+            assert_eq!(archive::settle(&mut state, written), 0);
+            assert!(state.players.contains_key(&player_id));
+            let written = archive::persist(archive::sweep(&state, now));
+            assert_eq!(archive::settle(&mut state, written), 1);
+            assert!(!state.players.contains_key(&player_id));
+            assert!(state.players.contains_key(&active_id));
+            assert!(state.archived.contains(&player_id));
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/login", web::post().to(login_player))
+                .route("/api/players", web::get().to(list_players)),
+        )
+        .await;
+        let req = actix_test::TestRequest::get()
+            .uri("/api/players")
+            .to_request();
+        let players: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(players.as_array().unwrap().len(), 1);
+
+        let login = |token: &str| {
+            actix_test::TestRequest::post()
+                .uri("/api/player/login")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "player_id": player_id }))
+                .to_request()
+        };
+        assert_eq!(
+            actix_test::call_service(&app, login("guess"))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert!(store.lock().unwrap().archived.contains(&player_id));
+        let resp = actix_test::call_service(&app, login(&token)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let state = store.lock().unwrap();
+        assert!(state.players.contains_key(&player_id));
+        assert!(!state.archived.contains(&player_id));
+        drop(state);
+        // ログインしたばかりなので次の整理では移らない
+        assert!(archive::sweep(&store.lock().unwrap(), now).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn season_end_records_standings_and_sync_reports_the_new_season() {
+        let store = test_store();
+        let (leader_id, token) = registered(&store);
+        let (runner_up_id, _) = registered(&store);
+        let (cheater_id, _) = registered(&store);
+        let now = Utc::now().timestamp();
+        {
+            let mut state = store.lock().unwrap();
+            state.season.current = season::Season {
+                number: 3,
+                started_at: now - 100,
+                ends_at: Some(now + 100),
+            };
+            for (id, stage, flagged) in [
+                (&leader_id, 40, false),
+                (&runner_up_id, 25, false),
+                (&cheater_id, 500, true),
+            ] {
+                let profile = state.players.get_mut(id).unwrap();
+                profile.progress.stage = stage;
+                profile.flagged = flagged;
+            }
+            assert!(season::rollover(&mut state, now).is_none());
+            let rollover = season::rollover(&mut state, now + 100).unwrap();
+            season::persist(&rollover);
+            assert_eq!(rollover.standings.season, 3);
+            assert_eq!(rollover.standings.entries.len(), 2);
+            assert_eq!(rollover.standings.entries[0].player_id, leader_id);
+            assert_eq!(state.season.current.number, 4);
+            assert!(state.players[&cheater_id].seasons.is_empty());
+            let result = &state.players[&runner_up_id].seasons[0];
+            assert_eq!((result.season, result.rank, result.players), (3, 2, 2));
+        }
+
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}/sync", web::post().to(sync_player))
+                .route("/api/season", web::get().to(season::info)),
+        )
+        .await;
+        let req = actix_test::TestRequest::get()
+            .uri("/api/season")
+            .to_request();
+        let info: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(info["season"], 4);
+        assert_eq!(info["previous"]["season"], 3);
+        assert_eq!(info["previous"]["top"][0]["stage"], 40);
+
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/player/{}/sync", leader_id))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "progress": PlayerProgress::default() }))
+            .to_request();
+        let profile: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(profile["season"], 4);
+        assert_eq!(profile["seasons"][0]["rank"], 1);
+    }
+
+    #[actix_web::test]
+    async fn admin_season_end_exports_an_archive_and_starts_afresh() {
+        use std::io::Read;
+
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (leader_id, leader_token) = registered(&store);
+        let (runner_up_id, _) = registered(&store);
+        {
+            let mut state = store.lock().unwrap();
+            state.season.current = season::Season {
+                number: 7,
+                started_at: Utc::now().timestamp() - 100,
+                ends_at: None,
+            };
+            for (id, stage) in [(&leader_id, 30), (&runner_up_id, 20)] {
+                state.players.get_mut(id).unwrap().progress.stage = stage;
+            }
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/raid", web::get().to(raid::status))
+                .route("/api/raid/damage", web::post().to(raid::damage))
+                .route(
+                    "/api/raid/{id}/contribute",
+                    web::post().to(raid::contribute),
+                )
+                .route("/api/admin/raid/{id}", web::post().to(raid::create))
+                .route("/api/admin/season/end", web::post().to(season::end)),
+        )
+        .await;
+        let req = actix_test::TestRequest::post()
+            .uri("/api/admin/raid/weekly")
+            .insert_header(("authorization", "Bearer admin-secret"))
+            .set_json(serde_json::json!({ "ends_at": Utc::now().timestamp() + 3600 }))
+            .to_request();
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+        let hit = |uri: &str, damage: u64| {
+            actix_test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("authorization", format!("Bearer {}", leader_token)))
+                .set_json(serde_json::json!({ "player_id": leader_id, "damage": damage }))
+                .to_request()
+        };
+        for req in [
+            hit("/api/raid/damage", 500),
+            hit("/api/raid/weekly/contribute", 200),
+        ] {
+            assert_eq!(
+                actix_test::call_service(&app, req).await.status(),
+                StatusCode::OK
+            );
+        }
+
+        let end = |token: &str| {
+            actix_test::TestRequest::post()
+                .uri("/api/admin/season/end")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+        assert_eq!(
+            actix_test::call_service(&app, end(&leader_token))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let close = || async {
+            let res = actix_test::call_service(&app, end("admin-secret")).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get("content-type").unwrap(),
+                "application/gzip"
+            );
+            let body = actix_test::read_body(res).await;
+            let mut json = String::new();
+            flate2::read::GzDecoder::new(&body[..])
+                .read_to_string(&mut json)
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        };
+        let archive = close().await;
+        assert_eq!(archive["standings"]["season"], 7);
+        assert_eq!(archive["players"][0]["player_id"], leader_id.as_str());
+        assert_eq!(archive["players"][0]["rank"], 1);
+        assert_eq!(archive["players"][0]["raid_damage"], 700);
+        assert_eq!(archive["players"][1]["raid_damage"], 0);
+        assert_eq!(archive["raids"][0]["raid_id"], "boss-1");
+        assert_eq!(archive["raids"][1]["raid_id"], "weekly");
+
+        {
+            let state = store.lock().unwrap();
+            assert_eq!(state.season.current.number, 8);
+            assert_eq!(state.players[&runner_up_id].seasons[0].rank, 2);
+            // 受け取りが残る個別のレイドは残るが、次の記録では締めた分を数えない
+            assert_eq!(state.raids.len(), 1);
+        }
+        let req = hit("/api/raid/weekly/contribute", 50);
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+        let archive = close().await;
+        assert_eq!(archive["standings"]["season"], 8);
+        assert_eq!(archive["raids"].as_array().unwrap().len(), 1);
+        assert_eq!(archive["raids"][0]["total_damage"], 50);
+        let req = actix_test::TestRequest::get().uri("/api/raid").to_request();
+        let boss: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(boss["generation"], 1);
+        assert_eq!(boss["contributors"], 0);
+        assert_eq!(boss["hp"], boss["max_hp"]);
+    }
+
+    // 受け取っていない撃破済みのボスの報酬は、次のシーズンの同じ世代と重ならない名前で残る
+    #[actix_web::test]
+    async fn unclaimed_raid_boss_rewards_survive_the_season_end() {
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        store.lock().unwrap().season.current.number = 3;
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/raid", web::get().to(raid::status))
+                .route("/api/raid/damage", web::post().to(raid::damage))
+                .route("/api/raid/{id}/claim", web::post().to(raid::claim_reward))
+                .route("/api/admin/season/end", web::post().to(season::end)),
+        )
+        .await;
+        let post = |uri: &str, token: &str, body: serde_json::Value| {
+            actix_test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(body)
+                .to_request()
+        };
+        let end_season = || {
+            post(
+                "/api/admin/season/end",
+                "admin-secret",
+                serde_json::json!({}),
+            )
+        };
+
+        let req = post(
+            "/api/raid/damage",
+            &token,
+            serde_json::json!({ "player_id": player_id, "damage": u64::MAX }),
+        );
+        assert_eq!(
+            actix_test::call_service(&app, req).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            actix_test::call_service(&app, end_season()).await.status(),
+            StatusCode::OK
+        );
+
+        let req = actix_test::TestRequest::get()
+            .uri(&format!("/api/raid?player_id={}", player_id))
+            .to_request();
+        let boss: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(boss["generation"], 1);
+        assert_eq!(boss["pending_rewards"], serde_json::json!(["boss-1-s3"]));
+        let req = post(
+            "/api/raid/boss-1-s3/claim",
+            &token,
+            serde_json::json!({ "player_id": player_id }),
+        );
+        let reward: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(reward["coins"], 300);
+
+        // 受け取り済みになれば次のシーズンの終わりに消える
+        assert_eq!(
+            actix_test::call_service(&app, end_season()).await.status(),
+            StatusCode::OK
+        );
+        assert!(store.lock().unwrap().raids.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn shop_rotates_its_stock_and_sync_keeps_only_known_items() {
+        let now = Utc::now().timestamp();
+        let current = shop::stock(now);
+        assert_eq!(current.offers.len(), 4);
+        assert!(current.refreshed_at <= now && now < current.expires_at);
+        let again = shop::stock(current.expires_at - 1);
+        assert_eq!(again.rotation, current.rotation);
+        let items = |stock: &shop::ShopStock| -> Vec<&str> {
+            stock.offers.iter().map(|offer| offer.item).collect()
+        };
+        assert_eq!(items(&again), items(&current));
+        assert_eq!(
+            shop::stock(current.expires_at).rotation,
+            current.rotation + 1
+        );
+
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}/sync", web::post().to(sync_player))
+                .route("/api/shop", web::get().to(shop::get)),
+        )
+        .await;
+        let req = actix_test::TestRequest::get().uri("/api/shop").to_request();
+        let body: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["offers"].as_array().unwrap().len(), 4);
+        assert!(body["expires_at"].as_i64().unwrap() > now);
+
+        let progress = PlayerProgress {
+            prestige: Some(PrestigeProgress {
+                shop_items: vec![
+                    "lucky_coin".to_string(),
+                    "lucky_coin".to_string(),
+                    "free_coins".to_string(),
+                ],
+                ..PrestigeProgress::default()
+            }),
+            ..PlayerProgress::default()
+        };
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/player/{}/sync", player_id))
+            .insert_header(("authorization", format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "progress": progress }))
+            .to_request();
+        let profile: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            profile["progress"]["prestige"]["shop_items"],
+            serde_json::json!(["lucky_coin"])
+        );
+    }
+
+    #[test]
+    fn flags_override_the_environment() {
+        let from_env = config::ServerConfig {
+            bind: "127.0.0.1:9000".to_string(),
+            data_dir: None,
+            max_players: Some(10),
+            rate_limit: None,
+            player_rate_limit: None,
+            archive_after_days: None,
+            season: None,
+            season_ends_at: None,
+            season_days: None,
+        };
+        let args = [
+            "--port",
+            "8081",
+            "--data-dir=/srv/kurikka",
+            "--max-players",
+            "0",
+            "--rate-limit=0",
+        ];
+        let config::Parsed::Run(config) = from_env
+            .clone()
+            .parse(args.iter().map(|arg| arg.to_string()))
+            .unwrap()
+        else {
+            panic!("expected a config");
+        };
+        assert_eq!(config.bind, "127.0.0.1:8081");
+        assert_eq!(config.data_dir, Some(PathBuf::from("/srv/kurikka")));
+        assert_eq!(config.max_players, Some(0));
+        assert_eq!(config.rate_limit, Some(0));
+        assert!(from_env.clone().parse(["--port".to_string()]).is_err());
+        assert!(from_env.parse(["--verbose".to_string()]).is_err());
+    }
+
+    #[actix_web::test]
+    async fn taken_names_get_a_discriminator_and_login_needs_the_token() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        store
+            .lock()
+            .unwrap()
+            .name_index
+            .insert("fuzz".to_string(), player_id.clone());
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/register", web::post().to(register_player))
+                .route("/api/player/login", web::post().to(login_player)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/player/register")
+            .set_json(serde_json::json!({ "player_name": "Fuzz" }))
+            .to_request();
+        let body: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+        let name = body["player_name"].as_str().unwrap();
+        assert_ne!(body["player_id"], player_id.as_str());
+        assert!(name.starts_with("Fuzz#") && name.len() == "Fuzz#1234".len());
+        assert_eq!(register(&store, "Fuzz#0001").await, StatusCode::BAD_REQUEST);
+
+        let login = |token: &str| {
+            actix_test::TestRequest::post()
+                .uri("/api/player/login")
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "player_id": player_id }))
+                .to_request()
+        };
+        assert_eq!(
+            actix_test::call_service(&app, login("guess"))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let body: serde_json::Value =
+            actix_test::call_and_read_body_json(&app, login(&token)).await;
+        assert_eq!(body["player_id"], player_id.as_str());
+        assert_eq!(body["token"], token.as_str());
+    }
+
+    // トークン導入前のプレイヤーは公開されている ID だけでは乗っ取れず、管理者が発行したトークンで入る
+    #[actix_web::test]
+    async fn legacy_accounts_need_an_admin_issued_token() {
+        std::env::set_var("MULTIPLAYER_ADMIN_TOKEN", "admin-secret");
+        let store = test_store();
+        let (player_id, _) = registered(&store);
+        store.lock().unwrap().tokens.revoke(&player_id);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/login", web::post().to(login_player))
+                .route(
+                    "/api/admin/player/{id}/token",
+                    web::post().to(admin::issue_token),
+                ),
+        )
+        .await;
+        let login = |token: Option<&str>| {
+            let mut req = actix_test::TestRequest::post()
+                .uri("/api/player/login")
+                .set_json(serde_json::json!({ "player_id": player_id }));
+            if let Some(token) = token {
+                req = req.insert_header(("authorization", format!("Bearer {}", token)));
+            }
+            req.to_request()
+        };
+        let issue = |token: &str| {
+            actix_test::TestRequest::post()
+                .uri(&format!("/api/admin/player/{}/token", player_id))
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        let resp = actix_test::call_service(&app, login(None)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(store.lock().unwrap().tokens.get(&player_id).is_none());
+        let resp = actix_test::call_service(&app, issue("guess")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let body: serde_json::Value =
+            actix_test::call_and_read_body_json(&app, issue("admin-secret")).await;
+        let token = body["token"].as_str().unwrap().to_string();
+        let resp = actix_test::call_service(&app, login(None)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value =
+            actix_test::call_and_read_body_json(&app, login(Some(&token))).await;
+        assert_eq!(body["token"], token.as_str());
+    }
+
+    #[actix_web::test]
+    async fn armies_must_match_synced_upgrades() {
+        let store = test_store();
+        let (player_id, token) = registered(&store);
+        store
+            .lock()
+            .unwrap()
+            .players
+            .get_mut(&player_id)
+            .unwrap()
+            .progress
+            .upgrades
+            .small_attack = 20;
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .route("/api/player/{id}/army", web::get().to(army::get))
+                .route("/api/player/{id}/army", web::post().to(army::upload)),
+        )
+        .await;
+        let uri = format!("/api/player/{}/army", player_id);
+        let upload = |small_attack: u32, token: &str| {
+            let mut upgrades = serde_json::to_value(UpgradesProgress::default()).unwrap();
+            upgrades["small_attack"] = small_attack.into();
+            actix_test::TestRequest::post()
+                .uri(&uri)
+                .insert_header(("authorization", format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "upgrades": upgrades }))
+                .to_request()
+        };
+
+        let get = || actix_test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(
+            actix_test::call_service(&app, get()).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            actix_test::call_service(&app, upload(20, "guess"))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            actix_test::call_service(&app, upload(30, &token))
+                .await
+                .status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            actix_test::call_service(&app, upload(20, &token))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        let body: serde_json::Value = actix_test::call_and_read_body_json(&app, get()).await;
+        assert_eq!(body["player_id"], player_id.as_str());
+        assert_eq!(body["upgrades"]["small_attack"], 20);
+        assert!(body["created_at"].as_i64().unwrap() > 0);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn sanitized_progress_is_storable(mut progress in any_progress()) {
+            progress.sanitize();
+            assert_storable(&progress);
+            for level in [progress.upgrades.small_attack, progress.upgrades.heal_rate] {
+                prop_assert!(level <= MAX_UPGRADE_LEVEL);
+            }
+        }
+
+        #[test]
+        fn sync_never_errors_server_side(body in mutated_sync_body()) {
+            let store = test_store();
+            let (player_id, token) = registered(&store);
+            let status = actix_web::rt::System::new()
+                .block_on(sync(&store, &player_id, &token, serde_json::to_vec(&body).unwrap()));
+            prop_assert!(!status.is_server_error(), "status {}", status);
+            assert_storable(&store.lock().unwrap().players[&player_id].progress);
+        }
+
+        #[test]
+        fn sync_rejects_garbage_payloads(body in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let store = test_store();
+            let (player_id, token) = registered(&store);
+            let status = actix_web::rt::System::new().block_on(sync(&store, &player_id, &token, body));
+            prop_assert!(!status.is_server_error(), "status {}", status);
+        }
+
+        #[test]
+        fn register_validates_names(name in ".{0,64}") {
+            let store = test_store();
+            let response = actix_web::rt::System::new().block_on(register(&store, &name));
+            prop_assert!(!response.is_server_error());
+            for profile in store.lock().unwrap().players.values() {
+                prop_assert!(!profile.player_name.is_empty());
+                prop_assert!(profile.player_name.chars().count() <= MAX_NAME_LEN);
+                prop_assert!(!profile.player_name.chars().any(char::is_control));
+            }
+        }
+    }
 }
@@ -0,0 +1,170 @@
+use crate::{auth, i18n, PlayerStore};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, HttpRequest, HttpResponse, Responder,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+const DEFAULT_MESSAGE: &str = "Server is under maintenance";
+const DEFAULT_RETRY_AFTER_SECS: u64 = 300;
+const MAX_RETRY_AFTER_SECS: u64 = 24 * 60 * 60;
+const MAX_MESSAGE_LEN: usize = 200;
+
+// POST でも書き込みをしないエンドポイント（メンテナンス中も使える）
+// （login は last_seen を保存し、書庫に移ったプロファイルを戻すので書き込みに数える）
+const READ_ONLY_POSTS: [&str; 1] = ["/api/players/batch"];
+
+// メンテナンス中は書き込みを 503 で断り、読み取りだけ受け付ける（再起動しても続く）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Maintenance {
+    enabled: bool,
+    message: String,
+    retry_after_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    since: Option<i64>,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: DEFAULT_MESSAGE.to_string(),
+            retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+            since: None,
+        }
+    }
+}
+
+impl Maintenance {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn unavailable(&self, req: &HttpRequest) -> HttpResponse {
+        HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, self.retry_after_secs.to_string()))
+            .json(serde_json::json!({
+                "error": i18n::format(req, "maintenance", &[]),
+                "code": "maintenance",
+                "maintenance": true,
+                "message": self.message,
+                "retry_after_secs": self.retry_after_secs,
+            }))
+    }
+}
+
+fn maintenance_path() -> PathBuf {
+    let mut path = crate::data_root();
+    path.push("maintenance.json");
+    path
+}
+
+pub fn load() -> Maintenance {
+    fs::read_to_string(maintenance_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(maintenance: &Maintenance) -> std::io::Result<()> {
+    let path = maintenance_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(maintenance).unwrap_or_default();
+    fs::write(path, json)
+}
+
+fn is_write(req: &ServiceRequest) -> bool {
+    let path = req.path();
+    !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        && !path.starts_with("/api/admin/")
+        && !READ_ONLY_POSTS.contains(&path)
+}
+
+// 全ルートに掛けるミドルウェア。メンテナンス中の書き込みはハンドラーまで届かない
+pub async fn guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if is_write(&req) {
+        let rejection = req.app_data::<web::Data<PlayerStore>>().and_then(|store| {
+            let state = store.lock().unwrap();
+            state
+                .maintenance
+                .is_enabled()
+                .then(|| state.maintenance.unavailable(req.request()))
+        });
+        if let Some(response) = rejection {
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+pub async fn status(store: web::Data<PlayerStore>) -> impl Responder {
+    let maintenance = store.lock().unwrap().maintenance.clone();
+    HttpResponse::Ok().json(maintenance)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    enabled: bool,
+    message: Option<String>,
+    retry_after_secs: Option<u64>,
+}
+
+// 管理者トークン（MULTIPLAYER_ADMIN_TOKEN）で切り替える
+pub async fn update(
+    req: HttpRequest,
+    data: web::Json<MaintenanceRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let message = data
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .unwrap_or(DEFAULT_MESSAGE);
+    if message.chars().count() > MAX_MESSAGE_LEN {
+        return HttpResponse::BadRequest().json(i18n::error_with(
+            &req,
+            "maintenance_message_too_long",
+            &[("max", &MAX_MESSAGE_LEN)],
+        ));
+    }
+
+    let maintenance = {
+        let mut state = store.lock().unwrap();
+        // 有効のまま文言だけ変えても開始時刻は変えない
+        let since = match (data.enabled, state.maintenance.since) {
+            (false, _) => None,
+            (true, Some(since)) if state.maintenance.enabled => Some(since),
+            (true, _) => Some(Utc::now().timestamp()),
+        };
+        state.maintenance = Maintenance {
+            enabled: data.enabled,
+            message: message.to_string(),
+            retry_after_secs: data
+                .retry_after_secs
+                .unwrap_or(DEFAULT_RETRY_AFTER_SECS)
+                .clamp(1, MAX_RETRY_AFTER_SECS),
+            since,
+        };
+        state.maintenance.clone()
+    };
+    if let Err(err) = save(&maintenance) {
+        eprintln!("Failed to save maintenance state: {}", err);
+    }
+    HttpResponse::Ok().json(maintenance)
+}
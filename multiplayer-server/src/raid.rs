@@ -0,0 +1,750 @@
+use crate::{auth, i18n, PlayerStore, ServerState, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+const MAX_RAID_ID_LEN: usize = 64;
+
+// 全員で削る共有ボス。倒されるたびに次の世代がより高い HP で現れる
+const BOSS_BASE_HP: u64 = 1_000_000;
+const BOSS_HP_GROWTH: f64 = 1.25;
+const BOSS_NAMES: [&str; 4] = ["Behemoth", "Leviathan", "Colossus", "Hydra"];
+// 1 回の送信で受け付けるダメージの上限
+const MAX_DAMAGE_PER_CONTRIBUTION: u64 = 10_000_000;
+
+// 貢献度の上位何%に入ったかで報酬が決まる（上から順に判定）
+const REWARD_TIERS: [(RewardTier, f64, u64); 4] = [
+    (RewardTier::Legendary, 10.0, 5000),
+    (RewardTier::Epic, 25.0, 2000),
+    (RewardTier::Rare, 50.0, 1000),
+    (RewardTier::Common, 100.0, 300),
+];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardTier {
+    Legendary,
+    Epic,
+    Rare,
+    Common,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RaidContribution {
+    player_id: String,
+    player_name: String,
+    damage: u64,
+    #[serde(default)]
+    claimed: bool,
+    // 共有ボス撃破時の順位で確定したコイン（claim で受け取り、クライアントの進行に加える）
+    #[serde(default)]
+    reward: Option<u64>,
+    // 締めたシーズンの記録に入れ済みのダメージ（次のシーズンの記録では数えない）
+    #[serde(default)]
+    archived_damage: u64,
+}
+
+// ダメージ降順（同値なら名前順）
+fn by_damage(a: &RaidContribution, b: &RaidContribution) -> std::cmp::Ordering {
+    b.damage
+        .cmp(&a.damage)
+        .then_with(|| a.player_name.cmp(&b.player_name))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidLedger {
+    raid_id: String,
+    contributions: HashMap<String, RaidContribution>, // player_id -> 貢献
+    // 管理者が作ったレイドの締め切り。共有ボスと、作成を管理者に限る前のレイドにはない（締め切り済みとして扱う）
+    #[serde(default)]
+    ends_at: Option<i64>,
+}
+
+impl RaidLedger {
+    fn new(raid_id: &str, ends_at: Option<i64>) -> Self {
+        Self {
+            raid_id: raid_id.to_string(),
+            contributions: HashMap::new(),
+            ends_at,
+        }
+    }
+
+    fn is_open(&self, now: i64) -> bool {
+        self.ends_at.is_some_and(|ends_at| now < ends_at)
+    }
+
+    // ダメージ降順（同値なら名前順）
+    fn ranked(&self) -> Vec<&RaidContribution> {
+        let mut ranked: Vec<&RaidContribution> = self.contributions.values().collect();
+        ranked.sort_by(|a, b| by_damage(a, b));
+        ranked
+    }
+
+    // 前に締めたシーズンより後のダメージだけで並べた貢献
+    fn unarchived(&self) -> Vec<RaidContribution> {
+        let mut ranked: Vec<RaidContribution> = self
+            .contributions
+            .values()
+            .filter(|c| c.damage > c.archived_damage)
+            .map(|c| RaidContribution {
+                damage: c.damage - c.archived_damage,
+                ..c.clone()
+            })
+            .collect();
+        ranked.sort_by(by_damage);
+        ranked
+    }
+
+    fn standing(&self, player_id: &str) -> Option<RaidStanding> {
+        let ranked = self.ranked();
+        let total = ranked.len();
+        ranked
+            .iter()
+            .position(|c| c.player_id == player_id)
+            .map(|index| RaidStanding::new(ranked[index], index + 1, total))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RaidStanding {
+    rank: usize,
+    player_id: String,
+    player_name: String,
+    damage: u64,
+    // 上位何%か（1位が最も小さい）
+    percentile: f64,
+    tier: RewardTier,
+    claimed: bool,
+}
+
+impl RaidStanding {
+    fn new(contribution: &RaidContribution, rank: usize, total: usize) -> Self {
+        let percentile = rank as f64 / total.max(1) as f64 * 100.0;
+        Self {
+            rank,
+            player_id: contribution.player_id.clone(),
+            player_name: contribution.player_name.clone(),
+            damage: contribution.damage,
+            percentile,
+            tier: tier_for(percentile).0,
+            claimed: contribution.claimed,
+        }
+    }
+}
+
+fn tier_for(percentile: f64) -> (RewardTier, u64) {
+    REWARD_TIERS
+        .iter()
+        .find(|(_, max_percentile, _)| percentile <= *max_percentile)
+        .map(|(tier, _, coins)| (*tier, *coins))
+        .unwrap_or((RewardTier::Common, REWARD_TIERS[3].2))
+}
+
+fn raids_dir() -> PathBuf {
+    let mut dir = crate::data_root();
+    dir.push("raids");
+    dir
+}
+
+pub fn load_ledgers() -> HashMap<String, RaidLedger> {
+    let mut ledgers = HashMap::new();
+    if let Ok(entries) = fs::read_dir(raids_dir()) {
+        for entry in entries.flatten() {
+            if let Ok(contents) = fs::read_to_string(entry.path()) {
+                if let Ok(ledger) = serde_json::from_str::<RaidLedger>(&contents) {
+                    ledgers.insert(ledger.raid_id.clone(), ledger);
+                }
+            }
+        }
+    }
+    ledgers
+}
+
+fn save_ledger(ledger: &RaidLedger) -> std::io::Result<()> {
+    let dir = raids_dir();
+    fs::create_dir_all(&dir)?;
+    let mut path = dir;
+    path.push(format!("{}.json", ledger.raid_id));
+    let json = serde_json::to_string_pretty(ledger).unwrap_or_default();
+    fs::write(path, json)
+}
+
+// 共有ボスの raid_id（貢献は /api/raid/damage からだけ受け付ける）
+const BOSS_RAID_PREFIX: &str = "boss-";
+
+// raid_id はファイル名にも使うので英数字・'-'・'_' に限る
+fn valid_raid_id(raid_id: &str) -> bool {
+    !raid_id.is_empty()
+        && raid_id.len() <= MAX_RAID_ID_LEN
+        && raid_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn invalid_raid_id(req: &HttpRequest) -> HttpResponse {
+    HttpResponse::BadRequest().json(i18n::error(req, "invalid_raid_id"))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRaidRequest {
+    ends_at: i64,
+}
+
+// 個別のレイドは管理者だけが作る（ID ごとに台帳のファイルを書くので、プレイヤーには作らせない）
+pub async fn create(
+    req: HttpRequest,
+    raid_id: web::Path<String>,
+    data: web::Json<CreateRaidRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    if !valid_raid_id(&raid_id) || raid_id.starts_with(BOSS_RAID_PREFIX) {
+        return invalid_raid_id(&req);
+    }
+    if data.ends_at <= Utc::now().timestamp() {
+        return HttpResponse::BadRequest().json(i18n::error(&req, "raid_ends_in_past"));
+    }
+
+    let mut state = store.lock().unwrap();
+    if state.raids.contains_key(raid_id.as_str()) {
+        return HttpResponse::Conflict().json(i18n::error(&req, "raid_exists"));
+    }
+    let ledger = RaidLedger::new(&raid_id, Some(data.ends_at));
+    state.raids.insert(ledger.raid_id.clone(), ledger.clone());
+    drop(state);
+
+    if let Err(err) = save_ledger(&ledger) {
+        eprintln!("Failed to save raid ledger: {}", err);
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "raid_id": ledger.raid_id,
+        "ends_at": data.ends_at,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributeRequest {
+    player_id: String,
+    damage: u64,
+}
+
+pub async fn contribute(
+    req: HttpRequest,
+    raid_id: web::Path<String>,
+    data: web::Json<ContributeRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !valid_raid_id(&raid_id) {
+        return invalid_raid_id(&req);
+    }
+
+    if raid_id.starts_with(BOSS_RAID_PREFIX) {
+        return HttpResponse::BadRequest().json(i18n::error(&req, "use_raid_damage"));
+    }
+
+    let mut state = store.lock().unwrap();
+    let Some(player_name) = state
+        .players
+        .get(&data.player_id)
+        .map(|profile| profile.player_name.clone())
+    else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    };
+    if !auth::is_authorized(&req, &state, &data.player_id) {
+        return auth::unauthorized(&req);
+    }
+    let Some(ledger) = state.raids.get(raid_id.as_str()) else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "raid_not_found"));
+    };
+    if !ledger.is_open(Utc::now().timestamp()) {
+        return HttpResponse::Conflict().json(i18n::error(&req, "raid_ended"));
+    }
+
+    let ledger = record_contribution(
+        &mut state,
+        &raid_id,
+        &data.player_id,
+        player_name,
+        data.damage.min(MAX_DAMAGE_PER_CONTRIBUTION),
+    );
+    let standing = ledger.standing(&data.player_id);
+    let ledger_clone = ledger.clone();
+    drop(state);
+
+    if let Err(err) = save_ledger(&ledger_clone) {
+        eprintln!("Failed to save raid ledger: {}", err);
+    }
+
+    HttpResponse::Ok().json(standing)
+}
+
+fn record_contribution<'a>(
+    state: &'a mut ServerState,
+    raid_id: &str,
+    player_id: &str,
+    player_name: String,
+    damage: u64,
+) -> &'a mut RaidLedger {
+    let ledger = state
+        .raids
+        .entry(raid_id.to_string())
+        .or_insert_with(|| RaidLedger::new(raid_id, None));
+    let contribution = ledger
+        .contributions
+        .entry(player_id.to_string())
+        .or_insert_with(|| RaidContribution {
+            player_id: player_id.to_string(),
+            player_name: player_name.clone(),
+            damage: 0,
+            claimed: false,
+            reward: None,
+            archived_damage: 0,
+        });
+    contribution.player_name = player_name;
+    contribution.damage = contribution.damage.saturating_add(damage);
+    ledger
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RaidLeaderboardQuery {
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RaidLeaderboardPage {
+    raid_id: String,
+    ends_at: Option<i64>,
+    entries: Vec<RaidStanding>,
+    total_damage: u64,
+    page: usize,
+    page_size: usize,
+    total: usize,
+}
+
+// page は 1 始まり。まだ誰も参加していないレイドは空で返す
+pub async fn leaderboard(
+    req: HttpRequest,
+    raid_id: web::Path<String>,
+    query: web::Query<RaidLeaderboardQuery>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !valid_raid_id(&raid_id) {
+        return invalid_raid_id(&req);
+    }
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let state = store.lock().unwrap();
+    let ends_at = state
+        .raids
+        .get(raid_id.as_str())
+        .and_then(|ledger| ledger.ends_at);
+    let (entries, total_damage, total) = match state.raids.get(raid_id.as_str()) {
+        Some(ledger) => {
+            let ranked = ledger.ranked();
+            let total = ranked.len();
+            let total_damage = ranked
+                .iter()
+                .fold(0u64, |sum, c| sum.saturating_add(c.damage));
+            let offset = (page - 1).saturating_mul(page_size);
+            let entries = ranked
+                .into_iter()
+                .enumerate()
+                .skip(offset)
+                .take(page_size)
+                .map(|(index, contribution)| RaidStanding::new(contribution, index + 1, total))
+                .collect();
+            (entries, total_damage, total)
+        }
+        None => (Vec::new(), 0, 0),
+    };
+
+    HttpResponse::Ok().json(RaidLeaderboardPage {
+        raid_id: raid_id.to_string(),
+        ends_at,
+        entries,
+        total_damage,
+        page,
+        page_size,
+        total,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClaimRequest {
+    player_id: String,
+}
+
+#[derive(Serialize)]
+struct RaidReward {
+    raid_id: String,
+    rank: usize,
+    percentile: f64,
+    tier: RewardTier,
+    coins: u64,
+}
+
+// 報酬は締め切りの後に 1 レイドにつき 1 回だけ。順位は締め切った時点のもので確定する
+pub async fn claim_reward(
+    req: HttpRequest,
+    raid_id: web::Path<String>,
+    data: web::Json<ClaimRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    if !valid_raid_id(&raid_id) {
+        return invalid_raid_id(&req);
+    }
+
+    let mut state = store.lock().unwrap();
+    if !auth::is_authorized(&req, &state, &data.player_id) {
+        return auth::unauthorized(&req);
+    }
+    // 共有ボスの報酬は撃破時に確定する
+    if state.raid_boss.raid_id == raid_id.as_str() {
+        return HttpResponse::Conflict().json(i18n::error(&req, "raid_boss_alive"));
+    }
+    let Some(ledger) = state.raids.get_mut(raid_id.as_str()) else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "no_raid_contribution"));
+    };
+    if ledger.is_open(Utc::now().timestamp()) {
+        return HttpResponse::Conflict().json(i18n::error(&req, "raid_not_ended"));
+    }
+    let Some(standing) = ledger.standing(&data.player_id) else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "no_raid_contribution"));
+    };
+    if standing.claimed {
+        return HttpResponse::Conflict().json(i18n::error(&req, "reward_already_claimed"));
+    }
+    let (tier, mut coins) = tier_for(standing.percentile);
+    if let Some(contribution) = ledger.contributions.get_mut(&data.player_id) {
+        contribution.claimed = true;
+        coins = contribution.reward.unwrap_or(coins);
+    }
+    let ledger_clone = ledger.clone();
+    drop(state);
+
+    if let Err(err) = save_ledger(&ledger_clone) {
+        eprintln!("Failed to save raid ledger: {}", err);
+    }
+
+    HttpResponse::Ok().json(RaidReward {
+        raid_id: raid_id.to_string(),
+        rank: standing.rank,
+        percentile: standing.percentile,
+        tier,
+        coins,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidBoss {
+    generation: u32,
+    raid_id: String,
+    name: String,
+    max_hp: u64,
+    hp: u64,
+}
+
+impl RaidBoss {
+    fn spawn(generation: u32) -> Self {
+        let growth = BOSS_HP_GROWTH.powi(generation.saturating_sub(1).min(1_000) as i32);
+        let max_hp = (BOSS_BASE_HP as f64 * growth).min(u64::MAX as f64) as u64;
+        Self {
+            generation,
+            raid_id: format!("{}{}", BOSS_RAID_PREFIX, generation),
+            name: BOSS_NAMES[(generation as usize).saturating_sub(1) % BOSS_NAMES.len()]
+                .to_string(),
+            max_hp,
+            hp: max_hp,
+        }
+    }
+}
+
+impl Default for RaidBoss {
+    fn default() -> Self {
+        Self::spawn(1)
+    }
+}
+
+fn boss_path() -> PathBuf {
+    let mut path = crate::data_root();
+    path.push("raid_boss.json");
+    path
+}
+
+pub fn load_boss() -> RaidBoss {
+    fs::read_to_string(boss_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_boss(boss: &RaidBoss) -> std::io::Result<()> {
+    let path = boss_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(boss).unwrap_or_default();
+    fs::write(path, json)
+}
+
+#[derive(Serialize)]
+struct RaidBossStatus {
+    raid_id: String,
+    name: String,
+    generation: u32,
+    hp: u64,
+    max_hp: u64,
+    contributors: usize,
+    // 撃破済みで報酬を受け取っていないレイド（player_id を指定したときだけ）
+    pending_rewards: Vec<String>,
+}
+
+fn boss_status(state: &ServerState, player_id: Option<&str>) -> RaidBossStatus {
+    let boss = &state.raid_boss;
+    let pending_rewards = match player_id {
+        Some(player_id) => state
+            .raids
+            .values()
+            .filter(|ledger| {
+                ledger
+                    .contributions
+                    .get(player_id)
+                    .is_some_and(|c| c.reward.is_some() && !c.claimed)
+            })
+            .map(|ledger| ledger.raid_id.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+    RaidBossStatus {
+        raid_id: boss.raid_id.clone(),
+        name: boss.name.clone(),
+        generation: boss.generation,
+        hp: boss.hp,
+        max_hp: boss.max_hp,
+        contributors: state
+            .raids
+            .get(&boss.raid_id)
+            .map_or(0, |ledger| ledger.contributions.len()),
+        pending_rewards,
+    }
+}
+
+// 撃破時の順位で貢献者全員の報酬を確定する。コインは claim の応答でだけ渡す
+// （プロファイルにも加えると、クライアントの同期と合わせて二重に付与される）
+fn pay_out(ledger: &mut RaidLedger) {
+    let ranked: Vec<(String, f64)> = {
+        let ranked = ledger.ranked();
+        let total = ranked.len();
+        ranked
+            .iter()
+            .enumerate()
+            .map(|(index, c)| {
+                let standing = RaidStanding::new(c, index + 1, total);
+                (c.player_id.clone(), standing.percentile)
+            })
+            .collect()
+    };
+
+    for (player_id, percentile) in ranked {
+        if let Some(contribution) = ledger.contributions.get_mut(&player_id) {
+            contribution.reward = Some(tier_for(percentile).1);
+        }
+    }
+}
+
+// シーズンの記録に残すレイドの結果（順位はその時点のもの）
+#[derive(Debug, Serialize)]
+pub struct RaidResult {
+    raid_id: String,
+    total_damage: u64,
+    entries: Vec<RaidStanding>,
+}
+
+// 締めるシーズンのレイドの結果。前のシーズンの記録に入れたダメージは数えない
+pub struct RaidSummary {
+    pub results: Vec<RaidResult>,
+    // プレイヤーごとの全レイドの合計ダメージ
+    pub damage: HashMap<String, u64>,
+}
+
+// 状態は変えない（記録を書き出せると分かってから end_season で締める）
+pub fn season_summary(state: &ServerState) -> RaidSummary {
+    let mut results = Vec::with_capacity(state.raids.len());
+    let mut damage: HashMap<String, u64> = HashMap::new();
+    for ledger in state.raids.values() {
+        let ranked = ledger.unarchived();
+        if ranked.is_empty() {
+            continue;
+        }
+        let total = ranked.len();
+        for contribution in &ranked {
+            let sum = damage.entry(contribution.player_id.clone()).or_default();
+            *sum = sum.saturating_add(contribution.damage);
+        }
+        results.push(RaidResult {
+            raid_id: ledger.raid_id.clone(),
+            total_damage: ranked
+                .iter()
+                .fold(0u64, |sum, c| sum.saturating_add(c.damage)),
+            entries: ranked
+                .iter()
+                .enumerate()
+                .map(|(index, c)| RaidStanding::new(c, index + 1, total))
+                .collect(),
+        });
+    }
+    results.sort_by(|a, b| a.raid_id.cmp(&b.raid_id));
+    RaidSummary { results, damage }
+}
+
+// シーズンの終わりに締めたレイド（ロックの外で保存する）
+pub struct SeasonRaids {
+    boss: RaidBoss,
+    cleared: Vec<String>,
+    archived: Vec<RaidLedger>,
+}
+
+// 共有ボスを第 1 世代からやり直し、その貢献は消す
+// 報酬の受け取りが残る撃破済みのボスは、次のシーズンの同じ世代と raid_id が重ならないよう "<raid_id>-s<シーズン>" に移して残す
+// 受け取りが残る個別のレイドは残し、ここまでのダメージを記録済みにする
+pub fn end_season(state: &mut ServerState) -> SeasonRaids {
+    let season = state.season.current.number;
+    let bosses: Vec<String> = state
+        .raids
+        .keys()
+        .filter(|raid_id| raid_id.starts_with(BOSS_RAID_PREFIX))
+        .cloned()
+        .collect();
+    let mut cleared = Vec::new();
+    let mut moved = Vec::new();
+    for raid_id in bosses {
+        let Some(mut ledger) = state.raids.remove(&raid_id) else {
+            continue;
+        };
+        let pending = ledger
+            .contributions
+            .values()
+            .any(|c| c.reward.is_some() && !c.claimed);
+        if !pending {
+            cleared.push(raid_id);
+            continue;
+        }
+        // 前のシーズンに移したものはそのまま
+        let generation = &raid_id[BOSS_RAID_PREFIX.len()..];
+        if generation.bytes().all(|b| b.is_ascii_digit()) {
+            ledger.raid_id = format!("{}-s{}", raid_id, season);
+            moved.push(ledger.raid_id.clone());
+            cleared.push(raid_id);
+        }
+        state.raids.insert(ledger.raid_id.clone(), ledger);
+    }
+    let mut archived = Vec::new();
+    for ledger in state.raids.values_mut() {
+        let mut changed = moved.contains(&ledger.raid_id);
+        for contribution in ledger.contributions.values_mut() {
+            if contribution.archived_damage != contribution.damage {
+                contribution.archived_damage = contribution.damage;
+                changed = true;
+            }
+        }
+        if changed {
+            archived.push(ledger.clone());
+        }
+    }
+    state.raid_boss = RaidBoss::default();
+    SeasonRaids {
+        boss: state.raid_boss.clone(),
+        cleared,
+        archived,
+    }
+}
+
+impl SeasonRaids {
+    pub fn persist(&self) {
+        if let Err(err) = save_boss(&self.boss) {
+            eprintln!("Failed to save raid boss: {}", err);
+        }
+        for raid_id in &self.cleared {
+            let _ = fs::remove_file(raids_dir().join(format!("{}.json", raid_id)));
+        }
+        for ledger in &self.archived {
+            if let Err(err) = save_ledger(ledger) {
+                eprintln!("Failed to save raid ledger: {}", err);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RaidStatusQuery {
+    player_id: Option<String>,
+}
+
+pub async fn status(
+    query: web::Query<RaidStatusQuery>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let state = store.lock().unwrap();
+    HttpResponse::Ok().json(boss_status(&state, query.player_id.as_deref()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DamageRequest {
+    player_id: String,
+    damage: u64,
+}
+
+// 前回の送信から与えたダメージを共有ボスに加える。倒しきったら次の世代に入れ替える
+pub async fn damage(
+    req: HttpRequest,
+    data: web::Json<DamageRequest>,
+    store: web::Data<PlayerStore>,
+) -> impl Responder {
+    let mut state = store.lock().unwrap();
+    let Some(player_name) = state
+        .players
+        .get(&data.player_id)
+        .map(|profile| profile.player_name.clone())
+    else {
+        return HttpResponse::NotFound().json(i18n::error(&req, "player_not_found"));
+    };
+    if !auth::is_authorized(&req, &state, &data.player_id) {
+        return auth::unauthorized(&req);
+    }
+
+    let damage = data
+        .damage
+        .min(MAX_DAMAGE_PER_CONTRIBUTION)
+        .min(state.raid_boss.hp);
+    let raid_id = state.raid_boss.raid_id.clone();
+    record_contribution(&mut state, &raid_id, &data.player_id, player_name, damage);
+    state.raid_boss.hp -= damage;
+
+    if state.raid_boss.hp == 0 {
+        if let Some(ledger) = state.raids.get_mut(&raid_id) {
+            pay_out(ledger);
+        }
+        state.raid_boss = RaidBoss::spawn(state.raid_boss.generation.saturating_add(1));
+    }
+    let ledger_clone = state.raids.get(&raid_id).cloned();
+    let boss_clone = state.raid_boss.clone();
+    let status = boss_status(&state, Some(&data.player_id));
+    drop(state);
+
+    if let Some(Err(err)) = ledger_clone.as_ref().map(save_ledger) {
+        eprintln!("Failed to save raid ledger: {}", err);
+    }
+    if let Err(err) = save_boss(&boss_clone) {
+        eprintln!("Failed to save raid boss: {}", err);
+    }
+    HttpResponse::Ok().json(status)
+}
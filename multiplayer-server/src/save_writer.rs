@@ -0,0 +1,320 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// プロファイルごとに残す世代数と、世代を進める間隔（同期のたびに全世代が埋まらないように）
+pub const BACKUPS: usize = 3;
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// 書けなくなったときに書き込みを試し直す間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+enum Job {
+    Write(PathBuf, Vec<u8>),
+    // バックアップも含めて消す
+    Remove(PathBuf),
+}
+
+impl Job {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Write(path, _) | Self::Remove(path) => path,
+        }
+    }
+}
+
+// 書けなくなった保存先と理由。/health と管理用のメトリクスで返す
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageFailure {
+    pub path: String,
+    pub reason: String,
+    pub since: i64,
+    // メモリに残していて、まだ書けていないファイルの数
+    pub pending: usize,
+}
+
+// 書けない間は書き込みを止め、ファイルごとに最新の内容だけを残しておく
+struct Health {
+    failure: Option<StorageFailure>,
+    dirty: Vec<Job>,
+    next_retry: Option<Instant>,
+}
+
+static HEALTH: Mutex<Health> = Mutex::new(Health {
+    failure: None,
+    dirty: Vec::new(),
+    next_retry: None,
+});
+
+// 書けないと分かっている間は積まずにメモリに残す。試し直す時刻なら残した分をまとめて返す
+fn hold(job: Job) -> Vec<Job> {
+    let mut health = HEALTH.lock().unwrap();
+    if health.failure.is_none() {
+        return vec![job];
+    }
+    // 書き込みの順番と同じく、同じファイルの古い内容は最新のものに置き換えて後ろに並べる
+    health.dirty.retain(|kept| kept.path() != job.path());
+    health.dirty.push(job);
+    take_due(&mut health)
+}
+
+fn take_due(health: &mut Health) -> Vec<Job> {
+    if health.next_retry.is_some_and(|at| Instant::now() < at) {
+        return Vec::new();
+    }
+    health.next_retry = Some(Instant::now() + RETRY_INTERVAL);
+    std::mem::take(&mut health.dirty)
+}
+
+// 書き込みの結果を覚える。失敗した内容は、その後に新しい内容が来ていなければ残して試し直す
+// ログには書けなくなったとき・理由が変わったとき・戻ったときだけ出す
+fn record(job: Job, result: &Result<(), String>) {
+    let mut health = HEALTH.lock().unwrap();
+    let path = job.path().display().to_string();
+    match result {
+        // 書けなかったファイルが書けて、残りもなければ元に戻す
+        Ok(()) => {
+            let recovered = health
+                .failure
+                .as_ref()
+                .is_some_and(|failure| failure.path == path);
+            if recovered && health.dirty.is_empty() {
+                println!("[storage] writes recovered: {}", path);
+                health.failure = None;
+                health.next_retry = None;
+            }
+        }
+        Err(reason) => {
+            if !health.dirty.iter().any(|kept| kept.path() == job.path()) {
+                health.dirty.push(job);
+            }
+            let same = health
+                .failure
+                .as_ref()
+                .is_some_and(|failure| failure.path == path && &failure.reason == reason);
+            if !same {
+                eprintln!("Failed to save {}: {}", path, reason);
+                let since = health
+                    .failure
+                    .as_ref()
+                    .map_or_else(|| Utc::now().timestamp(), |failure| failure.since);
+                health.failure = Some(StorageFailure {
+                    path,
+                    reason: reason.clone(),
+                    since,
+                    pending: 0,
+                });
+            }
+            if health.next_retry.is_none() {
+                health.next_retry = Some(Instant::now() + RETRY_INTERVAL);
+            }
+        }
+    }
+}
+
+// 書けない状態なら保存先と理由を返す
+pub fn status() -> Option<StorageFailure> {
+    let health = HEALTH.lock().unwrap();
+    health.failure.clone().map(|failure| StorageFailure {
+        pending: health.dirty.len(),
+        ..failure
+    })
+}
+
+type Reply = Sender<Result<(), String>>;
+
+enum Message {
+    Job(Job, Option<Reply>),
+    Flush(Sender<()>),
+}
+
+static WRITER: Mutex<Option<Sender<Message>>> = Mutex::new(None);
+
+// すべての書き込みを 1 つのスレッドで順に行う（同じプロファイルへの同時の書き込みが混ざらない）
+// スレッドを立てられなければ呼び出し元に返す
+fn send(message: Message) -> Result<(), Message> {
+    let mut writer = WRITER.lock().unwrap();
+    if writer.is_none() {
+        let (sender, receiver) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("save-writer".to_string())
+            .spawn(move || run(receiver));
+        if spawned.is_ok() {
+            *writer = Some(sender);
+        }
+    }
+    match writer.as_ref() {
+        Some(sender) => sender.send(message).map_err(|err| err.0),
+        None => Err(message),
+    }
+}
+
+fn dispatch(job: Job) {
+    if let Err(Message::Job(job, _)) = send(Message::Job(job, None)) {
+        let result = perform(&job).map_err(|e| e.to_string());
+        record(job, &result);
+    }
+}
+
+// 積んで待たずに戻る（同期などのリクエストをディスクの書き込みで待たせない）
+fn enqueue(job: Job) {
+    for job in hold(job) {
+        dispatch(job);
+    }
+}
+
+pub fn submit(path: PathBuf, bytes: Vec<u8>) {
+    enqueue(Job::Write(path, bytes));
+}
+
+pub fn remove(path: PathBuf) {
+    enqueue(Job::Remove(path));
+}
+
+// 積んである分も含めて書き終わるまで待ち、この書き込みの結果を返す
+// （書けたことを確かめてから別のファイルを消す書庫からの復元用。ブロックするので web::block の中で呼ぶ）
+pub fn write(path: PathBuf, bytes: Vec<u8>) -> io::Result<()> {
+    let (reply, done) = mpsc::channel();
+    match send(Message::Job(Job::Write(path, bytes), Some(reply))) {
+        Ok(()) => done
+            .recv()
+            .unwrap_or_else(|_| Err("Save writer stopped".to_string()))
+            .map_err(io::Error::other),
+        Err(Message::Job(job, _)) => {
+            let result = perform(&job).map_err(|e| e.to_string());
+            record(job, &result);
+            result.map_err(io::Error::other)
+        }
+        Err(Message::Flush(_)) => unreachable!(),
+    }
+}
+
+// 積んである書き込みが終わるまで待つ（終了時とテスト用）。メモリに残した分も間隔を待たずに試す
+pub fn flush() {
+    let dirty = std::mem::take(&mut HEALTH.lock().unwrap().dirty);
+    for job in dirty {
+        dispatch(job);
+    }
+    let (reply, done) = mpsc::channel();
+    if send(Message::Flush(reply)).is_ok() {
+        let _ = done.recv();
+    }
+}
+
+// 新しい書き込みがなくても、書けない間は間隔をあけて残した分を試し直す
+fn run(receiver: Receiver<Message>) {
+    loop {
+        let first = match receiver.recv_timeout(RETRY_INTERVAL) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        let retries = {
+            let mut health = HEALTH.lock().unwrap();
+            if health.failure.is_some() {
+                take_due(&mut health)
+            } else {
+                Vec::new()
+            }
+        };
+        let mut pending: Vec<(Job, Vec<Reply>)> =
+            retries.into_iter().map(|job| (job, Vec::new())).collect();
+        let mut flushes = Vec::new();
+        for message in first.into_iter().chain(receiver.try_iter()) {
+            match message {
+                // 同じファイルへの古い書き込み・削除は最新のものに置き換え、最新の順番で行う
+                // （別のファイルに書いてから消す書庫への移動の順番を崩さない）
+                Message::Job(job, reply) => {
+                    let mut replies: Vec<Reply> = reply.into_iter().collect();
+                    if let Some(index) = pending
+                        .iter()
+                        .position(|(queued, _)| queued.path() == job.path())
+                    {
+                        replies.extend(pending.remove(index).1);
+                    }
+                    pending.push((job, replies));
+                }
+                Message::Flush(reply) => flushes.push(reply),
+            }
+        }
+        for (job, replies) in pending {
+            let result = perform(&job).map_err(|e| e.to_string());
+            for reply in replies {
+                let _ = reply.send(result.clone());
+            }
+            record(job, &result);
+        }
+        for reply in flushes {
+            let _ = reply.send(());
+        }
+    }
+}
+
+fn perform(job: &Job) -> io::Result<()> {
+    match job {
+        Job::Write(path, bytes) => write_atomic(path, bytes),
+        Job::Remove(path) => {
+            for generation in 1..=BACKUPS {
+                let _ = fs::remove_file(backup_path(path, generation));
+            }
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+// ここから下はクライアント（src-tauri/src/save_writer.rs）と同じ書き方。
+// サーバーとクライアントは共有のクレートを持たない別々のビルドなので、それぞれに置いている
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// 1 が最も新しい世代
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    suffixed(path, &format!("bak{}", generation))
+}
+
+fn backup_due(path: &Path) -> bool {
+    fs::metadata(backup_path(path, 1))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age >= BACKUP_INTERVAL)
+}
+
+// 古い世代から順にずらし、今のファイルを 1 世代目として残す
+fn rotate(path: &Path) {
+    for generation in (1..BACKUPS).rev() {
+        let _ = fs::rename(
+            backup_path(path, generation),
+            backup_path(path, generation + 1),
+        );
+    }
+    let _ = fs::copy(path, backup_path(path, 1));
+}
+
+// 一時ファイルに書いて同期してから置き換える（書き込みの途中で落ちても元のファイルは壊れない）
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = suffixed(path, "tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    if path.exists() && backup_due(path) {
+        rotate(path);
+    }
+    fs::rename(&temp, path)
+}
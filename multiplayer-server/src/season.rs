@@ -0,0 +1,438 @@
+use crate::{
+    auth, cache, data_root, i18n, raid, save_profile, PlayerProfile, PlayerStore, ServerState,
+};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io::Write, path::PathBuf, time::Duration};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// プロファイルに残す過去シーズンの成績の数
+const MAX_RESULTS: usize = 20;
+// /api/season で返す前シーズンの上位
+const PREVIOUS_TOP: usize = 10;
+
+// 現在のシーズン（終了時刻がなければ管理者が次の値を設定するまで続く）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Season {
+    pub number: u32,
+    pub started_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<i64>,
+}
+
+impl Default for Season {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            started_at: 0,
+            ends_at: None,
+        }
+    }
+}
+
+// シーズン終了時の各プレイヤーの成績（プロファイルに積む）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SeasonResult {
+    pub season: u32,
+    pub stage: u32,
+    pub coins: u64,
+    pub rank: usize,
+    pub players: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    pub rank: usize,
+    pub player_id: String,
+    pub player_name: String,
+    pub stage: u32,
+    pub coins: u64,
+}
+
+// 終わったシーズンの順位表（data/seasons/season-N.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonStandings {
+    pub season: u32,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub entries: Vec<Standing>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SeasonState {
+    pub current: Season,
+    pub previous: Option<SeasonStandings>,
+}
+
+// 切り替えで書き出すもの（ロックの外で保存する）
+pub struct Rollover {
+    pub season: Season,
+    pub standings: SeasonStandings,
+    pub profiles: Vec<PlayerProfile>,
+}
+
+fn season_path() -> PathBuf {
+    data_root().join("season.json")
+}
+
+fn standings_path(season: u32) -> PathBuf {
+    data_root()
+        .join("seasons")
+        .join(format!("season-{}.json", season))
+}
+
+fn archive_name(season: u32) -> String {
+    format!("season-{}-archive.json.gz", season)
+}
+
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+// MULTIPLAYER_SEASON_DAYS（--season-days から設定される）。0 なら終了時刻を自動で決めない
+fn season_days() -> Option<i64> {
+    env_number::<i64>("MULTIPLAYER_SEASON_DAYS").filter(|days| *days > 0)
+}
+
+fn next_end(started_at: i64) -> Option<i64> {
+    season_days().map(|days| started_at.saturating_add(days.saturating_mul(DAY_SECS)))
+}
+
+// 保存済みのシーズンを読み、設定（MULTIPLAYER_SEASON / MULTIPLAYER_SEASON_ENDS_AT）を反映する
+// 設定の番号が保存済みより大きいときだけ新しいシーズンとして始める
+pub fn load(now: i64) -> SeasonState {
+    let saved: Option<Season> = fs::read_to_string(season_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+    let configured = env_number::<u32>("MULTIPLAYER_SEASON");
+    let mut season = match saved.clone() {
+        Some(saved) if configured.is_none_or(|number| number <= saved.number) => saved,
+        _ => Season {
+            number: configured.unwrap_or(1).max(1),
+            started_at: now,
+            ends_at: None,
+        },
+    };
+    // 切り替え後の再起動で古い終了時刻を当て直さない
+    if let Some(ends_at) = env_number::<i64>("MULTIPLAYER_SEASON_ENDS_AT") {
+        if ends_at > season.started_at {
+            season.ends_at = Some(ends_at);
+        }
+    }
+    if season.ends_at.is_none() {
+        season.ends_at = next_end(season.started_at);
+    }
+    if saved.as_ref() != Some(&season) {
+        save(&season);
+    }
+    let previous = season
+        .number
+        .checked_sub(1)
+        .and_then(|number| fs::read_to_string(standings_path(number)).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+    SeasonState {
+        current: season,
+        previous,
+    }
+}
+
+fn save(season: &Season) {
+    let path = season_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(season).unwrap_or_default();
+    if let Err(err) = fs::write(path, json) {
+        eprintln!("Failed to save season: {}", err);
+    }
+}
+
+// 終了時刻を過ぎていれば順位を確定して次のシーズンに進める
+// このシーズンに同期した印なしのプレイヤーだけを順位に入れる（書庫のプロファイルは対象外）
+pub fn rollover(state: &mut ServerState, now: i64) -> Option<Rollover> {
+    let ends_at = state.season.current.ends_at;
+    if ends_at.is_none_or(|ends_at| ends_at > now) {
+        return None;
+    }
+    Some(close(state, now))
+}
+
+// 終了時刻に関係なく今のシーズンを締める
+fn close(state: &mut ServerState, now: i64) -> Rollover {
+    let standings = standings(state, now);
+    apply(state, standings, now)
+}
+
+// 今の順位表（状態は変えない）
+fn standings(state: &ServerState, now: i64) -> SeasonStandings {
+    let current = &state.season.current;
+    let mut ranked: Vec<&PlayerProfile> = state
+        .players
+        .values()
+        .filter(|profile| !profile.flagged && profile.last_update >= current.started_at)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.progress
+            .stage
+            .cmp(&a.progress.stage)
+            .then_with(|| b.progress.coins.cmp(&a.progress.coins))
+            .then_with(|| a.player_name.cmp(&b.player_name))
+    });
+    SeasonStandings {
+        season: current.number,
+        started_at: current.started_at,
+        ended_at: now,
+        entries: ranked
+            .into_iter()
+            .enumerate()
+            .map(|(index, profile)| Standing {
+                rank: index + 1,
+                player_id: profile.player_id.clone(),
+                player_name: profile.player_name.clone(),
+                stage: profile.progress.stage,
+                coins: profile.progress.coins,
+            })
+            .collect(),
+    }
+}
+
+// 順位表を各プロファイルの成績に積み、次のシーズンを始める
+fn apply(state: &mut ServerState, standings: SeasonStandings, now: i64) -> Rollover {
+    let players = standings.entries.len();
+    let mut profiles = Vec::with_capacity(players);
+    for standing in &standings.entries {
+        let Some(profile) = state.players.get_mut(&standing.player_id) else {
+            continue;
+        };
+        profile.seasons.push(SeasonResult {
+            season: standings.season,
+            stage: standing.stage,
+            coins: standing.coins,
+            rank: standing.rank,
+            players,
+        });
+        if profile.seasons.len() > MAX_RESULTS {
+            profile.seasons.remove(0);
+        }
+        profiles.push(profile.clone());
+    }
+
+    state.season = SeasonState {
+        current: Season {
+            number: standings.season + 1,
+            started_at: now,
+            ends_at: next_end(now),
+        },
+        previous: Some(standings.clone()),
+    };
+    Rollover {
+        season: state.season.current.clone(),
+        standings,
+        profiles,
+    }
+}
+
+pub fn persist(rollover: &Rollover) {
+    let path = standings_path(rollover.standings.season);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(&rollover.standings).unwrap_or_default();
+    if let Err(err) = fs::write(path, json) {
+        eprintln!("Failed to save season standings: {}", err);
+    }
+    for profile in &rollover.profiles {
+        save_profile(profile);
+    }
+    save(&rollover.season);
+}
+
+fn save_archive(name: &str, bytes: &[u8]) -> std::io::Result<()> {
+    let dir = data_root().join("seasons");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), bytes)
+}
+
+// 1 分ごとに終了時刻を確かめる（メンテナンス中は切り替えない）
+pub async fn run(store: PlayerStore) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let rollover = {
+            let mut state = store.lock().unwrap();
+            if state.maintenance.is_enabled() {
+                continue;
+            }
+            rollover(&mut state, Utc::now().timestamp())
+        };
+        if let Some(rollover) = rollover {
+            println!(
+                "Season {} ended with {} ranked players",
+                rollover.standings.season,
+                rollover.standings.entries.len()
+            );
+            persist(&rollover);
+        }
+    }
+}
+
+// 管理者がシーズンを締めたときの記録（data/seasons/season-N-archive.json.gz）
+#[derive(Serialize)]
+pub struct SeasonArchive {
+    exported_at: i64,
+    standings: SeasonStandings,
+    players: Vec<PlayerSeasonStats>,
+    raids: Vec<raid::RaidResult>,
+}
+
+// このシーズンに同期したプレイヤーの成績（flagged は順位なしで残す）
+#[derive(Serialize)]
+struct PlayerSeasonStats {
+    player_id: String,
+    player_name: String,
+    rank: Option<usize>,
+    stage: u32,
+    coins: u64,
+    raid_damage: u64,
+    flagged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guild_id: Option<String>,
+}
+
+fn player_stats(
+    state: &ServerState,
+    standings: &SeasonStandings,
+    raid_damage: &HashMap<String, u64>,
+) -> Vec<PlayerSeasonStats> {
+    let ranks: HashMap<&str, usize> = standings
+        .entries
+        .iter()
+        .map(|standing| (standing.player_id.as_str(), standing.rank))
+        .collect();
+    let mut players: Vec<PlayerSeasonStats> = state
+        .players
+        .values()
+        .filter(|profile| {
+            profile.last_update >= standings.started_at
+                || raid_damage.contains_key(&profile.player_id)
+        })
+        .map(|profile| PlayerSeasonStats {
+            player_id: profile.player_id.clone(),
+            player_name: profile.player_name.clone(),
+            rank: ranks.get(profile.player_id.as_str()).copied(),
+            stage: profile.progress.stage,
+            coins: profile.progress.coins,
+            raid_damage: raid_damage
+                .get(&profile.player_id)
+                .copied()
+                .unwrap_or_default(),
+            flagged: profile.flagged,
+            guild_id: profile.guild_id.clone(),
+        })
+        .collect();
+    players.sort_by(|a, b| {
+        a.rank
+            .unwrap_or(usize::MAX)
+            .cmp(&b.rank.unwrap_or(usize::MAX))
+            .then_with(|| a.player_name.cmp(&b.player_name))
+    });
+    players
+}
+
+fn compress(archive: &SeasonArchive) -> std::io::Result<Vec<u8>> {
+    let json = serde_json::to_vec(archive)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    encoder.finish()
+}
+
+// 今のシーズンをすぐに締め、順位表・プレイヤーごとの成績・レイドの結果を 1 つの gzip にして返す
+// 記録は状態を変える前に同じロックの中で作って圧縮し、作れなければ何も締めない
+// 順位の確定、次のシーズンの開始、共有ボスのやり直しは 1 回のロックの中で行う
+pub async fn end(req: HttpRequest, store: web::Data<PlayerStore>) -> impl Responder {
+    if !auth::is_admin(&req) {
+        return auth::unauthorized(&req);
+    }
+    let now = Utc::now().timestamp();
+    let (rollover, raids, bytes) = {
+        let mut state = store.lock().unwrap();
+        let summary = raid::season_summary(&state);
+        let standings = standings(&state, now);
+        let archive = SeasonArchive {
+            exported_at: now,
+            players: player_stats(&state, &standings, &summary.damage),
+            standings: standings.clone(),
+            raids: summary.results,
+        };
+        let bytes = match compress(&archive) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("Failed to build season archive: {}", err);
+                return HttpResponse::InternalServerError()
+                    .json(i18n::error(&req, "season_archive_failed"));
+            }
+        };
+        let raids = raid::end_season(&mut state);
+        let rollover = apply(&mut state, standings, now);
+        (rollover, raids, bytes)
+    };
+    println!(
+        "Season {} closed by an admin with {} ranked players",
+        rollover.standings.season,
+        rollover.standings.entries.len()
+    );
+    persist(&rollover);
+    raids.persist();
+
+    // 保存できなくても締めた後なので、応答の記録を失わないよう返す
+    let name = archive_name(rollover.standings.season);
+    if let Err(err) = save_archive(&name, &bytes) {
+        eprintln!("Failed to save season archive {}: {}", name, err);
+    }
+    HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header((
+            "content-disposition",
+            format!("attachment; filename=\"{}\"", name),
+        ))
+        .body(bytes)
+}
+
+#[derive(Serialize)]
+struct SeasonInfo<'a> {
+    season: u32,
+    started_at: i64,
+    ends_at: Option<i64>,
+    // 前シーズンの上位（まだ終わったシーズンがなければ null）
+    previous: Option<PreviousSeason<'a>>,
+}
+
+#[derive(Serialize)]
+struct PreviousSeason<'a> {
+    season: u32,
+    ended_at: i64,
+    players: usize,
+    top: &'a [Standing],
+}
+
+pub async fn info(req: HttpRequest, store: web::Data<PlayerStore>) -> impl Responder {
+    let state = store.lock().unwrap();
+    let season = &state.season;
+    let info = SeasonInfo {
+        season: season.current.number,
+        started_at: season.current.started_at,
+        ends_at: season.current.ends_at,
+        previous: season.previous.as_ref().map(|standings| PreviousSeason {
+            season: standings.season,
+            ended_at: standings.ended_at,
+            players: standings.entries.len(),
+            top: &standings.entries[..standings.entries.len().min(PREVIOUS_TOP)],
+        }),
+    };
+    cache::conditional_json(&req, &info, season.current.started_at)
+}
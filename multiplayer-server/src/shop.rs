@@ -0,0 +1,93 @@
+use crate::cache;
+use actix_web::{HttpRequest, Responder};
+use chrono::Utc;
+use serde::Serialize;
+
+// 品揃えが入れ替わる間隔と、1 回に並ぶ数
+const ROTATION_SECS: i64 = 6 * 60 * 60;
+const STOCK_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferKind {
+    // 見た目だけ（効果はない）
+    Cosmetic,
+    // 持っている間ずっと効く強化
+    Artifact,
+    // 機能の解放
+    Unlock,
+}
+
+// 転生ポイントで買える品（ID, 種類, 価格）。効果はクライアントが ID で決める
+const CATALOG: &[(&str, OfferKind, u64)] = &[
+    ("crimson_banner", OfferKind::Cosmetic, 3),
+    ("neon_trails", OfferKind::Cosmetic, 4),
+    ("starfield_sky", OfferKind::Cosmetic, 4),
+    ("golden_base", OfferKind::Cosmetic, 6),
+    ("war_drum", OfferKind::Artifact, 12),
+    ("iron_bulwark", OfferKind::Artifact, 12),
+    ("lucky_coin", OfferKind::Artifact, 15),
+    ("auto_buy_pass", OfferKind::Unlock, 20),
+];
+
+#[derive(Debug, Serialize)]
+pub struct ShopOffer {
+    pub item: &'static str,
+    pub kind: OfferKind,
+    // 転生ポイント
+    pub price: u64,
+}
+
+// /api/shop の応答。expires_at を過ぎると次の品揃えになる
+#[derive(Debug, Serialize)]
+pub struct ShopStock {
+    pub rotation: i64,
+    pub refreshed_at: i64,
+    pub expires_at: i64,
+    pub offers: Vec<ShopOffer>,
+}
+
+pub fn is_item(id: &str) -> bool {
+    CATALOG.iter().any(|(item, _, _)| *item == id)
+}
+
+// 同じ回ならどのサーバーでも同じ品揃えになるよう、回の番号から並べ替える
+fn shuffled(rotation: i64) -> Vec<usize> {
+    let mut seed = rotation as u64;
+    let mut next = || {
+        // splitmix64
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let mut order: Vec<usize> = (0..CATALOG.len()).collect();
+    for i in (1..order.len()).rev() {
+        order.swap(i, (next() % (i as u64 + 1)) as usize);
+    }
+    order
+}
+
+pub fn stock(now: i64) -> ShopStock {
+    let rotation = now.div_euclid(ROTATION_SECS);
+    let mut picked: Vec<usize> = shuffled(rotation).into_iter().take(STOCK_SIZE).collect();
+    picked.sort_unstable();
+    ShopStock {
+        rotation,
+        refreshed_at: rotation * ROTATION_SECS,
+        expires_at: (rotation + 1) * ROTATION_SECS,
+        offers: picked
+            .into_iter()
+            .map(|index| {
+                let (item, kind, price) = CATALOG[index];
+                ShopOffer { item, kind, price }
+            })
+            .collect(),
+    }
+}
+
+pub async fn get(req: HttpRequest) -> impl Responder {
+    let stock = stock(Utc::now().timestamp());
+    cache::conditional_json(&req, &stock, stock.refreshed_at)
+}
@@ -0,0 +1,92 @@
+use crate::PlayerProgress;
+
+// 1 回の同期で無条件に認める増加量（時計のずれや短い間隔の同期のため）
+const STAGE_ALLOWANCE: u64 = 5;
+const COIN_ALLOWANCE: u64 = 10_000;
+// 経過時間あたりに進めるステージ数の上限（1 ステージ最短 20 秒程度）
+const MAX_STAGES_PER_MINUTE: f64 = 3.0;
+// ステージ 1 あたり毎秒稼げるコインの上限（撃破・クリア・ボス報酬込みの目安）
+const MAX_COINS_PER_SECOND_PER_STAGE: f64 = 20.0;
+// 上限のこの倍を超える増加は保存せずに拒否する
+const REJECT_FACTOR: u64 = 10;
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Plausible,
+    // 保存はするがプロファイルに印を付ける
+    Suspicious(String),
+    // 明らかな改ざんなので保存しない
+    Rejected(String),
+}
+
+// 前回の同期からの経過時間に対して、ステージとコインの伸びがありえる範囲か判定する
+// （プレステージやコインの消費による減少は常に認める）
+pub fn check(previous: &PlayerProgress, next: &PlayerProgress, elapsed_secs: i64) -> Verdict {
+    let elapsed = elapsed_secs.max(1) as f64;
+
+    let stage_limit = STAGE_ALLOWANCE + (elapsed / 60.0 * MAX_STAGES_PER_MINUTE) as u64;
+    let stage_gain = next.stage.saturating_sub(previous.stage) as u64;
+
+    let stage = previous.stage.max(next.stage) as f64;
+    let coin_limit =
+        COIN_ALLOWANCE.saturating_add((elapsed * MAX_COINS_PER_SECOND_PER_STAGE * stage) as u64);
+    let coin_gain = next.coins.saturating_sub(previous.coins);
+
+    let reasons = [
+        (stage_gain, stage_limit, "stage"),
+        (coin_gain, coin_limit, "coins"),
+    ];
+    let mut suspicious = None;
+    for (gain, limit, field) in reasons {
+        let reason = format!("{} +{} in {}s (limit {})", field, gain, elapsed_secs, limit);
+        if gain > limit.saturating_mul(REJECT_FACTOR) {
+            return Verdict::Rejected(reason);
+        }
+        if gain > limit && suspicious.is_none() {
+            suspicious = Some(reason);
+        }
+    }
+    suspicious.map_or(Verdict::Plausible, Verdict::Suspicious)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(stage: u32, coins: u64) -> PlayerProgress {
+        PlayerProgress {
+            stage,
+            coins,
+            ..PlayerProgress::default()
+        }
+    }
+
+    #[test]
+    fn steady_play_is_plausible() {
+        assert_eq!(
+            check(&progress(10, 500), &progress(12, 3_000), 120),
+            Verdict::Plausible
+        );
+        // プレステージでステージが戻るのは正常
+        assert_eq!(
+            check(&progress(300, 9_000), &progress(1, 0), 5),
+            Verdict::Plausible
+        );
+    }
+
+    #[test]
+    fn jumps_are_flagged_or_rejected() {
+        assert!(matches!(
+            check(&progress(10, 0), &progress(30, 0), 60),
+            Verdict::Suspicious(_)
+        ));
+        assert!(matches!(
+            check(&progress(1, 0), &progress(1_000, 0), 60),
+            Verdict::Rejected(_)
+        ));
+        assert!(matches!(
+            check(&progress(1, 0), &progress(1, u64::MAX), 3_600),
+            Verdict::Rejected(_)
+        ));
+    }
+}
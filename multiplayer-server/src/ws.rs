@@ -0,0 +1,151 @@
+use crate::{auth, i18n, PlayerProfile, PlayerStore};
+use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 進行状況の更新（PlayerProfile の JSON）をソケットへ送る
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+pub struct ProgressPush(pub String);
+
+pub struct Subscriber {
+    client_id: Option<String>,
+    addr: Addr<PlayerSocket>,
+}
+
+pub struct PlayerSocket {
+    player_id: String,
+    client_id: Option<String>,
+    store: PlayerStore,
+    last_heartbeat: Instant,
+}
+
+impl PlayerSocket {
+    fn heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.last_heartbeat) > CLIENT_TIMEOUT {
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+    }
+}
+
+impl Actor for PlayerSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.heartbeat(ctx);
+        let mut state = self.store.lock().unwrap();
+        state
+            .sockets
+            .entry(self.player_id.clone())
+            .or_default()
+            .push(Subscriber {
+                client_id: self.client_id.clone(),
+                addr: ctx.address(),
+            });
+        // 接続直後に現在の進行状況を送る
+        if let Some(profile) = state.players.get(&self.player_id) {
+            if let Ok(json) = serde_json::to_string(profile) {
+                ctx.text(json);
+            }
+        }
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+        let mut state = self.store.lock().unwrap();
+        if let Some(subscribers) = state.sockets.get_mut(&self.player_id) {
+            subscribers.retain(|s| s.addr != addr);
+            if subscribers.is_empty() {
+                state.sockets.remove(&self.player_id);
+            }
+        }
+    }
+}
+
+impl Handler<ProgressPush> for PlayerSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: ProgressPush, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for PlayerSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(bytes)) => {
+                self.last_heartbeat = Instant::now();
+                ctx.pong(&bytes);
+            }
+            Ok(ws::Message::Pong(_)) => self.last_heartbeat = Instant::now(),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            // クライアントからの送信は受け付けない（同期は HTTP で行う）
+            Ok(_) => self.last_heartbeat = Instant::now(),
+            Err(_) => ctx.stop(),
+        }
+    }
+}
+
+// プレイヤーの全ソケットへ最新の進行状況を配信（送信元のクライアントには送り返さない）
+pub fn broadcast(store: &PlayerStore, origin: Option<&str>, profile: &PlayerProfile) {
+    let Ok(json) = serde_json::to_string(profile) else {
+        return;
+    };
+    let state = store.lock().unwrap();
+    if let Some(subscribers) = state.sockets.get(&profile.player_id) {
+        for subscriber in subscribers {
+            if origin.is_some() && subscriber.client_id.as_deref() == origin {
+                continue;
+            }
+            subscriber.addr.do_send(ProgressPush(json.clone()));
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SocketQuery {
+    client_id: Option<String>,
+    // WebSocket はヘッダーを付けられないクライアントもあるのでクエリでも受け付ける
+    token: Option<String>,
+}
+
+pub async fn player_socket(
+    req: HttpRequest,
+    stream: web::Payload,
+    player_id: web::Path<String>,
+    query: web::Query<SocketQuery>,
+    store: web::Data<PlayerStore>,
+) -> Result<HttpResponse, actix_web::Error> {
+    {
+        let state = store.lock().unwrap();
+        if !state.players.contains_key(player_id.as_str()) {
+            return Ok(HttpResponse::NotFound().json(i18n::error(&req, "player_not_found")));
+        }
+        let token = auth::bearer_token(&req).or(query.token.as_deref());
+        if !state.tokens.verify(&player_id, token) {
+            return Ok(auth::unauthorized(&req));
+        }
+    }
+    ws::start(
+        PlayerSocket {
+            player_id: player_id.into_inner(),
+            client_id: query.into_inner().client_id,
+            store: store.get_ref().clone(),
+            last_heartbeat: Instant::now(),
+        },
+        &req,
+        stream,
+    )
+}
@@ -0,0 +1,176 @@
+use crate::game::GameState;
+use crate::i18n::Language;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+// 読み上げが重ならないよう、定期の要約はこの間隔より短くしない
+const MIN_INTERVAL_SECS: u32 = 5;
+const DEFAULT_INTERVAL_SECS: u32 = 15;
+// 味方の基地がこの割合を下回ったら間隔を待たずに知らせる
+const URGENT_BASE_RATIO: f32 = 0.25;
+const URGENT_MIN_GAP: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct A11yConfig {
+    // 定期的に a11y-summary を送るか
+    pub summaries: bool,
+    pub interval_secs: u32,
+    // 要約の言語（"en" か "ja"、それ以外は英語）
+    pub locale: String,
+}
+
+impl Default for A11yConfig {
+    fn default() -> Self {
+        Self {
+            summaries: false,
+            interval_secs: DEFAULT_INTERVAL_SECS,
+            locale: "en".to_string(),
+        }
+    }
+}
+
+// スクリーンリーダーの読み上げの割り込み方（aria-live の値）
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Politeness {
+    Polite,
+    Assertive,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct StateSummary {
+    pub text: String,
+    // 読み上げの区切りごと（text はこれを空白でつないだもの）
+    pub sentences: Vec<String>,
+    pub politeness: Politeness,
+    pub locale: &'static str,
+}
+
+fn percent(hp: f32, max_hp: f32) -> u32 {
+    if max_hp <= 0.0 || !hp.is_finite() {
+        return 0;
+    }
+    (hp / max_hp * 100.0).round().clamp(0.0, 100.0) as u32
+}
+
+// 例: "Stage 12. 12 allies vs 8 enemies. Your base at 75%, enemy base at 40%."
+pub fn summarize(game: &GameState, locale: &str) -> StateSummary {
+    let language = Language::of(locale);
+    let allies = game.player_units.len();
+    let enemies = game.enemy_units.len();
+    let base = percent(game.player_base_hp, game.max_player_base_hp);
+    let enemy_base = percent(game.enemy_base_hp, game.max_enemy_base_hp);
+    let urgent = game.player_base_hp < game.max_player_base_hp * URGENT_BASE_RATIO;
+    let boss = game.boss_status();
+
+    let mut sentences = Vec::new();
+    match language {
+        Language::English => {
+            sentences.push(format!("Stage {}.", game.stage));
+            if let Some(boss) = &boss {
+                sentences.push(format!(
+                    "Boss {} at {}%.",
+                    boss.name,
+                    percent(boss.hp, boss.max_hp)
+                ));
+            }
+            let plural = |count: usize, one: &str, many: &str| {
+                format!("{} {}", count, if count == 1 { one } else { many })
+            };
+            sentences.push(format!(
+                "{} vs {}.",
+                plural(allies, "ally", "allies"),
+                plural(enemies, "enemy", "enemies")
+            ));
+            sentences.push(format!(
+                "Your base at {}%, enemy base at {}%.",
+                base, enemy_base
+            ));
+            if urgent {
+                sentences.push("Warning: your base is in danger.".to_string());
+            }
+        }
+        Language::Japanese => {
+            sentences.push(format!("ステージ{}。", game.stage));
+            if let Some(boss) = &boss {
+                sentences.push(format!(
+                    "ボス{}の体力は{}%。",
+                    boss.name,
+                    percent(boss.hp, boss.max_hp)
+                ));
+            }
+            sentences.push(format!("味方{}体、敵{}体。", allies, enemies));
+            sentences.push(format!(
+                "自陣の基地は{}%、敵の基地は{}%。",
+                base, enemy_base
+            ));
+            if urgent {
+                sentences.push("警告：基地が危険です。".to_string());
+            }
+        }
+    }
+    StateSummary {
+        text: sentences.join(" "),
+        sentences,
+        politeness: if urgent {
+            Politeness::Assertive
+        } else {
+            Politeness::Polite
+        },
+        locale: language.code(),
+    }
+}
+
+// 定期の要約の間引き（設定はコマンドとループで共有）
+pub struct A11yAnnouncer {
+    config: Mutex<A11yConfig>,
+    // 前回送った時刻・内容・急ぎだったか
+    last: Mutex<Option<(Instant, String, bool)>>,
+}
+
+impl A11yAnnouncer {
+    pub fn new(config: A11yConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn configure(&self, config: A11yConfig) {
+        *self.config.lock() = config;
+    }
+
+    pub fn locale(&self) -> String {
+        self.config.lock().locale.clone()
+    }
+
+    // 送るべき要約があれば返す。前回と同じ内容は送らない
+    pub fn poll(&self, game: &GameState) -> Option<StateSummary> {
+        let config = self.config.lock().clone();
+        if !config.summaries {
+            return None;
+        }
+        let mut last = self.last.lock();
+        // 基地が危険になった直後だけは間隔を待たずに知らせる
+        let urgent = game.player_base_hp < game.max_player_base_hp * URGENT_BASE_RATIO;
+        let gap = match last.as_ref() {
+            Some((_, _, was_urgent)) if urgent && !was_urgent => URGENT_MIN_GAP,
+            _ => Duration::from_secs(config.interval_secs.max(MIN_INTERVAL_SECS) as u64),
+        };
+        if last.as_ref().is_some_and(|(at, _, _)| at.elapsed() < gap) {
+            return None;
+        }
+        let summary = summarize(game, &config.locale);
+        if last
+            .as_ref()
+            .is_some_and(|(_, text, _)| *text == summary.text)
+        {
+            return None;
+        }
+        *last = Some((Instant::now(), summary.text.clone(), urgent));
+        Some(summary)
+    }
+}
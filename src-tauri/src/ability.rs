@@ -0,0 +1,76 @@
+use crate::game::UnitType;
+use rdev::Key;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 近接ユニットの攻撃距離
+pub const MELEE_RANGE: f32 = 10.0;
+// 遠隔ユニットは離れた位置から攻撃する
+const RANGED_RANGE: f32 = 120.0;
+// 回復ユニットの回復範囲と、最大HPに対する毎秒の回復割合
+pub const HEALER_RADIUS: f32 = 100.0;
+pub const HEALER_RATE: f32 = 0.04;
+// 回復ユニットはこの間隔でまとめて回復する
+pub const HEALER_PULSE_INTERVAL: f32 = 2.0;
+// タンクはこの距離内の敵の狙いを自分に引きつける
+pub const TAUNT_RADIUS: f32 = 150.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitAbility {
+    #[default]
+    Melee,
+    Ranged,
+    Healer,
+    Tank,
+}
+
+impl UnitAbility {
+    // 特殊キーで出撃する特殊ユニット（Enter: 遠隔、Tab: 回復、Backspace/Delete: タンク）
+    pub fn for_key(key: &Key) -> Option<Self> {
+        match key {
+            Key::Return | Key::KpReturn => Some(Self::Ranged),
+            Key::Tab => Some(Self::Healer),
+            Key::Backspace | Key::Delete => Some(Self::Tank),
+            _ => None,
+        }
+    }
+
+    // 能力値の元になるユニット種別
+    pub fn base_type(self) -> UnitType {
+        match self {
+            Self::Melee | Self::Ranged | Self::Healer => UnitType::Small,
+            Self::Tank => UnitType::Medium,
+        }
+    }
+
+    // 基本能力値に掛かる倍率（体力、攻撃力、速度）
+    pub fn stat_multipliers(self) -> (f32, f32, f32) {
+        match self {
+            Self::Melee => (1.0, 1.0, 1.0),
+            Self::Ranged => (0.7, 0.8, 1.0),
+            Self::Healer => (0.8, 0.3, 0.9),
+            Self::Tank => (2.5, 0.5, 0.7),
+        }
+    }
+
+    // 発動型の能力の再使用までの時間（常時効果の能力は None）
+    pub fn cooldown(self) -> Option<f32> {
+        match self {
+            Self::Healer => Some(HEALER_PULSE_INTERVAL),
+            Self::Melee | Self::Ranged | Self::Tank => None,
+        }
+    }
+
+    // 飛行ユニットを攻撃できる能力
+    pub fn hits_air(self) -> bool {
+        self == Self::Ranged
+    }
+
+    pub fn attack_range(self) -> f32 {
+        match self {
+            Self::Ranged => RANGED_RANGE,
+            _ => MELEE_RANGE,
+        }
+    }
+}
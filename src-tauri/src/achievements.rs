@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Clicks,
+    Keystrokes,
+    EnemiesDefeated,
+    StagesCleared,
+    UpgradesPurchased,
+}
+
+// (id, 名前, 説明, 指標, 必要数)
+const ACHIEVEMENTS: &[(&str, &str, &str, Metric, u64)] = &[
+    (
+        "clicks_100",
+        "First Clicks",
+        "Click 100 times",
+        Metric::Clicks,
+        100,
+    ),
+    (
+        "clicks_10k",
+        "Click Enthusiast",
+        "Click 10,000 times",
+        Metric::Clicks,
+        10_000,
+    ),
+    (
+        "clicks_1m",
+        "Click Machine",
+        "Click 1,000,000 times",
+        Metric::Clicks,
+        1_000_000,
+    ),
+    (
+        "keys_1k",
+        "Warming Up",
+        "Type 1,000 keys",
+        Metric::Keystrokes,
+        1_000,
+    ),
+    (
+        "keys_100k",
+        "Touch Typist",
+        "Type 100,000 keys",
+        Metric::Keystrokes,
+        100_000,
+    ),
+    (
+        "keys_10m",
+        "Keyboard Warrior",
+        "Type 10,000,000 keys",
+        Metric::Keystrokes,
+        10_000_000,
+    ),
+    (
+        "kills_100",
+        "Skirmisher",
+        "Defeat 100 enemies",
+        Metric::EnemiesDefeated,
+        100,
+    ),
+    (
+        "kills_10k",
+        "Veteran",
+        "Defeat 10,000 enemies",
+        Metric::EnemiesDefeated,
+        10_000,
+    ),
+    (
+        "kills_1m",
+        "Legion Breaker",
+        "Defeat 1,000,000 enemies",
+        Metric::EnemiesDefeated,
+        1_000_000,
+    ),
+    (
+        "stages_10",
+        "Advancing",
+        "Clear 10 stages",
+        Metric::StagesCleared,
+        10,
+    ),
+    (
+        "stages_100",
+        "Conqueror",
+        "Clear 100 stages",
+        Metric::StagesCleared,
+        100,
+    ),
+    (
+        "stages_1000",
+        "Unstoppable",
+        "Clear 1,000 stages",
+        Metric::StagesCleared,
+        1_000,
+    ),
+    (
+        "upgrades_1",
+        "Investor",
+        "Purchase an upgrade",
+        Metric::UpgradesPurchased,
+        1,
+    ),
+    (
+        "upgrades_50",
+        "Arms Race",
+        "Purchase 50 upgrades",
+        Metric::UpgradesPurchased,
+        50,
+    ),
+];
+
+// 実績ごとの称号 (id, 名前)
+pub fn titles() -> impl Iterator<Item = (&'static str, &'static str)> {
+    ACHIEVEMENTS.iter().map(|&(id, name, ..)| (id, name))
+}
+
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct LifetimeStats {
+    pub clicks: u64,
+    pub keystrokes: u64,
+    pub enemies_defeated: u64,
+    pub stages_cleared: u64,
+    pub upgrades_purchased: u64,
+}
+
+impl LifetimeStats {
+    fn get(&self, metric: Metric) -> u64 {
+        match metric {
+            Metric::Clicks => self.clicks,
+            Metric::Keystrokes => self.keystrokes,
+            Metric::EnemiesDefeated => self.enemies_defeated,
+            Metric::StagesCleared => self.stages_cleared,
+            Metric::UpgradesPurchased => self.upgrades_purchased,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct UnlockedAchievement {
+    pub id: String,
+    pub unlocked_at: i64,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct AchievementStatus {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub metric: Metric,
+    #[ts(type = "number")]
+    pub threshold: u64,
+    #[ts(type = "number")]
+    pub progress: u64,
+    #[ts(type = "number | null")]
+    pub unlocked_at: Option<i64>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct Achievements {
+    pub stats: LifetimeStats,
+    pub unlocked: Vec<UnlockedAchievement>,
+    // 解除されたがまだ通知していない実績
+    #[serde(skip)]
+    pending: Vec<AchievementStatus>,
+}
+
+impl Achievements {
+    fn unlocked_at(&self, id: &str) -> Option<i64> {
+        self.unlocked
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| a.unlocked_at)
+    }
+
+    fn status(&self, entry: &(&str, &str, &str, Metric, u64)) -> AchievementStatus {
+        let &(id, name, description, metric, threshold) = entry;
+        AchievementStatus {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            metric,
+            threshold,
+            progress: self.stats.get(metric).min(threshold),
+            unlocked_at: self.unlocked_at(id),
+        }
+    }
+
+    // 条件を満たした実績を解除する
+    pub fn check(&mut self, now: i64) {
+        for entry in ACHIEVEMENTS {
+            let &(id, _, _, metric, threshold) = entry;
+            if self.stats.get(metric) < threshold || self.unlocked_at(id).is_some() {
+                continue;
+            }
+            self.unlocked.push(UnlockedAchievement {
+                id: id.to_string(),
+                unlocked_at: now,
+            });
+            let status = self.status(entry);
+            self.pending.push(status);
+        }
+    }
+
+    pub fn take_pending(&mut self) -> Vec<AchievementStatus> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn list(&self) -> Vec<AchievementStatus> {
+        ACHIEVEMENTS
+            .iter()
+            .map(|entry| self.status(entry))
+            .collect()
+    }
+}
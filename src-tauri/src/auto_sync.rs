@@ -0,0 +1,108 @@
+use crate::config::AppConfig;
+use crate::error::GameError;
+use crate::game::unix_timestamp;
+use crate::multiplayer::{MultiplayerApi, MultiplayerError};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 15;
+const MAX_INTERVAL_SECS: u64 = 60 * 60;
+// 失敗が続いたときの再試行間隔（1 回ごとに倍）
+const RETRY_MIN_SECS: u64 = 5;
+const RETRY_MAX_SECS: u64 = 15 * 60;
+// 無効・未接続の間に設定を見直す間隔
+const IDLE_CHECK: Duration = Duration::from_secs(5);
+
+pub fn clamp_interval(secs: u64) -> u64 {
+    secs.clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS)
+}
+
+// "mp-sync-status" で送る自動同期の状態
+#[derive(Clone, Serialize, Debug, Default, TS)]
+pub struct SyncStatus {
+    pub enabled: bool,
+    pub connected: bool,
+    // 同期中
+    pub pending: bool,
+    #[ts(type = "number | null")]
+    pub last_success: Option<i64>,
+    #[ts(type = "number | null")]
+    pub last_attempt: Option<i64>,
+    pub error: Option<GameError>,
+    pub consecutive_failures: u32,
+    // 次に同期する時刻
+    #[ts(type = "number | null")]
+    pub next_attempt: Option<i64>,
+}
+
+fn retry_delay(failures: u32, err: &GameError) -> u64 {
+    let backoff = RETRY_MIN_SECS
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(RETRY_MAX_SECS);
+    // サーバーに待つよう言われたら、少なくともその間は送らない
+    match err {
+        GameError::Multiplayer {
+            error: MultiplayerError::RateLimited { retry_after_secs },
+            ..
+        } => backoff.max(*retry_after_secs),
+        _ => backoff,
+    }
+}
+
+// 接続中は一定間隔で進行状況を送ってリモートの変更を取り込み、失敗したら間隔を倍にして再試行する
+// （間隔と有効・無効は毎回設定から読むので、変更はすぐ反映される）
+pub async fn run<S, Fut, E>(mp_client: Arc<dyn MultiplayerApi>, sync: S, on_status: E)
+where
+    S: Fn() -> Fut,
+    Fut: Future<Output = Result<(), GameError>>,
+    E: Fn(&SyncStatus),
+{
+    let mut status = SyncStatus::default();
+    let mut wait = Duration::ZERO;
+    loop {
+        tokio::time::sleep(wait).await;
+        let config = AppConfig::load();
+        let connected = mp_client.is_connected();
+        if !config.auto_sync || !connected {
+            if status.enabled != config.auto_sync || status.connected != connected {
+                status.enabled = config.auto_sync;
+                status.connected = connected;
+                status.next_attempt = None;
+                on_status(&status);
+            }
+            wait = IDLE_CHECK;
+            continue;
+        }
+        status.enabled = true;
+        status.connected = true;
+        status.pending = true;
+        status.next_attempt = None;
+        on_status(&status);
+
+        let result = sync().await;
+        let now = unix_timestamp();
+        status.pending = false;
+        status.last_attempt = Some(now);
+        let delay = match result {
+            Ok(()) => {
+                status.last_success = Some(now);
+                status.error = None;
+                status.consecutive_failures = 0;
+                clamp_interval(config.auto_sync_interval_secs)
+            }
+            Err(err) => {
+                status.consecutive_failures = status.consecutive_failures.saturating_add(1);
+                let delay = retry_delay(status.consecutive_failures, &err);
+                status.error = Some(err);
+                delay
+            }
+        };
+        status.next_attempt = Some(now + delay as i64);
+        on_status(&status);
+        wait = Duration::from_secs(delay);
+    }
+}
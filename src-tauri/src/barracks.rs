@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 前線基地を置ける最大位置（敵基地に近づきすぎないように）
+pub const MAX_POSITION: f32 = 600.0;
+// 敵がこの距離まで近づくと破壊される
+const DESTROY_RANGE: f32 = 10.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct ForwardBarracks {
+    pub position: f32,
+}
+
+impl ForwardBarracks {
+    pub fn is_overrun(&self, enemy_position: f32) -> bool {
+        enemy_position <= self.position + DESTROY_RANGE
+    }
+}
+
+pub fn build_cost(stage: u32) -> u64 {
+    200 + stage as u64 * 20
+}
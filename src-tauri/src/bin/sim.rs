@@ -0,0 +1,4 @@
+// ステージ曲線の調整用: Tauri を起動せずにゲームを N 時間進めて統計を出す
+fn main() {
+    tauri_app_lib::sim::main()
+}
@@ -0,0 +1,118 @@
+use crate::stage;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// このステージ数ごとにボスが出現する
+pub const BOSS_INTERVAL: u32 = 10;
+
+// 通常の大型敵に対する倍率
+const HP_MULTIPLIER: f32 = 25.0;
+const ATTACK_MULTIPLIER: f32 = 3.0;
+const SPEED: f32 = 35.0;
+// 範囲攻撃（周囲の味方を吹き飛ばす）
+const AOE_INTERVAL: f32 = 8.0;
+const ENRAGED_AOE_INTERVAL: f32 = 5.0;
+pub const AOE_RADIUS: f32 = 150.0;
+// 味方の最大HPに対するダメージ割合
+pub const AOE_DAMAGE_RATIO: f32 = 0.1;
+pub const AOE_KNOCKBACK: f32 = 150.0;
+pub const AOE_KNOCKBACK_SECS: f32 = 0.5;
+// HP がこの割合を下回ると第2形態
+const ENRAGE_HP_RATIO: f32 = 0.5;
+
+const BOSS_NAMES: [&str; 5] = [
+    "Iron Colossus",
+    "Siege Warden",
+    "Storm Behemoth",
+    "Obsidian Tyrant",
+    "Abyssal Titan",
+];
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BossState {
+    pub unit_id: u32,
+    pub name: String,
+    pub phase: u32,
+    aoe_timer: f32,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct BossStatus {
+    pub name: String,
+    pub hp: f32,
+    pub max_hp: f32,
+    pub phase: u32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct BossPreview {
+    pub name: String,
+    pub hp: f32,
+    pub attack: f32,
+    pub speed: f32,
+}
+
+pub fn is_boss_stage(stage: u32) -> bool {
+    stage > 0 && stage.is_multiple_of(BOSS_INTERVAL)
+}
+
+pub fn name(stage: u32) -> &'static str {
+    BOSS_NAMES[(stage / BOSS_INTERVAL) as usize % BOSS_NAMES.len()]
+}
+
+// (体力, 攻撃力, 移動速度)
+pub fn stats(stage: u32) -> (f32, f32, f32) {
+    let (_, hp, attack, _) = stage::pick_enemy(1.0);
+    let multiplier = stage::stat_multiplier(stage);
+    (
+        hp * multiplier * HP_MULTIPLIER,
+        attack * multiplier * ATTACK_MULTIPLIER,
+        SPEED,
+    )
+}
+
+pub fn preview(stage: u32) -> Option<BossPreview> {
+    is_boss_stage(stage).then(|| {
+        let (hp, attack, speed) = stats(stage);
+        BossPreview {
+            name: name(stage).to_string(),
+            hp,
+            attack,
+            speed,
+        }
+    })
+}
+
+// 撃破ボーナスのコイン
+pub fn reward(stage: u32) -> u64 {
+    stage as u64 * 20
+}
+
+impl BossState {
+    pub fn new(unit_id: u32, stage: u32) -> Self {
+        Self {
+            unit_id,
+            name: name(stage).to_string(),
+            phase: 1,
+            aoe_timer: AOE_INTERVAL,
+        }
+    }
+
+    // 形態を更新し、範囲攻撃のタイミングなら true
+    pub fn tick(&mut self, delta: f32, hp: f32, max_hp: f32) -> bool {
+        if self.phase == 1 && hp < max_hp * ENRAGE_HP_RATIO {
+            self.phase = 2;
+            self.aoe_timer = self.aoe_timer.min(ENRAGED_AOE_INTERVAL);
+        }
+        self.aoe_timer -= delta;
+        if self.aoe_timer > 0.0 {
+            return false;
+        }
+        self.aoe_timer = if self.phase >= 2 {
+            ENRAGED_AOE_INTERVAL
+        } else {
+            AOE_INTERVAL
+        };
+        true
+    }
+}
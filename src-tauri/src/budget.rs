@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+// 片側に同時に存在できるユニット数の既定値と、設定できる範囲
+pub const DEFAULT_MAX_UNITS: usize = 400;
+pub const MIN_MAX_UNITS: usize = 50;
+pub const MAX_MAX_UNITS: usize = 5000;
+
+// 上限に達したときに新しく出現するユニットの扱い
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // 出現位置に最も近いユニットに合流させる（戦力は失われない）
+    #[default]
+    Merge,
+    // 最も古い小型ユニットを撃破報酬と同じコインに換えて枠を空ける
+    Despawn,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub struct UnitCaps {
+    pub max_player_units: usize,
+    pub max_enemy_units: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for UnitCaps {
+    fn default() -> Self {
+        Self {
+            max_player_units: DEFAULT_MAX_UNITS,
+            max_enemy_units: DEFAULT_MAX_UNITS,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl UnitCaps {
+    pub fn new(max_player_units: usize, max_enemy_units: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            max_player_units: max_player_units.clamp(MIN_MAX_UNITS, MAX_MAX_UNITS),
+            max_enemy_units: max_enemy_units.clamp(MIN_MAX_UNITS, MAX_MAX_UNITS),
+            overflow,
+        }
+    }
+}
+
+// ゲーム状態が使っているメモリの概算（確保済みの容量で数える）
+#[derive(Clone, Serialize, Debug)]
+pub struct MemoryUsage {
+    pub player_units: usize,
+    pub enemy_units: usize,
+    pub caps: UnitCaps,
+    pub unit_bytes: usize,
+    pub estimated_bytes: usize,
+}
@@ -0,0 +1,213 @@
+use crate::encryption;
+use crate::error::GameError;
+use crate::game::unix_timestamp;
+use crate::integrity;
+use crate::profile;
+use crate::save_writer::{self, SaveJob};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// 残すチェックポイントの数（古いものから消す）
+pub const MAX_CHECKPOINTS: usize = 20;
+
+// 取り返しのつかない操作の直前に取る
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointReason {
+    Prestige,
+    Respec,
+    ImportSave,
+    RemoteProgress,
+    // チェックポイントから戻す直前の状態
+    Restore,
+    // サーバーのシーズンが切り替わって周回をやり直す直前
+    Season,
+}
+
+impl CheckpointReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckpointReason::Prestige => "prestige",
+            CheckpointReason::Respec => "respec",
+            CheckpointReason::ImportSave => "import_save",
+            CheckpointReason::RemoteProgress => "remote_progress",
+            CheckpointReason::Restore => "restore",
+            CheckpointReason::Season => "season",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [
+            CheckpointReason::Prestige,
+            CheckpointReason::Respec,
+            CheckpointReason::ImportSave,
+            CheckpointReason::RemoteProgress,
+            CheckpointReason::Restore,
+            CheckpointReason::Season,
+        ]
+        .into_iter()
+        .find(|reason| reason.as_str() == value)
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct CheckpointInfo {
+    // restore_checkpoint に渡す名前（ファイル名から拡張子を除いたもの）
+    pub id: String,
+    pub reason: CheckpointReason,
+    pub created_at: i64,
+    pub stage: Option<u32>,
+    pub coins: Option<u64>,
+}
+
+// 一覧の表示に必要な項目だけを読む
+#[derive(Deserialize)]
+struct CheckpointSummary {
+    stage: u32,
+    coins: u64,
+}
+
+fn checkpoint_dir() -> Option<PathBuf> {
+    profile::data_dir().map(|dir| dir.join("checkpoints"))
+}
+
+// "<作成時刻>-<理由>" か、同じ秒に重なったら "<作成時刻>-<理由>-<連番>"
+fn parse_id(id: &str) -> Option<(i64, CheckpointReason)> {
+    let (created_at, rest) = id.split_once('-')?;
+    let reason = match rest.rsplit_once('-') {
+        Some((reason, n)) if n.bytes().all(|b| b.is_ascii_digit()) => reason,
+        _ => rest,
+    };
+    Some((created_at.parse().ok()?, CheckpointReason::parse(reason)?))
+}
+
+fn ids() -> Vec<(String, i64, CheckpointReason)> {
+    let Some(entries) = checkpoint_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let (created_at, reason) = parse_id(&id)?;
+            Some((id, created_at, reason))
+        })
+        .collect();
+    // 新しい順
+    ids.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    ids
+}
+
+// セーブと同じく暗号化して署名を添える。書けたら上限を超えた古いものを消す
+// （書けなければ書き込み用のスレッドがセーブと同じく保存先の状態に入れ、メモリに残して試し直す）
+pub fn write(reason: CheckpointReason, json: &[u8]) -> Result<String, GameError> {
+    let dir =
+        checkpoint_dir().ok_or_else(|| GameError::storage("Failed to resolve checkpoint path"))?;
+    let base = format!("{}-{}", unix_timestamp(), reason.as_str());
+    let id = (1..)
+        .map(|n| match n {
+            1 => base.clone(),
+            n => format!("{}-{}", base, n),
+        })
+        .find(|id| !dir.join(format!("{}.json", id)).exists())
+        .unwrap_or(base);
+    let path = dir.join(format!("{}.json", id));
+    let sealed = encryption::seal_file(&path, json).map_err(GameError::storage)?;
+    save_writer::write(SaveJob::new(path, sealed).with_companion(
+        dir.join(format!("{}.sig", id)),
+        integrity::sign(json).into_bytes(),
+    ))
+    .map_err(GameError::storage)?;
+    prune(MAX_CHECKPOINTS);
+    Ok(id)
+}
+
+fn prune(keep: usize) {
+    let Some(dir) = checkpoint_dir() else {
+        return;
+    };
+    for (id, _, _) in ids().into_iter().skip(keep) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", id)));
+        let _ = fs::remove_file(dir.join(format!("{}.sig", id)));
+    }
+}
+
+pub fn list() -> Vec<CheckpointInfo> {
+    let dir = checkpoint_dir();
+    ids()
+        .into_iter()
+        .map(|(id, created_at, reason)| {
+            let summary = dir
+                .as_ref()
+                .and_then(|dir| encryption::read_file(&dir.join(format!("{}.json", id))).ok())
+                .and_then(|bytes| serde_json::from_slice::<CheckpointSummary>(&bytes).ok());
+            CheckpointInfo {
+                stage: summary.as_ref().map(|s| s.stage),
+                coins: summary.as_ref().map(|s| s.coins),
+                id,
+                reason,
+                created_at,
+            }
+        })
+        .collect()
+}
+
+// 中身と、署名が合ったかどうか（一覧にある名前しか受け付けない）
+pub fn read(id: &str) -> Result<(Vec<u8>, bool), GameError> {
+    let not_found = || GameError::CheckpointNotFound { id: id.to_string() };
+    if !ids().iter().any(|(known, _, _)| known == id) {
+        return Err(not_found());
+    }
+    let dir = checkpoint_dir().ok_or_else(not_found)?;
+    let contents =
+        encryption::read_file(&dir.join(format!("{}.json", id))).map_err(|_| not_found())?;
+    let signed = fs::read_to_string(dir.join(format!("{}.sig", id)))
+        .is_ok_and(|signature| integrity::verify(&contents, &signature));
+    Ok((contents, signed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_round_trip_every_reason() {
+        for reason in [
+            CheckpointReason::Prestige,
+            CheckpointReason::Respec,
+            CheckpointReason::ImportSave,
+            CheckpointReason::RemoteProgress,
+            CheckpointReason::Restore,
+            CheckpointReason::Season,
+        ] {
+            assert_eq!(CheckpointReason::parse(reason.as_str()), Some(reason));
+            let id = format!("1700000000-{}", reason.as_str());
+            assert_eq!(parse_id(&id), Some((1_700_000_000, reason)));
+            // 同じ秒に重なったときの連番
+            assert_eq!(
+                parse_id(&format!("{}-3", id)),
+                Some((1_700_000_000, reason))
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_ids_are_ignored() {
+        for id in [
+            "",
+            "1700000000",
+            "1700000000-",
+            "soon-prestige",
+            "1700000000-unknown",
+            "1700000000-prestige-x",
+            "1700000000-prestige-2-3",
+        ] {
+            assert_eq!(parse_id(id), None, "{:?}", id);
+        }
+    }
+}
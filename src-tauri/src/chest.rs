@@ -0,0 +1,178 @@
+use crate::game::UPGRADE_OPTIONS;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// 未開封で保持できる宝箱の上限
+const MAX_STORED_CHESTS: usize = 50;
+// この数だけエピック以外が続いたら次はエピック確定
+const EPIC_PITY_THRESHOLD: u32 = 30;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChestRarity {
+    Common,
+    Rare,
+    Epic,
+}
+
+const COIN_VARIANCE: (f32, f32) = (0.75, 1.25);
+
+// レアリティごとの (出現率, コイン倍率, 無料アップグレード確率)
+const CHEST_TABLE: [(ChestRarity, f32, f32, f32); 3] = [
+    (ChestRarity::Common, 0.75, 1.0, 0.1),
+    (ChestRarity::Rare, 0.2, 3.0, 0.35),
+    (ChestRarity::Epic, 0.05, 8.0, 1.0),
+];
+
+fn table_entry(rarity: ChestRarity) -> (ChestRarity, f32, f32, f32) {
+    CHEST_TABLE
+        .iter()
+        .copied()
+        .find(|entry| entry.0 == rarity)
+        .unwrap_or(CHEST_TABLE[0])
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Chest {
+    pub id: u32,
+    pub rarity: ChestRarity,
+    // 入手したステージ（報酬量の基準）
+    pub stage: u32,
+    pub dropped_at: i64,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ChestUpgrade {
+    pub upgrade_type: String,
+    pub unit_type: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ChestReward {
+    pub chest_id: u32,
+    pub rarity: ChestRarity,
+    pub coins: u64,
+    pub upgrade: Option<ChestUpgrade>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ChestRate {
+    pub rarity: ChestRarity,
+    pub chance: f32,
+    pub coin_multiplier: f32,
+    pub upgrade_chance: f32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PityCounter {
+    pub rarity: ChestRarity,
+    pub count: u32,
+    pub threshold: u32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ChestDropRates {
+    pub rates: Vec<ChestRate>,
+    // 報酬コインの基準値に掛かるばらつきの範囲
+    pub coin_variance: (f32, f32),
+    pub pity: Vec<PityCounter>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct ChestInventory {
+    pub chests: Vec<Chest>,
+    next_id: u32,
+    // 前回のエピック以降に入手した宝箱の数
+    #[serde(default)]
+    epic_pity: u32,
+}
+
+impl ChestInventory {
+    // roll は 0.0〜1.0 の乱数
+    pub fn drop_chest(&mut self, stage: u32, now: i64, roll: f32) -> Option<Chest> {
+        if self.chests.len() >= MAX_STORED_CHESTS {
+            return None;
+        }
+        let rarity = if self.epic_pity + 1 >= EPIC_PITY_THRESHOLD {
+            ChestRarity::Epic
+        } else {
+            roll_rarity(roll)
+        };
+        self.epic_pity = if rarity == ChestRarity::Epic {
+            0
+        } else {
+            self.epic_pity + 1
+        };
+        let chest = Chest {
+            id: self.next_id,
+            rarity,
+            stage,
+            dropped_at: now,
+        };
+        self.next_id = self.next_id.wrapping_add(1);
+        self.chests.push(chest.clone());
+        Some(chest)
+    }
+
+    pub fn drop_rates(&self) -> ChestDropRates {
+        ChestDropRates {
+            rates: CHEST_TABLE
+                .iter()
+                .map(
+                    |&(rarity, chance, coin_multiplier, upgrade_chance)| ChestRate {
+                        rarity,
+                        chance,
+                        coin_multiplier,
+                        upgrade_chance,
+                    },
+                )
+                .collect(),
+            coin_variance: COIN_VARIANCE,
+            pity: vec![PityCounter {
+                rarity: ChestRarity::Epic,
+                count: self.epic_pity,
+                threshold: EPIC_PITY_THRESHOLD,
+            }],
+        }
+    }
+
+    pub fn take(&mut self, chest_id: u32) -> Option<Chest> {
+        let index = self.chests.iter().position(|c| c.id == chest_id)?;
+        Some(self.chests.remove(index))
+    }
+}
+
+// roll は 0.0〜1.0 の乱数
+fn roll_rarity(roll: f32) -> ChestRarity {
+    let mut acc = 0.0;
+    for &(rarity, chance, _, _) in &CHEST_TABLE {
+        acc += chance;
+        if roll < acc {
+            return rarity;
+        }
+    }
+    ChestRarity::Common
+}
+
+pub fn roll_reward(chest: &Chest, rng: &mut impl Rng) -> ChestReward {
+    let (_, _, coin_multiplier, upgrade_chance) = table_entry(chest.rarity);
+
+    // ステージクリア報酬を基準にばらつきを付ける
+    let base = (chest.stage as u64 * 5).max(10) as f32 * coin_multiplier;
+    let coins = (base * rng.gen_range(COIN_VARIANCE.0..=COIN_VARIANCE.1)).round() as u64;
+
+    let upgrade = rng.gen_bool(upgrade_chance as f64).then(|| {
+        let (upgrade_type, unit_type) = UPGRADE_OPTIONS[rng.gen_range(0..UPGRADE_OPTIONS.len())];
+        ChestUpgrade {
+            upgrade_type: upgrade_type.to_string(),
+            unit_type: unit_type.to_string(),
+        }
+    });
+
+    ChestReward {
+        chest_id: chest.id,
+        rarity: chest.rarity,
+        coins,
+        upgrade,
+    }
+}
@@ -0,0 +1,106 @@
+use crate::ability::UnitAbility;
+use crate::game::UnitType;
+use serde::Serialize;
+use ts_rs::TS;
+
+// ステージクリア時にシミュレーションを遅くする時間（実時間の秒）と速さ
+pub const SLOW_MOTION_SECS: f32 = 2.0;
+pub const SLOW_MOTION_SCALE: f32 = 0.25;
+
+// 敵基地にとどめを刺した一撃
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct FinalBlow {
+    pub stage: u32,
+    pub attacker_id: u32,
+    pub attacker_type: UnitType,
+    pub attacker_ability: UnitAbility,
+    pub attacker_position: f32,
+    // 破壊された側（今は敵基地のみ）
+    pub defender: &'static str,
+    pub defender_max_hp: f32,
+    pub damage: f32,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum SequencePhase {
+    Start,
+    End,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct SequenceEvent {
+    pub stage: u32,
+    pub phase: SequencePhase,
+    pub duration_secs: f32,
+    pub time_scale: f32,
+}
+
+// クリア演出の進行。演出が終わるまで次のステージへは進まない
+#[derive(Clone, Default)]
+pub struct StageClearSequence {
+    stage: u32,
+    remaining: f32,
+    final_blows: Vec<FinalBlow>,
+    pending: Vec<SequenceEvent>,
+}
+
+impl StageClearSequence {
+    pub fn start(&mut self, stage: u32, final_blow: Option<FinalBlow>) {
+        self.stage = stage;
+        self.remaining = SLOW_MOTION_SECS;
+        self.final_blows.extend(final_blow);
+        self.pending.push(SequenceEvent {
+            stage,
+            phase: SequencePhase::Start,
+            duration_secs: SLOW_MOTION_SECS,
+            time_scale: SLOW_MOTION_SCALE,
+        });
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        if self.is_active() {
+            SLOW_MOTION_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    // 実時間で進め、この呼び出しで演出が終わったら true
+    pub fn tick(&mut self, delta: f32) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        self.remaining -= delta;
+        if self.is_active() {
+            return false;
+        }
+        self.remaining = 0.0;
+        self.pending.push(SequenceEvent {
+            stage: self.stage,
+            phase: SequencePhase::End,
+            duration_secs: SLOW_MOTION_SECS,
+            time_scale: 1.0,
+        });
+        true
+    }
+
+    // ステージのやり直しなどで演出を打ち切る（終了通知だけ送る）
+    pub fn cancel(&mut self) {
+        if self.is_active() {
+            self.tick(self.remaining);
+        }
+    }
+
+    pub fn take_final_blows(&mut self) -> Vec<FinalBlow> {
+        std::mem::take(&mut self.final_blows)
+    }
+
+    pub fn take_pending(&mut self) -> Vec<SequenceEvent> {
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,66 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+// 最後の入力からこの秒数が過ぎるとコンボが減り始める
+const COMBO_WINDOW_SECS: f32 = 1.5;
+// 途切れた後に1秒あたり失う割合
+const DECAY_PER_SEC: f32 = 0.6;
+// 入力1回あたりの倍率上昇と上限
+const MULTIPLIER_PER_INPUT: f32 = 0.002;
+const MAX_MULTIPLIER_BONUS: f32 = 0.5;
+const MILESTONES: [u32; 6] = [25, 50, 100, 250, 500, 1000];
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct ComboMilestone {
+    pub combo: u32,
+    pub multiplier: f32,
+}
+
+#[derive(Clone, Default)]
+pub struct Combo {
+    value: f32,
+    idle: f32,
+    pending: Vec<ComboMilestone>,
+}
+
+impl Combo {
+    pub fn count(&self) -> u32 {
+        self.value as u32
+    }
+
+    // 出撃するユニットの体力・攻撃力に掛かる倍率
+    pub fn multiplier(&self) -> f32 {
+        1.0 + (self.count() as f32 * MULTIPLIER_PER_INPUT).min(MAX_MULTIPLIER_BONUS)
+    }
+
+    pub fn record_inputs(&mut self, inputs: u32) {
+        if inputs == 0 {
+            return;
+        }
+        let before = self.count();
+        self.value += inputs as f32;
+        self.idle = 0.0;
+        let after = self.count();
+        for &milestone in MILESTONES.iter().filter(|&&m| before < m && m <= after) {
+            self.pending.push(ComboMilestone {
+                combo: milestone,
+                multiplier: self.multiplier(),
+            });
+        }
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        self.idle += delta;
+        if self.idle <= COMBO_WINDOW_SECS {
+            return;
+        }
+        self.value *= (1.0 - DECAY_PER_SEC * delta).max(0.0);
+        if self.value < 1.0 {
+            self.value = 0.0;
+        }
+    }
+
+    pub fn take_milestones(&mut self) -> Vec<ComboMilestone> {
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -1,8 +1,76 @@
-use directories::ProjectDirs;
+use crate::a11y::A11yConfig;
+use crate::auto_sync;
+use crate::budget::{self, OverflowPolicy, UnitCaps};
+use crate::encryption::{self, SaveEncryption};
+use crate::energy::EnergyConfig;
+use crate::error::GameError;
+use crate::hotkey::{Hotkey, HotkeySequence};
+use crate::hotkey_action::ActionHotkeys;
+use crate::idle;
+use crate::input_backend::InputBackendKind;
+use crate::loop_control;
+use crate::notifications::NotificationConfig;
+use crate::palette::{self, CommandHotkey};
+use crate::profile;
+use crate::respec;
+use crate::save_writer::{self, SaveJob};
+use crate::schedule::ScheduleConfig;
+use crate::widget::{self, WidgetAlignment};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+// キー種別ごとのユニット生成への寄与率（一部だけ書いた表でも残りは既定値で読む）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyWeights {
+    pub letters: f32,
+    pub digits: f32,
+    pub symbols: f32,
+    pub editing: f32,
+    pub navigation: f32,
+    pub modifiers: f32,
+    pub function_keys: f32,
+    pub media: f32,
+}
+
+impl Default for KeyWeights {
+    fn default() -> Self {
+        Self {
+            letters: 1.0,
+            digits: 1.0,
+            symbols: 1.0,
+            editing: 1.0,
+            navigation: 0.5,
+            modifiers: 0.2,
+            function_keys: 0.0,
+            media: 0.0,
+        }
+    }
+}
+
+// ホイールとマウス移動のユニット生成への寄与（1.0 貯まるごとに小型ユニット 1 体）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointerWeights {
+    // ホイール 1 目盛りあたり
+    pub scroll: f32,
+    // マウス移動 1 ピクセルあたり
+    pub mouse_move: f32,
+    // 1 秒あたりに貯まる量の上限（ホイールの連打で稼げないように）
+    pub max_per_second: f32,
+}
+
+impl Default for PointerWeights {
+    fn default() -> Self {
+        Self {
+            scroll: 0.1,
+            mouse_move: 0.0005,
+            max_per_second: 2.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     #[serde(default = "default_server_url")]
@@ -11,10 +79,125 @@ pub struct AppConfig {
     pub multiplayer_player_name: String,
     #[serde(default = "default_player_id")]
     pub multiplayer_player_id: String,
+    // サーバーが登録時に発行する認証トークン
+    #[serde(default)]
+    pub multiplayer_token: String,
+    // 名前を登録せずにランキングだけを見る
+    #[serde(default)]
+    pub multiplayer_anonymous: bool,
+    // 開発用: サーバーにつながず手元のメモリで動かす（次の起動から）
+    #[serde(default)]
+    pub multiplayer_offline: bool,
     #[serde(default = "default_widget_offset")]
     pub widget_y_offset: i32,
     #[serde(default = "default_widget_unit_size")]
     pub widget_unit_size: i32,
+    // ウィジェットを置くモニターの名前（None なら現在のモニター）
+    #[serde(default)]
+    pub widget_monitor: Option<String>,
+    // モニター幅に対するウィジェットの幅（％、Full 配置では使わない）
+    #[serde(default = "default_widget_width_percent")]
+    pub widget_width_percent: u32,
+    #[serde(default)]
+    pub widget_alignment: WidgetAlignment,
+    // 起動時にウィジェットをクリックできる状態にするか（false ならクリックを透過）
+    #[serde(default)]
+    pub widget_interactive: bool,
+    // ウィジェットの不透明度（0.1〜1.0）と、コインとステージだけの細い表示にするか
+    #[serde(default = "default_widget_opacity")]
+    pub widget_opacity: f32,
+    #[serde(default)]
+    pub widget_compact: bool,
+    // ウィジェットの描画データを送る頻度（fps、1〜30）
+    #[serde(default = "default_widget_fps")]
+    pub widget_fps: u32,
+    // メインウィンドウを閉じたときに終了せずトレイに格納する
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    // クリック透過を切り替えるホットキー（例: "Ctrl+Alt+W"、空なら無効）
+    #[serde(default = "default_widget_hotkey")]
+    pub widget_hotkey: String,
+    #[serde(default = "default_ime_normalization")]
+    pub ime_normalization: bool,
+    #[serde(default = "default_ime_calibration")]
+    pub ime_calibration: f32,
+    #[serde(default)]
+    pub key_weights: KeyWeights,
+    #[serde(default)]
+    pub pointer_weights: PointerWeights,
+    // 入力フックの実装（フックは外せないので次の起動から反映）
+    #[serde(default)]
+    pub input_backend: InputBackendKind,
+    // 集中ブロック中に使うと減点になるアプリ名
+    #[serde(default)]
+    pub focus_blacklist: Vec<String>,
+    // ユニット生成に入力を数えるアプリ（空なら全アプリ）と、数えないアプリ（パスワード管理ソフトなど）
+    #[serde(default)]
+    pub input_app_allowlist: Vec<String>,
+    #[serde(default)]
+    pub input_app_denylist: Vec<String>,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default = "default_auto_pause_presentation")]
+    pub auto_pause_presentation: bool,
+    #[serde(default)]
+    pub save_encryption: SaveEncryption,
+    #[serde(default)]
+    pub save_encryption_salt: String,
+    // ゲームループの更新頻度（Hz）。バッテリー節約のため下げられる
+    #[serde(default = "default_tick_rate")]
+    pub tick_rate: u32,
+    // 入力がこの秒数ないと省電力モードに入る（0 で無効）
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    // 片側に同時に存在できるユニット数と、それを超えたときの扱い
+    #[serde(default = "default_max_units")]
+    pub max_player_units: usize,
+    #[serde(default = "default_max_units")]
+    pub max_enemy_units: usize,
+    #[serde(default)]
+    pub unit_overflow: OverflowPolicy,
+    // 進み具合を並べて表示するプレイヤーの ID
+    #[serde(default)]
+    pub friends: Vec<String>,
+    // 接続中に一定間隔で進行状況を自動で同期する
+    #[serde(default = "default_auto_sync")]
+    pub auto_sync: bool,
+    #[serde(default = "default_auto_sync_interval_secs")]
+    pub auto_sync_interval_secs: u64,
+    // カテゴリごとに OS の通知を出すか
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    // 強化の振り直しで返すコインの割合（％、100 まで）
+    #[serde(default = "default_respec_refund_percent")]
+    pub respec_refund_percent: u32,
+    // 入力をエネルギーとして貯め、出すユニットを選ぶモード
+    #[serde(default)]
+    pub energy: EnergyConfig,
+    // コマンドパレットのコマンドを結び付けたホットキー
+    #[serde(default)]
+    pub command_hotkeys: Vec<CommandHotkey>,
+    // スクリーンリーダー向けの戦況の要約
+    #[serde(default)]
+    pub a11y: A11yConfig,
+    // 一時停止やステージのやり直しなどゲーム操作のホットキー
+    #[serde(default)]
+    pub action_hotkeys: ActionHotkeys,
+    // 控えめモード（送信を 1 秒ごとにし、演出と OS の通知を止める）
+    #[serde(default)]
+    pub low_distraction: bool,
+    // 通知・エラー・トレイの文言の言語（"en" か "ja"）
+    #[serde(default = "default_locale")]
+    pub locale: String,
+}
+
+// 暗号化有効時は config.json から切り離して暗号化保存するマルチプレイ識別情報
+#[derive(Serialize, Deserialize)]
+struct Identity {
+    multiplayer_player_id: String,
+    multiplayer_player_name: String,
+    #[serde(default)]
+    multiplayer_token: String,
 }
 
 fn default_server_url() -> String {
@@ -25,6 +208,10 @@ fn default_player_name() -> String {
     "".to_string()
 }
 
+fn default_locale() -> String {
+    "en".to_string()
+}
+
 fn default_player_id() -> String {
     "".to_string()
 }
@@ -37,45 +224,228 @@ fn default_widget_unit_size() -> i32 {
     6 // デフォルトのユニットサイズ(中)
 }
 
+fn default_widget_opacity() -> f32 {
+    widget::MAX_OPACITY
+}
+
+fn default_widget_fps() -> u32 {
+    widget::DEFAULT_FPS
+}
+
+fn default_widget_hotkey() -> String {
+    "Ctrl+Alt+W".to_string()
+}
+
+fn default_widget_width_percent() -> u32 {
+    widget::MAX_WIDTH_PERCENT
+}
+
+fn default_auto_pause_presentation() -> bool {
+    true
+}
+
+fn default_tick_rate() -> u32 {
+    loop_control::DEFAULT_TICK_RATE
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    idle::DEFAULT_IDLE_TIMEOUT_SECS
+}
+
+fn default_max_units() -> usize {
+    budget::DEFAULT_MAX_UNITS
+}
+
+fn default_auto_sync() -> bool {
+    true
+}
+
+fn default_auto_sync_interval_secs() -> u64 {
+    auto_sync::DEFAULT_INTERVAL_SECS
+}
+
+fn default_respec_refund_percent() -> u32 {
+    respec::DEFAULT_REFUND_PERCENT
+}
+
+fn default_ime_normalization() -> bool {
+    true
+}
+
+fn default_ime_calibration() -> f32 {
+    1.0 // IME入力中のキー1回あたりの換算係数
+}
+
+// 換算係数の上限。大きすぎる値や負の値・NaN で打鍵数が壊れないよう読み込みと設定で丸める
+pub const MAX_IME_CALIBRATION: f32 = 4.0;
+
+pub fn clamp_ime_calibration(calibration: f32) -> f32 {
+    if calibration.is_finite() {
+        calibration.clamp(0.0, MAX_IME_CALIBRATION)
+    } else {
+        default_ime_calibration()
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             multiplayer_server_url: default_server_url(),
             multiplayer_player_name: default_player_name(),
             multiplayer_player_id: default_player_id(),
+            multiplayer_token: String::new(),
+            multiplayer_anonymous: false,
+            multiplayer_offline: false,
             widget_y_offset: default_widget_offset(),
             widget_unit_size: default_widget_unit_size(),
+            widget_monitor: None,
+            widget_width_percent: default_widget_width_percent(),
+            widget_alignment: WidgetAlignment::default(),
+            widget_interactive: false,
+            widget_opacity: default_widget_opacity(),
+            widget_compact: false,
+            widget_fps: default_widget_fps(),
+            widget_hotkey: default_widget_hotkey(),
+            minimize_to_tray: false,
+            ime_normalization: default_ime_normalization(),
+            ime_calibration: default_ime_calibration(),
+            key_weights: KeyWeights::default(),
+            pointer_weights: PointerWeights::default(),
+            input_backend: InputBackendKind::default(),
+            focus_blacklist: Vec::new(),
+            input_app_allowlist: Vec::new(),
+            input_app_denylist: Vec::new(),
+            schedule: ScheduleConfig::default(),
+            auto_pause_presentation: default_auto_pause_presentation(),
+            save_encryption: SaveEncryption::None,
+            save_encryption_salt: String::new(),
+            tick_rate: default_tick_rate(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            max_player_units: default_max_units(),
+            max_enemy_units: default_max_units(),
+            unit_overflow: OverflowPolicy::default(),
+            friends: Vec::new(),
+            auto_sync: default_auto_sync(),
+            auto_sync_interval_secs: default_auto_sync_interval_secs(),
+            notifications: NotificationConfig::default(),
+            respec_refund_percent: default_respec_refund_percent(),
+            energy: EnergyConfig::default(),
+            command_hotkeys: Vec::new(),
+            a11y: A11yConfig::default(),
+            action_hotkeys: ActionHotkeys::default(),
+            low_distraction: false,
+            locale: default_locale(),
         }
     }
 }
 
 impl AppConfig {
+    // 空ならホットキーなし
+    pub fn widget_hotkey(&self) -> Result<Option<Hotkey>, GameError> {
+        if self.widget_hotkey.trim().is_empty() {
+            return Ok(None);
+        }
+        Hotkey::parse(&self.widget_hotkey)
+            .map(Some)
+            .ok_or_else(|| GameError::InvalidHotkey {
+                hotkey: self.widget_hotkey.clone(),
+            })
+    }
+
+    // 保存する前にホットキーとコマンドの両方を確かめる
+    pub fn command_hotkeys(&self) -> Result<Vec<(String, HotkeySequence)>, GameError> {
+        self.command_hotkeys
+            .iter()
+            .map(|binding| {
+                let hotkey = HotkeySequence::parse(&binding.hotkey).ok_or_else(|| {
+                    GameError::InvalidHotkey {
+                        hotkey: binding.hotkey.clone(),
+                    }
+                })?;
+                palette::parse(&binding.command)?;
+                Ok((binding.command.clone(), hotkey))
+            })
+            .collect()
+    }
+
+    // 範囲外の値は上限・下限に丸める
+    pub fn unit_caps(&self) -> UnitCaps {
+        UnitCaps::new(
+            self.max_player_units,
+            self.max_enemy_units,
+            self.unit_overflow,
+        )
+    }
+
     fn config_file_path() -> Option<PathBuf> {
-        ProjectDirs::from("com", "ClickerClicker", "ClickerClickerClicker")
-            .map(|dirs| dirs.config_dir().join("config.json"))
+        profile::config_dir().map(|dir| dir.join("config.json"))
+    }
+
+    fn identity_file_path() -> Option<PathBuf> {
+        profile::config_dir().map(|dir| dir.join("identity.bin"))
     }
 
+    // 本体が読めなければ新しい世代のバックアップから順に試す
     pub fn load() -> Self {
-        if let Some(path) = Self::config_file_path() {
-            if let Ok(contents) = fs::read_to_string(&path) {
-                if let Ok(config) = serde_json::from_str(&contents) {
-                    return config;
+        let Some(path) = Self::config_file_path() else {
+            return Self::default();
+        };
+        let loaded = (0..=save_writer::BACKUPS).find_map(|generation| {
+            let path = match generation {
+                0 => path.clone(),
+                _ => save_writer::backup_path(&path, generation),
+            };
+            let contents = fs::read_to_string(path).ok()?;
+            serde_json::from_str::<AppConfig>(&contents).ok()
+        });
+        match loaded {
+            Some(mut config) => {
+                config.ime_calibration = clamp_ime_calibration(config.ime_calibration);
+                if config.save_encryption != SaveEncryption::None {
+                    config.load_identity();
                 }
+                config
             }
+            None => Self::default(),
         }
-        Self::default()
     }
 
-    pub fn save(&self) -> Result<(), String> {
+    fn load_identity(&mut self) {
+        let identity = Self::identity_file_path()
+            .and_then(|path| encryption::read_file(&path).ok())
+            .and_then(|bytes| serde_json::from_slice::<Identity>(&bytes).ok());
+        if let Some(identity) = identity {
+            self.multiplayer_player_id = identity.multiplayer_player_id;
+            self.multiplayer_player_name = identity.multiplayer_player_name;
+            self.multiplayer_token = identity.multiplayer_token;
+        }
+    }
+
+    pub fn save(&self) -> Result<(), GameError> {
         if let Some(path) = Self::config_file_path() {
-            if let Some(parent) = path.parent() {
-                let _ = fs::create_dir_all(parent);
+            let mut stored = self.clone();
+            if let Some(identity_path) = Self::identity_file_path() {
+                if encryption::is_enabled() {
+                    let identity = Identity {
+                        multiplayer_player_id: self.multiplayer_player_id.clone(),
+                        multiplayer_player_name: self.multiplayer_player_name.clone(),
+                        multiplayer_token: self.multiplayer_token.clone(),
+                    };
+                    let json = serde_json::to_vec(&identity).map_err(GameError::storage)?;
+                    encryption::write_file(&identity_path, &json).map_err(GameError::storage)?;
+                    stored.multiplayer_player_id = String::new();
+                    stored.multiplayer_player_name = String::new();
+                    stored.multiplayer_token = String::new();
+                } else if identity_path.exists() {
+                    let _ = fs::remove_file(identity_path);
+                }
             }
-            if let Ok(json) = serde_json::to_string_pretty(self) {
-                fs::write(path, json).map_err(|e| e.to_string())?;
+            if let Ok(json) = serde_json::to_string_pretty(&stored) {
+                let job = SaveJob::new(path, json.into_bytes()).with_backups();
+                save_writer::write(job).map_err(GameError::storage)?;
                 return Ok(());
             }
         }
-        Err("Failed to save config".to_string())
+        Err(GameError::storage("Failed to save config"))
     }
 }
@@ -0,0 +1,147 @@
+use crate::game::PlayerProgressData;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use ts_rs::TS;
+
+// サーバーの進行状況が手元と食い違ったときの解決方法
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    KeepLocal,
+    KeepRemote,
+    Merge,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Divergence {
+    Same,
+    // 片方がもう片方をすべての項目で上回っている（失うものがない）
+    LocalAhead,
+    RemoteAhead,
+    // 別々の端末で進めていて、どちらを採っても何かを失う
+    Diverged,
+}
+
+// フロントエンドに確認を求めるときに送る内容
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct SyncConflict {
+    pub local: PlayerProgressData,
+    pub remote: PlayerProgressData,
+    pub merged: PlayerProgressData,
+    #[ts(type = "number")]
+    pub remote_update: i64,
+    // サーバーの時計で測った、リモートの更新からの経過秒数（手元の時計のずれに左右されない）
+    #[ts(type = "number")]
+    pub remote_age_secs: i64,
+}
+
+impl SyncConflict {
+    pub fn new(
+        local: PlayerProgressData,
+        remote: PlayerProgressData,
+        remote_update: i64,
+        server_now: i64,
+    ) -> Self {
+        Self {
+            merged: merge(&local, &remote),
+            local,
+            remote,
+            remote_update,
+            remote_age_secs: (server_now - remote_update).max(0),
+        }
+    }
+
+    pub fn resolve(&self, resolution: Resolution) -> &PlayerProgressData {
+        match resolution {
+            Resolution::KeepLocal => &self.local,
+            Resolution::KeepRemote => &self.remote,
+            Resolution::Merge => &self.merged,
+        }
+    }
+}
+
+// 比べる項目（転生の進み具合、ステージ、コイン、各アップグレード）
+fn measures(progress: &PlayerProgressData) -> Vec<u64> {
+    let prestige = progress.prestige.as_ref();
+    let mut values = vec![
+        prestige.map_or(0, |p| p.prestige_count as u64),
+        prestige.map_or(0, |p| p.total_points_earned),
+        progress.stage as u64,
+        progress.coins,
+    ];
+    values.extend(progress.upgrades.levels().map(u64::from));
+    values
+}
+
+pub fn compare(local: &PlayerProgressData, remote: &PlayerProgressData) -> Divergence {
+    let mut local_ahead = false;
+    let mut remote_ahead = false;
+    for (l, r) in measures(local).into_iter().zip(measures(remote)) {
+        match l.cmp(&r) {
+            Ordering::Greater => local_ahead = true,
+            Ordering::Less => remote_ahead = true,
+            Ordering::Equal => {}
+        }
+    }
+    match (local_ahead, remote_ahead) {
+        (false, false) => Divergence::Same,
+        (true, false) => Divergence::LocalAhead,
+        (false, true) => Divergence::RemoteAhead,
+        (true, true) => Divergence::Diverged,
+    }
+}
+
+// 項目ごとの大きい方を採る。転生回数が違う場合は周回ごとに進行がリセットされているので、
+// 周回の進んだ側をそのまま採り、最高到達ステージだけ両方の大きい方にする
+pub fn merge(local: &PlayerProgressData, remote: &PlayerProgressData) -> PlayerProgressData {
+    let count =
+        |progress: &PlayerProgressData| progress.prestige.as_ref().map_or(0, |p| p.prestige_count);
+    let best_stage = |progress: &PlayerProgressData| {
+        progress
+            .prestige
+            .as_ref()
+            .map_or(0, |p| p.best_stage)
+            .max(progress.stage)
+    };
+
+    let mut merged = match count(local).cmp(&count(remote)) {
+        Ordering::Greater => local.clone(),
+        Ordering::Less => remote.clone(),
+        Ordering::Equal => {
+            let prestige = match (&local.prestige, &remote.prestige) {
+                (Some(l), Some(r)) if r.total_points_earned > l.total_points_earned => {
+                    Some(r.clone())
+                }
+                (Some(l), _) => Some(l.clone()),
+                (None, r) => r.clone(),
+            };
+            PlayerProgressData {
+                stage: local.stage.max(remote.stage),
+                coins: local.coins.max(remote.coins),
+                upgrades: local.upgrades.max_with(&remote.upgrades),
+                max_player_base_hp: local.max_player_base_hp.max(remote.max_player_base_hp),
+                max_enemy_base_hp: local.max_enemy_base_hp.max(remote.max_enemy_base_hp),
+                prestige,
+                titles: None,
+                cosmetics: None,
+            }
+        }
+    };
+    if let Some(prestige) = merged.prestige.as_mut() {
+        prestige.best_stage = best_stage(local).max(best_stage(remote));
+        prestige.highest_stage = prestige.highest_stage.max(merged.stage);
+    }
+    // 称号はどちらで獲得したものも残す
+    let mut titles = local.titles.clone().unwrap_or_default();
+    if let Some(remote_titles) = &remote.titles {
+        titles.absorb(remote_titles);
+    }
+    merged.titles = Some(titles);
+    // 見た目の解除も同じ
+    let mut cosmetics = local.cosmetics.clone().unwrap_or_default();
+    if let Some(remote_cosmetics) = &remote.cosmetics {
+        cosmetics.absorb(remote_cosmetics);
+    }
+    merged.cosmetics = Some(cosmetics);
+    merged
+}
@@ -0,0 +1,106 @@
+use crate::error::GameError;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 同じ机で 2 人で遊ぶときの担当（協力プレイでないときはすべて A）
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum CoopPlayer {
+    #[default]
+    A,
+    B,
+}
+
+// 入力の出どころ。rdev は入力デバイスの ID を返さないので、キーボードとマウスで分ける
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    Keyboard,
+    Mouse,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct CoopStatus {
+    pub enabled: bool,
+    #[ts(type = "number")]
+    pub coins_a: u64,
+    #[ts(type = "number")]
+    pub coins_b: u64,
+    // 撃破数（協力プレイを始めてから）
+    #[ts(type = "number")]
+    pub kills_a: u64,
+    #[ts(type = "number")]
+    pub kills_b: u64,
+}
+
+// キーボードの入力は A、マウスの入力は B のユニットになり、撃破報酬はそれぞれの財布に入る。
+// 基地と強化は共有。A の財布は GameState.coins をそのまま使う
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Coop {
+    pub enabled: bool,
+    coins: u64,
+    #[serde(default)]
+    coin_fraction: f64,
+    #[serde(default)]
+    kills: [u64; 2],
+}
+
+impl Coop {
+    pub fn player_for(&self, source: InputSource) -> CoopPlayer {
+        match (self.enabled, source) {
+            (true, InputSource::Mouse) => CoopPlayer::B,
+            _ => CoopPlayer::A,
+        }
+    }
+
+    // 切り替えても B の財布は残す（再開したときに続きから）
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && !self.enabled {
+            self.kills = [0; 2];
+        }
+        self.enabled = enabled;
+    }
+
+    pub fn coins(&self) -> u64 {
+        self.coins
+    }
+
+    pub fn add_coins(&mut self, amount: f64) {
+        self.coin_fraction += amount;
+        let whole = self.coin_fraction.floor();
+        self.coin_fraction -= whole;
+        self.coins = self.coins.saturating_add(whole as u64);
+    }
+
+    pub fn spend(&mut self, cost: u64) -> Result<(), GameError> {
+        if self.coins < cost {
+            return Err(GameError::NotEnoughCoins {
+                needed: cost,
+                have: self.coins,
+            });
+        }
+        self.coins -= cost;
+        Ok(())
+    }
+
+    pub fn record_kill(&mut self, player: CoopPlayer) {
+        let index = player as usize;
+        self.kills[index] = self.kills[index].saturating_add(1);
+    }
+
+    pub fn repair(&mut self) {
+        if !self.coin_fraction.is_finite() {
+            self.coin_fraction = 0.0;
+        }
+        self.coin_fraction = self.coin_fraction.clamp(0.0, 1.0);
+    }
+
+    pub fn status(&self, coins_a: u64) -> CoopStatus {
+        CoopStatus {
+            enabled: self.enabled,
+            coins_a,
+            coins_b: self.coins,
+            kills_a: self.kills[0],
+            kills_b: self.kills[1],
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CosmeticKind {
+    // プロフィールとランキングに出るアイコン
+    Avatar,
+    // 味方ユニットの色
+    Skin,
+}
+
+// 解除の条件（満たしているかは GameState が判定する）
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Unlock {
+    Free,
+    // 最高到達ステージ（転生しても下がらない）
+    Stage { stage: u32 },
+    Achievement { id: &'static str },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Cosmetic {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub kind: CosmeticKind,
+    pub unlock: Unlock,
+}
+
+const fn cosmetic(
+    id: &'static str,
+    name: &'static str,
+    kind: CosmeticKind,
+    unlock: Unlock,
+) -> Cosmetic {
+    Cosmetic {
+        id,
+        name,
+        kind,
+        unlock,
+    }
+}
+
+const COSMETICS: &[Cosmetic] = &[
+    cosmetic("recruit", "Recruit", CosmeticKind::Avatar, Unlock::Free),
+    cosmetic(
+        "knight",
+        "Knight",
+        CosmeticKind::Avatar,
+        Unlock::Stage { stage: 10 },
+    ),
+    cosmetic(
+        "general",
+        "General",
+        CosmeticKind::Avatar,
+        Unlock::Stage { stage: 50 },
+    ),
+    cosmetic(
+        "warlord",
+        "Warlord",
+        CosmeticKind::Avatar,
+        Unlock::Stage { stage: 200 },
+    ),
+    cosmetic(
+        "clicker",
+        "Clicker",
+        CosmeticKind::Avatar,
+        Unlock::Achievement { id: "clicks_10k" },
+    ),
+    cosmetic(
+        "typist",
+        "Typist",
+        CosmeticKind::Avatar,
+        Unlock::Achievement { id: "keys_100k" },
+    ),
+    cosmetic("classic", "Classic", CosmeticKind::Skin, Unlock::Free),
+    cosmetic(
+        "crimson",
+        "Crimson",
+        CosmeticKind::Skin,
+        Unlock::Stage { stage: 25 },
+    ),
+    cosmetic(
+        "golden",
+        "Golden",
+        CosmeticKind::Skin,
+        Unlock::Stage { stage: 100 },
+    ),
+    cosmetic(
+        "azure",
+        "Azure",
+        CosmeticKind::Skin,
+        Unlock::Achievement { id: "kills_10k" },
+    ),
+    cosmetic(
+        "shadow",
+        "Shadow",
+        CosmeticKind::Skin,
+        Unlock::Achievement { id: "stages_1000" },
+    ),
+];
+
+pub fn catalog() -> impl Iterator<Item = &'static Cosmetic> {
+    COSMETICS.iter()
+}
+
+pub fn find(id: &str) -> Option<&'static Cosmetic> {
+    COSMETICS.iter().find(|cosmetic| cosmetic.id == id)
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct CosmeticStatus {
+    pub id: String,
+    pub name: String,
+    pub kind: CosmeticKind,
+    pub unlock: Unlock,
+    pub unlocked: bool,
+    pub selected: bool,
+}
+
+// 解除した見た目と選んでいるもの。サーバーと同期し、ランキングにアバターが表示される
+#[derive(Clone, Serialize, Deserialize, Default, Debug, TS)]
+pub struct Cosmetics {
+    #[serde(default)]
+    pub unlocked: Vec<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub skin: Option<String>,
+}
+
+impl Cosmetics {
+    pub fn unlock(&mut self, id: &str) {
+        if !self.is_unlocked(id) {
+            self.unlocked.push(id.to_string());
+        }
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|unlocked| unlocked == id)
+    }
+
+    pub fn selected(&self, kind: CosmeticKind) -> Option<&str> {
+        match kind {
+            CosmeticKind::Avatar => self.avatar.as_deref(),
+            CosmeticKind::Skin => self.skin.as_deref(),
+        }
+    }
+
+    // 解除済みかは呼び出し側で確かめる。None で既定の見た目に戻す
+    pub fn select(&mut self, kind: CosmeticKind, id: Option<String>) {
+        match kind {
+            CosmeticKind::Avatar => self.avatar = id,
+            CosmeticKind::Skin => self.skin = id,
+        }
+    }
+
+    // 別の端末で解除した見た目を合わせる（解除は失われない）
+    pub fn absorb(&mut self, other: &Cosmetics) {
+        for id in &other.unlocked {
+            self.unlock(id);
+        }
+        if other.avatar.is_some() {
+            self.avatar = other.avatar.clone();
+        }
+        if other.skin.is_some() {
+            self.skin = other.skin.clone();
+        }
+    }
+
+    pub fn list(&self) -> Vec<CosmeticStatus> {
+        catalog()
+            .map(|cosmetic| CosmeticStatus {
+                id: cosmetic.id.to_string(),
+                name: cosmetic.name.to_string(),
+                kind: cosmetic.kind,
+                unlock: cosmetic.unlock,
+                unlocked: self.is_unlocked(cosmetic.id),
+                selected: self.selected(cosmetic.kind) == Some(cosmetic.id),
+            })
+            .collect()
+    }
+}
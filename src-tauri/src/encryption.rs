@@ -0,0 +1,248 @@
+use crate::profile;
+use crate::save_writer::{self, SaveJob};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use parking_lot::{const_rwlock, RwLock};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+// 暗号化ファイルの先頭に付けるマジックバイト（平文 JSON と区別する）
+const MAGIC: &[u8] = b"KRKENC1\0";
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const KEYRING_SERVICE: &str = "ClickerClickerClicker";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveEncryption {
+    #[default]
+    None,
+    Passphrase,
+    Keyring,
+}
+
+struct EncryptionState {
+    mode: SaveEncryption,
+    key: Option<[u8; KEY_LEN]>,
+}
+
+static STATE: RwLock<EncryptionState> = const_rwlock(EncryptionState {
+    mode: SaveEncryption::None,
+    key: None,
+});
+
+pub fn configure(mode: SaveEncryption, key: Option<[u8; KEY_LEN]>) {
+    let mut state = STATE.write();
+    state.mode = mode;
+    state.key = if mode == SaveEncryption::None {
+        None
+    } else {
+        key
+    };
+}
+
+// 方式を切り替える前の状態（書き直しに失敗したときに戻す）
+pub fn current() -> (SaveEncryption, Option<[u8; KEY_LEN]>) {
+    let state = STATE.read();
+    (state.mode, state.key)
+}
+
+// 起動時・プロファイル切り替え時の初期化。パスフレーズ方式は unlock されるまでロック状態
+pub fn init(mode: SaveEncryption) {
+    let key = match mode {
+        SaveEncryption::Keyring => keyring_key(false).ok(),
+        _ => None,
+    };
+    configure(mode, key);
+}
+
+pub fn mode() -> SaveEncryption {
+    STATE.read().mode
+}
+
+pub fn is_enabled() -> bool {
+    mode() != SaveEncryption::None
+}
+
+pub fn is_unlocked() -> bool {
+    let state = STATE.read();
+    state.mode == SaveEncryption::None || state.key.is_some()
+}
+
+pub fn generate_salt() -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn derive_key(passphrase: &str, salt: &str) -> Result<[u8; KEY_LEN], String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase is required".to_string());
+    }
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt.as_bytes(), &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+// OS のキーリングに保存した鍵を取得（create なら無いときに生成）
+pub fn keyring_key(create: bool) -> Result<[u8; KEY_LEN], String> {
+    let user = format!("save-key-{}", profile::active_profile());
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &user)
+        .map_err(|e| format!("Failed to open keyring: {}", e))?;
+    match entry.get_secret() {
+        Ok(secret) if secret.len() == KEY_LEN => {
+            let mut key = [0u8; KEY_LEN];
+            key.copy_from_slice(&secret);
+            Ok(key)
+        }
+        Ok(_) | Err(keyring::Error::NoEntry) if create => {
+            let mut key = [0u8; KEY_LEN];
+            rand::thread_rng().fill_bytes(&mut key);
+            entry
+                .set_secret(&key)
+                .map_err(|e| format!("Failed to store key in keyring: {}", e))?;
+            Ok(key)
+        }
+        Ok(_) => Err("Keyring entry is corrupted".to_string()),
+        Err(e) => Err(format!("Failed to read keyring: {}", e)),
+    }
+}
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn file_is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; MAGIC.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map(|_| is_encrypted(&header))
+        .unwrap_or(false)
+}
+
+fn encrypt_with(key: &[u8; KEY_LEN], plain: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plain)
+        .map_err(|_| "Failed to encrypt save".to_string())?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt_with(key: &[u8; KEY_LEN], bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_encrypted(bytes) || bytes.len() < MAGIC.len() + NONCE_LEN {
+        return Err("Not an encrypted save".to_string());
+    }
+    let (nonce, ciphertext) = bytes[MAGIC.len()..].split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong passphrase or corrupted save".to_string())
+}
+
+// 暗号化されていればキーで復号し、平文ならそのまま返す（既存セーブの移行用）
+pub fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    open(&bytes)
+}
+
+pub fn open(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    STATE.read().open(bytes)
+}
+
+// ファイルではなく 1 件ずつ暗号化する（追記型のジャーナル用）
+pub fn seal(plain: &[u8]) -> Result<Vec<u8>, String> {
+    STATE.read().seal(plain)
+}
+
+// path に書く内容。暗号化が有効なのにロック中の場合は書かせない（既存の暗号化セーブを上書きしないため）
+pub fn seal_file(path: &Path, plain: &[u8]) -> Result<Vec<u8>, String> {
+    STATE.read().seal_file(path, plain)
+}
+
+// 読み書きは今の方式のコピーに対して行う（テストはグローバルの方式を変えずに自分の方式で試せる）
+impl EncryptionState {
+    fn open(&self, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        if !is_encrypted(bytes) {
+            return Ok(bytes.to_vec());
+        }
+        let key = self.key.ok_or("Save is locked")?;
+        decrypt_with(&key, bytes)
+    }
+
+    fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, String> {
+        match (self.mode, self.key) {
+            (SaveEncryption::None, _) => Ok(plain.to_vec()),
+            (_, Some(key)) => encrypt_with(&key, plain),
+            (_, None) => Err("Save is locked".to_string()),
+        }
+    }
+
+    fn seal_file(&self, path: &Path, plain: &[u8]) -> Result<Vec<u8>, String> {
+        match self.mode {
+            // 設定が失われても暗号化済みのセーブを平文で上書きしない
+            SaveEncryption::None if file_is_encrypted(path) => Err("Save is locked".to_string()),
+            _ => self.seal(plain),
+        }
+    }
+}
+
+pub fn write_file(path: &Path, plain: &[u8]) -> Result<(), String> {
+    write_sealed(path, seal_file(path, plain)?)
+}
+
+// 方式を切り替えたときの書き直し。古い方式で暗号化されたファイルも今の方式で上書きする
+pub fn write_file_migrating(path: &Path, plain: &[u8]) -> Result<(), String> {
+    write_sealed(path, seal(plain)?)
+}
+
+fn write_sealed(path: &Path, bytes: Vec<u8>) -> Result<(), String> {
+    save_writer::write(SaveJob::new(path.to_path_buf(), bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_from_passphrase_to_none_rewrites_plaintext() {
+        let path =
+            std::env::temp_dir().join(format!("kurikka-encryption-{}.json", std::process::id()));
+        let plain = br#"{"coins":42}"#;
+        let passphrase = EncryptionState {
+            mode: SaveEncryption::Passphrase,
+            key: Some(derive_key("hunter2", &generate_salt()).unwrap()),
+        };
+        let none = EncryptionState {
+            mode: SaveEncryption::None,
+            key: None,
+        };
+
+        write_sealed(&path, passphrase.seal_file(&path, plain).unwrap()).unwrap();
+        assert!(file_is_encrypted(&path));
+
+        // 鍵を捨てた後の通常の書き込みは暗号化済みのファイルを上書きしない
+        assert!(none.seal_file(&path, plain).is_err());
+        assert!(file_is_encrypted(&path));
+
+        // 切り替え時の書き直しは平文で上書きする
+        write_sealed(&path, none.seal(plain).unwrap()).unwrap();
+        assert!(!file_is_encrypted(&path));
+        assert_eq!(none.open(&fs::read(&path).unwrap()).unwrap(), plain);
+
+        // 平文に戻したファイルは次の自動セーブでもそのまま書ける
+        assert_eq!(none.seal_file(&path, plain).unwrap(), plain);
+        let _ = fs::remove_file(&path);
+    }
+}
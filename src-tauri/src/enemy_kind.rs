@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 群れの敵が一度に出る数と、並ぶ間隔
+const SWARM_SIZE: u32 = 4;
+pub const SWARM_SPACING: f32 = 12.0;
+// 重装の敵が受けるダメージの割合
+const ARMORED_DAMAGE_TAKEN: f32 = 0.5;
+
+// 敵の性質（大きさとは別に、出現テーブルで混ぜる）
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyKind {
+    #[default]
+    Standard,
+    // 受けるダメージを減らす
+    Armored,
+    // 速度 2 倍、体力は低い
+    Fast,
+    // 弱いが群れで出る
+    Swarm,
+}
+
+const KINDS: [EnemyKind; 4] = [
+    EnemyKind::Standard,
+    EnemyKind::Armored,
+    EnemyKind::Fast,
+    EnemyKind::Swarm,
+];
+
+// (このステージから, 出現率 [標準, 重装, 高速, 群れ])
+const SPAWN_TABLE: [(u32, [f32; 4]); 4] = [
+    (1, [1.0, 0.0, 0.0, 0.0]),
+    (5, [0.75, 0.0, 0.25, 0.0]),
+    (12, [0.55, 0.15, 0.2, 0.1]),
+    (40, [0.4, 0.2, 0.2, 0.2]),
+];
+
+impl EnemyKind {
+    // 基本能力値に掛かる倍率（体力、攻撃力、速度）
+    pub fn stat_multipliers(self) -> (f32, f32, f32) {
+        match self {
+            Self::Standard => (1.0, 1.0, 1.0),
+            Self::Armored => (1.2, 1.0, 0.8),
+            Self::Fast => (0.5, 0.8, 2.0),
+            Self::Swarm => (0.3, 0.5, 1.1),
+        }
+    }
+
+    pub fn damage_taken(self) -> f32 {
+        match self {
+            Self::Armored => ARMORED_DAMAGE_TAKEN,
+            _ => 1.0,
+        }
+    }
+
+    pub fn group_size(self) -> u32 {
+        match self {
+            Self::Swarm => SWARM_SIZE,
+            _ => 1,
+        }
+    }
+}
+
+// そのステージの出現率
+pub fn spawn_weights(stage: u32) -> Vec<(EnemyKind, f32)> {
+    let weights = SPAWN_TABLE
+        .iter()
+        .rev()
+        .find(|&&(from, _)| stage >= from)
+        .map(|&(_, weights)| weights)
+        .unwrap_or(SPAWN_TABLE[0].1);
+    KINDS.into_iter().zip(weights).collect()
+}
+
+// roll は 0.0〜1.0 の乱数
+pub fn pick(stage: u32, roll: f32) -> EnemyKind {
+    let mut acc = 0.0;
+    for (kind, chance) in spawn_weights(stage) {
+        acc += chance;
+        if roll < acc {
+            return kind;
+        }
+    }
+    EnemyKind::Standard
+}
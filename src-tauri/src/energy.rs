@@ -0,0 +1,122 @@
+use crate::error::GameError;
+use crate::game::UnitType;
+use crate::hotkey::Hotkey;
+use serde::{Deserialize, Serialize};
+
+// 貯められるエネルギーの上限
+const CAPACITY: f64 = 200.0;
+// 入力 1 回で貯まる量（クリックは即時出撃の中型と釣り合うように多め）
+const PER_TYPE: f64 = 1.0;
+const PER_CLICK: f64 = 3.0;
+// 呼び出せるユニットとエネルギーの消費量
+const COSTS: [(UnitType, f64); 3] = [
+    (UnitType::Small, 1.0),
+    (UnitType::Medium, 3.0),
+    (UnitType::Large, 12.0),
+];
+
+// エネルギーモードの設定（オフなら従来どおり入力のたびに出撃する）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct EnergyConfig {
+    pub enabled: bool,
+    // 種類ごとの呼び出しホットキー（空なら画面のボタンだけ）
+    pub small_hotkey: String,
+    pub medium_hotkey: String,
+    pub large_hotkey: String,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            small_hotkey: "Ctrl+Alt+1".to_string(),
+            medium_hotkey: "Ctrl+Alt+2".to_string(),
+            large_hotkey: "Ctrl+Alt+3".to_string(),
+        }
+    }
+}
+
+impl EnergyConfig {
+    pub fn hotkeys(&self) -> Result<Vec<(UnitType, Hotkey)>, GameError> {
+        [
+            (UnitType::Small, &self.small_hotkey),
+            (UnitType::Medium, &self.medium_hotkey),
+            (UnitType::Large, &self.large_hotkey),
+        ]
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(unit_type, text)| {
+            Hotkey::parse(text)
+                .map(|hotkey| (unit_type, hotkey))
+                .ok_or_else(|| GameError::InvalidHotkey {
+                    hotkey: text.clone(),
+                })
+        })
+        .collect()
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct EnergyStatus {
+    pub enabled: bool,
+    pub energy: f64,
+    pub capacity: f64,
+    pub costs: Vec<(UnitType, f64)>,
+}
+
+// 入力で貯めたエネルギーで、出すユニットを選んで呼び出す
+// モードは AppConfig にあるので、ここには貯めた量だけを持つ
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct EnergyPool {
+    energy: f64,
+}
+
+impl EnergyPool {
+    pub fn charge(&mut self, types: u32, clicks: u32) {
+        let gained = types as f64 * PER_TYPE + clicks as f64 * PER_CLICK;
+        self.energy = (self.energy + gained).min(CAPACITY);
+    }
+
+    pub fn cost(unit_type: UnitType) -> Result<f64, GameError> {
+        COSTS
+            .iter()
+            .find(|&&(kind, _)| kind == unit_type)
+            .map(|&(_, cost)| cost)
+            .ok_or_else(|| GameError::NotEnergySummonable {
+                unit_type: format!("{:?}", unit_type),
+            })
+    }
+
+    pub fn spend(&mut self, unit_type: UnitType) -> Result<(), GameError> {
+        let cost = Self::cost(unit_type)?;
+        if self.energy < cost {
+            return Err(GameError::NotEnoughEnergy {
+                needed: cost,
+                have: self.energy,
+            });
+        }
+        self.energy -= cost;
+        Ok(())
+    }
+
+    pub fn repair(&mut self) {
+        if !self.energy.is_finite() {
+            self.energy = 0.0;
+        }
+        self.energy = self.energy.clamp(0.0, CAPACITY);
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.energy
+    }
+
+    pub fn status(&self, enabled: bool) -> EnergyStatus {
+        EnergyStatus {
+            enabled,
+            energy: self.energy,
+            capacity: CAPACITY,
+            costs: COSTS.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::i18n::{self, Language};
+use crate::multiplayer::MultiplayerError;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+use ts_rs::TS;
+
+// コマンドのエラー。フロントエンドは code で判別し、message をそのまま表示できる
+// （シリアライズは下の Serialize 実装で message を足す）
+#[derive(Debug, Clone, Error, Serialize, PartialEq, TS)]
+#[serde(remote = "Self", tag = "code", rename_all = "snake_case")]
+#[ts(rename = "GameErrorFields")]
+pub enum GameError {
+    #[error("Not enough coins (need {needed}, have {have})")]
+    NotEnoughCoins {
+        #[ts(type = "number")]
+        needed: u64,
+        #[ts(type = "number")]
+        have: u64,
+    },
+    #[error("Not enough prestige points (need {needed}, have {have})")]
+    NotEnoughPrestigePoints {
+        #[ts(type = "number")]
+        needed: u64,
+        #[ts(type = "number")]
+        have: u64,
+    },
+    #[error("Invalid upgrade type: {upgrade_type}")]
+    InvalidUpgrade { upgrade_type: String },
+    #[error("Unknown command: {name}")]
+    UnknownCommand { name: String },
+    #[error("Usage: {usage}")]
+    InvalidCommandArguments { usage: String },
+    #[error("Auto-buy entry {index} not found")]
+    AutoBuyEntryNotFound { index: usize },
+    #[error("No upgrades to refund")]
+    NothingToRespec,
+    #[error("Invalid prestige upgrade: {kind}")]
+    InvalidPrestigeUpgrade { kind: String },
+    // ステージ未クリアで次へ進めない
+    #[error("Stage is not cleared yet")]
+    StageLocked,
+    #[error("Reach stage {min_stage} before prestiging")]
+    PrestigeLocked { min_stage: u32 },
+    #[error("Chest {chest_id} not found")]
+    ChestNotFound { chest_id: u32 },
+    #[error("Forward barracks already built on this stage")]
+    BarracksAlreadyBuilt,
+    #[error("Invalid barracks position")]
+    InvalidBarracksPosition,
+    #[error("Position has not been captured yet")]
+    PositionNotCaptured,
+    #[error("Invalid rally point")]
+    InvalidRallyPoint,
+    #[error("Invalid hold position")]
+    InvalidStance,
+    // 範囲外の位置、見えない敵、長すぎる期限
+    #[error("Invalid unit order")]
+    InvalidOrder,
+    #[error("None of the selected units can follow this order")]
+    NoUnitsOrdered,
+    #[error("Focus block already running")]
+    FocusAlreadyRunning,
+    #[error("Invalid focus duration")]
+    InvalidFocusDuration,
+    #[error("Invalid save slot {slot}")]
+    InvalidSaveSlot { slot: u32 },
+    #[error("Save slot {slot} is empty")]
+    SaveSlotEmpty { slot: u32 },
+    #[error("Checkpoint not found: {id}")]
+    CheckpointNotFound { id: String },
+    #[error("Save is locked")]
+    SaveLocked,
+    #[error("Save is not passphrase protected")]
+    SaveNotPassphraseProtected,
+    #[error("Wrong passphrase or corrupted save")]
+    WrongPassphrase,
+    #[error("Save integrity check failed; progress can't be submitted")]
+    SaveTampered,
+    #[error("Profile names are 1-32 letters, digits, '-' or '_'")]
+    InvalidProfileName,
+    #[error("Invalid macro name")]
+    InvalidMacroName,
+    #[error("Macro not found: {name}")]
+    MacroNotFound { name: String },
+    #[error("Already recording a macro")]
+    MacroAlreadyRecording,
+    #[error("Not recording a macro")]
+    MacroNotRecording,
+    #[error("Macro has no actions")]
+    EmptyMacro,
+    #[error("Not recording a replay")]
+    ReplayNotRecording,
+    #[error("Custom rule factors must be between {min} and {max}")]
+    InvalidGameRules { min: f32, max: f32 },
+    // 戦闘中に難易度を変えるにはステージをやり直す
+    #[error("Changing difficulty mid-stage requires a stage reset")]
+    DifficultyNeedsReset,
+    // スマホからの入力の待ち受け
+    #[error("No local network address for remote play")]
+    NoLocalNetwork,
+    #[error("Port {port} is not available for remote play")]
+    RemotePlayPortUnavailable { port: u16 },
+    #[error("Widget window not available")]
+    WidgetUnavailable,
+    #[error("Invalid hotkey: {hotkey}")]
+    InvalidHotkey { hotkey: String },
+    // 空の ID や自分自身はフレンドにできない
+    #[error("Invalid friend player id")]
+    InvalidFriend,
+    #[error("At most {max} friends")]
+    TooManyFriends { max: usize },
+    // 進行状況の食い違いを解決するまで同期しない
+    #[error("Resolve the progress conflict before syncing")]
+    SyncConflictPending,
+    #[error("No progress conflict to resolve")]
+    NoSyncConflict,
+    #[error("Title {id} has not been earned")]
+    TitleNotEarned { id: String },
+    #[error("Unknown cosmetic: {id}")]
+    UnknownCosmetic { id: String },
+    #[error("Cosmetic {id} is not unlocked yet")]
+    CosmeticLocked { id: String },
+    // エクスポート文字列が読めない・新しい形式・改ざんされている
+    #[error("Save code is corrupt or incomplete")]
+    SaveCodeCorrupt,
+    #[error("Save code format {version} is newer than this version supports ({supported})")]
+    SaveCodeTooNew { version: u32, supported: u32 },
+    #[error("Save code checksum does not match; it may have been edited")]
+    SaveCodeChecksumMismatch,
+    #[error("Quest {quest_id} not found")]
+    QuestNotFound { quest_id: String },
+    #[error("Quest {quest_id} is not complete yet")]
+    QuestNotComplete { quest_id: String },
+    #[error("Quest {quest_id} reward already claimed")]
+    QuestAlreadyClaimed { quest_id: String },
+    #[error("Summon is on cooldown for {remaining_secs:.1}s")]
+    SummonOnCooldown { remaining_secs: f32 },
+    #[error("{item} is on cooldown for {remaining_secs:.1}s")]
+    ItemOnCooldown { item: String, remaining_secs: f32 },
+    #[error("No {item} left")]
+    NoItemsLeft { item: String },
+    #[error("Can't carry more than {max} of {item}")]
+    ItemStackFull { item: String, max: u32 },
+    // 転生ショップ
+    #[error("Shop stock has expired; refresh the shop")]
+    ShopStockExpired,
+    #[error("{item} is not in the shop right now")]
+    ShopOfferNotFound { item: String },
+    #[error("{item} is already owned")]
+    ShopItemOwned { item: String },
+    #[error("Not enough energy (need {needed}, have {have:.0})")]
+    NotEnoughEnergy { needed: f64, have: f64 },
+    #[error("Energy mode is off")]
+    EnergyModeOff,
+    #[error("{unit_type} units can't be summoned with energy")]
+    NotEnergySummonable { unit_type: String },
+    // タイピング練習ツールの履歴の取り込み
+    #[error("Typing history is not a supported MonkeyType or typing.io CSV export")]
+    TypingHistoryUnsupported,
+    #[error("Typing history file is larger than {max_bytes} bytes")]
+    TypingHistoryTooLarge {
+        #[ts(type = "number")]
+        max_bytes: u64,
+    },
+    #[error("Typing history has already been imported into this save")]
+    TypingHistoryAlreadyImported,
+    // ファイル・暗号化・設定の読み書きの失敗
+    #[error("{message}")]
+    Storage { message: String },
+    // retryable なら時間をおいて再試行できる
+    #[error("{error}")]
+    Multiplayer {
+        error: MultiplayerError,
+        retryable: bool,
+    },
+    #[error("{message}")]
+    Internal { message: String },
+}
+
+impl GameError {
+    pub fn storage(e: impl ToString) -> Self {
+        Self::Storage {
+            message: e.to_string(),
+        }
+    }
+
+    pub fn multiplayer(error: MultiplayerError) -> Self {
+        Self::Multiplayer {
+            retryable: error.is_retryable(),
+            error,
+        }
+    }
+
+    pub fn internal(e: impl ToString) -> Self {
+        Self::Internal {
+            message: e.to_string(),
+        }
+    }
+
+    // 表示用の文（訳がなければ英語の #[error]。ファイルやサーバーからのメッセージはそのまま）
+    pub fn message_in(&self, language: Language) -> String {
+        GameError::serialize(self, serde_json::value::Serializer)
+            .ok()
+            .and_then(|value| i18n::error_message(language, &value))
+            .unwrap_or_else(|| self.to_string())
+    }
+
+    pub fn message(&self) -> String {
+        self.message_in(i18n::language())
+    }
+}
+
+// { "code": ..., 各フィールド..., "message": 今の言語の表示用の文 }
+impl Serialize for GameError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = GameError::serialize(self, serde_json::value::Serializer)
+            .map_err(serde::ser::Error::custom)?;
+        let message =
+            i18n::error_message(i18n::language(), &value).unwrap_or_else(|| self.to_string());
+        if let Some(fields) = value.as_object_mut() {
+            fields.insert("message".to_string(), message.into());
+        }
+        value.serialize(serializer)
+    }
+}
+
+// 上の Serialize 実装で送る形。command-failed イベントの TypeScript の型を作るためだけに使う
+#[allow(dead_code)]
+#[derive(TS)]
+#[ts(rename = "GameError")]
+pub struct GameErrorPayload {
+    #[ts(flatten)]
+    error: GameError,
+    message: String,
+}
@@ -0,0 +1,54 @@
+// フロントエンドに送るイベントの一覧（型名 = "イベント名": ペイロードの Rust の型 [as TypeScript に書き出す型]）
+// src/events.rs が include! し、ペイロードの型から ../src/generated/events.ts を書き出す
+events! {
+    // ゲーム画面
+    GameUpdate = "game-update": crate::GameStateUpdate;
+    GameIdleUpdate = "game-idle-update": crate::idle::IdleUpdate;
+    GameHeartbeat = "game-heartbeat": crate::loop_control::LoopStatus;
+    IdleChanged = "idle-changed": crate::loop_control::LoopStatus;
+    LowDistractionChanged = "low-distraction-changed": crate::loop_control::LoopStatus;
+    OfflineProgress = "offline-progress": crate::offline::OfflineProgress;
+    LocaleChanged = "locale-changed": str;
+    PresentationMode = "presentation-mode": bool;
+    HotkeyAction = "hotkey-action": crate::hotkey_action::HotkeyActionFeedback;
+    InputBackendChanged = "input-backend-changed": crate::input_backend::InputBackendStatus;
+    CommandExecuted = "command-executed": crate::palette::CommandOutcome;
+    CommandFailed = "command-failed": crate::error::GameError as crate::error::GameErrorPayload;
+    SubsystemDown = "subsystem-down": crate::watchdog::Incident;
+    StorageError = "storage-error": Option<crate::save_writer::StorageFailure>;
+
+    // 戦闘
+    UnitSpawned = "unit-spawned": Vec<crate::game::Unit>;
+    UnitRemoved = "unit-removed": Vec<u32>;
+    UnitMoved = "unit-moved": Vec<crate::unit_events::UnitMove>;
+    AbilityCooldowns = "ability-cooldowns": Vec<crate::unit_events::AbilityCooldown>;
+    StageReport = "stage-report": crate::report::BattleReport;
+    Invasion = "invasion": crate::invasion::InvasionEvent;
+    ComboMilestone = "combo-milestone": crate::combo::ComboMilestone;
+    FinalBlow = "final-blow": crate::cinematic::FinalBlow;
+    StageClearSequence = "stage-clear-sequence": crate::cinematic::SequenceEvent;
+    PvpBattleResult = "pvp-battle-result": crate::pvp::BattleResult;
+
+    // ウィジェット
+    WidgetUpdate = "widget-update": crate::widget::WidgetFrame;
+    WidgetCompactUpdate = "widget-compact-update": crate::CompactWidgetUpdate;
+    WidgetStyle = "widget-style": crate::widget::WidgetStyle;
+    WidgetInteractive = "widget-interactive": bool;
+
+    // マルチプレイ
+    MpRemoteUpdate = "mp-remote-update": crate::multiplayer::PlayerProfile;
+    MpSyncConflict = "mp-sync-conflict": crate::conflict::SyncConflict;
+    MpSyncStatus = "mp-sync-status": crate::auto_sync::SyncStatus;
+    MpMaintenance = "mp-maintenance": Option<crate::multiplayer::MaintenanceNotice>;
+    FriendsUpdate = "friends-update": Vec<crate::friends::FriendProgress>;
+    RaidUpdate = "raid-update": crate::multiplayer::RaidBossStatus;
+    SeasonRollover = "season-rollover": crate::season::SeasonRollover;
+
+    // 読み上げ・通知
+    A11ySummary = "a11y-summary": crate::a11y::StateSummary;
+    AchievementUnlocked = "achievement-unlocked": crate::achievements::AchievementStatus;
+    QuestCompleted = "quest-completed": crate::quests::Quest;
+    FocusCompleted = "focus-completed": crate::focus::FocusBlock;
+    Notification = "notification": crate::notifications::Notification;
+    DailyRecap = "daily-recap": crate::recap::DailyRecap;
+}
@@ -0,0 +1,161 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, EventTarget};
+use ts_rs::TS;
+
+// フロントエンドに送るイベント。名前を文字列で書かずにこの型を通して送るので、
+// 名前とペイロードの型は event_catalog.rs の一覧から外れない
+pub trait Event {
+    const NAME: &'static str;
+    type Payload: Serialize + ?Sized;
+    // events.ts に書き出す型。ふつうはペイロードの型そのもので、フィールドを変えれば events.ts も変わる
+    type Typescript: TS + ?Sized + 'static;
+}
+
+// Serialize を手で書いて derive と形が変わるペイロードだけ、送る形を表す型を `as` で添える
+macro_rules! typescript {
+    ($payload:ty) => {
+        $payload
+    };
+    ($payload:ty, $typescript:ty) => {
+        $typescript
+    };
+}
+
+macro_rules! events {
+    ($($event:ident = $name:literal: $payload:ty $(as $typescript:ty)?;)*) => {
+        $(
+            // GameStateUpdate などクレートの外に出さないペイロードもあるので pub(crate)
+            pub(crate) struct $event;
+
+            impl Event for $event {
+                const NAME: &'static str = $name;
+                type Payload = $payload;
+                type Typescript = typescript!($payload $(, $typescript)?);
+            }
+        )*
+
+        // (型名, イベント名, ペイロードの TypeScript の型)
+        #[cfg(test)]
+        fn catalog() -> Vec<(&'static str, &'static str, String)> {
+            vec![$((stringify!($event), $name, <<$event as Event>::Typescript as TS>::name())),*]
+        }
+
+        #[cfg(test)]
+        fn visit_payloads(visitor: &mut impl ts_rs::TypeVisitor) {
+            $(visitor.visit::<<$event as Event>::Typescript>();)*
+        }
+    };
+}
+
+include!("event_catalog.rs");
+
+// 送れなかったとき（ウィンドウがまだないなど）は捨てる
+pub fn emit<E: Event>(app: &AppHandle, payload: &E::Payload) {
+    let _ = app.emit(E::NAME, payload);
+}
+
+pub fn emit_to<E: Event>(app: &AppHandle, target: impl Into<EventTarget>, payload: &E::Payload) {
+    let _ = app.emit_to(target, E::NAME, payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::TypeId;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use ts_rs::{Dependency, TypeVisitor};
+
+    const GENERATED: &str = "../src/generated/events.ts";
+
+    // ペイロードから辿れる名前付きの型の宣言を名前順に集める
+    #[derive(Default)]
+    struct Declarations(BTreeMap<String, (TypeId, String)>);
+
+    impl TypeVisitor for Declarations {
+        fn visit<T: TS + 'static + ?Sized>(&mut self) {
+            if Dependency::from_ty::<T>().is_some() {
+                let name = T::ident();
+                if let Some((seen, _)) = self.0.get(&name) {
+                    // 別のモジュールの同じ名前の型は #[ts(rename = "...")] で分ける
+                    assert!(
+                        *seen == TypeId::of::<T>(),
+                        "two payload types are exported as {}",
+                        name
+                    );
+                    return;
+                }
+                self.0.insert(name, (TypeId::of::<T>(), T::decl()));
+            }
+            T::visit_dependencies(self);
+        }
+    }
+
+    fn typescript() -> String {
+        let mut declarations = Declarations::default();
+        visit_payloads(&mut declarations);
+
+        let mut out = String::from(
+            "// src-tauri/src/event_catalog.rs のペイロードの型から生成する（手で編集せず UPDATE_EVENT_TYPES=1 cargo test events で書き直す）\n\n",
+        );
+        for (_, declaration) in declarations.0.values() {
+            out.push_str("export ");
+            out.push_str(declaration);
+            out.push_str("\n\n");
+        }
+        out.push_str("export const EVENTS = {\n");
+        for (event, name, _) in catalog() {
+            out.push_str(&format!("  {}: \"{}\",\n", event, name));
+        }
+        out.push_str("} as const;\n\n");
+        out.push_str("export type EventName = (typeof EVENTS)[keyof typeof EVENTS];\n\n");
+        out.push_str("export interface EventPayloads {\n");
+        for (_, name, payload) in catalog() {
+            out.push_str(&format!("  \"{}\": {};\n", name, payload));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    #[test]
+    fn event_names_are_unique() {
+        let mut names: Vec<&str> = catalog().iter().map(|(_, name, _)| *name).collect();
+        names.sort_unstable();
+        let duplicate = names.windows(2).find(|pair| pair[0] == pair[1]);
+        assert!(duplicate.is_none(), "duplicate event name: {:?}", duplicate);
+    }
+
+    // i64・u64 も serde_json は JSON の数値で送るので、フィールドに #[ts(type = "number")] を付ける
+    #[test]
+    fn payloads_have_no_bigint_fields() {
+        let contents = typescript();
+        let bigints: Vec<&str> = contents
+            .lines()
+            .filter(|line| line.contains("bigint"))
+            .collect();
+        assert!(
+            bigints.is_empty(),
+            "add #[ts(type = \"number\")] to the 64-bit fields of: {:#?}",
+            bigints
+        );
+    }
+
+    // 取り込んだ events.ts がペイロードの型と食い違っていれば失敗する
+    // （UPDATE_EVENT_TYPES=1 cargo test events で書き直す。変わらなければ書かず Vite の再読み込みを起こさない）
+    #[test]
+    fn typescript_definitions_are_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GENERATED);
+        let contents = typescript();
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        if std::env::var_os("UPDATE_EVENT_TYPES").is_some() {
+            if current != contents {
+                std::fs::write(&path, contents).expect("failed to write events.ts");
+            }
+            return;
+        }
+        assert!(
+            current == contents,
+            "src/generated/events.ts is out of date; run `UPDATE_EVENT_TYPES=1 cargo test events` in src-tauri"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::error::GameError;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 集中ブロック中のタイプ入力ボーナス倍率
+const FOCUS_TYPE_MULTIPLIER: f32 = 1.5;
+// 禁止アプリでのクリック1回ごとの倍率ペナルティ
+const DISTRACTION_PENALTY: f32 = 0.05;
+const MAX_FOCUS_HISTORY: usize = 50;
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct FocusBlock {
+    #[ts(type = "number")]
+    pub started_at: i64,
+    #[ts(type = "number")]
+    pub duration_secs: i64,
+    #[ts(type = "number | null")]
+    pub ended_at: Option<i64>,
+    pub completed: bool,
+    pub keystrokes: u32,
+    pub bonus_units: u32,
+    pub distractions: u32,
+}
+
+impl FocusBlock {
+    pub fn multiplier(&self) -> f32 {
+        (FOCUS_TYPE_MULTIPLIER - self.distractions as f32 * DISTRACTION_PENALTY).max(1.0)
+    }
+
+    pub fn remaining_secs(&self, now: i64) -> i64 {
+        (self.started_at + self.duration_secs - now).max(0)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct FocusTimer {
+    pub active: Option<FocusBlock>,
+    pub history: Vec<FocusBlock>,
+    #[serde(skip)]
+    bonus_carry: f32,
+}
+
+impl FocusTimer {
+    pub fn start(&mut self, now: i64, duration_secs: i64) -> Result<(), GameError> {
+        if self.active.is_some() {
+            return Err(GameError::FocusAlreadyRunning);
+        }
+        if duration_secs <= 0 {
+            return Err(GameError::InvalidFocusDuration);
+        }
+        self.active = Some(FocusBlock {
+            started_at: now,
+            duration_secs,
+            ended_at: None,
+            completed: false,
+            keystrokes: 0,
+            bonus_units: 0,
+            distractions: 0,
+        });
+        self.bonus_carry = 0.0;
+        Ok(())
+    }
+
+    pub fn stop(&mut self, now: i64) {
+        self.finish(now, false);
+    }
+
+    fn finish(&mut self, now: i64, completed: bool) {
+        if let Some(mut block) = self.active.take() {
+            block.ended_at = Some(now);
+            block.completed = completed;
+            self.history.push(block);
+            if self.history.len() > MAX_FOCUS_HISTORY {
+                let overflow = self.history.len() - MAX_FOCUS_HISTORY;
+                self.history.drain(0..overflow);
+            }
+        }
+    }
+
+    // 集中ブロック中の入力を補正し、実際に生成するユニット数を返す。ブロックが完了したら true
+    pub fn apply(
+        &mut self,
+        now: i64,
+        clicks: u32,
+        types: u32,
+        distracted: bool,
+    ) -> (u32, u32, bool) {
+        let Some(block) = self.active.as_mut() else {
+            return (clicks, types, false);
+        };
+
+        let mut clicks = clicks;
+        if distracted && clicks > 0 {
+            // 禁止アプリでのクリックはユニットにならず、倍率も下がる
+            block.distractions += clicks;
+            clicks = 0;
+        }
+
+        block.keystrokes += types;
+        self.bonus_carry += types as f32 * (block.multiplier() - 1.0);
+        let bonus = self.bonus_carry.floor();
+        self.bonus_carry -= bonus;
+        block.bonus_units += bonus as u32;
+        let types = types + bonus as u32;
+
+        let finished = block.remaining_secs(now) == 0;
+        if finished {
+            let end = block.started_at + block.duration_secs;
+            self.finish(end, true);
+        }
+        (clicks, types, finished)
+    }
+}
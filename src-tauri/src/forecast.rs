@@ -0,0 +1,175 @@
+use crate::game::{GameState, UnitType, UPGRADE_OPTIONS};
+use serde::Serialize;
+
+// 予測シミュレーションの固定タイムステップ（30 FPS 相当）
+const SIM_STEP: f32 = 1.0 / 30.0;
+pub const DEFAULT_TRIALS: u32 = 8;
+pub const MAX_FORECAST_SECONDS: f32 = 300.0;
+const RECOMMEND_SECONDS: f32 = 60.0;
+const RECOMMEND_TRIALS: u32 = 6;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ForecastResult {
+    pub win_probability: f32,
+    pub loss_probability: f32,
+    // 勝利した試行でのクリアまでの平均秒数
+    pub expected_time_to_clear: Option<f32>,
+    pub coins_per_minute: f32,
+    pub simulated_seconds: f32,
+    pub trials: u32,
+}
+
+impl ForecastResult {
+    // 推奨計算用の総合評価値
+    fn value(&self) -> f32 {
+        let speed_bonus = self
+            .expected_time_to_clear
+            .map_or(0.0, |time| self.simulated_seconds - time);
+        (self.win_probability - self.loss_probability) * 100.0
+            + speed_bonus * self.win_probability
+            + self.coins_per_minute
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct UpgradeRecommendation {
+    pub upgrade_type: String,
+    pub unit_type: String,
+    pub cost: u64,
+    pub affordable: bool,
+    pub win_probability_delta: f32,
+    pub coins_per_minute_delta: f32,
+    // 1000コインあたりの評価値の伸び
+    pub score_per_1k_coins: f32,
+}
+
+enum TrialOutcome {
+    Win(f32),
+    Loss,
+    Undecided,
+}
+
+fn run_trial(
+    snapshot: &GameState,
+    seconds: f32,
+    reinforcements_per_sec: f32,
+    seed: u64,
+) -> (TrialOutcome, u64) {
+    let mut sim = snapshot.snapshot();
+    sim.seed_rng(seed);
+    // 稼ぎ中なら次のステージを予測する
+    if sim.farming {
+        let _ = sim.advance_stage();
+    }
+    sim.manual_advance = false;
+    let start_stage = sim.stage;
+    let start_coins = sim.coins;
+    let mut elapsed = 0.0;
+    let mut reinforcement_carry = 0.0;
+    let mut outcome = TrialOutcome::Undecided;
+
+    while elapsed < seconds {
+        // 直近の入力ペースで味方が補充されると仮定
+        reinforcement_carry += reinforcements_per_sec * SIM_STEP;
+        while reinforcement_carry >= 1.0 {
+            sim.spawn_unit(UnitType::Small);
+            reinforcement_carry -= 1.0;
+        }
+
+        sim.update(SIM_STEP);
+        elapsed += SIM_STEP;
+        if sim.stage > start_stage {
+            outcome = TrialOutcome::Win(elapsed);
+            break;
+        }
+        if sim.defeats > 0 {
+            outcome = TrialOutcome::Loss;
+            break;
+        }
+    }
+    (outcome, sim.coins.saturating_sub(start_coins))
+}
+
+// 現在の戦場から、毎秒 reinforcements_per_sec 体の小ユニットが補充されると仮定して勝敗を予測する
+pub fn forecast(
+    snapshot: &GameState,
+    seconds: f32,
+    trials: u32,
+    reinforcements_per_sec: f32,
+) -> ForecastResult {
+    let seconds = seconds.clamp(1.0, MAX_FORECAST_SECONDS);
+    let trials = trials.max(1);
+    let mut wins = 0;
+    let mut losses = 0;
+    let mut clear_time_total = 0.0;
+    let mut coins_total = 0u64;
+    let mut time_total = 0.0;
+
+    // 試行ごとにシードを変える（同じ戦場からは毎回同じ予測になり、アップグレードの比較もぶれない）
+    for trial in 0..trials {
+        let (outcome, coins) = run_trial(snapshot, seconds, reinforcements_per_sec, trial as u64);
+        coins_total += coins;
+        match outcome {
+            TrialOutcome::Win(time) => {
+                wins += 1;
+                clear_time_total += time;
+                time_total += time;
+            }
+            TrialOutcome::Loss => {
+                losses += 1;
+                time_total += seconds;
+            }
+            TrialOutcome::Undecided => time_total += seconds,
+        }
+    }
+
+    ForecastResult {
+        win_probability: wins as f32 / trials as f32,
+        loss_probability: losses as f32 / trials as f32,
+        expected_time_to_clear: (wins > 0).then(|| clear_time_total / wins as f32),
+        coins_per_minute: coins_total as f32 / (time_total / 60.0).max(f32::EPSILON),
+        simulated_seconds: seconds,
+        trials,
+    }
+}
+
+// 各アップグレードを1段階適用した場合の予測と比較し、コストあたりの効果で並べる
+pub fn recommend_upgrades(
+    snapshot: &GameState,
+    reinforcements_per_sec: f32,
+) -> Vec<UpgradeRecommendation> {
+    let baseline = forecast(
+        snapshot,
+        RECOMMEND_SECONDS,
+        RECOMMEND_TRIALS,
+        reinforcements_per_sec,
+    );
+
+    let mut recommendations: Vec<UpgradeRecommendation> = UPGRADE_OPTIONS
+        .iter()
+        .filter_map(|&(upgrade_type, unit_type)| {
+            let cost = snapshot.upgrades.get_cost(upgrade_type, unit_type);
+            let mut upgraded = snapshot.snapshot();
+            upgraded.preview_upgrade(upgrade_type, unit_type).ok()?;
+            let result = forecast(
+                &upgraded,
+                RECOMMEND_SECONDS,
+                RECOMMEND_TRIALS,
+                reinforcements_per_sec,
+            );
+            Some(UpgradeRecommendation {
+                upgrade_type: upgrade_type.to_string(),
+                unit_type: unit_type.to_string(),
+                cost,
+                affordable: snapshot.coins >= cost,
+                win_probability_delta: result.win_probability - baseline.win_probability,
+                coins_per_minute_delta: result.coins_per_minute - baseline.coins_per_minute,
+                score_per_1k_coins: (result.value() - baseline.value()) / cost.max(1) as f32
+                    * 1000.0,
+            })
+        })
+        .collect();
+
+    recommendations.sort_by(|a, b| b.score_per_1k_coins.total_cmp(&a.score_per_1k_coins));
+    recommendations
+}
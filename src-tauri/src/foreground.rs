@@ -0,0 +1,215 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// 前面アプリの判定はコストが高いため、入力ごとではなく定期的に取得する
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+// 設定画面で候補に出す最近使ったアプリの数
+const MAX_RECENT_APPS: usize = 20;
+
+// プレゼン・画面共有中とみなすウィンドウタイトル
+const PRESENTATION_TITLE_KEYWORDS: &[&str] = &[
+    "slide show",
+    "slideshow",
+    "スライド ショー",
+    "presenter view",
+    "発表者ツール",
+    "you are screen sharing",
+    "is sharing your screen",
+    "画面を共有しています",
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct ForegroundWindow {
+    pub app_name: String,
+    pub title: String,
+    pub process_id: u64,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// 設定画面で許可・除外リストを作るための情報
+#[derive(Clone, Debug, Serialize)]
+pub struct ForegroundAppStatus {
+    pub app_name: Option<String>,
+    // いまの前面アプリへの入力を数えているか
+    pub counting_input: bool,
+    pub recent_apps: Vec<String>,
+}
+
+// モニターの矩形 (x, y, width, height)
+pub type MonitorRect = (f64, f64, f64, f64);
+
+pub struct ForegroundWatcher {
+    current: Mutex<Option<ForegroundWindow>>,
+    focus_blacklist: Mutex<Vec<String>>,
+    // 入力を数えるアプリ（空なら全アプリ）と数えないアプリ
+    input_allowlist: Mutex<Vec<String>>,
+    input_denylist: Mutex<Vec<String>>,
+    // 新しい順
+    recent_apps: Mutex<VecDeque<String>>,
+    auto_pause_presentation: AtomicBool,
+    presentation_override: AtomicBool,
+}
+
+impl ForegroundWatcher {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+            focus_blacklist: Mutex::new(Vec::new()),
+            input_allowlist: Mutex::new(Vec::new()),
+            input_denylist: Mutex::new(Vec::new()),
+            recent_apps: Mutex::new(VecDeque::new()),
+            auto_pause_presentation: AtomicBool::new(true),
+            presentation_override: AtomicBool::new(false),
+        }
+    }
+
+    pub fn current_app(&self) -> Option<String> {
+        self.current
+            .lock()
+            .as_ref()
+            .map(|window| window.app_name.clone())
+    }
+
+    pub fn set_focus_blacklist(&self, apps: Vec<String>) {
+        *self.focus_blacklist.lock() = normalize_apps(apps);
+    }
+
+    // 前面アプリが集中モードの禁止リストに含まれるか
+    pub fn is_distracting(&self) -> bool {
+        let Some(app) = self.current_app() else {
+            return false;
+        };
+        matches_any(&app, &self.focus_blacklist.lock())
+    }
+
+    pub fn set_input_filter(&self, allowlist: Vec<String>, denylist: Vec<String>) {
+        *self.input_allowlist.lock() = normalize_apps(allowlist);
+        *self.input_denylist.lock() = normalize_apps(denylist);
+    }
+
+    // 前面アプリへの入力をユニット生成に数えるか。
+    // 前面アプリを取得できない環境では入力がすべて止まらないよう数える
+    pub fn accepts_input(&self) -> bool {
+        let Some(app) = self.current_app() else {
+            return true;
+        };
+        if matches_any(&app, &self.input_denylist.lock()) {
+            return false;
+        }
+        let allowlist = self.input_allowlist.lock();
+        allowlist.is_empty() || matches_any(&app, &allowlist)
+    }
+
+    pub fn app_status(&self) -> ForegroundAppStatus {
+        ForegroundAppStatus {
+            app_name: self.current_app(),
+            counting_input: self.accepts_input(),
+            recent_apps: self.recent_apps.lock().iter().cloned().collect(),
+        }
+    }
+
+    fn remember_app(&self, app: &str) {
+        let mut recent = self.recent_apps.lock();
+        if recent.front().is_some_and(|latest| latest == app) {
+            return;
+        }
+        recent.retain(|seen| seen != app);
+        recent.push_front(app.to_string());
+        recent.truncate(MAX_RECENT_APPS);
+    }
+
+    pub fn set_auto_pause_presentation(&self, value: bool) {
+        self.auto_pause_presentation.store(value, Ordering::Relaxed);
+    }
+
+    // 手動で自動一時停止を無効化する
+    pub fn set_presentation_override(&self, value: bool) {
+        self.presentation_override.store(value, Ordering::Relaxed);
+    }
+
+    pub fn presentation_override(&self) -> bool {
+        self.presentation_override.load(Ordering::Relaxed)
+    }
+
+    // プレゼン・画面共有中かの推定（全画面表示かタイトルで判定）
+    pub fn is_presenting(&self, monitors: &[MonitorRect]) -> bool {
+        if !self.auto_pause_presentation.load(Ordering::Relaxed) || self.presentation_override() {
+            return false;
+        }
+        let current = self.current.lock();
+        let Some(window) = current.as_ref() else {
+            return false;
+        };
+
+        let title = window.title.to_lowercase();
+        if PRESENTATION_TITLE_KEYWORDS
+            .iter()
+            .any(|keyword| title.contains(keyword))
+        {
+            return true;
+        }
+
+        // 自分自身のウィンドウは除外
+        if window.process_id == std::process::id() as u64 {
+            return false;
+        }
+        monitors.iter().any(|&(x, y, width, height)| {
+            width > 0.0
+                && height > 0.0
+                && window.x <= x + 1.0
+                && window.y <= y + 1.0
+                && window.x + window.width >= x + width - 1.0
+                && window.y + window.height >= y + height - 1.0
+        })
+    }
+
+    fn refresh(&self) {
+        let window = active_win_pos_rs::get_active_window()
+            .ok()
+            .filter(|window| !window.app_name.is_empty())
+            .map(|window| ForegroundWindow {
+                app_name: window.app_name,
+                title: window.title,
+                process_id: window.process_id,
+                x: window.position.x,
+                y: window.position.y,
+                width: window.position.width,
+                height: window.position.height,
+            });
+        // 自分自身は一覧に出さない
+        if let Some(window) = window
+            .as_ref()
+            .filter(|window| window.process_id != std::process::id() as u64)
+        {
+            self.remember_app(&window.app_name);
+        }
+        *self.current.lock() = window;
+    }
+}
+
+// 大文字小文字を区別せず、部分一致で比べる
+fn normalize_apps(apps: Vec<String>) -> Vec<String> {
+    apps.into_iter()
+        .map(|app| app.trim().to_lowercase())
+        .filter(|app| !app.is_empty())
+        .collect()
+}
+
+fn matches_any(app: &str, entries: &[String]) -> bool {
+    let app = app.to_lowercase();
+    entries.iter().any(|entry| app.contains(entry.as_str()))
+}
+
+pub fn start_foreground_watcher(watcher: Arc<ForegroundWatcher>) {
+    loop {
+        watcher.refresh();
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
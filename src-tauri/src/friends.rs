@@ -0,0 +1,110 @@
+use crate::config::AppConfig;
+use crate::error::GameError;
+use crate::game::GameState;
+use crate::multiplayer::{MultiplayerApi, MultiplayerError, PlayerSnapshot};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use ts_rs::TS;
+
+// サーバーの一括取得の上限に合わせる
+pub const MAX_FRIENDS: usize = 100;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// フレンドの進行状況と自分との差（正ならフレンドが先行している）
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct FriendProgress {
+    #[serde(flatten)]
+    pub snapshot: PlayerSnapshot,
+    pub stage_gap: i64,
+    pub coin_gap: i64,
+    // サーバーの時計で測った最終更新からの経過秒数
+    pub updated_secs_ago: i64,
+}
+
+// 追加したら true（登録済みなら false）
+pub fn add(friends: &mut Vec<String>, player_id: &str, own_id: &str) -> Result<bool, GameError> {
+    let player_id = player_id.trim();
+    if player_id.is_empty() || player_id == own_id {
+        return Err(GameError::InvalidFriend);
+    }
+    if friends.iter().any(|id| id == player_id) {
+        return Ok(false);
+    }
+    if friends.len() >= MAX_FRIENDS {
+        return Err(GameError::TooManyFriends { max: MAX_FRIENDS });
+    }
+    friends.push(player_id.to_string());
+    Ok(true)
+}
+
+// 削除したら true
+pub fn remove(friends: &mut Vec<String>, player_id: &str) -> bool {
+    let before = friends.len();
+    friends.retain(|id| id != player_id.trim());
+    friends.len() != before
+}
+
+// ステージの高い順に並べる（server_now はサーバーの時計での現在時刻）
+pub fn compare(
+    snapshots: Vec<PlayerSnapshot>,
+    stage: u32,
+    coins: u64,
+    server_now: i64,
+) -> Vec<FriendProgress> {
+    let mut progress: Vec<FriendProgress> = snapshots
+        .into_iter()
+        .map(|snapshot| FriendProgress {
+            stage_gap: snapshot.stage as i64 - stage as i64,
+            coin_gap: (snapshot.coins as i128 - coins as i128)
+                .clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+            updated_secs_ago: (server_now - snapshot.last_update).max(0),
+            snapshot,
+        })
+        .collect();
+    progress.sort_by(|a, b| {
+        b.snapshot
+            .stage
+            .cmp(&a.snapshot.stage)
+            .then_with(|| a.snapshot.player_name.cmp(&b.snapshot.player_name))
+    });
+    progress
+}
+
+pub async fn fetch(
+    mp_client: &dyn MultiplayerApi,
+    game_state: &Mutex<GameState>,
+    friends: &[String],
+) -> Result<Vec<FriendProgress>, MultiplayerError> {
+    if friends.is_empty() {
+        return Ok(Vec::new());
+    }
+    let snapshots = mp_client.get_players_batch(friends).await?;
+    let (stage, coins) = {
+        let game = game_state.lock();
+        (game.stage, game.coins)
+    };
+    Ok(compare(snapshots, stage, coins, mp_client.server_now()))
+}
+
+// フレンドがいてサーバーが設定されている間、定期的に取り直して通知する
+pub async fn run_refresh<F>(
+    mp_client: Arc<dyn MultiplayerApi>,
+    game_state: Arc<Mutex<GameState>>,
+    on_update: F,
+) where
+    F: Fn(Vec<FriendProgress>) + Send + 'static,
+{
+    loop {
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+        let friends = AppConfig::load().friends;
+        if friends.is_empty() || mp_client.get_server_url().is_empty() {
+            continue;
+        }
+        match fetch(mp_client.as_ref(), &game_state, &friends).await {
+            Ok(progress) => on_update(progress),
+            Err(err) => eprintln!("Failed to refresh friends: {}", err),
+        }
+    }
+}
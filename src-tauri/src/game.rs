@@ -1,16 +1,122 @@
-use directories::ProjectDirs;
-use rand::Rng;
+use crate::ability::{self, UnitAbility};
+use crate::achievements::Achievements;
+use crate::barracks::{self, ForwardBarracks};
+use crate::boss::{self, BossState, BossStatus};
+use crate::budget::{MemoryUsage, OverflowPolicy, UnitCaps};
+use crate::checkpoint::{self, CheckpointReason};
+use crate::chest::{self, ChestInventory, ChestReward};
+use crate::cinematic::{FinalBlow, StageClearSequence};
+use crate::combo::Combo;
+use crate::conflict::{self, Divergence};
+use crate::coop::{Coop, CoopPlayer, CoopStatus, InputSource};
+use crate::cosmetics::{self, CosmeticKind, CosmeticStatus, Cosmetics, Unlock};
+use crate::encryption;
+use crate::enemy_kind::{self, EnemyKind};
+use crate::energy::EnergyPool;
+use crate::error::GameError;
+use crate::focus::FocusTimer;
+use crate::i18n;
+use crate::integrity::{self, SaveIntegrity};
+use crate::invasion::{self, InvasionState, InvasionStatus, InvasionStep};
+use crate::item::{self, ItemInventory, ItemKind, ItemStatus};
+use crate::journal::{self, JournalEntry, JournalEvent};
+use crate::morale::Morale;
+use crate::notifications::{Notification, Notifications};
+use crate::offline::{self, OfflineProgress};
+use crate::orders::{self, Order, UnitOrder, UnitSelector};
+use crate::personality::{self, EnemyPersonality};
+use crate::prestige::{PrestigeState, PrestigeStatus, PrestigeUpgrades};
+use crate::profile;
+use crate::pvp::{ArmySnapshot, BattleEvent, BattleFrame, BattleResult, BattleWinner, FrameUnit};
+use crate::quests::{Quest, QuestLog, QuestMetric};
+use crate::rally::{self, RallyPoint};
+use crate::replay::{Replay, ReplayInput, ReplayRecorder};
+use crate::report::BattleReports;
+use crate::respec::{RespecQuote, UpgradeLedger};
+use crate::rules::GameRules;
+use crate::save_check::SaveCheckReport;
+use crate::save_code;
+use crate::save_writer::{self, SaveJob};
+use crate::season::{SeasonResult, SeasonRollover, SeasonTracker};
+use crate::session::SessionTracker;
+use crate::shop::{ShopCache, ShopOffer, ShopStatus, ShopStock};
+use crate::spatial::{self, LaneIndex};
+use crate::stage::{self, StagePreview};
+use crate::stance::{self, Stance};
+use crate::stats::Activity;
+use crate::summon::{SummonStatus, Summons};
+use crate::titles::Titles;
+use crate::typing_import::{self, TypingHistory, TypingImport};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use ts_rs::TS;
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+// セーブスロット数（1番は従来の game_state.json を使う）
+pub const SAVE_SLOT_COUNT: u32 = 5;
+
+#[derive(Serialize, Deserialize)]
+struct SlotIndex {
+    active: u32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct SaveSlotInfo {
+    pub slot: u32,
+    pub active: bool,
+    pub stage: Option<u32>,
+    pub coins: Option<u64>,
+    pub saved_at: Option<i64>,
+}
+
+// スロット一覧の表示に必要な項目だけを読む
+#[derive(Deserialize)]
+struct SlotSummary {
+    stage: u32,
+    coins: u64,
+}
+
+pub fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// step() 1 回で進める秒数（リプレイは何回目の step かで入力を記録する）
+pub const STEP_SECS: f32 = 1.0 / 60.0;
+// 攻撃間隔（秒）。1回のダメージは attack × 間隔
+const ATTACK_INTERVAL: f32 = 0.5;
+// 強打による吹き飛ばし
+const HEAVY_HIT_RATIO: f32 = 0.25;
+const HEAVY_HIT_KNOCKBACK: f32 = 60.0;
+const HEAVY_HIT_KNOCKBACK_SECS: f32 = 0.3;
+const HEAVY_HIT_INVULNERABLE_SECS: f32 = 0.5;
+// 戦場の長さと、前線の計算で交戦中とみなす距離
+pub const FIELD_LENGTH: f32 = 1000.0;
+const ENGAGE_RANGE: f32 = 50.0;
+// 自陣の回復エリア（範囲、最大HPに対する毎秒の回復割合）
+const HEAL_BASE_RADIUS: f32 = 80.0;
+const HEAL_BASE_RATE: f32 = 0.02;
+// 大型ユニット・前線基地が隠密状態の敵を探知する距離
+const DETECTION_RANGE: f32 = 120.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
 pub enum UnitType {
     Small,
     Medium,
     Large,
+    // コインで呼び出す英雄（強化の対象外）
+    Hero,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, TS)]
 pub struct Unit {
     pub id: u32,
     pub unit_type: UnitType,
@@ -27,9 +133,185 @@ pub struct Unit {
     pub knockback_time: f32,
     #[serde(default)]
     pub knockback_total: f32,
+    // 次の攻撃までの残り時間
+    #[serde(default)]
+    pub attack_cooldown: f32,
+    // 強打を受けた直後の無敵時間
+    #[serde(default)]
+    pub invulnerable_time: f32,
+    // 隠密状態の敵（攻撃するか探知されるまで狙われない）
+    #[serde(default)]
+    pub stealthed: bool,
+    // 飛行ユニットは対空攻撃を持つユニットにしか狙われない
+    #[serde(default)]
+    pub is_flying: bool,
+    #[serde(default)]
+    pub ability: UnitAbility,
+    // 能力を再び発動できるまでの残り時間
+    #[serde(default)]
+    pub ability_cooldown: f32,
+    // 集結地点で待機中の味方
+    #[serde(default)]
+    pub rallying: bool,
+    // 所属するレーン（同じレーンの相手とだけ戦う）
+    #[serde(default)]
+    pub lane: u8,
+    // 協力プレイでどちらの入力から出たか（敵は常に A）
+    #[serde(default)]
+    pub owner: CoopPlayer,
+    // 敵の性質（味方は常に Standard）
+    #[serde(default)]
+    pub kind: EnemyKind,
+    // 手動の命令（期限までは AI の判断より優先する）
+    #[serde(default)]
+    pub order: Option<UnitOrder>,
+}
+
+impl Unit {
+    // 攻撃を受ける。このヒットで撃破された場合は true
+    fn take_hit(&mut self, damage: f32, push_direction: f32) -> bool {
+        if self.hp <= 0.0 || self.invulnerable_time > 0.0 {
+            return false;
+        }
+        self.hp -= damage;
+        if self.hp <= 0.0 {
+            return true;
+        }
+        // 最大HPの一定割合以上を一撃で失うと吹き飛ぶ
+        if damage >= self.max_hp * HEAVY_HIT_RATIO {
+            self.knockback_velocity =
+                push_direction * HEAVY_HIT_KNOCKBACK / HEAVY_HIT_KNOCKBACK_SECS;
+            self.knockback_time = HEAVY_HIT_KNOCKBACK_SECS;
+            self.knockback_total = HEAVY_HIT_KNOCKBACK_SECS;
+            self.invulnerable_time = HEAVY_HIT_INVULNERABLE_SECS;
+        }
+        false
+    }
+
+    fn tick_timers(&mut self, delta: f32) {
+        self.attack_cooldown = (self.attack_cooldown - delta).max(0.0);
+        self.invulnerable_time = (self.invulnerable_time - delta).max(0.0);
+        self.ability_cooldown = (self.ability_cooldown - delta).max(0.0);
+    }
+
+    // 対空攻撃を持つのは遠隔ユニットと大型ユニット
+    pub fn hits_air(&self) -> bool {
+        self.ability.hits_air() || matches!(self.unit_type, UnitType::Large | UnitType::Hero)
+    }
+
+    // 能力の準備が整った割合（0.0〜1.0）。発動型の能力を持たないなら None
+    pub fn ability_readiness(&self) -> Option<f32> {
+        let cooldown = self.ability.cooldown()?;
+        Some((1.0 - self.ability_cooldown / cooldown).clamp(0.0, 1.0))
+    }
+
+    // 壊れた数値（NaN・無限大・範囲外）を直す。HP・攻撃力・速度が不正なら直せないので false
+    fn repair(&mut self) -> bool {
+        let stats_valid = self.max_hp.is_finite()
+            && self.max_hp > 0.0
+            && self.hp.is_finite()
+            && self.attack.is_finite()
+            && self.attack >= 0.0
+            && self.speed.is_finite()
+            && self.speed >= 0.0;
+        if !stats_valid {
+            return false;
+        }
+        self.hp = self.hp.min(self.max_hp);
+        let home = if self.is_player { 0.0 } else { FIELD_LENGTH };
+        self.position = finite_or(self.position, home).clamp(0.0, FIELD_LENGTH);
+        self.knockback_velocity = finite_or(self.knockback_velocity, 0.0);
+        for timer in [
+            &mut self.knockback_time,
+            &mut self.knockback_total,
+            &mut self.attack_cooldown,
+            &mut self.invulnerable_time,
+            &mut self.ability_cooldown,
+        ] {
+            *timer = finite_or(*timer, 0.0).max(0.0);
+        }
+        true
+    }
+
+    fn is_finite(&self) -> bool {
+        [
+            self.position,
+            self.hp,
+            self.max_hp,
+            self.attack,
+            self.speed,
+            self.knockback_velocity,
+            self.knockback_time,
+            self.knockback_total,
+            self.attack_cooldown,
+            self.invulnerable_time,
+            self.ability_cooldown,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+    }
+
+    // 上限を超えて出撃したユニットの体力・攻撃力を引き取る
+    fn absorb(&mut self, other: &Unit) {
+        self.hp += other.hp;
+        self.max_hp += other.max_hp;
+        self.attack += other.attack;
+    }
+}
+
+fn finite_or(value: f32, default: f32) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        default
+    }
+}
+
+// 基地の最大HPは正の有限値に限る
+fn valid_max_hp(value: f32, default: f32) -> f32 {
+    if value.is_finite() && value > 0.0 {
+        value
+    } else {
+        default
+    }
+}
+
+// 上限未満なら追加し、上限なら出現位置に最も近いユニット（同種を優先）に合流させる
+// 兵種ごとの基本の体力・攻撃力・速度
+fn unit_base_stats(unit_type: UnitType) -> (f32, f32, f32) {
+    match unit_type {
+        UnitType::Small => (10.0, 5.0, 100.0),
+        UnitType::Medium => (30.0, 15.0, 80.0),
+        UnitType::Large => (100.0, 50.0, 60.0),
+        UnitType::Hero => (400.0, 120.0, 90.0),
+    }
+}
+
+fn push_capped(units: &mut Vec<Unit>, unit: Unit, cap: usize) {
+    if units.len() < cap {
+        units.push(unit);
+        return;
+    }
+    let nearest = |same_kind: bool| {
+        units
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| {
+                !same_kind || (u.unit_type == unit.unit_type && u.ability == unit.ability)
+            })
+            .min_by(|(_, a), (_, b)| {
+                (a.position - unit.position)
+                    .abs()
+                    .total_cmp(&(b.position - unit.position).abs())
+            })
+            .map(|(index, _)| index)
+    };
+    if let Some(index) = nearest(true).or_else(|| nearest(false)) {
+        units[index].absorb(&unit);
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
 pub struct Upgrades {
     // 攻撃力アップグレード（％）
     pub small_attack: u32,
@@ -47,6 +329,11 @@ pub struct Upgrades {
     pub coin_rate: u32,
     // 基地体力
     pub base_hp: u32,
+    // 基地周辺の回復エリアの範囲・回復量（％）
+    #[serde(default)]
+    pub heal_radius: u32,
+    #[serde(default)]
+    pub heal_rate: u32,
 }
 
 impl Upgrades {
@@ -63,11 +350,62 @@ impl Upgrades {
             large_speed: 0,
             coin_rate: 0,
             base_hp: 0,
+            heal_radius: 0,
+            heal_rate: 0,
+        }
+    }
+
+    // 兵種ごとの強化（体力・攻撃・速度、％）
+    pub fn unit_bonus(&self, unit_type: UnitType) -> (u32, u32, u32) {
+        match unit_type {
+            UnitType::Small => (self.small_hp, self.small_attack, self.small_speed),
+            UnitType::Medium => (self.medium_hp, self.medium_attack, self.medium_speed),
+            UnitType::Large => (self.large_hp, self.large_attack, self.large_speed),
+            UnitType::Hero => (0, 0, 0),
+        }
+    }
+
+    // UPGRADE_OPTIONS と同じ順のレベル
+    pub fn levels(&self) -> [u32; 13] {
+        [
+            self.small_attack,
+            self.medium_attack,
+            self.large_attack,
+            self.small_hp,
+            self.medium_hp,
+            self.large_hp,
+            self.small_speed,
+            self.medium_speed,
+            self.large_speed,
+            self.coin_rate,
+            self.base_hp,
+            self.heal_radius,
+            self.heal_rate,
+        ]
+    }
+
+    // 項目ごとに高い方のレベル
+    pub fn max_with(&self, other: &Self) -> Self {
+        Self {
+            small_attack: self.small_attack.max(other.small_attack),
+            medium_attack: self.medium_attack.max(other.medium_attack),
+            large_attack: self.large_attack.max(other.large_attack),
+            small_hp: self.small_hp.max(other.small_hp),
+            medium_hp: self.medium_hp.max(other.medium_hp),
+            large_hp: self.large_hp.max(other.large_hp),
+            small_speed: self.small_speed.max(other.small_speed),
+            medium_speed: self.medium_speed.max(other.medium_speed),
+            large_speed: self.large_speed.max(other.large_speed),
+            coin_rate: self.coin_rate.max(other.coin_rate),
+            base_hp: self.base_hp.max(other.base_hp),
+            heal_radius: self.heal_radius.max(other.heal_radius),
+            heal_rate: self.heal_rate.max(other.heal_rate),
         }
     }
 
-    pub fn get_cost(&self, upgrade_type: &str, unit_type: &str) -> u32 {
-        let level = match (upgrade_type, unit_type) {
+    // 存在しない強化なら None
+    pub fn level(&self, upgrade_type: &str, unit_type: &str) -> Option<u32> {
+        Some(match (upgrade_type, unit_type) {
             ("attack", "small") => self.small_attack,
             ("attack", "medium") => self.medium_attack,
             ("attack", "large") => self.large_attack,
@@ -79,43 +417,158 @@ impl Upgrades {
             ("speed", "large") => self.large_speed,
             ("coin_rate", _) => self.coin_rate,
             ("base_hp", _) => self.base_hp,
-            _ => 0,
-        };
-        // 初期値3000、1.2倍ずつ増加
-        (3000.0 * 1.2_f32.powi(level as i32)) as u32
+            ("heal_radius", _) => self.heal_radius,
+            ("heal_rate", _) => self.heal_rate,
+            _ => return None,
+        })
+    }
+
+    pub fn get_cost(&self, upgrade_type: &str, unit_type: &str) -> u64 {
+        upgrade_cost(self.level(upgrade_type, unit_type).unwrap_or(0))
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct AutoBuyConfig {
-    pub enabled: bool,
+// 初期値3000、1.2倍ずつ増加
+pub fn upgrade_cost(level: u32) -> u64 {
+    (3000.0 * 1.2_f64.powi(level as i32)) as u64
+}
+
+// 購入可能なアップグレードの一覧 (upgrade_type, unit_type)
+pub const UPGRADE_OPTIONS: [(&str, &str); 13] = [
+    ("attack", "small"),
+    ("attack", "medium"),
+    ("attack", "large"),
+    ("hp", "small"),
+    ("hp", "medium"),
+    ("hp", "large"),
+    ("speed", "small"),
+    ("speed", "medium"),
+    ("speed", "large"),
+    ("coin_rate", ""),
+    ("base_hp", ""),
+    ("heal_radius", ""),
+    ("heal_rate", ""),
+];
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AutoBuyEntry {
     pub upgrade_type: String, // "attack", "hp", "speed", "coin_rate", "base_hp"
-    pub unit_type: String,    // "small", "medium", "large", ""
     #[serde(default)]
+    pub unit_type: String, // "small", "medium", "large", ""
+    // このレベルに達したら買わない（None なら上限なし）
+    #[serde(default)]
+    pub max_level: Option<u32>,
+}
+
+// 優先度の高い順に並べた購入対象
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(from = "StoredAutoBuy")]
+pub struct AutoBuyConfig {
+    pub enabled: bool,
+    pub queue: Vec<AutoBuyEntry>,
     pub remaining_time: f32, // 残り時間（秒）
 }
 
-impl Default for AutoBuyConfig {
-    fn default() -> Self {
+// 対象を 1 つしか持たない旧バージョンのセーブも読めるようにする
+#[derive(Deserialize)]
+struct StoredAutoBuy {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    queue: Vec<AutoBuyEntry>,
+    #[serde(default)]
+    upgrade_type: String,
+    #[serde(default)]
+    unit_type: String,
+    #[serde(default)]
+    remaining_time: f32,
+}
+
+impl From<StoredAutoBuy> for AutoBuyConfig {
+    fn from(stored: StoredAutoBuy) -> Self {
+        let mut queue = stored.queue;
+        if queue.is_empty() && !stored.upgrade_type.is_empty() {
+            queue.push(AutoBuyEntry {
+                upgrade_type: stored.upgrade_type,
+                unit_type: stored.unit_type,
+                max_level: None,
+            });
+        }
         Self {
-            enabled: false,
-            upgrade_type: String::new(),
-            unit_type: String::new(),
-            remaining_time: 0.0,
+            enabled: stored.enabled,
+            queue,
+            remaining_time: stored.remaining_time,
+        }
+    }
+}
+
+impl AutoBuyConfig {
+    pub fn validate(queue: &[AutoBuyEntry]) -> Result<(), GameError> {
+        let invalid = |upgrade_type: &str| GameError::InvalidUpgrade {
+            upgrade_type: upgrade_type.to_string(),
+        };
+        if queue.is_empty() {
+            return Err(invalid(""));
         }
+        let upgrades = Upgrades::new();
+        match queue.iter().find(|entry| {
+            upgrades
+                .level(&entry.upgrade_type, &entry.unit_type)
+                .is_none()
+        }) {
+            Some(entry) => Err(invalid(&entry.upgrade_type)),
+            None => Ok(()),
+        }
+    }
+
+    // 上限に達しておらず、今のコインで買える最初の対象
+    fn next_purchase(&self, upgrades: &Upgrades, coins: u64) -> Option<&AutoBuyEntry> {
+        self.queue.iter().find(|entry| {
+            let Some(level) = upgrades.level(&entry.upgrade_type, &entry.unit_type) else {
+                return false;
+            };
+            entry.max_level.is_none_or(|max| level < max)
+                && coins >= upgrades.get_cost(&entry.upgrade_type, &entry.unit_type)
+        })
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+// ゲームループ 1 回分の入力（アプリのゲームループとシミュレーターで共用）
+#[derive(Clone, Debug, Default)]
+pub struct TickInput {
+    pub clicks: u32,
+    pub types: u32,
+    // ホイールとマウス移動で貯まった小型ユニット
+    pub pointer_units: u32,
+    pub abilities: Vec<UnitAbility>,
+    // エネルギーモードでホットキーから選んだユニット
+    pub summons: Vec<UnitType>,
+    pub energy_mode: bool,
+    pub idle: bool,
+    // 集中ブロック中に気を散らすアプリでクリックした
+    pub distracted: bool,
+    // 1 分ごとの強力ユニット
+    pub reinforcement: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
 pub struct PlayerProgressData {
     pub stage: u32,
-    pub coins: u32,
+    #[ts(type = "number")]
+    pub coins: u64,
     pub upgrades: Upgrades,
     pub max_player_base_hp: f32,
     pub max_enemy_base_hp: f32,
+    // 転生データを持たない旧バージョンの進行状況では None
+    #[serde(default)]
+    pub prestige: Option<PrestigeState>,
+    #[serde(default)]
+    pub titles: Option<Titles>,
+    #[serde(default)]
+    pub cosmetics: Option<Cosmetics>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameState {
     pub player_units: Vec<Unit>,
     pub enemy_units: Vec<Unit>,
@@ -123,40 +576,208 @@ pub struct GameState {
     pub enemy_base_hp: f32,
     pub max_player_base_hp: f32,
     pub max_enemy_base_hp: f32,
-    pub coins: u32,
+    pub coins: u64,
     pub stage: u32,
     pub click_count: u32,
     pub type_count: u32,
     pub upgrades: Upgrades,
     #[serde(default)]
     pub auto_buy: AutoBuyConfig,
+    #[serde(default)]
+    pub sessions: SessionTracker,
+    #[serde(default)]
+    pub focus: FocusTimer,
+    #[serde(default)]
+    pub integrity: SaveIntegrity,
+    #[serde(default)]
+    pub chests: ChestInventory,
+    #[serde(default)]
+    pub prestige: PrestigeState,
+    // マルチプレイのシーズン（切り替わると周回をやり直して報酬を受け取る）
+    #[serde(default)]
+    pub season: SeasonTracker,
+    #[serde(default)]
+    pub morale: Morale,
+    #[serde(default)]
+    pub achievements: Achievements,
+    #[serde(default)]
+    pub titles: Titles,
+    // 解除したアバター・ユニットの見た目と選んでいるもの
+    #[serde(default)]
+    pub cosmetics: Cosmetics,
+    // デイリー・ウィークリーのクエスト
+    #[serde(default)]
+    pub quests: QuestLog,
+    // タイピング練習ツールの履歴の取り込み（転生しても 1 回まで）
+    #[serde(default)]
+    pub typing_import: Option<TypingImport>,
+    // ボスステージで撃破前のボス
+    #[serde(default)]
+    pub boss: Option<BossState>,
+    #[serde(default)]
+    pub battle: BattleReports,
+    // 前線基地（ステージごとに建設、ステージが変わると撤去）
+    #[serde(default)]
+    pub barracks: Option<ForwardBarracks>,
+    #[serde(default)]
+    pub rally: Option<RallyPoint>,
+    #[serde(default)]
+    pub stance: Stance,
+    // 難易度（敵の強さ・出現間隔・報酬・基地の体力の倍率）
+    #[serde(default)]
+    rules: GameRules,
+    // 2 人で遊ぶときの入力の振り分けと B の財布
+    #[serde(default)]
+    pub coop: Coop,
+    // 強化に使ったコイン（振り直しで返す）
+    #[serde(default)]
+    upgrade_ledger: UpgradeLedger,
+    // エネルギーモードで入力から貯めた分
+    #[serde(default)]
+    pub energy: EnergyPool,
+    // コインによる呼び出しの購入回数とクールダウン
+    #[serde(default)]
+    summons: Summons,
+    // 消耗品の所持数・クールダウンと使用中の効果
+    #[serde(default)]
+    items: ItemInventory,
+    // 最後に取得した転生ショップの品揃え
+    #[serde(default)]
+    shop: ShopCache,
+    // 通常の出現とは別の時間で予告してやって来る襲撃
+    #[serde(default)]
+    pub invasion: InvasionState,
+    // 撃破報酬の1コイン未満の端数
+    #[serde(default)]
+    coin_fraction: f64,
+    // 共有レイドボスへまだ送っていない与ダメージ
+    #[serde(default)]
+    raid_damage: f64,
+    // 敵基地を破壊しても自動で次のステージへ進まない
+    #[serde(default)]
+    pub manual_advance: bool,
+    // クリア済みステージで弱い敵を倒してコインを稼いでいる状態
+    #[serde(default)]
+    pub farming: bool,
     next_unit_id: u32,
     enemy_spawn_timer: f32,
     stage_clear: bool,
+    // 連続入力のコンボ（保存しない）
+    #[serde(skip)]
+    pub combo: Combo,
+    #[serde(skip)]
+    pub clear_sequence: StageClearSequence,
+    // ゲームループが "notification" として送るまでの通知
+    #[serde(skip)]
+    pub notifications: Notifications,
     #[serde(skip)]
     save_timer: f32,
+    // スナップショット（予測シミュレーション用）はディスクに保存しない
+    #[serde(skip)]
+    simulation: bool,
+    #[serde(skip)]
+    pub defeats: u32,
+    // 放置中の省電力モード（敵が出現しない）
+    #[serde(skip)]
+    idle: bool,
+    // 片側あたりのユニット数の上限（設定から適用する）
+    #[serde(skip)]
+    unit_caps: UnitCaps,
+    // 統計の時系列用に、前回取り出してからの出来事を数える
+    #[serde(skip)]
+    activity: Activity,
+    // 敵の出現・吹き飛ばし・宝箱の乱数（シードを決めれば同じ戦闘を再現できる）
+    #[serde(skip, default = "StdRng::from_entropy")]
+    rng: StdRng,
+    // step() を呼んだ回数と、固定の刻みに満たず持ち越した秒数
+    #[serde(skip)]
+    tick: u64,
+    #[serde(skip)]
+    step_carry: f32,
+    #[serde(skip)]
+    recorder: Option<ReplayRecorder>,
+    // 最後に止めた記録（タイムラプスの書き出し用）
+    #[serde(skip)]
+    last_replay: Option<Replay>,
 }
 
 impl GameState {
     pub fn new() -> Self {
-        if let Some(mut loaded) = Self::load_from_disk() {
-            loaded.save_timer = 0.0;
-            loaded.next_unit_id = loaded
-                .player_units
-                .iter()
-                .chain(loaded.enemy_units.iter())
-                .map(|u| u.id)
-                .max()
-                .unwrap_or(0)
-                .saturating_add(1);
-            return loaded;
+        let slot = Self::active_slot();
+        let (mut state, loaded) = match Self::load_from_disk(slot) {
+            Some(loaded) => (Self::prepare_loaded(loaded), true),
+            None => (Self::fresh(), false),
+        };
+        if state.replay_journal(slot) || !loaded {
+            state.persist_state();
         }
-
-        let state = Self::fresh();
-        state.persist_state();
         state
     }
 
+    // 前回の保存より後の進行がジャーナルに残っていれば反映する（異常終了やセーブの破損からの復旧）
+    fn replay_journal(&mut self, slot: u32) -> bool {
+        let Some(path) = Self::slot_file_path(slot, "journal") else {
+            return false;
+        };
+        let Some(entry) = journal::read(&path).pop() else {
+            return false;
+        };
+        // 消し損ねた古い記録でセーブを巻き戻さない
+        let saved = self.export_progress();
+        if matches!(
+            conflict::compare(&saved, &entry.progress),
+            Divergence::Same | Divergence::LocalAhead
+        ) {
+            journal::clear(&path);
+            return false;
+        }
+        self.import_progress(&entry.progress);
+        true
+    }
+
+    // 次のセーブまでの間の進行をジャーナルに追記する
+    fn record(&self, event: JournalEvent) {
+        if self.simulation {
+            return;
+        }
+        let Some(path) = Self::slot_file_path(Self::active_slot(), "journal") else {
+            return;
+        };
+        let entry = JournalEntry {
+            at: unix_timestamp(),
+            event,
+            progress: self.export_progress(),
+        };
+        // 書けなかったことはセーブと同じ保存先の状態として画面に出す
+        save_writer::report(&path, &journal::append(&path, &entry));
+    }
+
+    fn prepare_loaded(mut loaded: Self) -> Self {
+        if !loaded.is_finite() {
+            eprintln!("Repairing non-finite values in loaded save");
+        }
+        loaded.repair();
+        let upgrades = loaded.upgrades.clone();
+        loaded.upgrade_ledger.reconcile(&upgrades);
+        loaded.save_timer = 0.0;
+        // 以降の保存はすべて署名付き
+        loaded.integrity.signed = true;
+        // クリア演出の途中で終了していたら演出を飛ばして進める
+        if loaded.stage_clear && !loaded.farming {
+            loaded.finish_stage_clear();
+        }
+        loaded.prestige.record_stage(loaded.stage);
+        loaded.next_unit_id = loaded
+            .player_units
+            .iter()
+            .chain(loaded.enemy_units.iter())
+            .map(|u| u.id)
+            .max()
+            .unwrap_or(0)
+            .saturating_add(1);
+        loaded
+    }
+
     pub fn export_progress(&self) -> PlayerProgressData {
         PlayerProgressData {
             stage: self.stage,
@@ -164,27 +785,105 @@ impl GameState {
             upgrades: self.upgrades.clone(),
             max_player_base_hp: self.max_player_base_hp,
             max_enemy_base_hp: self.max_enemy_base_hp,
+            prestige: Some(self.prestige.clone()),
+            titles: Some(self.titles.clone()),
+            cosmetics: Some(self.cosmetics.clone()),
         }
     }
 
     pub fn import_progress(&mut self, progress: &PlayerProgressData) {
+        self.checkpoint(CheckpointReason::RemoteProgress);
         self.stage = progress.stage.max(1);
         self.coins = progress.coins;
         self.upgrades = progress.upgrades.clone();
-        self.max_player_base_hp = progress.max_player_base_hp.max(100.0);
+        self.upgrade_ledger.reconcile(&progress.upgrades);
+        self.max_player_base_hp = valid_max_hp(progress.max_player_base_hp, 1000.0).max(100.0);
         self.player_base_hp = self.max_player_base_hp;
-        self.max_enemy_base_hp = progress.max_enemy_base_hp.max(100.0);
+        self.max_enemy_base_hp = valid_max_hp(progress.max_enemy_base_hp, 500.0).max(100.0);
         self.enemy_base_hp = self.max_enemy_base_hp;
+        if let Some(prestige) = &progress.prestige {
+            self.prestige = prestige.clone();
+        }
+        if let Some(titles) = &progress.titles {
+            self.titles.absorb(titles);
+        }
+        if let Some(cosmetics) = &progress.cosmetics {
+            self.cosmetics.absorb(cosmetics);
+        }
+        self.prestige.record_stage(self.stage);
         self.player_units.clear();
         self.enemy_units.clear();
+        self.invasion.abandon();
         self.click_count = 0;
         self.type_count = 0;
+        self.clear_sequence.cancel();
         self.stage_clear = false;
+        self.farming = false;
+        self.barracks = None;
+        self.battle.reset();
+        self.summons.new_stage();
+        self.spawn_boss();
         self.enemy_spawn_timer = 0.0;
-        self.auto_buy = AutoBuyConfig::default();
         self.persist_state();
     }
 
+    // 壊れた数値を直す（直せないユニットは取り除く）
+    fn repair(&mut self) {
+        self.max_player_base_hp = valid_max_hp(self.max_player_base_hp, 1000.0);
+        self.max_enemy_base_hp = valid_max_hp(self.max_enemy_base_hp, 500.0);
+        self.player_base_hp =
+            finite_or(self.player_base_hp, self.max_player_base_hp).min(self.max_player_base_hp);
+        self.enemy_base_hp =
+            finite_or(self.enemy_base_hp, self.max_enemy_base_hp).min(self.max_enemy_base_hp);
+        self.player_units.retain_mut(Unit::repair);
+        self.enemy_units.retain_mut(Unit::repair);
+        self.enemy_spawn_timer = finite_or(self.enemy_spawn_timer, 0.0).max(0.0);
+        if !self.raid_damage.is_finite() || self.raid_damage < 0.0 {
+            self.raid_damage = 0.0;
+        }
+        if !self.coin_fraction.is_finite() {
+            self.coin_fraction = 0.0;
+        }
+        self.coin_fraction = self.coin_fraction.clamp(0.0, 1.0);
+        self.coop.repair();
+        self.energy.repair();
+        // 手で書き換えたセーブの範囲外の倍率は Normal に戻す
+        self.rules = self.rules.clone().validated().unwrap_or_default();
+        self.morale.value = finite_or(self.morale.value, 0.0).clamp(-1.0, 1.0);
+        self.auto_buy.remaining_time = finite_or(self.auto_buy.remaining_time, 0.0).max(0.0);
+        self.summons.repair();
+        self.items.repair();
+        self.invasion.repair();
+        if self
+            .barracks
+            .as_ref()
+            .is_some_and(|b| !b.position.is_finite())
+        {
+            self.barracks = None;
+        }
+    }
+
+    // シミュレーションの数値がすべて有限か（毎 tick のデバッグ検査用）
+    fn is_finite(&self) -> bool {
+        [
+            self.player_base_hp,
+            self.enemy_base_hp,
+            self.max_player_base_hp,
+            self.max_enemy_base_hp,
+            self.enemy_spawn_timer,
+            self.morale.value,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+            && self.coin_fraction.is_finite()
+            && self.raid_damage.is_finite()
+            && self
+                .player_units
+                .iter()
+                .chain(self.enemy_units.iter())
+                .all(Unit::is_finite)
+    }
+
     fn fresh() -> Self {
         Self {
             player_units: Vec::new(),
@@ -199,184 +898,1327 @@ impl GameState {
             type_count: 0,
             upgrades: Upgrades::new(),
             auto_buy: AutoBuyConfig::default(),
+            sessions: SessionTracker::default(),
+            focus: FocusTimer::default(),
+            integrity: SaveIntegrity {
+                signed: true,
+                ..SaveIntegrity::default()
+            },
+            chests: ChestInventory::default(),
+            prestige: PrestigeState::default(),
+            season: SeasonTracker::default(),
+            morale: Morale::default(),
+            achievements: Achievements::default(),
+            titles: Titles::default(),
+            cosmetics: Cosmetics::default(),
+            quests: QuestLog::default(),
+            typing_import: None,
+            boss: None,
+            battle: BattleReports::default(),
+            barracks: None,
+            rally: None,
+            stance: Stance::Aggressive,
+            rules: GameRules::default(),
+            coop: Coop::default(),
+            upgrade_ledger: UpgradeLedger::default(),
+            energy: EnergyPool::default(),
+            summons: Summons::default(),
+            items: ItemInventory::default(),
+            shop: ShopCache::default(),
+            invasion: InvasionState::default(),
+            coin_fraction: 0.0,
+            raid_damage: 0.0,
+            manual_advance: false,
+            farming: false,
             next_unit_id: 0,
             enemy_spawn_timer: 0.0,
             stage_clear: false,
+            combo: Combo::default(),
+            clear_sequence: StageClearSequence::default(),
+            notifications: Notifications::default(),
             save_timer: 0.0,
+            simulation: false,
+            defeats: 0,
+            idle: false,
+            unit_caps: UnitCaps::default(),
+            activity: Activity::default(),
+            rng: StdRng::from_entropy(),
+            tick: 0,
+            step_carry: 0.0,
+            recorder: None,
+            last_replay: None,
         }
     }
 
-    fn data_file_path() -> Option<PathBuf> {
-        ProjectDirs::from("com", "ClickerClicker", "ClickerClickerClicker")
-            .map(|dirs| dirs.data_dir().join("game_state.json"))
+    fn slot_file_path(slot: u32, extension: &str) -> Option<PathBuf> {
+        let name = if slot <= 1 {
+            format!("game_state.{}", extension)
+        } else {
+            format!("game_state_slot{}.{}", slot, extension)
+        };
+        profile::data_dir().map(|dir| dir.join(name))
     }
 
-    fn load_from_disk() -> Option<Self> {
-        let path = Self::data_file_path()?;
-        let contents = fs::read_to_string(path).ok()?;
-        serde_json::from_str(&contents).ok()
+    fn slot_index_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("save_slots.json"))
     }
 
-    fn persist_state(&self) {
-        if let Some(path) = Self::data_file_path() {
-            if let Some(parent) = path.parent() {
-                let _ = fs::create_dir_all(parent);
-            }
-            if let Ok(json) = serde_json::to_string(self) {
-                let _ = fs::write(path, json);
-            }
+    fn validate_slot(slot: u32) -> Result<(), GameError> {
+        if slot == 0 || slot > SAVE_SLOT_COUNT {
+            return Err(GameError::InvalidSaveSlot { slot });
         }
+        Ok(())
     }
 
-    pub fn spawn_unit(&mut self, unit_type: UnitType) {
-        let (base_hp, base_attack, base_speed) = match unit_type {
-            UnitType::Small => (10.0, 5.0, 100.0),
-            UnitType::Medium => (30.0, 15.0, 80.0),
-            UnitType::Large => (100.0, 50.0, 60.0),
-        };
+    // 自動セーブの書き込み先
+    pub fn active_slot() -> u32 {
+        Self::slot_index_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<SlotIndex>(&contents).ok())
+            .map(|index| index.active)
+            .filter(|&slot| Self::validate_slot(slot).is_ok())
+            .unwrap_or(1)
+    }
 
-        // アップグレード適用
-        let (hp_bonus, attack_bonus, speed_bonus) = match unit_type {
-            UnitType::Small => (
-                self.upgrades.small_hp,
-                self.upgrades.small_attack,
-                self.upgrades.small_speed,
-            ),
-            UnitType::Medium => (
-                self.upgrades.medium_hp,
-                self.upgrades.medium_attack,
-                self.upgrades.medium_speed,
-            ),
-            UnitType::Large => (
-                self.upgrades.large_hp,
-                self.upgrades.large_attack,
-                self.upgrades.large_speed,
-            ),
-        };
+    fn set_active_slot(slot: u32) -> Result<(), GameError> {
+        let path = Self::slot_index_path()
+            .ok_or_else(|| GameError::storage("Failed to resolve save slot path"))?;
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let json =
+            serde_json::to_string(&SlotIndex { active: slot }).map_err(GameError::storage)?;
+        fs::write(path, json).map_err(GameError::storage)
+    }
 
-        let hp = base_hp * (1.0 + hp_bonus as f32 / 100.0);
-        let attack = base_attack * (1.0 + attack_bonus as f32 / 100.0);
-        let speed = base_speed * (1.0 + speed_bonus as f32 / 100.0);
+    fn data_file_path() -> Option<PathBuf> {
+        Self::slot_file_path(Self::active_slot(), "json")
+    }
 
-        self.player_units.push(Unit {
-            id: self.next_unit_id,
-            unit_type,
-            position: 0.0,
-            hp,
-            max_hp: hp,
-            attack,
-            speed,
-            is_player: true,
-            target_id: None,
-            knockback_velocity: 0.0,
-            knockback_time: 0.0,
-            knockback_total: 0.0,
-        });
+    pub fn list_slots() -> Vec<SaveSlotInfo> {
+        save_writer::flush();
+        let active = Self::active_slot();
+        (1..=SAVE_SLOT_COUNT)
+            .map(|slot| {
+                let path = Self::slot_file_path(slot, "json");
+                let summary = path
+                    .as_ref()
+                    .and_then(|p| encryption::read_file(p).ok())
+                    .and_then(|bytes| serde_json::from_slice::<SlotSummary>(&bytes).ok());
+                let saved_at = path
+                    .and_then(|p| fs::metadata(p).ok())
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64);
+                SaveSlotInfo {
+                    slot,
+                    active: slot == active,
+                    stage: summary.as_ref().map(|s| s.stage),
+                    coins: summary.as_ref().map(|s| s.coins),
+                    saved_at,
+                }
+            })
+            .collect()
+    }
 
-        self.next_unit_id += 1;
+    // 現在の状態を指定スロットに保存し、以降の自動セーブもそのスロットへ書く
+    pub fn save_to_slot(&self, slot: u32) -> Result<(), GameError> {
+        Self::validate_slot(slot)?;
+        self.write_slot(slot)?;
+        Self::set_active_slot(slot)
+    }
 
-        match unit_type {
-            UnitType::Small => self.type_count += 1,
-            UnitType::Medium => self.click_count += 1,
-            _ => {}
+    pub fn load_from_slot(slot: u32) -> Result<Self, GameError> {
+        Self::validate_slot(slot)?;
+        if !Self::slot_file_path(slot, "json").is_some_and(|p| p.exists()) {
+            return Err(GameError::SaveSlotEmpty { slot });
+        }
+        let loaded = Self::load_from_disk(slot)
+            .ok_or_else(|| GameError::storage("Failed to read save slot"))?;
+        Self::set_active_slot(slot)?;
+        let mut state = Self::prepare_loaded(loaded);
+        if state.replay_journal(slot) {
+            state.persist_state();
         }
+        Ok(state)
     }
 
-    fn spawn_enemy(&mut self) {
-        let mut rng = rand::thread_rng();
-        // 1000ステージ想定でなだらかに難易度上昇（対数的スケーリング）
-        let stage_multiplier =
-            1.0 + (self.stage as f32 - 1.0) * 0.05 + ((self.stage as f32).ln() / 10.0) * 0.3;
-
-        let unit_type = if rng.gen_bool(0.7) {
-            UnitType::Small
-        } else if rng.gen_bool(0.5) {
-            UnitType::Medium
-        } else {
-            UnitType::Large
-        };
-
-        let (base_hp, base_attack, base_speed) = match unit_type {
-            UnitType::Small => (15.0, 4.0, 90.0),
-            UnitType::Medium => (40.0, 12.0, 70.0),
-            UnitType::Large => (120.0, 40.0, 50.0),
-        };
-
-        self.enemy_units.push(Unit {
-            id: self.next_unit_id,
-            unit_type,
-            position: 1000.0,
-            hp: base_hp * stage_multiplier,
-            max_hp: base_hp * stage_multiplier,
-            attack: base_attack * stage_multiplier,
-            speed: base_speed,
-            is_player: false,
-            target_id: None,
-            knockback_velocity: 0.0,
-            knockback_time: 0.0,
-            knockback_total: 0.0,
-        });
-
-        self.next_unit_id += 1;
+    // 暗号化方式の変更前に全スロットとそのバックアップを平文で読み出しておく
+    pub fn read_slot_files() -> Vec<(PathBuf, Vec<u8>)> {
+        save_writer::flush();
+        (1..=SAVE_SLOT_COUNT)
+            .filter_map(|slot| Self::slot_file_path(slot, "json"))
+            .flat_map(|path| {
+                (0..=save_writer::BACKUPS).map(move |generation| match generation {
+                    0 => path.clone(),
+                    _ => save_writer::backup_path(&path, generation),
+                })
+            })
+            .filter_map(|path| encryption::read_file(&path).ok().map(|bytes| (path, bytes)))
+            .collect()
     }
 
-    pub fn update(&mut self, delta: f32) {
-        // 敵のスポーン（なだらかに速度上昇、1000ステージ想定）
-        self.enemy_spawn_timer += delta;
-        let spawn_interval = (3.0 - (self.stage as f32 * 0.002).min(2.0)).max(1.0);
-        if self.enemy_spawn_timer >= spawn_interval {
-            self.spawn_enemy();
-            self.enemy_spawn_timer = 0.0;
+    // 新しい方式で書き直す。古い方式の暗号文も上書きし、1 つでも書けなければ止める
+    pub fn rewrite_slot_files(files: &[(PathBuf, Vec<u8>)]) -> Result<(), GameError> {
+        for (path, bytes) in files {
+            encryption::write_file_migrating(path, bytes).map_err(GameError::storage)?;
         }
+        Ok(())
+    }
 
-        // ユニットの移動と戦闘
-        let mut units_to_remove: Vec<u32> = Vec::new();
+    // 本体が読めなければ新しい世代のバックアップから順に試す
+    fn load_from_disk(slot: u32) -> Option<Self> {
+        save_writer::flush();
+        let path = Self::slot_file_path(slot, "json")?;
+        let signature_path = Self::slot_file_path(slot, "sig")?;
+        let signed = Self::slot_file_path(slot, "signed").is_some_and(|marker| marker.exists());
+        (0..=save_writer::BACKUPS).find_map(|generation| match generation {
+            0 => Self::read_save(&path, &signature_path, signed),
+            _ => Self::read_save(
+                &save_writer::backup_path(&path, generation),
+                &save_writer::backup_path(&signature_path, generation),
+                signed,
+            ),
+        })
+    }
 
-        // ターゲット検出とユニット移動
-        for i in 0..self.player_units.len() {
-            let unit = &mut self.player_units[i];
+    // signed はセーブの外に置いた印（本体の integrity.signed は書き換えられるので、それだけには頼らない）
+    fn read_save(path: &Path, signature_path: &Path, signed: bool) -> Option<Self> {
+        let contents = encryption::read_file(path).ok()?;
+        let mut state: Self = serde_json::from_slice(&contents).ok()?;
 
-            // ノックバック処理（吹き飛ばし）
-            if unit.knockback_time > 0.0 {
-                unit.position += unit.knockback_velocity * delta;
-                unit.knockback_time = (unit.knockback_time - delta).max(0.0);
-                if unit.knockback_time == 0.0 {
-                    unit.knockback_velocity = 0.0;
-                    unit.knockback_total = 0.0;
+        // 署名の検証（署名導入前のセーブは未署名として扱う）
+        match fs::read_to_string(signature_path).ok() {
+            Some(signature) => {
+                if !integrity::verify(&contents, &signature) {
+                    state.integrity.mark_tampered("signature mismatch");
                 }
             }
-
-            // ターゲットが有効かチェック
-            if let Some(target_id) = unit.target_id {
-                if !self.enemy_units.iter().any(|e| e.id == target_id) {
-                    unit.target_id = None;
+            None => {
+                if signed || state.integrity.signed {
+                    state.integrity.mark_tampered("signature missing");
                 }
             }
+        }
+        Some(state)
+    }
 
-            // ターゲットを探す
-            if unit.target_id.is_none() {
-                if let Some(enemy) = self.enemy_units.iter().min_by(|a, b| {
-                    (a.position - unit.position)
-                        .abs()
-                        .partial_cmp(&(b.position - unit.position).abs())
-                        .unwrap()
-                }) {
-                    unit.target_id = Some(enemy.id);
+    // 暗号化済みセーブをパスフレーズで検証（セーブが無いか平文なら常に成功）
+    pub fn verify_save_key(key: &[u8; 32]) -> Result<(), GameError> {
+        let Some(path) = Self::data_file_path() else {
+            return Ok(());
+        };
+        save_writer::flush();
+        match fs::read(path) {
+            Ok(bytes) if encryption::is_encrypted(&bytes) => encryption::decrypt_with(key, &bytes)
+                .map(|_| ())
+                .map_err(|_| GameError::WrongPassphrase),
+            _ => Ok(()),
+        }
+    }
+
+    // 書き出しと読み直しを一通り試し、壊れている箇所を報告する（状態は変えない）
+    pub fn verify_integrity(&self) -> SaveCheckReport {
+        let mut report = SaveCheckReport::default();
+
+        report.run("serialize");
+        let json = match serde_json::to_string(self) {
+            Ok(json) => json,
+            Err(e) => {
+                report.fail("serialize", e.to_string());
+                return report.finish();
+            }
+        };
+        report.size = json.len();
+
+        // 読み直した状態をもう一度書き出しても同じ内容になるか
+        report.run("reload");
+        match serde_json::from_str::<Self>(&json) {
+            Ok(reloaded) => {
+                let original = serde_json::from_str::<serde_json::Value>(&json).ok();
+                if original.is_none() || original != serde_json::to_value(&reloaded).ok() {
+                    report.fail("reload", i18n::text("save_check.reload"));
+                }
+            }
+            Err(e) => report.fail("reload", e.to_string()),
+        }
+
+        report.run("ranges");
+        self.check_ranges(&mut report);
+
+        report.run("checksum");
+        self.check_saved_file(&mut report);
+
+        // 引き継ぎ用の文字列は形式のバージョンとチェックサムを持つ
+        report.run("version");
+        match self.export_save().and_then(|code| save_code::decode(&code)) {
+            Ok(decoded) if decoded == json => {}
+            Ok(_) => report.fail("version", i18n::text("save_check.version")),
+            Err(e) => report.fail("version", e.to_string()),
+        }
+        report.finish()
+    }
+
+    fn check_ranges(&self, report: &mut SaveCheckReport) {
+        if !self.is_finite() {
+            report.fail("ranges", i18n::text("save_check.non_finite"));
+        }
+        if self.stage == 0 {
+            report.fail("ranges", i18n::text("save_check.stage_zero"));
+        }
+        if self.player_base_hp > self.max_player_base_hp
+            || self.enemy_base_hp > self.max_enemy_base_hp
+        {
+            report.warn("ranges", i18n::text("save_check.base_hp"));
+        }
+        // 読み込み時の修復で変わる値があれば、次の起動で戻されることを知らせる
+        let mut repaired = self.clone();
+        repaired.repair();
+        if serde_json::to_value(&repaired).ok() != serde_json::to_value(self).ok() {
+            report.warn("ranges", i18n::text("save_check.repaired"));
+        }
+        let mut ids = HashSet::new();
+        let units = self.player_units.iter().chain(self.enemy_units.iter());
+        if units.clone().any(|unit| !ids.insert(unit.id)) {
+            report.warn("ranges", i18n::text("save_check.duplicate_id"));
+        }
+        if units.clone().any(|unit| unit.id >= self.next_unit_id) {
+            report.warn("ranges", i18n::text("save_check.id_ahead"));
+        }
+        if !self.auto_buy.queue.is_empty() {
+            if let Err(e) = AutoBuyConfig::validate(&self.auto_buy.queue) {
+                report.warn(
+                    "ranges",
+                    i18n::format("save_check.auto_buy", &[("error", &e.message())]),
+                );
+            }
+        }
+    }
+
+    // 遊んでいるスロットのファイルが読めて、署名が合うか
+    fn check_saved_file(&self, report: &mut SaveCheckReport) {
+        save_writer::flush();
+        let slot = Self::active_slot();
+        let Some(path) = Self::slot_file_path(slot, "json").filter(|path| path.exists()) else {
+            report.warn("checksum", i18n::text("save_check.not_saved"));
+            return;
+        };
+        let contents = match encryption::read_file(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                report.fail(
+                    "checksum",
+                    i18n::format("save_check.unreadable", &[("error", &e)]),
+                );
+                return;
+            }
+        };
+        if serde_json::from_slice::<Self>(&contents).is_err() {
+            report.fail("checksum", i18n::text("save_check.invalid"));
+        }
+        match Self::slot_file_path(slot, "sig").and_then(|p| fs::read_to_string(p).ok()) {
+            Some(signature) if integrity::verify(&contents, &signature) => {}
+            Some(_) => report.fail("checksum", i18n::text("save_check.signature")),
+            None => report.warn("checksum", i18n::text("save_check.unsigned")),
+        }
+        if self.integrity.tampered {
+            report.warn(
+                "checksum",
+                i18n::format(
+                    "save_check.tampered",
+                    &[("reasons", &self.integrity.reasons.join(", "))],
+                ),
+            );
+        }
+    }
+
+    pub fn set_idle(&mut self, idle: bool) {
+        if self.idle != idle {
+            self.record_replay(ReplayInput::Idle { idle });
+        }
+        self.idle = idle;
+    }
+
+    // ディスクに触れない新しいゲーム（シミュレーター用）
+    pub fn headless() -> Self {
+        Self::fresh().snapshot()
+    }
+
+    // 現在の戦場を複製したシミュレーション用の状態
+    pub fn snapshot(&self) -> Self {
+        let mut snapshot = self.clone();
+        snapshot.simulation = true;
+        snapshot.defeats = 0;
+        snapshot.recorder = None;
+        snapshot.last_replay = None;
+        snapshot
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // 乱数をシードで作り直し、以降の入力を記録する（記録中なら最初からやり直す）
+    pub fn start_replay(&mut self, seed: u64) {
+        self.seed_rng(seed);
+        self.recorder = None;
+        self.recorder = Some(ReplayRecorder::new(seed, self.snapshot(), self.tick));
+    }
+
+    pub fn stop_replay(&mut self) -> Option<Replay> {
+        let tick = self.tick;
+        let replay = self.recorder.take()?.finish(tick);
+        self.last_replay = Some(replay.clone());
+        Some(replay)
+    }
+
+    // 記録中ならここまでの分、止めた後なら最後の記録
+    pub fn recorded_replay(&self) -> Option<Replay> {
+        match &self.recorder {
+            Some(recorder) => Some(recorder.clone().finish(self.tick)),
+            None => self.last_replay.clone(),
+        }
+    }
+
+    fn record_replay(&mut self, input: ReplayInput) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(self.tick, input);
+        }
+    }
+
+    // 書き込み用のスレッドに渡して待たない（ゲームループを止めない）
+    fn persist_state(&self) {
+        if self.simulation {
+            return;
+        }
+        if let Ok(job) = self.save_job(Self::active_slot()) {
+            save_writer::submit(job);
+        }
+    }
+
+    // 書き終わるまで待つ（書けたかどうかで続きが変わる操作用）
+    fn write_slot(&self, slot: u32) -> Result<(), GameError> {
+        let job = self.save_job(slot)?;
+        save_writer::write(job).map_err(GameError::storage)
+    }
+
+    // 本体・署名・ジャーナルの消去を 1 回の書き込みにまとめる（本体が書けたときだけ残りを行う）
+    fn save_job(&self, slot: u32) -> Result<SaveJob, GameError> {
+        let path = Self::slot_file_path(slot, "json")
+            .ok_or_else(|| GameError::storage("Failed to resolve save path"))?;
+        let json = serde_json::to_string(self).map_err(GameError::storage)?;
+        let bytes = encryption::seal_file(&path, json.as_bytes()).map_err(GameError::storage)?;
+        let mut job = SaveJob::new(path, bytes).with_backups();
+        if let Some(signature_path) = Self::slot_file_path(slot, "sig") {
+            job = job.with_companion(
+                signature_path,
+                integrity::sign(json.as_bytes()).into_bytes(),
+            );
+        }
+        if let Some(journal_path) = Self::slot_file_path(slot, "journal") {
+            job = job.clearing(journal_path);
+        }
+        // 署名付きのセーブが書けていれば印を残す。以降は署名ファイルが無ければ改ざんとみなす
+        let signature = Self::slot_file_path(slot, "sig");
+        if let (Some(signature), Some(marker)) = (signature, Self::slot_file_path(slot, "signed")) {
+            if signature.exists() && !marker.exists() {
+                save_writer::submit(SaveJob::new(marker, b"1".to_vec()));
+            }
+        }
+        Ok(job)
+    }
+
+    // 別の PC へ持ち出すための文字列（サーバーを介さない引き継ぎ）
+    pub fn export_save(&self) -> Result<String, GameError> {
+        let json = serde_json::to_string(self).map_err(GameError::internal)?;
+        save_code::encode(json, unix_timestamp())
+    }
+
+    // 検証した状態をまずディスクに書き、書けたときだけ遊んでいる状態と入れ替える
+    // （古い形式のセーブに無い項目は読み込み時の既定値で補う）
+    pub fn import_save(&mut self, code: &str) -> Result<(), GameError> {
+        let json = save_code::decode(code)?;
+        let imported: Self = serde_json::from_str(&json).map_err(|_| GameError::SaveCodeCorrupt)?;
+        let mut imported = Self::prepare_loaded(imported);
+        imported.unit_caps = self.unit_caps;
+        self.checkpoint(CheckpointReason::ImportSave);
+        self.sessions.end(unix_timestamp());
+        imported.write_slot(Self::active_slot())?;
+        *self = imported;
+        Ok(())
+    }
+
+    // 取り返しのつかない操作の直前の状態を残す（書けなくても操作は止めない）
+    // 書けなかったことはセーブと同じく保存先の状態として画面に出るので、ここでは出さない
+    fn checkpoint(&self, reason: CheckpointReason) {
+        if self.simulation {
+            return;
+        }
+        if let Ok(json) = serde_json::to_vec(self) {
+            let _ = checkpoint::write(reason, &json);
+        }
+    }
+
+    // チェックポイントの状態に戻す。戻す前の状態もチェックポイントに残す
+    pub fn restore_checkpoint(&mut self, id: &str) -> Result<(), GameError> {
+        let (contents, signed) = checkpoint::read(id)?;
+        let mut restored: Self = serde_json::from_slice(&contents).map_err(GameError::storage)?;
+        if !signed {
+            restored
+                .integrity
+                .mark_tampered("checkpoint signature mismatch");
+        }
+        let mut restored = Self::prepare_loaded(restored);
+        restored.unit_caps = self.unit_caps;
+        self.checkpoint(CheckpointReason::Restore);
+        self.sessions.end(unix_timestamp());
+        restored.write_slot(Self::active_slot())?;
+        *self = restored;
+        Ok(())
+    }
+
+    pub fn flush_save(&self) {
+        self.persist_state();
+    }
+
+    fn add_coins(&mut self, amount: u64) {
+        self.coins = self.coins.saturating_add(amount);
+        self.sessions.record_coins(amount);
+        self.activity.coins_earned = self.activity.coins_earned.saturating_add(amount);
+    }
+
+    // 撃破1体あたりのコイン
+    fn coin_bonus(&self) -> f64 {
+        (1.0 + self.upgrades.coin_rate as f64 / 100.0)
+            * self.prestige.coin_multiplier()
+            * self.rules.coin_multiplier as f64
+    }
+
+    // スリープ中などシミュレーションしなかった時間を、撃破報酬の概算で埋め合わせる
+    pub fn apply_offline_progress(&mut self, seconds: f32) -> Option<OfflineProgress> {
+        let seconds = seconds.clamp(0.0, offline::MAX_OFFLINE_SECS);
+        self.record_replay(ReplayInput::Offline { seconds });
+        let enemies = (seconds as f64 / self.rules.spawn_interval(self.stage) as f64
+            * offline::OFFLINE_EFFICIENCY)
+            .floor() as u64;
+        if enemies == 0 {
+            return None;
+        }
+        let coins_before = self.coins;
+        self.add_fractional_coins(enemies as f64 * self.coin_bonus());
+        self.achievements.stats.enemies_defeated += enemies;
+        self.activity.enemies_killed = self
+            .activity
+            .enemies_killed
+            .saturating_add(enemies.min(u32::MAX as u64) as u32);
+        self.quests.record(QuestMetric::EnemiesDefeated, enemies);
+        self.persist_state();
+        Some(OfflineProgress {
+            seconds,
+            enemies_defeated: enemies,
+            coins: self.coins - coins_before,
+        })
+    }
+
+    // 過去の打鍵数を一度だけコインに換算する（上限を超える分は切り捨て）
+    pub fn import_typing_history(
+        &mut self,
+        history: &TypingHistory,
+    ) -> Result<TypingImport, GameError> {
+        if self.typing_import.is_some() {
+            return Err(GameError::TypingHistoryAlreadyImported);
+        }
+        let keystrokes = history
+            .keystrokes
+            .min(typing_import::MAX_IMPORTED_KEYSTROKES);
+        let coins = keystrokes / typing_import::KEYSTROKES_PER_COIN;
+        self.add_coins(coins);
+        let record = TypingImport {
+            source: history.source,
+            imported_at: unix_timestamp(),
+            keystrokes,
+            coins,
+        };
+        self.typing_import = Some(record.clone());
+        self.persist_state();
+        Ok(record)
+    }
+
+    pub fn unit_caps(&self) -> UnitCaps {
+        self.unit_caps
+    }
+
+    // 上限を下げたときは超えている分を新しい順に出現し直したものとして扱う
+    pub fn set_unit_caps(&mut self, caps: UnitCaps) {
+        self.unit_caps = caps;
+        let player_excess = self
+            .player_units
+            .split_off(self.player_units.len().min(caps.max_player_units));
+        let enemy_excess = self
+            .enemy_units
+            .split_off(self.enemy_units.len().min(caps.max_enemy_units));
+        for unit in player_excess.into_iter().chain(enemy_excess) {
+            self.admit_unit(unit);
+        }
+        self.player_units.shrink_to(caps.max_player_units);
+        self.enemy_units.shrink_to(caps.max_enemy_units);
+    }
+
+    // 上限に達していたら、設定に従って最も古い小型ユニットをコインに換えるか既存のユニットに合流させる
+    fn admit_unit(&mut self, unit: Unit) {
+        let coin_bonus = self.coin_bonus();
+        let (units, cap) = if unit.is_player {
+            (&mut self.player_units, self.unit_caps.max_player_units)
+        } else {
+            (&mut self.enemy_units, self.unit_caps.max_enemy_units)
+        };
+        let mut despawned = 0;
+        if self.unit_caps.overflow == OverflowPolicy::Despawn {
+            while units.len() >= cap {
+                let oldest_small = units
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, u)| u.unit_type == UnitType::Small)
+                    .min_by_key(|(_, u)| u.id)
+                    .map(|(index, _)| index);
+                // 小型がいなければ合流にまかせる
+                let Some(index) = oldest_small else {
+                    break;
+                };
+                units.remove(index);
+                despawned += 1;
+            }
+        }
+        push_capped(units, unit, cap);
+        if despawned > 0 {
+            self.add_fractional_coins(despawned as f64 * coin_bonus);
+        }
+    }
+
+    // ユニットとゲーム状態の使用メモリの概算（確保済みの容量で数える）
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let unit_bytes = std::mem::size_of::<Unit>();
+        let estimated_bytes = std::mem::size_of::<Self>()
+            + (self.player_units.capacity() + self.enemy_units.capacity()) * unit_bytes;
+        MemoryUsage {
+            player_units: self.player_units.len(),
+            enemy_units: self.enemy_units.len(),
+            caps: self.unit_caps,
+            unit_bytes,
+            estimated_bytes,
+        }
+    }
+
+    // 1コイン未満の端数を貯めておき、整数分だけ付与する
+    fn add_fractional_coins(&mut self, amount: f64) {
+        self.battle.record_coins(amount);
+        self.coin_fraction += amount;
+        let whole = self.coin_fraction.floor();
+        self.coin_fraction -= whole;
+        self.add_coins(whole as u64);
+    }
+
+    // ボスやステージクリアの報酬は協力プレイなら 2 人で折半する
+    fn add_shared_coins(&mut self, amount: f64) {
+        if self.coop.enabled {
+            self.add_fractional_coins(amount / 2.0);
+            self.battle.record_coins(amount / 2.0);
+            self.coop.add_coins(amount / 2.0);
+        } else {
+            self.add_fractional_coins(amount);
+        }
+    }
+
+    pub fn set_coop(&mut self, enabled: bool) {
+        if self.coop.enabled != enabled {
+            self.record_replay(ReplayInput::SetCoop { enabled });
+        }
+        self.coop.set_enabled(enabled);
+    }
+
+    pub fn coop_status(&self) -> CoopStatus {
+        self.coop.status(self.coins)
+    }
+
+    // エネルギーモードでは入力はエネルギーになり、出すユニットは選んで呼び出す
+    pub fn summon_with_energy(&mut self, unit_type: UnitType) -> Result<(), GameError> {
+        self.energy.spend(unit_type)?;
+        self.spawn_unit(unit_type);
+        Ok(())
+    }
+
+    pub fn record_input(&mut self, clicks: u32, types: u32) {
+        if clicks + types > 0 {
+            self.record_replay(ReplayInput::Input { clicks, types });
+        }
+        let now = unix_timestamp();
+        self.sessions.record_input(now, clicks, types);
+        self.sessions.check_afk(now);
+        self.achievements.stats.clicks += clicks as u64;
+        self.achievements.stats.keystrokes += types as u64;
+        self.combo.record_inputs(clicks + types);
+        self.activity.actions += clicks + types;
+        self.activity.keys += types;
+        self.quests.record(QuestMetric::Clicks, clicks as u64);
+        self.quests.record(QuestMetric::Keystrokes, types as u64);
+    }
+
+    pub fn take_activity(&mut self) -> Activity {
+        std::mem::take(&mut self.activity)
+    }
+
+    // 集中ブロックによる入力補正。ブロックが完了した tick は第3要素が true
+    pub fn apply_focus(&mut self, clicks: u32, types: u32, distracted: bool) -> (u32, u32, bool) {
+        let result = self
+            .focus
+            .apply(unix_timestamp(), clicks, types, distracted);
+        if result.2 {
+            self.persist_state();
+        }
+        result
+    }
+
+    pub fn start_focus(&mut self, duration_secs: i64) -> Result<(), GameError> {
+        self.focus.start(unix_timestamp(), duration_secs)?;
+        self.persist_state();
+        Ok(())
+    }
+
+    pub fn stop_focus(&mut self) {
+        self.focus.stop(unix_timestamp());
+        self.persist_state();
+    }
+
+    // アプリ終了時にセッションを閉じて保存
+    pub fn end_session(&mut self) {
+        self.sessions.end(unix_timestamp());
+        self.persist_state();
+    }
+
+    pub fn spawn_unit(&mut self, unit_type: UnitType) {
+        self.spawn_unit_for(unit_type, CoopPlayer::A);
+    }
+
+    // 協力プレイで入力した側のユニットとして出す
+    pub fn spawn_unit_for(&mut self, unit_type: UnitType, player: CoopPlayer) {
+        self.record_replay(ReplayInput::Spawn { unit_type, player });
+        self.spawn_player_unit(unit_type, UnitAbility::Melee, player);
+
+        match unit_type {
+            UnitType::Small => self.type_count += 1,
+            UnitType::Medium => self.click_count += 1,
+            _ => {}
+        }
+    }
+
+    // コインを払ってユニットを呼び出す（ステージ内で呼ぶたびに値上がりし、種別ごとにクールダウンがある）
+    pub fn summon_unit(&mut self, unit_type: UnitType) -> Result<u64, GameError> {
+        let cost = self.summons.check(unit_type, self.stage, self.coins)?;
+        self.record_replay(ReplayInput::Summon { unit_type });
+        self.coins -= cost;
+        self.summons.record(unit_type);
+        self.spawn_player_unit(unit_type, UnitAbility::Melee, CoopPlayer::A);
+        self.record(JournalEvent::Summon { unit_type });
+        Ok(cost)
+    }
+
+    // 対戦用の軍勢（ID と名前はサーバーから取得したときに入る）
+    pub fn army_snapshot(&self) -> ArmySnapshot {
+        ArmySnapshot {
+            player_id: String::new(),
+            player_name: String::new(),
+            upgrades: self.upgrades.clone(),
+            prestige_attack: self.prestige.upgrades.attack,
+            prestige_hp: self.prestige.upgrades.hp,
+            created_at: unix_timestamp(),
+        }
+    }
+
+    pub fn summon_status(&self) -> Vec<SummonStatus> {
+        self.summons.status(self.stage)
+    }
+
+    // 消耗品を買い、払ったコインを返す
+    pub fn buy_item(&mut self, kind: ItemKind, quantity: u32) -> Result<u64, GameError> {
+        let cost = self
+            .items
+            .check_purchase(kind, quantity, self.stage, self.coins)?;
+        self.record_replay(ReplayInput::BuyItem { kind, quantity });
+        self.coins -= cost;
+        self.items.add(kind, quantity);
+        self.persist_state();
+        Ok(cost)
+    }
+
+    // 空爆と凍結は次の update で効果が出る（修理キットはその場で回復）
+    pub fn use_item(&mut self, kind: ItemKind) -> Result<(), GameError> {
+        self.items.take(kind)?;
+        self.record_replay(ReplayInput::UseItem { kind });
+        match kind {
+            ItemKind::Airstrike => self.items.queue_airstrike(),
+            ItemKind::FreezeBomb => self.items.freeze(),
+            ItemKind::RepairKit => {
+                self.player_base_hp = (self.player_base_hp
+                    + self.max_player_base_hp * item::REPAIR_RATIO)
+                    .min(self.max_player_base_hp);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn item_status(&self) -> Vec<ItemStatus> {
+        self.items.status(self.stage)
+    }
+
+    // 特殊キーによる特殊ユニットの出撃
+    pub fn spawn_special(&mut self, ability: UnitAbility) {
+        self.record_replay(ReplayInput::Special { ability });
+        self.spawn_player_unit(ability.base_type(), ability, CoopPlayer::A);
+    }
+
+    fn spawn_player_unit(&mut self, unit_type: UnitType, ability: UnitAbility, owner: CoopPlayer) {
+        let (base_hp, base_attack, base_speed) = unit_base_stats(unit_type);
+
+        // アップグレード適用
+        let (hp_bonus, attack_bonus, speed_bonus) = self.upgrades.unit_bonus(unit_type);
+
+        let combo_multiplier = self.combo.multiplier();
+        let (hp_ratio, attack_ratio, speed_ratio) = ability.stat_multipliers();
+        let hp = base_hp
+            * hp_ratio
+            * (1.0 + hp_bonus as f32 / 100.0)
+            * self.prestige.hp_multiplier()
+            * combo_multiplier;
+        let attack = base_attack
+            * attack_ratio
+            * (1.0 + attack_bonus as f32 / 100.0)
+            * self.prestige.attack_multiplier()
+            * combo_multiplier;
+        let speed = base_speed * speed_ratio * (1.0 + speed_bonus as f32 / 100.0);
+
+        let unit = Unit {
+            id: self.next_unit_id,
+            unit_type,
+            position: self.barracks.as_ref().map_or(0.0, |b| b.position),
+            hp,
+            max_hp: hp,
+            attack,
+            speed,
+            is_player: true,
+            target_id: None,
+            knockback_velocity: 0.0,
+            knockback_time: 0.0,
+            knockback_total: 0.0,
+            attack_cooldown: 0.0,
+            invulnerable_time: 0.0,
+            stealthed: false,
+            is_flying: false,
+            ability,
+            ability_cooldown: 0.0,
+            rallying: self.rally.is_some(),
+            lane: self.pick_player_lane(),
+            owner,
+            kind: EnemyKind::Standard,
+            order: None,
+        };
+        self.admit_unit(unit);
+        self.activity.units_spawned += 1;
+        match unit_type {
+            UnitType::Small => self.quests.record(QuestMetric::SmallUnits, 1),
+            UnitType::Medium => self.quests.record(QuestMetric::MediumUnits, 1),
+            UnitType::Large | UnitType::Hero => {}
+        }
+
+        self.next_unit_id += 1;
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        stage::lane_count(self.stage)
+    }
+
+    // 味方は敵の数に対して手薄なレーンへ出撃する
+    fn pick_player_lane(&self) -> u8 {
+        let lanes = self.lane_count();
+        let mut pressure = vec![0i64; lanes as usize];
+        let last = lanes.saturating_sub(1) as usize;
+        for enemy in &self.enemy_units {
+            pressure[(enemy.lane as usize).min(last)] += 1;
+        }
+        for unit in &self.player_units {
+            pressure[(unit.lane as usize).min(last)] -= 1;
+        }
+        let mut best = 0;
+        for lane in 1..pressure.len() {
+            if pressure[lane] > pressure[best] {
+                best = lane;
+            }
+        }
+        best as u8
+    }
+
+    fn spawn_enemy(&mut self) {
+        self.spawn_enemy_as(self.farming);
+    }
+
+    // weak なら稼ぎ用の弱い敵
+    fn spawn_enemy_as(&mut self, weak: bool) {
+        let lanes = self.lane_count();
+        let (stage_multiplier, (unit_type, base_hp, base_attack, base_speed)) = if weak {
+            stage::farming_enemy(self.stage)
+        } else {
+            (
+                stage::stat_multiplier(self.stage),
+                stage::pick_enemy(self.rng.gen()),
+            )
+        };
+        let stage_multiplier = stage_multiplier * self.rules.enemy_scaling;
+        // 稼ぎ中は標準の敵だけ
+        let kind = if weak {
+            EnemyKind::Standard
+        } else {
+            enemy_kind::pick(self.stage, self.rng.gen())
+        };
+        let (hp_multiplier, attack_multiplier, speed_multiplier) = kind.stat_multipliers();
+        let hp = base_hp * stage_multiplier * hp_multiplier;
+        let stealthed = !weak && self.rng.gen_bool(stage::stealth_chance() as f64);
+        let is_flying = !weak
+            && unit_type != UnitType::Large
+            && self.rng.gen_bool(stage::flying_chance(self.stage) as f64);
+        let lane = self.rng.gen_range(0..lanes);
+
+        // 群れは同じレーンに少しずつ後ろへずらして並ぶ
+        for i in 0..kind.group_size() {
+            let unit = Unit {
+                id: self.next_unit_id,
+                unit_type,
+                position: 1000.0 + i as f32 * enemy_kind::SWARM_SPACING,
+                hp,
+                max_hp: hp,
+                attack: base_attack * stage_multiplier * attack_multiplier,
+                speed: base_speed * speed_multiplier,
+                is_player: false,
+                target_id: None,
+                knockback_velocity: 0.0,
+                knockback_time: 0.0,
+                knockback_total: 0.0,
+                attack_cooldown: 0.0,
+                invulnerable_time: 0.0,
+                stealthed,
+                is_flying,
+                ability: UnitAbility::Melee,
+                ability_cooldown: 0.0,
+                rallying: false,
+                lane,
+                owner: CoopPlayer::A,
+                kind,
+                order: None,
+            };
+            self.admit_unit(unit);
+
+            self.next_unit_id += 1;
+        }
+    }
+
+    // 大型ユニットと前線基地の周囲にいる隠密状態の敵を暴く
+    fn reveal_stealthed(&mut self) {
+        // (レーン, 位置)。前線基地はすべてのレーンを見張る
+        let detectors: Vec<(Option<u8>, f32)> = self
+            .player_units
+            .iter()
+            .filter(|unit| matches!(unit.unit_type, UnitType::Large))
+            .map(|unit| (Some(unit.lane), unit.position))
+            .chain(self.barracks.as_ref().map(|b| (None, b.position)))
+            .collect();
+        for enemy in self.enemy_units.iter_mut().filter(|e| e.stealthed) {
+            if detectors.iter().any(|&(lane, position)| {
+                lane.is_none_or(|lane| lane == enemy.lane)
+                    && (enemy.position - position).abs() <= DETECTION_RANGE
+            }) {
+                enemy.stealthed = false;
+            }
+        }
+    }
+
+    // ボスステージなら開始時にボスを1体出現させる
+    fn spawn_boss(&mut self) {
+        self.boss = None;
+        if !boss::is_boss_stage(self.stage) {
+            return;
+        }
+        let (hp, attack, speed) = boss::stats(self.stage);
+        let (hp, attack) = (
+            hp * self.rules.enemy_scaling,
+            attack * self.rules.enemy_scaling,
+        );
+        self.enemy_units.push(Unit {
+            id: self.next_unit_id,
+            unit_type: UnitType::Large,
+            position: FIELD_LENGTH,
+            hp,
+            max_hp: hp,
+            attack,
+            speed,
+            is_player: false,
+            target_id: None,
+            knockback_velocity: 0.0,
+            knockback_time: 0.0,
+            knockback_total: 0.0,
+            attack_cooldown: 0.0,
+            invulnerable_time: 0.0,
+            stealthed: false,
+            is_flying: false,
+            ability: UnitAbility::Melee,
+            ability_cooldown: 0.0,
+            rallying: false,
+            // ボスは中央のレーンに立ち、どのレーンからも狙われる
+            lane: self.lane_count() / 2,
+            owner: CoopPlayer::A,
+            kind: EnemyKind::Standard,
+            order: None,
+        });
+        self.boss = Some(BossState::new(self.next_unit_id, self.stage));
+        self.next_unit_id += 1;
+    }
+
+    // ボスの形態変化と周囲の味方への範囲攻撃
+    fn update_boss(&mut self, delta: f32) {
+        let Some(boss) = self.boss.as_mut() else {
+            return;
+        };
+        let Some(boss_unit) = self.enemy_units.iter().find(|e| e.id == boss.unit_id) else {
+            return;
+        };
+        let boss_position = boss_unit.position;
+        if !boss.tick(delta, boss_unit.hp, boss_unit.max_hp) {
+            return;
+        }
+        for unit in &mut self.player_units {
+            if (unit.position - boss_position).abs() > boss::AOE_RADIUS {
+                continue;
+            }
+            unit.hp -= unit.max_hp * boss::AOE_DAMAGE_RATIO;
+            unit.target_id = None;
+            unit.knockback_velocity = -boss::AOE_KNOCKBACK / boss::AOE_KNOCKBACK_SECS;
+            unit.knockback_time = boss::AOE_KNOCKBACK_SECS;
+            unit.knockback_total = boss::AOE_KNOCKBACK_SECS;
+        }
+        let before = self.player_units.len();
+        self.player_units.retain(|u| u.hp > 0.0);
+        self.battle
+            .record_losses((before - self.player_units.len()) as u32);
+    }
+
+    // 襲撃の予告と出現（放置中や凍結中も時間は進み、クリア演出中とボス戦中は予告を待つ）
+    fn update_invasion(&mut self, delta: f32) {
+        let ready = !self.clear_sequence.is_active() && self.boss.is_none();
+        let stage = self.stage;
+        let rng = &mut self.rng;
+        match self.invasion.tick(delta, stage, ready, || rng.gen()) {
+            Some(InvasionStep::Warn) => self.notifications.push(Notification::invasion_warning(
+                invasion::enemy_count(stage),
+                invasion::WARNING_SECS,
+            )),
+            // 稼ぎ中でもそのステージの通常の強さで来る
+            Some(InvasionStep::Arrive(enemies)) => {
+                let first_id = self.next_unit_id;
+                for _ in 0..enemies {
+                    self.spawn_enemy_as(false);
+                }
+                self.invasion.begin(stage, first_id, self.next_unit_id);
+            }
+            None => {}
+        }
+    }
+
+    fn invaders_remaining(&self) -> usize {
+        self.enemy_units
+            .iter()
+            .filter(|e| self.invasion.is_invader(e.id))
+            .count()
+    }
+
+    pub fn invasion_status(&self) -> InvasionStatus {
+        self.invasion.status(self.invaders_remaining())
+    }
+
+    pub fn boss_status(&self) -> Option<BossStatus> {
+        let boss = self.boss.as_ref()?;
+        let unit = self.enemy_units.iter().find(|e| e.id == boss.unit_id)?;
+        Some(BossStatus {
+            name: boss.name.clone(),
+            hp: unit.hp.max(0.0),
+            max_hp: unit.max_hp,
+            phase: boss.phase,
+        })
+    }
+
+    // 固定の刻みで 1 回進める（リプレイとテストはこちらで進める）
+    pub fn step(&mut self) {
+        self.update(STEP_SECS);
+        self.tick += 1;
+    }
+
+    // 経過時間を固定の刻みに分けて進める。刻みに満たない端数は次回に持ち越す
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.step_carry += delta.max(0.0);
+        let mut steps = 0;
+        while self.step_carry >= STEP_SECS {
+            self.step_carry -= STEP_SECS;
+            self.step();
+            steps += 1;
+        }
+        steps
+    }
+
+    // 入力を反映してから delta 秒進める。集中ブロックが完了した回は true
+    // 協力プレイではキーボードとマウスでそれぞれの担当のユニットになる
+    // エネルギーモードでは入力を貯め、ホットキーで選んだユニットを呼び出す
+    // ホイールとマウス移動で貯まった分は小型ユニット（マウス担当）になる
+    pub fn run_tick(&mut self, input: TickInput, delta: f32) -> bool {
+        self.set_idle(input.idle);
+        self.record_input(input.clicks, input.types);
+        let (clicks, types, focus_completed) =
+            self.apply_focus(input.clicks, input.types, input.distracted);
+        if input.energy_mode {
+            self.energy.charge(types + input.pointer_units, clicks);
+            for unit_type in input.summons {
+                let _ = self.summon_with_energy(unit_type);
+            }
+        } else {
+            let typist = self.coop.player_for(InputSource::Keyboard);
+            let clicker = self.coop.player_for(InputSource::Mouse);
+            for _ in 0..types {
+                self.spawn_unit_for(UnitType::Small, typist);
+            }
+            for _ in 0..clicks {
+                self.spawn_unit_for(UnitType::Medium, clicker);
+            }
+            for _ in 0..input.pointer_units {
+                self.spawn_unit_for(UnitType::Small, clicker);
+            }
+        }
+        for ability in input.abilities {
+            self.spawn_special(ability);
+        }
+        if input.reinforcement {
+            self.spawn_unit(UnitType::Large);
+        }
+        // 固定の刻みで進め、リプレイで再現できるようにする
+        self.advance(delta);
+        focus_completed
+    }
+
+    // 回ごとの入力を input で作りながら ticks 回進める（シミュレーター用）
+    pub fn run_ticks(
+        &mut self,
+        ticks: u64,
+        delta: f32,
+        mut input: impl FnMut(&GameState, u64) -> TickInput,
+    ) {
+        for tick in 0..ticks {
+            let next = input(self, tick);
+            self.run_tick(next, delta);
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        // クリア演出中は遅く進め、演出が終わったら次のステージへ
+        if self.clear_sequence.tick(delta) {
+            self.finish_stage_clear();
+        }
+        let delta = delta * self.clear_sequence.time_scale();
+
+        // 敵のスポーン（凍結中は止まる）
+        let frozen = self.items.is_frozen();
+        if !self.idle && !frozen {
+            self.enemy_spawn_timer += delta;
+        }
+        let enemy_personality = stage::personality(self.stage);
+        if self.enemy_spawn_timer >= self.rules.spawn_interval(self.stage) {
+            for _ in 0..enemy_personality.spawn_burst() {
+                self.spawn_enemy();
+            }
+            self.enemy_spawn_timer = 0.0;
+        }
+        self.update_invasion(delta);
+
+        self.battle.tick(delta);
+        self.combo.tick(delta);
+        self.summons.tick(delta);
+        self.items.tick(delta);
+        self.reveal_stealthed();
+        self.update_boss(delta);
+        // ボスが生きている間は敵基地にダメージが通らない
+        let base_shielded = self.boss.is_some();
+
+        // ユニットの移動と戦闘
+        let mut units_to_remove: Vec<u32> = Vec::new();
+        let mut coins_earned = 0.0;
+        let mut coins_earned_b = 0.0;
+        let mut kills = 0;
+        let mut deaths = 0;
+        let mut final_blow = None;
+        let base_hp_before = self.player_base_hp;
+
+        // 空爆は隠密状態や飛行中の敵も含めて全ての敵に当たる
+        let airstrikes = self.items.take_airstrikes();
+        if airstrikes > 0 {
+            let boss_id = self.boss.as_ref().map(|b| b.unit_id);
+            let coin_bonus = self.coin_bonus();
+            for _ in 0..airstrikes {
+                for enemy in &mut self.enemy_units {
+                    let ratio = if Some(enemy.id) == boss_id {
+                        item::BOSS_AIRSTRIKE_RATIO
+                    } else {
+                        item::AIRSTRIKE_RATIO
+                    };
+                    let damage = enemy.max_hp * ratio * enemy.kind.damage_taken();
+                    self.raid_damage += damage as f64;
+                    if enemy.take_hit(damage, 1.0) {
+                        units_to_remove.push(enemy.id);
+                        kills += 1;
+                        coins_earned += coin_bonus;
+                    }
+                }
+            }
+        }
+
+        // 士気による味方の攻撃力・速度の補正
+        let attack_multiplier = self.morale.attack_multiplier();
+        let speed_multiplier = self.morale.speed_multiplier();
+
+        // 集結地点に規定数が揃ったら一斉に進軍させる
+        if let Some(point) = &self.rally {
+            let gathered = self
+                .player_units
+                .iter()
+                .filter(|u| u.rallying && (u.position - point.position).abs() <= rally::HOLD_RADIUS)
+                .count();
+            if gathered >= point.threshold as usize {
+                self.release_rallying();
+            }
+        }
+        let rally_position = self.rally.as_ref().map(|point| point.position);
+        let hold_position = self
+            .stance
+            .hold_position(self.barracks.as_ref().map(|b| b.position));
+
+        // 狙える敵（隠密状態を除く）のレーン別の位置索引とID索引。対空攻撃を持たない味方は地上の敵だけを狙う
+        let lanes = self.lane_count();
+        let mut enemy_index = LaneIndex::new(&self.enemy_units, lanes, |e| !e.stealthed);
+        let mut ground_index =
+            LaneIndex::new(&self.enemy_units, lanes, |e| !e.stealthed && !e.is_flying);
+        let enemy_slots = spatial::slots(&self.enemy_units);
+        if let Some(&index) = self.boss.as_ref().and_then(|b| enemy_slots.get(&b.unit_id)) {
+            enemy_index.insert_everywhere(&self.enemy_units, index);
+            ground_index.insert_everywhere(&self.enemy_units, index);
+        }
+
+        // ターゲット検出とユニット移動
+        for i in 0..self.player_units.len() {
+            let unit = &mut self.player_units[i];
+
+            unit.tick_timers(delta);
+
+            // ノックバック処理（吹き飛ばし）
+            if unit.knockback_time > 0.0 {
+                unit.position += unit.knockback_velocity * delta;
+                unit.knockback_time = (unit.knockback_time - delta).max(0.0);
+                if unit.knockback_time == 0.0 {
+                    unit.knockback_velocity = 0.0;
+                    unit.knockback_total = 0.0;
+                }
+            }
+
+            // ターゲットが有効かチェック
+            if let Some(target_id) = unit.target_id {
+                if !enemy_slots.contains_key(&target_id) {
+                    unit.target_id = None;
+                }
+            }
+
+            // 手動の命令は期限が切れたら AI に戻す
+            if unit.order.as_mut().is_some_and(|order| !order.tick(delta)) {
+                unit.order = None;
+            }
+            let destination = unit.order.and_then(|order| order.destination());
+            let arrived = destination
+                .is_some_and(|point| (point - unit.position).abs() <= orders::ARRIVE_RADIUS);
+
+            // 集結中と移動先に着いた後は近くの敵にだけ応戦し、離れた敵は追わない
+            if unit.rallying || arrived {
+                if let Some(&index) = unit.target_id.and_then(|id| enemy_slots.get(&id)) {
+                    if (self.enemy_units[index].position - unit.position).abs()
+                        > rally::DEFEND_RADIUS
+                    {
+                        unit.target_id = None;
+                    }
+                }
+            }
+
+            // 攻撃の命令では指定の敵だけを狙い、倒したら命令を終える。移動中は応戦しない
+            match unit.order.map(|order| order.order) {
+                Some(Order::Attack { target_id }) => {
+                    if enemy_slots.contains_key(&target_id) {
+                        unit.target_id = Some(target_id);
+                    } else {
+                        unit.order = None;
+                    }
+                }
+                Some(Order::Move { .. }) if !arrived => unit.target_id = None,
+                _ => {}
+            }
+
+            // ターゲットを探す（隠密状態の敵は対象外）
+            if unit.target_id.is_none() && (destination.is_none() || arrived) {
+                let targets = if unit.hits_air() {
+                    enemy_index.lane(unit.lane)
+                } else {
+                    ground_index.lane(unit.lane)
+                };
+                let nearest = if unit.rallying || arrived {
+                    targets.nearest_within(unit.position, rally::DEFEND_RADIUS)
+                } else {
+                    targets.nearest(unit.position)
+                };
+                if let Some(index) = nearest {
+                    unit.target_id = Some(self.enemy_units[index].id);
                 }
             }
 
             // 移動または攻撃
             if let Some(target_id) = unit.target_id {
-                if let Some(enemy) = self.enemy_units.iter_mut().find(|e| e.id == target_id) {
+                if let Some(&index) = enemy_slots.get(&target_id) {
+                    let enemy = &mut self.enemy_units[index];
                     let distance = (enemy.position - unit.position).abs();
-                    if distance <= 10.0 {
+                    if distance <= unit.ability.attack_range() {
                         // 攻撃範囲内
-                        enemy.hp -= unit.attack * delta;
-                        if enemy.hp <= 0.0 {
-                            units_to_remove.push(enemy.id);
-                            let coin_bonus = 1.0 + self.upgrades.coin_rate as f32 / 100.0;
-                            // 敵撃破報酬を1～3コインに削減
-                            self.coins += (1.0 * coin_bonus).max(1.0) as u32;
+                        if unit.attack_cooldown <= 0.0 {
+                            unit.attack_cooldown = ATTACK_INTERVAL;
+                            let damage = unit.attack
+                                * attack_multiplier
+                                * ATTACK_INTERVAL
+                                * enemy.kind.damage_taken();
+                            let killed = enemy.take_hit(damage, 1.0);
+                            self.battle
+                                .record_damage(unit.id, unit.unit_type, damage, killed);
+                            self.raid_damage += damage as f64;
+                            if killed {
+                                let owner = unit.owner;
+                                units_to_remove.push(enemy.id);
+                                kills += 1;
+                                // 敵撃破報酬を1～3コインに削減（端数は持ち越し）
+                                // 協力プレイでは倒したユニットを出した側の財布に入る
+                                if self.coop.enabled {
+                                    self.coop.record_kill(owner);
+                                }
+                                match owner {
+                                    CoopPlayer::B if self.coop.enabled => {
+                                        coins_earned_b += 1.0 * self.coin_bonus()
+                                    }
+                                    _ => coins_earned += 1.0 * self.coin_bonus(),
+                                }
+                            }
                         }
                     } else {
                         // 移動
@@ -385,24 +2227,68 @@ impl GameState {
                         } else {
                             -1.0
                         };
-                        unit.position += direction * unit.speed * delta;
+                        unit.position += direction * unit.speed * speed_multiplier * delta;
                     }
                 }
+            } else if let Some(point) = destination {
+                // 命令された位置へ移動して待機
+                let offset = point - unit.position;
+                let step = unit.speed * speed_multiplier * delta;
+                unit.position += offset.signum() * step.min(offset.abs());
+            } else if let Some(point) = rally_position.filter(|_| unit.rallying) {
+                // 集結地点へ移動して待機
+                let offset = point - unit.position;
+                if offset.abs() > rally::HOLD_RADIUS {
+                    unit.position += offset.signum() * unit.speed * speed_multiplier * delta;
+                }
+            } else if let Some(point) = hold_position {
+                // 態勢に応じた位置で待機
+                let offset = point - unit.position;
+                if offset.abs() > stance::HOLD_TOLERANCE {
+                    let step = unit.speed * speed_multiplier * delta;
+                    unit.position += offset.signum() * step.min(offset.abs());
+                }
             } else {
                 // ターゲットがいない場合は敵基地へ移動
                 if unit.position < 1000.0 {
-                    unit.position += unit.speed * delta;
-                } else {
+                    unit.position += unit.speed * speed_multiplier * delta;
+                } else if !base_shielded {
                     // 敵基地を攻撃
-                    self.enemy_base_hp -= unit.attack * delta;
+                    let damage = unit.attack * attack_multiplier * delta;
+                    if self.enemy_base_hp > 0.0 && self.enemy_base_hp <= damage {
+                        final_blow = Some(FinalBlow {
+                            stage: self.stage,
+                            attacker_id: unit.id,
+                            attacker_type: unit.unit_type,
+                            attacker_ability: unit.ability,
+                            attacker_position: unit.position,
+                            defender: "enemy_base",
+                            defender_max_hp: self.max_enemy_base_hp,
+                            damage,
+                        });
+                    }
+                    self.enemy_base_hp -= damage;
+                    self.battle
+                        .record_damage(unit.id, unit.unit_type, damage, false);
+                    self.raid_damage += damage as f64;
                 }
             }
         }
 
-        // 敵ユニットの移動と戦闘
-        for i in 0..self.enemy_units.len() {
+        // 味方の位置索引（タンクは周囲の敵の狙いを引きつける）
+        let player_index = LaneIndex::new(&self.player_units, lanes, |_| true);
+        let taunt_index = LaneIndex::new(&self.player_units, lanes, |unit| {
+            unit.ability == UnitAbility::Tank && unit.hp > 0.0
+        });
+        let player_slots = spatial::slots(&self.player_units);
+
+        // 敵ユニットの移動と戦闘（凍結中は何もしない）
+        let moving = if frozen { 0 } else { self.enemy_units.len() };
+        for i in 0..moving {
             let unit = &mut self.enemy_units[i];
 
+            unit.tick_timers(delta);
+
             // 敵のノックバック処理
             if unit.knockback_time > 0.0 {
                 unit.position += unit.knockback_velocity * delta;
@@ -414,29 +2300,59 @@ impl GameState {
             }
 
             if let Some(target_id) = unit.target_id {
-                if !self.player_units.iter().any(|e| e.id == target_id) {
-                    unit.target_id = None;
+                match player_slots.get(&target_id) {
+                    None => unit.target_id = None,
+                    // 攻城型は離れていった相手を追わずに進軍へ戻る
+                    Some(&index)
+                        if enemy_personality == EnemyPersonality::Siege
+                            && (self.player_units[index].position - unit.position).abs()
+                                > personality::SIEGE_ENGAGE_RADIUS =>
+                    {
+                        unit.target_id = None;
+                    }
+                    Some(_) => {}
                 }
             }
 
+            // 範囲内のタンクがいれば最寄りのタンクを狙う
+            if let Some(index) = taunt_index
+                .lane(unit.lane)
+                .nearest_within(unit.position, ability::TAUNT_RADIUS)
+            {
+                unit.target_id = Some(self.player_units[index].id);
+            }
+
             if unit.target_id.is_none() {
-                if let Some(player) = self.player_units.iter().min_by(|a, b| {
-                    (a.position - unit.position)
-                        .abs()
-                        .partial_cmp(&(b.position - unit.position).abs())
-                        .unwrap()
-                }) {
-                    unit.target_id = Some(player.id);
+                let player_index = player_index.lane(unit.lane);
+                let target = match enemy_personality {
+                    EnemyPersonality::Rush => player_index.nearest(unit.position),
+                    EnemyPersonality::Siege => {
+                        player_index.nearest_within(unit.position, personality::SIEGE_ENGAGE_RADIUS)
+                    }
+                    EnemyPersonality::Attrition => player_index
+                        .within(unit.position, personality::ATTRITION_SCAN_RADIUS)
+                        .min_by(|&a, &b| {
+                            self.player_units[a].hp.total_cmp(&self.player_units[b].hp)
+                        })
+                        .or_else(|| player_index.nearest(unit.position)),
+                };
+                if let Some(index) = target {
+                    unit.target_id = Some(self.player_units[index].id);
                 }
             }
 
             if let Some(target_id) = unit.target_id {
-                if let Some(player) = self.player_units.iter_mut().find(|e| e.id == target_id) {
+                if let Some(&index) = player_slots.get(&target_id) {
+                    let player = &mut self.player_units[index];
                     let distance = (player.position - unit.position).abs();
-                    if distance <= 10.0 {
-                        player.hp -= unit.attack * delta;
-                        if player.hp <= 0.0 {
-                            units_to_remove.push(player.id);
+                    if distance <= unit.ability.attack_range() {
+                        if unit.attack_cooldown <= 0.0 {
+                            unit.attack_cooldown = ATTACK_INTERVAL;
+                            unit.stealthed = false;
+                            if player.take_hit(unit.attack * ATTACK_INTERVAL, -1.0) {
+                                units_to_remove.push(player.id);
+                                deaths += 1;
+                            }
                         }
                     } else {
                         let direction = if player.position > unit.position {
@@ -451,37 +2367,150 @@ impl GameState {
                 if unit.position > 0.0 {
                     unit.position -= unit.speed * delta;
                 } else {
+                    unit.stealthed = false;
                     self.player_base_hp -= unit.attack * delta;
                 }
             }
         }
 
+        self.add_fractional_coins(coins_earned);
+        if coins_earned_b > 0.0 {
+            self.battle.record_coins(coins_earned_b);
+            self.coop.add_coins(coins_earned_b);
+        }
+
+        // 自陣付近の味方ユニットを回復
+        let heal_radius = HEAL_BASE_RADIUS * (1.0 + self.upgrades.heal_radius as f32 / 100.0);
+        let heal_rate = HEAL_BASE_RATE * (1.0 + self.upgrades.heal_rate as f32 / 100.0);
+        for unit in &mut self.player_units {
+            if unit.hp > 0.0 && unit.position <= heal_radius {
+                unit.hp = (unit.hp + unit.max_hp * heal_rate * delta).min(unit.max_hp);
+            }
+        }
+
+        // 準備の整った回復ユニットが周囲の味方をまとめて回復する（自分自身は対象外）
+        let is_ready_healer = |unit: &Unit| {
+            unit.ability == UnitAbility::Healer && unit.hp > 0.0 && unit.ability_cooldown <= 0.0
+        };
+        let healer_index = LaneIndex::new(&self.player_units, lanes, is_ready_healer);
+        for unit in &mut self.player_units {
+            if unit.hp <= 0.0 {
+                continue;
+            }
+            let mut nearby = healer_index
+                .lane(unit.lane)
+                .count_within(unit.position, ability::HEALER_RADIUS);
+            if is_ready_healer(unit) {
+                nearby = nearby.saturating_sub(1);
+                unit.ability_cooldown = unit.ability.cooldown().unwrap_or(0.0);
+            }
+            if nearby > 0 {
+                let heal = unit.max_hp
+                    * ability::HEALER_RATE
+                    * ability::HEALER_PULSE_INTERVAL
+                    * nearby as f32;
+                unit.hp = (unit.hp + heal).min(unit.max_hp);
+            }
+        }
+
+        // 敵が前線基地に到達したら破壊
+        if let Some(forward) = &self.barracks {
+            if self
+                .enemy_units
+                .iter()
+                .any(|e| forward.is_overrun(e.position))
+            {
+                self.barracks = None;
+            }
+        }
+
+        // 戦況に応じて士気が変動し、時間とともに平常へ戻る
+        self.morale.record_kills(kills);
+        self.achievements.stats.enemies_defeated += kills as u64;
+        self.activity.enemies_killed += kills;
+        self.quests
+            .record(QuestMetric::EnemiesDefeated, kills as u64);
+        self.morale.record_deaths(deaths);
+        self.battle.record_losses(deaths);
+        self.morale.record_base_damage(
+            base_hp_before - self.player_base_hp,
+            self.max_player_base_hp,
+        );
+        self.morale.decay(delta);
+
         // 位置の範囲をクランプ
         for unit in &mut self.player_units {
-            unit.position = unit.position.max(0.0).min(1000.0);
+            unit.position = unit.position.clamp(0.0, 1000.0);
         }
         for unit in &mut self.enemy_units {
-            unit.position = unit.position.max(0.0).min(1000.0);
+            unit.position = unit.position.clamp(0.0, 1000.0);
         }
 
         // 死亡したユニットを削除
+        let units_to_remove: HashSet<u32> = units_to_remove.into_iter().collect();
         self.player_units
             .retain(|u| !units_to_remove.contains(&u.id));
         self.enemy_units
             .retain(|u| !units_to_remove.contains(&u.id));
 
+        // ボス撃破ボーナス
+        let boss_defeated = self
+            .boss
+            .as_ref()
+            .is_some_and(|b| !self.enemy_units.iter().any(|e| e.id == b.unit_id));
+        if boss_defeated {
+            self.boss = None;
+            let reward = boss::reward(self.stage) as f64 * self.rules.coin_multiplier as f64;
+            self.add_shared_coins(reward * self.prestige.coin_multiplier());
+            self.chests
+                .drop_chest(self.stage, unix_timestamp(), self.rng.gen());
+        }
+
+        // 襲撃の敵を全て倒したら撃退ボーナス
+        if let Some(stage) = self.invasion.settle(self.invaders_remaining()) {
+            let reward = invasion::reward(stage) * self.rules.coin_multiplier as f64;
+            let coins_before = self.coins;
+            self.add_shared_coins(reward * self.prestige.coin_multiplier());
+            let coins = self.coins - coins_before;
+            self.invasion.record_reward(stage, coins);
+            self.notifications
+                .push(Notification::invasion_repelled(coins));
+        }
+
         // 勝敗判定
         if self.enemy_base_hp <= 0.0 && !self.stage_clear {
             self.stage_clear = true;
+            self.notifications
+                .push(Notification::stage_clear(self.stage));
             let _coin_bonus = 1.0 + self.upgrades.coin_rate as f32 / 100.0;
             // ステージクリア報酬を大幅に削減
-            self.coins += (20 * (self.stage as u32) / 2).max(10) as u32;
-            self.next_stage();
+            let reward =
+                (20 * (self.stage as u64) / 2).max(10) as f64 * self.rules.coin_multiplier as f64;
+            self.add_shared_coins(reward * self.prestige.coin_multiplier());
+            self.sessions.record_stage_clear();
+            self.achievements.stats.stages_cleared += 1;
+            self.quests.record(QuestMetric::StagesCleared, 1);
+            self.activity.stages_cleared += 1;
+            self.morale.record_stage_result(true);
+            self.chests
+                .drop_chest(self.stage, unix_timestamp(), self.rng.gen());
+            self.battle.finish(self.stage, unix_timestamp());
+            // 予測シミュレーションでは演出を挟まない
+            if self.simulation {
+                self.finish_stage_clear();
+            } else {
+                self.clear_sequence.start(self.stage, final_blow);
+            }
         }
 
-        if self.player_base_hp <= 0.0 {
+        // クリア演出中は味方の基地が落ちてもクリアを取り消さない
+        if self.player_base_hp <= 0.0 && !self.clear_sequence.is_active() {
+            self.defeats += 1;
+            self.morale.record_stage_result(false);
             self.reset_current_stage();
         }
+        self.notifications
+            .check_base(self.player_base_hp, self.max_player_base_hp);
 
         // 自動購入処理（時間ベース）
         if self.auto_buy.remaining_time > 0.0 {
@@ -489,46 +2518,580 @@ impl GameState {
             if self.auto_buy.remaining_time <= 0.0 {
                 self.auto_buy.remaining_time = 0.0;
                 self.auto_buy.enabled = false;
+                self.notifications.push(Notification::auto_buy_expired());
+            }
+
+            // 1 回の更新で買うのは 1 つだけ
+            if self.auto_buy.enabled {
+                if let Some(entry) = self
+                    .auto_buy
+                    .next_purchase(&self.upgrades, self.coins)
+                    .cloned()
+                {
+                    let _ = self.buy_upgrade(&entry.upgrade_type, &entry.unit_type);
+                }
+            }
+        } else {
+            self.auto_buy.enabled = false;
+        }
+
+        self.achievements.check(unix_timestamp());
+        self.titles.record_achievements(&self.achievements);
+        self.unlock_cosmetics();
+        self.quests.refresh(unix_timestamp(), self.stage);
+
+        // NaN が戦闘計算に広がったまま保存されないようにする（デバッグビルドでは即座に検出）
+        let finite = self.is_finite();
+        debug_assert!(finite, "non-finite value in simulation");
+        if !finite {
+            self.repair();
+        }
+
+        // 定期セーブ
+        self.save_timer += delta;
+        if self.save_timer >= 5.0 {
+            self.save_timer = 0.0;
+            self.persist_state();
+        }
+    }
+
+    fn finish_stage_clear(&mut self) {
+        if self.manual_advance {
+            self.enemy_base_hp = 0.0;
+            self.farming = true;
+            self.record(JournalEvent::StageClear { stage: self.stage });
+            self.persist_state();
+        } else {
+            self.next_stage();
+        }
+    }
+
+    fn next_stage(&mut self) {
+        self.clear_sequence.cancel();
+        self.stage += 1;
+        self.prestige.record_stage(self.stage);
+        self.enemy_base_hp = self.rules.enemy_base_hp(self.stage);
+        self.max_enemy_base_hp = self.enemy_base_hp;
+        self.enemy_units.clear();
+        self.invasion.abandon();
+        self.enemy_spawn_timer = 0.0;
+        self.stage_clear = false;
+        self.farming = false;
+        self.barracks = None;
+        self.battle.reset();
+        self.summons.new_stage();
+        self.spawn_boss();
+        self.reposition_player_units();
+        self.record(JournalEvent::StageClear {
+            stage: self.stage - 1,
+        });
+        self.persist_state();
+    }
+
+    // 送信するダメージの整数部分を取り出す（端数は次回に持ち越す）
+    pub fn take_raid_damage(&mut self) -> u64 {
+        let whole = self.raid_damage.floor();
+        self.raid_damage -= whole;
+        whole as u64
+    }
+
+    // 送信に失敗したダメージを戻す
+    pub fn return_raid_damage(&mut self, damage: u64) {
+        self.raid_damage += damage as f64;
+    }
+
+    // tier は報酬のランク（称号の判定に使う）
+    pub fn claim_raid_reward(&mut self, coins: u64, tier: &str) {
+        self.add_coins(coins);
+        self.titles.record_raid(tier);
+        self.persist_state();
+    }
+
+    // None で称号を外す
+    pub fn set_active_title(&mut self, id: Option<String>) -> Result<(), GameError> {
+        self.titles.set_active(id)?;
+        self.persist_state();
+        Ok(())
+    }
+
+    // 見た目の解除条件はここで判定する（最高到達ステージは転生しても下がらない）
+    fn meets_unlock(&self, unlock: Unlock) -> bool {
+        match unlock {
+            Unlock::Free => true,
+            Unlock::Stage { stage } => self.prestige.best_stage.max(self.stage) >= stage,
+            Unlock::Achievement { id } => self.achievements.unlocked.iter().any(|a| a.id == id),
+        }
+    }
+
+    // 条件を満たした見た目を解除済みとして残す
+    fn unlock_cosmetics(&mut self) {
+        for cosmetic in cosmetics::catalog() {
+            if !self.cosmetics.is_unlocked(cosmetic.id) && self.meets_unlock(cosmetic.unlock) {
+                self.cosmetics.unlock(cosmetic.id);
+            }
+        }
+    }
+
+    pub fn cosmetics_status(&self) -> Vec<CosmeticStatus> {
+        self.cosmetics.list()
+    }
+
+    // None で既定の見た目に戻す。解除前の見た目は選べない
+    pub fn set_cosmetic(
+        &mut self,
+        kind: CosmeticKind,
+        id: Option<String>,
+    ) -> Result<(), GameError> {
+        if let Some(id) = &id {
+            let cosmetic = cosmetics::find(id)
+                .filter(|cosmetic| cosmetic.kind == kind)
+                .ok_or_else(|| GameError::UnknownCosmetic { id: id.clone() })?;
+            if !self.cosmetics.is_unlocked(id) && !self.meets_unlock(cosmetic.unlock) {
+                return Err(GameError::CosmeticLocked { id: id.clone() });
+            }
+            self.cosmetics.unlock(id);
+        }
+        self.cosmetics.select(kind, id);
+        self.persist_state();
+        Ok(())
+    }
+
+    // 宝箱の報酬はすべてここで付与する
+
+    pub fn open_chest(&mut self, chest_id: u32) -> Result<ChestReward, GameError> {
+        let chest = self
+            .chests
+            .take(chest_id)
+            .ok_or(GameError::ChestNotFound { chest_id })?;
+        self.record_replay(ReplayInput::OpenChest { chest_id });
+        let reward = chest::roll_reward(&chest, &mut self.rng);
+        self.add_coins(reward.coins);
+        if let Some(upgrade) = &reward.upgrade {
+            self.apply_upgrade_level(&upgrade.upgrade_type, &upgrade.unit_type)?;
+        }
+        self.persist_state();
+        Ok(reward)
+    }
+
+    pub fn claim_quest_reward(&mut self, quest_id: &str) -> Result<Quest, GameError> {
+        let quest = self
+            .quests
+            .get_mut(quest_id)
+            .ok_or_else(|| GameError::QuestNotFound {
+                quest_id: quest_id.to_string(),
+            })?;
+        if !quest.is_complete() {
+            return Err(GameError::QuestNotComplete {
+                quest_id: quest_id.to_string(),
+            });
+        }
+        if quest.claimed {
+            return Err(GameError::QuestAlreadyClaimed {
+                quest_id: quest_id.to_string(),
+            });
+        }
+        quest.claimed = true;
+        let quest = quest.clone();
+        self.add_coins(quest.reward);
+        self.persist_state();
+        Ok(quest)
+    }
+
+    // 交戦中のユニットの重心（0.0 = 自陣、1.0 = 敵陣）
+    pub fn front_line(&self) -> f32 {
+        let engaged = |units: &[Unit], opponents: &[Unit]| -> Vec<f32> {
+            units
+                .iter()
+                .filter(|unit| {
+                    unit.target_id
+                        .and_then(|id| opponents.iter().find(|o| o.id == id))
+                        .is_some_and(|target| {
+                            (target.position - unit.position).abs() <= ENGAGE_RANGE
+                        })
+                })
+                .map(|unit| unit.position)
+                .collect()
+        };
+        let mut positions = engaged(&self.player_units, &self.enemy_units);
+        positions.extend(engaged(&self.enemy_units, &self.player_units));
+
+        let position = if !positions.is_empty() {
+            positions.iter().sum::<f32>() / positions.len() as f32
+        } else if let Some(front) = self
+            .player_units
+            .iter()
+            .map(|u| u.position)
+            .max_by(f32::total_cmp)
+        {
+            // 交戦していなければ最前線のユニット位置
+            front
+        } else {
+            self.enemy_units
+                .iter()
+                .map(|u| u.position)
+                .min_by(f32::total_cmp)
+                .unwrap_or(FIELD_LENGTH / 2.0)
+        };
+        (position / FIELD_LENGTH).clamp(0.0, 1.0)
+    }
+
+    pub fn prestige_status(&self) -> PrestigeStatus {
+        self.prestige.status()
+    }
+
+    // 転生: ステージ・コイン・アップグレードを初期化し、転生ポイントを得る
+    pub fn prestige(&mut self) -> Result<u64, GameError> {
+        let mut prestige = self.prestige.clone();
+        let points = prestige.claim()?;
+        self.checkpoint(CheckpointReason::Prestige);
+        self.prestige = prestige;
+        self.start_new_run();
+        self.record(JournalEvent::Prestige {
+            count: self.prestige.prestige_count,
+        });
+        self.persist_state();
+        Ok(points)
+    }
+
+    // 転生とシーズンの切り替えで共通。強化・ステージ・戦況を最初に戻す（転生の強化は残す）
+    fn start_new_run(&mut self) {
+        let fresh = Self::fresh();
+        self.stage = fresh.stage;
+        self.coins = fresh.coins;
+        self.coin_fraction = 0.0;
+        self.upgrades = fresh.upgrades;
+        self.upgrade_ledger.reset();
+        self.max_player_base_hp = fresh.max_player_base_hp * self.rules.base_hp;
+        self.player_base_hp = self.max_player_base_hp;
+        self.enemy_base_hp = self.rules.enemy_base_hp(fresh.stage);
+        self.max_enemy_base_hp = self.enemy_base_hp;
+        self.player_units.clear();
+        self.enemy_units.clear();
+        self.invasion.abandon();
+        self.enemy_spawn_timer = 0.0;
+        self.clear_sequence.cancel();
+        self.stage_clear = false;
+        self.farming = false;
+        self.barracks = None;
+        self.boss = None;
+        self.battle.reset();
+        self.summons.new_stage();
+    }
+
+    // 同期の応答からサーバーのシーズンを反映する。終わったシーズンの報酬を転生ポイントで受け取り、
+    // 前に見たシーズンから進んでいれば転生と同じく周回をやり直す（転生できるステージでなくても）
+    pub fn apply_season(
+        &mut self,
+        season: u32,
+        results: &[SeasonResult],
+    ) -> Option<SeasonRollover> {
+        // シーズンのない古いサーバー
+        if season == 0 {
+            return None;
+        }
+        let first_seen = self.season.current == 0;
+        let rewards = self.season.claim(results);
+        let reset = self.season.advance(season);
+        if rewards.is_empty() && !reset {
+            if first_seen {
+                self.persist_state();
+            }
+            return None;
+        }
+        if reset {
+            self.checkpoint(CheckpointReason::Season);
+        }
+        let points: u64 = rewards.iter().map(|reward| reward.points).sum();
+        self.prestige.points += points;
+        self.prestige.total_points_earned += points;
+        if reset {
+            self.prestige.prestige_count += 1;
+            self.prestige.highest_stage = 1;
+            self.start_new_run();
+            self.record(JournalEvent::Season { season });
+        }
+        self.persist_state();
+        Some(SeasonRollover {
+            season,
+            reset,
+            rewards,
+            points,
+        })
+    }
+
+    pub fn purchase_prestige_upgrade(&mut self, kind: &str) -> Result<(), GameError> {
+        self.prestige.purchase(kind)?;
+        self.record_replay(ReplayInput::PrestigeUpgrade {
+            kind: kind.to_string(),
+        });
+        self.persist_state();
+        Ok(())
+    }
+
+    pub fn shop_status(&self) -> ShopStatus {
+        self.shop.status(unix_timestamp(), &self.prestige)
+    }
+
+    pub fn shop_is_fresh(&self) -> bool {
+        self.shop.is_fresh(unix_timestamp())
+    }
+
+    pub fn store_shop_stock(&mut self, stock: ShopStock) {
+        self.shop.store(stock);
+    }
+
+    // 手元の品揃えから転生ポイントで買う（品揃えはサーバー次第なのでリプレイには残さない）
+    pub fn buy_shop_offer(&mut self, item: &str) -> Result<ShopOffer, GameError> {
+        let offer = self.shop.buy(item, unix_timestamp(), &mut self.prestige)?;
+        self.persist_state();
+        Ok(offer)
+    }
+
+    // 確保済みの位置に前線基地を建設し、以降の味方をそこから出撃させる
+    pub fn build_barracks(&mut self, position: f32) -> Result<(), GameError> {
+        if self.barracks.is_some() {
+            return Err(GameError::BarracksAlreadyBuilt);
+        }
+        if !(position > 0.0 && position <= barracks::MAX_POSITION) {
+            return Err(GameError::InvalidBarracksPosition);
+        }
+        let forward = ForwardBarracks { position };
+        if position > self.front_line() * FIELD_LENGTH
+            || self
+                .enemy_units
+                .iter()
+                .any(|e| forward.is_overrun(e.position))
+        {
+            return Err(GameError::PositionNotCaptured);
+        }
+        let cost = barracks::build_cost(self.stage);
+        if self.coins < cost {
+            return Err(GameError::NotEnoughCoins {
+                needed: cost,
+                have: self.coins,
+            });
+        }
+        self.record_replay(ReplayInput::BuildBarracks { position });
+        self.coins -= cost;
+        self.barracks = Some(forward);
+        self.persist_state();
+        Ok(())
+    }
+
+    // threshold を省略すると既定の人数で進軍する
+    pub fn set_rally_point(
+        &mut self,
+        position: f32,
+        threshold: Option<u32>,
+    ) -> Result<(), GameError> {
+        let threshold = threshold.unwrap_or(rally::DEFAULT_THRESHOLD);
+        if !(0.0..=FIELD_LENGTH).contains(&position)
+            || !(1..=rally::MAX_THRESHOLD).contains(&threshold)
+        {
+            return Err(GameError::InvalidRallyPoint);
+        }
+        self.record_replay(ReplayInput::SetRallyPoint {
+            position,
+            threshold: Some(threshold),
+        });
+        self.rally = Some(RallyPoint {
+            position,
+            threshold,
+        });
+        Ok(())
+    }
+
+    pub fn clear_rally_point(&mut self) {
+        self.record_replay(ReplayInput::ClearRallyPoint);
+        self.rally = None;
+        self.release_rallying();
+    }
+
+    // 待機中の味方を進軍させる（集結地点は残す）。進軍させた数を返す
+    pub fn release_rally(&mut self) -> usize {
+        self.record_replay(ReplayInput::ReleaseRally);
+        self.release_rallying()
+    }
+
+    // 条件に合う味方の ID（画面で兵種や範囲を選んだとき）
+    pub fn select_units(&self, selector: &UnitSelector) -> Vec<u32> {
+        self.player_units
+            .iter()
+            .filter(|unit| selector.matches(unit))
+            .map(|unit| unit.id)
+            .collect()
+    }
+
+    // 選んだ味方に手動の命令を出す。別のレーンの敵や、対空攻撃のない味方への飛行中の敵の
+    // 攻撃命令は受けない（ボスはどのレーンからも狙える）。命令を受けた数を返す
+    pub fn order_units(
+        &mut self,
+        unit_ids: &[u32],
+        order: Order,
+        duration_secs: Option<f32>,
+    ) -> Result<usize, GameError> {
+        let duration = duration_secs.unwrap_or(orders::DEFAULT_DURATION);
+        if !(duration > 0.0 && duration <= orders::MAX_DURATION) {
+            return Err(GameError::InvalidOrder);
+        }
+        let target = match order {
+            Order::Move { position } if (0.0..=FIELD_LENGTH).contains(&position) => None,
+            Order::Move { .. } => return Err(GameError::InvalidOrder),
+            Order::Attack { target_id } => {
+                let boss_id = self.boss.as_ref().map(|b| b.unit_id);
+                let enemy = self
+                    .enemy_units
+                    .iter()
+                    .find(|e| e.id == target_id && !e.stealthed)
+                    .ok_or(GameError::InvalidOrder)?;
+                Some((enemy.lane, enemy.is_flying, Some(target_id) == boss_id))
+            }
+        };
+
+        let selected: HashSet<u32> = unit_ids.iter().copied().collect();
+        let mut ordered = 0;
+        for unit in self
+            .player_units
+            .iter_mut()
+            .filter(|u| selected.contains(&u.id))
+        {
+            if let Some((lane, flying, boss)) = target {
+                if (unit.lane != lane && !boss) || (flying && !unit.hits_air()) {
+                    continue;
+                }
+            }
+            unit.order = Some(UnitOrder {
+                order,
+                remaining: duration,
+            });
+            unit.target_id = None;
+            ordered += 1;
+        }
+        if ordered == 0 {
+            return Err(GameError::NoUnitsOrdered);
+        }
+        self.record_replay(ReplayInput::OrderUnits {
+            unit_ids: unit_ids.to_vec(),
+            order,
+            duration_secs: Some(duration),
+        });
+        Ok(ordered)
+    }
+
+    // 命令を取り消して AI に戻す。取り消した数を返す
+    pub fn cancel_orders(&mut self, unit_ids: &[u32]) -> usize {
+        self.record_replay(ReplayInput::CancelOrders {
+            unit_ids: unit_ids.to_vec(),
+        });
+        let selected: HashSet<u32> = unit_ids.iter().copied().collect();
+        let mut cancelled = 0;
+        for unit in &mut self.player_units {
+            if selected.contains(&unit.id) && unit.order.take().is_some() {
+                cancelled += 1;
             }
+        }
+        cancelled
+    }
+
+    fn release_rallying(&mut self) -> usize {
+        let mut released = 0;
+        for unit in self.player_units.iter_mut().filter(|u| u.rallying) {
+            unit.rallying = false;
+            released += 1;
+        }
+        released
+    }
+
+    pub fn advance_stage(&mut self) -> Result<(), GameError> {
+        if !self.farming {
+            return Err(GameError::StageLocked);
+        }
+        self.record_replay(ReplayInput::AdvanceStage);
+        self.next_stage();
+        Ok(())
+    }
 
-            if self.auto_buy.enabled && !self.auto_buy.upgrade_type.is_empty() {
-                let upgrade_type = self.auto_buy.upgrade_type.clone();
-                let unit_type = self.auto_buy.unit_type.clone();
-                let cost = self.upgrades.get_cost(&upgrade_type, &unit_type);
-                if self.coins >= cost {
-                    let _ = self.purchase_upgrade(&upgrade_type, &unit_type);
-                }
+    pub fn set_stance(&mut self, stance: Stance) -> Result<(), GameError> {
+        if let Stance::Hold { position } = stance {
+            if !(0.0..=FIELD_LENGTH).contains(&position) {
+                return Err(GameError::InvalidStance);
             }
-        } else {
-            self.auto_buy.enabled = false;
         }
+        self.record_replay(ReplayInput::SetStance { stance });
+        self.stance = stance;
+        self.persist_state();
+        Ok(())
+    }
 
-        // 定期セーブ
-        self.save_timer += delta;
-        if self.save_timer >= 5.0 {
-            self.save_timer = 0.0;
+    // 自動進行に戻した時点でクリア済みなら次のステージへ進む
+    pub fn set_manual_advance(&mut self, enabled: bool) {
+        self.record_replay(ReplayInput::SetManualAdvance { enabled });
+        self.manual_advance = enabled;
+        if !enabled && self.farming {
+            self.next_stage();
+        } else {
             self.persist_state();
         }
     }
 
-    fn next_stage(&mut self) {
-        self.stage += 1;
-        self.enemy_base_hp = 500.0 * (1.0 + (self.stage as f32 - 1.0) * 0.5);
-        self.max_enemy_base_hp = self.enemy_base_hp;
-        self.enemy_units.clear();
-        self.enemy_spawn_timer = 0.0;
-        self.stage_clear = false;
-        self.reposition_player_units();
-        self.persist_state();
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+
+    // 難易度を変えると今のステージを最初からやり直す（戦闘中なら reset_stage で了承が要る）
+    pub fn set_difficulty(
+        &mut self,
+        rules: GameRules,
+        reset_stage: bool,
+    ) -> Result<GameRules, GameError> {
+        let rules = rules.validated()?;
+        if rules == self.rules {
+            return Ok(rules);
+        }
+        if !reset_stage && self.stage_in_progress() {
+            return Err(GameError::DifficultyNeedsReset);
+        }
+        self.record_replay(ReplayInput::SetRules {
+            rules: rules.clone(),
+        });
+        // 基地強化の分は残したまま倍率だけ掛け替える
+        self.max_player_base_hp = valid_max_hp(
+            self.max_player_base_hp / self.rules.base_hp * rules.base_hp,
+            1000.0,
+        );
+        self.rules = rules.clone();
+        self.max_enemy_base_hp = self.rules.enemy_base_hp(self.stage);
+        self.reset_current_stage();
+        Ok(rules)
+    }
+
+    // ボス以外の敵・味方が戦場にいるか、どちらかの基地が削れている
+    fn stage_in_progress(&self) -> bool {
+        let boss_id = self.boss.as_ref().map(|boss| boss.unit_id);
+        !self.player_units.is_empty()
+            || self.enemy_units.iter().any(|e| Some(e.id) != boss_id)
+            || self.player_base_hp < self.max_player_base_hp
+            || self.enemy_base_hp < self.max_enemy_base_hp
+    }
+
+    pub fn preview_next_stage(&self) -> StagePreview {
+        self.rules.adjust_preview(stage::preview(self.stage + 1))
     }
 
     pub fn reset_current_stage(&mut self) {
         self.player_units.clear();
         self.enemy_units.clear();
+        self.invasion.abandon();
         self.player_base_hp = self.max_player_base_hp;
         self.enemy_base_hp = self.max_enemy_base_hp;
         self.enemy_spawn_timer = 0.0;
+        self.clear_sequence.cancel();
         self.stage_clear = false;
+        self.farming = false;
+        self.barracks = None;
+        self.battle.reset();
+        self.summons.new_stage();
+        self.spawn_boss();
         self.persist_state();
     }
 
@@ -536,15 +3099,114 @@ impl GameState {
         &mut self,
         upgrade_type: &str,
         unit_type: &str,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, GameError> {
+        self.purchase_upgrade_as(upgrade_type, unit_type, CoopPlayer::A)
+    }
+
+    // 一番安い強化を買い、買ったものと払ったコインを返す（同じ値段なら一覧の先のもの）
+    pub fn purchase_cheapest_upgrade(
+        &mut self,
+    ) -> Result<(&'static str, &'static str, u64), GameError> {
+        let (upgrade_type, unit_type, cost) = UPGRADE_OPTIONS
+            .iter()
+            .map(|&(u, t)| (u, t, self.upgrades.get_cost(u, t)))
+            .min_by_key(|&(_, _, cost)| cost)
+            .unwrap_or(("attack", "small", 0));
+        self.purchase_upgrade(upgrade_type, unit_type)?;
+        Ok((upgrade_type, unit_type, cost))
+    }
+
+    // 強化は共有で、払うのは買った側の財布
+    pub fn purchase_upgrade_as(
+        &mut self,
+        upgrade_type: &str,
+        unit_type: &str,
+        player: CoopPlayer,
+    ) -> Result<bool, GameError> {
+        let bought = match player {
+            CoopPlayer::A => self.buy_upgrade(upgrade_type, unit_type)?,
+            CoopPlayer::B => {
+                let cost = self.upgrades.get_cost(upgrade_type, unit_type);
+                if self.coop.coins() < cost {
+                    return Err(GameError::NotEnoughCoins {
+                        needed: cost,
+                        have: self.coop.coins(),
+                    });
+                }
+                self.apply_upgrade_level(upgrade_type, unit_type)?;
+                self.coop.spend(cost)?;
+                self.upgrade_ledger.record(upgrade_type, unit_type, cost);
+                self.achievements.stats.upgrades_purchased += 1;
+                self.persist_state();
+                true
+            }
+        };
+        self.record_replay(ReplayInput::Purchase {
+            upgrade_type: upgrade_type.to_string(),
+            unit_type: unit_type.to_string(),
+            player,
+        });
+        Ok(bought)
+    }
+
+    // 自動購入は update の中で起きるので、リプレイには記録しない
+    fn buy_upgrade(&mut self, upgrade_type: &str, unit_type: &str) -> Result<bool, GameError> {
         let cost = self.upgrades.get_cost(upgrade_type, unit_type);
 
         if self.coins < cost {
-            return Err("Not enough coins".to_string());
+            return Err(GameError::NotEnoughCoins {
+                needed: cost,
+                have: self.coins,
+            });
         }
 
+        self.apply_upgrade_level(upgrade_type, unit_type)?;
         self.coins -= cost;
+        self.upgrade_ledger.record(upgrade_type, unit_type, cost);
+        self.achievements.stats.upgrades_purchased += 1;
+
+        // 購入のたびにセーブ全体を書き直さず、次の定期セーブまではジャーナルで守る
+        self.record(JournalEvent::Purchase {
+            upgrade_type: upgrade_type.to_string(),
+            unit_type: unit_type.to_string(),
+        });
+        Ok(true)
+    }
+
+    pub fn respec_quote(&self, refund_percent: u32) -> RespecQuote {
+        self.upgrade_ledger.quote(refund_percent)
+    }
+
+    // 強化をすべて初期化し、使ったコインの一部を返す（手数料は振り直すたびに倍になる）
+    pub fn respec(&mut self, refund_percent: u32) -> Result<RespecQuote, GameError> {
+        let quote = self.upgrade_ledger.quote(refund_percent);
+        if self.upgrades.levels().iter().all(|&level| level == 0) {
+            return Err(GameError::NothingToRespec);
+        }
+        let available = self.coins.saturating_add(quote.refund);
+        if available < quote.fee {
+            return Err(GameError::NotEnoughCoins {
+                needed: quote.fee.saturating_sub(quote.refund),
+                have: self.coins,
+            });
+        }
+        self.checkpoint(CheckpointReason::Respec);
+        self.record_replay(ReplayInput::Respec { refund_percent });
+        self.coins = available - quote.fee;
+        self.upgrades = Upgrades::new();
+        self.upgrade_ledger.record_respec();
+        // 基地強化の分だけ戻す（難易度の倍率は残す）
+        self.max_player_base_hp = Self::fresh().max_player_base_hp * self.rules.base_hp;
+        self.player_base_hp = self.player_base_hp.min(self.max_player_base_hp);
+        self.persist_state();
+        Ok(quote)
+    }
 
+    fn apply_upgrade_level(
+        &mut self,
+        upgrade_type: &str,
+        unit_type: &str,
+    ) -> Result<(), GameError> {
         match (upgrade_type, unit_type) {
             ("attack", "small") => self.upgrades.small_attack += 10,
             ("attack", "medium") => self.upgrades.medium_attack += 10,
@@ -561,15 +3223,61 @@ impl GameState {
                 self.max_player_base_hp *= 1.1;
                 self.player_base_hp = self.max_player_base_hp;
             }
-            _ => return Err("Invalid upgrade type".to_string()),
+            ("heal_radius", _) => self.upgrades.heal_radius += 10,
+            ("heal_rate", _) => self.upgrades.heal_rate += 10,
+            _ => {
+                return Err(GameError::InvalidUpgrade {
+                    upgrade_type: upgrade_type.to_string(),
+                })
+            }
         }
+        Ok(())
+    }
 
-        self.persist_state();
-        Ok(true)
+    // スナップショット上でアップグレードを無償適用し、既存ユニットにも反映する（推奨計算用）
+    pub fn preview_upgrade(
+        &mut self,
+        upgrade_type: &str,
+        unit_type: &str,
+    ) -> Result<(), GameError> {
+        let target = match unit_type {
+            "small" => Some(UnitType::Small),
+            "medium" => Some(UnitType::Medium),
+            "large" => Some(UnitType::Large),
+            _ => None,
+        };
+        let before = self.upgrades.clone();
+        self.apply_upgrade_level(upgrade_type, unit_type)?;
+
+        let ratio = |old: u32, new: u32| (1.0 + new as f32 / 100.0) / (1.0 + old as f32 / 100.0);
+        for unit in &mut self.player_units {
+            let (attack, hp, speed) = match (unit.unit_type, target) {
+                (UnitType::Small, Some(UnitType::Small)) => (
+                    ratio(before.small_attack, self.upgrades.small_attack),
+                    ratio(before.small_hp, self.upgrades.small_hp),
+                    ratio(before.small_speed, self.upgrades.small_speed),
+                ),
+                (UnitType::Medium, Some(UnitType::Medium)) => (
+                    ratio(before.medium_attack, self.upgrades.medium_attack),
+                    ratio(before.medium_hp, self.upgrades.medium_hp),
+                    ratio(before.medium_speed, self.upgrades.medium_speed),
+                ),
+                (UnitType::Large, Some(UnitType::Large)) => (
+                    ratio(before.large_attack, self.upgrades.large_attack),
+                    ratio(before.large_hp, self.upgrades.large_hp),
+                    ratio(before.large_speed, self.upgrades.large_speed),
+                ),
+                _ => continue,
+            };
+            unit.attack *= attack;
+            unit.hp *= hp;
+            unit.max_hp *= hp;
+            unit.speed *= speed;
+        }
+        Ok(())
     }
 
     fn reposition_player_units(&mut self) {
-        let mut rng = rand::thread_rng();
         // 小ユニットの最大 HP を基準にダメージを計算（アップグレードを考慮）
         let small_base_hp = 10.0 * (1.0 + self.upgrades.small_hp as f32 / 100.0);
         let damage = small_base_hp * 0.35; // 小ユニット HP の 35% 固定ダメージ
@@ -578,11 +3286,11 @@ impl GameState {
             // ハーフフィールドへ移動
             unit.position = unit.position.min(400.0);
             // ダメージ適用（HPが0以下になったら死亡させるため、max(1.0)を削除）
-            unit.hp = unit.hp - damage;
+            unit.hp -= damage;
 
             // 吹き飛ばし（ランダム距離と時間）
-            let distance = rng.gen_range(30.0..200.0);
-            let duration = rng.gen_range(0.35..0.85);
+            let distance = self.rng.gen_range(30.0..200.0);
+            let duration = self.rng.gen_range(0.35..0.85);
             // 左方向(自陣側)へ移動させる
             unit.knockback_velocity = -(distance / duration);
             unit.knockback_time = duration;
@@ -593,3 +3301,1194 @@ impl GameState {
         self.player_units.retain(|u| u.hp > 0.0);
     }
 }
+
+// 対戦の刻み幅・制限時間と、再生用に盤面を残す間隔
+const PVP_STEP_SECS: f32 = 0.1;
+const PVP_TIME_LIMIT_SECS: f32 = 180.0;
+const PVP_FRAME_STEPS: u32 = 5;
+// 両軍とも 4 刻み（0.4 秒）ごとに 1 体ずつ出撃する
+const PVP_SPAWN_STEPS: u32 = 4;
+
+// 対戦中のユニット（Unit の能力や吹き飛ばしは使わない）
+struct Fighter {
+    id: u32,
+    unit_type: UnitType,
+    attacker: bool,
+    position: f32,
+    hp: f32,
+    attack: f32,
+    speed: f32,
+    cooldown: f32,
+}
+
+enum FighterAction {
+    Hit(usize),
+    HitBase,
+    Advance,
+}
+
+// 出撃順に並べた軍勢。ID は攻め側から通し番号
+fn pvp_reserve(army: &ArmySnapshot, attacker: bool, first_id: u32) -> VecDeque<Fighter> {
+    let prestige = PrestigeUpgrades {
+        attack: army.prestige_attack,
+        hp: army.prestige_hp,
+        coin: 0,
+    };
+    let mut reserve = VecDeque::new();
+    for (unit_type, count) in army.squads() {
+        let (base_hp, base_attack, base_speed) = unit_base_stats(unit_type);
+        let (hp_bonus, attack_bonus, speed_bonus) = army.upgrades.unit_bonus(unit_type);
+        for _ in 0..count {
+            reserve.push_back(Fighter {
+                id: first_id + reserve.len() as u32,
+                unit_type,
+                attacker,
+                position: if attacker { 0.0 } else { FIELD_LENGTH },
+                hp: base_hp * (1.0 + hp_bonus as f32 / 100.0) * prestige.hp_multiplier(),
+                attack: base_attack
+                    * (1.0 + attack_bonus as f32 / 100.0)
+                    * prestige.attack_multiplier(),
+                speed: base_speed * (1.0 + speed_bonus as f32 / 100.0),
+                cooldown: 0.0,
+            });
+        }
+    }
+    reserve
+}
+
+fn pvp_frame(time: f32, bases: [f32; 2], field: &[Fighter]) -> BattleFrame {
+    BattleFrame {
+        time,
+        attacker_base_hp: bases[0],
+        defender_base_hp: bases[1],
+        units: field
+            .iter()
+            .map(|fighter| FrameUnit {
+                id: fighter.id,
+                unit_type: fighter.unit_type,
+                attacker: fighter.attacker,
+                position: fighter.position,
+                hp: fighter.hp,
+            })
+            .collect(),
+    }
+}
+
+// 2 つの軍勢を戦わせる。乱数を使わないので、同じ軍勢からは必ず同じ結果と記録になる
+// 攻め側は 0、守り側は FIELD_LENGTH の基地から出撃し、相手の基地を先に壊した方が勝つ
+pub fn simulate_battle(attacker: &ArmySnapshot, defender: &ArmySnapshot) -> BattleResult {
+    let mut reserves = [pvp_reserve(attacker, true, 1), VecDeque::new()];
+    let first_defender_id = reserves[0].len() as u32 + 1;
+    reserves[1] = pvp_reserve(defender, false, first_defender_id);
+    let max_bases = [attacker.base_hp(), defender.base_hp()];
+    let mut bases = max_bases;
+    let mut field: Vec<Fighter> = Vec::new();
+    let mut frames = Vec::new();
+    let mut events = Vec::new();
+    let mut winner = None;
+    let total_steps = (PVP_TIME_LIMIT_SECS / PVP_STEP_SECS).round() as u32;
+    let mut step = 0;
+
+    while step < total_steps {
+        let time = step as f32 * PVP_STEP_SECS;
+        if step % PVP_SPAWN_STEPS == 0 {
+            for reserve in &mut reserves {
+                if let Some(fighter) = reserve.pop_front() {
+                    events.push(BattleEvent::Spawn {
+                        time,
+                        id: fighter.id,
+                    });
+                    field.push(fighter);
+                }
+            }
+        }
+        if step % PVP_FRAME_STEPS == 0 {
+            frames.push(pvp_frame(time, bases, &field));
+        }
+
+        // 全員の行動をこの刻みの盤面で決めてから、ダメージをまとめて与える（処理の順で結果が変わらない）
+        let actions: Vec<FighterAction> = field
+            .iter()
+            .map(|fighter| {
+                let target = field
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| other.attacker != fighter.attacker)
+                    .map(|(index, other)| (index, (other.position - fighter.position).abs()))
+                    .filter(|&(_, distance)| distance <= ENGAGE_RANGE)
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                let enemy_base = if fighter.attacker { FIELD_LENGTH } else { 0.0 };
+                match target {
+                    Some((index, _)) => FighterAction::Hit(index),
+                    None if (enemy_base - fighter.position).abs() <= ENGAGE_RANGE => {
+                        FighterAction::HitBase
+                    }
+                    None => FighterAction::Advance,
+                }
+            })
+            .collect();
+
+        let mut damage = vec![(0.0f32, 0u32); field.len()];
+        let mut base_damage = [0.0f32; 2];
+        for (fighter, action) in field.iter_mut().zip(&actions) {
+            fighter.cooldown = (fighter.cooldown - PVP_STEP_SECS).max(0.0);
+            match *action {
+                FighterAction::Advance => {
+                    let direction = if fighter.attacker { 1.0 } else { -1.0 };
+                    fighter.position = (fighter.position
+                        + direction * fighter.speed * PVP_STEP_SECS)
+                        .clamp(0.0, FIELD_LENGTH);
+                }
+                _ if fighter.cooldown > 0.0 => {}
+                FighterAction::Hit(index) => {
+                    damage[index].0 += fighter.attack * ATTACK_INTERVAL;
+                    damage[index].1 = fighter.id;
+                    fighter.cooldown = ATTACK_INTERVAL;
+                }
+                FighterAction::HitBase => {
+                    base_damage[usize::from(fighter.attacker)] += fighter.attack * ATTACK_INTERVAL;
+                    fighter.cooldown = ATTACK_INTERVAL;
+                }
+            }
+        }
+
+        let time = (step + 1) as f32 * PVP_STEP_SECS;
+        for (fighter, &(amount, by)) in field.iter_mut().zip(&damage) {
+            if amount > 0.0 {
+                fighter.hp -= amount;
+                if fighter.hp <= 0.0 {
+                    events.push(BattleEvent::Defeated {
+                        time,
+                        id: fighter.id,
+                        by,
+                    });
+                }
+            }
+        }
+        field.retain(|fighter| fighter.hp > 0.0);
+
+        // base_damage[0] は攻め側の基地が受けたダメージ
+        for (side, amount) in base_damage.into_iter().enumerate() {
+            bases[side] = (bases[side] - amount).max(0.0);
+        }
+        step += 1;
+        let destroyed = [bases[0] <= 0.0, bases[1] <= 0.0];
+        if destroyed[0] || destroyed[1] {
+            for (side, &gone) in destroyed.iter().enumerate() {
+                if gone {
+                    events.push(BattleEvent::BaseDestroyed {
+                        time,
+                        attacker: side == 0,
+                    });
+                }
+            }
+            winner = Some(match destroyed {
+                [true, true] => BattleWinner::Draw,
+                [false, true] => BattleWinner::Attacker,
+                _ => BattleWinner::Defender,
+            });
+            break;
+        }
+    }
+
+    let duration_secs = step as f32 * PVP_STEP_SECS;
+    frames.push(pvp_frame(duration_secs, bases, &field));
+    // 時間切れなら基地の残り体力の割合で決める
+    let winner = winner.unwrap_or_else(|| {
+        let attacker_ratio = bases[0] / max_bases[0];
+        let defender_ratio = bases[1] / max_bases[1];
+        if attacker_ratio > defender_ratio {
+            BattleWinner::Attacker
+        } else if defender_ratio > attacker_ratio {
+            BattleWinner::Defender
+        } else {
+            BattleWinner::Draw
+        }
+    });
+
+    BattleResult {
+        attacker: attacker.clone(),
+        defender: defender.clone(),
+        winner,
+        duration_secs,
+        attacker_base_hp: bases[0],
+        defender_base_hp: bases[1],
+        frames,
+        events,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{DEFAULT_MAX_UNITS, MIN_MAX_UNITS};
+    use crate::i18n::Language;
+    use crate::invasion::InvasionEvent;
+    use crate::multiplayer::MultiplayerError;
+    use crate::shop::OfferKind;
+
+    fn battlefield() -> GameState {
+        let mut game = GameState::fresh().snapshot();
+        game.player_base_hp = 1.0e9;
+        game.max_player_base_hp = 1.0e9;
+        game.enemy_base_hp = 1.0e9;
+        game.max_enemy_base_hp = 1.0e9;
+        game
+    }
+
+    #[test]
+    fn overflow_spawns_merge_into_existing_units() {
+        let mut game = battlefield();
+        for _ in 0..DEFAULT_MAX_UNITS + 100 {
+            game.spawn_unit(UnitType::Small);
+        }
+        assert_eq!(game.player_units.len(), DEFAULT_MAX_UNITS);
+        let single_hp = game.player_units[DEFAULT_MAX_UNITS - 1].max_hp;
+        let total_hp: f32 = game.player_units.iter().map(|u| u.max_hp).sum();
+        let expected = single_hp * (DEFAULT_MAX_UNITS + 100) as f32;
+        assert!((total_hp - expected).abs() < expected * 1.0e-4);
+    }
+
+    #[test]
+    fn errors_serialize_with_code_and_message() {
+        let error = serde_json::to_value(GameError::NotEnoughCoins {
+            needed: 10,
+            have: 3,
+        })
+        .unwrap();
+        assert_eq!(error["code"], "not_enough_coins");
+        assert_eq!(error["needed"], 10);
+        assert_eq!(error["message"], "Not enough coins (need 10, have 3)");
+    }
+
+    #[test]
+    fn shop_offers_spend_prestige_points_until_the_stock_expires() {
+        let mut game = GameState::fresh().snapshot();
+        game.prestige.points = 20;
+        let base_attack = game.prestige.attack_multiplier();
+        assert_eq!(
+            game.buy_shop_offer("war_drum"),
+            Err(GameError::ShopStockExpired)
+        );
+        let offer = |item: &str, price| ShopOffer {
+            item: item.to_string(),
+            kind: OfferKind::Artifact,
+            price,
+        };
+        game.store_shop_stock(ShopStock {
+            rotation: 1,
+            refreshed_at: unix_timestamp() - 60,
+            expires_at: unix_timestamp() + 60,
+            offers: vec![offer("war_drum", 12), offer("lucky_coin", 15)],
+        });
+        assert!(game.buy_shop_offer("war_drum").is_ok());
+        assert_eq!(game.prestige.points, 8);
+        assert!(game.prestige.attack_multiplier() > base_attack);
+        assert_eq!(
+            game.buy_shop_offer("war_drum"),
+            Err(GameError::ShopItemOwned {
+                item: "war_drum".to_string()
+            })
+        );
+        assert_eq!(
+            game.buy_shop_offer("lucky_coin"),
+            Err(GameError::NotEnoughPrestigePoints {
+                needed: 15,
+                have: 8
+            })
+        );
+        assert!(matches!(
+            game.buy_shop_offer("golden_base"),
+            Err(GameError::ShopOfferNotFound { .. })
+        ));
+        let status = game.shop_status();
+        assert!(!status.expired && status.offers[0].owned);
+
+        game.store_shop_stock(ShopStock {
+            rotation: 0,
+            refreshed_at: 0,
+            expires_at: unix_timestamp() - 1,
+            offers: vec![offer("lucky_coin", 1)],
+        });
+        assert!(game.shop_status().expired);
+        assert_eq!(
+            game.buy_shop_offer("lucky_coin"),
+            Err(GameError::ShopStockExpired)
+        );
+    }
+
+    #[test]
+    fn errors_are_translated_from_their_fields() {
+        let error = GameError::ItemOnCooldown {
+            item: "airstrike".to_string(),
+            remaining_secs: 2.5,
+        };
+        assert_eq!(
+            error.message_in(Language::Japanese),
+            "airstrike はあと 2.5 秒使えません"
+        );
+        assert_eq!(error.message_in(Language::English), error.to_string());
+        // ファイルやサーバーからのメッセージは訳さない
+        let storage = GameError::storage("disk full");
+        assert_eq!(storage.message_in(Language::Japanese), "disk full");
+        let throttled = GameError::multiplayer(MultiplayerError::RateLimited {
+            retry_after_secs: 30,
+        });
+        assert_eq!(
+            throttled.message_in(Language::Japanese),
+            "リクエストが多すぎます。30 秒後にやり直してください"
+        );
+    }
+
+    #[test]
+    fn save_codes_round_trip_and_reject_bad_input() {
+        let mut game = battlefield();
+        game.coins = 12_345;
+        let code = game.export_save().unwrap();
+        let restored: GameState = serde_json::from_str(&save_code::decode(&code).unwrap()).unwrap();
+        assert_eq!(restored.coins, 12_345);
+
+        let (_, payload) = code.split_once(':').unwrap();
+        assert!(matches!(
+            save_code::decode(&format!("ccc99:{}", payload)),
+            Err(GameError::SaveCodeTooNew { version: 99, .. })
+        ));
+        assert!(matches!(
+            save_code::decode(&code[..code.len() / 2]),
+            Err(GameError::SaveCodeCorrupt)
+        ));
+    }
+
+    #[test]
+    fn time_away_is_capped_and_paid_in_coins() {
+        let mut game = battlefield();
+        let coins = game.coins;
+        let progress = game.apply_offline_progress(1.0e9).unwrap();
+        assert_eq!(progress.seconds, offline::MAX_OFFLINE_SECS);
+        assert!(progress.enemies_defeated > 0);
+        assert_eq!(game.coins - coins, progress.coins);
+        assert!(game.apply_offline_progress(0.0).is_none());
+    }
+
+    #[test]
+    fn completed_quests_pay_out_once() {
+        let mut game = battlefield();
+        game.quests.refresh(unix_timestamp(), game.stage);
+        // デイリーが先に並ぶ（ウィークリーの必要数はデイリーより多い）
+        let quest = game.quests.list().remove(0);
+        assert!(matches!(
+            game.claim_quest_reward(&quest.id),
+            Err(GameError::QuestNotComplete { .. })
+        ));
+        game.quests.record(quest.metric, quest.target);
+        assert_eq!(game.quests.take_pending().len(), 1);
+        let coins = game.coins;
+        game.claim_quest_reward(&quest.id).unwrap();
+        assert_eq!(game.coins - coins, quest.reward);
+        assert!(matches!(
+            game.claim_quest_reward(&quest.id),
+            Err(GameError::QuestAlreadyClaimed { .. })
+        ));
+    }
+
+    #[test]
+    fn despawn_policy_trades_oldest_small_units_for_coins() {
+        let mut game = battlefield();
+        game.set_unit_caps(UnitCaps::new(
+            MIN_MAX_UNITS,
+            MIN_MAX_UNITS,
+            OverflowPolicy::Despawn,
+        ));
+        let coins = game.coins;
+        for _ in 0..MIN_MAX_UNITS + 10 {
+            game.spawn_unit(UnitType::Small);
+        }
+        assert_eq!(game.player_units.len(), MIN_MAX_UNITS);
+        // 最初の 10 体が消え、残りは出現したままの強さ
+        assert!(game.player_units.iter().all(|u| u.id >= 10));
+        assert!(game.player_units.iter().all(|u| u.hp == u.max_hp));
+        assert_eq!(game.coins - coins, 10);
+    }
+
+    #[test]
+    fn repair_removes_non_finite_values() {
+        let mut game = battlefield();
+        game.spawn_unit(UnitType::Small);
+        game.spawn_unit(UnitType::Small);
+        game.player_units[0].position = f32::NAN;
+        game.player_units[0].attack_cooldown = f32::INFINITY;
+        game.player_units[1].hp = f32::NAN;
+        game.enemy_base_hp = f32::NAN;
+        game.max_player_base_hp = -1.0;
+        game.morale.value = f32::NEG_INFINITY;
+        assert!(!game.is_finite());
+
+        game.repair();
+        assert!(game.is_finite());
+        assert_eq!(game.player_units.len(), 1);
+        assert_eq!(game.player_units[0].position, 0.0);
+        assert_eq!(game.enemy_base_hp, game.max_enemy_base_hp);
+        assert_eq!(game.max_player_base_hp, 1000.0);
+    }
+
+    #[test]
+    fn enemy_personalities_choose_targets_differently() {
+        let mut game = battlefield();
+        game.spawn_unit(UnitType::Small);
+        game.spawn_unit(UnitType::Small);
+        game.spawn_enemy();
+        game.player_units[0].position = 500.0;
+        game.player_units[1].position = 400.0;
+        game.player_units[1].hp = 1.0;
+        game.enemy_units[0].position = 600.0;
+        game.enemy_units[0].stealthed = false;
+        let (near, weak) = (game.player_units[0].id, game.player_units[1].id);
+
+        for (stage, expected) in [(1, Some(near)), (11, None), (21, Some(weak))] {
+            game.stage = stage;
+            game.enemy_units[0].target_id = None;
+            game.update(0.0);
+            assert_eq!(game.enemy_units[0].target_id, expected, "stage {}", stage);
+        }
+    }
+
+    #[test]
+    fn rallied_units_hold_until_the_threshold_gathers() {
+        let mut game = battlefield();
+        game.set_rally_point(100.0, Some(3)).unwrap();
+        assert_eq!(
+            game.set_rally_point(f32::NAN, None),
+            Err(GameError::InvalidRallyPoint)
+        );
+        game.spawn_unit(UnitType::Small);
+        game.spawn_unit(UnitType::Small);
+        for _ in 0..60 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!(game
+            .player_units
+            .iter()
+            .all(|u| u.rallying && (u.position - 100.0).abs() <= rally::HOLD_RADIUS));
+
+        game.spawn_unit(UnitType::Small);
+        for _ in 0..60 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!(game.player_units.iter().all(|u| !u.rallying));
+        assert!(game.player_units.iter().all(|u| u.position > 100.0));
+    }
+
+    #[test]
+    fn units_without_targets_follow_the_stance() {
+        let mut game = battlefield();
+        assert_eq!(
+            game.set_stance(Stance::Hold { position: 2000.0 }),
+            Err(GameError::InvalidStance)
+        );
+        game.set_stance(Stance::Hold { position: 300.0 }).unwrap();
+        game.spawn_unit(UnitType::Small);
+        for _ in 0..100 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!((game.player_units[0].position - 300.0).abs() <= stance::HOLD_TOLERANCE);
+
+        game.set_stance(Stance::Defensive).unwrap();
+        for _ in 0..100 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!(game.player_units[0].position < 300.0);
+    }
+
+    #[test]
+    fn only_anti_air_units_target_flying_enemies() {
+        let mut game = battlefield();
+        game.spawn_unit(UnitType::Small);
+        game.spawn_special(UnitAbility::Ranged);
+        game.spawn_enemy();
+        game.enemy_units[0].is_flying = true;
+        game.enemy_units[0].stealthed = false;
+        let flyer = game.enemy_units[0].id;
+
+        game.update(0.0);
+        assert_eq!(game.player_units[0].target_id, None);
+        assert_eq!(game.player_units[1].target_id, Some(flyer));
+    }
+
+    #[test]
+    fn units_only_fight_within_their_lane() {
+        let mut game = battlefield();
+        game.stage = 60;
+        assert_eq!(game.lane_count(), stage::MAX_LANES);
+        game.spawn_enemy();
+        // 群れで出ても 1 体だけにする（残りのレーンで出撃先が変わらないように）
+        game.enemy_units.truncate(1);
+        game.enemy_units[0].lane = 2;
+        game.enemy_units[0].stealthed = false;
+        game.enemy_units[0].is_flying = false;
+        // 敵のいるレーンへ優先して出撃する
+        game.spawn_unit(UnitType::Small);
+        assert_eq!(game.player_units[0].lane, 2);
+        game.spawn_unit(UnitType::Small);
+        assert_ne!(game.player_units[1].lane, 2);
+
+        game.update(0.0);
+        let enemy = game.enemy_units[0].id;
+        assert_eq!(game.player_units[0].target_id, Some(enemy));
+        assert_eq!(game.player_units[1].target_id, None);
+        assert_eq!(game.enemy_units[0].target_id, Some(game.player_units[0].id));
+    }
+
+    #[test]
+    fn manual_orders_override_ai_until_they_expire() {
+        let mut game = battlefield();
+        game.spawn_unit(UnitType::Small);
+        game.spawn_unit(UnitType::Medium);
+        let (small, medium) = (game.player_units[0].id, game.player_units[1].id);
+        let only_medium = UnitSelector {
+            unit_types: vec![UnitType::Medium],
+            ..Default::default()
+        };
+        assert_eq!(game.select_units(&only_medium), vec![medium]);
+        assert_eq!(
+            game.order_units(&[small], Order::Move { position: 2000.0 }, None),
+            Err(GameError::InvalidOrder)
+        );
+
+        // 指定位置で止まり、期限が切れると進軍に戻る
+        game.order_units(&[small], Order::Move { position: 200.0 }, Some(5.0))
+            .unwrap();
+        for _ in 0..60 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!((game.player_units[0].position - 200.0).abs() <= orders::ARRIVE_RADIUS);
+        assert!(game.player_units[0].order.is_some());
+        for _ in 0..60 {
+            game.enemy_units.clear();
+            game.update(0.05);
+        }
+        assert!(game.player_units[0].order.is_none());
+        assert!(game.player_units[0].position > 200.0 + orders::ARRIVE_RADIUS);
+
+        // 攻撃命令は近い敵より指定した敵を狙う
+        game.enemy_units.clear();
+        game.spawn_enemy();
+        game.spawn_enemy();
+        for enemy in &mut game.enemy_units {
+            enemy.lane = 0;
+            enemy.stealthed = false;
+            enemy.is_flying = false;
+            // 近くの味方が反撃で倒されないように敵の攻撃を止める
+            enemy.attack_cooldown = f32::MAX;
+        }
+        game.enemy_units[0].position = 300.0;
+        let target = game.enemy_units.last().unwrap().id;
+        assert_eq!(
+            game.order_units(&[medium], Order::Attack { target_id: target }, None),
+            Ok(1)
+        );
+        game.update(0.0);
+        assert_eq!(game.player_units[1].target_id, Some(target));
+        assert_eq!(game.cancel_orders(&[small, medium]), 1);
+    }
+
+    #[test]
+    fn summons_escalate_and_cool_down() {
+        let mut game = battlefield();
+        game.coins = 10_000;
+        let first = game.summon_unit(UnitType::Hero).unwrap();
+        assert_eq!(game.player_units[0].unit_type, UnitType::Hero);
+        assert_eq!(game.coins, 10_000 - first);
+        assert!(matches!(
+            game.summon_unit(UnitType::Hero),
+            Err(GameError::SummonOnCooldown { .. })
+        ));
+
+        game.update(61.0);
+        game.enemy_units.clear();
+        let second = game.summon_unit(UnitType::Hero).unwrap();
+        assert!(second > first);
+        game.reset_current_stage();
+        game.update(61.0);
+        let status = game.summon_status();
+        let hero = status
+            .iter()
+            .find(|s| s.unit_type == UnitType::Hero)
+            .unwrap();
+        assert_eq!((hero.cost, hero.purchases), (first, 0));
+    }
+
+    #[test]
+    fn typing_history_imports_once_with_caps() {
+        let monkeytype =
+            "_id,isPb,wpm,acc,rawWpm,consistency,charStats,mode,mode2,testDuration,tags\n\
+            a,false,60,98,62,80,150;2;1;0,time,30,30,\"x,y\"\n\
+            b,false,900,98,62,80,5000;0;0;0,time,15,15,\n\
+            c,false,80,97,81,80,,words,25,20,\n";
+        let history = typing_import::parse(monkeytype).unwrap();
+        assert_eq!(history.source, typing_import::TypingSource::MonkeyType);
+        assert_eq!((history.tests, history.skipped_rows), (2, 1));
+        assert_eq!(history.keystrokes, 153 + 133);
+        assert_eq!(
+            typing_import::parse("date,accuracy\n2024-01-01,98\n").unwrap_err(),
+            GameError::TypingHistoryUnsupported
+        );
+
+        let mut game = battlefield();
+        let coins = game.coins;
+        let huge = TypingHistory {
+            keystrokes: u64::MAX,
+            ..history
+        };
+        let record = game.import_typing_history(&huge).unwrap();
+        assert_eq!(record.keystrokes, typing_import::MAX_IMPORTED_KEYSTROKES);
+        assert_eq!(game.coins - coins, record.coins);
+        assert_eq!(
+            game.import_typing_history(&huge).unwrap_err(),
+            GameError::TypingHistoryAlreadyImported
+        );
+    }
+
+    // 1000 体以上並んでも上限まで合流させて戦力を保ち、その後の更新でも上限を超えない
+    // （索引の検索が全件を見ないことは spatial.rs のテストで確かめる）
+    #[test]
+    fn many_units_merge_down_to_the_cap_and_stay_bounded() {
+        let mut game = battlefield();
+        game.spawn_unit(UnitType::Medium);
+        game.spawn_enemy();
+        let ally = game.player_units.pop().unwrap();
+        let enemy = game.enemy_units.pop().unwrap();
+        for i in 0..1200u32 {
+            let mut unit = ally.clone();
+            unit.id = 10_000 + i;
+            unit.position = (i % 500) as f32;
+            game.player_units.push(unit);
+            let mut unit = enemy.clone();
+            unit.id = 20_000 + i;
+            unit.position = 500.0 + (i % 500) as f32;
+            unit.stealthed = false;
+            game.enemy_units.push(unit);
+        }
+        let total_attack = |units: &[Unit]| units.iter().map(|u| u.attack as f64).sum::<f64>();
+        let player_attack = total_attack(&game.player_units);
+        let enemy_attack = total_attack(&game.enemy_units);
+
+        game.set_unit_caps(UnitCaps::default());
+        assert_eq!(game.player_units.len(), DEFAULT_MAX_UNITS);
+        assert_eq!(game.enemy_units.len(), DEFAULT_MAX_UNITS);
+        assert!((total_attack(&game.player_units) - player_attack).abs() < 1.0);
+        assert!((total_attack(&game.enemy_units) - enemy_attack).abs() < 1.0);
+
+        for _ in 0..60 {
+            game.update(0.016);
+            assert!(game.player_units.len() <= DEFAULT_MAX_UNITS);
+            assert!(game.enemy_units.len() <= DEFAULT_MAX_UNITS);
+        }
+    }
+
+    // 同じ軍勢なら記録まで一致し、強化した側が勝つ
+    #[test]
+    fn pvp_battles_are_deterministic_and_favor_upgrades() {
+        let weak = GameState::fresh().army_snapshot();
+        let mut strong = weak.clone();
+        strong.upgrades.small_attack = 50;
+        strong.upgrades.medium_hp = 50;
+        strong.upgrades.large_attack = 30;
+        strong.prestige_attack = 4;
+
+        let first = simulate_battle(&strong, &weak);
+        let second = simulate_battle(&strong, &weak);
+        assert_eq!(first.winner, BattleWinner::Attacker);
+        assert_eq!(first.duration_secs, second.duration_secs);
+        assert_eq!(first.events.len(), second.events.len());
+        assert_eq!(first.frames.len(), second.frames.len());
+        assert_eq!(first.defender_base_hp, 0.0);
+        assert!(first.duration_secs <= PVP_TIME_LIMIT_SECS);
+
+        assert_eq!(
+            simulate_battle(&weak, &strong).winner,
+            BattleWinner::Defender
+        );
+        assert!(strong.squads().iter().map(|(_, n)| n).sum::<u32>() > 10);
+    }
+
+    // 同じシードと入力なら同じ戦場になり、記録から再計算できる
+    #[test]
+    fn seeded_replays_resimulate_the_session() {
+        let mut game = battlefield();
+        game.coins = 10_000;
+        game.start_replay(7);
+        for tick in 0..600u32 {
+            if tick % 20 == 0 {
+                game.record_input(1, 2);
+                game.spawn_unit(UnitType::Small);
+            }
+            if tick == 100 {
+                game.purchase_upgrade("attack", "small").unwrap();
+            }
+            if tick == 300 {
+                game.spawn_unit(UnitType::Medium);
+            }
+            game.step();
+        }
+        let replay = game.stop_replay().unwrap();
+        assert_eq!(replay.ticks, 600);
+        assert!(replay
+            .inputs
+            .iter()
+            .any(|entry| entry.tick == 100 && matches!(entry.input, ReplayInput::Purchase { .. })));
+        assert!(replay.verify(&game));
+        assert!(game.stop_replay().is_none());
+
+        let mut first = battlefield();
+        let mut second = battlefield();
+        first.seed_rng(3);
+        second.seed_rng(3);
+        for _ in 0..600 {
+            first.step();
+            second.step();
+        }
+        assert!(!first.enemy_units.is_empty());
+        assert_eq!(
+            serde_json::to_string(&first.enemy_units).unwrap(),
+            serde_json::to_string(&second.enemy_units).unwrap()
+        );
+    }
+
+    // 協力プレイでは撃破報酬が倒したユニットを出した側の財布に入る
+    #[test]
+    fn coop_kills_pay_the_owner() {
+        use crate::coop::InputSource;
+
+        let mut game = battlefield();
+        assert_eq!(game.coop.player_for(InputSource::Mouse), CoopPlayer::A);
+        game.set_coop(true);
+        assert_eq!(game.coop.player_for(InputSource::Keyboard), CoopPlayer::A);
+        assert_eq!(game.coop.player_for(InputSource::Mouse), CoopPlayer::B);
+
+        game.spawn_unit_for(UnitType::Large, CoopPlayer::B);
+        let (position, lane) = (game.player_units[0].position, game.player_units[0].lane);
+        game.spawn_enemy();
+        let enemy = game.enemy_units.last_mut().unwrap();
+        enemy.hp = 0.1;
+        enemy.position = position + 1.0;
+        enemy.lane = lane;
+        enemy.stealthed = false;
+        enemy.is_flying = false;
+        let coins = game.coins;
+        for _ in 0..30 {
+            game.update(0.1);
+        }
+        let status = game.coop_status();
+        assert!(status.kills_b >= 1);
+        assert_eq!(status.kills_a, 0);
+        assert!(status.coins_b >= 1);
+        assert_eq!(game.coins, coins);
+
+        // 強化は共有で、B の財布から払う
+        let cost = game.upgrades.get_cost("attack", "large");
+        game.coop.add_coins(cost as f64);
+        let before = game.coop.coins();
+        game.purchase_upgrade_as("attack", "large", CoopPlayer::B)
+            .unwrap();
+        assert_eq!(game.coop.coins(), before - cost);
+        assert_eq!(game.coins, coins);
+        assert_eq!(game.upgrades.large_attack, 10);
+    }
+
+    #[test]
+    fn late_stages_mix_enemy_kinds() {
+        let mut game = battlefield();
+        game.seed_rng(7);
+        for _ in 0..50 {
+            game.spawn_enemy();
+        }
+        assert!(game
+            .enemy_units
+            .iter()
+            .all(|e| e.kind == EnemyKind::Standard));
+
+        game.enemy_units.clear();
+        game.stage = 60;
+        for _ in 0..200 {
+            game.spawn_enemy();
+        }
+        let count = |kind| game.enemy_units.iter().filter(|e| e.kind == kind).count();
+        assert!(count(EnemyKind::Armored) > 0);
+        assert!(count(EnemyKind::Fast) > 0);
+        // 群れはまとめて出るので、必ず群れの数の倍数になる
+        assert!(count(EnemyKind::Swarm) > 0);
+        assert_eq!(count(EnemyKind::Swarm) % 4, 0);
+
+        let standard = game
+            .enemy_units
+            .iter()
+            .find(|e| e.kind == EnemyKind::Standard)
+            .unwrap();
+        let fast = game
+            .enemy_units
+            .iter()
+            .find(|e| e.kind == EnemyKind::Fast && e.unit_type == standard.unit_type)
+            .unwrap();
+        assert_eq!(fast.speed, standard.speed * 2.0);
+        assert!(fast.max_hp < standard.max_hp);
+    }
+
+    #[test]
+    fn energy_summons_spend_the_charged_pool() {
+        let mut game = battlefield();
+        game.energy.charge(2, 1);
+        assert_eq!(game.energy.energy(), 5.0);
+        assert!(matches!(
+            game.summon_with_energy(UnitType::Large),
+            Err(GameError::NotEnoughEnergy { .. })
+        ));
+        assert!(matches!(
+            game.summon_with_energy(UnitType::Hero),
+            Err(GameError::NotEnergySummonable { .. })
+        ));
+        game.summon_with_energy(UnitType::Medium).unwrap();
+        game.summon_with_energy(UnitType::Small).unwrap();
+        assert_eq!(game.energy.energy(), 1.0);
+        assert_eq!(game.player_units.len(), 2);
+    }
+
+    #[test]
+    fn auto_buy_walks_the_queue_and_reads_old_saves() {
+        let old: AutoBuyConfig = serde_json::from_str(
+            r#"{"enabled":true,"upgrade_type":"attack","unit_type":"small","remaining_time":5.0}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            old.queue,
+            vec![AutoBuyEntry {
+                upgrade_type: "attack".to_string(),
+                unit_type: "small".to_string(),
+                max_level: None,
+            }]
+        );
+
+        let mut game = battlefield();
+        game.auto_buy = AutoBuyConfig {
+            enabled: true,
+            queue: vec![
+                AutoBuyEntry {
+                    upgrade_type: "coin_rate".to_string(),
+                    unit_type: String::new(),
+                    max_level: Some(10),
+                },
+                AutoBuyEntry {
+                    upgrade_type: "hp".to_string(),
+                    unit_type: "small".to_string(),
+                    max_level: None,
+                },
+            ],
+            remaining_time: 60.0,
+        };
+        game.coins = upgrade_cost(0) * 2;
+        game.update(0.01);
+        game.update(0.01);
+        // 上限に達した先頭を飛ばして次の対象を買う
+        assert_eq!(game.upgrades.coin_rate, 10);
+        assert_eq!(game.upgrades.small_hp, 10);
+        assert!(AutoBuyConfig::validate(&[AutoBuyEntry {
+            upgrade_type: "dance".to_string(),
+            unit_type: String::new(),
+            max_level: None,
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn respec_refunds_tracked_spend_with_a_growing_fee() {
+        let mut game = GameState::fresh().snapshot();
+        assert!(matches!(game.respec(80), Err(GameError::NothingToRespec)));
+
+        game.coins = 1_000_000;
+        game.purchase_upgrade("attack", "small").unwrap();
+        game.purchase_upgrade("attack", "small").unwrap();
+        game.purchase_upgrade("coin_rate", "").unwrap();
+        let spent = upgrade_cost(0) * 2 + upgrade_cost(10);
+        assert_eq!(game.respec_quote(80).spent, spent);
+
+        let coins = game.coins;
+        let quote = game.respec(80).unwrap();
+        assert_eq!(quote.refund, spent * 80 / 100);
+        assert_eq!(game.coins, coins + quote.refund - quote.fee);
+        assert_eq!(game.upgrades.levels(), Upgrades::new().levels());
+        assert_eq!(game.respec_quote(80).spent, 0);
+
+        // 2 回目は手数料が倍
+        game.purchase_upgrade("hp", "large").unwrap();
+        assert_eq!(game.respec(80).unwrap().fee, quote.fee * 2);
+    }
+
+    // 買うとコインが減り、使うと効果が出て、使った後は待ち時間がある
+    #[test]
+    fn items_are_bought_used_and_cool_down() {
+        let mut game = battlefield();
+        game.coins = 100_000;
+        assert!(matches!(
+            game.use_item(ItemKind::RepairKit),
+            Err(GameError::NoItemsLeft { .. })
+        ));
+        let cost = game.buy_item(ItemKind::RepairKit, 2).unwrap();
+        assert_eq!(game.coins, 100_000 - cost);
+        game.player_base_hp = 1.0;
+        game.use_item(ItemKind::RepairKit).unwrap();
+        assert!(game.player_base_hp > game.max_player_base_hp * 0.3);
+        assert!(matches!(
+            game.use_item(ItemKind::RepairKit),
+            Err(GameError::ItemOnCooldown { .. })
+        ));
+        assert!(matches!(
+            game.buy_item(ItemKind::Airstrike, item::MAX_STACK + 1),
+            Err(GameError::ItemStackFull { .. })
+        ));
+
+        game.spawn_enemy();
+        let hp = game.enemy_units[0].max_hp;
+        game.buy_item(ItemKind::Airstrike, 1).unwrap();
+        game.buy_item(ItemKind::FreezeBomb, 1).unwrap();
+        game.use_item(ItemKind::Airstrike).unwrap();
+        game.use_item(ItemKind::FreezeBomb).unwrap();
+        let position = game.enemy_units[0].position;
+        game.update(0.1);
+        assert!(game.enemy_units[0].hp < hp);
+        assert_eq!(game.enemy_units[0].position, position);
+    }
+
+    #[test]
+    fn invasions_arrive_while_idle_and_pay_when_repelled() {
+        let mut game = battlefield();
+        game.set_idle(true);
+        game.update(10.0 * 60.0);
+        assert!(matches!(
+            game.invasion.take_pending()[..],
+            [InvasionEvent::Warning { enemies: 6, .. }]
+        ));
+        assert!(game.enemy_units.is_empty());
+        assert!(game.invasion_status().arrives_in.is_some());
+
+        game.update(invasion::WARNING_SECS);
+        assert!(matches!(
+            game.invasion.take_pending()[..],
+            [InvasionEvent::Started { .. }]
+        ));
+        assert!(game.invasion_status().remaining >= 6);
+
+        let coins = game.coins;
+        for enemy in &mut game.enemy_units {
+            enemy.hp = 1.0;
+        }
+        game.items.queue_airstrike();
+        game.update(0.1);
+        assert!(game.enemy_units.is_empty());
+        assert!(game.coins > coins);
+        assert!(matches!(
+            game.invasion.take_pending()[..],
+            [InvasionEvent::Repelled { stage: 1, .. }]
+        ));
+        assert_eq!(game.invasion.repelled, 1);
+    }
+
+    #[test]
+    fn season_rollover_pays_once_and_restarts_the_run() {
+        let mut game = GameState::fresh().snapshot();
+        // 初めて見たシーズンでは周回をやり直さない
+        assert!(game.apply_season(2, &[]).is_none());
+        game.stage = 30;
+        game.coins = 5_000;
+        let results = [SeasonResult {
+            season: 2,
+            stage: 30,
+            coins: 5_000,
+            rank: 1,
+            players: 8,
+        }];
+        let rollover = game.apply_season(3, &results).unwrap();
+        assert!(rollover.reset);
+        assert_eq!(rollover.points, crate::prestige::points_for_stage(30) + 5);
+        assert_eq!((game.stage, game.coins), (1, 0));
+        assert_eq!(game.prestige.points, rollover.points);
+        assert!(game.apply_season(3, &results).is_none());
+    }
+
+    // 書き出しと読み直しで変わらず、壊れた数値はエラーとして報告する
+    #[test]
+    fn integrity_check_round_trips_and_reports_bad_values() {
+        use crate::save_check::ProblemSeverity;
+
+        let mut game = GameState::fresh().snapshot();
+        let report = game.verify_integrity();
+        assert_eq!(
+            report.checks,
+            ["serialize", "reload", "ranges", "checksum", "version"]
+        );
+        // ディスクのセーブは環境によるので、状態そのものの検査だけを見る
+        assert!(report.problems.iter().all(|p| p.check == "checksum"));
+
+        game.player_base_hp = f32::NAN;
+        let report = game.verify_integrity();
+        assert!(report
+            .problems
+            .iter()
+            .any(|p| p.check == "ranges" && p.severity == ProblemSeverity::Error));
+        assert!(!report.healthy);
+    }
+
+    // 値段が並んだら一覧の先のものから買う
+    #[test]
+    fn hotkey_buys_the_cheapest_upgrade() {
+        let mut game = GameState::fresh().snapshot();
+        game.coins = 0;
+        assert!(matches!(
+            game.purchase_cheapest_upgrade(),
+            Err(GameError::NotEnoughCoins { .. })
+        ));
+
+        game.coins = 1_000_000;
+        assert_eq!(
+            game.purchase_cheapest_upgrade().unwrap(),
+            ("attack", "small", upgrade_cost(0))
+        );
+        assert_eq!(
+            game.purchase_cheapest_upgrade().unwrap(),
+            ("attack", "medium", upgrade_cost(0))
+        );
+        // 1 回の購入で 10 レベル上がる
+        assert_eq!(game.upgrades.get_cost("attack", "small"), upgrade_cost(10));
+    }
+
+    // 難易度は敵と基地に掛かり、戦闘中の変更はステージのやり直しを伴う
+    #[test]
+    fn difficulty_scales_enemies_and_needs_a_stage_reset() {
+        use crate::rules::{Difficulty, GameRules};
+
+        let mut game = GameState::fresh().snapshot();
+        game.spawn_unit(UnitType::Small);
+        let hard = GameRules::preset(Difficulty::Hard);
+        assert!(matches!(
+            game.set_difficulty(hard.clone(), false),
+            Err(GameError::DifficultyNeedsReset)
+        ));
+        assert_eq!(game.rules().difficulty, Difficulty::Normal);
+
+        game.set_difficulty(hard.clone(), true).unwrap();
+        assert!(game.player_units.is_empty());
+        assert_eq!(game.max_player_base_hp, 1000.0 * hard.base_hp);
+        assert_eq!(game.player_base_hp, game.max_player_base_hp);
+        assert_eq!(
+            game.enemy_base_hp,
+            stage::enemy_base_hp(game.stage) * hard.enemy_scaling
+        );
+
+        let mut normal = GameState::fresh().snapshot();
+        normal.seed_rng(5);
+        game.seed_rng(5);
+        normal.spawn_enemy();
+        game.spawn_enemy();
+        let ratio = game.enemy_units[0].max_hp / normal.enemy_units[0].max_hp;
+        assert!((ratio - hard.enemy_scaling).abs() < 1e-4);
+        assert!(game.rules().spawn_interval(100) < normal.rules().spawn_interval(100));
+
+        let broken = GameRules {
+            difficulty: Difficulty::Custom,
+            enemy_scaling: 0.0,
+            ..GameRules::default()
+        };
+        assert!(matches!(
+            game.set_difficulty(broken, false),
+            Err(GameError::InvalidGameRules { .. })
+        ));
+
+        // 戦場が空ならそのまま変えられ、基地強化の分は残る
+        game.enemy_units.clear();
+        game.set_difficulty(GameRules::preset(Difficulty::Normal), false)
+            .unwrap();
+        assert!((game.max_player_base_hp - 1000.0).abs() < 1e-3);
+    }
+
+    // シミュレーターの刻みはアプリと同じ入力の反映で、同じシードなら同じ結果になる
+    #[test]
+    fn run_ticks_applies_input_and_is_reproducible() {
+        let run = |seed: u64| {
+            let mut game = GameState::headless();
+            game.seed_rng(seed);
+            game.run_ticks(600, STEP_SECS, |_, tick| TickInput {
+                clicks: u32::from(tick % 60 == 0),
+                types: u32::from(tick % 15 == 0),
+                reinforcement: tick == 0,
+                ..TickInput::default()
+            });
+            game
+        };
+
+        let mut first = GameState::headless();
+        first.run_tick(
+            TickInput {
+                clicks: 1,
+                types: 2,
+                ..TickInput::default()
+            },
+            0.0,
+        );
+        assert_eq!(first.player_units.len(), 3);
+        assert_eq!((first.click_count, first.type_count), (1, 2));
+
+        let a = run(9);
+        let b = run(9);
+        assert_eq!(a.tick, 600);
+        assert_eq!(a.click_count, 10);
+        assert_eq!(a.type_count, 40);
+        assert_eq!(a.coins, b.coins);
+        assert_eq!(a.player_units.len(), b.player_units.len());
+        assert_eq!(a.enemy_units.len(), b.enemy_units.len());
+    }
+
+    // 見た目は条件を満たすまで選べず、解除は転生や同期で失われない
+    #[test]
+    fn cosmetics_unlock_by_stage_and_survive_sync() {
+        let mut game = GameState::fresh().snapshot();
+        game.update(STEP_SECS);
+        assert!(game.cosmetics.is_unlocked("recruit"));
+        assert_eq!(
+            game.set_cosmetic(CosmeticKind::Avatar, Some("knight".to_string())),
+            Err(GameError::CosmeticLocked {
+                id: "knight".to_string()
+            })
+        );
+        assert_eq!(
+            game.set_cosmetic(CosmeticKind::Skin, Some("knight".to_string())),
+            Err(GameError::UnknownCosmetic {
+                id: "knight".to_string()
+            })
+        );
+
+        game.stage = 10;
+        game.set_cosmetic(CosmeticKind::Avatar, Some("knight".to_string()))
+            .unwrap();
+        game.stage = 1;
+        game.set_cosmetic(CosmeticKind::Skin, Some("classic".to_string()))
+            .unwrap();
+        assert_eq!(game.cosmetics.avatar.as_deref(), Some("knight"));
+
+        let mut other = GameState::fresh().snapshot();
+        other.import_progress(&game.export_progress());
+        assert!(other.cosmetics.is_unlocked("knight"));
+        assert_eq!(other.cosmetics.avatar.as_deref(), Some("knight"));
+        assert_eq!(other.cosmetics.skin.as_deref(), Some("classic"));
+        other
+            .set_cosmetic(CosmeticKind::Avatar, Some("knight".to_string()))
+            .unwrap();
+        other.set_cosmetic(CosmeticKind::Avatar, None).unwrap();
+        assert!(other
+            .cosmetics_status()
+            .iter()
+            .all(|cosmetic| cosmetic.kind != CosmeticKind::Avatar || !cosmetic.selected));
+    }
+}
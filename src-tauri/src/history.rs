@@ -0,0 +1,102 @@
+use crate::game::GameState;
+use crate::profile;
+use crate::save_writer::{self, SaveJob};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+// 1 時間ごとのサンプルを 30 日分残す
+const MAX_SAMPLES: usize = 30 * 24;
+
+// その時間に入って最初に見た進行状況。hour は UNIX 時刻を 3600 で割ったもの
+#[derive(Clone, Copy, Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct ProgressSample {
+    pub hour: i64,
+    pub coins: u64,
+    pub stage: u32,
+    pub player_units: u32,
+    pub enemy_units: u32,
+}
+
+impl ProgressSample {
+    fn new(hour: i64, game: &GameState) -> Self {
+        Self {
+            hour,
+            coins: game.coins,
+            stage: game.stage,
+            player_units: game.player_units.len() as u32,
+            enemy_units: game.enemy_units.len() as u32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryRange {
+    Day,
+    Week,
+    Month,
+}
+
+impl HistoryRange {
+    fn hours(self) -> i64 {
+        match self {
+            HistoryRange::Day => 24,
+            HistoryRange::Week => 7 * 24,
+            HistoryRange::Month => 30 * 24,
+        }
+    }
+}
+
+fn history_file_path() -> Option<PathBuf> {
+    profile::data_dir().map(|dir| dir.join("progress_history.json"))
+}
+
+// 進行状況のリングバッファ（サーバーの履歴を使わずに統計画面でグラフにする）
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct ProgressHistory {
+    samples: VecDeque<ProgressSample>,
+}
+
+impl ProgressHistory {
+    pub fn load() -> Self {
+        history_file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    // 書けないときは save_writer がメモリに残して試し直す
+    pub fn save(&self) {
+        if let Some(path) = history_file_path() {
+            if let Ok(json) = serde_json::to_vec(self) {
+                save_writer::submit(SaveJob::new(path, json));
+            }
+        }
+    }
+
+    // 時間が変わったら 1 つ積む。積んだら true（保存の合図）
+    pub fn record(&mut self, now: i64, game: &GameState) -> bool {
+        let hour = now.div_euclid(3600);
+        // 時計が戻った場合も同じ時間を二重に積まない
+        if self.samples.back().is_some_and(|last| last.hour >= hour) {
+            return false;
+        }
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ProgressSample::new(hour, game));
+        true
+    }
+
+    // 遊んでいなかった時間は抜けたまま返す（グラフ側で線をつなぐ）
+    pub fn series(&self, range: HistoryRange, now: i64) -> Vec<ProgressSample> {
+        let start = now.div_euclid(3600) - range.hours();
+        self.samples
+            .iter()
+            .filter(|sample| sample.hour > start)
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,308 @@
+use crate::input_hook::InputEvent;
+use rdev::Key;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+// 続けて押す組み合わせの段の間に待つ時間と、段の上限
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1500);
+const MAX_SEQUENCE_STEPS: usize = 4;
+
+// ホットキーに使えるキーの名前
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("A", Key::KeyA),
+    ("B", Key::KeyB),
+    ("C", Key::KeyC),
+    ("D", Key::KeyD),
+    ("E", Key::KeyE),
+    ("F", Key::KeyF),
+    ("G", Key::KeyG),
+    ("H", Key::KeyH),
+    ("I", Key::KeyI),
+    ("J", Key::KeyJ),
+    ("K", Key::KeyK),
+    ("L", Key::KeyL),
+    ("M", Key::KeyM),
+    ("N", Key::KeyN),
+    ("O", Key::KeyO),
+    ("P", Key::KeyP),
+    ("Q", Key::KeyQ),
+    ("R", Key::KeyR),
+    ("S", Key::KeyS),
+    ("T", Key::KeyT),
+    ("U", Key::KeyU),
+    ("V", Key::KeyV),
+    ("W", Key::KeyW),
+    ("X", Key::KeyX),
+    ("Y", Key::KeyY),
+    ("Z", Key::KeyZ),
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("SPACE", Key::Space),
+    ("TAB", Key::Tab),
+    ("ESCAPE", Key::Escape),
+    ("INSERT", Key::Insert),
+    ("HOME", Key::Home),
+    ("END", Key::End),
+    ("PAGEUP", Key::PageUp),
+    ("PAGEDOWN", Key::PageDown),
+];
+
+// 修飾キーとキー 1 つの組み合わせ（例: "Ctrl+Alt+W"）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hotkey {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+    key: Key,
+}
+
+impl Hotkey {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut hotkey = Self {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: Key::Unknown(0),
+        };
+        let mut key = None;
+        for part in text.split('+').map(|part| part.trim().to_uppercase()) {
+            match part.as_str() {
+                "CTRL" | "CONTROL" => hotkey.ctrl = true,
+                "ALT" | "OPTION" => hotkey.alt = true,
+                "SHIFT" => hotkey.shift = true,
+                "META" | "SUPER" | "WIN" | "CMD" => hotkey.meta = true,
+                name => {
+                    // キーは 1 つだけ
+                    if key.is_some() {
+                        return None;
+                    }
+                    key = KEY_NAMES
+                        .iter()
+                        .find(|(candidate, _)| *candidate == name)
+                        .map(|&(_, key)| key);
+                    key?;
+                }
+            }
+        }
+        hotkey.key = key?;
+        Some(hotkey)
+    }
+
+    fn modifiers_match(&self, held: &HashSet<Key>) -> bool {
+        let ctrl = held.contains(&Key::ControlLeft) || held.contains(&Key::ControlRight);
+        let alt = held.contains(&Key::Alt) || held.contains(&Key::AltGr);
+        let shift = held.contains(&Key::ShiftLeft) || held.contains(&Key::ShiftRight);
+        let meta = held.contains(&Key::MetaLeft) || held.contains(&Key::MetaRight);
+        (ctrl, alt, shift, meta) == (self.ctrl, self.alt, self.shift, self.meta)
+    }
+}
+
+// 続けて押す組み合わせ（例: "Ctrl+K, P"）。1 段なら Hotkey と同じ
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotkeySequence {
+    steps: Vec<Hotkey>,
+}
+
+impl HotkeySequence {
+    pub fn parse(text: &str) -> Option<Self> {
+        let steps = text
+            .split(',')
+            .map(Hotkey::parse)
+            .collect::<Option<Vec<_>>>()?;
+        (steps.len() <= MAX_SEQUENCE_STEPS).then_some(Self { steps })
+    }
+}
+
+impl From<Hotkey> for HotkeySequence {
+    fn from(hotkey: Hotkey) -> Self {
+        Self {
+            steps: vec![hotkey],
+        }
+    }
+}
+
+fn is_modifier(key: &Key) -> bool {
+    matches!(
+        key,
+        Key::ControlLeft
+            | Key::ControlRight
+            | Key::Alt
+            | Key::AltGr
+            | Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::MetaLeft
+            | Key::MetaRight
+    )
+}
+
+// 押されている修飾キーを追い、ホットキーが押された瞬間を記録する
+// （一時停止中の入力も見るので InputCounter の押下状態とは別に持つ）
+#[derive(Default)]
+pub struct HotkeyDetector {
+    hotkey: Option<Hotkey>,
+    modifiers: HashSet<Key>,
+    key_held: bool,
+    triggered: bool,
+}
+
+impl HotkeyDetector {
+    pub fn set_hotkey(&mut self, hotkey: Option<Hotkey>) {
+        self.hotkey = hotkey;
+        self.key_held = false;
+        self.triggered = false;
+    }
+
+    pub fn observe(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::KeyPress(key) if is_modifier(key) => {
+                self.modifiers.insert(*key);
+            }
+            InputEvent::KeyRelease(key) if is_modifier(key) => {
+                self.modifiers.remove(key);
+            }
+            InputEvent::KeyPress(key) => {
+                let Some(hotkey) = self.hotkey else {
+                    return;
+                };
+                // 押しっぱなしのキーリピートでは切り替えない
+                if *key == hotkey.key && !self.key_held {
+                    self.key_held = true;
+                    if hotkey.modifiers_match(&self.modifiers) {
+                        self.triggered = true;
+                    }
+                }
+            }
+            InputEvent::KeyRelease(key) => {
+                if self.hotkey.is_some_and(|hotkey| hotkey.key == *key) {
+                    self.key_held = false;
+                }
+            }
+            InputEvent::Click(_) | InputEvent::Scroll(_) | InputEvent::MouseMove(_) => {}
+        }
+    }
+
+    pub fn take_triggered(&mut self) -> bool {
+        std::mem::take(&mut self.triggered)
+    }
+}
+
+// 段ごとの検出器と、どこまで押し進めたか
+struct SequenceDetector {
+    steps: Vec<HotkeyDetector>,
+    progress: usize,
+    last_step: Instant,
+    // キーリピートで途中まで進んだ組み合わせを崩さないため
+    held: HashSet<Key>,
+}
+
+impl SequenceDetector {
+    fn new(sequence: HotkeySequence) -> Self {
+        Self {
+            steps: sequence
+                .steps
+                .into_iter()
+                .map(|hotkey| {
+                    let mut detector = HotkeyDetector::default();
+                    detector.set_hotkey(Some(hotkey));
+                    detector
+                })
+                .collect(),
+            progress: 0,
+            last_step: Instant::now(),
+            held: HashSet::new(),
+        }
+    }
+
+    // 最後の段まで押し切ったら true
+    fn observe(&mut self, event: &InputEvent) -> bool {
+        let hits: Vec<bool> = self
+            .steps
+            .iter_mut()
+            .map(|detector| {
+                detector.observe(event);
+                detector.take_triggered()
+            })
+            .collect();
+        match event {
+            InputEvent::KeyPress(key) if !is_modifier(key) => {
+                if !self.held.insert(*key) {
+                    return false;
+                }
+            }
+            InputEvent::KeyRelease(key) => {
+                self.held.remove(key);
+                return false;
+            }
+            _ => return false,
+        }
+        if self.progress > 0 && self.last_step.elapsed() > SEQUENCE_TIMEOUT {
+            self.progress = 0;
+        }
+        // 違うキーが挟まったら最初からやり直す（そのキーが最初の段ならそこから）
+        self.progress = if hits[self.progress] {
+            self.progress + 1
+        } else {
+            usize::from(hits[0])
+        };
+        self.last_step = Instant::now();
+        if self.progress == self.steps.len() {
+            self.progress = 0;
+            return true;
+        }
+        false
+    }
+}
+
+// 複数のホットキーと、それぞれに結び付けた値（呼び出すユニットや実行するコマンド）
+pub struct HotkeyBindings<T> {
+    bindings: Vec<(T, SequenceDetector)>,
+}
+
+impl<T> Default for HotkeyBindings<T> {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> HotkeyBindings<T> {
+    pub fn new<H: Into<HotkeySequence>>(bindings: Vec<(T, H)>) -> Self {
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(value, hotkey)| (value, SequenceDetector::new(hotkey.into())))
+                .collect(),
+        }
+    }
+
+    // この入力で押されたホットキーの値
+    pub fn observe(&mut self, event: &InputEvent) -> Vec<T> {
+        self.bindings
+            .iter_mut()
+            .filter_map(|(value, detector)| detector.observe(event).then(|| value.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::error::GameError;
+use crate::hotkey::HotkeySequence;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// ウィジェットはクリックを透過するので、ゲームの操作をキーボードから行う
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    TogglePause,
+    ResetStage,
+    ToggleWidget,
+    BuyCheapestUpgrade,
+    ToggleLowDistraction,
+}
+
+// 操作ごとのホットキー（空なら無効。"Ctrl+K, R" のように続けて押す組み合わせも書ける）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ActionHotkeys {
+    pub toggle_pause: String,
+    // 戦況を失うので既定では割り当てない
+    pub reset_stage: String,
+    pub toggle_widget: String,
+    pub buy_cheapest_upgrade: String,
+    pub toggle_low_distraction: String,
+}
+
+impl Default for ActionHotkeys {
+    fn default() -> Self {
+        Self {
+            toggle_pause: "Ctrl+Alt+P".to_string(),
+            reset_stage: String::new(),
+            toggle_widget: "Ctrl+Alt+H".to_string(),
+            buy_cheapest_upgrade: "Ctrl+Alt+U".to_string(),
+            toggle_low_distraction: "Ctrl+Alt+L".to_string(),
+        }
+    }
+}
+
+impl ActionHotkeys {
+    pub fn hotkeys(&self) -> Result<Vec<(HotkeyAction, HotkeySequence)>, GameError> {
+        [
+            (HotkeyAction::TogglePause, &self.toggle_pause),
+            (HotkeyAction::ResetStage, &self.reset_stage),
+            (HotkeyAction::ToggleWidget, &self.toggle_widget),
+            (HotkeyAction::BuyCheapestUpgrade, &self.buy_cheapest_upgrade),
+            (
+                HotkeyAction::ToggleLowDistraction,
+                &self.toggle_low_distraction,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(action, text)| {
+            HotkeySequence::parse(text)
+                .map(|sequence| (action, sequence))
+                .ok_or_else(|| GameError::InvalidHotkey {
+                    hotkey: text.clone(),
+                })
+        })
+        .collect()
+    }
+}
+
+// 画面側で押されたことを一瞬表示するための hotkey-action イベント
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct HotkeyActionFeedback {
+    pub action: HotkeyAction,
+    pub ok: bool,
+    pub message: String,
+}
+
+impl HotkeyActionFeedback {
+    pub fn new(action: HotkeyAction, result: Result<String, GameError>) -> Self {
+        let ok = result.is_ok();
+        let message = result.unwrap_or_else(|err| err.message());
+        Self {
+            action,
+            ok,
+            message,
+        }
+    }
+}
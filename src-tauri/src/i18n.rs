@@ -0,0 +1,574 @@
+use serde_json::Value;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// 通知・エラー・トレイなど、バックエンドで作る文言の言語（画面側と set_locale で揃える）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    English,
+    Japanese,
+}
+
+impl Language {
+    // "ja-JP" のような地域付きの指定は言語部分で判定し、対応していなければ英語
+    pub fn of(locale: &str) -> Self {
+        match locale.split(['-', '_']).next().unwrap_or_default() {
+            "ja" => Self::Japanese,
+            _ => Self::English,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Japanese => "ja",
+        }
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::English => EN,
+            Self::Japanese => JA,
+        }
+    }
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+pub fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::Japanese,
+        _ => Language::English,
+    }
+}
+
+pub fn set_locale(locale: &str) -> Language {
+    let language = Language::of(locale);
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+    language
+}
+
+// 英語の文言（エラーの英語は GameError の #[error] のまま）
+const EN: &[(&str, &str)] = &[
+    ("notification.stage_clear.title", "Stage cleared"),
+    ("notification.stage_clear.body", "Stage {stage} cleared"),
+    ("notification.base_danger.title", "Base under attack"),
+    (
+        "notification.base_danger.body",
+        "Base HP is at {percent:.0}%",
+    ),
+    ("notification.achievement.title", "Achievement unlocked"),
+    ("notification.auto_buy.title", "Auto-buy finished"),
+    ("notification.auto_buy.body", "Auto-buy time ran out"),
+    ("notification.sync_failed.title", "Sync failed"),
+    ("notification.invasion_warning.title", "Invasion incoming"),
+    (
+        "notification.invasion_warning.body",
+        "{enemies} enemies arrive in {seconds:.0} seconds",
+    ),
+    ("notification.invasion_repelled.title", "Invasion repelled"),
+    (
+        "notification.invasion_repelled.body",
+        "Earned {coins} bonus coins",
+    ),
+    ("hotkey.resumed", "Resumed"),
+    ("hotkey.paused", "Paused"),
+    ("hotkey.stage_reset", "Stage reset"),
+    ("hotkey.widget_hidden", "Widget hidden"),
+    ("hotkey.widget_shown", "Widget shown"),
+    ("hotkey.bought", "Bought {name} for {cost} coins"),
+    ("hotkey.low_distraction_on", "Low-distraction mode on"),
+    ("hotkey.low_distraction_off", "Low-distraction mode off"),
+    ("tray.open", "Open main window"),
+    ("tray.pause", "Pause game"),
+    ("tray.widget", "Show widget"),
+    ("tray.sync", "Sync now"),
+    ("tray.quit", "Quit"),
+    ("tray.tooltip", "Stage {stage} / {coins} coins"),
+    ("save_check.reload", "State changes after a save and reload"),
+    (
+        "save_check.version",
+        "Exported save code does not match the state",
+    ),
+    (
+        "save_check.non_finite",
+        "Battle values contain NaN or infinity",
+    ),
+    ("save_check.stage_zero", "Stage is 0"),
+    ("save_check.base_hp", "Base HP is above its maximum"),
+    (
+        "save_check.repaired",
+        "Some values are out of range and will be reset on the next load",
+    ),
+    ("save_check.duplicate_id", "Two units share the same id"),
+    (
+        "save_check.id_ahead",
+        "A unit id is ahead of the id counter",
+    ),
+    ("save_check.auto_buy", "Auto-buy queue: {error}"),
+    ("save_check.not_saved", "Nothing has been saved to disk yet"),
+    ("save_check.unreadable", "Save file can't be read: {error}"),
+    (
+        "save_check.invalid",
+        "Save file on disk is not a valid save",
+    ),
+    ("save_check.signature", "Save file signature does not match"),
+    ("save_check.unsigned", "Save file has no signature"),
+    (
+        "save_check.tampered",
+        "Save was marked as modified: {reasons}",
+    ),
+    ("input_backend.hook_failed", "Input hook failed: {error}"),
+    (
+        "input_backend.macos_accessibility",
+        "Allow this app under Privacy & Security > Accessibility to count input",
+    ),
+    (
+        "input_backend.no_display",
+        "No X11 display (DISPLAY is not set)",
+    ),
+    (
+        "input_backend.xwayland",
+        "Running through XWayland: only input to X11 windows is counted",
+    ),
+    (
+        "input_backend.evdev_unreadable",
+        "Can't read {path}: {error}",
+    ),
+    (
+        "input_backend.evdev_permission",
+        "No readable devices in {path} (add your user to the input group)",
+    ),
+    (
+        "input_backend.devices_closed",
+        "All input devices were closed",
+    ),
+    ("input_backend.panicked", "Input hook crashed"),
+    ("input_backend.stopped", "Input hook stopped"),
+    (
+        "input_backend.none",
+        "No input hook works here; only phone taps are counted",
+    ),
+    (
+        "watchdog.stalled",
+        "Game loop has not responded for {secs} s",
+    ),
+    ("watchdog.input_stopped", "Every running input hook stopped"),
+];
+
+// 日本語の文言。エラーは "error.{code}"、通信エラーは "multiplayer.{kind}" で、
+// 置き換える値の名前は GameError・MultiplayerError のフィールド名
+const JA: &[(&str, &str)] = &[
+    ("notification.stage_clear.title", "ステージクリア"),
+    (
+        "notification.stage_clear.body",
+        "ステージ{stage}をクリアしました",
+    ),
+    ("notification.base_danger.title", "基地が攻撃されています"),
+    (
+        "notification.base_danger.body",
+        "基地の体力が{percent:.0}%です",
+    ),
+    ("notification.achievement.title", "実績を解除しました"),
+    ("notification.auto_buy.title", "自動購入が終わりました"),
+    ("notification.auto_buy.body", "自動購入の時間が切れました"),
+    ("notification.sync_failed.title", "同期に失敗しました"),
+    ("notification.invasion_warning.title", "襲撃が迫っています"),
+    (
+        "notification.invasion_warning.body",
+        "{seconds:.0}秒後に敵が{enemies}体やって来ます",
+    ),
+    ("notification.invasion_repelled.title", "襲撃を撃退しました"),
+    (
+        "notification.invasion_repelled.body",
+        "ボーナスとして{coins}コインを獲得しました",
+    ),
+    ("hotkey.resumed", "再開しました"),
+    ("hotkey.paused", "一時停止しました"),
+    ("hotkey.stage_reset", "ステージをやり直しました"),
+    ("hotkey.widget_hidden", "ウィジェットを隠しました"),
+    ("hotkey.widget_shown", "ウィジェットを表示しました"),
+    ("hotkey.bought", "{name}を{cost}コインで購入しました"),
+    ("hotkey.low_distraction_on", "控えめモードをオンにしました"),
+    ("hotkey.low_distraction_off", "控えめモードをオフにしました"),
+    ("tray.open", "メインウィンドウを開く"),
+    ("tray.pause", "ゲームを一時停止"),
+    ("tray.widget", "ウィジェットを表示"),
+    ("tray.sync", "今すぐ同期"),
+    ("tray.quit", "終了"),
+    ("tray.tooltip", "ステージ{stage} / {coins}コイン"),
+    ("save_check.reload", "保存して読み直すと状態が変わります"),
+    (
+        "save_check.version",
+        "書き出したセーブコードが状態と一致しません",
+    ),
+    (
+        "save_check.non_finite",
+        "戦闘の値に NaN か無限大が含まれています",
+    ),
+    ("save_check.stage_zero", "ステージが 0 です"),
+    ("save_check.base_hp", "基地の体力が最大値を超えています"),
+    (
+        "save_check.repaired",
+        "範囲外の値があり、次に読み込むときに戻されます",
+    ),
+    ("save_check.duplicate_id", "同じ ID のユニットがあります"),
+    (
+        "save_check.id_ahead",
+        "ID の採番より先の ID を持つユニットがあります",
+    ),
+    ("save_check.auto_buy", "自動購入の順番: {error}"),
+    ("save_check.not_saved", "まだディスクに保存されていません"),
+    (
+        "save_check.unreadable",
+        "セーブファイルを読めません: {error}",
+    ),
+    (
+        "save_check.invalid",
+        "ディスク上のセーブファイルが正しい形式ではありません",
+    ),
+    ("save_check.signature", "セーブファイルの署名が一致しません"),
+    ("save_check.unsigned", "セーブファイルに署名がありません"),
+    (
+        "save_check.tampered",
+        "セーブは書き換えられた印が付いています: {reasons}",
+    ),
+    ("input_backend.hook_failed", "入力フックを開始できません: {error}"),
+    (
+        "input_backend.macos_accessibility",
+        "入力を数えるには「プライバシーとセキュリティ」>「アクセシビリティ」でこのアプリを許可してください",
+    ),
+    (
+        "input_backend.no_display",
+        "X11 のディスプレイがありません（DISPLAY が未設定）",
+    ),
+    (
+        "input_backend.xwayland",
+        "XWayland 経由のため、X11 のウィンドウへの入力だけを数えます",
+    ),
+    ("input_backend.evdev_unreadable", "{path} を読めません: {error}"),
+    (
+        "input_backend.evdev_permission",
+        "{path} に読めるデバイスがありません（ユーザーを input グループに追加してください）",
+    ),
+    (
+        "input_backend.devices_closed",
+        "すべての入力デバイスが閉じられました",
+    ),
+    ("input_backend.panicked", "入力フックが異常終了しました"),
+    ("input_backend.stopped", "入力フックが止まりました"),
+    (
+        "input_backend.none",
+        "使える入力フックがないため、スマホからのタップだけを数えます",
+    ),
+    ("watchdog.stalled", "ゲームループが {secs} 秒応答していません"),
+    ("watchdog.input_stopped", "動いていた入力フックがすべて止まりました"),
+    (
+        "error.not_enough_coins",
+        "コインが足りません（必要 {needed}、所持 {have}）",
+    ),
+    (
+        "error.not_enough_prestige_points",
+        "転生ポイントが足りません（必要 {needed}、所持 {have}）",
+    ),
+    (
+        "error.invalid_upgrade",
+        "不明な強化の種類です: {upgrade_type}",
+    ),
+    ("error.unknown_command", "不明なコマンドです: {name}"),
+    ("error.invalid_command_arguments", "使い方: {usage}"),
+    (
+        "error.auto_buy_entry_not_found",
+        "自動購入の {index} 番目の項目が見つかりません",
+    ),
+    ("error.nothing_to_respec", "払い戻せる強化がありません"),
+    (
+        "error.invalid_prestige_upgrade",
+        "不明な転生強化です: {kind}",
+    ),
+    ("error.stage_locked", "ステージをまだクリアしていません"),
+    (
+        "error.prestige_locked",
+        "転生するにはステージ{min_stage}に到達してください",
+    ),
+    ("error.chest_not_found", "宝箱 {chest_id} が見つかりません"),
+    (
+        "error.barracks_already_built",
+        "このステージには前線基地を建設済みです",
+    ),
+    (
+        "error.invalid_barracks_position",
+        "前線基地を建てられない位置です",
+    ),
+    ("error.position_not_captured", "まだ占領していない位置です"),
+    ("error.invalid_rally_point", "集結地点にできない位置です"),
+    ("error.invalid_stance", "待機位置にできない位置です"),
+    ("error.invalid_order", "この命令は出せません"),
+    (
+        "error.no_units_ordered",
+        "選んだユニットはこの命令に従えません",
+    ),
+    ("error.focus_already_running", "集中タイマーは実行中です"),
+    (
+        "error.invalid_focus_duration",
+        "集中タイマーの長さが正しくありません",
+    ),
+    (
+        "error.invalid_save_slot",
+        "セーブスロット {slot} はありません",
+    ),
+    ("error.save_slot_empty", "セーブスロット {slot} は空です"),
+    (
+        "error.checkpoint_not_found",
+        "チェックポイントが見つかりません: {id}",
+    ),
+    ("error.save_locked", "セーブはロックされています"),
+    (
+        "error.save_not_passphrase_protected",
+        "セーブはパスフレーズで保護されていません",
+    ),
+    (
+        "error.wrong_passphrase",
+        "パスフレーズが違うか、セーブが壊れています",
+    ),
+    (
+        "error.save_tampered",
+        "セーブの整合性の確認に失敗したため、進行状況を送信できません",
+    ),
+    (
+        "error.invalid_profile_name",
+        "プロフィール名は英数字・'-'・'_' の 1〜32 文字です",
+    ),
+    ("error.invalid_macro_name", "マクロ名が正しくありません"),
+    ("error.macro_not_found", "マクロが見つかりません: {name}"),
+    (
+        "error.macro_already_recording",
+        "すでにマクロを記録しています",
+    ),
+    ("error.macro_not_recording", "マクロを記録していません"),
+    ("error.empty_macro", "マクロに操作がありません"),
+    ("error.replay_not_recording", "リプレイを記録していません"),
+    (
+        "error.invalid_game_rules",
+        "カスタムルールの倍率は {min}〜{max} の範囲で指定してください",
+    ),
+    (
+        "error.difficulty_needs_reset",
+        "戦闘中に難易度を変えるにはステージをやり直してください",
+    ),
+    (
+        "error.no_local_network",
+        "リモートプレイに使えるローカルネットワークのアドレスがありません",
+    ),
+    (
+        "error.remote_play_port_unavailable",
+        "ポート {port} はリモートプレイに使えません",
+    ),
+    (
+        "error.widget_unavailable",
+        "ウィジェットのウィンドウがありません",
+    ),
+    (
+        "error.invalid_hotkey",
+        "ホットキーが正しくありません: {hotkey}",
+    ),
+    (
+        "error.invalid_friend",
+        "フレンドにできないプレイヤー ID です",
+    ),
+    ("error.too_many_friends", "フレンドは {max} 人までです"),
+    (
+        "error.sync_conflict_pending",
+        "同期する前に進行状況の食い違いを解決してください",
+    ),
+    (
+        "error.no_sync_conflict",
+        "解決する進行状況の食い違いはありません",
+    ),
+    ("error.title_not_earned", "称号 {id} はまだ獲得していません"),
+    ("error.unknown_cosmetic", "見た目 {id} はありません"),
+    ("error.cosmetic_locked", "見た目 {id} はまだ解除されていません"),
+    (
+        "error.save_code_corrupt",
+        "セーブコードが壊れているか、途中までしかありません",
+    ),
+    (
+        "error.save_code_too_new",
+        "セーブコードの形式 {version} はこのバージョンが読める形式（{supported}）より新しいです",
+    ),
+    (
+        "error.save_code_checksum_mismatch",
+        "セーブコードのチェックサムが一致しません。書き換えられた可能性があります",
+    ),
+    (
+        "error.quest_not_found",
+        "クエスト {quest_id} が見つかりません",
+    ),
+    (
+        "error.quest_not_complete",
+        "クエスト {quest_id} はまだ達成していません",
+    ),
+    (
+        "error.quest_already_claimed",
+        "クエスト {quest_id} の報酬は受け取り済みです",
+    ),
+    (
+        "error.summon_on_cooldown",
+        "呼び出しはあと {remaining_secs:.1} 秒使えません",
+    ),
+    (
+        "error.item_on_cooldown",
+        "{item} はあと {remaining_secs:.1} 秒使えません",
+    ),
+    ("error.no_items_left", "{item} が残っていません"),
+    (
+        "error.item_stack_full",
+        "{item} は {max} 個までしか持てません",
+    ),
+    (
+        "error.shop_stock_expired",
+        "ショップの品揃えの期限が切れました。更新してください",
+    ),
+    (
+        "error.shop_offer_not_found",
+        "{item} は今ショップに並んでいません",
+    ),
+    ("error.shop_item_owned", "{item} はすでに持っています"),
+    (
+        "error.not_enough_energy",
+        "エネルギーが足りません（必要 {needed}、所持 {have:.0}）",
+    ),
+    ("error.energy_mode_off", "エネルギーモードがオフです"),
+    (
+        "error.not_energy_summonable",
+        "{unit_type} ユニットはエネルギーで呼び出せません",
+    ),
+    (
+        "error.typing_history_unsupported",
+        "タイピング履歴が対応している MonkeyType・typing.io の CSV ではありません",
+    ),
+    (
+        "error.typing_history_too_large",
+        "タイピング履歴のファイルが {max_bytes} バイトを超えています",
+    ),
+    (
+        "error.typing_history_already_imported",
+        "このセーブにはタイピング履歴を取り込み済みです",
+    ),
+    (
+        "multiplayer.not_configured",
+        "サーバーの URL が設定されていません",
+    ),
+    ("multiplayer.not_registered", "サーバーに登録していません"),
+    (
+        "multiplayer.timeout",
+        "サーバーから時間内に応答がありませんでした",
+    ),
+    ("multiplayer.network", "サーバーに接続できません: {message}"),
+    (
+        "multiplayer.client_error",
+        "サーバーがエラーを返しました: {status}",
+    ),
+    (
+        "multiplayer.server_error",
+        "サーバーがエラーを返しました: {status}",
+    ),
+    ("multiplayer.schema", "応答を読み取れません: {message}"),
+    (
+        "multiplayer.read_only",
+        "匿名モードでは閲覧だけができます。進行状況を同期するには登録してください",
+    ),
+    (
+        "multiplayer.rate_limited",
+        "リクエストが多すぎます。{retry_after_secs} 秒後にやり直してください",
+    ),
+];
+
+fn lookup(language: Language, key: &str) -> Option<&'static str> {
+    language
+        .table()
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+// "{name}" と "{name:.1}"（小数の桁数）を置き換える。値のない名前はそのまま残す
+fn fill(template: &str, value: impl Fn(&str, Option<usize>) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..=start + len];
+        let spec = &placeholder[1..placeholder.len() - 1];
+        let (name, precision) = match spec.split_once(":.") {
+            Some((name, digits)) => (name, digits.parse().ok()),
+            None => (spec, None),
+        };
+        match value(name, precision) {
+            Some(text) => out.push_str(&text),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// 今の言語の文言（なければ英語、それもなければキーをそのまま返す）
+pub fn text(key: &str) -> String {
+    format(key, &[])
+}
+
+pub fn format(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = lookup(language(), key)
+        .or_else(|| lookup(Language::English, key))
+        .unwrap_or(key);
+    fill(template, |name, precision| {
+        let (_, value) = args.iter().find(|(arg, _)| *arg == name)?;
+        Some(match precision {
+            Some(digits) => format!("{:.*}", digits, value),
+            None => value.to_string(),
+        })
+    })
+}
+
+fn render(value: &Value, precision: Option<usize>) -> String {
+    match (value, precision) {
+        (Value::Number(number), Some(digits)) => {
+            format!("{:.*}", digits, number.as_f64().unwrap_or_default())
+        }
+        // 小数は #[error] と同じく 5.0 を "5" と表す
+        (Value::Number(number), None) if number.is_f64() => {
+            number.as_f64().unwrap_or_default().to_string()
+        }
+        (Value::String(text), _) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+// シリアライズしたエラー（{ "code": ..., 各フィールド }）の訳。英語や訳のないエラーは None
+pub fn error_message(language: Language, error: &Value) -> Option<String> {
+    if language == Language::English {
+        return None;
+    }
+    let fields = error.as_object()?;
+    let code = fields.get("code")?.as_str()?;
+    let (key, fields) = match code {
+        // 通信エラーは中の種類ごとに訳す
+        "multiplayer" => {
+            let inner = fields.get("error")?.as_object()?;
+            (
+                format!("multiplayer.{}", inner.get("kind")?.as_str()?),
+                inner,
+            )
+        }
+        _ => (format!("error.{}", code), fields),
+    };
+    let template = lookup(language, &key)?;
+    Some(fill(template, |name, precision| {
+        fields.get(name).map(|value| render(value, precision))
+    }))
+}
@@ -0,0 +1,91 @@
+use crate::game::GameState;
+use serde::Serialize;
+use std::time::Duration;
+use ts_rs::TS;
+
+// 放置中のループ間隔
+pub const IDLE_TICK: Duration = Duration::from_secs(1);
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 120;
+
+// 放置中に送る要約（ユニットは数だけ）
+#[derive(Clone, PartialEq)]
+struct IdleSnapshot {
+    coins: u64,
+    stage: u32,
+    player_base_hp: u32,
+    enemy_base_hp: u32,
+    player_units: usize,
+    enemy_units: usize,
+}
+
+impl IdleSnapshot {
+    fn of(game: &GameState) -> Self {
+        Self {
+            coins: game.coins,
+            stage: game.stage,
+            player_base_hp: game.player_base_hp.max(0.0).round() as u32,
+            enemy_base_hp: game.enemy_base_hp.max(0.0).round() as u32,
+            player_units: game.player_units.len(),
+            enemy_units: game.enemy_units.len(),
+        }
+    }
+}
+
+// 前回送った要約から変わった項目だけを持つ差分
+#[derive(Clone, Serialize, Debug, Default, TS)]
+pub struct IdleUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(type = "number | null")]
+    pub coins: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_base_hp: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enemy_base_hp: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_units: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enemy_units: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct IdleTracker {
+    last: Option<IdleSnapshot>,
+}
+
+impl IdleTracker {
+    // 変化がなければ None（放置に入った直後は全項目を送る）
+    pub fn diff(&mut self, game: &GameState) -> Option<IdleUpdate> {
+        let current = IdleSnapshot::of(game);
+        let update = match &self.last {
+            Some(last) if *last == current => return None,
+            Some(last) => IdleUpdate {
+                coins: (last.coins != current.coins).then_some(current.coins),
+                stage: (last.stage != current.stage).then_some(current.stage),
+                player_base_hp: (last.player_base_hp != current.player_base_hp)
+                    .then_some(current.player_base_hp),
+                enemy_base_hp: (last.enemy_base_hp != current.enemy_base_hp)
+                    .then_some(current.enemy_base_hp),
+                player_units: (last.player_units != current.player_units)
+                    .then_some(current.player_units),
+                enemy_units: (last.enemy_units != current.enemy_units)
+                    .then_some(current.enemy_units),
+            },
+            None => IdleUpdate {
+                coins: Some(current.coins),
+                stage: Some(current.stage),
+                player_base_hp: Some(current.player_base_hp),
+                enemy_base_hp: Some(current.enemy_base_hp),
+                player_units: Some(current.player_units),
+                enemy_units: Some(current.enemy_units),
+            },
+        };
+        self.last = Some(current);
+        Some(update)
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
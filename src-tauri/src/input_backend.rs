@@ -0,0 +1,551 @@
+use crate::i18n;
+use crate::input_hook::InputEvent;
+use parking_lot::Mutex;
+use rdev::{listen, Event, EventType};
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::Sender;
+use ts_rs::TS;
+
+// 入力フックの実装。Auto は環境に合うものから順に試す
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum InputBackendKind {
+    #[default]
+    Auto,
+    // OS のフック（Windows の低レベルフック、macOS の CGEventTap、X11 の XRecord）
+    Native,
+    // Linux の /dev/input を直接読む（Wayland 向け。input グループへの所属が必要）
+    Evdev,
+    // 入力を数えない（スマホからのタップだけ）
+    Disabled,
+}
+
+// その実装で拾える入力
+#[derive(Clone, Copy, Serialize, Debug, Default, PartialEq, TS)]
+pub struct InputCapabilities {
+    pub keyboard: bool,
+    pub mouse_buttons: bool,
+    pub wheel: bool,
+    pub mouse_move: bool,
+}
+
+impl InputCapabilities {
+    const ALL: Self = Self {
+        keyboard: true,
+        mouse_buttons: true,
+        wheel: true,
+        mouse_move: true,
+    };
+}
+
+// 試した実装とうまくいかなかった理由
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct BackendAttempt {
+    pub backend: String,
+    pub reason: String,
+}
+
+// 設定画面に出す入力フックの状態
+#[derive(Clone, Serialize, Debug, Default, TS)]
+pub struct InputBackendStatus {
+    pub requested: InputBackendKind,
+    // 動いている実装（None なら入力を数えていない）
+    pub active: Option<String>,
+    pub capabilities: InputCapabilities,
+    // 動いているが権限が足りないなどの注意
+    pub warning: Option<String>,
+    pub failed: Vec<BackendAttempt>,
+    // 設定を変えたが、フックは外せないので次の起動まで反映されない
+    pub restart_required: bool,
+}
+
+pub trait InputBackend {
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> InputCapabilities;
+
+    // 始める前に使える環境かを確かめる。使えなければ理由を返す
+    fn probe(&self) -> Result<(), String>;
+
+    // 動いているが入力が届かないかもしれない理由
+    fn warning(&self) -> Option<String> {
+        None
+    }
+
+    // 入力を送り続ける（戻ったらフックが止まったということ）
+    fn run(&self, sender: Sender<InputEvent>) -> Result<(), String>;
+}
+
+// rdev のコールバックを InputEvent に変えてチャネルへ送る
+// （コールバックはロックを取らない。ゲームループがロック中でもシステム全体の入力を遅らせないため）
+fn listen_rdev(sender: Sender<InputEvent>) -> Result<(), String> {
+    // 移動距離を出すための前回の位置（コールバックの中だけで持つ）
+    let mut last_position: Option<(f64, f64)> = None;
+    let callback = move |event: Event| {
+        let input = match event.event_type {
+            EventType::ButtonPress(button) => InputEvent::Click(button),
+            EventType::KeyPress(key) => InputEvent::KeyPress(key),
+            EventType::KeyRelease(key) => InputEvent::KeyRelease(key),
+            EventType::Wheel { delta_x, delta_y } => {
+                let ticks = delta_x.unsigned_abs() + delta_y.unsigned_abs();
+                InputEvent::Scroll(ticks.min(u32::MAX as u64) as u32)
+            }
+            EventType::MouseMove { x, y } => {
+                let Some((last_x, last_y)) = last_position.replace((x, y)) else {
+                    return;
+                };
+                InputEvent::MouseMove((x - last_x).hypot(y - last_y) as f32)
+            }
+            _ => return,
+        };
+        // 受信側（ゲームループ）が終了していれば捨てる
+        let _ = sender.send(input);
+    };
+    listen(callback).map_err(|error| {
+        i18n::format(
+            "input_backend.hook_failed",
+            &[("error", &format!("{:?}", error))],
+        )
+    })
+}
+
+#[cfg(target_os = "windows")]
+struct WindowsHook;
+
+#[cfg(target_os = "windows")]
+impl InputBackend for WindowsHook {
+    fn name(&self) -> &'static str {
+        "windows_hook"
+    }
+
+    fn capabilities(&self) -> InputCapabilities {
+        InputCapabilities::ALL
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn run(&self, sender: Sender<InputEvent>) -> Result<(), String> {
+        listen_rdev(sender)
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+// アクセシビリティの許可がないとタップは作れても入力が届かない（エラーにもならない）
+#[cfg(target_os = "macos")]
+struct MacEventTap;
+
+#[cfg(target_os = "macos")]
+impl InputBackend for MacEventTap {
+    fn name(&self) -> &'static str {
+        "macos_event_tap"
+    }
+
+    fn capabilities(&self) -> InputCapabilities {
+        InputCapabilities::ALL
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn warning(&self) -> Option<String> {
+        // SAFETY: 引数を取らず、呼び出し元のプロセスの許可を返すだけ
+        let trusted = unsafe { AXIsProcessTrusted() };
+        (!trusted).then(|| i18n::text("input_backend.macos_accessibility"))
+    }
+
+    fn run(&self, sender: Sender<InputEvent>) -> Result<(), String> {
+        listen_rdev(sender)
+    }
+}
+
+// XWayland もない純粋な Wayland セッションでは XRecord が使えない
+#[cfg(target_os = "linux")]
+struct X11Record;
+
+#[cfg(target_os = "linux")]
+impl InputBackend for X11Record {
+    fn name(&self) -> &'static str {
+        "x11_record"
+    }
+
+    fn capabilities(&self) -> InputCapabilities {
+        InputCapabilities::ALL
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        match std::env::var_os("DISPLAY") {
+            Some(display) if !display.is_empty() => Ok(()),
+            _ => Err(i18n::text("input_backend.no_display")),
+        }
+    }
+
+    fn warning(&self) -> Option<String> {
+        // XWayland 経由では X のウィンドウへの入力しか届かない
+        std::env::var_os("WAYLAND_DISPLAY")
+            .is_some()
+            .then(|| i18n::text("input_backend.xwayland"))
+    }
+
+    fn run(&self, sender: Sender<InputEvent>) -> Result<(), String> {
+        listen_rdev(sender)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod evdev {
+    use super::{InputBackend, InputCapabilities};
+    use crate::i18n;
+    use crate::input_hook::InputEvent;
+    use rdev::{Button, Key};
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::sync::mpsc::Sender;
+
+    const INPUT_DIR: &str = "/dev/input";
+    // struct input_event: timeval（long 2 つ）+ type(u16) + code(u16) + value(i32)
+    const TIMEVAL_SIZE: usize = 2 * std::mem::size_of::<usize>();
+    const EVENT_SIZE: usize = TIMEVAL_SIZE + 8;
+
+    const EV_SYN: u16 = 0x00;
+    const EV_KEY: u16 = 0x01;
+    const EV_REL: u16 = 0x02;
+    const REL_X: u16 = 0x00;
+    const REL_Y: u16 = 0x01;
+    const REL_HWHEEL: u16 = 0x06;
+    const REL_WHEEL: u16 = 0x08;
+    // これより下はキーボードのキー
+    const BTN_MISC: u16 = 0x100;
+    const BTN_MOUSE: u16 = 0x110;
+    const BTN_TASK: u16 = 0x117;
+
+    pub struct Evdev;
+
+    // 読めるデバイスだけを開く（どれも読めなければ権限がない）
+    fn open_devices() -> Result<Vec<File>, String> {
+        let entries = fs::read_dir(INPUT_DIR).map_err(|e| {
+            i18n::format(
+                "input_backend.evdev_unreadable",
+                &[("path", &INPUT_DIR), ("error", &e)],
+            )
+        })?;
+        let paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("event"))
+            })
+            .collect();
+        let devices: Vec<File> = paths
+            .iter()
+            .filter_map(|path| File::open(path).ok())
+            .collect();
+        if devices.is_empty() {
+            return Err(i18n::format(
+                "input_backend.evdev_permission",
+                &[("path", &INPUT_DIR)],
+            ));
+        }
+        Ok(devices)
+    }
+
+    // rdev の X11 のキーコードは evdev のコード + 8（IME キーの判定もこれに合わせる）
+    fn key(code: u16) -> Key {
+        match code {
+            1 => Key::Escape,
+            2 => Key::Num1,
+            3 => Key::Num2,
+            4 => Key::Num3,
+            5 => Key::Num4,
+            6 => Key::Num5,
+            7 => Key::Num6,
+            8 => Key::Num7,
+            9 => Key::Num8,
+            10 => Key::Num9,
+            11 => Key::Num0,
+            12 => Key::Minus,
+            13 => Key::Equal,
+            14 => Key::Backspace,
+            15 => Key::Tab,
+            16 => Key::KeyQ,
+            17 => Key::KeyW,
+            18 => Key::KeyE,
+            19 => Key::KeyR,
+            20 => Key::KeyT,
+            21 => Key::KeyY,
+            22 => Key::KeyU,
+            23 => Key::KeyI,
+            24 => Key::KeyO,
+            25 => Key::KeyP,
+            26 => Key::LeftBracket,
+            27 => Key::RightBracket,
+            28 => Key::Return,
+            29 => Key::ControlLeft,
+            30 => Key::KeyA,
+            31 => Key::KeyS,
+            32 => Key::KeyD,
+            33 => Key::KeyF,
+            34 => Key::KeyG,
+            35 => Key::KeyH,
+            36 => Key::KeyJ,
+            37 => Key::KeyK,
+            38 => Key::KeyL,
+            39 => Key::SemiColon,
+            40 => Key::Quote,
+            41 => Key::BackQuote,
+            42 => Key::ShiftLeft,
+            43 => Key::BackSlash,
+            44 => Key::KeyZ,
+            45 => Key::KeyX,
+            46 => Key::KeyC,
+            47 => Key::KeyV,
+            48 => Key::KeyB,
+            49 => Key::KeyN,
+            50 => Key::KeyM,
+            51 => Key::Comma,
+            52 => Key::Dot,
+            53 => Key::Slash,
+            54 => Key::ShiftRight,
+            55 => Key::KpMultiply,
+            56 => Key::Alt,
+            57 => Key::Space,
+            58 => Key::CapsLock,
+            59 => Key::F1,
+            60 => Key::F2,
+            61 => Key::F3,
+            62 => Key::F4,
+            63 => Key::F5,
+            64 => Key::F6,
+            65 => Key::F7,
+            66 => Key::F8,
+            67 => Key::F9,
+            68 => Key::F10,
+            69 => Key::NumLock,
+            70 => Key::ScrollLock,
+            71 => Key::Kp7,
+            72 => Key::Kp8,
+            73 => Key::Kp9,
+            74 => Key::KpMinus,
+            75 => Key::Kp4,
+            76 => Key::Kp5,
+            77 => Key::Kp6,
+            78 => Key::KpPlus,
+            79 => Key::Kp1,
+            80 => Key::Kp2,
+            81 => Key::Kp3,
+            82 => Key::Kp0,
+            83 => Key::KpDelete,
+            86 => Key::IntlBackslash,
+            87 => Key::F11,
+            88 => Key::F12,
+            96 => Key::KpReturn,
+            97 => Key::ControlRight,
+            98 => Key::KpDivide,
+            99 => Key::PrintScreen,
+            100 => Key::AltGr,
+            102 => Key::Home,
+            103 => Key::UpArrow,
+            104 => Key::PageUp,
+            105 => Key::LeftArrow,
+            106 => Key::RightArrow,
+            107 => Key::End,
+            108 => Key::DownArrow,
+            109 => Key::PageDown,
+            110 => Key::Insert,
+            111 => Key::Delete,
+            119 => Key::Pause,
+            125 => Key::MetaLeft,
+            126 => Key::MetaRight,
+            _ => Key::Unknown(code as u32 + 8),
+        }
+    }
+
+    fn button(code: u16) -> Button {
+        match code {
+            0x110 => Button::Left,
+            0x111 => Button::Right,
+            0x112 => Button::Middle,
+            _ => Button::Unknown((code - BTN_MOUSE) as u8),
+        }
+    }
+
+    // 1 台分の入力を読み続ける。移動は同期イベント（EV_SYN）ごとにまとめて送る
+    fn read_device(mut device: File, sender: Sender<InputEvent>) {
+        let mut buffer = [0u8; EVENT_SIZE];
+        let (mut dx, mut dy) = (0.0f32, 0.0f32);
+        while device.read_exact(&mut buffer).is_ok() {
+            let kind = u16::from_ne_bytes([buffer[TIMEVAL_SIZE], buffer[TIMEVAL_SIZE + 1]]);
+            let code = u16::from_ne_bytes([buffer[TIMEVAL_SIZE + 2], buffer[TIMEVAL_SIZE + 3]]);
+            let value = i32::from_ne_bytes([
+                buffer[TIMEVAL_SIZE + 4],
+                buffer[TIMEVAL_SIZE + 5],
+                buffer[TIMEVAL_SIZE + 6],
+                buffer[TIMEVAL_SIZE + 7],
+            ]);
+            let input = match (kind, code) {
+                (EV_KEY, BTN_MOUSE..=BTN_TASK) if value == 1 => InputEvent::Click(button(code)),
+                (EV_KEY, BTN_MOUSE..=BTN_TASK) => continue,
+                // 押しっぱなしのリピート（2）は送らない
+                (EV_KEY, 0..BTN_MISC) if value == 1 => InputEvent::KeyPress(key(code)),
+                (EV_KEY, 0..BTN_MISC) if value == 0 => InputEvent::KeyRelease(key(code)),
+                (EV_REL, REL_X) => {
+                    dx += value as f32;
+                    continue;
+                }
+                (EV_REL, REL_Y) => {
+                    dy += value as f32;
+                    continue;
+                }
+                (EV_REL, REL_WHEEL | REL_HWHEEL) => InputEvent::Scroll(value.unsigned_abs()),
+                (EV_SYN, _) if dx != 0.0 || dy != 0.0 => {
+                    let distance = dx.hypot(dy);
+                    (dx, dy) = (0.0, 0.0);
+                    InputEvent::MouseMove(distance)
+                }
+                _ => continue,
+            };
+            if sender.send(input).is_err() {
+                return;
+            }
+        }
+    }
+
+    impl InputBackend for Evdev {
+        fn name(&self) -> &'static str {
+            "evdev"
+        }
+
+        // タッチパッドの絶対座標（EV_ABS）は読まないので、移動はマウスだけ
+        fn capabilities(&self) -> InputCapabilities {
+            InputCapabilities::ALL
+        }
+
+        fn probe(&self) -> Result<(), String> {
+            open_devices().map(|_| ())
+        }
+
+        // デバイスごとに読むスレッドを立て、すべて閉じたら戻る
+        fn run(&self, sender: Sender<InputEvent>) -> Result<(), String> {
+            let handles: Vec<_> = open_devices()?
+                .into_iter()
+                .map(|device| {
+                    let sender = sender.clone();
+                    std::thread::spawn(move || read_device(device, sender))
+                })
+                .collect();
+            for handle in handles {
+                let _ = handle.join();
+            }
+            Err(i18n::text("input_backend.devices_closed"))
+        }
+    }
+}
+
+// 設定に合わせて試す順番
+fn candidates(requested: InputBackendKind) -> Vec<Box<dyn InputBackend>> {
+    let mut backends: Vec<Box<dyn InputBackend>> = Vec::new();
+    let native = matches!(requested, InputBackendKind::Auto | InputBackendKind::Native);
+    let evdev = matches!(requested, InputBackendKind::Auto | InputBackendKind::Evdev);
+    #[cfg(target_os = "windows")]
+    if native {
+        backends.push(Box::new(WindowsHook));
+    }
+    #[cfg(target_os = "macos")]
+    if native {
+        backends.push(Box::new(MacEventTap));
+    }
+    // Wayland のセッションでは XWayland より先に evdev を試す
+    #[cfg(target_os = "linux")]
+    {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        if evdev && wayland {
+            backends.push(Box::new(evdev::Evdev));
+        }
+        if native {
+            backends.push(Box::new(X11Record));
+        }
+        if evdev && !wayland {
+            backends.push(Box::new(evdev::Evdev));
+        }
+    }
+    let _ = (native, evdev);
+    backends
+}
+
+// 入力フックの状態（フックのスレッドが書き、コマンドが読む）
+#[derive(Default)]
+pub struct InputBackendMonitor {
+    status: Mutex<InputBackendStatus>,
+}
+
+impl InputBackendMonitor {
+    pub fn status(&self, configured: InputBackendKind) -> InputBackendStatus {
+        let mut status = self.status.lock().clone();
+        status.restart_required = configured != status.requested;
+        status
+    }
+
+    // 候補を順に試し、止まったり使えなかったりしたら次へ進む。
+    // 状態が変わるたびに on_change を呼ぶ（最後まで失敗しても、入力を数えないだけで戻る）。
+    // どれかが一度でも動いていたら true
+    pub fn run(
+        &self,
+        requested: InputBackendKind,
+        sender: Sender<InputEvent>,
+        on_change: impl Fn(&InputBackendStatus),
+    ) -> bool {
+        *self.status.lock() = InputBackendStatus {
+            requested,
+            ..InputBackendStatus::default()
+        };
+        let mut ran = false;
+        for backend in candidates(requested) {
+            let result = backend.probe().and_then(|()| {
+                ran = true;
+                {
+                    let mut status = self.status.lock();
+                    status.active = Some(backend.name().to_string());
+                    status.capabilities = backend.capabilities();
+                    status.warning = backend.warning();
+                }
+                on_change(&self.status(requested));
+                // フックの中で panic してもスレッドごと落とさず次の候補へ
+                panic::catch_unwind(AssertUnwindSafe(|| backend.run(sender.clone())))
+                    .unwrap_or_else(|_| Err(i18n::text("input_backend.panicked")))
+            });
+            let reason = match result {
+                Ok(()) => i18n::text("input_backend.stopped"),
+                Err(reason) => reason,
+            };
+            eprintln!("Input backend {} unavailable: {}", backend.name(), reason);
+            let mut status = self.status.lock();
+            status.active = None;
+            status.capabilities = InputCapabilities::default();
+            status.warning = None;
+            status.failed.push(BackendAttempt {
+                backend: backend.name().to_string(),
+                reason,
+            });
+        }
+        let status = {
+            let mut status = self.status.lock();
+            if requested != InputBackendKind::Disabled {
+                status.warning = Some(i18n::text("input_backend.none"));
+            }
+            status.clone()
+        };
+        on_change(&status);
+        ran
+    }
+}
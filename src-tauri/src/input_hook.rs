@@ -1,10 +1,538 @@
-use parking_lot::Mutex;
-use rdev::{listen, Event, EventType};
-use std::sync::Arc;
+use crate::ability::UnitAbility;
+use crate::config::{self, KeyWeights, PointerWeights};
+use crate::game::UnitType;
+use crate::hotkey::{Hotkey, HotkeyBindings, HotkeyDetector, HotkeySequence};
+use crate::hotkey_action::HotkeyAction;
+use crate::profile;
+use crate::save_writer::{self, SaveJob};
+use rdev::{Button, Key};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+pub const HEAT_WINDOW_SECS: usize = 60;
+// 押下が届かなくなってから押しっぱなしとみなすのをやめるまでの時間
+// （OS のリピート開始の遅れは最長 1 秒なので、それより長く取る）
+const HELD_KEY_TIMEOUT: Duration = Duration::from_millis(1500);
+
+// IME 制御キー（プラットフォーム固有のキーコード）
+#[cfg(target_os = "windows")]
+const IME_TOGGLE_CODES: &[u32] = &[0x15, 0x16, 0x1A, 0xF0, 0xF2, 0xF3, 0xF4];
+#[cfg(target_os = "windows")]
+const IME_CONVERSION_CODES: &[u32] = &[0x17, 0x18, 0x19, 0x1C, 0x1D, 0x1E, 0x1F, 0xE5];
+
+#[cfg(target_os = "linux")]
+const IME_TOGGLE_CODES: &[u32] = &[101, 130];
+#[cfg(target_os = "linux")]
+const IME_CONVERSION_CODES: &[u32] = &[100, 102, 131];
+
+#[cfg(target_os = "macos")]
+const IME_TOGGLE_CODES: &[u32] = &[102, 104];
+#[cfg(target_os = "macos")]
+const IME_CONVERSION_CODES: &[u32] = &[];
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+const IME_TOGGLE_CODES: &[u32] = &[];
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+const IME_CONVERSION_CODES: &[u32] = &[];
+
+// IME 入力の正規化（変換・確定キーによるカウントの水増しを防ぐ）
+pub struct ImeNormalizer {
+    pub enabled: bool,
+    // IME 入力中のキー 1 回あたりの換算係数
+    pub calibration: f32,
+    ime_active: bool,
+    composing: bool,
+}
+
+impl ImeNormalizer {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            calibration: 1.0,
+            ime_active: false,
+            composing: false,
+        }
+    }
+
+    fn is_composition_key(key: &Key) -> bool {
+        !matches!(
+            key,
+            Key::Alt
+                | Key::AltGr
+                | Key::ControlLeft
+                | Key::ControlRight
+                | Key::ShiftLeft
+                | Key::ShiftRight
+                | Key::MetaLeft
+                | Key::MetaRight
+                | Key::CapsLock
+                | Key::Tab
+                | Key::Escape
+                | Key::Return
+                | Key::KpReturn
+                | Key::Space
+                | Key::Backspace
+                | Key::Delete
+                | Key::UpArrow
+                | Key::DownArrow
+                | Key::LeftArrow
+                | Key::RightArrow
+                | Key::Unknown(_)
+        )
+    }
+
+    // キー 1 回あたりの重みを返す（0.0 ならカウントしない）
+    pub fn weigh(&mut self, key: &Key) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+
+        if let Key::Unknown(code) = key {
+            if IME_TOGGLE_CODES.contains(code) {
+                self.ime_active = !self.ime_active;
+                self.composing = false;
+                return 0.0;
+            }
+            if IME_CONVERSION_CODES.contains(code) {
+                // 変換キーが押されたなら IME は有効とみなす
+                self.ime_active = true;
+                return 0.0;
+            }
+        }
+
+        if !self.ime_active {
+            return 1.0;
+        }
+
+        match key {
+            // 変換候補の選択
+            Key::Space | Key::UpArrow | Key::DownArrow if self.composing => 0.0,
+            // 確定・取り消し
+            Key::Return | Key::KpReturn | Key::Escape if self.composing => {
+                self.composing = false;
+                0.0
+            }
+            key if Self::is_composition_key(key) => {
+                self.composing = true;
+                self.calibration.max(0.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyCategory {
+    Letter,
+    Digit,
+    Symbol,
+    Editing,
+    Navigation,
+    Modifier,
+    Function,
+    Media,
+}
+
+impl KeyCategory {
+    pub fn of(key: &Key) -> Self {
+        match key {
+            Key::KeyA
+            | Key::KeyB
+            | Key::KeyC
+            | Key::KeyD
+            | Key::KeyE
+            | Key::KeyF
+            | Key::KeyG
+            | Key::KeyH
+            | Key::KeyI
+            | Key::KeyJ
+            | Key::KeyK
+            | Key::KeyL
+            | Key::KeyM
+            | Key::KeyN
+            | Key::KeyO
+            | Key::KeyP
+            | Key::KeyQ
+            | Key::KeyR
+            | Key::KeyS
+            | Key::KeyT
+            | Key::KeyU
+            | Key::KeyV
+            | Key::KeyW
+            | Key::KeyX
+            | Key::KeyY
+            | Key::KeyZ => KeyCategory::Letter,
+            Key::Num0
+            | Key::Num1
+            | Key::Num2
+            | Key::Num3
+            | Key::Num4
+            | Key::Num5
+            | Key::Num6
+            | Key::Num7
+            | Key::Num8
+            | Key::Num9
+            | Key::Kp0
+            | Key::Kp1
+            | Key::Kp2
+            | Key::Kp3
+            | Key::Kp4
+            | Key::Kp5
+            | Key::Kp6
+            | Key::Kp7
+            | Key::Kp8
+            | Key::Kp9 => KeyCategory::Digit,
+            Key::BackQuote
+            | Key::Minus
+            | Key::Equal
+            | Key::LeftBracket
+            | Key::RightBracket
+            | Key::SemiColon
+            | Key::Quote
+            | Key::BackSlash
+            | Key::IntlBackslash
+            | Key::Comma
+            | Key::Dot
+            | Key::Slash
+            | Key::KpMinus
+            | Key::KpPlus
+            | Key::KpMultiply
+            | Key::KpDivide => KeyCategory::Symbol,
+            Key::Space
+            | Key::Return
+            | Key::KpReturn
+            | Key::Tab
+            | Key::Backspace
+            | Key::Delete
+            | Key::KpDelete
+            | Key::Insert => KeyCategory::Editing,
+            Key::UpArrow
+            | Key::DownArrow
+            | Key::LeftArrow
+            | Key::RightArrow
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Escape => KeyCategory::Navigation,
+            Key::Alt
+            | Key::AltGr
+            | Key::ControlLeft
+            | Key::ControlRight
+            | Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::MetaLeft
+            | Key::MetaRight
+            | Key::CapsLock
+            | Key::NumLock
+            | Key::ScrollLock
+            | Key::Function => KeyCategory::Modifier,
+            Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+            | Key::F12
+            | Key::PrintScreen
+            | Key::Pause => KeyCategory::Function,
+            // メディアキー等、rdev が識別しないキー
+            Key::Unknown(_) => KeyCategory::Media,
+        }
+    }
+
+    pub fn weight(&self, weights: &KeyWeights) -> f32 {
+        match self {
+            KeyCategory::Letter => weights.letters,
+            KeyCategory::Digit => weights.digits,
+            KeyCategory::Symbol => weights.symbols,
+            KeyCategory::Editing => weights.editing,
+            KeyCategory::Navigation => weights.navigation,
+            KeyCategory::Modifier => weights.modifiers,
+            KeyCategory::Function => weights.function_keys,
+            KeyCategory::Media => weights.media,
+        }
+    }
+}
+
+// 直近60秒間の入力強度（1秒ごとの入力回数）
+pub struct InputHeat {
+    buckets: [u32; HEAT_WINDOW_SECS],
+    started: Instant,
+    current_second: u64,
+}
+
+impl InputHeat {
+    pub fn new() -> Self {
+        Self {
+            buckets: [0; HEAT_WINDOW_SECS],
+            started: Instant::now(),
+            current_second: 0,
+        }
+    }
+
+    fn advance(&mut self) {
+        let now = self.started.elapsed().as_secs();
+        let elapsed = now.saturating_sub(self.current_second);
+        if elapsed == 0 {
+            return;
+        }
+        // 経過した秒数分のバケットをクリア
+        for step in 1..=elapsed.min(HEAT_WINDOW_SECS as u64) {
+            let index = ((self.current_second + step) % HEAT_WINDOW_SECS as u64) as usize;
+            self.buckets[index] = 0;
+        }
+        self.current_second = now;
+    }
+
+    pub fn record(&mut self) {
+        self.advance();
+        let index = (self.current_second % HEAT_WINDOW_SECS as u64) as usize;
+        self.buckets[index] = self.buckets[index].saturating_add(1);
+    }
+
+    // 古い順に並べたヒストグラム
+    pub fn snapshot(&mut self) -> Vec<u32> {
+        self.advance();
+        (1..=HEAT_WINDOW_SECS as u64)
+            .map(|offset| {
+                let index = ((self.current_second + offset) % HEAT_WINDOW_SECS as u64) as usize;
+                self.buckets[index]
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MouseButtonCounts {
+    pub left: u64,
+    pub right: u64,
+    pub middle: u64,
+    pub other: u64,
+}
+
+// 累計の入力統計（キー別・マウスボタン別）
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputStats {
+    pub keys: BTreeMap<String, u64>,
+    pub mouse: MouseButtonCounts,
+    pub total_keys: u64,
+    pub total_clicks: u64,
+    // キー入力があった秒数（WPM の算出用）
+    pub typing_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyCount {
+    pub key: String,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InputStatsSummary {
+    // 多い順
+    pub keys: Vec<KeyCount>,
+    pub mouse: MouseButtonCounts,
+    pub total_keys: u64,
+    pub total_clicks: u64,
+    pub typing_secs: u64,
+    // 5打鍵 = 1語として換算
+    pub words_per_minute: f32,
+}
+
+fn stats_file_path() -> Option<PathBuf> {
+    profile::data_dir().map(|dir| dir.join("input_stats.json"))
+}
+
+impl InputStats {
+    pub fn load() -> Self {
+        stats_file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    // 書けないときは save_writer がメモリに残して試し直す
+    pub fn save(&self) {
+        if let Some(path) = stats_file_path() {
+            if let Ok(json) = serde_json::to_vec(self) {
+                save_writer::submit(SaveJob::new(path, json));
+            }
+        }
+    }
+
+    pub fn summary(&self) -> InputStatsSummary {
+        let mut keys: Vec<KeyCount> = self
+            .keys
+            .iter()
+            .map(|(key, &count)| KeyCount {
+                key: key.clone(),
+                count,
+            })
+            .collect();
+        keys.sort_by_key(|k| std::cmp::Reverse(k.count));
+        let minutes = self.typing_secs as f32 / 60.0;
+        InputStatsSummary {
+            keys,
+            mouse: self.mouse.clone(),
+            total_keys: self.total_keys,
+            total_clicks: self.total_clicks,
+            typing_secs: self.typing_secs,
+            words_per_minute: if minutes > 0.0 {
+                self.total_keys as f32 / 5.0 / minutes
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+// 入力ごとにキー名を文字列化せず、取得時にまとめて反映する
+pub struct InputStatsTracker {
+    stats: InputStats,
+    pending_keys: HashMap<Key, u64>,
+    started: Instant,
+    last_key_second: Option<u64>,
+}
+
+impl InputStatsTracker {
+    pub fn new(stats: InputStats) -> Self {
+        Self {
+            stats,
+            pending_keys: HashMap::new(),
+            started: Instant::now(),
+            last_key_second: None,
+        }
+    }
+
+    fn record_key(&mut self, key: &Key) {
+        *self.pending_keys.entry(*key).or_insert(0) += 1;
+        self.stats.total_keys += 1;
+        let second = self.started.elapsed().as_secs();
+        if self.last_key_second != Some(second) {
+            self.last_key_second = Some(second);
+            self.stats.typing_secs += 1;
+        }
+    }
+
+    fn record_button(&mut self, button: &Button) {
+        let mouse = &mut self.stats.mouse;
+        match button {
+            Button::Left => mouse.left += 1,
+            Button::Right => mouse.right += 1,
+            Button::Middle => mouse.middle += 1,
+            Button::Unknown(_) => mouse.other += 1,
+        }
+        self.stats.total_clicks += 1;
+    }
+
+    pub fn snapshot(&mut self) -> InputStats {
+        for (key, count) in self.pending_keys.drain() {
+            *self.stats.keys.entry(format!("{:?}", key)).or_insert(0) += count;
+        }
+        self.stats.clone()
+    }
+
+    // プロファイル切り替え時に読み込み直す
+    pub fn replace(&mut self, stats: InputStats) {
+        self.stats = stats;
+        self.pending_keys.clear();
+    }
+}
+
+// フックからゲームループへ渡す入力
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    Click(Button),
+    KeyPress(Key),
+    KeyRelease(Key),
+    // ホイールの目盛り数（縦横の合計）
+    Scroll(u32),
+    // 前回の位置からの移動距離（ピクセル）
+    MouseMove(f32),
+}
+
+// ホイールとマウス移動を少しずつ貯め、1.0 ごとに小型ユニット 1 体にする
+pub struct PointerEnergy {
+    carry: f32,
+    units: u32,
+    // 直近 1 秒に貯まった量（上限の判定用）
+    window_start: Instant,
+    window_gained: f32,
+}
+
+impl PointerEnergy {
+    fn new() -> Self {
+        Self {
+            carry: 0.0,
+            units: 0,
+            window_start: Instant::now(),
+            window_gained: 0.0,
+        }
+    }
+
+    fn add(&mut self, amount: f32, max_per_second: f32) {
+        if amount.is_nan() || amount <= 0.0 {
+            return;
+        }
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.window_start = Instant::now();
+            self.window_gained = 0.0;
+        }
+        let amount = amount.min((max_per_second - self.window_gained).max(0.0));
+        self.window_gained += amount;
+        self.carry += amount;
+        if self.carry >= 1.0 {
+            let whole = self.carry.floor();
+            self.units = self.units.saturating_add(whole as u32);
+            self.carry -= whole;
+        }
+    }
+
+    fn take_units(&mut self) -> u32 {
+        std::mem::take(&mut self.units)
+    }
+}
 
 pub struct InputCounter {
     pub clicks: u32,
     pub types: u32,
+    pub ime: ImeNormalizer,
+    pub key_weights: KeyWeights,
+    pub pointer_weights: PointerWeights,
+    pointer: PointerEnergy,
+    pub heat: InputHeat,
+    pub stats: InputStatsTracker,
+    // 特殊キーで出撃待ちの特殊ユニット
+    abilities: Vec<UnitAbility>,
+    type_carry: f32,
+    // キーリピート（押しっぱなし）を除外するため押下中のキーと最後に押下が届いた時刻を記録
+    held_keys: HashMap<Key, Instant>,
+    // 最後にクリック・キー入力があった時刻（放置判定用）
+    last_input: Instant,
+    // rdev はフックを解除できないため、終了処理中はここで入力を捨てる
+    unhooked: bool,
+    // ウィジェットの操作モード切り替え用
+    hotkey: HotkeyDetector,
+    // エネルギーモード（入力はエネルギーになり、ユニットはホットキーで呼び出す）
+    energy_mode: bool,
+    summon_hotkeys: HotkeyBindings<UnitType>,
+    summon_requests: Vec<UnitType>,
+    // コマンドパレットのコマンドを結び付けたホットキーと、押されたもの
+    command_hotkeys: HotkeyBindings<String>,
+    command_requests: Vec<String>,
+    // ゲーム操作のホットキー（入力を数えないときも効く）
+    action_hotkeys: HotkeyBindings<HotkeyAction>,
+    action_requests: Vec<HotkeyAction>,
 }
 
 impl InputCounter {
@@ -12,17 +540,203 @@ impl InputCounter {
         Self {
             clicks: 0,
             types: 0,
+            ime: ImeNormalizer::new(),
+            key_weights: KeyWeights::default(),
+            pointer_weights: PointerWeights::default(),
+            pointer: PointerEnergy::new(),
+            heat: InputHeat::new(),
+            stats: InputStatsTracker::new(InputStats::load()),
+            abilities: Vec::new(),
+            type_carry: 0.0,
+            held_keys: HashMap::new(),
+            last_input: Instant::now(),
+            unhooked: false,
+            hotkey: HotkeyDetector::default(),
+            energy_mode: false,
+            summon_hotkeys: HotkeyBindings::default(),
+            summon_requests: Vec::new(),
+            command_hotkeys: HotkeyBindings::default(),
+            command_requests: Vec::new(),
+            action_hotkeys: HotkeyBindings::default(),
+            action_requests: Vec::new(),
         }
     }
 
-    pub fn add_click(&mut self) {
+    pub fn set_key_weights(&mut self, weights: KeyWeights) {
+        self.key_weights = weights;
+    }
+
+    pub fn set_pointer_weights(&mut self, weights: PointerWeights) {
+        self.pointer_weights = weights;
+    }
+
+    pub fn set_ime_settings(&mut self, enabled: bool, calibration: f32) {
+        self.ime.enabled = enabled;
+        self.ime.calibration = config::clamp_ime_calibration(calibration);
+    }
+
+    pub fn set_hotkey(&mut self, hotkey: Option<Hotkey>) {
+        self.hotkey.set_hotkey(hotkey);
+    }
+
+    // 呼び出しのホットキーはエネルギーモードのときだけ効く
+    pub fn set_energy_mode(&mut self, enabled: bool, hotkeys: Vec<(UnitType, Hotkey)>) {
+        self.energy_mode = enabled;
+        self.summon_hotkeys =
+            HotkeyBindings::new(hotkeys.into_iter().filter(|_| enabled).collect());
+        self.summon_requests.clear();
+    }
+
+    pub fn set_command_hotkeys(&mut self, hotkeys: Vec<(String, HotkeySequence)>) {
+        self.command_hotkeys = HotkeyBindings::new(hotkeys);
+        self.command_requests.clear();
+    }
+
+    pub fn set_action_hotkeys(&mut self, hotkeys: Vec<(HotkeyAction, HotkeySequence)>) {
+        self.action_hotkeys = HotkeyBindings::new(hotkeys);
+        self.action_requests.clear();
+    }
+
+    // 入力を数えないときは押されても捨てる（押下状態だけは追う）
+    fn observe_bindings(&mut self, event: &InputEvent, accept: bool) {
+        let summons = self.summon_hotkeys.observe(event);
+        let commands = self.command_hotkeys.observe(event);
+        self.action_requests
+            .extend(self.action_hotkeys.observe(event));
+        if accept {
+            self.summon_requests.extend(summons);
+            self.command_requests.extend(commands);
+        }
+    }
+
+    pub fn unhook(&mut self) {
+        self.unhooked = true;
+    }
+
+    pub fn add_click(&mut self, button: &Button) {
+        if self.unhooked {
+            return;
+        }
         self.clicks += 1;
+        self.last_input = Instant::now();
+        self.heat.record();
+        self.stats.record_button(button);
+    }
+
+    // スマホから届いたタップ（ボタンの内訳の統計には入れない）
+    pub fn add_remote_clicks(&mut self, taps: u32) {
+        if self.unhooked {
+            return;
+        }
+        self.clicks += taps;
+        self.last_input = Instant::now();
+        for _ in 0..taps {
+            self.heat.record();
+        }
     }
 
     pub fn add_type(&mut self) {
         self.types += 1;
     }
 
+    // 端数は次回に持ち越す
+    pub fn add_weighted_type(&mut self, weight: f32) {
+        self.type_carry += weight;
+        if self.type_carry >= 1.0 {
+            let whole = self.type_carry.floor();
+            self.types = self.types.saturating_add(whole as u32);
+            self.type_carry -= whole;
+        }
+    }
+
+    pub fn add_key(&mut self, key: &Key) {
+        if self.unhooked {
+            return;
+        }
+        // リピートが届いている間は押しっぱなしとみなす。離したのを取りこぼしても
+        // （フォーカスの移動やグラブ中など）間が空いた押下は新しい押下として数える
+        let now = Instant::now();
+        let repeat = self
+            .held_keys
+            .insert(*key, now)
+            .is_some_and(|last| now.duration_since(last) < HELD_KEY_TIMEOUT);
+        if repeat {
+            return;
+        }
+        self.last_input = now;
+        self.heat.record();
+        self.stats.record_key(key);
+        if let Some(ability) = UnitAbility::for_key(key) {
+            self.abilities.push(ability);
+        }
+        let weight = self.ime.weigh(key) * KeyCategory::of(key).weight(&self.key_weights);
+        if weight == 1.0 {
+            self.add_type();
+        } else if weight > 0.0 {
+            self.add_weighted_type(weight);
+        }
+    }
+
+    pub fn release_key(&mut self, key: &Key) {
+        self.held_keys.remove(key);
+    }
+
+    // ホイールとマウス移動は放置判定を解くが、入力の強度や統計には入れない
+    pub fn add_scroll(&mut self, ticks: u32) {
+        if self.unhooked {
+            return;
+        }
+        self.last_input = Instant::now();
+        let weights = &self.pointer_weights;
+        self.pointer
+            .add(ticks as f32 * weights.scroll, weights.max_per_second);
+    }
+
+    pub fn add_mouse_move(&mut self, distance: f32) {
+        if self.unhooked {
+            return;
+        }
+        self.last_input = Instant::now();
+        let weights = &self.pointer_weights;
+        self.pointer
+            .add(distance * weights.mouse_move, weights.max_per_second);
+    }
+
+    pub fn process(&mut self, event: InputEvent) {
+        self.hotkey.observe(&event);
+        self.observe_bindings(&event, true);
+        match event {
+            InputEvent::Click(button) => self.add_click(&button),
+            InputEvent::KeyPress(key) => self.add_key(&key),
+            InputEvent::KeyRelease(key) => self.release_key(&key),
+            InputEvent::Scroll(ticks) => self.add_scroll(ticks),
+            InputEvent::MouseMove(distance) => self.add_mouse_move(distance),
+        }
+    }
+
+    // フックから届いた入力をまとめて反映する（ゲームループから呼ぶ）
+    pub fn drain(&mut self, events: &Receiver<InputEvent>) {
+        for event in events.try_iter() {
+            self.process(event);
+        }
+    }
+
+    // 一時停止中や除外したアプリへの入力は捨てる（押しっぱなし判定が残らないよう離したキーだけ反映）
+    pub fn ignore(&mut self, event: InputEvent) {
+        // ホットキーとゲーム操作は入力を数えないときも効く（呼び出しとコマンドは入力を数えるときだけ）
+        self.hotkey.observe(&event);
+        self.observe_bindings(&event, false);
+        if let InputEvent::KeyRelease(key) = event {
+            self.release_key(&key);
+        }
+    }
+
+    pub fn discard(&mut self, events: &Receiver<InputEvent>) {
+        for event in events.try_iter() {
+            self.ignore(event);
+        }
+    }
+
     pub fn consume_inputs(&mut self) -> (u32, u32) {
         let clicks = self.clicks;
         let types = self.types;
@@ -30,22 +744,37 @@ impl InputCounter {
         self.types = 0;
         (clicks, types)
     }
-}
 
-pub fn start_input_hook(counter: Arc<Mutex<InputCounter>>) {
-    let callback = move |event: Event| match event.event_type {
-        EventType::ButtonPress(_) => {
-            let mut counter = counter.lock();
-            counter.add_click();
-        }
-        EventType::KeyPress(_) => {
-            let mut counter = counter.lock();
-            counter.add_type();
-        }
-        _ => {}
-    };
+    pub fn idle_for(&self) -> Duration {
+        self.last_input.elapsed()
+    }
+
+    // ホイールとマウス移動で貯まった小型ユニットの数
+    pub fn consume_pointer_units(&mut self) -> u32 {
+        self.pointer.take_units()
+    }
+
+    pub fn consume_abilities(&mut self) -> Vec<UnitAbility> {
+        std::mem::take(&mut self.abilities)
+    }
+
+    pub fn take_hotkey(&mut self) -> bool {
+        self.hotkey.take_triggered()
+    }
+
+    pub fn energy_mode(&self) -> bool {
+        self.energy_mode
+    }
+
+    pub fn take_summon_requests(&mut self) -> Vec<UnitType> {
+        std::mem::take(&mut self.summon_requests)
+    }
+
+    pub fn take_command_requests(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.command_requests)
+    }
 
-    if let Err(error) = listen(callback) {
-        eprintln!("Input hook error: {:?}", error);
+    pub fn take_action_requests(&mut self) -> Vec<HotkeyAction> {
+        std::mem::take(&mut self.action_requests)
     }
 }
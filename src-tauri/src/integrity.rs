@@ -0,0 +1,94 @@
+use crate::profile;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// ローカルに置く以上完全な改ざん防止はできないため、手軽な書き換えを検出する目的
+const SIGNING_KEY: &[u8] = b"ClickerClickerClicker/save-integrity/v1";
+
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct SaveIntegrity {
+    // 一度でも署名付きで保存されたか（署名ファイルの削除による回避を防ぐ）
+    pub signed: bool,
+    // 改ざんを検出したセーブ。ローカルでは遊べるがランキング等には送信しない
+    pub tampered: bool,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+}
+
+impl SaveIntegrity {
+    // 0.0〜1.0 の信頼度スコア
+    pub fn score(&self) -> f32 {
+        if self.tampered {
+            0.0
+        } else if self.signed {
+            1.0
+        } else {
+            0.5
+        }
+    }
+
+    pub fn mark_tampered(&mut self, reason: &str) {
+        self.tampered = true;
+        if !self.reasons.iter().any(|r| r == reason) {
+            self.reasons.push(reason.to_string());
+        }
+    }
+}
+
+fn portable_mac() -> HmacSha256 {
+    HmacSha256::new_from_slice(SIGNING_KEY).expect("HMAC accepts keys of any length")
+}
+
+fn mac() -> HmacSha256 {
+    let mut mac = portable_mac();
+    mac.update(profile::active_profile().as_bytes());
+    mac
+}
+
+pub fn sign(contents: &[u8]) -> String {
+    let mut mac = mac();
+    mac.update(contents);
+    encode_hex(&mac.finalize().into_bytes())
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn verify(contents: &[u8], signature: &str) -> bool {
+    let Some(expected) = decode_hex(signature.trim()) else {
+        return false;
+    };
+    let mut mac = mac();
+    mac.update(contents);
+    mac.verify_slice(&expected).is_ok()
+}
+
+// プロファイル名を含めない署名（別の PC・プロファイルへ持ち出すエクスポート用）
+pub fn sign_portable(contents: &[u8]) -> String {
+    let mut mac = portable_mac();
+    mac.update(contents);
+    encode_hex(&mac.finalize().into_bytes())
+}
+
+pub fn verify_portable(contents: &[u8], signature: &str) -> bool {
+    let Some(expected) = decode_hex(signature.trim()) else {
+        return false;
+    };
+    let mut mac = portable_mac();
+    mac.update(contents);
+    mac.verify_slice(&expected).is_ok()
+}
+
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
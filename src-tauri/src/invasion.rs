@@ -0,0 +1,212 @@
+use crate::stage;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 起動して最初の襲撃までと、それ以降の襲撃の間隔（秒）
+const FIRST_DELAY_SECS: f32 = 10.0 * 60.0;
+const MIN_INTERVAL_SECS: f32 = 15.0 * 60.0;
+const MAX_INTERVAL_SECS: f32 = 30.0 * 60.0;
+// 予告してから敵が現れるまで
+pub const WARNING_SECS: f32 = 20.0;
+// 襲撃の敵の数（ステージが進むと増える）
+const BASE_ENEMIES: u32 = 6;
+const ENEMIES_PER_STAGES: u32 = 5;
+const MAX_ENEMIES: u32 = 20;
+// 撃退の基本報酬（ステージの強さに合わせて増える）
+const BASE_REWARD: f64 = 150.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct Warning {
+    remaining: f32,
+    enemies: u32,
+}
+
+// 出現した襲撃の敵（ID が first_id 以上 end_id 未満の敵）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct Raid {
+    stage: u32,
+    first_id: u32,
+    end_id: u32,
+}
+
+// "invasion" で送る
+#[derive(Clone, Serialize, Debug, TS)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum InvasionEvent {
+    Warning {
+        stage: u32,
+        enemies: u32,
+        arrives_in: f32,
+    },
+    Started {
+        stage: u32,
+        enemies: u32,
+    },
+    Repelled {
+        stage: u32,
+        #[ts(type = "number")]
+        coins: u64,
+    },
+    // 敗北・ステージの切り替え・転生で撃退する前に戦場が片付けられた
+    Failed {
+        stage: u32,
+    },
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct InvasionStatus {
+    // 予告中なら敵が現れるまでの秒数
+    pub arrives_in: Option<f32>,
+    // 残っている襲撃の敵
+    pub remaining: usize,
+    pub repelled: u32,
+}
+
+// 次の予告までの時間は通常の敵の出現とは別に進む（放置中も止まらない）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct InvasionState {
+    next_in: f32,
+    warning: Option<Warning>,
+    raid: Option<Raid>,
+    pub repelled: u32,
+    #[serde(skip)]
+    pending: Vec<InvasionEvent>,
+}
+
+impl Default for InvasionState {
+    fn default() -> Self {
+        Self {
+            next_in: FIRST_DELAY_SECS,
+            warning: None,
+            raid: None,
+            repelled: 0,
+            pending: Vec::new(),
+        }
+    }
+}
+
+// 更新で起こすこと
+pub enum InvasionStep {
+    Warn,
+    Arrive(u32),
+}
+
+pub fn enemy_count(stage: u32) -> u32 {
+    (BASE_ENEMIES + stage / ENEMIES_PER_STAGES).min(MAX_ENEMIES)
+}
+
+pub fn reward(stage: u32) -> f64 {
+    BASE_REWARD * stage::stat_multiplier(stage) as f64
+}
+
+impl InvasionState {
+    // ready でない間（クリア演出やボス戦の最中）は予告を始めずに待つ
+    // roll は 0.0〜1.0 の乱数で、次の間隔を決めるときだけ使う
+    pub fn tick(
+        &mut self,
+        delta: f32,
+        stage: u32,
+        ready: bool,
+        roll: impl FnOnce() -> f32,
+    ) -> Option<InvasionStep> {
+        if self.raid.is_some() {
+            return None;
+        }
+        if let Some(warning) = self.warning.as_mut() {
+            warning.remaining -= delta;
+            if warning.remaining > 0.0 {
+                return None;
+            }
+            let enemies = warning.enemies;
+            self.warning = None;
+            return Some(InvasionStep::Arrive(enemies));
+        }
+        self.next_in = (self.next_in - delta).max(0.0);
+        if self.next_in > 0.0 || !ready {
+            return None;
+        }
+        self.next_in = MIN_INTERVAL_SECS + (MAX_INTERVAL_SECS - MIN_INTERVAL_SECS) * roll();
+        let enemies = enemy_count(stage);
+        self.warning = Some(Warning {
+            remaining: WARNING_SECS,
+            enemies,
+        });
+        self.pending.push(InvasionEvent::Warning {
+            stage,
+            enemies,
+            arrives_in: WARNING_SECS,
+        });
+        Some(InvasionStep::Warn)
+    }
+
+    // 出現させた敵の ID の範囲を記録する（1 体も出せなければ何もしない）
+    pub fn begin(&mut self, stage: u32, first_id: u32, end_id: u32) {
+        if end_id <= first_id {
+            return;
+        }
+        self.raid = Some(Raid {
+            stage,
+            first_id,
+            end_id,
+        });
+        self.pending.push(InvasionEvent::Started {
+            stage,
+            enemies: end_id - first_id,
+        });
+    }
+
+    pub fn is_invader(&self, id: u32) -> bool {
+        self.raid
+            .as_ref()
+            .is_some_and(|raid| (raid.first_id..raid.end_id).contains(&id))
+    }
+
+    // 襲撃の敵が残っていなければ撃退として終わらせ、そのステージを返す
+    pub fn settle(&mut self, remaining: usize) -> Option<u32> {
+        if remaining > 0 {
+            return None;
+        }
+        let raid = self.raid.take()?;
+        self.repelled += 1;
+        Some(raid.stage)
+    }
+
+    pub fn record_reward(&mut self, stage: u32, coins: u64) {
+        self.pending.push(InvasionEvent::Repelled { stage, coins });
+    }
+
+    // 敗北などで戦場が片付けられた
+    pub fn abandon(&mut self) {
+        if let Some(raid) = self.raid.take() {
+            self.pending
+                .push(InvasionEvent::Failed { stage: raid.stage });
+        }
+    }
+
+    pub fn repair(&mut self) {
+        if !self.next_in.is_finite() {
+            self.next_in = FIRST_DELAY_SECS;
+        }
+        self.next_in = self.next_in.clamp(0.0, MAX_INTERVAL_SECS);
+        if let Some(warning) = self.warning.as_mut() {
+            if !warning.remaining.is_finite() {
+                warning.remaining = 0.0;
+            }
+            warning.remaining = warning.remaining.clamp(0.0, WARNING_SECS);
+            warning.enemies = warning.enemies.min(MAX_ENEMIES);
+        }
+    }
+
+    pub fn status(&self, remaining: usize) -> InvasionStatus {
+        InvasionStatus {
+            arrives_in: self.warning.as_ref().map(|warning| warning.remaining),
+            remaining,
+            repelled: self.repelled,
+        }
+    }
+
+    pub fn take_pending(&mut self) -> Vec<InvasionEvent> {
+        std::mem::take(&mut self.pending)
+    }
+}
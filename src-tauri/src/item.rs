@@ -0,0 +1,182 @@
+use crate::error::GameError;
+use crate::stage;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Airstrike,
+    FreezeBomb,
+    RepairKit,
+}
+
+impl ItemKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemKind::Airstrike => "airstrike",
+            ItemKind::FreezeBomb => "freeze_bomb",
+            ItemKind::RepairKit => "repair_kit",
+        }
+    }
+}
+
+// コインで買える消耗品（種類, 基本価格, 使った後のクールダウン秒）
+const ITEMS: [(ItemKind, u64, f32); 3] = [
+    (ItemKind::Airstrike, 500, 30.0),
+    (ItemKind::FreezeBomb, 300, 45.0),
+    (ItemKind::RepairKit, 400, 60.0),
+];
+// 1 種類あたりの所持数の上限
+pub const MAX_STACK: u32 = 99;
+// 空爆は全ての敵に最大HPのこの割合のダメージを与える（ボスには少しだけ）
+pub const AIRSTRIKE_RATIO: f32 = 0.6;
+pub const BOSS_AIRSTRIKE_RATIO: f32 = 0.05;
+// 凍結中は敵が動かず攻撃もせず、新しい敵も出てこない
+pub const FREEZE_SECS: f32 = 6.0;
+// 修理キットで自陣の最大HPのこの割合を回復する
+pub const REPAIR_RATIO: f32 = 0.35;
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct ItemStatus {
+    pub kind: ItemKind,
+    pub count: u32,
+    #[ts(type = "number")]
+    pub price: u64,
+    pub cooldown_remaining: f32,
+    pub cooldown_total: f32,
+    // 効果が続いている残り時間（凍結のみ）
+    pub active_remaining: f32,
+}
+
+// 所持数と残りのクールダウン（ITEMS と同じ順）、使用中の効果
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ItemInventory {
+    counts: [u32; ITEMS.len()],
+    cooldowns: [f32; ITEMS.len()],
+    freeze: f32,
+    // 次の更新で落とす空爆
+    airstrikes: u32,
+}
+
+impl ItemInventory {
+    fn slot(kind: ItemKind) -> usize {
+        ITEMS
+            .iter()
+            .position(|&(item, _, _)| item == kind)
+            .unwrap_or(0)
+    }
+
+    // 後のステージほど敵と報酬に合わせて高くなる
+    fn price(slot: usize, stage: u32) -> u64 {
+        (ITEMS[slot].1 as f64 * stage::stat_multiplier(stage) as f64).min(u64::MAX as f64) as u64
+    }
+
+    // 買えるなら合計の価格
+    pub fn check_purchase(
+        &self,
+        kind: ItemKind,
+        quantity: u32,
+        stage: u32,
+        coins: u64,
+    ) -> Result<u64, GameError> {
+        let slot = Self::slot(kind);
+        if self.counts[slot].saturating_add(quantity) > MAX_STACK {
+            return Err(GameError::ItemStackFull {
+                item: kind.as_str().to_string(),
+                max: MAX_STACK,
+            });
+        }
+        let cost = Self::price(slot, stage).saturating_mul(quantity as u64);
+        if coins < cost {
+            return Err(GameError::NotEnoughCoins {
+                needed: cost,
+                have: coins,
+            });
+        }
+        Ok(cost)
+    }
+
+    pub fn add(&mut self, kind: ItemKind, quantity: u32) {
+        let slot = Self::slot(kind);
+        self.counts[slot] = self.counts[slot].saturating_add(quantity).min(MAX_STACK);
+    }
+
+    // 使えるなら 1 つ減らしてクールダウンを始める（効果は呼び出し側で与える）
+    pub fn take(&mut self, kind: ItemKind) -> Result<(), GameError> {
+        let slot = Self::slot(kind);
+        if self.counts[slot] == 0 {
+            return Err(GameError::NoItemsLeft {
+                item: kind.as_str().to_string(),
+            });
+        }
+        if self.cooldowns[slot] > 0.0 {
+            return Err(GameError::ItemOnCooldown {
+                item: kind.as_str().to_string(),
+                remaining_secs: self.cooldowns[slot],
+            });
+        }
+        self.counts[slot] -= 1;
+        self.cooldowns[slot] = ITEMS[slot].2;
+        Ok(())
+    }
+
+    pub fn queue_airstrike(&mut self) {
+        self.airstrikes += 1;
+    }
+
+    pub fn take_airstrikes(&mut self) -> u32 {
+        std::mem::take(&mut self.airstrikes)
+    }
+
+    pub fn freeze(&mut self) {
+        self.freeze = FREEZE_SECS;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.freeze > 0.0
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - delta).max(0.0);
+        }
+        self.freeze = (self.freeze - delta).max(0.0);
+    }
+
+    // 読み込んだ値が壊れていてもすぐ使える状態に戻す
+    pub fn repair(&mut self) {
+        for (cooldown, &(_, _, total)) in self.cooldowns.iter_mut().zip(&ITEMS) {
+            if !cooldown.is_finite() {
+                *cooldown = 0.0;
+            }
+            *cooldown = cooldown.clamp(0.0, total);
+        }
+        for count in &mut self.counts {
+            *count = (*count).min(MAX_STACK);
+        }
+        if !self.freeze.is_finite() {
+            self.freeze = 0.0;
+        }
+        self.freeze = self.freeze.clamp(0.0, FREEZE_SECS);
+    }
+
+    pub fn status(&self, stage: u32) -> Vec<ItemStatus> {
+        ITEMS
+            .iter()
+            .enumerate()
+            .map(|(slot, &(kind, _, total))| ItemStatus {
+                kind,
+                count: self.counts[slot],
+                price: Self::price(slot, stage),
+                cooldown_remaining: self.cooldowns[slot],
+                cooldown_total: total,
+                active_remaining: if kind == ItemKind::FreezeBomb {
+                    self.freeze
+                } else {
+                    0.0
+                },
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use crate::encryption;
+use crate::game::{PlayerProgressData, UnitType};
+use crate::integrity;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+// セーブの合間に起きた大きな進行の変化
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEvent {
+    Purchase {
+        upgrade_type: String,
+        unit_type: String,
+    },
+    StageClear {
+        stage: u32,
+    },
+    Prestige {
+        count: u32,
+    },
+    Season {
+        season: u32,
+    },
+    Summon {
+        unit_type: UnitType,
+    },
+}
+
+// 変化の直後の進行状況をまるごと持つので、最後の 1 件を反映すれば復元できる
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct JournalEntry {
+    pub at: i64,
+    pub event: JournalEvent,
+    pub progress: PlayerProgressData,
+}
+
+// 1 行 1 件で「暗号化した JSON（16 進）<TAB>署名」を追記する
+pub fn append(path: &Path, entry: &JournalEntry) -> Result<(), String> {
+    let json = serde_json::to_vec(entry).map_err(|e| e.to_string())?;
+    let sealed = encryption::seal(&json)?;
+    let line = format!(
+        "{}\t{}\n",
+        integrity::encode_hex(&sealed),
+        integrity::sign(&json)
+    );
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+    file.sync_data().map_err(|e| e.to_string())
+}
+
+// 書き込み途中で終了した末尾や署名の合わない行があれば、そこから先は読まない
+pub fn read(path: &Path) -> Vec<JournalEntry> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .map_while(|line| {
+            let (payload, signature) = line.split_once('\t')?;
+            let sealed = integrity::decode_hex(payload)?;
+            let json = encryption::open(&sealed).ok()?;
+            if !integrity::verify(&json, signature) {
+                return None;
+            }
+            serde_json::from_slice(&json).ok()
+        })
+        .collect()
+}
+
+// セーブが書けたらそれまでの記録は要らない
+pub fn clear(path: &Path) {
+    if path.exists() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Upgrades;
+
+    // 中身は読み戻せるかどうかだけを見るので、進行状況は最小限
+    fn entry(stage: u32) -> JournalEntry {
+        let progress = serde_json::from_value(serde_json::json!({
+            "stage": stage,
+            "coins": 0,
+            "upgrades": Upgrades::new(),
+            "max_player_base_hp": 1000.0,
+            "max_enemy_base_hp": 500.0,
+        }))
+        .unwrap();
+        JournalEntry {
+            at: stage as i64,
+            event: JournalEvent::StageClear { stage },
+            progress,
+        }
+    }
+
+    // 追記した順に読み戻し、壊れた行から先は捨てる
+    #[test]
+    fn entries_round_trip_until_a_damaged_line() {
+        let path = std::env::temp_dir().join(format!("kurikka-journal-{}.log", std::process::id()));
+        clear(&path);
+        assert!(read(&path).is_empty());
+
+        append(&path, &entry(2)).unwrap();
+        append(&path, &entry(3)).unwrap();
+        let stages = |entries: Vec<JournalEntry>| -> Vec<u32> {
+            entries.iter().map(|entry| entry.progress.stage).collect()
+        };
+        assert_eq!(stages(read(&path)), [2, 3]);
+        assert_eq!(read(&path)[1].event, JournalEvent::StageClear { stage: 3 });
+
+        // 署名の合わない行・書きかけの行の後ろは読まない
+        let contents = fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+        let (payload, _) = lines[1].split_once('\t').unwrap();
+        lines[1] = format!("{}\t{}", payload, integrity::sign(b"something else"));
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+        append(&path, &entry(4)).unwrap();
+        assert_eq!(stages(read(&path)), [2]);
+
+        fs::write(&path, format!("{}\n{}", lines[0], &lines[0][..10])).unwrap();
+        assert_eq!(stages(read(&path)), [2]);
+
+        clear(&path);
+        assert!(!path.exists());
+    }
+}
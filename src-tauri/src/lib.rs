@@ -1,242 +1,2262 @@
 use parking_lot::Mutex;
 use serde::Serialize;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
-use tauri::{Emitter, Manager, PhysicalPosition, PhysicalSize, Position, Size, WindowEvent};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, Position, Size, WindowEvent};
+use ts_rs::TS;
 
+mod a11y;
+mod ability;
+mod achievements;
+mod auto_sync;
+mod barracks;
+mod boss;
+mod budget;
+mod checkpoint;
+mod chest;
+mod cinematic;
+mod combo;
 mod config;
+mod conflict;
+mod coop;
+mod cosmetics;
+mod encryption;
+mod enemy_kind;
+mod energy;
+mod error;
+mod events;
+mod focus;
+mod forecast;
+mod foreground;
+mod friends;
 mod game;
+mod history;
+mod hotkey;
+mod hotkey_action;
+mod i18n;
+mod idle;
+mod input_backend;
 mod input_hook;
+mod integrity;
+mod invasion;
+mod item;
+mod journal;
+mod loop_control;
+mod macros;
+mod morale;
 mod multiplayer;
+mod multiplayer_memory;
+mod net_stats;
+mod notifications;
+mod numbers;
+mod offline;
+mod orders;
+mod palette;
+mod personality;
+mod prestige;
+mod profile;
+mod pvp;
+mod quests;
+mod rally;
+mod recap;
+mod remote;
+mod replay;
+mod report;
+mod respec;
+mod rules;
+mod save_check;
+mod save_code;
+mod save_writer;
+mod schedule;
+mod season;
+mod session;
+mod shop;
+mod shutdown;
+pub mod sim;
+mod spatial;
+mod stage;
+mod stance;
+mod stats;
+mod summon;
+mod taskbar;
+mod timelapse;
+mod titles;
+mod tray;
+mod typing_import;
+mod unit_events;
+mod watchdog;
+mod widget;
 
+use a11y::{A11yAnnouncer, StateSummary};
+use achievements::AchievementStatus;
+use barracks::ForwardBarracks;
+use boss::BossStatus;
+use budget::MemoryUsage;
+use checkpoint::CheckpointInfo;
+use chest::{Chest, ChestDropRates, ChestReward};
 use config::AppConfig;
-use game::{AutoBuyConfig, GameState, Unit, UnitType};
-use input_hook::InputCounter;
-use multiplayer::MultiplayerClient;
+use conflict::{Divergence, Resolution, SyncConflict};
+use coop::{CoopPlayer, CoopStatus};
+use cosmetics::{CosmeticKind, CosmeticStatus};
+use encryption::SaveEncryption;
+use energy::EnergyStatus;
+use error::GameError;
+use focus::FocusBlock;
+use forecast::{ForecastResult, UpgradeRecommendation};
+use foreground::{ForegroundAppStatus, ForegroundWatcher, MonitorRect};
+use friends::FriendProgress;
+use game::{AutoBuyConfig, AutoBuyEntry, GameState, SaveSlotInfo, TickInput, Unit, UnitType};
+use history::{HistoryRange, ProgressHistory, ProgressSample};
+use hotkey_action::{HotkeyAction, HotkeyActionFeedback};
+use idle::IdleTracker;
+use input_backend::{InputBackendKind, InputBackendMonitor, InputBackendStatus};
+use input_hook::{InputCounter, InputStats, InputStatsSummary};
+use invasion::InvasionStatus;
+use item::{ItemKind, ItemStatus};
+use loop_control::{LoopControl, LoopStatus};
+use macros::{Macro, MacroAction, MacroRecorder, MacroRunResult};
+use multiplayer::{
+    ClockStatus, GuildInfo, GuildLeaderboardPage, LeaderboardPage, MaintenanceNotice,
+    MultiplayerApi, MultiplayerClient, MultiplayerError, PlayerProfile, RaidBossStatus,
+    RaidLeaderboardPage, RaidReward, RegisterResult, ServerHealth,
+};
+use multiplayer_memory::InMemoryMultiplayer;
+use net_stats::Diagnostics;
+use notifications::Notification;
+use numbers::NumberStyle;
+use orders::{Order, UnitSelector};
+use palette::{CommandHelp, CommandOutcome};
+use prestige::PrestigeStatus;
+use profile::ProfileIndex;
+use pvp::BattleResult;
+use quests::Quest;
+use rally::RallyPoint;
+use remote::{RemotePairing, RemotePlay, RemoteStatus};
+use replay::ReplayLog;
+use report::BattleReport;
+use respec::RespecQuote;
+use rules::{Difficulty, GameRules};
+use save_check::SaveCheckReport;
+use schedule::{ScheduleStatus, Scheduler};
+use season::SeasonInfo;
+use session::SessionSummary;
+use shop::ShopStatus;
+use shutdown::Shutdown;
+use stage::StagePreview;
+use stance::Stance;
+use stats::{StatsHistory, StatsRange, StatsSample, StatsSummary};
+use summon::SummonStatus;
+use taskbar::{Indicator, Taskbar};
+use timelapse::TimelapseExport;
+use titles::TitleStatus;
+use unit_events::{CooldownTracker, UnitDiffTracker};
+use watchdog::{Incident, Subsystem, Watchdog};
+use widget::{MonitorInfo, WidgetFrame, WidgetMode, WidgetStyle};
 
-#[derive(Clone, Serialize)]
+// ユニットはスナップショットのときだけ含める（通常は unit-* イベントの差分で送る）
+#[derive(Clone, Serialize, TS)]
 struct GameStateUpdate {
-    player_units: Vec<Unit>,
-    enemy_units: Vec<Unit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    player_units: Option<Vec<Unit>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enemy_units: Option<Vec<Unit>>,
     player_base_hp: f32,
     enemy_base_hp: f32,
-    coins: u32,
+    #[ts(type = "number")]
+    coins: u64,
+    // 省略表記（例: "1.23M"）
+    coins_display: String,
+    // 桁区切り表記（例: "1,234,567"）
+    coins_grouped: String,
     stage: u32,
     click_count: u32,
     type_count: u32,
     upgrades: game::Upgrades,
+    farming: bool,
+    // 前線の位置（0.0 = 自陣、1.0 = 敵陣）
+    front_line: f32,
+    // 現在のステージのレーン数（ユニットの lane は 0 から）
+    lanes: u8,
+    // 士気（-1.0〜1.0）
+    morale: f32,
+    barracks: Option<ForwardBarracks>,
+    rally: Option<RallyPoint>,
+    stance: Stance,
+    boss: Option<BossStatus>,
+    combo: u32,
+    combo_multiplier: f32,
+    unopened_chests: usize,
+    // コインによる呼び出しのコストと残りクールダウン
+    summons: Vec<SummonStatus>,
+    // 消耗品の所持数・価格・クールダウン
+    items: Vec<ItemStatus>,
+    // 襲撃の予告の残り時間と残っている襲撃の敵
+    invasion: InvasionStatus,
+    // シミュレーションの進む速さ（クリア演出中は 1.0 未満）
+    time_scale: f32,
+    // 直近60秒の入力強度（古い順、1秒ごと）
+    input_heat: Vec<u32>,
+    // 協力プレイ中だけ 2 人の財布と撃破数
+    coop: Option<CoopStatus>,
+    // エネルギーモードで貯めた量
+    energy: f64,
+    // 控えめモードでは false（画面側で演出を出さない）
+    animations: bool,
+}
+
+impl GameStateUpdate {
+    fn new(game: &GameState, input_heat: Vec<u32>, with_units: bool, animations: bool) -> Self {
+        Self {
+            player_units: with_units.then(|| game.player_units.clone()),
+            enemy_units: with_units.then(|| game.enemy_units.clone()),
+            player_base_hp: game.player_base_hp,
+            enemy_base_hp: game.enemy_base_hp,
+            coins: game.coins,
+            coins_display: numbers::format_compact(game.coins),
+            coins_grouped: numbers::format_grouped(game.coins, numbers::DEFAULT_LOCALE),
+            stage: game.stage,
+            click_count: game.click_count,
+            type_count: game.type_count,
+            upgrades: game.upgrades.clone(),
+            farming: game.farming,
+            front_line: game.front_line(),
+            lanes: game.lane_count(),
+            morale: game.morale.value,
+            barracks: game.barracks.clone(),
+            rally: game.rally.clone(),
+            stance: game.stance,
+            boss: game.boss_status(),
+            combo: game.combo.count(),
+            combo_multiplier: game.combo.multiplier(),
+            unopened_chests: game.chests.chests.len(),
+            summons: game.summon_status(),
+            items: game.item_status(),
+            invasion: game.invasion_status(),
+            time_scale: game.clear_sequence.time_scale(),
+            input_heat,
+            coop: game.coop.enabled.then(|| game.coop_status()),
+            energy: game.energy.energy(),
+            animations,
+        }
+    }
+}
+
+// コンパクト表示のウィジェット向けの要約（ユニットの一覧や強化の内訳は含めない）
+#[derive(Clone, Serialize, TS)]
+struct CompactWidgetUpdate {
+    coins_display: String,
+    stage: u32,
+    // 基地の残り体力（0.0〜1.0）
+    player_base: f32,
+    enemy_base: f32,
+    front_line: f32,
+    farming: bool,
+    boss: bool,
+    combo: u32,
+    animations: bool,
+}
+
+impl CompactWidgetUpdate {
+    fn new(game: &GameState, animations: bool) -> Self {
+        let ratio = |hp: f32, max: f32| if max > 0.0 { hp / max } else { 0.0 };
+        Self {
+            coins_display: numbers::format_compact(game.coins),
+            stage: game.stage,
+            player_base: ratio(game.player_base_hp, game.max_player_base_hp),
+            enemy_base: ratio(game.enemy_base_hp, game.max_enemy_base_hp),
+            front_line: game.front_line(),
+            farming: game.farming,
+            boss: game.boss.is_some(),
+            combo: game.combo.count(),
+            animations,
+        }
+    }
+}
+
+// 報酬の抽選確率はすべてここから公開する
+#[derive(Clone, Serialize)]
+struct DropRatesResponse {
+    chests: ChestDropRates,
+}
+
+#[derive(Clone, Serialize)]
+struct RegisterCommandResponse {
+    player_id: String,
+    player_name: String,
+    message: String,
+    last_update: i64,
+    stage: u32,
+    coins: u64,
+}
+
+// ウィジェット・共有カード・オーバーレイで表示をそろえるための共通書式
+#[tauri::command]
+fn format_number(value: u64, style: Option<NumberStyle>, locale: Option<String>) -> String {
+    numbers::format(
+        value,
+        style.unwrap_or_default(),
+        locale.as_deref().unwrap_or(numbers::DEFAULT_LOCALE),
+    )
+}
+
+#[tauri::command]
+fn get_game_state(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    input_counter: tauri::State<Arc<Mutex<InputCounter>>>,
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+) -> GameStateUpdate {
+    let input_heat = input_counter.lock().heat.snapshot();
+    let game = state.lock();
+    GameStateUpdate::new(&game, input_heat, true, !loop_control.is_low_distraction())
+}
+
+// 同じ LAN のスマホのブラウザからタップを送れるようにする（返す QR コードを読み取って開く）
+#[tauri::command]
+fn start_remote_play(
+    remote: tauri::State<'_, Arc<RemotePlay>>,
+    input_counter: tauri::State<'_, Arc<Mutex<InputCounter>>>,
+    port: Option<u16>,
+) -> Result<RemotePairing, GameError> {
+    remote.start(
+        port.unwrap_or(remote::DEFAULT_PORT),
+        Arc::clone(&input_counter),
+    )
+}
+
+#[tauri::command]
+fn stop_remote_play(remote: tauri::State<'_, Arc<RemotePlay>>) {
+    remote.stop();
+}
+
+#[tauri::command]
+fn get_remote_play_status(remote: tauri::State<'_, Arc<RemotePlay>>) -> RemoteStatus {
+    remote.status()
+}
+
+#[tauri::command]
+fn get_input_stats(input_counter: tauri::State<Arc<Mutex<InputCounter>>>) -> InputStatsSummary {
+    let stats = input_counter.lock().stats.snapshot();
+    stats.summary()
+}
+
+#[tauri::command]
+fn purchase_upgrade(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    upgrade_type: String,
+    unit_type: String,
+    player: Option<CoopPlayer>,
+) -> Result<bool, GameError> {
+    let mut game = state.lock();
+    let purchased =
+        game.purchase_upgrade_as(&upgrade_type, &unit_type, player.unwrap_or_default())?;
+    recorder.record(MacroAction::PurchaseUpgrade {
+        upgrade_type,
+        unit_type,
+    });
+    Ok(purchased)
+}
+
+// コインを払ってユニットを呼び出し、払ったコインを返す
+#[tauri::command]
+fn summon_unit(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    unit_type: UnitType,
+) -> Result<u64, GameError> {
+    state.lock().summon_unit(unit_type)
+}
+
+// 消耗品を買い、払ったコインを返す（quantity を省くと 1 つ）
+#[tauri::command]
+fn buy_item(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    kind: ItemKind,
+    quantity: Option<u32>,
+) -> Result<u64, GameError> {
+    state.lock().buy_item(kind, quantity.unwrap_or(1).max(1))
+}
+
+#[tauri::command]
+fn use_item(state: tauri::State<Arc<Mutex<GameState>>>, kind: ItemKind) -> Result<(), GameError> {
+    state.lock().use_item(kind)
+}
+
+#[tauri::command]
+fn get_items(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<ItemStatus> {
+    state.lock().item_status()
+}
+
+#[tauri::command]
+fn get_invasion(state: tauri::State<Arc<Mutex<GameState>>>) -> InvasionStatus {
+    state.lock().invasion_status()
+}
+
+#[tauri::command]
+fn reset_stage(state: tauri::State<Arc<Mutex<GameState>>>) {
+    let mut game = state.lock();
+    game.reset_current_stage();
+}
+
+#[tauri::command]
+fn advance_stage(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+) -> Result<(), GameError> {
+    state.lock().advance_stage()?;
+    recorder.record(MacroAction::AdvanceStage);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_manual_advance(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    enabled: bool,
+) {
+    state.lock().set_manual_advance(enabled);
+    recorder.record(MacroAction::SetManualAdvance { enabled });
+}
+
+#[tauri::command]
+fn get_chests(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<Chest> {
+    state.lock().chests.chests.clone()
+}
+
+#[tauri::command]
+fn open_chest(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    chest_id: u32,
+) -> Result<ChestReward, GameError> {
+    state.lock().open_chest(chest_id)
+}
+
+#[tauri::command]
+fn get_drop_rates(state: tauri::State<Arc<Mutex<GameState>>>) -> DropRatesResponse {
+    DropRatesResponse {
+        chests: state.lock().chests.drop_rates(),
+    }
+}
+
+#[tauri::command]
+fn build_barracks(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    position: f32,
+) -> Result<(), GameError> {
+    state.lock().build_barracks(position)?;
+    recorder.record(MacroAction::BuildBarracks { position });
+    Ok(())
+}
+
+#[tauri::command]
+fn set_rally_point(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    x: f32,
+    threshold: Option<u32>,
+) -> Result<(), GameError> {
+    state.lock().set_rally_point(x, threshold)
+}
+
+#[tauri::command]
+fn set_stance(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    stance: Stance,
+) -> Result<(), GameError> {
+    state.lock().set_stance(stance)?;
+    recorder.record(MacroAction::SetStance { stance });
+    Ok(())
+}
+
+// キーボードの入力を A、マウスの入力を B のユニットにする（基地と強化は共有、財布は別）
+#[tauri::command]
+fn set_coop_mode(state: tauri::State<Arc<Mutex<GameState>>>, enabled: bool) -> CoopStatus {
+    let mut game = state.lock();
+    game.set_coop(enabled);
+    game.coop_status()
+}
+
+#[tauri::command]
+fn summon_with_energy(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    unit_type: UnitType,
+) -> Result<EnergyStatus, GameError> {
+    if !AppConfig::load().energy.enabled {
+        return Err(GameError::EnergyModeOff);
+    }
+    let mut game = state.lock();
+    game.summon_with_energy(unit_type)?;
+    Ok(game.energy.status(true))
+}
+
+#[tauri::command]
+fn get_energy_status(state: tauri::State<Arc<Mutex<GameState>>>) -> EnergyStatus {
+    state.lock().energy.status(AppConfig::load().energy.enabled)
+}
+
+// コマンドパレットから打ち込んだコマンド（"buy attack small x5" など）
+#[tauri::command]
+fn execute_command(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    text: String,
+) -> Result<CommandOutcome, GameError> {
+    palette::run(&text, &mut state.lock())
+}
+
+// スクリーンリーダー向けの戦況の要約（locale を省けば設定の言語）
+#[tauri::command]
+fn get_state_summary_text(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    announcer: tauri::State<'_, Arc<A11yAnnouncer>>,
+    locale: Option<String>,
+) -> StateSummary {
+    let locale = locale.unwrap_or_else(|| announcer.locale());
+    a11y::summarize(&state.lock(), &locale)
+}
+
+#[tauri::command]
+fn get_command_help() -> Vec<CommandHelp> {
+    palette::help()
+}
+
+#[tauri::command]
+fn get_coop_status(state: tauri::State<Arc<Mutex<GameState>>>) -> CoopStatus {
+    state.lock().coop_status()
+}
+
+#[tauri::command]
+fn get_difficulty(state: tauri::State<Arc<Mutex<GameState>>>) -> GameRules {
+    state.lock().rules().clone()
+}
+
+// Custom のときだけ custom の倍率を使う。戦闘中に変えるには reset_stage が要る
+#[tauri::command]
+fn set_difficulty(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    difficulty: Difficulty,
+    custom: Option<GameRules>,
+    reset_stage: Option<bool>,
+) -> Result<GameRules, GameError> {
+    let rules = match (difficulty, custom) {
+        (Difficulty::Custom, Some(custom)) => GameRules {
+            difficulty: Difficulty::Custom,
+            ..custom
+        },
+        _ => GameRules::preset(difficulty),
+    };
+    state
+        .lock()
+        .set_difficulty(rules, reset_stage.unwrap_or(false))
+}
+
+#[tauri::command]
+fn clear_rally_point(state: tauri::State<Arc<Mutex<GameState>>>) {
+    state.lock().clear_rally_point();
+}
+
+#[tauri::command]
+fn release_rally(state: tauri::State<Arc<Mutex<GameState>>>) -> usize {
+    state.lock().release_rally()
+}
+
+// 兵種・範囲・レーンで味方を選ぶ（選んだ ID は画面側で持ち、命令に添えて送る）
+#[tauri::command]
+fn select_units(state: tauri::State<Arc<Mutex<GameState>>>, selector: UnitSelector) -> Vec<u32> {
+    state.lock().select_units(&selector)
+}
+
+// 選んだ味方に移動・攻撃を命じる（期限が切れるか取り消すと AI に戻る）
+#[tauri::command]
+fn order_units(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    unit_ids: Vec<u32>,
+    order: Order,
+    duration_secs: Option<f32>,
+) -> Result<usize, GameError> {
+    state.lock().order_units(&unit_ids, order, duration_secs)
+}
+
+#[tauri::command]
+fn cancel_orders(state: tauri::State<Arc<Mutex<GameState>>>, unit_ids: Vec<u32>) -> usize {
+    state.lock().cancel_orders(&unit_ids)
+}
+
+#[tauri::command]
+fn get_achievements(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<AchievementStatus> {
+    state.lock().achievements.list()
+}
+
+#[tauri::command]
+fn get_quests(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<Quest> {
+    let mut game = state.lock();
+    // ゲームループが止まっていても日付が変わっていれば作り直す
+    let stage = game.stage;
+    game.quests.refresh(game::unix_timestamp(), stage);
+    game.quests.list()
+}
+
+#[tauri::command]
+fn claim_quest_reward(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    quest_id: String,
+) -> Result<Quest, GameError> {
+    state.lock().claim_quest_reward(&quest_id)
+}
+
+#[tauri::command]
+fn get_titles(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<TitleStatus> {
+    state.lock().titles.list()
+}
+
+#[tauri::command]
+fn set_active_title(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    id: Option<String>,
+) -> Result<(), GameError> {
+    state.lock().set_active_title(id)
+}
+
+#[tauri::command]
+fn get_cosmetics(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<CosmeticStatus> {
+    state.lock().cosmetics_status()
+}
+
+// 選んだアバターは次の同期でランキングに反映される
+#[tauri::command]
+fn set_cosmetic(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    kind: CosmeticKind,
+    id: Option<String>,
+) -> Result<Vec<CosmeticStatus>, GameError> {
+    let mut game = state.lock();
+    game.set_cosmetic(kind, id)?;
+    Ok(game.cosmetics_status())
+}
+
+#[tauri::command]
+fn get_battle_reports(state: tauri::State<Arc<Mutex<GameState>>>) -> Vec<BattleReport> {
+    state.lock().battle.list()
+}
+
+#[tauri::command]
+fn get_prestige_state(state: tauri::State<Arc<Mutex<GameState>>>) -> PrestigeStatus {
+    state.lock().prestige_status()
+}
+
+#[tauri::command]
+fn do_prestige(state: tauri::State<Arc<Mutex<GameState>>>) -> Result<PrestigeStatus, GameError> {
+    let mut game = state.lock();
+    game.prestige()?;
+    Ok(game.prestige_status())
+}
+
+#[tauri::command]
+fn buy_prestige_upgrade(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    kind: String,
+) -> Result<PrestigeStatus, GameError> {
+    let mut game = state.lock();
+    game.purchase_prestige_upgrade(&kind)?;
+    recorder.record(MacroAction::PrestigeUpgrade { kind });
+    Ok(game.prestige_status())
+}
+
+// 手元に取ってある転生ショップの品揃え（期限が切れていれば expired）
+#[tauri::command]
+fn get_shop(state: tauri::State<Arc<Mutex<GameState>>>) -> ShopStatus {
+    state.lock().shop_status()
+}
+
+#[tauri::command]
+fn buy_shop_offer(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    item: String,
+) -> Result<ShopStatus, GameError> {
+    let mut game = state.lock();
+    game.buy_shop_offer(&item)?;
+    Ok(game.shop_status())
+}
+
+#[tauri::command]
+fn get_respec_quote(state: tauri::State<Arc<Mutex<GameState>>>) -> RespecQuote {
+    state
+        .lock()
+        .respec_quote(AppConfig::load().respec_refund_percent)
+}
+
+#[tauri::command]
+fn respec(state: tauri::State<Arc<Mutex<GameState>>>) -> Result<RespecQuote, GameError> {
+    state.lock().respec(AppConfig::load().respec_refund_percent)
+}
+
+#[tauri::command]
+fn start_macro_recording(
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+    name: String,
+) -> Result<(), GameError> {
+    recorder.start(&name)
+}
+
+#[tauri::command]
+fn stop_macro_recording(
+    recorder: tauri::State<'_, Arc<MacroRecorder>>,
+) -> Result<Macro, GameError> {
+    recorder.stop()
+}
+
+#[tauri::command]
+fn cancel_macro_recording(recorder: tauri::State<'_, Arc<MacroRecorder>>) {
+    recorder.cancel();
+}
+
+#[tauri::command]
+fn get_macro_recording(recorder: tauri::State<'_, Arc<MacroRecorder>>) -> Option<Macro> {
+    recorder.status()
+}
+
+#[tauri::command]
+fn list_macros() -> Vec<Macro> {
+    macros::load_macros()
+}
+
+#[tauri::command]
+fn delete_macro(name: String) -> Result<(), GameError> {
+    macros::delete_macro(&name)
+}
+
+#[tauri::command]
+fn run_macro(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    name: String,
+) -> Result<MacroRunResult, GameError> {
+    macros::run_macro(&name, &mut state.lock())
+}
+
+// シードを省略すると乱数で決める。決めたシードを返す
+#[tauri::command]
+fn start_replay_recording(
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    seed: Option<u64>,
+) -> u64 {
+    let seed = seed.unwrap_or_else(rand::random);
+    game_state.lock().start_replay(seed);
+    seed
+}
+
+// 記録を止め、記録から再計算した戦場が今の戦場と一致するかを確かめて返す
+#[tauri::command]
+fn stop_replay_recording(
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+) -> Result<ReplayLog, GameError> {
+    let (replay, live) = {
+        let mut game = game_state.lock();
+        let replay = game.stop_replay().ok_or(GameError::ReplayNotRecording)?;
+        (replay, game.snapshot())
+    };
+    Ok(replay.log(replay.verify(&live)))
+}
+
+// 記録中（または最後に止めた）リプレイの最後の minutes 分を、戦場のタイムラプス GIF に書き出す
+#[tauri::command]
+async fn export_timelapse(
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    minutes: Option<u32>,
+) -> Result<TimelapseExport, GameError> {
+    let replay = game_state
+        .lock()
+        .recorded_replay()
+        .ok_or(GameError::ReplayNotRecording)?;
+    let minutes = minutes.unwrap_or(timelapse::DEFAULT_MINUTES);
+    tauri::async_runtime::spawn_blocking(move || timelapse::export(&replay, minutes))
+        .await
+        .map_err(GameError::internal)?
+        .map_err(GameError::storage)
+}
+
+#[tauri::command]
+fn pause_game(loop_control: tauri::State<'_, Arc<LoopControl>>) -> LoopStatus {
+    loop_control.pause();
+    loop_control.status()
+}
+
+#[tauri::command]
+fn resume_game(loop_control: tauri::State<'_, Arc<LoopControl>>) -> LoopStatus {
+    loop_control.resume();
+    loop_control.status()
+}
+
+// 控えめモードを切り替えて設定に保存する
+fn set_low_distraction_mode(
+    app: &tauri::AppHandle,
+    loop_control: &LoopControl,
+    enabled: bool,
+) -> Result<LoopStatus, GameError> {
+    let mut config = AppConfig::load();
+    config.low_distraction = enabled;
+    config.save()?;
+    loop_control.set_low_distraction(enabled);
+    let status = loop_control.status();
+    events::emit::<events::LowDistractionChanged>(app, &status);
+    Ok(status)
+}
+
+#[tauri::command]
+fn set_low_distraction(
+    app: tauri::AppHandle,
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+    enabled: bool,
+) -> Result<LoopStatus, GameError> {
+    set_low_distraction_mode(&app, &loop_control, enabled)
+}
+
+// 画面の言語に合わせて通知・エラー・トレイ・サーバーの応答の言語を切り替える
+#[tauri::command]
+fn set_locale(app: tauri::AppHandle, locale: String) -> Result<String, GameError> {
+    let language = i18n::set_locale(&locale);
+    let mut config = AppConfig::load();
+    config.locale = language.code().to_string();
+    config.save()?;
+    tray::relabel(&app);
+    events::emit::<events::LocaleChanged>(&app, language.code());
+    Ok(language.code().to_string())
+}
+
+#[tauri::command]
+fn get_locale() -> String {
+    i18n::language().code().to_string()
+}
+
+#[tauri::command]
+fn get_loop_status(loop_control: tauri::State<'_, Arc<LoopControl>>) -> LoopStatus {
+    loop_control.status()
+}
+
+// 更新頻度を変更して設定に保存する
+#[tauri::command]
+fn set_tick_rate(
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+    tick_rate: u32,
+) -> Result<LoopStatus, GameError> {
+    let mut config = AppConfig::load();
+    config.tick_rate = loop_control.set_tick_rate(tick_rate);
+    config.save()?;
+    Ok(loop_control.status())
+}
+
+#[tauri::command]
+fn get_config() -> AppConfig {
+    AppConfig::load()
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+fn save_config(
+    mut config: AppConfig,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    input_counter: tauri::State<'_, Arc<Mutex<InputCounter>>>,
+    foreground: tauri::State<'_, Arc<ForegroundWatcher>>,
+    scheduler: tauri::State<'_, Arc<Scheduler>>,
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+    announcer: tauri::State<'_, Arc<A11yAnnouncer>>,
+) -> Result<(), GameError> {
+    let hotkey = config.widget_hotkey()?;
+    let summon_hotkeys = config.energy.hotkeys()?;
+    let command_hotkeys = config.command_hotkeys()?;
+    let action_hotkeys = config.action_hotkeys.hotkeys()?;
+    game_state.lock().set_unit_caps(config.unit_caps());
+    loop_control.set_tick_rate(config.tick_rate);
+    loop_control.set_idle_timeout(config.idle_timeout_secs);
+    loop_control.set_low_distraction(config.low_distraction);
+    foreground.set_focus_blacklist(config.focus_blacklist.clone());
+    foreground.set_input_filter(
+        config.input_app_allowlist.clone(),
+        config.input_app_denylist.clone(),
+    );
+    foreground.set_auto_pause_presentation(config.auto_pause_presentation);
+    scheduler.set_config(config.schedule.clone());
+    announcer.configure(config.a11y.clone());
+    // サーバーURLを更新
+    mp_client.set_server_url(config.multiplayer_server_url.clone());
+    if config.multiplayer_anonymous != mp_client.is_anonymous() {
+        mp_client.set_anonymous(config.multiplayer_anonymous);
+    }
+    // トークンを持たない画面側の設定で保存済みのトークンを消さない
+    if config.multiplayer_token.is_empty() {
+        config.multiplayer_token = AppConfig::load().multiplayer_token;
+    }
+    // 言語は set_locale で変える（言語を持たない古い画面の設定で英語に戻さない）
+    config.locale = AppConfig::load().locale;
+    config.ime_calibration = crate::config::clamp_ime_calibration(config.ime_calibration);
+    {
+        let mut counter = input_counter.lock();
+        counter.set_ime_settings(config.ime_normalization, config.ime_calibration);
+        counter.set_key_weights(config.key_weights.clone());
+        counter.set_pointer_weights(config.pointer_weights.clone());
+        counter.set_hotkey(hotkey);
+        counter.set_energy_mode(config.energy.enabled, summon_hotkeys);
+        counter.set_command_hotkeys(command_hotkeys);
+        counter.set_action_hotkeys(action_hotkeys);
+    }
+    config.save()
+}
+
+fn monitor_info(monitor: &tauri::Monitor, primary: Option<&tauri::Monitor>) -> MonitorInfo {
+    let position = monitor.position();
+    let size = monitor.size();
+    MonitorInfo {
+        name: monitor.name().cloned(),
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor: monitor.scale_factor(),
+        primary: primary.is_some_and(|p| p.position() == position && p.size() == size),
+    }
+}
+
+fn monitor_infos(app: &tauri::AppHandle) -> Vec<MonitorInfo> {
+    let primary = app.primary_monitor().ok().flatten();
+    app.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| monitor_info(monitor, primary.as_ref()))
+        .collect()
+}
+
+// 設定で選んだモニター（見つからなければウィジェットのあるモニター、主モニターの順）に配置する
+fn place_widget(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), GameError> {
+    let widget_window = app
+        .get_webview_window("widget")
+        .ok_or(GameError::WidgetUnavailable)?;
+    let monitors = monitor_infos(app);
+    let monitor = widget::find_monitor(&monitors, config.widget_monitor.as_deref())
+        .cloned()
+        .or_else(|| {
+            let current = widget_window.current_monitor().ok().flatten()?;
+            Some(monitor_info(&current, None))
+        })
+        .or_else(|| monitors.iter().find(|m| m.primary).cloned())
+        .ok_or(GameError::WidgetUnavailable)?;
+
+    let placement = widget::placement(
+        &monitor,
+        config.widget_width_percent,
+        config.widget_alignment,
+        config.widget_y_offset,
+        config.widget_compact,
+    );
+    let _ = widget_window.set_size(Size::Physical(PhysicalSize::new(
+        placement.width,
+        placement.height,
+    )));
+    let _ = widget_window.set_position(Position::Physical(PhysicalPosition::new(
+        placement.x,
+        placement.y,
+    )));
+    Ok(())
+}
+
+#[tauri::command]
+fn list_monitors(app: tauri::AppHandle) -> Vec<MonitorInfo> {
+    monitor_infos(&app)
+}
+
+// 操作モードではクリックを受け付け、通常はクリックを背面へ透過する
+fn set_widget_mode(
+    app: &tauri::AppHandle,
+    mode: &WidgetMode,
+    interactive: bool,
+) -> Result<(), GameError> {
+    let widget_window = app
+        .get_webview_window("widget")
+        .ok_or(GameError::WidgetUnavailable)?;
+    widget_window
+        .set_ignore_cursor_events(!interactive)
+        .map_err(GameError::internal)?;
+    mode.set_interactive(interactive);
+    events::emit::<events::WidgetInteractive>(app, &interactive);
+    Ok(())
+}
+
+// ゲーム操作のホットキーを実行する（ウィジェットの表示はループのスケジュール判定で反映される）
+fn run_hotkey_action(
+    app: &tauri::AppHandle,
+    action: HotkeyAction,
+    game_state: &Mutex<GameState>,
+    loop_control: &LoopControl,
+    widget_mode: &WidgetMode,
+) -> HotkeyActionFeedback {
+    let result = match action {
+        HotkeyAction::TogglePause if loop_control.is_paused() => {
+            loop_control.resume();
+            Ok(i18n::text("hotkey.resumed"))
+        }
+        HotkeyAction::TogglePause => {
+            loop_control.pause();
+            Ok(i18n::text("hotkey.paused"))
+        }
+        HotkeyAction::ResetStage => {
+            game_state.lock().reset_current_stage();
+            Ok(i18n::text("hotkey.stage_reset"))
+        }
+        HotkeyAction::ToggleWidget => {
+            let hidden = !widget_mode.is_hidden();
+            widget_mode.set_hidden(hidden);
+            Ok(i18n::text(if hidden {
+                "hotkey.widget_hidden"
+            } else {
+                "hotkey.widget_shown"
+            }))
+        }
+        HotkeyAction::BuyCheapestUpgrade => {
+            let bought = game_state.lock().purchase_cheapest_upgrade();
+            bought.map(|(upgrade_type, unit_type, cost)| {
+                let name = format!("{} {}", upgrade_type, unit_type);
+                i18n::format("hotkey.bought", &[("name", &name.trim()), ("cost", &cost)])
+            })
+        }
+        HotkeyAction::ToggleLowDistraction => {
+            let enabled = !loop_control.is_low_distraction();
+            set_low_distraction_mode(app, loop_control, enabled).map(|_| {
+                i18n::text(if enabled {
+                    "hotkey.low_distraction_on"
+                } else {
+                    "hotkey.low_distraction_off"
+                })
+            })
+        }
+    };
+    tray::refresh(app);
+    HotkeyActionFeedback::new(action, result)
+}
+
+#[tauri::command]
+fn set_widget_interactive(
+    app: tauri::AppHandle,
+    widget_mode: tauri::State<'_, Arc<WidgetMode>>,
+    interactive: bool,
+) -> Result<(), GameError> {
+    set_widget_mode(&app, &widget_mode, interactive)
+}
+
+#[tauri::command]
+fn get_widget_interactive(widget_mode: tauri::State<'_, Arc<WidgetMode>>) -> bool {
+    widget_mode.is_interactive()
+}
+
+// 不透明度はウィンドウが透過なので画面側で全体に掛ける（OS ごとのウィンドウ不透明度の API に頼らない）
+#[tauri::command]
+fn set_widget_opacity(
+    app: tauri::AppHandle,
+    widget_mode: tauri::State<'_, Arc<WidgetMode>>,
+    opacity: f32,
+) -> Result<f32, GameError> {
+    let opacity = widget_mode.set_opacity(opacity);
+    let mut config = AppConfig::load();
+    config.widget_opacity = opacity;
+    config.save()?;
+    events::emit::<events::WidgetStyle>(&app, &widget_mode.style());
+    Ok(opacity)
+}
+
+// コンパクト表示ではウィンドウを細くし、ウィジェットへはユニットを含まない要約だけ送る
+#[tauri::command]
+fn set_widget_compact(
+    app: tauri::AppHandle,
+    widget_mode: tauri::State<'_, Arc<WidgetMode>>,
+    compact: bool,
+) -> Result<(), GameError> {
+    let mut config = AppConfig::load();
+    config.widget_compact = compact;
+    config.save()?;
+    widget_mode.set_compact(compact);
+    place_widget(&app, &config)?;
+    events::emit::<events::WidgetStyle>(&app, &widget_mode.style());
+    Ok(())
+}
+
+// ウィジェットの描画に要る分だけの状態（widget-update と同じ内容）
+#[tauri::command]
+fn get_widget_frame(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+) -> WidgetFrame {
+    WidgetFrame::new(&state.lock(), !loop_control.is_low_distraction())
+}
+
+#[tauri::command]
+fn get_widget_style(widget_mode: tauri::State<'_, Arc<WidgetMode>>) -> WidgetStyle {
+    widget_mode.style()
+}
+
+#[tauri::command]
+fn apply_widget_config(
+    app: tauri::AppHandle,
+    widget_mode: tauri::State<'_, Arc<WidgetMode>>,
+    config: AppConfig,
+) -> Result<(), GameError> {
+    widget_mode.set_opacity(config.widget_opacity);
+    widget_mode.set_compact(config.widget_compact);
+    widget_mode.set_fps(config.widget_fps);
+    place_widget(&app, &config)?;
+    events::emit::<events::WidgetStyle>(&app, &widget_mode.style());
+    Ok(())
+}
+
+// 保存済みの ID とトークンがあり同じ名前なら、新しく登録せずにログインする
+// （名前が使われていれば、サーバーが "名前#1234" で別のプレイヤーとして登録する）
+#[tauri::command]
+async fn mp_register_player(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    player_name: String,
+) -> Result<RegisterCommandResponse, GameError> {
+    let config = AppConfig::load();
+    let saved = !config.multiplayer_player_id.is_empty()
+        && !config.multiplayer_token.is_empty()
+        && config
+            .multiplayer_player_name
+            .trim()
+            .eq_ignore_ascii_case(player_name.trim());
+    let register_result = if saved {
+        mp_client.login(config.multiplayer_player_id).await
+    } else {
+        mp_client.register_player(player_name).await
+    }
+    .map_err(GameError::multiplayer)?;
+    Ok(signed_in(&game_state, register_result))
+}
+
+// 別の端末で作ったプレイヤーに、その ID とトークンで入る（省略すると保存済みのもの）
+#[tauri::command]
+async fn mp_login(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    player_id: Option<String>,
+    token: Option<String>,
+) -> Result<RegisterCommandResponse, GameError> {
+    let config = AppConfig::load();
+    let token = token.unwrap_or(config.multiplayer_token);
+    mp_client.set_token(Some(token.trim().to_string()));
+    let player_id = player_id.unwrap_or(config.multiplayer_player_id);
+    let register_result = mp_client
+        .login(player_id.trim().to_string())
+        .await
+        .map_err(GameError::multiplayer)?;
+    Ok(signed_in(&game_state, register_result))
+}
+
+fn signed_in(
+    game_state: &Mutex<GameState>,
+    register_result: RegisterResult,
+) -> RegisterCommandResponse {
+    {
+        let mut game = game_state.lock();
+        game.import_progress(&register_result.progress);
+    }
+
+    let mut config = AppConfig::load();
+    config.multiplayer_player_name = register_result.player_name.clone();
+    config.multiplayer_player_id = register_result.player_id.clone();
+    config.multiplayer_token = register_result.token.clone();
+    // 書けなかったときは保存先の異常として画面に出る（登録自体は済んでいる）
+    if let Err(err) = config.save() {
+        eprintln!("Failed to save multiplayer identity: {}", err);
+    }
+
+    RegisterCommandResponse {
+        player_id: register_result.player_id,
+        player_name: register_result.player_name,
+        message: register_result.message,
+        last_update: register_result.last_update,
+        stage: register_result.progress.stage,
+        coins: register_result.progress.coins,
+    }
+}
+
+// サーバーの進行状況を反映する。手元だけ進んでいれば残し（次の同期で送る）、
+// 両方で別々に進んでいれば上書きせずに確認を求める。反映したら true
+fn apply_remote_progress(
+    app: &tauri::AppHandle,
+    mp_client: &dyn MultiplayerApi,
+    game_state: &Mutex<GameState>,
+    profile: &PlayerProfile,
+) -> bool {
+    let mut game = game_state.lock();
+    let local = game.export_progress();
+    match conflict::compare(&local, &profile.progress) {
+        Divergence::Same | Divergence::LocalAhead => false,
+        Divergence::RemoteAhead => {
+            game.import_progress(&profile.progress);
+            true
+        }
+        Divergence::Diverged => {
+            let conflict = SyncConflict::new(
+                local,
+                profile.progress.clone(),
+                profile.last_update,
+                mp_client.server_now(),
+            );
+            events::emit::<events::MpSyncConflict>(app, &conflict);
+            mp_client.set_conflict(conflict);
+            false
+        }
+    }
+}
+
+// リモートの進行状況を反映できたら画面に知らせる
+fn receive_remote_progress(
+    app: &tauri::AppHandle,
+    mp_client: &dyn MultiplayerApi,
+    game_state: &Mutex<GameState>,
+    profile: PlayerProfile,
+) {
+    if apply_remote_progress(app, mp_client, game_state, &profile) {
+        events::emit::<events::MpRemoteUpdate>(app, &profile);
+    }
+}
+
+// 前回から与えたダメージを共有レイドボスへ送り、撃破済みレイドの報酬を受け取る
+async fn contribute_raid(
+    app: &tauri::AppHandle,
+    mp_client: &dyn MultiplayerApi,
+    game_state: &Mutex<GameState>,
+) -> Result<RaidBossStatus, GameError> {
+    let damage = game_state.lock().take_raid_damage();
+    let status = match mp_client.contribute_raid_damage(damage).await {
+        Ok(status) => status,
+        Err(e) => {
+            game_state.lock().return_raid_damage(damage);
+            return Err(GameError::multiplayer(e));
+        }
+    };
+    for raid_id in &status.pending_rewards {
+        let reward = mp_client
+            .claim_raid_reward(raid_id)
+            .await
+            .map_err(GameError::multiplayer)?;
+        game_state
+            .lock()
+            .claim_raid_reward(reward.coins, &reward.tier);
+    }
+    events::emit::<events::RaidUpdate>(app, &status);
+    Ok(status)
+}
+
+#[tauri::command]
+async fn mp_update_state(
+    app: tauri::AppHandle,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+) -> Result<(), GameError> {
+    sync_now(&app, mp_client.as_ref(), &game_state).await
+}
+
+// 進行状況をサーバーへ送る（画面からの定期同期とトレイの「Sync now」で共通）
+async fn sync_now(
+    app: &tauri::AppHandle,
+    mp_client: &dyn MultiplayerApi,
+    game_state: &Mutex<GameState>,
+) -> Result<(), GameError> {
+    // 食い違いを解決する前に送ると相手の端末の進行状況を上書きしてしまう
+    if mp_client.conflict().is_some() {
+        return Err(GameError::SyncConflictPending);
+    }
+    let progress = {
+        let game = game_state.lock();
+        // 改ざんされたセーブはローカルでのみ遊べる
+        if game.integrity.tampered {
+            return Err(GameError::SaveTampered);
+        }
+        game.export_progress()
+    };
+
+    // メンテナンス中は画面に知らせ、再開予定時刻まで同期を止める
+    let profile = match mp_client.sync_progress(&progress).await {
+        Ok(profile) => profile,
+        Err(err) => {
+            if matches!(err, MultiplayerError::Maintenance { .. }) {
+                events::emit::<events::MpMaintenance>(app, &mp_client.maintenance());
+            }
+            return Err(GameError::multiplayer(err));
+        }
+    };
+    if mp_client.clear_maintenance() {
+        events::emit::<events::MpMaintenance>(app, &None);
+    }
+    // シーズンが切り替わっていれば報酬を受け取り、周回をやり直した状態は次の同期で送る
+    let rollover = game_state
+        .lock()
+        .apply_season(profile.season, &profile.seasons);
+    if let Some(rollover) = rollover {
+        events::emit::<events::SeasonRollover>(app, &rollover);
+    }
+    // レイドへの送信に失敗してもダメージは次の同期に持ち越されるので同期自体は成功とする
+    let _ = contribute_raid(app, mp_client, game_state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn mp_get_players(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Result<Vec<serde_json::Value>, GameError> {
+    mp_client
+        .get_all_players()
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_get_leaderboard(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    sort: Option<String>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<LeaderboardPage, GameError> {
+    mp_client
+        .get_leaderboard(
+            sort.as_deref().unwrap_or("stage"),
+            page.unwrap_or(1),
+            page_size.unwrap_or(20),
+        )
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_get_raid_leaderboard(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    raid_id: String,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<RaidLeaderboardPage, GameError> {
+    mp_client
+        .get_raid_leaderboard(&raid_id, page.unwrap_or(1), page_size.unwrap_or(20))
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_get_season(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Result<SeasonInfo, GameError> {
+    mp_client.get_season().await.map_err(GameError::multiplayer)
+}
+
+// 品揃えの期限が切れていれば（force なら常に）サーバーから取り直す
+#[tauri::command]
+async fn mp_get_shop(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    force: Option<bool>,
+) -> Result<ShopStatus, GameError> {
+    if !force.unwrap_or(false) && game_state.lock().shop_is_fresh() {
+        return Ok(game_state.lock().shop_status());
+    }
+    let stock = mp_client.get_shop().await.map_err(GameError::multiplayer)?;
+    let mut game = game_state.lock();
+    game.store_shop_stock(stock);
+    Ok(game.shop_status())
+}
+
+#[tauri::command]
+async fn mp_get_raid_status(
+    app: tauri::AppHandle,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Result<RaidBossStatus, GameError> {
+    let status = mp_client
+        .get_raid_status()
+        .await
+        .map_err(GameError::multiplayer)?;
+    events::emit::<events::RaidUpdate>(&app, &status);
+    Ok(status)
+}
+
+#[tauri::command]
+async fn mp_contribute_raid_damage(
+    app: tauri::AppHandle,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+) -> Result<RaidBossStatus, GameError> {
+    contribute_raid(&app, mp_client.as_ref(), &game_state).await
+}
+
+#[tauri::command]
+async fn mp_claim_raid_reward(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    raid_id: String,
+) -> Result<RaidReward, GameError> {
+    let reward = mp_client
+        .claim_raid_reward(&raid_id)
+        .await
+        .map_err(GameError::multiplayer)?;
+    game_state
+        .lock()
+        .claim_raid_reward(reward.coins, &reward.tier);
+    Ok(reward)
+}
+
+// 自分の軍勢を送ってから相手の軍勢と戦わせる（結果はサーバーに送らず、その場で計算する）
+#[tauri::command]
+async fn mp_challenge_player(
+    app: tauri::AppHandle,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    opponent_id: String,
+) -> Result<BattleResult, GameError> {
+    let army = game_state.lock().army_snapshot();
+    let attacker = mp_client
+        .upload_army(&army)
+        .await
+        .map_err(GameError::multiplayer)?;
+    let defender = mp_client
+        .fetch_army(&opponent_id)
+        .await
+        .map_err(GameError::multiplayer)?;
+    let result = game::simulate_battle(&attacker, &defender);
+    events::emit::<events::PvpBattleResult>(&app, &result);
+    Ok(result)
+}
+
+#[tauri::command]
+async fn mp_create_guild(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    name: String,
+) -> Result<GuildInfo, GameError> {
+    mp_client
+        .create_guild(&name)
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_join_guild(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    code: String,
+) -> Result<GuildInfo, GameError> {
+    mp_client
+        .join_guild(&code)
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_leave_guild(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Result<serde_json::Value, GameError> {
+    mp_client
+        .leave_guild()
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+// guild_id を省くと自分のギルド（未所属なら None）
+#[tauri::command]
+async fn mp_get_guild(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    guild_id: Option<String>,
+) -> Result<Option<GuildInfo>, GameError> {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => match mp_client
+            .fetch_profile()
+            .await
+            .map_err(GameError::multiplayer)?
+            .guild_id
+        {
+            Some(guild_id) => guild_id,
+            None => return Ok(None),
+        },
+    };
+    mp_client
+        .get_guild(&guild_id)
+        .await
+        .map(Some)
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_get_guild_leaderboard(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    sort: Option<String>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<GuildLeaderboardPage, GameError> {
+    mp_client
+        .get_guild_leaderboard(
+            sort.as_deref().unwrap_or("stage"),
+            page.unwrap_or(1),
+            page_size.unwrap_or(20),
+        )
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_get_friends_progress(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+) -> Result<Vec<FriendProgress>, GameError> {
+    let friends = AppConfig::load().friends;
+    friends::fetch(mp_client.as_ref(), &game_state, &friends)
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+fn add_friend(player_id: String) -> Result<Vec<String>, GameError> {
+    let mut config = AppConfig::load();
+    let own_id = config.multiplayer_player_id.clone();
+    if friends::add(&mut config.friends, &player_id, &own_id)? {
+        config.save()?;
+    }
+    Ok(config.friends)
+}
+
+#[tauri::command]
+fn remove_friend(player_id: String) -> Result<Vec<String>, GameError> {
+    let mut config = AppConfig::load();
+    if friends::remove(&mut config.friends, &player_id) {
+        config.save()?;
+    }
+    Ok(config.friends)
+}
+
+#[tauri::command]
+async fn mp_pull_state(
+    app: tauri::AppHandle,
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+) -> Result<bool, GameError> {
+    let profile = mp_client
+        .fetch_profile()
+        .await
+        .map_err(GameError::multiplayer)?;
+    Ok(mp_client.mark_remote_update(profile.last_update)
+        && apply_remote_progress(&app, mp_client.as_ref(), &game_state, &profile))
+}
+
+#[tauri::command]
+fn mp_get_sync_conflict(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Option<SyncConflict> {
+    mp_client.conflict()
+}
+
+// keep_local / merge の結果はそのままサーバーへ送る
+#[tauri::command]
+async fn mp_resolve_conflict(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    resolution: Resolution,
+) -> Result<(), GameError> {
+    let progress = {
+        let mut game = game_state.lock();
+        if resolution != Resolution::KeepRemote && game.integrity.tampered {
+            return Err(GameError::SaveTampered);
+        }
+        let conflict = mp_client.take_conflict().ok_or(GameError::NoSyncConflict)?;
+        if resolution != Resolution::KeepLocal {
+            game.import_progress(conflict.resolve(resolution));
+        }
+        game.export_progress()
+    };
+    if resolution == Resolution::KeepRemote {
+        return Ok(());
+    }
+    mp_client
+        .sync_progress(&progress)
+        .await
+        .map(|_| ())
+        .map_err(GameError::multiplayer)
+}
+
+#[tauri::command]
+async fn mp_health_check(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    server_url: Option<String>,
+) -> Result<ServerHealth, GameError> {
+    mp_client
+        .health_check(server_url.as_deref())
+        .await
+        .map_err(GameError::multiplayer)
+}
+
+// サーバーとの時計のずれ（応答を受け取るたびに更新される）
+#[tauri::command]
+fn mp_get_clock(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) -> ClockStatus {
+    mp_client.clock_status()
+}
+
+// エンドポイントごとの送受信量・リクエスト数・直近のエラー（起動時かリセット時から）
+#[tauri::command]
+fn mp_get_diagnostics(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) -> Diagnostics {
+    mp_client.diagnostics()
+}
+
+#[tauri::command]
+fn mp_reset_diagnostics(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) {
+    mp_client.reset_diagnostics();
+}
+
+#[tauri::command]
+fn mp_get_maintenance(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+) -> Option<MaintenanceNotice> {
+    mp_client.maintenance()
+}
+
+#[tauri::command]
+fn mp_is_connected(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) -> bool {
+    mp_client.is_connected()
+}
+
+// 匿名モード（名前を登録せずランキングの閲覧だけ）を切り替える
+#[tauri::command]
+fn mp_set_anonymous(
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    enabled: bool,
+) -> Result<(), GameError> {
+    let mut config = AppConfig::load();
+    config.multiplayer_anonymous = enabled;
+    config.save()?;
+    mp_client.set_anonymous(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn mp_is_anonymous(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) -> bool {
+    mp_client.is_anonymous()
+}
+
+// WebSocket が切れている間はフロントエンド側のポーリングを続ける
+#[tauri::command]
+fn mp_is_push_connected(mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>) -> bool {
+    mp_client.is_push_connected()
+}
+
+#[tauri::command]
+// entries（優先度順）を渡せば並びを置き換え、従来の upgrade_type/unit_type なら 1 つだけにする。
+// どちらもなければ今の並びのまま始め直す
+fn start_auto_buy(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    upgrade_type: Option<String>,
+    unit_type: Option<String>,
+    entries: Option<Vec<AutoBuyEntry>>,
+    duration_seconds: f32,
+) -> Result<(), GameError> {
+    let mut game = state.lock();
+    let queue = match (entries, upgrade_type) {
+        (Some(entries), _) => entries,
+        (None, Some(upgrade_type)) => vec![AutoBuyEntry {
+            upgrade_type,
+            unit_type: unit_type.unwrap_or_default(),
+            max_level: None,
+        }],
+        (None, None) => game.auto_buy.queue.clone(),
+    };
+    AutoBuyConfig::validate(&queue)?;
+
+    // 自動購入のコスト: 5000コイン（転生ショップのパスがあれば無料）
+    let auto_buy_cost = if game
+        .prestige
+        .shop_items
+        .iter()
+        .any(|item| item == shop::AUTO_BUY_PASS)
+    {
+        0
+    } else {
+        5000
+    };
+    if game.coins < auto_buy_cost {
+        return Err(GameError::NotEnoughCoins {
+            needed: auto_buy_cost,
+            have: game.coins,
+        });
+    }
+
+    game.coins -= auto_buy_cost;
+    game.auto_buy = AutoBuyConfig {
+        enabled: true,
+        queue,
+        remaining_time: duration_seconds,
+    };
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_auto_buy(state: tauri::State<Arc<Mutex<GameState>>>) -> AutoBuyConfig {
+    let game = state.lock();
+    game.auto_buy.clone()
+}
+
+#[tauri::command]
+// index を渡せばその対象だけを外し、なければ自動購入を止める
+fn stop_auto_buy(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    index: Option<usize>,
+) -> Result<AutoBuyConfig, GameError> {
+    let mut game = state.lock();
+    match index {
+        Some(index) if index < game.auto_buy.queue.len() => {
+            game.auto_buy.queue.remove(index);
+        }
+        Some(index) => return Err(GameError::AutoBuyEntryNotFound { index }),
+        None => {
+            game.auto_buy.enabled = false;
+            game.auto_buy.remaining_time = 0.0;
+        }
+    }
+    if game.auto_buy.queue.is_empty() {
+        game.auto_buy.enabled = false;
+    }
+    Ok(game.auto_buy.clone())
 }
 
 #[derive(Clone, Serialize)]
-struct RegisterCommandResponse {
-    player_id: String,
-    player_name: String,
-    message: String,
-    last_update: i64,
-    stage: u32,
-    coins: u32,
+struct SessionsResponse {
+    current: Option<SessionSummary>,
+    history: Vec<SessionSummary>,
 }
 
 #[tauri::command]
-fn get_game_state(state: tauri::State<Arc<Mutex<GameState>>>) -> GameStateUpdate {
+fn get_sessions(state: tauri::State<Arc<Mutex<GameState>>>) -> SessionsResponse {
     let game = state.lock();
-    GameStateUpdate {
-        player_units: game.player_units.clone(),
-        enemy_units: game.enemy_units.clone(),
-        player_base_hp: game.player_base_hp,
-        enemy_base_hp: game.enemy_base_hp,
-        coins: game.coins,
-        stage: game.stage,
-        click_count: game.click_count,
-        type_count: game.type_count,
-        upgrades: game.upgrades.clone(),
+    SessionsResponse {
+        current: game.sessions.current.clone(),
+        history: game.sessions.history.clone(),
     }
 }
 
+#[derive(Clone, Serialize)]
+struct FocusStateResponse {
+    active: Option<FocusBlock>,
+    remaining_secs: i64,
+    remaining_display: String,
+    multiplier: f32,
+    history: Vec<FocusBlock>,
+}
+
 #[tauri::command]
-fn purchase_upgrade(
+fn start_focus(
     state: tauri::State<Arc<Mutex<GameState>>>,
-    upgrade_type: String,
-    unit_type: String,
-) -> Result<bool, String> {
+    duration_minutes: u32,
+) -> Result<(), GameError> {
     let mut game = state.lock();
-    game.purchase_upgrade(&upgrade_type, &unit_type)
+    game.start_focus(duration_minutes as i64 * 60)
 }
 
 #[tauri::command]
-fn reset_stage(state: tauri::State<Arc<Mutex<GameState>>>) {
+fn stop_focus(state: tauri::State<Arc<Mutex<GameState>>>) {
     let mut game = state.lock();
-    game.reset_current_stage();
+    game.stop_focus();
 }
 
 #[tauri::command]
-fn get_config() -> AppConfig {
-    AppConfig::load()
+fn get_focus_state(state: tauri::State<Arc<Mutex<GameState>>>) -> FocusStateResponse {
+    let game = state.lock();
+    let active = game.focus.active.clone();
+    let remaining_secs = active
+        .as_ref()
+        .map_or(0, |block| block.remaining_secs(game::unix_timestamp()));
+    FocusStateResponse {
+        remaining_secs,
+        remaining_display: numbers::format_duration(remaining_secs.max(0) as u64),
+        multiplier: active.as_ref().map_or(1.0, |block| block.multiplier()),
+        active,
+        history: game.focus.history.clone(),
+    }
 }
 
 #[tauri::command]
-fn save_config(
-    config: AppConfig,
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
-) -> Result<(), String> {
-    // サーバーURLを更新
-    mp_client.set_server_url(config.multiplayer_server_url.clone());
-    config.save()
+fn get_schedule_status(scheduler: tauri::State<'_, Arc<Scheduler>>) -> ScheduleStatus {
+    scheduler.status()
 }
 
 #[tauri::command]
-fn apply_widget_config(app: tauri::AppHandle, config: AppConfig) -> Result<(), String> {
-    if let Some(widget_window) = app.get_webview_window("widget") {
-        if let Ok(Some(monitor)) = widget_window.current_monitor() {
-            let monitor_pos = monitor.position();
-            let monitor_size = monitor.size();
-            let width = monitor_size.width;
-            let height = 80_u32;
-            let x = monitor_pos.x;
-            let mut y = monitor_pos.y + monitor_size.height as i32 - height as i32;
-            y -= config.widget_y_offset;
-
-            let _ = widget_window.set_size(Size::Physical(PhysicalSize::new(width, height)));
-            let _ = widget_window.set_position(Position::Physical(PhysicalPosition::new(x, y)));
+fn get_foreground_app(foreground: tauri::State<'_, Arc<ForegroundWatcher>>) -> ForegroundAppStatus {
+    foreground.app_status()
+}
 
-            Ok(())
-        } else {
-            Err("Failed to get monitor info".to_string())
-        }
-    } else {
-        Err("Widget window not found".to_string())
-    }
+#[tauri::command]
+fn get_input_backend(
+    input_backend: tauri::State<'_, Arc<InputBackendMonitor>>,
+) -> InputBackendStatus {
+    input_backend.status(AppConfig::load().input_backend)
 }
 
+// ゲームループ・入力フックが落ちたり止まったりした記録（古い順）
 #[tauri::command]
-async fn mp_register_player(
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
-    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
-    player_name: String,
-) -> Result<RegisterCommandResponse, String> {
-    let register_result = mp_client.register_player(player_name).await?;
+fn get_crash_log(watchdog: tauri::State<'_, Arc<Watchdog>>) -> Vec<Incident> {
+    watchdog.incidents()
+}
 
-    {
-        let mut game = game_state.lock();
-        game.import_progress(&register_result.progress);
-    }
+// セーブを書けなくなっていれば保存先と理由（書ける間は null）
+#[tauri::command]
+fn get_storage_status() -> Option<save_writer::StorageFailure> {
+    save_writer::status()
+}
 
+// 入力フックの実装を選んで保存する（動いているフックは外せないので次の起動から）
+#[tauri::command]
+fn set_input_backend(
+    input_backend: tauri::State<'_, Arc<InputBackendMonitor>>,
+    backend: InputBackendKind,
+) -> Result<InputBackendStatus, GameError> {
     let mut config = AppConfig::load();
-    config.multiplayer_player_name = register_result.player_name.clone();
-    config.multiplayer_player_id = register_result.player_id.clone();
-    let _ = config.save();
+    config.input_backend = backend;
+    config.save()?;
+    Ok(input_backend.status(backend))
+}
 
-    Ok(RegisterCommandResponse {
-        player_id: register_result.player_id,
-        player_name: register_result.player_name,
-        message: register_result.message,
-        last_update: register_result.last_update,
-        stage: register_result.progress.stage,
-        coins: register_result.progress.coins,
-    })
+#[tauri::command]
+fn set_presentation_override(foreground: tauri::State<'_, Arc<ForegroundWatcher>>, enabled: bool) {
+    foreground.set_presentation_override(enabled);
 }
 
 #[tauri::command]
-async fn mp_update_state(
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
+fn list_profiles() -> ProfileIndex {
+    profile::list_profiles()
+}
+
+// コマンドの引数は管理しているステートごとに増える
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+fn switch_profile(
     game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
-) -> Result<(), String> {
-    let progress = {
-        let game = game_state.lock();
-        game.export_progress()
+    mp_client: tauri::State<'_, Arc<dyn MultiplayerApi>>,
+    input_counter: tauri::State<'_, Arc<Mutex<InputCounter>>>,
+    foreground: tauri::State<'_, Arc<ForegroundWatcher>>,
+    scheduler: tauri::State<'_, Arc<Scheduler>>,
+    loop_control: tauri::State<'_, Arc<LoopControl>>,
+    announcer: tauri::State<'_, Arc<A11yAnnouncer>>,
+    stats: tauri::State<'_, Arc<Mutex<StatsHistory>>>,
+    history: tauri::State<'_, Arc<Mutex<ProgressHistory>>>,
+    name: String,
+) -> Result<AppConfig, GameError> {
+    let mut game = game_state.lock();
+    // 現在のプロファイルを保存してから切り替える
+    game.end_session();
+    save_input_stats(&input_counter);
+    save_stats_history(&stats, true);
+    profile::switch_profile(&name)?;
+    let config = load_config_with_encryption();
+    *game = GameState::new();
+    drop(game);
+    input_counter.lock().stats.replace(InputStats::load());
+    *stats.lock() = StatsHistory::load();
+    *history.lock() = ProgressHistory::load();
+
+    mp_client.reset_identity();
+    mp_client.set_token(Some(config.multiplayer_token.clone()));
+    mp_client.set_anonymous(config.multiplayer_anonymous);
+    save_config(
+        config.clone(),
+        game_state,
+        mp_client,
+        input_counter,
+        foreground,
+        scheduler,
+        loop_control,
+        announcer,
+    )?;
+    Ok(config)
+}
+
+// 暗号化方式を設定から初期化し、暗号化された識別情報を含めて設定を読み直す
+fn load_config_with_encryption() -> AppConfig {
+    encryption::init(AppConfig::load().save_encryption);
+    AppConfig::load()
+}
+
+#[derive(Clone, Serialize)]
+struct SaveEncryptionStatus {
+    mode: SaveEncryption,
+    unlocked: bool,
+}
+
+#[tauri::command]
+fn get_save_encryption() -> SaveEncryptionStatus {
+    SaveEncryptionStatus {
+        mode: encryption::mode(),
+        unlocked: encryption::is_unlocked(),
+    }
+}
+
+#[tauri::command]
+fn set_save_encryption(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    mode: SaveEncryption,
+    passphrase: Option<String>,
+) -> Result<(), GameError> {
+    if !encryption::is_unlocked() {
+        return Err(GameError::SaveLocked);
+    }
+
+    let mut config = AppConfig::load();
+    let key = match mode {
+        SaveEncryption::None => None,
+        SaveEncryption::Passphrase => {
+            let salt = encryption::generate_salt();
+            let key = encryption::derive_key(passphrase.as_deref().unwrap_or(""), &salt)
+                .map_err(GameError::storage)?;
+            config.save_encryption_salt = salt;
+            Some(key)
+        }
+        SaveEncryption::Keyring => Some(encryption::keyring_key(true).map_err(GameError::storage)?),
     };
 
-    let _ = mp_client.sync_progress(&progress).await?;
+    // 新しい方式でセーブと設定を書き直す（平文セーブの移行もここで行われる）。
+    // 途中で失敗したら元の方式に戻して書き直し、鍵の無い暗号文を残さない
+    let game = state.lock();
+    let slot_files = GameState::read_slot_files();
+    let (previous_mode, previous_key) = encryption::current();
+    encryption::configure(mode, key);
+    config.save_encryption = mode;
+    let migrated = GameState::rewrite_slot_files(&slot_files).and_then(|_| config.save());
+    if let Err(err) = migrated {
+        encryption::configure(previous_mode, previous_key);
+        if let Err(rollback) = GameState::rewrite_slot_files(&slot_files) {
+            eprintln!("Failed to restore save encryption: {}", rollback);
+        }
+        return Err(err);
+    }
+    game.flush_save();
     Ok(())
 }
 
 #[tauri::command]
-async fn mp_get_players(
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
-) -> Result<Vec<serde_json::Value>, String> {
-    mp_client.get_all_players().await
+fn unlock_save(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    passphrase: String,
+) -> Result<(), GameError> {
+    let config = AppConfig::load();
+    if config.save_encryption != SaveEncryption::Passphrase {
+        return Err(GameError::SaveNotPassphraseProtected);
+    }
+    let key = encryption::derive_key(&passphrase, &config.save_encryption_salt)
+        .map_err(GameError::storage)?;
+    GameState::verify_save_key(&key)?;
+    encryption::configure(SaveEncryption::Passphrase, Some(key));
+
+    // ロック中に作られた仮の状態を破棄して復号したセーブを読み込む
+    let mut game = state.lock();
+    let caps = game.unit_caps();
+    *game = GameState::new();
+    game.set_unit_caps(caps);
+    Ok(())
 }
 
 #[tauri::command]
-async fn mp_pull_state(
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
-    game_state: tauri::State<'_, Arc<Mutex<GameState>>>,
-) -> Result<bool, String> {
-    let profile = mp_client.fetch_profile().await?;
-    if mp_client.mark_remote_update(profile.last_update) {
-        let mut game = game_state.lock();
-        game.import_progress(&profile.progress);
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+fn export_save(state: tauri::State<Arc<Mutex<GameState>>>) -> Result<String, GameError> {
+    state.lock().export_save()
 }
 
 #[tauri::command]
-async fn mp_health_check(
-    mp_client: tauri::State<'_, Arc<MultiplayerClient>>,
-) -> Result<String, String> {
-    mp_client.health_check().await
+fn import_save(state: tauri::State<Arc<Mutex<GameState>>>, code: String) -> Result<(), GameError> {
+    state.lock().import_save(&code)
 }
 
+// 転生・引き継ぎ・サーバーからの反映の直前に自動で取った状態（新しい順）
 #[tauri::command]
-fn mp_is_connected(mp_client: tauri::State<'_, Arc<MultiplayerClient>>) -> bool {
-    mp_client.is_connected()
+fn list_checkpoints() -> Vec<CheckpointInfo> {
+    checkpoint::list()
 }
 
 #[tauri::command]
-fn start_auto_buy(
+fn restore_checkpoint(
     state: tauri::State<Arc<Mutex<GameState>>>,
-    upgrade_type: String,
-    unit_type: String,
-    duration_seconds: f32,
-) -> Result<(), String> {
-    let mut game = state.lock();
+    id: String,
+) -> Result<(), GameError> {
+    state.lock().restore_checkpoint(&id)
+}
 
-    // 自動購入のコスト: 5000コイン
-    let auto_buy_cost = 5000;
-    if game.coins < auto_buy_cost {
-        return Err("Not enough coins for auto-buy".to_string());
+// タイピング練習ツールの CSV から過去の打鍵数を取り込む（セーブごとに 1 回）
+#[tauri::command]
+fn import_typing_history(
+    state: tauri::State<Arc<Mutex<GameState>>>,
+    path: String,
+) -> Result<typing_import::TypingImport, GameError> {
+    if state.lock().typing_import.is_some() {
+        return Err(GameError::TypingHistoryAlreadyImported);
     }
+    // ファイルの読み込み中はゲームの状態をロックしない
+    let history = typing_import::read_file(std::path::Path::new(&path))?;
+    state.lock().import_typing_history(&history)
+}
 
-    game.coins -= auto_buy_cost;
-    game.auto_buy = AutoBuyConfig {
-        enabled: true,
-        upgrade_type,
-        unit_type,
-        remaining_time: duration_seconds,
-    };
+#[tauri::command]
+fn list_save_slots() -> Vec<SaveSlotInfo> {
+    GameState::list_slots()
+}
+
+#[tauri::command]
+fn save_to_slot(state: tauri::State<Arc<Mutex<GameState>>>, slot: u32) -> Result<(), GameError> {
+    state.lock().save_to_slot(slot)
+}
 
+#[tauri::command]
+fn load_from_slot(state: tauri::State<Arc<Mutex<GameState>>>, slot: u32) -> Result<(), GameError> {
+    let mut game = state.lock();
+    // 現在のスロットを保存してから切り替える
+    game.end_session();
+    let caps = game.unit_caps();
+    *game = GameState::load_from_slot(slot)?;
+    game.set_unit_caps(caps);
     Ok(())
 }
 
+#[derive(Clone, Serialize)]
+struct SaveIntegrityResponse {
+    score: f32,
+    signed: bool,
+    tampered: bool,
+    reasons: Vec<String>,
+}
+
+// トラブルシューティング画面から、データフォルダを消す前に確かめる
 #[tauri::command]
-fn get_auto_buy(state: tauri::State<Arc<Mutex<GameState>>>) -> AutoBuyConfig {
+fn verify_save_integrity(state: tauri::State<Arc<Mutex<GameState>>>) -> SaveCheckReport {
+    state.lock().verify_integrity()
+}
+
+#[tauri::command]
+fn get_stats_summary(stats: tauri::State<'_, Arc<Mutex<StatsHistory>>>) -> StatsSummary {
+    stats.lock().summary(game::unix_timestamp())
+}
+
+// 今日ここまでの振り返り（通知と同じ内容）
+#[tauri::command]
+fn get_daily_recap(stats: tauri::State<'_, Arc<Mutex<StatsHistory>>>) -> recap::DailyRecap {
+    recap::DailyRecap::build(&stats.lock(), chrono::Local::now())
+}
+
+#[tauri::command]
+fn get_stats_series(
+    stats: tauri::State<'_, Arc<Mutex<StatsHistory>>>,
+    range: StatsRange,
+) -> Vec<StatsSample> {
+    stats.lock().series(range, game::unix_timestamp())
+}
+
+// 1 時間ごとのコイン・ステージ・ユニット数（古い順）
+#[tauri::command]
+fn get_history(
+    history: tauri::State<'_, Arc<Mutex<ProgressHistory>>>,
+    range: HistoryRange,
+) -> Vec<ProgressSample> {
+    history.lock().series(range, game::unix_timestamp())
+}
+
+#[tauri::command]
+fn get_memory_usage(state: tauri::State<Arc<Mutex<GameState>>>) -> MemoryUsage {
+    state.lock().memory_usage()
+}
+
+#[tauri::command]
+fn get_save_integrity(state: tauri::State<Arc<Mutex<GameState>>>) -> SaveIntegrityResponse {
     let game = state.lock();
-    game.auto_buy.clone()
+    SaveIntegrityResponse {
+        score: game.integrity.score(),
+        signed: game.integrity.signed,
+        tampered: game.integrity.tampered,
+        reasons: game.integrity.reasons.clone(),
+    }
 }
 
 #[tauri::command]
-fn stop_auto_buy(state: tauri::State<Arc<Mutex<GameState>>>) -> Result<(), String> {
-    let mut game = state.lock();
-    game.auto_buy.enabled = false;
-    game.auto_buy.remaining_time = 0.0;
-    Ok(())
+fn preview_next_stage(state: tauri::State<Arc<Mutex<GameState>>>) -> StagePreview {
+    state.lock().preview_next_stage()
+}
+
+// 直近60秒の入力ペース（1秒あたり）
+fn recent_input_rate(input_counter: &Mutex<InputCounter>) -> f32 {
+    let heat = input_counter.lock().heat.snapshot();
+    heat.iter().sum::<u32>() as f32 / heat.len().max(1) as f32
+}
+
+#[tauri::command]
+async fn forecast_battle(
+    state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    input_counter: tauri::State<'_, Arc<Mutex<InputCounter>>>,
+    seconds: f32,
+) -> Result<ForecastResult, GameError> {
+    let input_rate = recent_input_rate(&input_counter);
+    let snapshot = state.lock().snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        forecast::forecast(&snapshot, seconds, forecast::DEFAULT_TRIALS, input_rate)
+    })
+    .await
+    .map_err(GameError::internal)
+}
+
+#[tauri::command]
+async fn recommend_upgrade(
+    state: tauri::State<'_, Arc<Mutex<GameState>>>,
+    input_counter: tauri::State<'_, Arc<Mutex<InputCounter>>>,
+) -> Result<Vec<UpgradeRecommendation>, GameError> {
+    let input_rate = recent_input_rate(&input_counter);
+    let snapshot = state.lock().snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        forecast::recommend_upgrades(&snapshot, input_rate)
+    })
+    .await
+    .map_err(GameError::internal)
+}
+
+// ファイル書き込みはフックのロックを離してから行う
+fn save_input_stats(input_counter: &Mutex<InputCounter>) {
+    let stats = input_counter.lock().stats.snapshot();
+    stats.save();
+}
+
+fn save_stats_history(stats: &Mutex<StatsHistory>, flush: bool) {
+    let history = {
+        let mut stats = stats.lock();
+        if flush {
+            stats.flush();
+        }
+        stats.clone()
+    };
+    history.save();
+}
+
+const SHUTDOWN_LOOP_TIMEOUT: Duration = Duration::from_secs(1);
+const SHUTDOWN_SYNC_TIMEOUT: Duration = Duration::from_secs(3);
+
+fn end_session(app: &tauri::AppHandle) {
+    if let Some(state) = app.try_state::<Arc<Mutex<GameState>>>() {
+        state.lock().end_session();
+    }
+    if let Some(input_counter) = app.try_state::<Arc<Mutex<InputCounter>>>() {
+        save_input_stats(&input_counter);
+    }
+    if let Some(stats) = app.try_state::<Arc<Mutex<StatsHistory>>>() {
+        save_stats_history(&stats, true);
+    }
+}
+
+// 終了前に最後の進行状況をサーバーへ送る（応答が無ければ待たずに終了）
+fn flush_multiplayer(app: &tauri::AppHandle) {
+    let (Some(state), Some(mp_client)) = (
+        app.try_state::<Arc<Mutex<GameState>>>(),
+        app.try_state::<Arc<dyn MultiplayerApi>>(),
+    ) else {
+        return;
+    };
+    if !mp_client.is_connected() {
+        return;
+    }
+    let progress = {
+        let game = state.lock();
+        if game.integrity.tampered {
+            return;
+        }
+        game.export_progress()
+    };
+    let _ = tauri::async_runtime::block_on(tokio::time::timeout(
+        SHUTDOWN_SYNC_TIMEOUT,
+        mp_client.sync_progress(&progress),
+    ));
+}
+
+// 落ちたサブシステムを知らせる。ゲームループを再開するなら、壊れたかもしれない状態を捨てて
+// 最後のセーブ（とジャーナル）から読み直す
+fn subsystem_down(app: &tauri::AppHandle, game_state: &Mutex<GameState>, incident: &Incident) {
+    if incident.subsystem == Subsystem::GameLoop && incident.restarted {
+        let mut game = game_state.lock();
+        let caps = game.unit_caps();
+        *game = GameState::new();
+        game.set_unit_caps(caps);
+    }
+    events::emit::<events::SubsystemDown>(app, incident);
+}
+
+// ゲームループ停止 → 最終セーブ → 最終同期 → 入力フック停止 → 終了
+fn shutdown(app: tauri::AppHandle) {
+    let Some(shutdown) = app.try_state::<Arc<Shutdown>>() else {
+        app.exit(0);
+        return;
+    };
+    if !shutdown.request() {
+        return;
+    }
+    let shutdown = Arc::clone(&shutdown);
+    // 通信を待つ間もウィンドウのイベント処理を止めない
+    std::thread::spawn(move || {
+        shutdown.wait_for_loop(SHUTDOWN_LOOP_TIMEOUT);
+        end_session(&app);
+        save_writer::flush();
+        flush_multiplayer(&app);
+        if let Some(input_counter) = app.try_state::<Arc<Mutex<InputCounter>>>() {
+            input_counter.lock().unhook();
+        }
+        app.exit(0);
+    });
 }
 
 #[tauri::command]
 fn exit_app(app: tauri::AppHandle) {
-    app.exit(0);
+    shutdown(app);
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    profile::init();
+    let config = load_config_with_encryption();
     let game_state = Arc::new(Mutex::new(GameState::new()));
+    game_state.lock().set_unit_caps(config.unit_caps());
     let input_counter = Arc::new(Mutex::new(InputCounter::new()));
-    let mp_client = Arc::new(MultiplayerClient::new());
+    let foreground = Arc::new(ForegroundWatcher::new());
+    let input_backend = Arc::new(InputBackendMonitor::default());
+    let watchdog = Arc::new(Watchdog::new());
+    let widget_mode = Arc::new(WidgetMode::new(
+        config.widget_interactive,
+        config.widget_opacity,
+        config.widget_compact,
+        config.widget_fps,
+    ));
+
+    // オフラインの開発モードではサーバーにつながず、手元のメモリで同期やランキングを試す
+    let mp_client: Arc<dyn MultiplayerApi> = if config.multiplayer_offline {
+        Arc::new(InMemoryMultiplayer::new())
+    } else {
+        MultiplayerClient::new()
+    };
+
+    i18n::set_locale(&config.locale);
 
     // 設定からサーバーURLをロード
-    let config = AppConfig::load();
+    {
+        let mut counter = input_counter.lock();
+        counter.set_ime_settings(config.ime_normalization, config.ime_calibration);
+        counter.set_key_weights(config.key_weights.clone());
+        counter.set_pointer_weights(config.pointer_weights.clone());
+        // 不正なホットキーは無効として起動する
+        counter.set_hotkey(config.widget_hotkey().ok().flatten());
+        counter.set_energy_mode(
+            config.energy.enabled,
+            config.energy.hotkeys().unwrap_or_default(),
+        );
+        counter.set_command_hotkeys(config.command_hotkeys().unwrap_or_default());
+        counter.set_action_hotkeys(config.action_hotkeys.hotkeys().unwrap_or_default());
+    }
+    foreground.set_focus_blacklist(config.focus_blacklist.clone());
+    foreground.set_input_filter(
+        config.input_app_allowlist.clone(),
+        config.input_app_denylist.clone(),
+    );
+    foreground.set_auto_pause_presentation(config.auto_pause_presentation);
+    let scheduler = Arc::new(Scheduler::new(config.schedule.clone()));
+    let loop_control = Arc::new(LoopControl::new(config.tick_rate, config.idle_timeout_secs));
+    loop_control.set_low_distraction(config.low_distraction);
+    let announcer = Arc::new(A11yAnnouncer::new(config.a11y.clone()));
+    mp_client.set_token(Some(config.multiplayer_token.clone()));
+    mp_client.set_anonymous(config.multiplayer_anonymous);
     if !config.multiplayer_server_url.is_empty() {
         mp_client.set_server_url(config.multiplayer_server_url);
     }
@@ -244,35 +2264,206 @@ pub fn run() {
     // ゲームループ用のステート
     let game_state_loop = Arc::clone(&game_state);
     let input_counter_clone = Arc::clone(&input_counter);
+    let foreground_loop = Arc::clone(&foreground);
+    let scheduler_loop = Arc::clone(&scheduler);
+    let loop_control_loop = Arc::clone(&loop_control);
+    let announcer_loop = Arc::clone(&announcer);
+    let widget_mode_loop = Arc::clone(&widget_mode);
+    let stats_history = Arc::new(Mutex::new(StatsHistory::load()));
+    let stats_loop = Arc::clone(&stats_history);
+    let stats_recap = Arc::clone(&stats_history);
+    let progress_history = Arc::new(Mutex::new(ProgressHistory::load()));
+    let history_loop = Arc::clone(&progress_history);
+    let scheduler_recap = Arc::clone(&scheduler);
+    let mp_client_push = Arc::clone(&mp_client);
+    let game_state_push = Arc::clone(&game_state);
+    let mp_client_sync = Arc::clone(&mp_client);
+    let game_state_sync = Arc::clone(&game_state);
+    let mp_client_friends = Arc::clone(&mp_client);
+    let game_state_friends = Arc::clone(&game_state);
+    let shutdown_signal = Arc::new(Shutdown::default());
+    let shutdown_loop = Arc::clone(&shutdown_signal);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .on_window_event(|window, event| {
-            if let WindowEvent::CloseRequested { .. } = event {
+            if let WindowEvent::CloseRequested { api, .. } = event {
                 if window.label() == "main" {
-                    window.app_handle().exit(0);
+                    api.prevent_close();
+                    // トレイに格納する設定なら隠すだけ（終了はトレイの「Quit」から）
+                    if AppConfig::load().minimize_to_tray
+                        && window.app_handle().try_state::<tray::Tray>().is_some()
+                    {
+                        let _ = window.hide();
+                    } else {
+                        shutdown(window.app_handle().clone());
+                    }
                 }
             }
         })
         .manage(game_state)
         .manage(input_counter)
         .manage(mp_client)
+        .manage(foreground)
+        .manage(Arc::clone(&input_backend))
+        .manage(Arc::clone(&watchdog))
+        .manage(scheduler)
+        .manage(loop_control)
+        .manage(announcer)
+        .manage(widget_mode)
+        .manage(stats_history)
+        .manage(progress_history)
+        .manage(Arc::new(MacroRecorder::default()))
+        .manage(Arc::new(RemotePlay::new()))
+        .manage(Arc::clone(&shutdown_signal))
         .invoke_handler(tauri::generate_handler![
             get_game_state,
+            pause_game,
+            resume_game,
+            get_loop_status,
+            set_low_distraction,
+            set_tick_rate,
+            format_number,
+            get_input_stats,
+            start_remote_play,
+            stop_remote_play,
+            get_remote_play_status,
             purchase_upgrade,
+            summon_unit,
+            buy_item,
+            use_item,
+            get_items,
+            get_invasion,
+            set_locale,
+            get_locale,
             reset_stage,
+            advance_stage,
+            set_manual_advance,
+            get_chests,
+            open_chest,
+            get_drop_rates,
+            build_barracks,
+            set_rally_point,
+            clear_rally_point,
+            release_rally,
+            select_units,
+            order_units,
+            cancel_orders,
+            set_stance,
+            get_difficulty,
+            set_difficulty,
+            set_coop_mode,
+            get_coop_status,
+            execute_command,
+            get_command_help,
+            get_state_summary_text,
+            summon_with_energy,
+            get_energy_status,
+            get_achievements,
+            get_titles,
+            get_quests,
+            claim_quest_reward,
+            set_active_title,
+            get_cosmetics,
+            set_cosmetic,
+            get_battle_reports,
+            get_prestige_state,
+            do_prestige,
+            buy_prestige_upgrade,
+            get_shop,
+            buy_shop_offer,
+            get_respec_quote,
+            respec,
+            start_macro_recording,
+            stop_macro_recording,
+            cancel_macro_recording,
+            get_macro_recording,
+            list_macros,
+            delete_macro,
+            run_macro,
+            start_replay_recording,
+            stop_replay_recording,
+            export_timelapse,
             get_config,
             save_config,
             apply_widget_config,
+            list_monitors,
+            set_widget_interactive,
+            get_widget_interactive,
+            set_widget_opacity,
+            set_widget_compact,
+            get_widget_frame,
+            get_widget_style,
             mp_register_player,
+            mp_login,
             mp_update_state,
             mp_get_players,
+            mp_get_leaderboard,
+            mp_get_raid_leaderboard,
+            mp_claim_raid_reward,
+            mp_challenge_player,
+            mp_get_season,
+            mp_get_shop,
+            mp_get_raid_status,
+            mp_contribute_raid_damage,
+            mp_create_guild,
+            mp_join_guild,
+            mp_leave_guild,
+            mp_get_guild,
+            mp_get_guild_leaderboard,
+            mp_get_friends_progress,
+            add_friend,
+            remove_friend,
             mp_pull_state,
+            mp_get_sync_conflict,
+            mp_resolve_conflict,
             mp_health_check,
+            mp_get_maintenance,
+            mp_get_clock,
+            mp_get_diagnostics,
+            mp_reset_diagnostics,
             mp_is_connected,
+            mp_is_push_connected,
+            mp_set_anonymous,
+            mp_is_anonymous,
             start_auto_buy,
             get_auto_buy,
             stop_auto_buy,
+            get_sessions,
+            start_focus,
+            stop_focus,
+            get_focus_state,
+            get_schedule_status,
+            set_presentation_override,
+            get_foreground_app,
+            get_input_backend,
+            set_input_backend,
+            get_crash_log,
+            get_storage_status,
+            get_memory_usage,
+            get_stats_summary,
+            get_daily_recap,
+            get_stats_series,
+            get_history,
+            list_profiles,
+            switch_profile,
+            get_save_encryption,
+            set_save_encryption,
+            unlock_save,
+            get_save_integrity,
+            verify_save_integrity,
+            list_save_slots,
+            export_save,
+            import_save,
+            list_checkpoints,
+            restore_checkpoint,
+            import_typing_history,
+            save_to_slot,
+            load_from_slot,
+            preview_next_stage,
+            forecast_battle,
+            recommend_upgrade,
             exit_app
         ])
         .setup(move |app| {
@@ -282,106 +2473,493 @@ pub fn run() {
             if let Some(widget_window) = app_handle.get_webview_window("widget") {
                 let _ = widget_window.set_always_on_top(true);
                 let _ = widget_window.set_skip_taskbar(true);
-                let _ = widget_window.set_ignore_cursor_events(true);
+                let _ = widget_window.set_ignore_cursor_events(!config.widget_interactive);
                 let _ = widget_window.set_decorations(false);
+            }
+            if let Err(err) = place_widget(&app_handle, &config) {
+                println!("[widget] {}", err);
+            }
+            if let Err(err) = tray::build(&app_handle) {
+                println!("[tray] {}", err);
+            }
 
-                if let Ok(Some(monitor)) = widget_window.current_monitor() {
-                    let monitor_pos = monitor.position();
-                    let monitor_size = monitor.size();
-                    let width = monitor_size.width;
-                    let height = 80_u32;
-                    let x = monitor_pos.x;
-                    // Try to place the widget above the bottom edge; subtract a bit to avoid overlapping a taskbar.
-                    let mut y = monitor_pos.y + monitor_size.height as i32 - height as i32;
-                    // Position using config offset
-                    let fallback_offset = config.widget_y_offset;
-                    y -= fallback_offset;
-
-                    println!(
-                        "[widget] monitor pos={:?} size={:?} x={} y={} width={} height={}",
-                        monitor_pos, monitor_size, x, y, width, height
-                    );
-
-                    let _ =
-                        widget_window.set_size(Size::Physical(PhysicalSize::new(width, height)));
-                    let _ =
-                        widget_window.set_position(Position::Physical(PhysicalPosition::new(x, y)));
-                } else {
-                    println!("[widget] current_monitor not found; fallback to primary_monitor");
-                    if let Ok(Some(monitor)) = app_handle.primary_monitor() {
-                        let monitor_pos = monitor.position();
-                        let monitor_size = monitor.size();
-                        let width = monitor_size.width;
-                        let height = 80_u32;
-                        let x = monitor_pos.x;
-                        let mut y = monitor_pos.y + monitor_size.height as i32 - height as i32;
-                        y -= config.widget_y_offset;
-                        let _ = widget_window
-                            .set_size(Size::Physical(PhysicalSize::new(width, height)));
-                        let _ = widget_window
-                            .set_position(Position::Physical(PhysicalPosition::new(x, y)));
+            // グローバル入力フックの開始（使えない実装は飛ばし、どれも使えなければ入力を数えないだけ）。
+            // 動いていたフックがすべて止まったら候補を選び直す
+            let (input_sender, input_events) = mpsc::channel();
+            let app_handle_input = app_handle.clone();
+            let input_backend_hook = Arc::clone(&input_backend);
+            let requested_backend = config.input_backend;
+            let watchdog_hook = Arc::clone(&watchdog);
+            let shutdown_hook = Arc::clone(&shutdown_signal);
+            std::thread::spawn(move || {
+                let run_hook = || {
+                    let ran =
+                        input_backend_hook.run(requested_backend, input_sender.clone(), |status| {
+                            events::emit::<events::InputBackendChanged>(&app_handle_input, status);
+                        });
+                    if ran && !shutdown_hook.is_requested() {
+                        Err(i18n::text("watchdog.input_stopped"))
+                    } else {
+                        Ok(())
                     }
-                }
-            }
+                };
+                watchdog_hook.supervise(Subsystem::InputHook, run_hook, |incident| {
+                    events::emit::<events::SubsystemDown>(&app_handle_input, incident);
+                });
+            });
+
+            // ゲームループの生存通知の見張り（止まっても強制終了はできないので知らせて記録するだけ）
+            let watchdog_stall = Arc::clone(&watchdog);
+            let shutdown_stall = Arc::clone(&shutdown_signal);
+            let app_handle_stall = app_handle.clone();
+            std::thread::spawn(move || {
+                watchdog_stall.watch(
+                    || !shutdown_stall.is_requested(),
+                    |incident| {
+                        events::emit::<events::SubsystemDown>(&app_handle_stall, incident);
+                    },
+                );
+            });
+
+            // マルチプレイのプッシュ同期（リモートの進行状況を反映）
+            let mp_push = Arc::clone(&mp_client_push);
+            let mp_client_conflict = Arc::clone(&mp_client_push);
+            let game_state_push = Arc::clone(&game_state_push);
+            let app_handle_push = app_handle.clone();
+            tauri::async_runtime::spawn(mp_push.run_push_sync(Box::new(move |profile| {
+                receive_remote_progress(
+                    &app_handle_push,
+                    mp_client_conflict.as_ref(),
+                    &game_state_push,
+                    profile,
+                );
+            })));
+
+            // 一定間隔の自動同期（先にリモートの変更を取り込んでから送る）
+            let app_handle_sync = app_handle.clone();
+            let app_handle_sync_status = app_handle.clone();
+            let mp_client_sync_loop = Arc::clone(&mp_client_sync);
+            tauri::async_runtime::spawn(auto_sync::run(
+                mp_client_sync_loop,
+                move || {
+                    let app = app_handle_sync.clone();
+                    let mp_client = Arc::clone(&mp_client_sync);
+                    let game_state = Arc::clone(&game_state_sync);
+                    async move {
+                        mp_client
+                            .poll_once(&|profile| {
+                                receive_remote_progress(
+                                    &app,
+                                    mp_client.as_ref(),
+                                    &game_state,
+                                    profile,
+                                )
+                            })
+                            .await
+                            .map_err(GameError::multiplayer)?;
+                        sync_now(&app, mp_client.as_ref(), &game_state).await
+                    }
+                },
+                move |status| {
+                    events::emit::<events::MpSyncStatus>(&app_handle_sync_status, status);
+                    if let Some(notification) = Notification::sync_failed(status) {
+                        notifications::dispatch(&app_handle_sync_status, notification);
+                    }
+                },
+            ));
+
+            // フレンドの進行状況の定期取得
+            let app_handle_friends = app_handle.clone();
+            tauri::async_runtime::spawn(friends::run_refresh(
+                mp_client_friends,
+                game_state_friends,
+                move |progress| {
+                    events::emit::<events::FriendsUpdate>(&app_handle_friends, &progress);
+                },
+            ));
+
+            // 前面アプリの監視
+            let foreground_watcher = Arc::clone(&foreground_loop);
+            std::thread::spawn(move || {
+                foreground::start_foreground_watcher(foreground_watcher);
+            });
 
-            // グローバル入力フックの開始
-            let input_counter_hook = Arc::clone(&input_counter_clone);
+            // 1 日の振り返り通知
+            let app_handle_recap = app_handle.clone();
             std::thread::spawn(move || {
-                input_hook::start_input_hook(input_counter_hook);
+                recap::start_recap_watcher(app_handle_recap, scheduler_recap, stats_recap);
             });
 
-            // ゲームループ
+            // ゲームループ（panic したら最後のセーブから再開する）
+            let watchdog_loop = Arc::clone(&watchdog);
+            let game_state_restore = Arc::clone(&game_state_loop);
+            let app_handle_down = app_handle.clone();
             std::thread::spawn(move || {
-                let mut last_update = Instant::now();
-                let mut last_time_unit_spawn = Instant::now();
+                let game_loop = || {
+                    let mut last_update = Instant::now();
+                    let mut last_time_unit_spawn = Instant::now();
+                    let mut last_widget_frame: Option<Instant> = None;
+                    let mut last_state_sent: Option<Instant> = None;
+                    let mut last_status_check: Option<Instant> = None;
+                    let mut last_stats_save = Instant::now();
+                    let mut input_allowed = true;
+                    let mut widget_shown = true;
+                    let mut presenting = false;
+                    let mut last_heartbeat: Option<Instant> = None;
+                    let mut idle_tracker = IdleTracker::default();
+                    let mut unit_tracker = UnitDiffTracker::default();
+                    let mut cooldown_tracker = CooldownTracker::default();
+                    let mut taskbar = Taskbar::default();
 
-                loop {
-                    std::thread::sleep(Duration::from_millis(16)); // 約60 FPS
+                    loop {
+                        watchdog_loop.beat();
+                        if loop_control_loop.is_idle() {
+                            // 放置中は約1秒ごとに更新し、入力があれば即座に起きる
+                            if let Ok(event) = input_events.recv_timeout(idle::IDLE_TICK) {
+                                let mut counter = input_counter_clone.lock();
+                                if foreground_loop.accepts_input() {
+                                    counter.process(event);
+                                } else {
+                                    counter.ignore(event);
+                                }
+                            }
+                        } else {
+                            std::thread::sleep(loop_control_loop.tick_interval());
+                        }
+                        if shutdown_loop.is_requested() {
+                            break;
+                        }
 
-                    let delta = last_update.elapsed().as_secs_f32();
-                    last_update = Instant::now();
+                        // ホットキーでクリック透過を切り替える（一時停止中も有効）
+                        if input_counter_clone.lock().take_hotkey() {
+                            let interactive = !widget_mode_loop.is_interactive();
+                            if let Err(err) =
+                                set_widget_mode(&app_handle, &widget_mode_loop, interactive)
+                            {
+                                println!("[widget] {}", err);
+                            }
+                        }
 
-                    // 入力カウントの取得とユニット生成
-                    let (clicks, types) = {
-                        let mut counter = input_counter_clone.lock();
-                        counter.consume_inputs()
-                    };
+                        // ゲーム操作のホットキー（一時停止中も効くよう、停止の判定より先に処理する）
+                        let actions = input_counter_clone.lock().take_action_requests();
+                        for action in actions {
+                            let feedback = run_hotkey_action(
+                                &app_handle,
+                                action,
+                                &game_state_loop,
+                                &loop_control_loop,
+                                &widget_mode_loop,
+                            );
+                            events::emit::<events::HotkeyAction>(&app_handle, &feedback);
+                        }
 
-                    let mut game = game_state_loop.lock();
+                        // 一時停止中は更新も入力の反映もせず、生存通知だけ送る
+                        // （放置判定より優先し、再開後に改めて判定する）
+                        if loop_control_loop.is_paused() {
+                            loop_control_loop.set_idle(false);
+                            input_counter_clone.lock().discard(&input_events);
+                            last_update = Instant::now();
+                            if last_heartbeat
+                                .is_none_or(|t| t.elapsed() >= loop_control::HEARTBEAT_INTERVAL)
+                            {
+                                last_heartbeat = Some(Instant::now());
+                                events::emit::<events::GameHeartbeat>(
+                                    &app_handle,
+                                    &loop_control_loop.status(),
+                                );
+                                tray::refresh(&app_handle);
+                            }
+                            continue;
+                        }
+                        last_heartbeat = None;
 
-                    // ユニット生成
-                    for _ in 0..types {
-                        game.spawn_unit(UnitType::Small);
-                    }
-                    for _ in 0..clicks {
-                        game.spawn_unit(UnitType::Medium);
-                    }
+                        // スリープ復帰直後の巨大な経過時間で戦闘を一気に進めない
+                        let (delta, away) = offline::split_elapsed(
+                            last_update.elapsed().as_secs_f32(),
+                            loop_control_loop.tick_interval().as_secs_f32(),
+                        );
+                        last_update = Instant::now();
+                        if away > 0.0 {
+                            idle_tracker.reset();
+                            unit_tracker.force_snapshot();
+                            if let Some(progress) =
+                                game_state_loop.lock().apply_offline_progress(away)
+                            {
+                                events::emit::<events::OfflineProgress>(&app_handle, &progress);
+                            }
+                        }
 
-                    // 1分ごとの強力ユニット生成
-                    if last_time_unit_spawn.elapsed().as_secs() >= 60 {
-                        game.spawn_unit(UnitType::Large);
-                        last_time_unit_spawn = Instant::now();
-                    }
+                        // 入力カウントの取得とユニット生成
+                        let (
+                            clicks,
+                            types,
+                            abilities,
+                            summons,
+                            commands,
+                            pointer_units,
+                            energy_mode,
+                            input_heat,
+                            idle_for,
+                        ) = {
+                            let mut counter = input_counter_clone.lock();
+                            // 除外したアプリへの入力は数えない
+                            if foreground_loop.accepts_input() {
+                                counter.drain(&input_events);
+                            } else {
+                                counter.discard(&input_events);
+                            }
+                            let (clicks, types) = counter.consume_inputs();
+                            let abilities = counter.consume_abilities();
+                            (
+                                clicks,
+                                types,
+                                abilities,
+                                counter.take_summon_requests(),
+                                counter.take_command_requests(),
+                                counter.consume_pointer_units(),
+                                counter.energy_mode(),
+                                counter.heat.snapshot(),
+                                counter.idle_for(),
+                            )
+                        };
 
-                    // ゲーム更新
-                    game.update(delta);
-
-                    // フロントエンドに状態を送信
-                    let _ = app_handle.emit(
-                        "game-update",
-                        GameStateUpdate {
-                            player_units: game.player_units.clone(),
-                            enemy_units: game.enemy_units.clone(),
-                            player_base_hp: game.player_base_hp,
-                            enemy_base_hp: game.enemy_base_hp,
-                            coins: game.coins,
-                            stage: game.stage,
-                            click_count: game.click_count,
-                            type_count: game.type_count,
-                            upgrades: game.upgrades.clone(),
-                        },
-                    );
-                }
+                        // 一定時間入力がなければ省電力モード（次の入力で通常に戻る）
+                        let idle = loop_control_loop
+                            .idle_timeout()
+                            .is_some_and(|timeout| idle_for >= timeout);
+                        if loop_control_loop.set_idle(idle) {
+                            idle_tracker.reset();
+                            unit_tracker.force_snapshot();
+                            events::emit::<events::IdleChanged>(
+                                &app_handle,
+                                &loop_control_loop.status(),
+                            );
+                        }
+
+                        // 入力統計の定期保存
+                        if last_stats_save.elapsed().as_secs() >= 60 {
+                            save_input_stats(&input_counter_clone);
+                            last_stats_save = Instant::now();
+                        }
+
+                        // 勤務時間スケジュールとプレゼン中の判定（1秒ごと）
+                        if last_status_check.is_none_or(|t| t.elapsed().as_secs() >= 1) {
+                            last_status_check = Some(Instant::now());
+                            let schedule_status = scheduler_loop.status();
+                            let monitors: Vec<MonitorRect> = app_handle
+                                .available_monitors()
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|monitor| {
+                                    let pos = monitor.position();
+                                    let size = monitor.size();
+                                    (
+                                        pos.x as f64,
+                                        pos.y as f64,
+                                        size.width as f64,
+                                        size.height as f64,
+                                    )
+                                })
+                                .collect();
+                            let presenting_now = foreground_loop.is_presenting(&monitors);
+                            if presenting_now != presenting {
+                                presenting = presenting_now;
+                                events::emit::<events::PresentationMode>(&app_handle, &presenting);
+                            }
+
+                            let widget_visible = schedule_status.widget_visible
+                                && !presenting
+                                && !widget_mode_loop.is_hidden();
+                            if widget_visible != widget_shown {
+                                if let Some(widget_window) = app_handle.get_webview_window("widget")
+                                {
+                                    let _ = if widget_visible {
+                                        widget_window.show()
+                                    } else {
+                                        widget_window.hide()
+                                    };
+                                }
+                                widget_shown = widget_visible;
+                            }
+                            input_allowed = schedule_status.input_allowed && !presenting;
+
+                            // セーブを書けなくなった・書けるようになったら知らせる
+                            save_writer::retry_if_due();
+                            if save_writer::take_changed() {
+                                events::emit::<events::StorageError>(
+                                    &app_handle,
+                                    &save_writer::status(),
+                                );
+                            }
+                            tray::refresh(&app_handle);
+                            // ボスの残り体力・基地の危険・未開封の宝箱をタスクバーと Dock に出す
+                            let indicator = Indicator::from_game(&game_state_loop.lock());
+                            taskbar.refresh(&app_handle, indicator);
+                        }
+                        // 時間外・プレゼン中の入力は破棄する
+                        let (clicks, types, abilities, summons, commands, pointer_units) =
+                            if input_allowed {
+                                (clicks, types, abilities, summons, commands, pointer_units)
+                            } else {
+                                (0, 0, Vec::new(), Vec::new(), Vec::new(), 0)
+                            };
+
+                        let mut game = game_state_loop.lock();
+
+                        // ホットキーに結び付けたコマンド（結果はパレットと同じ形で知らせる）
+                        for command in commands {
+                            match palette::run(&command, &mut game) {
+                                Ok(outcome) => {
+                                    events::emit::<events::CommandExecuted>(&app_handle, &outcome);
+                                }
+                                Err(error) => {
+                                    events::emit::<events::CommandFailed>(&app_handle, &error);
+                                }
+                            }
+                        }
+
+                        // 1分ごとの強力ユニット生成
+                        let reinforcement = last_time_unit_spawn.elapsed().as_secs() >= 60;
+                        if reinforcement {
+                            last_time_unit_spawn = Instant::now();
+                        }
+
+                        // 入力の反映とゲーム更新（集中ブロック中は入力を補正する）
+                        let focus_completed = game.run_tick(
+                            TickInput {
+                                clicks,
+                                types,
+                                pointer_units,
+                                abilities,
+                                summons,
+                                energy_mode,
+                                idle,
+                                distracted: clicks > 0 && foreground_loop.is_distracting(),
+                                reinforcement,
+                            },
+                            delta,
+                        );
+                        if focus_completed {
+                            if let Some(block) = game.focus.history.last() {
+                                events::emit::<events::FocusCompleted>(&app_handle, block);
+                            }
+                        }
+                        // 統計の時系列に積み、分が変わったら保存する
+                        let minute_closed = stats_loop
+                            .lock()
+                            .record(game::unix_timestamp(), game.take_activity());
+                        if minute_closed {
+                            save_stats_history(&stats_loop, false);
+                        }
+                        // 時間が変わったら進行状況を積む
+                        {
+                            let mut history = history_loop.lock();
+                            if history.record(game::unix_timestamp(), &game) {
+                                history.save();
+                            }
+                        }
+                        for achievement in game.achievements.take_pending() {
+                            game.notifications
+                                .push(Notification::achievement(&achievement));
+                            events::emit::<events::AchievementUnlocked>(&app_handle, &achievement);
+                        }
+                        for notification in game.notifications.take_pending() {
+                            notifications::dispatch(&app_handle, notification);
+                        }
+                        for quest in game.quests.take_pending() {
+                            events::emit::<events::QuestCompleted>(&app_handle, &quest);
+                        }
+                        for report in game.battle.take_pending() {
+                            events::emit::<events::StageReport>(&app_handle, &report);
+                        }
+                        // 放置中も送る（予告で画面に戻ってもらう）
+                        for event in game.invasion.take_pending() {
+                            events::emit::<events::Invasion>(&app_handle, &event);
+                        }
+                        // 控えめモードでは演出のきっかけになるイベントを捨てる
+                        let calm = loop_control_loop.is_low_distraction();
+                        for milestone in game.combo.take_milestones() {
+                            if !calm {
+                                events::emit::<events::ComboMilestone>(&app_handle, &milestone);
+                            }
+                        }
+                        for blow in game.clear_sequence.take_final_blows() {
+                            if !calm {
+                                events::emit::<events::FinalBlow>(&app_handle, &blow);
+                            }
+                        }
+                        for event in game.clear_sequence.take_pending() {
+                            if !calm {
+                                events::emit::<events::StageClearSequence>(&app_handle, &event);
+                            }
+                        }
+                        if let Some(summary) = announcer_loop.poll(&game) {
+                            events::emit::<events::A11ySummary>(&app_handle, &summary);
+                        }
+
+                        // フロントエンドに状態を送信（放置中は変化した項目の要約だけ、控えめモードでは 1 秒ごと）
+                        let state_due = !calm
+                            || last_state_sent.is_none_or(|t| {
+                                t.elapsed() >= loop_control::LOW_DISTRACTION_INTERVAL
+                            });
+                        if idle {
+                            if let Some(update) = idle_tracker.diff(&game) {
+                                events::emit::<events::GameIdleUpdate>(&app_handle, &update);
+                            }
+                        } else if state_due {
+                            last_state_sent = Some(Instant::now());
+                            let diff = unit_tracker
+                                .diff(game.player_units.iter().chain(game.enemy_units.iter()));
+                            let snapshot = unit_tracker.snapshot_due();
+                            if snapshot {
+                                cooldown_tracker.reset();
+                            }
+                            let cooldowns = cooldown_tracker.diff(game.player_units.iter());
+                            if !cooldowns.is_empty() {
+                                events::emit::<events::AbilityCooldowns>(&app_handle, &cooldowns);
+                            }
+                            if !snapshot {
+                                if !diff.spawned.is_empty() {
+                                    events::emit::<events::UnitSpawned>(&app_handle, &diff.spawned);
+                                }
+                                if !diff.removed.is_empty() {
+                                    events::emit::<events::UnitRemoved>(&app_handle, &diff.removed);
+                                }
+                                if !diff.moved.is_empty() {
+                                    events::emit::<events::UnitMoved>(&app_handle, &diff.moved);
+                                }
+                            }
+                            // 完全な状態はメインウィンドウにだけ送り、ウィジェットには軽い描画データを間引いて送る
+                            events::emit_to::<events::GameUpdate>(
+                                &app_handle,
+                                "main",
+                                &GameStateUpdate::new(&game, input_heat, snapshot, !calm),
+                            );
+                            let frame_due = last_widget_frame
+                                .is_none_or(|t| t.elapsed() >= widget_mode_loop.frame_interval());
+                            if frame_due && widget_shown && !widget_mode_loop.is_compact() {
+                                last_widget_frame = Some(Instant::now());
+                                events::emit_to::<events::WidgetUpdate>(
+                                    &app_handle,
+                                    "widget",
+                                    &WidgetFrame::new(&game, !calm),
+                                );
+                            }
+                            if widget_mode_loop.is_compact() {
+                                events::emit_to::<events::WidgetCompactUpdate>(
+                                    &app_handle,
+                                    "widget",
+                                    &CompactWidgetUpdate::new(&game, !calm),
+                                );
+                            }
+                        }
+                    }
+                    Ok(())
+                };
+                watchdog_loop.supervise(Subsystem::GameLoop, game_loop, |incident| {
+                    subsystem_down(&app_handle_down, &game_state_restore, incident)
+                });
+                shutdown_loop.mark_loop_stopped();
             });
 
             Ok(())
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const DEFAULT_TICK_RATE: u32 = 60;
+const MIN_TICK_RATE: u32 = 1;
+const MAX_TICK_RATE: u32 = 120;
+// 一時停止中にフロントエンドへ送る生存通知の間隔
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+// 控えめモードで画面へ状態を送る間隔
+pub const LOW_DISTRACTION_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct LoopStatus {
+    pub paused: bool,
+    pub tick_rate: u32,
+    // 放置による省電力モード中
+    pub idle: bool,
+    // 控えめモード（シミュレーションは進めたまま、送信を減らし演出と通知を止める）
+    pub low_distraction: bool,
+}
+
+// ゲームループの一時停止と更新頻度（コマンドとループで共有）
+pub struct LoopControl {
+    paused: AtomicBool,
+    tick_rate: AtomicU32,
+    idle: AtomicBool,
+    // 0 なら放置判定をしない
+    idle_timeout_secs: AtomicU64,
+    low_distraction: AtomicBool,
+}
+
+impl LoopControl {
+    pub fn new(tick_rate: u32, idle_timeout_secs: u64) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            tick_rate: AtomicU32::new(clamp_tick_rate(tick_rate)),
+            idle: AtomicBool::new(false),
+            idle_timeout_secs: AtomicU64::new(idle_timeout_secs),
+            low_distraction: AtomicBool::new(false),
+        }
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    // 範囲外の値は丸めて、実際に設定した値を返す
+    pub fn set_tick_rate(&self, tick_rate: u32) -> u32 {
+        let tick_rate = clamp_tick_rate(tick_rate);
+        self.tick_rate.store(tick_rate, Ordering::SeqCst);
+        tick_rate
+    }
+
+    pub fn set_idle_timeout(&self, secs: u64) {
+        self.idle_timeout_secs.store(secs, Ordering::SeqCst);
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout_secs.load(Ordering::SeqCst) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    // 状態が変わった場合は true
+    pub fn set_idle(&self, idle: bool) -> bool {
+        self.idle.swap(idle, Ordering::SeqCst) != idle
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::SeqCst)
+    }
+
+    pub fn set_low_distraction(&self, enabled: bool) {
+        self.low_distraction.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_low_distraction(&self) -> bool {
+        self.low_distraction.load(Ordering::SeqCst)
+    }
+
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.tick_rate.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> LoopStatus {
+        LoopStatus {
+            paused: self.is_paused(),
+            tick_rate: self.tick_rate.load(Ordering::SeqCst),
+            idle: self.is_idle(),
+            low_distraction: self.is_low_distraction(),
+        }
+    }
+}
+
+fn clamp_tick_rate(tick_rate: u32) -> u32 {
+    tick_rate.clamp(MIN_TICK_RATE, MAX_TICK_RATE)
+}
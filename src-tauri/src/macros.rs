@@ -0,0 +1,187 @@
+use crate::error::GameError;
+use crate::game::GameState;
+use crate::profile;
+use crate::stance::Stance;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const MAX_MACRO_NAME_LEN: usize = 32;
+const MAX_MACRO_ACTIONS: usize = 200;
+
+// 記録・再生できる操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MacroAction {
+    PurchaseUpgrade {
+        upgrade_type: String,
+        unit_type: String,
+    },
+    PrestigeUpgrade {
+        kind: String,
+    },
+    AdvanceStage,
+    SetManualAdvance {
+        enabled: bool,
+    },
+    BuildBarracks {
+        position: f32,
+    },
+    SetStance {
+        stance: Stance,
+    },
+}
+
+impl MacroAction {
+    fn apply(&self, game: &mut GameState) -> Result<(), GameError> {
+        match self {
+            MacroAction::PurchaseUpgrade {
+                upgrade_type,
+                unit_type,
+            } => game.purchase_upgrade(upgrade_type, unit_type).map(|_| ()),
+            MacroAction::PrestigeUpgrade { kind } => game.purchase_prestige_upgrade(kind),
+            MacroAction::AdvanceStage => game.advance_stage(),
+            MacroAction::SetManualAdvance { enabled } => {
+                game.set_manual_advance(*enabled);
+                Ok(())
+            }
+            MacroAction::BuildBarracks { position } => game.build_barracks(*position),
+            MacroAction::SetStance { stance } => game.set_stance(*stance),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub actions: Vec<MacroAction>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MacroRunResult {
+    pub completed: usize,
+    pub total: usize,
+    // 途中で失敗した場合はそこで中断する
+    pub error: Option<GameError>,
+}
+
+fn macros_file_path() -> Option<PathBuf> {
+    profile::config_dir().map(|dir| dir.join("macros.json"))
+}
+
+pub fn load_macros() -> Vec<Macro> {
+    macros_file_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_macros(macros: &[Macro]) -> Result<(), GameError> {
+    let path =
+        macros_file_path().ok_or_else(|| GameError::storage("Failed to resolve macros path"))?;
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(macros).map_err(GameError::storage)?;
+    fs::write(path, json).map_err(GameError::storage)
+}
+
+fn validate_name(name: &str) -> Result<String, GameError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_MACRO_NAME_LEN {
+        return Err(GameError::InvalidMacroName);
+    }
+    Ok(name.to_string())
+}
+
+pub fn delete_macro(name: &str) -> Result<(), GameError> {
+    let mut macros = load_macros();
+    let before = macros.len();
+    macros.retain(|m| m.name != name);
+    if macros.len() == before {
+        return Err(GameError::MacroNotFound {
+            name: name.to_string(),
+        });
+    }
+    save_macros(&macros)
+}
+
+// 記録済みの操作を順に実行する
+pub fn run_macro(name: &str, game: &mut GameState) -> Result<MacroRunResult, GameError> {
+    let macro_ = load_macros()
+        .into_iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| GameError::MacroNotFound {
+            name: name.to_string(),
+        })?;
+    let total = macro_.actions.len();
+    for (index, action) in macro_.actions.iter().enumerate() {
+        if let Err(e) = action.apply(game) {
+            return Ok(MacroRunResult {
+                completed: index,
+                total,
+                error: Some(e),
+            });
+        }
+    }
+    Ok(MacroRunResult {
+        completed: total,
+        total,
+        error: None,
+    })
+}
+
+// 記録中のマクロ（成功した操作だけを積む）
+#[derive(Default)]
+pub struct MacroRecorder {
+    recording: Mutex<Option<Macro>>,
+}
+
+impl MacroRecorder {
+    pub fn start(&self, name: &str) -> Result<(), GameError> {
+        let name = validate_name(name)?;
+        let mut recording = self.recording.lock();
+        if recording.is_some() {
+            return Err(GameError::MacroAlreadyRecording);
+        }
+        *recording = Some(Macro {
+            name,
+            actions: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn record(&self, action: MacroAction) {
+        if let Some(recording) = self.recording.lock().as_mut() {
+            if recording.actions.len() < MAX_MACRO_ACTIONS {
+                recording.actions.push(action);
+            }
+        }
+    }
+
+    pub fn status(&self) -> Option<Macro> {
+        self.recording.lock().clone()
+    }
+
+    pub fn cancel(&self) {
+        *self.recording.lock() = None;
+    }
+
+    // 記録を終了して保存する（同名のマクロは上書き）
+    pub fn stop(&self) -> Result<Macro, GameError> {
+        let recorded = self
+            .recording
+            .lock()
+            .take()
+            .ok_or(GameError::MacroNotRecording)?;
+        if recorded.actions.is_empty() {
+            return Err(GameError::EmptyMacro);
+        }
+        let mut macros = load_macros();
+        macros.retain(|m| m.name != recorded.name);
+        macros.push(recorded.clone());
+        save_macros(&macros)?;
+        Ok(recorded)
+    }
+}
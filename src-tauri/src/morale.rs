@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+// 士気は -1.0〜1.0、0.0 が平常
+const KILL_GAIN: f32 = 0.02;
+const DEATH_LOSS: f32 = 0.03;
+// 基地が最大HPの何割削られたかに対する減少量
+const BASE_DAMAGE_LOSS: f32 = 2.0;
+// ステージの勝敗による変化量
+const STAGE_RESULT_SHIFT: f32 = 0.3;
+// 1秒あたりに平常へ戻る割合
+const DECAY_PER_SEC: f32 = 0.05;
+// 士気最大時の攻撃力・速度の変化量
+const MAX_ATTACK_EFFECT: f32 = 0.1;
+const MAX_SPEED_EFFECT: f32 = 0.05;
+
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct Morale {
+    pub value: f32,
+}
+
+impl Morale {
+    fn shift(&mut self, amount: f32) {
+        self.value = (self.value + amount).clamp(-1.0, 1.0);
+    }
+
+    pub fn record_kills(&mut self, count: u32) {
+        self.shift(count as f32 * KILL_GAIN);
+    }
+
+    pub fn record_deaths(&mut self, count: u32) {
+        self.shift(-(count as f32) * DEATH_LOSS);
+    }
+
+    pub fn record_base_damage(&mut self, damage: f32, max_hp: f32) {
+        if damage > 0.0 && max_hp > 0.0 {
+            self.shift(-damage / max_hp * BASE_DAMAGE_LOSS);
+        }
+    }
+
+    pub fn record_stage_result(&mut self, won: bool) {
+        self.shift(if won {
+            STAGE_RESULT_SHIFT
+        } else {
+            -STAGE_RESULT_SHIFT
+        });
+    }
+
+    pub fn decay(&mut self, delta: f32) {
+        self.value *= (1.0 - DECAY_PER_SEC).powf(delta);
+    }
+
+    pub fn attack_multiplier(&self) -> f32 {
+        1.0 + self.value * MAX_ATTACK_EFFECT
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        1.0 + self.value * MAX_SPEED_EFFECT
+    }
+}
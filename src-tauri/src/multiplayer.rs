@@ -1,7 +1,124 @@
-use crate::game::PlayerProgressData;
+use crate::conflict::SyncConflict;
+use crate::game::{self, PlayerProgressData};
+use crate::i18n;
+use crate::net_stats::{Diagnostics, NetStats};
+use crate::pvp::ArmySnapshot;
+use crate::season::{SeasonInfo, SeasonResult};
+use crate::shop::ShopStock;
+use async_trait::async_trait;
+use futures_util::StreamExt;
 use parking_lot::Mutex;
+use rand::Rng;
+use reqwest::header::{self, HeaderValue};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio_tungstenite::tungstenite::Message;
+use ts_rs::TS;
+
+// WebSocket の再接続待ち（指数バックオフ）
+const PUSH_RETRY_MIN: Duration = Duration::from_secs(1);
+const PUSH_RETRY_MAX: Duration = Duration::from_secs(60);
+// 未登録時や接続先の変更を確認する間隔
+const PUSH_IDLE_INTERVAL: Duration = Duration::from_secs(5);
+// ソケットが切れている間の HTTP ポーリング間隔
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// 429 に Retry-After が無いときの待ち時間と、指示された待ち時間の上限
+const RATE_LIMIT_DEFAULT_WAIT: Duration = Duration::from_secs(30);
+const RATE_LIMIT_MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+// ランキング・プレイヤー一覧はこの間は通信せずに手元の応答を返す
+const LIST_CACHE_TTL: Duration = Duration::from_secs(30);
+// これより古くなるまでは手元の応答をすぐ返し、裏で取り直す（超えたら取り直しを待つ）
+const LIST_CACHE_MAX_STALE: Duration = Duration::from_secs(10 * 60);
+
+// 通信エラーの分類。retryable なものは時間をおけば成功する見込みがある
+#[derive(Debug, Clone, Error, Serialize, PartialEq, TS)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MultiplayerError {
+    #[error("No server URL configured")]
+    NotConfigured,
+    #[error("Not registered to server")]
+    NotRegistered,
+    #[error("Server did not respond in time")]
+    Timeout,
+    #[error("Failed to reach server: {message}")]
+    Network { message: String },
+    // 4xx
+    #[error("Server returned error: {status}")]
+    ClientError { status: u16 },
+    // 5xx
+    #[error("Server returned error: {status}")]
+    ServerError { status: u16 },
+    // レスポンスが想定した形式でない（サーバーのバージョン違いなど）
+    #[error("Failed to parse response: {message}")]
+    Schema { message: String },
+    // サーバーがメンテナンス中で書き込みを受け付けない
+    #[error("{message}")]
+    Maintenance {
+        message: String,
+        #[ts(type = "number")]
+        retry_after_secs: u64,
+    },
+    // 匿名モードではランキングなどの閲覧だけができる
+    #[error("Anonymous mode is read-only; register to sync progress")]
+    ReadOnly,
+    // 429。retry_after_secs の間はリクエストを送らない
+    #[error("Too many requests; retry in {retry_after_secs}s")]
+    RateLimited {
+        #[ts(type = "number")]
+        retry_after_secs: u64,
+    },
+}
+
+impl MultiplayerError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout
+            | Self::Network { .. }
+            | Self::ServerError { .. }
+            | Self::Maintenance { .. }
+            | Self::RateLimited { .. } => true,
+            // 408 Request Timeout
+            Self::ClientError { status } => *status == 408,
+            Self::NotConfigured | Self::NotRegistered | Self::Schema { .. } | Self::ReadOnly => {
+                false
+            }
+        }
+    }
+
+    fn from_status(status: reqwest::StatusCode) -> Self {
+        if status.is_server_error() {
+            Self::ServerError {
+                status: status.as_u16(),
+            }
+        } else {
+            Self::ClientError {
+                status: status.as_u16(),
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for MultiplayerError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else if e.is_decode() {
+            Self::Schema {
+                message: e.to_string(),
+            }
+        } else {
+            Self::Network {
+                message: e.to_string(),
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerInfo {
@@ -9,79 +126,597 @@ pub struct PlayerInfo {
     pub player_name: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
 pub struct PlayerProfile {
     pub player_id: String,
     pub player_name: String,
     pub progress: PlayerProgressData,
+    #[ts(type = "number")]
+    pub last_update: i64,
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    // サーバーの現在のシーズン（シーズンのないサーバーでは 0）
+    #[serde(default)]
+    pub season: u32,
+    #[serde(default)]
+    pub seasons: Vec<SeasonResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub player_id: String,
+    pub player_name: String,
+    pub stage: u32,
+    pub coins: u64,
+    pub last_update: i64,
+    // サーバーが不正の疑いありと判定したプレイヤー
+    #[serde(default)]
+    pub flagged: bool,
+    // 表示中の称号と獲得済みの称号
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub badges: Vec<String>,
+    // 選んでいるアバターとユニットの見た目（選んでいなければ None）
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub skin: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardPage {
+    pub entries: Vec<LeaderboardEntry>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidStanding {
+    pub rank: usize,
+    pub player_id: String,
+    pub player_name: String,
+    pub damage: u64,
+    // 上位何%か
+    pub percentile: f64,
+    pub tier: String,
+    pub claimed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidLeaderboardPage {
+    pub raid_id: String,
+    pub entries: Vec<RaidStanding>,
+    pub total_damage: u64,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidReward {
+    pub raid_id: String,
+    pub rank: usize,
+    pub percentile: f64,
+    pub tier: String,
+    pub coins: u64,
+}
+
+// 全員で削る共有レイドボス
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct RaidBossStatus {
+    pub raid_id: String,
+    pub name: String,
+    pub generation: u32,
+    #[ts(type = "number")]
+    pub hp: u64,
+    #[ts(type = "number")]
+    pub max_hp: u64,
+    pub contributors: usize,
+    // 撃破済みで報酬を受け取っていないレイド
+    #[serde(default)]
+    pub pending_rewards: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildMember {
+    pub player_id: String,
+    pub player_name: String,
+    pub stage: u32,
+    pub coins: u64,
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildInfo {
+    pub guild_id: String,
+    pub name: String,
+    pub owner_id: String,
+    // メンバーとして取得したときだけ入る
+    #[serde(default)]
+    pub join_code: Option<String>,
+    pub members: Vec<GuildMember>,
+    pub total_stage: u64,
+    pub total_coins: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildLeaderboardEntry {
+    pub rank: usize,
+    pub guild_id: String,
+    pub name: String,
+    pub member_count: usize,
+    pub total_stage: u64,
+    pub total_coins: u64,
     pub last_update: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildLeaderboardPage {
+    pub entries: Vec<GuildLeaderboardEntry>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize,
+}
+
+// 他のプレイヤーの公開されている進行状況
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct PlayerSnapshot {
+    pub player_id: String,
+    pub player_name: String,
+    pub stage: u32,
+    pub coins: u64,
+    pub best_stage: u32,
+    pub prestige_count: u32,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub badges: Vec<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub skin: Option<String>,
+    pub last_update: i64,
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+// 古いサーバーは status・timestamp・player_count しか返さないので残りは省略可能
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerHealth {
+    pub status: String,
+    pub timestamp: i64,
+    pub player_count: usize,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub uptime_secs: Option<i64>,
+    #[serde(default)]
+    pub storage: Option<ServerStorage>,
+    #[serde(default)]
+    pub capacity: Option<ServerCapacity>,
+    #[serde(default)]
+    pub maintenance: Option<ServerMaintenance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerStorage {
+    pub backend: String,
+    pub data_dir: String,
+    #[serde(default)]
+    pub free_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerCapacity {
+    // None なら無制限
+    #[serde(default)]
+    pub max_players: Option<usize>,
+    pub full: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerMaintenance {
+    pub enabled: bool,
+    pub message: String,
+    pub retry_after_secs: u64,
+    #[serde(default)]
+    pub since: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockStatus {
+    pub server_now: i64,
+    // サーバーの時計 − 手元の時計（秒）
+    pub skew_secs: i64,
+}
+
+// メンテナンス中の 503 を受けてから書き込みを止めている間の情報
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct MaintenanceNotice {
+    pub message: String,
+    // この時刻（サーバーの時計の UNIX 秒）を過ぎたら書き込みを再開する
+    pub retry_at: i64,
+}
+
+// マルチプレイの操作。Tauri のコマンドはこれを通して使うので、HTTP の MultiplayerClient と
+// サーバーなしで動く InMemoryMultiplayer（オフラインの開発モードとテスト用）を差し替えられる
+#[async_trait]
+pub trait MultiplayerApi: Send + Sync {
+    fn set_server_url(&self, url: String);
+    fn get_server_url(&self) -> String;
+    fn set_token(&self, token: Option<String>);
+    // プロファイル切り替え時に登録情報を破棄
+    fn reset_identity(&self);
+    // 匿名モードに入るときは登録情報を手放し、サーバーへ自分の ID を送らない
+    // （保存済みの ID とトークンは設定に残るので、戻ったら登録し直せる）
+    fn set_anonymous(&self, enabled: bool);
+    fn is_anonymous(&self) -> bool;
+    fn diagnostics(&self) -> Diagnostics;
+    fn reset_diagnostics(&self);
+    // 再開予定時刻を過ぎていれば None
+    fn maintenance(&self) -> Option<MaintenanceNotice>;
+    // 書き込みが通ったらメンテナンスは終わっている。止めていた場合は true
+    fn clear_maintenance(&self) -> bool;
+    // サーバーの時計での現在時刻。last_update など、サーバーが付けた時刻との比較に使う
+    fn server_now(&self) -> i64;
+    fn clock_status(&self) -> ClockStatus;
+    fn is_connected(&self) -> bool;
+    fn is_push_connected(&self) -> bool;
+    fn set_conflict(&self, conflict: SyncConflict);
+    fn conflict(&self) -> Option<SyncConflict>;
+    fn take_conflict(&self) -> Option<SyncConflict>;
+    // 前回より新しいサーバーの更新なら覚えて true。比べるのはどちらもサーバーが付けた時刻なので、
+    // 手元の時計のずれは影響しない
+    fn mark_remote_update(&self, timestamp: i64) -> bool;
+
+    // 使われている名前ならサーバーが "名前#1234" に変えて新しく登録する
+    async fn register_player(
+        &self,
+        player_name: String,
+    ) -> Result<RegisterResult, MultiplayerError>;
+    // 登録時のトークンで既存のプレイヤーに入り直す
+    async fn login(&self, player_id: String) -> Result<RegisterResult, MultiplayerError>;
+    async fn sync_progress(
+        &self,
+        progress: &PlayerProgressData,
+    ) -> Result<PlayerProfile, MultiplayerError>;
+    async fn fetch_profile(&self) -> Result<PlayerProfile, MultiplayerError>;
+    async fn get_all_players(&self) -> Result<Vec<serde_json::Value>, MultiplayerError>;
+    // 存在しない ID は結果から省かれる
+    async fn get_players_batch(
+        &self,
+        player_ids: &[String],
+    ) -> Result<Vec<PlayerSnapshot>, MultiplayerError>;
+    // sort は "stage" / "coins" / "last_update"、page は 1 から
+    async fn get_leaderboard(
+        &self,
+        sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<LeaderboardPage, MultiplayerError>;
+    async fn get_raid_leaderboard(
+        &self,
+        raid_id: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<RaidLeaderboardPage, MultiplayerError>;
+    async fn get_raid_status(&self) -> Result<RaidBossStatus, MultiplayerError>;
+    async fn get_season(&self) -> Result<SeasonInfo, MultiplayerError>;
+    async fn get_shop(&self) -> Result<ShopStock, MultiplayerError>;
+    // 前回の送信から与えたダメージを共有レイドボスに加える
+    async fn contribute_raid_damage(&self, damage: u64)
+        -> Result<RaidBossStatus, MultiplayerError>;
+    // 貢献度の順位に応じた報酬を受け取る（1 レイドにつき 1 回）
+    async fn claim_raid_reward(&self, raid_id: &str) -> Result<RaidReward, MultiplayerError>;
+    async fn create_guild(&self, name: &str) -> Result<GuildInfo, MultiplayerError>;
+    // 参加コードは大文字小文字を区別しない
+    async fn join_guild(&self, code: &str) -> Result<GuildInfo, MultiplayerError>;
+    async fn leave_guild(&self) -> Result<serde_json::Value, MultiplayerError>;
+    // 自分のギルドなら参加コードも返る
+    async fn get_guild(&self, guild_id: &str) -> Result<GuildInfo, MultiplayerError>;
+    // sort は "stage" / "coins" / "last_update"（メンバーの合計で並ぶ）
+    async fn get_guild_leaderboard(
+        &self,
+        sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<GuildLeaderboardPage, MultiplayerError>;
+    // 対戦の相手として使われる自分の軍勢を送る（同期済みの強化を超えると 422）
+    async fn upload_army(&self, army: &ArmySnapshot) -> Result<ArmySnapshot, MultiplayerError>;
+    // 相手が一度も挑戦していなければ 404
+    async fn fetch_army(&self, player_id: &str) -> Result<ArmySnapshot, MultiplayerError>;
+    // server_url を省くと設定済みのサーバーを調べる（サーバー一覧では候補ごとに指定する）
+    async fn health_check(
+        &self,
+        server_url: Option<&str>,
+    ) -> Result<ServerHealth, MultiplayerError>;
+
+    // 自分の進行状況を取り直し、前回より新しければ on_update に渡す
+    async fn poll_once(
+        &self,
+        on_update: &(dyn Fn(PlayerProfile) + Send + Sync),
+    ) -> Result<(), MultiplayerError> {
+        let profile = self.fetch_profile().await?;
+        if self.mark_remote_update(profile.last_update) {
+            on_update(profile);
+        }
+        Ok(())
+    }
+
+    // サーバーからの進行状況の更新を受け取り続ける（既定はポーリングだけ）
+    async fn run_push_sync(self: Arc<Self>, on_update: Box<dyn Fn(PlayerProfile) + Send + Sync>) {
+        loop {
+            let _ = self.poll_once(&*on_update).await;
+            tokio::time::sleep(FALLBACK_POLL_INTERVAL).await;
+        }
+    }
+}
+
+// 条件付きリクエスト用に保持する前回の応答
+#[derive(Clone)]
+struct CachedResponse {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    body: Vec<u8>,
+}
+
+// ランキング・一覧の手元の応答（サーバーが遅くてもパネルを待たせない）
+struct ListCacheEntry {
+    body: Vec<u8>,
+    fetched_at: Instant,
+    // 裏で取り直している最中（同じ URL を重ねて取りに行かない）
+    refreshing: bool,
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, MultiplayerError> {
+    serde_json::from_slice(body).map_err(|e| MultiplayerError::Schema {
+        message: e.to_string(),
+    })
+}
+
 pub struct MultiplayerClient {
+    // 裏での取り直しに自分を渡すための参照
+    this: Weak<MultiplayerClient>,
     server_url: Arc<Mutex<String>>,
     player_info: Arc<Mutex<Option<PlayerInfo>>>,
     last_remote_update: Arc<Mutex<Option<i64>>>,
     http_client: reqwest::Client,
+    // 自分が送った同期をサーバーから送り返されないための識別子
+    client_id: String,
+    push_connected: AtomicBool,
+    // URL（クエリ込み）ごとの応答キャッシュ
+    response_cache: Mutex<HashMap<String, CachedResponse>>,
+    // URL（クエリ込み）ごとのランキング・一覧の応答
+    list_cache: Mutex<HashMap<String, ListCacheEntry>>,
+    // 登録時にサーバーが発行する認証トークン（AppConfig に保存して再起動後も使う）
+    token: Mutex<Option<String>>,
+    // 手元と食い違ったまま解決を待っている進行状況
+    conflict: Mutex<Option<SyncConflict>>,
+    maintenance: Mutex<Option<MaintenanceNotice>>,
+    // サーバーの時計 − 手元の時計（秒）。応答の Date ヘッダーやヘルスチェックの時刻から求め、
+    // 手元の時計がずれていてもサーバーの時刻と比べられるようにする
+    clock_skew: AtomicI64,
+    // 名前を登録せずに閲覧だけする（書き込みはすべて ReadOnly で断る）
+    anonymous: AtomicBool,
+    // 429 で指示された待ち時間の終わり。それまではサーバーに送らずに断る
+    throttled_until: Mutex<Option<Instant>>,
+    // エンドポイントごとの通信量と直近のエラー
+    net_stats: NetStats,
 }
 
 impl MultiplayerClient {
-    pub fn new() -> Self {
-        Self {
+    pub fn new() -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
             server_url: Arc::new(Mutex::new(String::new())),
             player_info: Arc::new(Mutex::new(None)),
             last_remote_update: Arc::new(Mutex::new(None)),
-            http_client: reqwest::Client::new(),
+            http_client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            client_id: format!("{:016x}", rand::thread_rng().gen::<u64>()),
+            push_connected: AtomicBool::new(false),
+            response_cache: Mutex::new(HashMap::new()),
+            list_cache: Mutex::new(HashMap::new()),
+            token: Mutex::new(None),
+            conflict: Mutex::new(None),
+            maintenance: Mutex::new(None),
+            clock_skew: AtomicI64::new(0),
+            anonymous: AtomicBool::new(false),
+            throttled_until: Mutex::new(None),
+            net_stats: NetStats::new(),
+        })
+    }
+
+    // 書き込みに使う登録情報
+    fn registered(&self) -> Result<PlayerInfo, MultiplayerError> {
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
         }
+        self.player_info
+            .lock()
+            .clone()
+            .ok_or(MultiplayerError::NotRegistered)
     }
 
-    pub fn set_server_url(&self, url: String) {
-        *self.server_url.lock() = url;
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.token.lock().as_deref() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
     }
 
-    pub fn get_server_url(&self) -> String {
-        self.server_url.lock().clone()
+    // 送受信の量と結果をエンドポイントごとに数えて送る
+    async fn send(
+        &self,
+        endpoint: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, MultiplayerError> {
+        self.execute(endpoint, request.build()?).await
     }
 
-    pub fn is_connected(&self) -> bool {
-        !self.get_server_url().is_empty() && self.player_info.lock().is_some()
+    async fn execute(
+        &self,
+        endpoint: &'static str,
+        mut request: reqwest::Request,
+    ) -> Result<reqwest::Response, MultiplayerError> {
+        // サーバーのエラーメッセージも同じ言語で受け取る
+        request.headers_mut().insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_static(i18n::language().code()),
+        );
+        let sent = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, |bytes| bytes.len() as u64);
+        match self.http_client.execute(request).await {
+            Ok(response) => {
+                let status = response.status();
+                let failed = !status.is_success() && status != reqwest::StatusCode::NOT_MODIFIED;
+                self.net_stats.record(
+                    endpoint,
+                    sent,
+                    response.content_length().unwrap_or(0),
+                    Some(status.as_u16()),
+                    failed.then(|| MultiplayerError::from_status(status).to_string()),
+                );
+                Ok(response)
+            }
+            Err(err) => {
+                let err = MultiplayerError::from(err);
+                self.net_stats
+                    .record(endpoint, sent, 0, None, Some(err.to_string()));
+                Err(err)
+            }
+        }
     }
 
-    pub async fn register_player(&self, player_name: String) -> Result<RegisterResult, String> {
-        let server_url = self.get_server_url();
-        if server_url.is_empty() {
-            return Err("No server URL configured".to_string());
+    // 429 の Retry-After が過ぎるまでは読み書きとも送らない
+    fn check_throttle(&self) -> Result<(), MultiplayerError> {
+        let mut guard = self.throttled_until.lock();
+        match *guard {
+            Some(until) if until > Instant::now() => Err(MultiplayerError::RateLimited {
+                retry_after_secs: until
+                    .saturating_duration_since(Instant::now())
+                    .as_secs()
+                    .max(1),
+            }),
+            _ => {
+                *guard = None;
+                Ok(())
+            }
         }
+    }
 
-        #[derive(Serialize)]
-        struct RegisterRequest {
-            player_name: String,
+    // 失敗した応答のエラー。429 なら待ち時間を覚えて以降のリクエストを止める
+    fn rejected(&self, response: &reqwest::Response) -> MultiplayerError {
+        let status = response.status();
+        if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return MultiplayerError::from_status(status);
         }
+        let retry_after_secs = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(RATE_LIMIT_DEFAULT_WAIT.as_secs())
+            .clamp(1, RATE_LIMIT_MAX_WAIT.as_secs());
+        *self.throttled_until.lock() = Some(Instant::now() + Duration::from_secs(retry_after_secs));
+        MultiplayerError::RateLimited { retry_after_secs }
+    }
 
-        let url = format!("{}/api/player/register", server_url);
-        let response = self
-            .http_client
-            .post(&url)
-            .json(&RegisterRequest {
-                player_name: player_name.clone(),
-            })
-            .send()
-            .await
-            .map_err(|e| format!("Failed to register player: {}", e))?;
+    // メンテナンス中は再開予定時刻まで書き込みを送らない
+    fn ensure_writable(&self) -> Result<(), MultiplayerError> {
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
+        }
+        self.check_throttle()?;
+        match self.maintenance() {
+            Some(notice) => Err(MultiplayerError::Maintenance {
+                retry_after_secs: (notice.retry_at - self.server_now()).max(0) as u64,
+                message: notice.message,
+            }),
+            None => Ok(()),
+        }
+    }
 
-        if !response.status().is_success() {
-            return Err(format!("Server returned error: {}", response.status()));
+    // 書き込みの応答を確かめる。メンテナンスの 503 なら書き込みを止める
+    async fn check_write(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, MultiplayerError> {
+        self.observe_date(&response);
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status != reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            return Err(self.rejected(&response));
         }
 
-        let register_response: RegisterResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+        #[derive(Deserialize)]
+        struct MaintenanceBody {
+            #[serde(default)]
+            maintenance: bool,
+            #[serde(default)]
+            message: String,
+            #[serde(default)]
+            retry_after_secs: u64,
+        }
+
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        match response.json::<MaintenanceBody>().await {
+            Ok(body) if body.maintenance => {
+                let retry_after_secs = retry_after.unwrap_or(body.retry_after_secs);
+                *self.maintenance.lock() = Some(MaintenanceNotice {
+                    message: body.message.clone(),
+                    retry_at: self.server_now() + retry_after_secs as i64,
+                });
+                Err(MultiplayerError::Maintenance {
+                    message: body.message,
+                    retry_after_secs,
+                })
+            }
+            _ => Err(MultiplayerError::from_status(status)),
+        }
+    }
+
+    fn observe_server_time(&self, server_time: i64) {
+        self.clock_skew
+            .store(server_time - game::unix_timestamp(), Ordering::Relaxed);
+    }
+
+    // Date ヘッダーは秒単位なので、1 秒未満のずれは気にしない
+    fn observe_date(&self, response: &reqwest::Response) {
+        let date = response
+            .headers()
+            .get(header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok());
+        if let Some(date) = date {
+            self.observe_server_time(date.timestamp());
+        }
+    }
 
+    fn signed_in(
+        &self,
+        register_response: RegisterResponse,
+    ) -> Result<RegisterResult, MultiplayerError> {
         *self.player_info.lock() = Some(PlayerInfo {
             player_id: register_response.player_id.clone(),
-            player_name,
+            player_name: register_response.player_name.clone(),
         });
         *self.last_remote_update.lock() = Some(register_response.last_update);
+        *self.token.lock() = Some(register_response.token.clone());
 
         Ok(RegisterResult {
             player_id: register_response.player_id,
@@ -89,134 +724,733 @@ impl MultiplayerClient {
             message: register_response.message,
             progress: register_response.progress,
             last_update: register_response.last_update,
+            token: register_response.token,
         })
     }
 
-    pub async fn sync_progress(
+    // body に player_id を足してギルドの操作を送る
+    async fn post_guild<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        mut body: serde_json::Value,
+    ) -> Result<T, MultiplayerError> {
+        let info = self.registered()?;
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.ensure_writable()?;
+
+        body["player_id"] = serde_json::Value::from(info.player_id);
+        let url = format!("{}{}", server_url, path);
+        let request = self.authorized(self.http_client.post(&url)).json(&body);
+        let response = self.send("guild_action", request).await?;
+        let response = self.check_write(response).await?;
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    // 前回の ETag / Last-Modified を付けて取得し、304 なら手元の応答を使う
+    async fn get_conditional<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, MultiplayerError> {
+        let body = self.fetch_conditional(endpoint, request.build()?).await?;
+        parse_body(&body)
+    }
+
+    async fn fetch_conditional(
+        &self,
+        endpoint: &'static str,
+        mut request: reqwest::Request,
+    ) -> Result<Vec<u8>, MultiplayerError> {
+        self.check_throttle()?;
+        let key = request.url().to_string();
+        let cached = self.response_cache.lock().get(&key).cloned();
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request
+                    .headers_mut()
+                    .insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request
+                    .headers_mut()
+                    .insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let response = self.execute(endpoint, request).await?;
+        self.observe_date(&response);
+        let body = match (response.status(), cached) {
+            (reqwest::StatusCode::NOT_MODIFIED, Some(cached)) => cached.body,
+            (status, _) if !status.is_success() => {
+                return Err(self.rejected(&response));
+            }
+            _ => {
+                let etag = response.headers().get(header::ETAG).cloned();
+                let last_modified = response.headers().get(header::LAST_MODIFIED).cloned();
+                let body = response.bytes().await?.to_vec();
+                if etag.is_some() || last_modified.is_some() {
+                    self.response_cache.lock().insert(
+                        key,
+                        CachedResponse {
+                            etag,
+                            last_modified,
+                            body: body.clone(),
+                        },
+                    );
+                }
+                body
+            }
+        };
+        Ok(body)
+    }
+
+    // ランキング・一覧の取得。TTL の間は手元の応答、それを過ぎても LIST_CACHE_MAX_STALE までは
+    // 手元の応答をすぐ返して裏で取り直す。手元になければ（古すぎれば）取得を待つ
+    async fn get_listing<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, MultiplayerError> {
+        let request = request.build()?;
+        let key = request.url().to_string();
+        let cached = {
+            let mut cache = self.list_cache.lock();
+            cache.get_mut(&key).and_then(|entry| {
+                let age = entry.fetched_at.elapsed();
+                if age >= LIST_CACHE_MAX_STALE {
+                    return None;
+                }
+                let revalidate = age >= LIST_CACHE_TTL && !entry.refreshing;
+                entry.refreshing |= revalidate;
+                Some((entry.body.clone(), revalidate))
+            })
+        };
+        match cached {
+            Some((body, revalidate)) => {
+                if revalidate {
+                    self.revalidate(endpoint, key, request);
+                }
+                parse_body(&body)
+            }
+            None => {
+                let body = self.fetch_conditional(endpoint, request).await?;
+                self.store_listing(key, body.clone());
+                parse_body(&body)
+            }
+        }
+    }
+
+    // 失敗しても手元の応答はそのまま残し、次の取得でまた取り直す
+    fn revalidate(&self, endpoint: &'static str, key: String, request: reqwest::Request) {
+        let Some(client) = self.this.upgrade() else {
+            return;
+        };
+        tauri::async_runtime::spawn(async move {
+            match client.fetch_conditional(endpoint, request).await {
+                Ok(body) => client.store_listing(key, body),
+                Err(_) => {
+                    if let Some(entry) = client.list_cache.lock().get_mut(&key) {
+                        entry.refreshing = false;
+                    }
+                }
+            }
+        });
+    }
+
+    fn store_listing(&self, key: String, body: Vec<u8>) {
+        self.list_cache.lock().insert(
+            key,
+            ListCacheEntry {
+                body,
+                fetched_at: Instant::now(),
+                refreshing: false,
+            },
+        );
+    }
+
+    fn socket_url(&self) -> Option<String> {
+        let server_url = self.get_server_url();
+        let player_id = self.player_info.lock().as_ref()?.player_id.clone();
+        let token = self.token.lock().clone().unwrap_or_default();
+        let base = if let Some(rest) = server_url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = server_url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            return None;
+        };
+        Some(format!(
+            "{}/ws/player/{}?client_id={}&token={}",
+            base.trim_end_matches('/'),
+            player_id,
+            self.client_id,
+            token
+        ))
+    }
+
+    async fn read_pushes<S, F>(&self, mut stream: S, url: &str, on_update: &F)
+    where
+        S: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+        F: Fn(PlayerProfile),
+    {
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        self.net_stats.record_push("push", text.len() as u64);
+                        if let Ok(profile) = serde_json::from_str::<PlayerProfile>(&text) {
+                            if self.mark_remote_update(profile.last_update) {
+                                on_update(profile);
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+                _ = tokio::time::sleep(PUSH_IDLE_INTERVAL) => {
+                    // プロファイル切り替えや接続先の変更で接続し直す
+                    if self.socket_url().as_deref() != Some(url) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MultiplayerApi for MultiplayerClient {
+    fn set_server_url(&self, url: String) {
+        *self.server_url.lock() = url;
+    }
+
+    fn get_server_url(&self) -> String {
+        self.server_url.lock().clone()
+    }
+
+    fn set_token(&self, token: Option<String>) {
+        *self.token.lock() = token.filter(|t| !t.is_empty());
+    }
+
+    fn reset_identity(&self) {
+        *self.player_info.lock() = None;
+        *self.last_remote_update.lock() = None;
+        *self.token.lock() = None;
+        *self.conflict.lock() = None;
+        *self.maintenance.lock() = None;
+    }
+
+    fn set_anonymous(&self, enabled: bool) {
+        if enabled {
+            self.reset_identity();
+        }
+        self.anonymous.store(enabled, Ordering::Relaxed);
+    }
+
+    fn is_anonymous(&self) -> bool {
+        self.anonymous.load(Ordering::Relaxed)
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        self.net_stats.snapshot()
+    }
+
+    fn reset_diagnostics(&self) {
+        self.net_stats.reset();
+    }
+
+    fn maintenance(&self) -> Option<MaintenanceNotice> {
+        let mut guard = self.maintenance.lock();
+        if guard
+            .as_ref()
+            .is_some_and(|notice| notice.retry_at <= self.server_now())
+        {
+            *guard = None;
+        }
+        guard.clone()
+    }
+
+    fn clear_maintenance(&self) -> bool {
+        self.maintenance.lock().take().is_some()
+    }
+
+    fn server_now(&self) -> i64 {
+        game::unix_timestamp() + self.clock_skew.load(Ordering::Relaxed)
+    }
+
+    fn clock_status(&self) -> ClockStatus {
+        ClockStatus {
+            server_now: self.server_now(),
+            skew_secs: self.clock_skew.load(Ordering::Relaxed),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.get_server_url().is_empty() && self.player_info.lock().is_some()
+    }
+
+    async fn register_player(
+        &self,
+        player_name: String,
+    ) -> Result<RegisterResult, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        #[derive(Serialize)]
+        struct RegisterRequest {
+            player_name: String,
+        }
+
+        self.ensure_writable()?;
+
+        // 使われている名前ならサーバーが "名前#1234" に変えて新しく登録する
+        let url = format!("{}/api/player/register", server_url);
+        let request = self
+            .http_client
+            .post(&url)
+            .json(&RegisterRequest { player_name });
+        let response = self.send("register", request).await?;
+        let response = self.check_write(response).await?;
+        self.signed_in(response.json().await?)
+    }
+
+    async fn login(&self, player_id: String) -> Result<RegisterResult, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
+        }
+
+        #[derive(Serialize)]
+        struct LoginRequest {
+            player_id: String,
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/player/login", server_url);
+        let request = self
+            .authorized(self.http_client.post(&url))
+            .json(&LoginRequest { player_id });
+        let response = self.send("login", request).await?;
+        let response = self.check_write(response).await?;
+        self.signed_in(response.json().await?)
+    }
+
+    async fn sync_progress(
         &self,
         progress: &PlayerProgressData,
-    ) -> Result<PlayerProfile, String> {
-        let info = self
-            .player_info
-            .lock()
-            .clone()
-            .ok_or("Not registered to server")?;
+    ) -> Result<PlayerProfile, MultiplayerError> {
+        let info = self.registered()?;
         let server_url = self.get_server_url();
         if server_url.is_empty() {
-            return Err("No server URL configured".to_string());
+            return Err(MultiplayerError::NotConfigured);
         }
 
         #[derive(Serialize)]
         struct SyncRequest<'a> {
             progress: &'a PlayerProgressData,
+            client_id: &'a str,
         }
 
+        self.ensure_writable()?;
+
         let url = format!("{}/api/player/{}/sync", server_url, info.player_id);
-        let response = self
+        let request = self
+            .authorized(self.http_client.post(&url))
+            .json(&SyncRequest {
+                progress,
+                client_id: &self.client_id,
+            });
+        let response = self.send("sync", request).await?;
+        let response = self.check_write(response).await?;
+
+        let profile: PlayerProfile = response.json().await?;
+        *self.last_remote_update.lock() = Some(profile.last_update);
+        Ok(profile)
+    }
+
+    async fn get_all_players(&self) -> Result<Vec<serde_json::Value>, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        let url = format!("{}/api/players", server_url);
+        self.get_listing("players", self.http_client.get(&url))
+            .await
+    }
+
+    async fn get_players_batch(
+        &self,
+        player_ids: &[String],
+    ) -> Result<Vec<PlayerSnapshot>, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/players/batch", server_url);
+        let request = self
             .http_client
             .post(&url)
-            .json(&SyncRequest { progress })
-            .send()
-            .await
-            .map_err(|e| format!("Failed to sync state: {}", e))?;
+            .json(&serde_json::json!({ "player_ids": player_ids }));
+        let response = self.send("players_batch", request).await?;
 
         if !response.status().is_success() {
-            return Err(format!("Server returned error: {}", response.status()));
+            return Err(self.rejected(&response));
         }
 
-        let profile: PlayerProfile = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-        *self.last_remote_update.lock() = Some(profile.last_update);
-        Ok(profile)
+        response.json().await.map_err(MultiplayerError::from)
     }
 
-    pub async fn get_all_players(&self) -> Result<Vec<serde_json::Value>, String> {
+    async fn get_leaderboard(
+        &self,
+        sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<LeaderboardPage, MultiplayerError> {
         let server_url = self.get_server_url();
         if server_url.is_empty() {
-            return Err("No server URL configured".to_string());
+            return Err(MultiplayerError::NotConfigured);
         }
 
-        let url = format!("{}/api/players", server_url);
-        let response = self
-            .http_client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get players: {}", e))?;
+        let url = format!("{}/api/leaderboard", server_url);
+        self.get_listing(
+            "leaderboard",
+            self.http_client.get(&url).query(&[
+                ("sort", sort.to_string()),
+                ("page", page.to_string()),
+                ("page_size", page_size.to_string()),
+            ]),
+        )
+        .await
+    }
+
+    async fn get_raid_leaderboard(
+        &self,
+        raid_id: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<RaidLeaderboardPage, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/raid/{}/leaderboard", server_url, raid_id);
+        let request = self.http_client.get(&url).query(&[
+            ("page", page.to_string()),
+            ("page_size", page_size.to_string()),
+        ]);
+        let response = self.send("raid_leaderboard", request).await?;
+
+        if !response.status().is_success() {
+            return Err(self.rejected(&response));
+        }
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn get_raid_status(&self) -> Result<RaidBossStatus, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/raid", server_url);
+        let mut request = self.http_client.get(&url);
+        if let Some(info) = self.player_info.lock().as_ref() {
+            request = request.query(&[("player_id", info.player_id.as_str())]);
+        }
+        let response = self.send("raid_status", request).await?;
+
+        if !response.status().is_success() {
+            return Err(self.rejected(&response));
+        }
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn get_season(&self) -> Result<SeasonInfo, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/season", server_url);
+        let response = self.send("season", self.http_client.get(&url)).await?;
 
         if !response.status().is_success() {
-            return Err(format!("Server returned error: {}", response.status()));
+            return Err(self.rejected(&response));
         }
 
-        let players: Vec<serde_json::Value> = response
-            .json()
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn get_shop(&self) -> Result<ShopStock, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/shop", server_url);
+        let response = self.send("shop", self.http_client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(self.rejected(&response));
+        }
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn contribute_raid_damage(
+        &self,
+        damage: u64,
+    ) -> Result<RaidBossStatus, MultiplayerError> {
+        let info = self.registered()?;
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.ensure_writable()?;
+
+        let url = format!("{}/api/raid/damage", server_url);
+        let request = self
+            .authorized(self.http_client.post(&url))
+            .json(&serde_json::json!({ "player_id": info.player_id, "damage": damage }));
+        let response = self.send("raid_damage", request).await?;
+        let response = self.check_write(response).await?;
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn claim_raid_reward(&self, raid_id: &str) -> Result<RaidReward, MultiplayerError> {
+        let info = self.registered()?;
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.ensure_writable()?;
+
+        let url = format!("{}/api/raid/{}/claim", server_url, raid_id);
+        let request = self
+            .authorized(self.http_client.post(&url))
+            .json(&serde_json::json!({ "player_id": info.player_id }));
+        let response = self.send("raid_claim", request).await?;
+        let response = self.check_write(response).await?;
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn create_guild(&self, name: &str) -> Result<GuildInfo, MultiplayerError> {
+        self.post_guild("/api/guild", serde_json::json!({ "name": name }))
+            .await
+    }
+
+    async fn join_guild(&self, code: &str) -> Result<GuildInfo, MultiplayerError> {
+        self.post_guild("/api/guild/join", serde_json::json!({ "code": code }))
+            .await
+    }
+
+    async fn leave_guild(&self) -> Result<serde_json::Value, MultiplayerError> {
+        self.post_guild("/api/guild/leave", serde_json::json!({}))
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+    }
+
+    async fn get_guild(&self, guild_id: &str) -> Result<GuildInfo, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/guild/{}", server_url, guild_id);
+        let mut request = self.authorized(self.http_client.get(&url));
+        if let Some(info) = self.player_info.lock().as_ref() {
+            request = request.query(&[("player_id", info.player_id.as_str())]);
+        }
+        let response = self.send("guild", request).await?;
+
+        if !response.status().is_success() {
+            return Err(self.rejected(&response));
+        }
 
-        Ok(players)
+        response.json().await.map_err(MultiplayerError::from)
     }
 
-    pub async fn fetch_profile(&self) -> Result<PlayerProfile, String> {
+    async fn upload_army(&self, army: &ArmySnapshot) -> Result<ArmySnapshot, MultiplayerError> {
+        let info = self.registered()?;
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.ensure_writable()?;
+
+        let url = format!("{}/api/player/{}/army", server_url, info.player_id);
+        let request = self.authorized(self.http_client.post(&url)).json(army);
+        let response = self.send("army_upload", request).await?;
+        let response = self.check_write(response).await?;
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn fetch_army(&self, player_id: &str) -> Result<ArmySnapshot, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        self.check_throttle()?;
+        let url = format!("{}/api/player/{}/army", server_url, player_id);
+        let response = self.send("army", self.http_client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(self.rejected(&response));
+        }
+
+        response.json().await.map_err(MultiplayerError::from)
+    }
+
+    async fn get_guild_leaderboard(
+        &self,
+        sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<GuildLeaderboardPage, MultiplayerError> {
+        let server_url = self.get_server_url();
+        if server_url.is_empty() {
+            return Err(MultiplayerError::NotConfigured);
+        }
+
+        let url = format!("{}/api/guilds/leaderboard", server_url);
+        self.get_conditional(
+            "guild_leaderboard",
+            self.http_client.get(&url).query(&[
+                ("sort", sort.to_string()),
+                ("page", page.to_string()),
+                ("page_size", page_size.to_string()),
+            ]),
+        )
+        .await
+    }
+
+    async fn fetch_profile(&self) -> Result<PlayerProfile, MultiplayerError> {
         let info = self
             .player_info
             .lock()
             .clone()
-            .ok_or("Not registered to server")?;
+            .ok_or(MultiplayerError::NotRegistered)?;
         let server_url = self.get_server_url();
         if server_url.is_empty() {
-            return Err("No server URL configured".to_string());
+            return Err(MultiplayerError::NotConfigured);
         }
 
         let url = format!("{}/api/player/{}", server_url, info.player_id);
-        let response = self
-            .http_client
-            .get(&url)
-            .send()
+        self.get_conditional("profile", self.authorized(self.http_client.get(&url)))
             .await
-            .map_err(|e| format!("Failed to fetch profile: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("Server returned error: {}", response.status()));
-        }
-
-        let profile: PlayerProfile = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-        Ok(profile)
     }
 
-    pub async fn health_check(&self) -> Result<String, String> {
-        let server_url = self.get_server_url();
+    async fn health_check(
+        &self,
+        server_url: Option<&str>,
+    ) -> Result<ServerHealth, MultiplayerError> {
+        let checking_other = server_url.is_some();
+        let server_url = match server_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => self.get_server_url(),
+        };
         if server_url.is_empty() {
-            return Err("No server URL configured".to_string());
+            return Err(MultiplayerError::NotConfigured);
         }
 
         let url = format!("{}/health", server_url);
-        let response = self
-            .http_client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to reach server: {}", e))?;
+        let response = self.send("health", self.http_client.get(&url)).await?;
 
         if !response.status().is_success() {
-            return Err(format!("Server returned error: {}", response.status()));
+            return Err(MultiplayerError::from_status(response.status()));
         }
 
-        let json: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+        let health: ServerHealth = response.json().await?;
+        // 別のサーバーを調べたときは時計を合わせない
+        if !checking_other {
+            self.observe_server_time(health.timestamp);
+        }
+        Ok(health)
+    }
+
+    fn is_push_connected(&self) -> bool {
+        self.push_connected.load(Ordering::Relaxed)
+    }
+
+    // WebSocket で受け、切断中は HTTP ポーリングで補う
+    async fn run_push_sync(self: Arc<Self>, on_update: Box<dyn Fn(PlayerProfile) + Send + Sync>) {
+        let mut backoff = PUSH_RETRY_MIN;
+        loop {
+            let Some(url) = self.socket_url() else {
+                backoff = PUSH_RETRY_MIN;
+                tokio::time::sleep(PUSH_IDLE_INTERVAL).await;
+                continue;
+            };
+
+            if let Ok((stream, _)) = tokio_tungstenite::connect_async(url.as_str()).await {
+                backoff = PUSH_RETRY_MIN;
+                self.push_connected.store(true, Ordering::Relaxed);
+                self.read_pushes(stream, &url, &on_update).await;
+                self.push_connected.store(false, Ordering::Relaxed);
+            }
+
+            let deadline = Instant::now() + backoff;
+            // 再試行しても無駄なエラーの後は次の再接続までポーリングを止める
+            let mut polling = true;
+            loop {
+                if polling {
+                    if let Err(e) = self.poll_once(&*on_update).await {
+                        polling = e.is_retryable();
+                    }
+                }
+                let now = Instant::now();
+                if now >= deadline || self.socket_url().as_deref() != Some(url.as_str()) {
+                    break;
+                }
+                tokio::time::sleep((deadline - now).min(FALLBACK_POLL_INTERVAL)).await;
+            }
+            backoff = (backoff * 2).min(PUSH_RETRY_MAX);
+        }
+    }
+
+    fn set_conflict(&self, conflict: SyncConflict) {
+        *self.conflict.lock() = Some(conflict);
+    }
+
+    fn conflict(&self) -> Option<SyncConflict> {
+        self.conflict.lock().clone()
+    }
 
-        Ok(json.to_string())
+    fn take_conflict(&self) -> Option<SyncConflict> {
+        self.conflict.lock().take()
     }
 
-    pub fn mark_remote_update(&self, timestamp: i64) -> bool {
+    fn mark_remote_update(&self, timestamp: i64) -> bool {
         let mut guard = self.last_remote_update.lock();
-        if guard.map_or(true, |current| timestamp > current) {
+        if guard.is_none_or(|current| timestamp > current) {
             *guard = Some(timestamp);
             true
         } else {
@@ -232,6 +1466,7 @@ struct RegisterResponse {
     message: String,
     progress: PlayerProgressData,
     last_update: i64,
+    token: String,
 }
 
 #[derive(Debug, Clone)]
@@ -241,4 +1476,5 @@ pub struct RegisterResult {
     pub message: String,
     pub progress: PlayerProgressData,
     pub last_update: i64,
+    pub token: String,
 }
@@ -0,0 +1,597 @@
+// サーバーなしで動くマルチプレイ（オフラインの開発モードと同期・マージのテスト用）。
+// 進行状況・ランキング・軍勢は手元のメモリで扱い、レイドとギルドは機能のない古いサーバーと同じく 404 を返す
+use crate::conflict::SyncConflict;
+use crate::game::{self, PlayerProgressData};
+use crate::multiplayer::{
+    ClockStatus, GuildInfo, GuildLeaderboardPage, LeaderboardEntry, LeaderboardPage,
+    MaintenanceNotice, MultiplayerApi, MultiplayerError, PlayerInfo, PlayerProfile, PlayerSnapshot,
+    RaidBossStatus, RaidLeaderboardPage, RaidReward, RegisterResult, ServerHealth,
+};
+use crate::net_stats::{Diagnostics, NetStats};
+use crate::pvp::ArmySnapshot;
+use crate::season::SeasonInfo;
+use crate::shop::ShopStock;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const NOT_FOUND: MultiplayerError = MultiplayerError::ClientError { status: 404 };
+const UNAUTHORIZED: MultiplayerError = MultiplayerError::ClientError { status: 401 };
+
+struct StoredPlayer {
+    name: String,
+    token: String,
+    progress: PlayerProgressData,
+    last_update: i64,
+    army: Option<ArmySnapshot>,
+}
+
+impl StoredPlayer {
+    fn profile(&self, player_id: &str) -> PlayerProfile {
+        PlayerProfile {
+            player_id: player_id.to_string(),
+            player_name: self.name.clone(),
+            progress: self.progress.clone(),
+            last_update: self.last_update,
+            guild_id: None,
+            season: 0,
+            seasons: Vec::new(),
+        }
+    }
+
+    fn snapshot(&self, player_id: &str) -> PlayerSnapshot {
+        let prestige = self.progress.prestige.clone().unwrap_or_default();
+        let titles = self.progress.titles.clone().unwrap_or_default();
+        let cosmetics = self.progress.cosmetics.clone().unwrap_or_default();
+        PlayerSnapshot {
+            player_id: player_id.to_string(),
+            player_name: self.name.clone(),
+            stage: self.progress.stage,
+            coins: self.progress.coins,
+            best_stage: prestige.best_stage.max(self.progress.stage),
+            prestige_count: prestige.prestige_count,
+            title: titles.active,
+            badges: titles.earned,
+            avatar: cosmetics.avatar,
+            skin: cosmetics.skin,
+            last_update: self.last_update,
+            flagged: false,
+        }
+    }
+}
+
+// 端末をまたいで共有するサーバー側のデータ
+#[derive(Default)]
+pub struct MemoryServer {
+    players: Mutex<HashMap<String, StoredPlayer>>,
+}
+
+impl MemoryServer {
+    // サーバーの時刻は進むだけにする（同じ秒の更新も前回より新しいと分かるように）
+    fn next_update(previous: i64) -> i64 {
+        game::unix_timestamp().max(previous + 1)
+    }
+}
+
+// 1 端末分の接続。同じ MemoryServer に別の端末をつなげば端末間の同期を再現できる
+pub struct InMemoryMultiplayer {
+    server: Arc<MemoryServer>,
+    server_url: Mutex<String>,
+    player_info: Mutex<Option<PlayerInfo>>,
+    token: Mutex<Option<String>>,
+    last_remote_update: Mutex<Option<i64>>,
+    conflict: Mutex<Option<SyncConflict>>,
+    anonymous: AtomicBool,
+    net_stats: NetStats,
+}
+
+impl InMemoryMultiplayer {
+    pub fn new() -> Self {
+        Self::connect(Arc::new(MemoryServer::default()))
+    }
+
+    pub fn connect(server: Arc<MemoryServer>) -> Self {
+        Self {
+            server,
+            server_url: Mutex::new(String::new()),
+            player_info: Mutex::new(None),
+            token: Mutex::new(None),
+            last_remote_update: Mutex::new(None),
+            conflict: Mutex::new(None),
+            anonymous: AtomicBool::new(false),
+            net_stats: NetStats::new(),
+        }
+    }
+
+    // 同じサーバーにつないだ別の端末
+    #[cfg(test)]
+    pub fn another_device(&self) -> Self {
+        Self::connect(Arc::clone(&self.server))
+    }
+
+    fn registered(&self) -> Result<PlayerInfo, MultiplayerError> {
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
+        }
+        self.player_info
+            .lock()
+            .clone()
+            .ok_or(MultiplayerError::NotRegistered)
+    }
+
+    fn signed_in(&self, player_id: &str, player: &StoredPlayer, message: &str) -> RegisterResult {
+        *self.player_info.lock() = Some(PlayerInfo {
+            player_id: player_id.to_string(),
+            player_name: player.name.clone(),
+        });
+        *self.last_remote_update.lock() = Some(player.last_update);
+        *self.token.lock() = Some(player.token.clone());
+        RegisterResult {
+            player_id: player_id.to_string(),
+            player_name: player.name.clone(),
+            message: message.to_string(),
+            progress: player.progress.clone(),
+            last_update: player.last_update,
+            token: player.token.clone(),
+        }
+    }
+}
+
+impl Default for InMemoryMultiplayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn random_hex() -> String {
+    format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+#[async_trait]
+impl MultiplayerApi for InMemoryMultiplayer {
+    fn set_server_url(&self, url: String) {
+        *self.server_url.lock() = url;
+    }
+
+    fn get_server_url(&self) -> String {
+        self.server_url.lock().clone()
+    }
+
+    fn set_token(&self, token: Option<String>) {
+        *self.token.lock() = token.filter(|t| !t.is_empty());
+    }
+
+    fn reset_identity(&self) {
+        *self.player_info.lock() = None;
+        *self.last_remote_update.lock() = None;
+        *self.token.lock() = None;
+        *self.conflict.lock() = None;
+    }
+
+    fn set_anonymous(&self, enabled: bool) {
+        if enabled {
+            self.reset_identity();
+        }
+        self.anonymous.store(enabled, Ordering::Relaxed);
+    }
+
+    fn is_anonymous(&self) -> bool {
+        self.anonymous.load(Ordering::Relaxed)
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        self.net_stats.snapshot()
+    }
+
+    fn reset_diagnostics(&self) {
+        self.net_stats.reset();
+    }
+
+    fn maintenance(&self) -> Option<MaintenanceNotice> {
+        None
+    }
+
+    fn clear_maintenance(&self) -> bool {
+        false
+    }
+
+    fn server_now(&self) -> i64 {
+        game::unix_timestamp()
+    }
+
+    fn clock_status(&self) -> ClockStatus {
+        ClockStatus {
+            server_now: self.server_now(),
+            skew_secs: 0,
+        }
+    }
+
+    // サーバーの URL がなくても動く
+    fn is_connected(&self) -> bool {
+        self.player_info.lock().is_some()
+    }
+
+    fn is_push_connected(&self) -> bool {
+        false
+    }
+
+    fn set_conflict(&self, conflict: SyncConflict) {
+        *self.conflict.lock() = Some(conflict);
+    }
+
+    fn conflict(&self) -> Option<SyncConflict> {
+        self.conflict.lock().clone()
+    }
+
+    fn take_conflict(&self) -> Option<SyncConflict> {
+        self.conflict.lock().take()
+    }
+
+    fn mark_remote_update(&self, timestamp: i64) -> bool {
+        let mut guard = self.last_remote_update.lock();
+        if guard.is_none_or(|current| timestamp > current) {
+            *guard = Some(timestamp);
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn register_player(
+        &self,
+        player_name: String,
+    ) -> Result<RegisterResult, MultiplayerError> {
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
+        }
+        let mut players = self.server.players.lock();
+        let base = player_name.trim().to_string();
+        let taken = |name: &str| {
+            players
+                .values()
+                .any(|player| player.name.eq_ignore_ascii_case(name))
+        };
+        let mut name = base.clone();
+        while taken(&name) {
+            name = format!("{}#{:04}", base, rand::thread_rng().gen_range(0..10000));
+        }
+        let player_id = random_hex();
+        let player = StoredPlayer {
+            name,
+            token: random_hex(),
+            progress: game::GameState::headless().export_progress(),
+            last_update: MemoryServer::next_update(0),
+            army: None,
+        };
+        let result = self.signed_in(&player_id, &player, "Registered");
+        players.insert(player_id, player);
+        Ok(result)
+    }
+
+    async fn login(&self, player_id: String) -> Result<RegisterResult, MultiplayerError> {
+        if self.is_anonymous() {
+            return Err(MultiplayerError::ReadOnly);
+        }
+        let players = self.server.players.lock();
+        let player = players.get(&player_id).ok_or(NOT_FOUND)?;
+        if self.token.lock().as_deref() != Some(player.token.as_str()) {
+            return Err(UNAUTHORIZED);
+        }
+        Ok(self.signed_in(&player_id, player, "Logged in"))
+    }
+
+    async fn sync_progress(
+        &self,
+        progress: &PlayerProgressData,
+    ) -> Result<PlayerProfile, MultiplayerError> {
+        let info = self.registered()?;
+        let mut players = self.server.players.lock();
+        let player = players.get_mut(&info.player_id).ok_or(NOT_FOUND)?;
+        player.progress = progress.clone();
+        player.last_update = MemoryServer::next_update(player.last_update);
+        *self.last_remote_update.lock() = Some(player.last_update);
+        Ok(player.profile(&info.player_id))
+    }
+
+    async fn fetch_profile(&self) -> Result<PlayerProfile, MultiplayerError> {
+        let info = self
+            .player_info
+            .lock()
+            .clone()
+            .ok_or(MultiplayerError::NotRegistered)?;
+        let players = self.server.players.lock();
+        let player = players.get(&info.player_id).ok_or(NOT_FOUND)?;
+        Ok(player.profile(&info.player_id))
+    }
+
+    async fn get_all_players(&self) -> Result<Vec<serde_json::Value>, MultiplayerError> {
+        let players = self.server.players.lock();
+        Ok(players
+            .iter()
+            .map(|(player_id, player)| serde_json::json!(player.snapshot(player_id)))
+            .collect())
+    }
+
+    async fn get_players_batch(
+        &self,
+        player_ids: &[String],
+    ) -> Result<Vec<PlayerSnapshot>, MultiplayerError> {
+        let players = self.server.players.lock();
+        Ok(player_ids
+            .iter()
+            .filter_map(|player_id| {
+                players
+                    .get(player_id)
+                    .map(|player| player.snapshot(player_id))
+            })
+            .collect())
+    }
+
+    async fn get_leaderboard(
+        &self,
+        sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<LeaderboardPage, MultiplayerError> {
+        let page = page.max(1);
+        let page_size = page_size.max(1);
+        let players = self.server.players.lock();
+        let mut snapshots: Vec<PlayerSnapshot> = players
+            .iter()
+            .map(|(player_id, player)| player.snapshot(player_id))
+            .collect();
+        snapshots.sort_by(|a, b| {
+            let order = match sort {
+                "coins" => b.coins.cmp(&a.coins),
+                "last_update" => b.last_update.cmp(&a.last_update),
+                _ => b.stage.cmp(&a.stage),
+            };
+            order.then_with(|| a.player_name.cmp(&b.player_name))
+        });
+        let total = snapshots.len();
+        let entries = snapshots
+            .into_iter()
+            .enumerate()
+            .skip((page - 1).saturating_mul(page_size))
+            .take(page_size)
+            .map(|(index, snapshot)| LeaderboardEntry {
+                rank: index + 1,
+                player_id: snapshot.player_id,
+                player_name: snapshot.player_name,
+                stage: snapshot.stage,
+                coins: snapshot.coins,
+                last_update: snapshot.last_update,
+                flagged: snapshot.flagged,
+                title: snapshot.title,
+                badges: snapshot.badges,
+                avatar: snapshot.avatar,
+                skin: snapshot.skin,
+            })
+            .collect();
+        Ok(LeaderboardPage {
+            entries,
+            page,
+            page_size,
+            total,
+        })
+    }
+
+    async fn get_raid_leaderboard(
+        &self,
+        _raid_id: &str,
+        _page: usize,
+        _page_size: usize,
+    ) -> Result<RaidLeaderboardPage, MultiplayerError> {
+        Err(NOT_FOUND)
+    }
+
+    async fn get_raid_status(&self) -> Result<RaidBossStatus, MultiplayerError> {
+        Err(NOT_FOUND)
+    }
+
+    // シーズンのないサーバーと同じく 0
+    async fn get_season(&self) -> Result<SeasonInfo, MultiplayerError> {
+        Ok(SeasonInfo {
+            season: 0,
+            started_at: 0,
+            ends_at: None,
+            previous: None,
+        })
+    }
+
+    async fn get_shop(&self) -> Result<ShopStock, MultiplayerError> {
+        let now = game::unix_timestamp();
+        Ok(ShopStock {
+            rotation: 0,
+            refreshed_at: now,
+            expires_at: now + 24 * 60 * 60,
+            offers: Vec::new(),
+        })
+    }
+
+    async fn contribute_raid_damage(
+        &self,
+        _damage: u64,
+    ) -> Result<RaidBossStatus, MultiplayerError> {
+        self.registered()?;
+        Err(NOT_FOUND)
+    }
+
+    async fn claim_raid_reward(&self, _raid_id: &str) -> Result<RaidReward, MultiplayerError> {
+        self.registered()?;
+        Err(NOT_FOUND)
+    }
+
+    async fn create_guild(&self, _name: &str) -> Result<GuildInfo, MultiplayerError> {
+        self.registered()?;
+        Err(NOT_FOUND)
+    }
+
+    async fn join_guild(&self, _code: &str) -> Result<GuildInfo, MultiplayerError> {
+        self.registered()?;
+        Err(NOT_FOUND)
+    }
+
+    async fn leave_guild(&self) -> Result<serde_json::Value, MultiplayerError> {
+        self.registered()?;
+        Err(NOT_FOUND)
+    }
+
+    async fn get_guild(&self, _guild_id: &str) -> Result<GuildInfo, MultiplayerError> {
+        Err(NOT_FOUND)
+    }
+
+    async fn get_guild_leaderboard(
+        &self,
+        _sort: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<GuildLeaderboardPage, MultiplayerError> {
+        Ok(GuildLeaderboardPage {
+            entries: Vec::new(),
+            page: page.max(1),
+            page_size: page_size.max(1),
+            total: 0,
+        })
+    }
+
+    async fn upload_army(&self, army: &ArmySnapshot) -> Result<ArmySnapshot, MultiplayerError> {
+        let info = self.registered()?;
+        let mut players = self.server.players.lock();
+        let player = players.get_mut(&info.player_id).ok_or(NOT_FOUND)?;
+        let stored = ArmySnapshot {
+            player_id: info.player_id.clone(),
+            player_name: player.name.clone(),
+            created_at: game::unix_timestamp(),
+            ..army.clone()
+        };
+        player.army = Some(stored.clone());
+        Ok(stored)
+    }
+
+    async fn fetch_army(&self, player_id: &str) -> Result<ArmySnapshot, MultiplayerError> {
+        let players = self.server.players.lock();
+        players
+            .get(player_id)
+            .and_then(|player| player.army.clone())
+            .ok_or(NOT_FOUND)
+    }
+
+    async fn health_check(
+        &self,
+        _server_url: Option<&str>,
+    ) -> Result<ServerHealth, MultiplayerError> {
+        Ok(ServerHealth {
+            status: "ok".to_string(),
+            timestamp: game::unix_timestamp(),
+            player_count: self.server.players.lock().len(),
+            version: Some("in-memory".to_string()),
+            uptime_secs: None,
+            storage: None,
+            capacity: None,
+            maintenance: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflict::{self, Divergence};
+    use crate::game::GameState;
+
+    fn progress(stage: u32, coins: u64) -> PlayerProgressData {
+        let mut progress = GameState::headless().export_progress();
+        progress.stage = stage;
+        progress.coins = coins;
+        progress
+    }
+
+    #[test]
+    fn second_device_logs_in_and_pulls_synced_progress() {
+        tauri::async_runtime::block_on(async {
+            let laptop = InMemoryMultiplayer::new();
+            let desktop = laptop.another_device();
+
+            let registered = laptop.register_player("Alice".to_string()).await.unwrap();
+            laptop.sync_progress(&progress(5, 300)).await.unwrap();
+
+            // トークンが違えば入れない
+            desktop.set_token(Some("wrong".to_string()));
+            assert_eq!(
+                desktop
+                    .login(registered.player_id.clone())
+                    .await
+                    .unwrap_err(),
+                UNAUTHORIZED
+            );
+            desktop.set_token(Some(registered.token.clone()));
+            let login = desktop.login(registered.player_id.clone()).await.unwrap();
+            assert_eq!(login.progress.stage, 5);
+
+            // もう一方の端末の同期は次のポーリングで一度だけ届く
+            desktop.sync_progress(&progress(8, 900)).await.unwrap();
+            let received = Mutex::new(Vec::new());
+            let on_update = |profile: PlayerProfile| received.lock().push(profile.progress.stage);
+            laptop.poll_once(&on_update).await.unwrap();
+            laptop.poll_once(&on_update).await.unwrap();
+            assert_eq!(*received.lock(), vec![8]);
+        });
+    }
+
+    #[test]
+    fn offline_progress_on_both_devices_is_detected_as_diverged() {
+        tauri::async_runtime::block_on(async {
+            let laptop = InMemoryMultiplayer::new();
+            let desktop = laptop.another_device();
+            let registered = laptop.register_player("Bob".to_string()).await.unwrap();
+            desktop.set_token(Some(registered.token.clone()));
+            desktop.login(registered.player_id).await.unwrap();
+
+            // 片方はステージ、もう片方はコインだけ進めて同期する
+            let laptop_local = progress(12, 100);
+            desktop.sync_progress(&progress(3, 5000)).await.unwrap();
+            let remote = laptop.fetch_profile().await.unwrap();
+            assert_eq!(
+                conflict::compare(&laptop_local, &remote.progress),
+                Divergence::Diverged
+            );
+
+            // 匿名モードでは送れない
+            laptop.set_anonymous(true);
+            assert_eq!(
+                laptop.sync_progress(&laptop_local).await.unwrap_err(),
+                MultiplayerError::ReadOnly
+            );
+        });
+    }
+
+    #[test]
+    fn leaderboard_ranks_players_by_requested_sort() {
+        tauri::async_runtime::block_on(async {
+            let server = Arc::new(MemoryServer::default());
+            for (name, stage, coins) in [("A", 3, 900), ("B", 9, 100), ("C", 6, 500)] {
+                let device = InMemoryMultiplayer::connect(Arc::clone(&server));
+                device.register_player(name.to_string()).await.unwrap();
+                device.sync_progress(&progress(stage, coins)).await.unwrap();
+            }
+            let viewer = InMemoryMultiplayer::connect(server);
+
+            let by_stage = viewer.get_leaderboard("stage", 1, 2).await.unwrap();
+            let names: Vec<_> = by_stage
+                .entries
+                .iter()
+                .map(|e| e.player_name.as_str())
+                .collect();
+            assert_eq!(names, vec!["B", "C"]);
+            assert_eq!(by_stage.total, 3);
+
+            let by_coins = viewer.get_leaderboard("coins", 2, 2).await.unwrap();
+            assert_eq!(by_coins.entries[0].player_name, "B");
+            assert_eq!(by_coins.entries[0].rank, 3);
+        });
+    }
+}
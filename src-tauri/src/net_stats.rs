@@ -0,0 +1,100 @@
+use crate::game::unix_timestamp;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// エンドポイントごとの通信量と結果（バイト数は本文だけ。ヘッダーは含めない）
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct EndpointStats {
+    pub requests: u64,
+    pub failures: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub last_status: Option<u16>,
+    pub last_request_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    // 集計を始めた時刻（起動時かリセット時）
+    pub since: i64,
+    pub requests: u64,
+    pub failures: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub endpoints: BTreeMap<&'static str, EndpointStats>,
+}
+
+// MultiplayerClient の送受信を数える（保存はせず、起動ごとに 0 から）
+pub struct NetStats {
+    since: Mutex<i64>,
+    endpoints: Mutex<BTreeMap<&'static str, EndpointStats>>,
+}
+
+impl NetStats {
+    pub fn new() -> Self {
+        Self {
+            since: Mutex::new(unix_timestamp()),
+            endpoints: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // status が None なら応答が無かった（タイムアウト・接続失敗）
+    pub fn record(
+        &self,
+        endpoint: &'static str,
+        bytes_sent: u64,
+        bytes_received: u64,
+        status: Option<u16>,
+        error: Option<String>,
+    ) {
+        let now = unix_timestamp();
+        let mut endpoints = self.endpoints.lock();
+        let stats = endpoints.entry(endpoint).or_default();
+        stats.requests += 1;
+        stats.bytes_sent = stats.bytes_sent.saturating_add(bytes_sent);
+        stats.bytes_received = stats.bytes_received.saturating_add(bytes_received);
+        stats.last_request_at = Some(now);
+        if status.is_some() {
+            stats.last_status = status;
+        }
+        if let Some(error) = error {
+            stats.failures += 1;
+            stats.last_error = Some(error);
+            stats.last_error_at = Some(now);
+        }
+    }
+
+    // WebSocket で受け取った通知（リクエストは数えない）
+    pub fn record_push(&self, endpoint: &'static str, bytes_received: u64) {
+        let mut endpoints = self.endpoints.lock();
+        let stats = endpoints.entry(endpoint).or_default();
+        stats.bytes_received = stats.bytes_received.saturating_add(bytes_received);
+        stats.last_request_at = Some(unix_timestamp());
+    }
+
+    pub fn snapshot(&self) -> Diagnostics {
+        let endpoints = self.endpoints.lock().clone();
+        let sum = |field: fn(&EndpointStats) -> u64| {
+            endpoints
+                .values()
+                .map(field)
+                .fold(0u64, u64::saturating_add)
+        };
+        Diagnostics {
+            since: *self.since.lock(),
+            requests: sum(|stats| stats.requests),
+            failures: sum(|stats| stats.failures),
+            bytes_sent: sum(|stats| stats.bytes_sent),
+            bytes_received: sum(|stats| stats.bytes_received),
+            endpoints,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.endpoints.lock().clear();
+        *self.since.lock() = unix_timestamp();
+    }
+}
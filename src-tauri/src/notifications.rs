@@ -0,0 +1,220 @@
+use crate::achievements::AchievementStatus;
+use crate::auto_sync::SyncStatus;
+use crate::config::AppConfig;
+use crate::events;
+use crate::game::unix_timestamp;
+use crate::i18n;
+use crate::loop_control::LoopControl;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+use ts_rs::TS;
+
+// 基地の体力がこの割合を下回ったら知らせる（回復して上回るまで再通知しない）
+const BASE_DANGER_RATIO: f32 = 0.2;
+// 画面に送られないまま溜まる通知の上限（シミュレーション用の複製など）
+const MAX_PENDING: usize = 32;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    StageClear,
+    BaseDanger,
+    Achievement,
+    AutoBuy,
+    Sync,
+    Invasion,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+// "notification" で送る。個別のイベント（"achievement-unlocked" など）はそのまま残す
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct Notification {
+    pub category: NotificationCategory,
+    pub severity: Severity,
+    pub title: String,
+    pub body: String,
+    #[ts(type = "number")]
+    pub at: i64,
+}
+
+impl Notification {
+    // title は文言のキー（本文は呼び出し側で訳す）
+    fn new(category: NotificationCategory, severity: Severity, title: &str, body: String) -> Self {
+        Self {
+            category,
+            severity,
+            title: i18n::text(title),
+            body,
+            at: unix_timestamp(),
+        }
+    }
+
+    pub fn stage_clear(stage: u32) -> Self {
+        Self::new(
+            NotificationCategory::StageClear,
+            Severity::Success,
+            "notification.stage_clear.title",
+            i18n::format("notification.stage_clear.body", &[("stage", &stage)]),
+        )
+    }
+
+    pub fn base_danger(hp: f32, max_hp: f32) -> Self {
+        Self::new(
+            NotificationCategory::BaseDanger,
+            Severity::Warning,
+            "notification.base_danger.title",
+            i18n::format(
+                "notification.base_danger.body",
+                &[("percent", &(hp / max_hp * 100.0))],
+            ),
+        )
+    }
+
+    pub fn achievement(achievement: &AchievementStatus) -> Self {
+        Self::new(
+            NotificationCategory::Achievement,
+            Severity::Success,
+            "notification.achievement.title",
+            achievement.name.clone(),
+        )
+    }
+
+    pub fn auto_buy_expired() -> Self {
+        Self::new(
+            NotificationCategory::AutoBuy,
+            Severity::Info,
+            "notification.auto_buy.title",
+            i18n::text("notification.auto_buy.body"),
+        )
+    }
+
+    pub fn invasion_warning(enemies: u32, arrives_in: f32) -> Self {
+        Self::new(
+            NotificationCategory::Invasion,
+            Severity::Warning,
+            "notification.invasion_warning.title",
+            i18n::format(
+                "notification.invasion_warning.body",
+                &[("enemies", &enemies), ("seconds", &arrives_in)],
+            ),
+        )
+    }
+
+    pub fn invasion_repelled(coins: u64) -> Self {
+        Self::new(
+            NotificationCategory::Invasion,
+            Severity::Success,
+            "notification.invasion_repelled.title",
+            i18n::format("notification.invasion_repelled.body", &[("coins", &coins)]),
+        )
+    }
+
+    // 連続した失敗のうち最初の 1 回だけ
+    pub fn sync_failed(status: &SyncStatus) -> Option<Self> {
+        let error = status.error.as_ref()?;
+        (status.consecutive_failures == 1).then(|| {
+            Self::new(
+                NotificationCategory::Sync,
+                Severity::Error,
+                "notification.sync_failed.title",
+                error.message(),
+            )
+        })
+    }
+}
+
+// OS の通知を出すカテゴリ（画面への "notification" は常に送る）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub stage_clear: bool,
+    pub base_danger: bool,
+    pub achievement: bool,
+    pub auto_buy: bool,
+    pub sync: bool,
+    pub invasion: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            // 数分おきに起きるので既定では出さない
+            stage_clear: false,
+            base_danger: true,
+            achievement: true,
+            auto_buy: true,
+            sync: true,
+            invasion: true,
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn native_enabled(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::StageClear => self.stage_clear,
+            NotificationCategory::BaseDanger => self.base_danger,
+            NotificationCategory::Achievement => self.achievement,
+            NotificationCategory::AutoBuy => self.auto_buy,
+            NotificationCategory::Sync => self.sync,
+            NotificationCategory::Invasion => self.invasion,
+        }
+    }
+}
+
+// ゲームの更新中に起きた通知をゲームループで送るまで溜める
+#[derive(Clone, Default)]
+pub struct Notifications {
+    pending: Vec<Notification>,
+    base_warned: bool,
+}
+
+impl Notifications {
+    pub fn push(&mut self, notification: Notification) {
+        if self.pending.len() < MAX_PENDING {
+            self.pending.push(notification);
+        }
+    }
+
+    pub fn check_base(&mut self, hp: f32, max_hp: f32) {
+        let danger = max_hp > 0.0 && hp > 0.0 && hp < max_hp * BASE_DANGER_RATIO;
+        if danger && !self.base_warned {
+            self.push(Notification::base_danger(hp, max_hp));
+        }
+        self.base_warned = danger;
+    }
+
+    pub fn take_pending(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// 控えめモード中は OS の通知を出さない（画面側の履歴には残す）
+pub fn dispatch(app: &AppHandle, notification: Notification) {
+    let muted = app
+        .try_state::<Arc<LoopControl>>()
+        .is_some_and(|loop_control| loop_control.is_low_distraction());
+    if !muted
+        && AppConfig::load()
+            .notifications
+            .native_enabled(notification.category)
+    {
+        let _ = app
+            .notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body)
+            .show();
+    }
+    events::emit::<events::Notification>(app, &notification);
+}
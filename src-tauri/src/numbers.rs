@@ -0,0 +1,93 @@
+use serde::Deserialize;
+
+// 大きな数値の省略表記に使う接尾辞（1000 ごと）
+const SUFFIXES: [&str; 7] = ["", "K", "M", "B", "T", "Qa", "Qi"];
+
+// 1234 -> "1.23K" のように有効数字3桁で表す
+pub fn format_compact(value: u64) -> String {
+    if value < 1000 {
+        return value.to_string();
+    }
+    let mut scaled = value as f64;
+    let mut index = 0;
+    while scaled >= 1000.0 && index < SUFFIXES.len() - 1 {
+        scaled /= 1000.0;
+        index += 1;
+    }
+    // 丸めで 1000 に繰り上がった場合は次の単位へ
+    if scaled >= 999.5 && index < SUFFIXES.len() - 1 {
+        scaled /= 1000.0;
+        index += 1;
+    }
+    let decimals = if scaled < 10.0 {
+        2
+    } else if scaled < 100.0 {
+        1
+    } else {
+        0
+    };
+    format!("{:.*}{}", decimals, scaled, SUFFIXES[index])
+}
+
+// 状態の送信など、呼び出し側がロケールを指定しない場合に使う
+pub const DEFAULT_LOCALE: &str = "en";
+
+#[derive(Clone, Copy, Debug, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberStyle {
+    #[default]
+    Compact,
+    Grouped,
+    Duration,
+}
+
+// 桁区切りの記号（"de-DE" のような地域付きの指定は言語部分で判定）
+fn group_separator(locale: &str) -> &'static str {
+    match locale.split(['-', '_']).next().unwrap_or_default() {
+        "de" | "es" | "id" | "it" | "nl" | "pt" | "tr" => ".",
+        "cs" | "fi" | "fr" | "nb" | "pl" | "ru" | "sv" | "uk" => "\u{202f}",
+        "ch" => "'",
+        _ => ",",
+    }
+}
+
+// 1234567 -> "1,234,567"
+pub fn format_grouped(value: u64, locale: &str) -> String {
+    let digits = value.to_string();
+    let separator = group_separator(locale);
+    let mut out = String::with_capacity(digits.len() * 2);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push_str(separator);
+        }
+        out.push(c);
+    }
+    out
+}
+
+// 秒数を "2d 3h" / "1h 05m" / "4m 09s" / "12s" の形にする（上位2単位まで）
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes, seconds) = (
+        secs / 86_400,
+        secs % 86_400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+pub fn format(value: u64, style: NumberStyle, locale: &str) -> String {
+    match style {
+        NumberStyle::Compact => format_compact(value),
+        NumberStyle::Grouped => format_grouped(value, locale),
+        NumberStyle::Duration => format_duration(value),
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+// 1 回の更新で進める最大の秒数（最低の更新頻度・放置中の約 1 秒ごとの更新より少し長い）
+pub const MAX_TICK_DELTA: f32 = 1.5;
+// これより長く更新が空いたらスリープ・休止から復帰したとみなす
+pub const SLEEP_THRESHOLD: f32 = 10.0;
+// 不在中の進行として換算する上限
+pub const MAX_OFFLINE_SECS: f32 = 8.0 * 60.0 * 60.0;
+// 不在中は入力がないので、出現するはずだった敵の半分を倒した扱いにする
+pub const OFFLINE_EFFICIENCY: f64 = 0.5;
+
+// 前回の更新からの経過秒数を、シミュレーションに渡す秒数と不在扱いにする秒数に分ける
+// （スリープ中も進む時計の OS では復帰直後に巨大な経過時間が返る）
+pub fn split_elapsed(elapsed: f32, tick: f32) -> (f32, f32) {
+    if !elapsed.is_finite() || elapsed < 0.0 {
+        return (0.0, 0.0);
+    }
+    if elapsed > SLEEP_THRESHOLD {
+        let delta = tick.min(MAX_TICK_DELTA);
+        return (delta, (elapsed - delta).min(MAX_OFFLINE_SECS));
+    }
+    // 一時的な引っかかりは上限で切り捨てる
+    (elapsed.min(MAX_TICK_DELTA), 0.0)
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct OfflineProgress {
+    pub seconds: f32,
+    #[ts(type = "number")]
+    pub enemies_defeated: u64,
+    #[ts(type = "number")]
+    pub coins: u64,
+}
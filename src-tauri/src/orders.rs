@@ -0,0 +1,69 @@
+use crate::game::{Unit, UnitType};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 命令の既定の長さと上限（秒）。切れたら AI の判断に戻る
+pub const DEFAULT_DURATION: f32 = 15.0;
+pub const MAX_DURATION: f32 = 120.0;
+// 移動先に着いたとみなす距離
+pub const ARRIVE_RADIUS: f32 = 10.0;
+
+// 手動の命令（放置中は出さない限り何も変わらない）
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Order {
+    // 敵に構わず指定位置まで進み、着いたら近くの敵にだけ応戦して待機する
+    Move { position: f32 },
+    // 指定した敵を倒すまで狙い続ける
+    Attack { target_id: u32 },
+}
+
+// ユニットごとに持つ命令と残り時間
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+pub struct UnitOrder {
+    pub order: Order,
+    pub remaining: f32,
+}
+
+impl UnitOrder {
+    // 期限が切れたら false
+    pub fn tick(&mut self, delta: f32) -> bool {
+        self.remaining -= delta;
+        self.remaining > 0.0
+    }
+
+    pub fn destination(&self) -> Option<f32> {
+        match self.order {
+            Order::Move { position } => Some(position),
+            Order::Attack { .. } => None,
+        }
+    }
+}
+
+// 味方を選ぶ条件。指定しなかった条件では絞り込まない
+#[derive(Clone, Deserialize, Debug, Default)]
+pub struct UnitSelector {
+    #[serde(default)]
+    pub unit_types: Vec<UnitType>,
+    // 範囲選択の両端（x 座標）
+    #[serde(default)]
+    pub from: Option<f32>,
+    #[serde(default)]
+    pub to: Option<f32>,
+    #[serde(default)]
+    pub lane: Option<u8>,
+}
+
+impl UnitSelector {
+    pub fn matches(&self, unit: &Unit) -> bool {
+        // 範囲は左右どちらからドラッグしても同じ
+        let (from, to) = match (self.from, self.to) {
+            (Some(a), Some(b)) => (Some(a.min(b)), Some(a.max(b))),
+            range => range,
+        };
+        (self.unit_types.is_empty() || self.unit_types.contains(&unit.unit_type))
+            && from.is_none_or(|from| unit.position >= from)
+            && to.is_none_or(|to| unit.position <= to)
+            && self.lane.is_none_or(|lane| unit.lane == lane)
+    }
+}
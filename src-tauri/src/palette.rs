@@ -0,0 +1,402 @@
+use crate::error::GameError;
+use crate::game::{GameState, UnitType, UPGRADE_OPTIONS};
+use crate::macros;
+use crate::stance::Stance;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 1 回の buy で買える上限（打ち間違いで全財産を使わないよう）
+const MAX_BUY_COUNT: u32 = 100;
+
+// ホットキーで実行するコマンド（例: "Ctrl+Alt+B" や "Ctrl+K, B" → "buy attack small x5"）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CommandHotkey {
+    pub hotkey: String,
+    pub command: String,
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub examples: &'static [&'static str],
+}
+
+// コマンドパレットとホットキーで使えるコマンド
+const COMMANDS: [CommandHelp; 8] = [
+    CommandHelp {
+        name: "buy",
+        usage: "buy <upgrade> [small|medium|large] [xN]",
+        description: "Buy an upgrade, optionally several times in a row",
+        examples: &["buy attack small x5", "buy coin_rate"],
+    },
+    CommandHelp {
+        name: "summon",
+        usage: "summon <small|medium|large|hero>",
+        description: "Summon a unit for coins",
+        examples: &["summon hero"],
+    },
+    CommandHelp {
+        name: "rally",
+        usage: "rally <position> [threshold] | rally clear | rally release",
+        description: "Set, clear or release the rally point",
+        examples: &["rally 300", "rally 300 20", "rally release"],
+    },
+    CommandHelp {
+        name: "stance",
+        usage: "stance <aggressive|defensive|hold <position>>",
+        description: "Change what idle units do",
+        examples: &["stance defensive", "stance hold 400"],
+    },
+    CommandHelp {
+        name: "barracks",
+        usage: "barracks <position>",
+        description: "Build the forward barracks",
+        examples: &["barracks 350"],
+    },
+    CommandHelp {
+        name: "advance",
+        usage: "advance",
+        description: "Advance to the next stage",
+        examples: &["advance"],
+    },
+    CommandHelp {
+        name: "macro",
+        usage: "macro <name>",
+        description: "Run a recorded macro",
+        examples: &["macro opener"],
+    },
+    CommandHelp {
+        name: "help",
+        usage: "help [command]",
+        description: "List commands or show one command's usage",
+        examples: &["help", "help buy"],
+    },
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteCommand {
+    Buy {
+        upgrade_type: String,
+        unit_type: String,
+        count: u32,
+    },
+    Summon {
+        unit_type: UnitType,
+    },
+    Rally {
+        position: f32,
+        threshold: Option<u32>,
+    },
+    ClearRally,
+    ReleaseRally,
+    Stance {
+        stance: Stance,
+    },
+    Barracks {
+        position: f32,
+    },
+    Advance,
+    Macro {
+        name: String,
+    },
+    Help {
+        command: Option<String>,
+    },
+}
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct CommandOutcome {
+    // 入力された文字列
+    pub command: String,
+    pub message: String,
+    // help のときだけ
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub help: Vec<CommandHelp>,
+}
+
+pub fn help() -> Vec<CommandHelp> {
+    COMMANDS.to_vec()
+}
+
+fn usage_error(name: &str) -> GameError {
+    let usage = COMMANDS
+        .iter()
+        .find(|help| help.name == name)
+        .map_or("", |help| help.usage);
+    GameError::InvalidCommandArguments {
+        usage: usage.to_string(),
+    }
+}
+
+fn parse_unit(word: &str) -> Option<UnitType> {
+    match word {
+        "small" | "s" => Some(UnitType::Small),
+        "medium" | "m" => Some(UnitType::Medium),
+        "large" | "l" => Some(UnitType::Large),
+        "hero" => Some(UnitType::Hero),
+        _ => None,
+    }
+}
+
+fn parse_position(word: &str, name: &str) -> Result<f32, GameError> {
+    word.parse::<f32>()
+        .ok()
+        .filter(|position| position.is_finite())
+        .ok_or_else(|| usage_error(name))
+}
+
+// 大文字小文字は区別しない。余分な引数はエラー
+pub fn parse(text: &str) -> Result<PaletteCommand, GameError> {
+    let lowered = text.trim().to_lowercase();
+    let mut words = lowered.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let command = match (name, args.as_slice()) {
+        ("buy", [upgrade_type, rest @ ..]) => {
+            let (unit_type, rest) = match rest {
+                [unit, rest @ ..] if !unit.starts_with('x') => (*unit, rest),
+                _ => ("", rest),
+            };
+            let count = match rest {
+                [] => 1,
+                [count] => count
+                    .strip_prefix('x')
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .filter(|n| (1..=MAX_BUY_COUNT).contains(n))
+                    .ok_or_else(|| usage_error(name))?,
+                _ => return Err(usage_error(name)),
+            };
+            let valid = UPGRADE_OPTIONS.iter().any(|&(u, t)| {
+                u == *upgrade_type && (t == unit_type || (t.is_empty() && unit_type.is_empty()))
+            });
+            if !valid {
+                return Err(GameError::InvalidUpgrade {
+                    upgrade_type: format!("{} {}", upgrade_type, unit_type).trim().to_string(),
+                });
+            }
+            PaletteCommand::Buy {
+                upgrade_type: upgrade_type.to_string(),
+                unit_type: unit_type.to_string(),
+                count,
+            }
+        }
+        ("summon", [unit]) => PaletteCommand::Summon {
+            unit_type: parse_unit(unit).ok_or_else(|| usage_error(name))?,
+        },
+        ("rally", ["clear"]) => PaletteCommand::ClearRally,
+        ("rally", ["release"]) => PaletteCommand::ReleaseRally,
+        ("rally", [position]) => PaletteCommand::Rally {
+            position: parse_position(position, name)?,
+            threshold: None,
+        },
+        ("rally", [position, threshold]) => PaletteCommand::Rally {
+            position: parse_position(position, name)?,
+            threshold: Some(threshold.parse().map_err(|_| usage_error(name))?),
+        },
+        ("stance", ["aggressive"]) => PaletteCommand::Stance {
+            stance: Stance::Aggressive,
+        },
+        ("stance", ["defensive"]) => PaletteCommand::Stance {
+            stance: Stance::Defensive,
+        },
+        ("stance", ["hold", position]) => PaletteCommand::Stance {
+            stance: Stance::Hold {
+                position: parse_position(position, name)?,
+            },
+        },
+        ("barracks", [position]) => PaletteCommand::Barracks {
+            position: parse_position(position, name)?,
+        },
+        ("advance", []) => PaletteCommand::Advance,
+        // マクロ名は大文字小文字を区別するので元の文字列から取る
+        ("macro", [_]) => PaletteCommand::Macro {
+            name: text.split_whitespace().nth(1).unwrap_or("").to_string(),
+        },
+        ("help", []) => PaletteCommand::Help { command: None },
+        ("help", [command]) => PaletteCommand::Help {
+            command: Some(command.to_string()),
+        },
+        _ if COMMANDS.iter().any(|help| help.name == name) => return Err(usage_error(name)),
+        _ => {
+            return Err(GameError::UnknownCommand {
+                name: name.to_string(),
+            })
+        }
+    };
+    Ok(command)
+}
+
+// 解釈して実行する
+pub fn run(text: &str, game: &mut GameState) -> Result<CommandOutcome, GameError> {
+    let (message, help) = parse(text)?.execute(game)?;
+    Ok(CommandOutcome {
+        command: text.trim().to_string(),
+        message,
+        help,
+    })
+}
+
+impl PaletteCommand {
+    // 結果のメッセージと、help のときはその内容
+    fn execute(&self, game: &mut GameState) -> Result<(String, Vec<CommandHelp>), GameError> {
+        let mut help = Vec::new();
+        let message = match self {
+            PaletteCommand::Buy {
+                upgrade_type,
+                unit_type,
+                count,
+            } => {
+                // 買えなくなったらそこで止める（1 つも買えなければエラー）
+                let mut bought = 0;
+                for _ in 0..*count {
+                    match game.purchase_upgrade(upgrade_type, unit_type) {
+                        Ok(_) => bought += 1,
+                        Err(e) if bought == 0 => return Err(e),
+                        Err(_) => break,
+                    }
+                }
+                format!("Bought {}/{}", bought, count)
+            }
+            PaletteCommand::Summon { unit_type } => {
+                let cost = game.summon_unit(*unit_type)?;
+                format!("Summoned {:?} for {} coins", unit_type, cost)
+            }
+            PaletteCommand::Rally {
+                position,
+                threshold,
+            } => {
+                game.set_rally_point(*position, *threshold)?;
+                format!("Rally point set at {}", position)
+            }
+            PaletteCommand::ClearRally => {
+                game.clear_rally_point();
+                "Rally point cleared".to_string()
+            }
+            PaletteCommand::ReleaseRally => {
+                format!("Released {} units", game.release_rally())
+            }
+            PaletteCommand::Stance { stance } => {
+                game.set_stance(*stance)?;
+                format!("Stance set to {:?}", stance)
+            }
+            PaletteCommand::Barracks { position } => {
+                game.build_barracks(*position)?;
+                format!("Barracks built at {}", position)
+            }
+            PaletteCommand::Advance => {
+                game.advance_stage()?;
+                format!("Advanced to stage {}", game.stage)
+            }
+            PaletteCommand::Macro { name } => {
+                let result = macros::run_macro(name, game)?;
+                if let Some(error) = result.error {
+                    return Err(error);
+                }
+                format!("Ran {} actions", result.completed)
+            }
+            PaletteCommand::Help { command } => {
+                help = match command {
+                    Some(command) => COMMANDS
+                        .iter()
+                        .filter(|help| help.name == command)
+                        .cloned()
+                        .collect(),
+                    None => self::help(),
+                };
+                if help.is_empty() {
+                    return Err(GameError::UnknownCommand {
+                        name: command.clone().unwrap_or_default(),
+                    });
+                }
+                help.iter()
+                    .map(|help| help.usage)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        };
+        Ok((message, help))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_case_insensitively() {
+        assert_eq!(
+            parse("  Buy Attack Small x5 ").unwrap(),
+            PaletteCommand::Buy {
+                upgrade_type: "attack".to_string(),
+                unit_type: "small".to_string(),
+                count: 5,
+            }
+        );
+        assert_eq!(
+            parse("buy coin_rate").unwrap(),
+            PaletteCommand::Buy {
+                upgrade_type: "coin_rate".to_string(),
+                unit_type: String::new(),
+                count: 1,
+            }
+        );
+        assert_eq!(
+            parse("SUMMON l").unwrap(),
+            PaletteCommand::Summon {
+                unit_type: UnitType::Large,
+            }
+        );
+        assert_eq!(
+            parse("rally 0.5 10").unwrap(),
+            PaletteCommand::Rally {
+                position: 0.5,
+                threshold: Some(10),
+            }
+        );
+        assert_eq!(
+            parse("stance hold 0.25").unwrap(),
+            PaletteCommand::Stance {
+                stance: Stance::Hold { position: 0.25 },
+            }
+        );
+        // マクロ名だけは大文字小文字をそのまま残す
+        assert_eq!(
+            parse("Macro OpenerA").unwrap(),
+            PaletteCommand::Macro {
+                name: "OpenerA".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_commands_report_their_usage() {
+        let usage = |name: &str| usage_error(name);
+        assert_eq!(parse("buy attack small x0").unwrap_err(), usage("buy"));
+        assert_eq!(parse("buy attack small x101").unwrap_err(), usage("buy"));
+        assert_eq!(parse("buy attack small x2 x3").unwrap_err(), usage("buy"));
+        assert_eq!(parse("rally nan").unwrap_err(), usage("rally"));
+        assert_eq!(parse("rally 0.5 many").unwrap_err(), usage("rally"));
+        assert_eq!(parse("advance now").unwrap_err(), usage("advance"));
+        assert_eq!(parse("summon dragon").unwrap_err(), usage("summon"));
+        assert_eq!(
+            parse("buy attack").unwrap_err(),
+            GameError::InvalidUpgrade {
+                upgrade_type: "attack".to_string(),
+            }
+        );
+        assert_eq!(
+            parse("dance").unwrap_err(),
+            GameError::UnknownCommand {
+                name: "dance".to_string(),
+            }
+        );
+        assert_eq!(
+            parse("   ").unwrap_err(),
+            GameError::UnknownCommand {
+                name: String::new(),
+            }
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+// 攻城型が足を止めて相手をする距離（これより遠い味方は無視して基地へ向かう）
+pub const SIEGE_ENGAGE_RADIUS: f32 = 40.0;
+// 消耗型が弱った味方を探す距離
+pub const ATTRITION_SCAN_RADIUS: f32 = 200.0;
+
+// 敵の出現ペースと狙い方（ステージ帯ごとに stage.rs で決まる）
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnemyPersonality {
+    // 短い間隔で絶えず送り込み、最寄りの味方に突撃する
+    Rush,
+    // 間隔を空けてまとめて送り込み、道を塞ぐ味方以外には構わず基地を目指す
+    Siege,
+    // 通常のペースで送り込み、近くで最も弱っている味方を狙う
+    Attrition,
+}
+
+impl EnemyPersonality {
+    pub fn spawn_interval_multiplier(self) -> f32 {
+        match self {
+            Self::Rush => 0.6,
+            Self::Siege => 2.5,
+            Self::Attrition => 1.0,
+        }
+    }
+
+    // 一度に出現する数
+    pub fn spawn_burst(self) -> u32 {
+        match self {
+            Self::Siege => 3,
+            Self::Rush | Self::Attrition => 1,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error::GameError;
+use crate::shop;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 転生に必要な到達ステージ
+pub const PRESTIGE_MIN_STAGE: u32 = 20;
+
+// 永続強化1段階あたりの効果（％）
+const ATTACK_PER_LEVEL: f32 = 5.0;
+const HP_PER_LEVEL: f32 = 5.0;
+const COIN_PER_LEVEL: f64 = 10.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, TS)]
+pub struct PrestigeUpgrades {
+    pub attack: u32,
+    pub hp: u32,
+    pub coin: u32,
+}
+
+impl PrestigeUpgrades {
+    pub fn attack_multiplier(&self) -> f32 {
+        1.0 + self.attack as f32 * ATTACK_PER_LEVEL / 100.0
+    }
+
+    pub fn hp_multiplier(&self) -> f32 {
+        1.0 + self.hp as f32 * HP_PER_LEVEL / 100.0
+    }
+
+    pub fn coin_multiplier(&self) -> f64 {
+        1.0 + self.coin as f64 * COIN_PER_LEVEL / 100.0
+    }
+
+    fn level_mut(&mut self, kind: &str) -> Option<&mut u32> {
+        match kind {
+            "attack" => Some(&mut self.attack),
+            "hp" => Some(&mut self.hp),
+            "coin" => Some(&mut self.coin),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, TS)]
+pub struct PrestigeState {
+    // 転生ポイント（未使用分）
+    #[ts(type = "number")]
+    pub points: u64,
+    #[ts(type = "number")]
+    pub total_points_earned: u64,
+    pub prestige_count: u32,
+    // 今回の周回での最高到達ステージ
+    pub highest_stage: u32,
+    // 全周回を通した最高到達ステージ
+    pub best_stage: u32,
+    pub upgrades: PrestigeUpgrades,
+    // 転生ショップで買った品の ID（転生しても残る）
+    #[serde(default)]
+    pub shop_items: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PrestigeStatus {
+    pub state: PrestigeState,
+    pub can_prestige: bool,
+    // 今転生した場合に得られるポイント
+    pub pending_points: u64,
+    pub min_stage: u32,
+}
+
+// 最高到達ステージから獲得ポイントを計算（高ステージほど伸びる）
+pub fn points_for_stage(stage: u32) -> u64 {
+    if stage < PRESTIGE_MIN_STAGE {
+        return 0;
+    }
+    (stage as f64 / 10.0).powf(1.5).floor() as u64
+}
+
+// 次の段階に必要なポイント
+pub fn upgrade_cost(level: u32) -> u64 {
+    level as u64 + 1
+}
+
+impl PrestigeState {
+    // 永続強化とショップの品を合わせた倍率
+    pub fn attack_multiplier(&self) -> f32 {
+        self.upgrades.attack_multiplier() * shop::artifact_multipliers(&self.shop_items).0
+    }
+
+    pub fn hp_multiplier(&self) -> f32 {
+        self.upgrades.hp_multiplier() * shop::artifact_multipliers(&self.shop_items).1
+    }
+
+    pub fn coin_multiplier(&self) -> f64 {
+        self.upgrades.coin_multiplier() * shop::artifact_multipliers(&self.shop_items).2
+    }
+
+    pub fn record_stage(&mut self, stage: u32) {
+        self.highest_stage = self.highest_stage.max(stage);
+        self.best_stage = self.best_stage.max(stage);
+    }
+
+    pub fn status(&self) -> PrestigeStatus {
+        PrestigeStatus {
+            state: self.clone(),
+            can_prestige: self.highest_stage >= PRESTIGE_MIN_STAGE,
+            pending_points: points_for_stage(self.highest_stage),
+            min_stage: PRESTIGE_MIN_STAGE,
+        }
+    }
+
+    // 転生してポイントを受け取る。周回の最高到達ステージはリセット
+    pub fn claim(&mut self) -> Result<u64, GameError> {
+        if self.highest_stage < PRESTIGE_MIN_STAGE {
+            return Err(GameError::PrestigeLocked {
+                min_stage: PRESTIGE_MIN_STAGE,
+            });
+        }
+        let points = points_for_stage(self.highest_stage);
+        self.points += points;
+        self.total_points_earned += points;
+        self.prestige_count += 1;
+        self.highest_stage = 1;
+        Ok(points)
+    }
+
+    pub fn purchase(&mut self, kind: &str) -> Result<(), GameError> {
+        let points = self.points;
+        let level =
+            self.upgrades
+                .level_mut(kind)
+                .ok_or_else(|| GameError::InvalidPrestigeUpgrade {
+                    kind: kind.to_string(),
+                })?;
+        let cost = upgrade_cost(*level);
+        if points < cost {
+            return Err(GameError::NotEnoughPrestigePoints {
+                needed: cost,
+                have: points,
+            });
+        }
+        *level += 1;
+        self.points -= cost;
+        Ok(())
+    }
+}
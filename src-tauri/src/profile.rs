@@ -0,0 +1,128 @@
+use crate::error::GameError;
+use directories::ProjectDirs;
+use parking_lot::{const_rwlock, RwLock};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub const DEFAULT_PROFILE: &str = "default";
+const MAX_PROFILE_NAME_LEN: usize = 32;
+
+static ACTIVE_PROFILE: RwLock<String> = const_rwlock(String::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileIndex {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+impl Default for ProfileIndex {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "ClickerClicker", "ClickerClickerClicker")
+}
+
+fn index_file_path() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().join("profiles.json"))
+}
+
+fn load_index() -> ProfileIndex {
+    index_file_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(index: &ProfileIndex) -> Result<(), GameError> {
+    let path = index_file_path()
+        .ok_or_else(|| GameError::storage("Failed to resolve profile index path"))?;
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(index).map_err(GameError::storage)?;
+    fs::write(path, json).map_err(GameError::storage)
+}
+
+fn validate_name(name: &str) -> Result<String, GameError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_PROFILE_NAME_LEN {
+        return Err(GameError::InvalidProfileName);
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(GameError::InvalidProfileName);
+    }
+    Ok(name.to_string())
+}
+
+// 起動時のプロファイル決定（--profile 引数 > 前回使用したプロファイル）
+pub fn init() {
+    let mut index = load_index();
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            if let Some(name) = args.next().and_then(|name| validate_name(&name).ok()) {
+                if !index.profiles.contains(&name) {
+                    index.profiles.push(name.clone());
+                }
+                index.active = name;
+                let _ = save_index(&index);
+            }
+        }
+    }
+    *ACTIVE_PROFILE.write() = index.active;
+}
+
+pub fn active_profile() -> String {
+    let active = ACTIVE_PROFILE.read();
+    if active.is_empty() {
+        DEFAULT_PROFILE.to_string()
+    } else {
+        active.clone()
+    }
+}
+
+pub fn list_profiles() -> ProfileIndex {
+    let mut index = load_index();
+    index.active = active_profile();
+    index
+}
+
+pub fn switch_profile(name: &str) -> Result<(), GameError> {
+    let name = validate_name(name)?;
+    let mut index = load_index();
+    if !index.profiles.contains(&name) {
+        index.profiles.push(name.clone());
+    }
+    index.active = name.clone();
+    save_index(&index)?;
+    *ACTIVE_PROFILE.write() = name;
+    Ok(())
+}
+
+// 既定プロファイルは従来のパスをそのまま使う
+fn profile_subdir(base: PathBuf) -> PathBuf {
+    let active = active_profile();
+    if active == DEFAULT_PROFILE {
+        base
+    } else {
+        base.join("profiles").join(active)
+    }
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| profile_subdir(dirs.data_dir().to_path_buf()))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| profile_subdir(dirs.config_dir().to_path_buf()))
+}
@@ -0,0 +1,100 @@
+use crate::game::{UnitType, Upgrades};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 基地の体力（基地強化 1 回で 1.1 倍、対戦では上限を設ける）
+pub const BASE_HP: f32 = 1000.0;
+pub const MAX_BASE_HP: f32 = 1.0e9;
+// 兵種ごとの基本の出撃数と上限。強化 3 回ごとに 1 体増える
+const BASE_SQUADS: [(UnitType, u32, u32); 3] = [
+    (UnitType::Small, 6, 15),
+    (UnitType::Medium, 3, 10),
+    (UnitType::Large, 1, 5),
+];
+const UPGRADE_PERCENT_PER_UNIT: u32 = 30;
+
+// 対戦用に書き出す軍勢。編成と能力値は強化の段階から決まるので、サーバーは同期済みの段階と照合できる
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct ArmySnapshot {
+    // サーバーから取得したときだけ入る
+    #[serde(default)]
+    pub player_id: String,
+    #[serde(default)]
+    pub player_name: String,
+    pub upgrades: Upgrades,
+    #[serde(default)]
+    pub prestige_attack: u32,
+    #[serde(default)]
+    pub prestige_hp: u32,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub created_at: i64,
+}
+
+impl ArmySnapshot {
+    // 出撃する兵種と数（出撃順）
+    pub fn squads(&self) -> Vec<(UnitType, u32)> {
+        BASE_SQUADS
+            .iter()
+            .map(|&(unit_type, base, max)| {
+                let (hp, attack, speed) = self.upgrades.unit_bonus(unit_type);
+                let extra =
+                    hp.saturating_add(attack).saturating_add(speed) / UPGRADE_PERCENT_PER_UNIT;
+                (unit_type, base.saturating_add(extra).min(max))
+            })
+            .collect()
+    }
+
+    pub fn base_hp(&self) -> f32 {
+        let purchases = (self.upgrades.base_hp / 10).min(i32::MAX as u32) as i32;
+        (BASE_HP * 1.1f32.powi(purchases)).min(MAX_BASE_HP)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleWinner {
+    Attacker,
+    Defender,
+    Draw,
+}
+
+// 再生用の一定間隔の盤面
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct BattleFrame {
+    pub time: f32,
+    pub attacker_base_hp: f32,
+    pub defender_base_hp: f32,
+    pub units: Vec<FrameUnit>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct FrameUnit {
+    pub id: u32,
+    pub unit_type: UnitType,
+    // 挑んだ側のユニット
+    pub attacker: bool,
+    pub position: f32,
+    pub hp: f32,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BattleEvent {
+    Spawn { time: f32, id: u32 },
+    Defeated { time: f32, id: u32, by: u32 },
+    BaseDestroyed { time: f32, attacker: bool },
+}
+
+// "pvp-battle-result" で送る対戦の結果と再生用の記録
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct BattleResult {
+    pub attacker: ArmySnapshot,
+    pub defender: ArmySnapshot,
+    pub winner: BattleWinner,
+    pub duration_secs: f32,
+    pub attacker_base_hp: f32,
+    pub defender_base_hp: f32,
+    pub frames: Vec<BattleFrame>,
+    pub events: Vec<BattleEvent>,
+}
@@ -0,0 +1,208 @@
+use crate::schedule::local_midnight;
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestMetric {
+    SmallUnits,
+    MediumUnits,
+    StagesCleared,
+    Keystrokes,
+    Clicks,
+    EnemiesDefeated,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestPeriod {
+    Daily,
+    Weekly,
+}
+
+// (指標, 説明, デイリーの必要数, ウィークリーの必要数, デイリーの基本報酬)
+const TEMPLATES: &[(QuestMetric, &str, u64, u64, u64)] = &[
+    (
+        QuestMetric::SmallUnits,
+        "Spawn {} small units",
+        500,
+        5_000,
+        200,
+    ),
+    (
+        QuestMetric::MediumUnits,
+        "Spawn {} medium units",
+        200,
+        2_000,
+        200,
+    ),
+    (QuestMetric::StagesCleared, "Clear {} stages", 3, 20, 300),
+    (QuestMetric::Keystrokes, "Type {} keys", 2_000, 20_000, 200),
+    (QuestMetric::Clicks, "Click {} times", 500, 5_000, 200),
+    (
+        QuestMetric::EnemiesDefeated,
+        "Defeat {} enemies",
+        300,
+        3_000,
+        250,
+    ),
+];
+
+const DAILY_QUESTS: usize = 3;
+const WEEKLY_QUESTS: usize = 2;
+// ウィークリーの報酬はデイリーの何倍か
+const WEEKLY_REWARD_MULTIPLIER: u64 = 5;
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct Quest {
+    pub id: String,
+    pub period: QuestPeriod,
+    pub metric: QuestMetric,
+    pub description: String,
+    #[ts(type = "number")]
+    pub target: u64,
+    #[ts(type = "number")]
+    pub progress: u64,
+    #[ts(type = "number")]
+    pub reward: u64,
+    #[serde(default)]
+    pub claimed: bool,
+}
+
+impl Quest {
+    pub fn is_complete(&self) -> bool {
+        self.progress >= self.target
+    }
+}
+
+// 日付から決まる擬似乱数（同じ日なら誰でも同じクエストになる）
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// 重複しないテンプレートを count 個選ぶ
+fn generate(period: QuestPeriod, key: &str, seed: u64, count: usize, stage: u32) -> Vec<Quest> {
+    let mut state = seed;
+    let mut indices: Vec<usize> = (0..TEMPLATES.len()).collect();
+    for i in (1..indices.len()).rev() {
+        let j = (splitmix(&mut state) % (i as u64 + 1)) as usize;
+        indices.swap(i, j);
+    }
+    // 先に進んでいるほど報酬を増やす
+    let stage_bonus = 1 + stage as u64 / 10;
+    indices
+        .into_iter()
+        .take(count)
+        .map(|index| {
+            let (metric, description, daily, weekly, reward) = TEMPLATES[index];
+            let (target, reward) = match period {
+                QuestPeriod::Daily => (daily, reward * stage_bonus),
+                QuestPeriod::Weekly => (weekly, reward * stage_bonus * WEEKLY_REWARD_MULTIPLIER),
+            };
+            Quest {
+                id: format!("{}-{:?}", key, metric).to_lowercase(),
+                period,
+                metric,
+                description: description.replace("{}", &target.to_string()),
+                target,
+                progress: 0,
+                reward,
+                claimed: false,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct QuestLog {
+    // 生成した日（"2026-10-16"）と週（"2026-w42"）
+    daily_key: String,
+    weekly_key: String,
+    quests: Vec<Quest>,
+    // この時刻を過ぎたら日付を確かめ直す（毎フレームの日付計算を避ける）
+    #[serde(default)]
+    next_check: i64,
+    // 達成したがまだ通知していないクエスト
+    #[serde(skip)]
+    pending: Vec<Quest>,
+}
+
+impl QuestLog {
+    // ローカル時刻の 0 時を過ぎたらデイリーを、月曜 0 時を過ぎたらウィークリーを作り直す
+    pub fn refresh(&mut self, now: i64, stage: u32) {
+        if now < self.next_check {
+            return;
+        }
+        let Some(today) = Local.timestamp_opt(now, 0).single().map(|t| t.date_naive()) else {
+            return;
+        };
+        self.rollover(today, stage);
+        self.next_check = today
+            .checked_add_days(Days::new(1))
+            .and_then(local_midnight)
+            .unwrap_or(now + 60);
+    }
+
+    fn rollover(&mut self, today: NaiveDate, stage: u32) {
+        let daily_key = today.format("%Y-%m-%d").to_string();
+        if daily_key != self.daily_key {
+            let seed = today.num_days_from_ce() as u64;
+            self.quests.retain(|q| q.period != QuestPeriod::Daily);
+            self.quests.extend(generate(
+                QuestPeriod::Daily,
+                &daily_key,
+                seed,
+                DAILY_QUESTS,
+                stage,
+            ));
+            self.daily_key = daily_key;
+        }
+        let week = today.iso_week();
+        let weekly_key = format!("{}-w{:02}", week.year(), week.week());
+        if weekly_key != self.weekly_key {
+            // 日替わりと同じ並びにならないよう種をずらす
+            let seed = (((week.year() as u64) << 8) | week.week() as u64) ^ 0x5EED_0000_0000;
+            self.quests.retain(|q| q.period != QuestPeriod::Weekly);
+            self.quests.extend(generate(
+                QuestPeriod::Weekly,
+                &weekly_key,
+                seed,
+                WEEKLY_QUESTS,
+                stage,
+            ));
+            self.weekly_key = weekly_key;
+        }
+    }
+
+    pub fn record(&mut self, metric: QuestMetric, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        for quest in self.quests.iter_mut().filter(|q| q.metric == metric) {
+            if quest.is_complete() {
+                continue;
+            }
+            quest.progress = (quest.progress + amount).min(quest.target);
+            if quest.is_complete() {
+                self.pending.push(quest.clone());
+            }
+        }
+    }
+
+    pub fn take_pending(&mut self) -> Vec<Quest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn list(&self) -> Vec<Quest> {
+        self.quests.clone()
+    }
+
+    pub fn get_mut(&mut self, quest_id: &str) -> Option<&mut Quest> {
+        self.quests.iter_mut().find(|q| q.id == quest_id)
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 集結地点に着いたとみなす距離
+pub const HOLD_RADIUS: f32 = 15.0;
+// 集結中でもこの距離まで近づいた敵には応戦する
+pub const DEFEND_RADIUS: f32 = 60.0;
+pub const DEFAULT_THRESHOLD: u32 = 10;
+pub const MAX_THRESHOLD: u32 = 200;
+
+// 新しく出撃した味方はまず集結地点で待機し、規定数が揃うと一斉に進軍する
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct RallyPoint {
+    pub position: f32,
+    pub threshold: u32,
+}
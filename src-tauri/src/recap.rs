@@ -0,0 +1,96 @@
+use crate::events;
+use crate::numbers;
+use crate::schedule::{self, Scheduler};
+use crate::stats::{StatsHistory, StatsTotals};
+use chrono::{DateTime, Days, Local};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+use ts_rs::TS;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct DailyRecap {
+    pub date: String,
+    pub today: StatsTotals,
+    pub yesterday: StatsTotals,
+    // 昨日と比べたコインの増減（％）。昨日の記録がなければ None
+    pub coins_change_percent: Option<f32>,
+}
+
+impl DailyRecap {
+    // 今日の 0 時から今までと、昨日 1 日分を統計の時系列から集計する
+    pub fn build(stats: &StatsHistory, now: DateTime<Local>) -> Self {
+        let today = now.date_naive();
+        let start = schedule::local_midnight(today).unwrap_or(now.timestamp() - DAY_SECS);
+        let yesterday_start = today
+            .checked_sub_days(Days::new(1))
+            .and_then(schedule::local_midnight)
+            .unwrap_or(start - DAY_SECS);
+        // 集計中の 1 分も含める
+        let today_totals = stats.totals_between(start, now.timestamp() + 60);
+        let yesterday_totals = stats.totals_between(yesterday_start, start);
+        let coins_change_percent = (yesterday_totals.coins_earned > 0).then(|| {
+            (today_totals.coins_earned as f32 / yesterday_totals.coins_earned as f32 - 1.0) * 100.0
+        });
+        Self {
+            date: today.format("%Y-%m-%d").to_string(),
+            today: today_totals,
+            yesterday: yesterday_totals,
+            coins_change_percent,
+        }
+    }
+
+    fn body(&self) -> String {
+        let mut body = format!(
+            "Typed {} keys, cleared {} stages, earned {} coins",
+            numbers::format_grouped(self.today.keys, numbers::DEFAULT_LOCALE),
+            self.today.stages_cleared,
+            numbers::format_compact(self.today.coins_earned),
+        );
+        if let Some(change) = self.coins_change_percent {
+            body.push_str(&format!(" ({:+.0}% vs yesterday)", change));
+        }
+        body
+    }
+}
+
+// フロントエンドが開いていなくても通知できるよう、ゲームループとは別のスレッドで時刻を見る
+pub fn start_recap_watcher(
+    app: AppHandle,
+    scheduler: Arc<Scheduler>,
+    stats: Arc<Mutex<StatsHistory>>,
+) {
+    loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        let now = Local::now();
+        if !scheduler.recap_due(&now) {
+            continue;
+        }
+        let recap = {
+            let mut stats = stats.lock();
+            let date = now.format("%Y-%m-%d").to_string();
+            if stats.recap_sent_on.as_deref() == Some(date.as_str()) {
+                continue;
+            }
+            stats.recap_sent_on = Some(date);
+            DailyRecap::build(&stats, now)
+        };
+        // 何もしなかった日は通知しない
+        if recap.today.active_minutes == 0 {
+            continue;
+        }
+        let _ = app
+            .notification()
+            .builder()
+            .title("Today's recap")
+            .body(recap.body())
+            .show();
+        events::emit::<events::DailyRecap>(&app, &recap);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::error::GameError;
+use crate::game::unix_timestamp;
+use crate::input_hook::InputCounter;
+use parking_lot::Mutex;
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use rand::RngCore;
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 47630;
+// スマホからのタップは 1 秒あたりこの数まで（連打アプリで水増しされないよう）
+const TAPS_PER_SEC: f32 = 8.0;
+const TAP_BURST: f32 = 16.0;
+// 画面側はタップをまとめて送るので、1 回のリクエストで受け付ける上限
+const MAX_TAPS_PER_REQUEST: u32 = 16;
+const MAX_REQUEST_BYTES: usize = 4096;
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+// 少しずつ送り続けて接続を居座らせないよう、リクエストを読み終えるまでの上限
+const REQUEST_DEADLINE: Duration = Duration::from_secs(5);
+// 同時に処理する接続の上限（超えた分はすぐ閉じる）
+const MAX_CONNECTIONS: usize = 8;
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+// スマホのブラウザで開くページ（ボタンを押すたびにタップを送る）
+const PAGE: &str = r#"<!doctype html>
+<html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width,initial-scale=1">
+<title>Reinforce</title>
+<style>body{margin:0;height:100vh;display:flex;flex-direction:column;align-items:center;justify-content:center;background:#121626;color:#fff;font-family:sans-serif}
+button{width:70vmin;height:70vmin;border-radius:50%;border:0;background:#5096ff;color:#fff;font-size:8vmin;touch-action:manipulation}
+button:active{background:#3070d0}</style></head>
+<body><button id="tap">TAP</button><p id="status">0</p>
+<script>
+const token = new URLSearchParams(location.search).get("t");
+let pending = 0, sent = 0;
+document.getElementById("tap").addEventListener("pointerdown", () => { pending++; });
+setInterval(async () => {
+  if (pending === 0) return;
+  const n = pending; pending = 0;
+  try {
+    const res = await fetch(`/tap?t=${token}&n=${n}`, { method: "POST" });
+    if (res.ok) { sent += (await res.json()).accepted; }
+    document.getElementById("status").textContent = res.ok ? sent : `error ${res.status}`;
+  } catch (e) { document.getElementById("status").textContent = "offline"; }
+}, 250);
+</script></body></html>"#;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct RemotePairing {
+    // スマホで開く URL と、それを読み取る QR コード（SVG）
+    pub url: String,
+    pub qr_svg: String,
+}
+
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct RemoteStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    // 受け付けたタップと、回数制限で捨てたタップ
+    pub taps: u64,
+    pub rejected: u64,
+    pub last_tap_at: Option<i64>,
+}
+
+// 1 秒あたりの上限を超えたタップは捨てる
+struct TapBucket {
+    tokens: f32,
+    refilled: Instant,
+}
+
+impl TapBucket {
+    fn new() -> Self {
+        Self {
+            tokens: TAP_BURST,
+            refilled: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, requested: u32) -> u32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * TAPS_PER_SEC).min(TAP_BURST);
+        self.refilled = now;
+        let granted = (self.tokens.floor() as u32).min(requested);
+        self.tokens -= granted as f32;
+        granted
+    }
+}
+
+// 同じ LAN のスマホからのタップをクリックとして数える（明示的に始めたときだけ待ち受ける）
+pub struct RemotePlay {
+    // 待ち受けているスレッドを止める旗
+    stop: Mutex<Option<Arc<AtomicBool>>>,
+    status: Arc<Mutex<RemoteStatus>>,
+}
+
+impl RemotePlay {
+    pub fn new() -> Self {
+        Self {
+            stop: Mutex::new(None),
+            status: Arc::new(Mutex::new(RemoteStatus::default())),
+        }
+    }
+
+    // 始めるたびにトークンを作り直すので、前のペアリングは使えなくなる
+    pub fn start(
+        &self,
+        port: u16,
+        counter: Arc<Mutex<InputCounter>>,
+    ) -> Result<RemotePairing, GameError> {
+        // 前のスレッドが待ち受けをやめてポートを空けるのを待つ
+        if self.stop() {
+            std::thread::sleep(ACCEPT_POLL * 2);
+        }
+        let address = local_address().ok_or(GameError::NoLocalNetwork)?;
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|_| GameError::RemotePlayPortUnavailable { port })?;
+        let token = generate_token();
+        let url = format!("http://{}:{}/?t={}", address, port, token);
+        // 1 マスを 1 単位にした余白付きの SVG（表示する大きさは画面側で決める）
+        let qr_svg = QrCode::with_error_correction_level(&url, EcLevel::L)
+            .map(|qr| qr.render::<svg::Color>().module_dimensions(1, 1).build())
+            .map_err(|_| GameError::internal("Pairing URL is too long for a QR code"))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = Server {
+            token,
+            counter,
+            status: Arc::clone(&self.status),
+            bucket: Mutex::new(TapBucket::new()),
+            connections: AtomicUsize::new(0),
+        };
+        let stop_server = Arc::clone(&stop);
+        std::thread::spawn(move || server.run(listener, stop_server));
+
+        *self.status.lock() = RemoteStatus {
+            running: true,
+            url: Some(url.clone()),
+            ..RemoteStatus::default()
+        };
+        *self.stop.lock() = Some(stop);
+        Ok(RemotePairing { url, qr_svg })
+    }
+
+    // 待ち受けていたら true
+    pub fn stop(&self) -> bool {
+        let Some(stop) = self.stop.lock().take() else {
+            return false;
+        };
+        stop.store(true, Ordering::Relaxed);
+        let mut status = self.status.lock();
+        status.running = false;
+        status.url = None;
+        true
+    }
+
+    pub fn status(&self) -> RemoteStatus {
+        self.status.lock().clone()
+    }
+}
+
+struct Server {
+    token: String,
+    counter: Arc<Mutex<InputCounter>>,
+    status: Arc<Mutex<RemoteStatus>>,
+    bucket: Mutex<TapBucket>,
+    connections: AtomicUsize,
+}
+
+impl Server {
+    // 遅い接続が他の接続を待たせないよう、接続ごとにスレッドで処理する
+    fn run(self, listener: TcpListener, stop: Arc<AtomicBool>) {
+        let server = Arc::new(self);
+        while !stop.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if server.connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                        server.connections.fetch_sub(1, Ordering::AcqRel);
+                        continue;
+                    }
+                    let server = Arc::clone(&server);
+                    std::thread::spawn(move || {
+                        let _ = server.handle(stream);
+                        server.connections.fetch_sub(1, Ordering::AcqRel);
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL),
+                Err(_) => std::thread::sleep(ACCEPT_POLL),
+            }
+        }
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let Some(request) = read_head(&mut stream)? else {
+            return respond(&mut stream, 400, "text/plain", "Bad Request");
+        };
+        let mut parts = request.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return respond(&mut stream, 400, "text/plain", "Bad Request");
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if !tokens_match(query_param(query, "t").unwrap_or(""), &self.token) {
+            return respond(&mut stream, 403, "text/plain", "Forbidden");
+        }
+        match (method, path) {
+            ("GET", "/") => respond(&mut stream, 200, "text/html; charset=utf-8", PAGE),
+            ("POST", "/tap") => {
+                let requested = query_param(query, "n")
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, MAX_TAPS_PER_REQUEST);
+                let accepted = self.bucket.lock().take(requested);
+                {
+                    let mut status = self.status.lock();
+                    status.taps += accepted as u64;
+                    status.rejected += (requested - accepted) as u64;
+                    if accepted > 0 {
+                        status.last_tap_at = Some(unix_timestamp());
+                    }
+                }
+                if accepted == 0 {
+                    return respond(&mut stream, 429, "text/plain", "Too Many Requests");
+                }
+                self.counter.lock().add_remote_clicks(accepted);
+                let body = format!("{{\"accepted\":{}}}", accepted);
+                respond(&mut stream, 200, "application/json", &body)
+            }
+            _ => respond(&mut stream, 404, "text/plain", "Not Found"),
+        }
+    }
+}
+
+// リクエスト行とヘッダーまで（本文は使わない）。大きすぎれば None、読み終わらなければ TimedOut
+fn read_head(stream: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let deadline = Instant::now() + REQUEST_DEADLINE;
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 512];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(ErrorKind::TimedOut.into());
+        }
+        stream.set_read_timeout(Some(remaining.min(IO_TIMEOUT)))?;
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    Ok(String::from_utf8(buffer)
+        .ok()
+        .and_then(|head| head.lines().next().map(str::to_string)))
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Too Many Requests",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+}
+
+// 一致するまでの時間でトークンを推測されないよう、長さが同じなら全文字を比べる
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 外向きの経路に使われる LAN 側のアドレス（UDP の connect はパケットを送らない）
+fn local_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    let address = socket.local_addr().ok()?.ip();
+    (!address.is_loopback() && !address.is_unspecified()).then_some(address)
+}
@@ -0,0 +1,283 @@
+use crate::ability::UnitAbility;
+use crate::coop::CoopPlayer;
+use crate::game::{GameState, UnitType};
+use crate::item::ItemKind;
+use crate::orders::Order;
+use crate::rules::GameRules;
+use crate::stance::Stance;
+use serde::{Deserialize, Serialize};
+
+// 1 回の記録で残す入力の上限（超えた分は記録しない）
+const MAX_REPLAY_INPUTS: usize = 100_000;
+
+// 戦闘の結果に関わる入力。転生や取り込み、報酬の受け取りは記録しないので、その区間は再現しきれない
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "input", rename_all = "snake_case")]
+pub enum ReplayInput {
+    // コンボ・クエストに数える入力回数
+    Input {
+        clicks: u32,
+        types: u32,
+    },
+    Spawn {
+        unit_type: UnitType,
+        #[serde(default)]
+        player: CoopPlayer,
+    },
+    Special {
+        ability: UnitAbility,
+    },
+    Summon {
+        unit_type: UnitType,
+    },
+    BuyItem {
+        kind: ItemKind,
+        quantity: u32,
+    },
+    UseItem {
+        kind: ItemKind,
+    },
+    Purchase {
+        upgrade_type: String,
+        unit_type: String,
+        #[serde(default)]
+        player: CoopPlayer,
+    },
+    PrestigeUpgrade {
+        kind: String,
+    },
+    Respec {
+        refund_percent: u32,
+    },
+    OpenChest {
+        chest_id: u32,
+    },
+    AdvanceStage,
+    SetManualAdvance {
+        enabled: bool,
+    },
+    BuildBarracks {
+        position: f32,
+    },
+    SetStance {
+        stance: Stance,
+    },
+    SetRallyPoint {
+        position: f32,
+        threshold: Option<u32>,
+    },
+    ClearRallyPoint,
+    ReleaseRally,
+    OrderUnits {
+        unit_ids: Vec<u32>,
+        order: Order,
+        duration_secs: Option<f32>,
+    },
+    CancelOrders {
+        unit_ids: Vec<u32>,
+    },
+    SetRules {
+        rules: GameRules,
+    },
+    SetCoop {
+        enabled: bool,
+    },
+    Idle {
+        idle: bool,
+    },
+    Offline {
+        seconds: f32,
+    },
+}
+
+impl ReplayInput {
+    fn apply(&self, game: &mut GameState) {
+        // 記録したときに成功した操作なので、失敗は再現がずれたことを示すだけ（verify で分かる）
+        let _ = match self {
+            ReplayInput::Input { clicks, types } => {
+                game.record_input(*clicks, *types);
+                Ok(())
+            }
+            ReplayInput::Spawn { unit_type, player } => {
+                game.spawn_unit_for(*unit_type, *player);
+                Ok(())
+            }
+            ReplayInput::Special { ability } => {
+                game.spawn_special(*ability);
+                Ok(())
+            }
+            ReplayInput::Summon { unit_type } => game.summon_unit(*unit_type).map(|_| ()),
+            ReplayInput::BuyItem { kind, quantity } => game.buy_item(*kind, *quantity).map(|_| ()),
+            ReplayInput::UseItem { kind } => game.use_item(*kind),
+            ReplayInput::Purchase {
+                upgrade_type,
+                unit_type,
+                player,
+            } => game
+                .purchase_upgrade_as(upgrade_type, unit_type, *player)
+                .map(|_| ()),
+            ReplayInput::PrestigeUpgrade { kind } => game.purchase_prestige_upgrade(kind),
+            ReplayInput::Respec { refund_percent } => game.respec(*refund_percent).map(|_| ()),
+            ReplayInput::OpenChest { chest_id } => game.open_chest(*chest_id).map(|_| ()),
+            ReplayInput::AdvanceStage => game.advance_stage(),
+            ReplayInput::SetManualAdvance { enabled } => {
+                game.set_manual_advance(*enabled);
+                Ok(())
+            }
+            ReplayInput::BuildBarracks { position } => game.build_barracks(*position),
+            ReplayInput::SetStance { stance } => game.set_stance(*stance),
+            ReplayInput::SetRallyPoint {
+                position,
+                threshold,
+            } => game.set_rally_point(*position, *threshold),
+            ReplayInput::ClearRallyPoint => {
+                game.clear_rally_point();
+                Ok(())
+            }
+            ReplayInput::ReleaseRally => {
+                game.release_rally();
+                Ok(())
+            }
+            ReplayInput::OrderUnits {
+                unit_ids,
+                order,
+                duration_secs,
+            } => game
+                .order_units(unit_ids, *order, *duration_secs)
+                .map(|_| ()),
+            ReplayInput::CancelOrders { unit_ids } => {
+                game.cancel_orders(unit_ids);
+                Ok(())
+            }
+            ReplayInput::SetCoop { enabled } => {
+                game.set_coop(*enabled);
+                Ok(())
+            }
+            ReplayInput::SetRules { rules } => game.set_difficulty(rules.clone(), true).map(|_| ()),
+            ReplayInput::Idle { idle } => {
+                game.set_idle(*idle);
+                Ok(())
+            }
+            ReplayInput::Offline { seconds } => {
+                game.apply_offline_progress(*seconds);
+                Ok(())
+            }
+        };
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReplayEntry {
+    // 記録を始めてから何回目の step の前に入ったか
+    pub tick: u64,
+    #[serde(flatten)]
+    pub input: ReplayInput,
+}
+
+// 記録中の入力と、開始時点の状態
+#[derive(Clone)]
+pub struct ReplayRecorder {
+    seed: u64,
+    start: Box<GameState>,
+    start_tick: u64,
+    entries: Vec<ReplayEntry>,
+}
+
+impl ReplayRecorder {
+    pub fn new(seed: u64, start: GameState, start_tick: u64) -> Self {
+        Self {
+            seed,
+            start: Box::new(start),
+            start_tick,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, input: ReplayInput) {
+        if self.entries.len() < MAX_REPLAY_INPUTS {
+            self.entries.push(ReplayEntry {
+                tick: tick - self.start_tick,
+                input,
+            });
+        }
+    }
+
+    pub fn finish(self, tick: u64) -> Replay {
+        Replay {
+            seed: self.seed,
+            start: self.start,
+            ticks: tick - self.start_tick,
+            inputs: self.entries,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub seed: u64,
+    start: Box<GameState>,
+    pub ticks: u64,
+    pub inputs: Vec<ReplayEntry>,
+}
+
+// stop_replay_recording で返す記録（開始時点の状態は含めない）
+#[derive(Clone, Serialize, Debug)]
+pub struct ReplayLog {
+    pub seed: u64,
+    pub ticks: u64,
+    pub inputs: Vec<ReplayEntry>,
+    // 記録から再計算した戦場が、記録を止めたときの戦場と一致した
+    pub verified: bool,
+}
+
+impl Replay {
+    // 開始時点の状態から、記録した tick で入力を与え直して同じ回数だけ進める
+    pub fn run(&self) -> GameState {
+        self.run_with(|_, _| {})
+    }
+
+    // on_step には step を終えた回数（1 から）とその時点の状態を渡す
+    pub fn run_with(&self, mut on_step: impl FnMut(u64, &GameState)) -> GameState {
+        let mut game = self.start.snapshot();
+        game.seed_rng(self.seed);
+        let mut inputs = self.inputs.iter().peekable();
+        for tick in 0..self.ticks {
+            while let Some(entry) = inputs.next_if(|entry| entry.tick == tick) {
+                entry.input.apply(&mut game);
+            }
+            game.step();
+            on_step(tick + 1, &game);
+        }
+        // 最後の step の後に入った入力
+        for entry in inputs {
+            entry.input.apply(&mut game);
+        }
+        game
+    }
+
+    pub fn verify(&self, live: &GameState) -> bool {
+        fingerprint(&self.run()) == fingerprint(live)
+    }
+
+    pub fn log(&self, verified: bool) -> ReplayLog {
+        ReplayLog {
+            seed: self.seed,
+            ticks: self.ticks,
+            inputs: self.inputs.clone(),
+            verified,
+        }
+    }
+}
+
+// 戦闘の結果として比べる部分（時刻の入る統計やセッションは比べない）
+fn fingerprint(game: &GameState) -> serde_json::Value {
+    serde_json::json!({
+        "player_units": game.player_units,
+        "enemy_units": game.enemy_units,
+        "player_base_hp": game.player_base_hp,
+        "enemy_base_hp": game.enemy_base_hp,
+        "coins": game.coins,
+        "stage": game.stage,
+        "upgrades": game.upgrades,
+    })
+}
@@ -0,0 +1,145 @@
+use crate::game::UnitType;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use ts_rs::TS;
+
+// 保持する戦闘レポートの数
+const MAX_REPORTS: usize = 20;
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, TS)]
+pub struct DamageByType {
+    pub small: f32,
+    pub medium: f32,
+    pub large: f32,
+    #[serde(default)]
+    pub hero: f32,
+}
+
+impl DamageByType {
+    fn add(&mut self, unit_type: UnitType, damage: f32) {
+        match unit_type {
+            UnitType::Small => self.small += damage,
+            UnitType::Medium => self.medium += damage,
+            UnitType::Large => self.large += damage,
+            UnitType::Hero => self.hero += damage,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct UnitContribution {
+    pub unit_id: u32,
+    pub unit_type: UnitType,
+    pub damage: f32,
+    pub kills: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct BattleReport {
+    pub stage: u32,
+    pub duration_secs: f32,
+    pub damage_by_type: DamageByType,
+    // 最も多くダメージを与えた味方ユニット
+    pub mvp: Option<UnitContribution>,
+    #[ts(type = "number")]
+    pub coins_earned: u64,
+    pub enemies_defeated: u32,
+    pub units_lost: u32,
+    #[ts(type = "number")]
+    pub finished_at: i64,
+}
+
+// 進行中のステージの集計
+#[derive(Clone, Serialize, Deserialize, Default)]
+struct BattleTracker {
+    elapsed: f32,
+    damage_by_type: DamageByType,
+    units: Vec<UnitContribution>,
+    coins_earned: f64,
+    enemies_defeated: u32,
+    units_lost: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct BattleReports {
+    reports: VecDeque<BattleReport>,
+    tracker: BattleTracker,
+    // 生成されたがまだ通知していないレポート
+    #[serde(skip)]
+    pending: Vec<BattleReport>,
+}
+
+impl BattleReports {
+    pub fn tick(&mut self, delta: f32) {
+        self.tracker.elapsed += delta;
+    }
+
+    pub fn record_damage(&mut self, unit_id: u32, unit_type: UnitType, damage: f32, killed: bool) {
+        let tracker = &mut self.tracker;
+        tracker.damage_by_type.add(unit_type, damage);
+        let index = match tracker.units.iter().position(|u| u.unit_id == unit_id) {
+            Some(index) => index,
+            None => {
+                tracker.units.push(UnitContribution {
+                    unit_id,
+                    unit_type,
+                    damage: 0.0,
+                    kills: 0,
+                });
+                tracker.units.len() - 1
+            }
+        };
+        let contribution = &mut tracker.units[index];
+        contribution.damage += damage;
+        if killed {
+            contribution.kills += 1;
+            tracker.enemies_defeated += 1;
+        }
+    }
+
+    pub fn record_losses(&mut self, count: u32) {
+        self.tracker.units_lost += count;
+    }
+
+    pub fn record_coins(&mut self, amount: f64) {
+        self.tracker.coins_earned += amount;
+    }
+
+    // ステージクリア時にレポートを確定する
+    pub fn finish(&mut self, stage: u32, now: i64) {
+        let tracker = std::mem::take(&mut self.tracker);
+        let mvp = tracker
+            .units
+            .into_iter()
+            .max_by(|a, b| a.damage.partial_cmp(&b.damage).unwrap());
+        let report = BattleReport {
+            stage,
+            duration_secs: tracker.elapsed,
+            damage_by_type: tracker.damage_by_type,
+            mvp,
+            coins_earned: tracker.coins_earned.floor() as u64,
+            enemies_defeated: tracker.enemies_defeated,
+            units_lost: tracker.units_lost,
+            finished_at: now,
+        };
+        if self.reports.len() >= MAX_REPORTS {
+            self.reports.pop_front();
+        }
+        self.reports.push_back(report.clone());
+        self.pending.push(report);
+    }
+
+    // 敗北やステージ変更時は集計を破棄する
+    pub fn reset(&mut self) {
+        self.tracker = BattleTracker::default();
+    }
+
+    pub fn take_pending(&mut self) -> Vec<BattleReport> {
+        std::mem::take(&mut self.pending)
+    }
+
+    // 新しい順
+    pub fn list(&self) -> Vec<BattleReport> {
+        self.reports.iter().rev().cloned().collect()
+    }
+}
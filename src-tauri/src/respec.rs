@@ -0,0 +1,94 @@
+use crate::game::{upgrade_cost, Upgrades, UPGRADE_OPTIONS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// 返すコインの割合（％）の既定。設定で変えられる
+pub const DEFAULT_REFUND_PERCENT: u32 = 80;
+// 振り直しの手数料。周回の中で振り直すたびに倍になる
+const BASE_FEE: u64 = 1000;
+const MAX_FEE_DOUBLINGS: u32 = 30;
+
+// 強化の系統の名前（"attack:small"、兵種のない強化は "coin_rate" など）
+fn line_key(upgrade_type: &str, unit_type: &str) -> String {
+    if unit_type.is_empty() {
+        upgrade_type.to_string()
+    } else {
+        format!("{}:{}", upgrade_type, unit_type)
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct RespecQuote {
+    pub spent: u64,
+    pub refund_percent: u32,
+    pub refund: u64,
+    pub fee: u64,
+    // 振り直しの回数（この周回）
+    pub respecs: u32,
+}
+
+// 強化に使ったコインの記録（振り直しで返す額の元）
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct UpgradeLedger {
+    #[serde(default)]
+    spent: BTreeMap<String, u64>,
+    #[serde(default)]
+    respecs: u32,
+}
+
+impl UpgradeLedger {
+    pub fn record(&mut self, upgrade_type: &str, unit_type: &str, cost: u64) {
+        let Some(&(upgrade_type, unit_type)) = UPGRADE_OPTIONS
+            .iter()
+            .find(|&&(u, t)| u == upgrade_type && (t.is_empty() || t == unit_type))
+        else {
+            return;
+        };
+        let spent = self
+            .spent
+            .entry(line_key(upgrade_type, unit_type))
+            .or_default();
+        *spent = spent.saturating_add(cost);
+    }
+
+    // 記録を始める前のセーブやサーバーから取り込んだ強化は、今の価格表で買ったものとみなす
+    pub fn reconcile(&mut self, upgrades: &Upgrades) {
+        for (&(upgrade_type, unit_type), level) in UPGRADE_OPTIONS.iter().zip(upgrades.levels()) {
+            let implied: u64 = (0..level).step_by(10).map(upgrade_cost).sum();
+            let spent = self
+                .spent
+                .entry(line_key(upgrade_type, unit_type))
+                .or_default();
+            *spent = if level == 0 { 0 } else { (*spent).max(implied) };
+        }
+        self.spent.retain(|_, spent| *spent > 0);
+    }
+
+    pub fn total_spent(&self) -> u64 {
+        self.spent
+            .values()
+            .fold(0u64, |sum, &v| sum.saturating_add(v))
+    }
+
+    pub fn quote(&self, refund_percent: u32) -> RespecQuote {
+        let spent = self.total_spent();
+        let refund_percent = refund_percent.min(100);
+        RespecQuote {
+            spent,
+            refund_percent,
+            refund: (spent as u128 * refund_percent as u128 / 100) as u64,
+            fee: BASE_FEE.saturating_mul(1 << self.respecs.min(MAX_FEE_DOUBLINGS)),
+            respecs: self.respecs,
+        }
+    }
+
+    pub fn record_respec(&mut self) {
+        self.spent.clear();
+        self.respecs = self.respecs.saturating_add(1);
+    }
+
+    // 転生で強化が初期化されたら手数料も戻す
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::GameError;
+use crate::stage::{self, StagePreview};
+use serde::{Deserialize, Serialize};
+
+// カスタムで指定できる倍率の範囲
+const MIN_FACTOR: f32 = 0.1;
+const MAX_FACTOR: f32 = 10.0;
+// スポーン間隔はこれより短くしない
+const MIN_SPAWN_INTERVAL: f32 = 0.25;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Custom,
+}
+
+// 難易度ごとの倍率（Normal はすべて 1.0 で従来どおり）
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct GameRules {
+    pub difficulty: Difficulty,
+    // 敵ユニット・ボス・敵基地の体力と攻撃力
+    pub enemy_scaling: f32,
+    // 敵のスポーン間隔（大きいほどゆっくり出る）
+    pub spawn_interval: f32,
+    // ステージが進むにつれてスポーン間隔が縮む速さ
+    pub spawn_acceleration: f32,
+    // 撃破・ステージクリア・ボスの報酬
+    pub coin_multiplier: f32,
+    // 味方の基地の最大体力
+    pub base_hp: f32,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self::preset(Difficulty::Normal)
+    }
+}
+
+impl GameRules {
+    // Custom は Normal と同じ値から始める
+    pub fn preset(difficulty: Difficulty) -> Self {
+        let (enemy_scaling, spawn_interval, spawn_acceleration, coin_multiplier, base_hp) =
+            match difficulty {
+                Difficulty::Easy => (0.75, 1.25, 0.75, 0.8, 1.5),
+                Difficulty::Normal | Difficulty::Custom => (1.0, 1.0, 1.0, 1.0, 1.0),
+                Difficulty::Hard => (1.4, 0.85, 1.25, 1.5, 0.8),
+            };
+        Self {
+            difficulty,
+            enemy_scaling,
+            spawn_interval,
+            spawn_acceleration,
+            coin_multiplier,
+            base_hp,
+        }
+    }
+
+    // プリセット以外の値が来たら Custom として範囲を確かめる
+    pub fn validated(self) -> Result<Self, GameError> {
+        if self.difficulty != Difficulty::Custom {
+            return Ok(Self::preset(self.difficulty));
+        }
+        let factors = [
+            self.enemy_scaling,
+            self.spawn_interval,
+            self.spawn_acceleration,
+            self.coin_multiplier,
+            self.base_hp,
+        ];
+        if factors
+            .iter()
+            .all(|factor| (MIN_FACTOR..=MAX_FACTOR).contains(factor))
+        {
+            Ok(self)
+        } else {
+            Err(GameError::InvalidGameRules {
+                min: MIN_FACTOR,
+                max: MAX_FACTOR,
+            })
+        }
+    }
+
+    pub fn spawn_interval(&self, stage: u32) -> f32 {
+        (stage::spawn_interval_curve(stage, self.spawn_acceleration) * self.spawn_interval)
+            .max(MIN_SPAWN_INTERVAL)
+    }
+
+    pub fn enemy_base_hp(&self, stage: u32) -> f32 {
+        stage::enemy_base_hp(stage) * self.enemy_scaling
+    }
+
+    pub fn adjust_preview(&self, mut preview: StagePreview) -> StagePreview {
+        preview.enemy_base_hp *= self.enemy_scaling;
+        preview.stat_multiplier *= self.enemy_scaling;
+        preview.spawn_interval = self.spawn_interval(preview.stage);
+        for enemy in &mut preview.enemies {
+            enemy.hp *= self.enemy_scaling;
+            enemy.attack *= self.enemy_scaling;
+        }
+        if let Some(boss) = &mut preview.boss {
+            boss.hp *= self.enemy_scaling;
+            boss.attack *= self.enemy_scaling;
+        }
+        preview
+    }
+}
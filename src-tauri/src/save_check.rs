@@ -0,0 +1,59 @@
+use serde::Serialize;
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemSeverity {
+    // 読み込み時に直るもの・遊ぶのに差し支えないもの
+    Warning,
+    // このままでは保存や読み込みで進行が失われうるもの
+    Error,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct SaveProblem {
+    pub check: &'static str,
+    pub severity: ProblemSeverity,
+    pub message: String,
+}
+
+// トラブルシューティング画面に出す検査結果（データフォルダを消す前に確かめる）
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct SaveCheckReport {
+    // エラーが 1 つもない（警告は含めない）
+    pub healthy: bool,
+    // 実行した検査の名前（順番どおり）
+    pub checks: Vec<&'static str>,
+    pub problems: Vec<SaveProblem>,
+    // 書き出したセーブの大きさ（バイト）
+    pub size: usize,
+}
+
+impl SaveCheckReport {
+    pub fn run(&mut self, check: &'static str) {
+        self.checks.push(check);
+    }
+
+    pub fn warn(&mut self, check: &'static str, message: impl Into<String>) {
+        self.push(check, ProblemSeverity::Warning, message.into());
+    }
+
+    pub fn fail(&mut self, check: &'static str, message: impl Into<String>) {
+        self.push(check, ProblemSeverity::Error, message.into());
+    }
+
+    fn push(&mut self, check: &'static str, severity: ProblemSeverity, message: String) {
+        self.problems.push(SaveProblem {
+            check,
+            severity,
+            message,
+        });
+    }
+
+    pub fn finish(mut self) -> Self {
+        self.healthy = self
+            .problems
+            .iter()
+            .all(|problem| problem.severity != ProblemSeverity::Error);
+        self
+    }
+}
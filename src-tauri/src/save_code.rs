@@ -0,0 +1,142 @@
+use crate::error::GameError;
+use crate::integrity;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// 形式を変えたら上げる（古い形式は読めるようにしておく）
+pub const FORMAT_VERSION: u32 = 1;
+const PREFIX: &str = "ccc";
+// 展開後の大きさの上限（壊れた・悪意ある文字列で巨大な展開をしない）
+const MAX_DECODED_BYTES: u64 = 64 * 1024 * 1024;
+
+// 状態の JSON は文字列のまま持ち、そのバイト列に署名する
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    app_version: String,
+    exported_at: i64,
+    state: String,
+    checksum: String,
+}
+
+// "ccc<形式>:<圧縮した JSON の base64>" を作る
+pub fn encode(state_json: String, exported_at: i64) -> Result<String, GameError> {
+    let envelope = Envelope {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at,
+        checksum: integrity::sign_portable(state_json.as_bytes()),
+        state: state_json,
+    };
+    let json = serde_json::to_vec(&envelope).map_err(GameError::internal)?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&json).map_err(GameError::internal)?;
+    let compressed = encoder.finish().map_err(GameError::internal)?;
+    Ok(format!(
+        "{}{}:{}",
+        PREFIX,
+        FORMAT_VERSION,
+        URL_SAFE_NO_PAD.encode(compressed)
+    ))
+}
+
+// 検証済みの状態の JSON を返す
+pub fn decode(text: &str) -> Result<String, GameError> {
+    let (header, payload) = text
+        .trim()
+        .split_once(':')
+        .ok_or(GameError::SaveCodeCorrupt)?;
+    let version: u32 = header
+        .strip_prefix(PREFIX)
+        .and_then(|version| version.parse().ok())
+        .ok_or(GameError::SaveCodeCorrupt)?;
+    if version > FORMAT_VERSION {
+        return Err(GameError::SaveCodeTooNew {
+            version,
+            supported: FORMAT_VERSION,
+        });
+    }
+    // 貼り付け時に紛れ込んだ空白・改行は無視する
+    let payload: String = payload.split_whitespace().collect();
+    let compressed = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| GameError::SaveCodeCorrupt)?;
+    let mut json = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_DECODED_BYTES)
+        .read_to_end(&mut json)
+        .map_err(|_| GameError::SaveCodeCorrupt)?;
+    let envelope: Envelope =
+        serde_json::from_slice(&json).map_err(|_| GameError::SaveCodeCorrupt)?;
+    if !integrity::verify_portable(envelope.state.as_bytes(), &envelope.checksum) {
+        return Err(GameError::SaveCodeChecksumMismatch);
+    }
+    Ok(envelope.state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress(envelope: &Envelope) -> String {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&serde_json::to_vec(envelope).unwrap())
+            .unwrap();
+        format!(
+            "{}{}:{}",
+            PREFIX,
+            FORMAT_VERSION,
+            URL_SAFE_NO_PAD.encode(encoder.finish().unwrap())
+        )
+    }
+
+    // 貼り付けで紛れ込んだ空白や改行があっても元の JSON に戻る
+    #[test]
+    fn codes_round_trip_through_pasted_whitespace() {
+        let json = r#"{"coins":42,"name":"テスト"}"#;
+        let code = encode(json.to_string(), 1_700_000_000).unwrap();
+        assert!(code.starts_with("ccc1:"));
+        assert_eq!(decode(&code).unwrap(), json);
+
+        let (header, payload) = code.split_once(':').unwrap();
+        let (first, second) = payload.split_at(payload.len() / 2);
+        let pasted = format!("  {}:{}\n {}\r\n", header, first, second);
+        assert_eq!(decode(&pasted).unwrap(), json);
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in ["", "hello", "ccc:abc", "xyz1:abc", "ccc1:!!!", "ccc1:abcd"] {
+            assert_eq!(
+                decode(code).unwrap_err(),
+                GameError::SaveCodeCorrupt,
+                "{:?}",
+                code
+            );
+        }
+        assert_eq!(
+            decode("ccc2:abcd").unwrap_err(),
+            GameError::SaveCodeTooNew {
+                version: 2,
+                supported: FORMAT_VERSION,
+            }
+        );
+    }
+
+    #[test]
+    fn edited_states_fail_the_checksum() {
+        let code = compress(&Envelope {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: 0,
+            checksum: integrity::sign_portable(br#"{"coins":1}"#),
+            state: r#"{"coins":999999}"#.to_string(),
+        });
+        assert_eq!(
+            decode(&code).unwrap_err(),
+            GameError::SaveCodeChecksumMismatch
+        );
+    }
+}
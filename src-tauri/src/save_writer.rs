@@ -0,0 +1,362 @@
+use crate::game::unix_timestamp;
+use parking_lot::{const_mutex, Mutex};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
+use ts_rs::TS;
+
+// セーブ・設定ごとに残す世代数と、世代を進める間隔（5 秒ごとの自動セーブで全世代が埋まらないように）
+pub const BACKUPS: usize = 3;
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// 書けなくなったときに書き込みを試し直す間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+// 書き込み 1 回分。本体が書けたときだけ添えたファイルを書き、消すファイルを消す
+pub struct SaveJob {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    backup: bool,
+    companion: Option<(PathBuf, Vec<u8>)>,
+    clear: Option<(PathBuf, u64)>,
+}
+
+impl SaveJob {
+    pub fn new(path: PathBuf, bytes: Vec<u8>) -> Self {
+        Self {
+            path,
+            bytes,
+            backup: false,
+            companion: None,
+            clear: None,
+        }
+    }
+
+    pub fn with_backups(mut self) -> Self {
+        self.backup = true;
+        self
+    }
+
+    // 本体と同じ世代で残すファイル（セーブの署名）
+    pub fn with_companion(mut self, path: PathBuf, bytes: Vec<u8>) -> Self {
+        self.companion = Some((path, bytes));
+        self
+    }
+
+    // 書けたら消すファイル（ジャーナル）。積んだ後に追記されていれば、その分を失わないよう残す
+    pub fn clearing(mut self, path: PathBuf) -> Self {
+        let len = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        self.clear = Some((path, len));
+        self
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let rotate = self.backup && backup_due(&self.path);
+        write_atomic(&self.path, &self.bytes, rotate)?;
+        if let Some((path, bytes)) = &self.companion {
+            write_atomic(path, bytes, rotate)?;
+        }
+        if let Some((path, len)) = &self.clear {
+            if fs::metadata(path).is_ok_and(|metadata| metadata.len() == *len) {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+// 書けなくなった保存先と理由。画面には書けるようになるまで出し続ける
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct StorageFailure {
+    pub path: String,
+    pub reason: String,
+    pub since: i64,
+    // メモリに残していて、まだ書けていないファイルの数
+    pub pending: usize,
+}
+
+// 書けない間は書き込みを止め、ファイルごとに最新の内容だけを残しておく
+struct Health {
+    failure: Option<StorageFailure>,
+    // 書けなかった内容をメモリに残しているか（残していなければ、どのファイルが書けても戻ったとみなす）
+    held: bool,
+    dirty: Vec<SaveJob>,
+    next_retry: Option<Instant>,
+    changed: bool,
+}
+
+static HEALTH: Mutex<Health> = const_mutex(Health {
+    failure: None,
+    held: false,
+    dirty: Vec::new(),
+    next_retry: None,
+    changed: false,
+});
+
+fn keep_dirty(dirty: &mut Vec<SaveJob>, job: SaveJob) {
+    match dirty.iter_mut().find(|kept| kept.path == job.path) {
+        Some(kept) => *kept = job,
+        None => dirty.push(job),
+    }
+}
+
+// 書けないと分かっている間は積まずにメモリに残す。試し直す時刻なら残した分をまとめて返す
+fn hold(job: SaveJob) -> Vec<SaveJob> {
+    let mut health = HEALTH.lock();
+    if health.failure.is_none() {
+        return vec![job];
+    }
+    keep_dirty(&mut health.dirty, job);
+    take_due(&mut health)
+}
+
+fn take_due(health: &mut Health) -> Vec<SaveJob> {
+    if health.next_retry.is_some_and(|at| Instant::now() < at) {
+        return Vec::new();
+    }
+    health.next_retry = Some(Instant::now() + RETRY_INTERVAL);
+    std::mem::take(&mut health.dirty)
+}
+
+// 書き込みの結果を覚える。失敗した内容は、その後に新しい内容が来ていなければ残して試し直す
+fn record(job: SaveJob, result: &Result<(), String>) {
+    let mut health = HEALTH.lock();
+    let path = job.path.clone();
+    if result.is_err() && !health.dirty.iter().any(|kept| kept.path == job.path) {
+        health.dirty.push(job);
+    }
+    note(&mut health, &path, result, true);
+}
+
+// 積まずに書くもの（ジャーナルの追記）の結果も同じ状態に入れる。内容はメモリに残さない
+// （次のセーブに同じ進行が入る）ので、失敗しても書き込みごとにはログに出さない
+pub fn report(path: &Path, result: &Result<(), String>) {
+    note(&mut HEALTH.lock(), path, result, false);
+}
+
+fn note(health: &mut Health, path: &Path, result: &Result<(), String>, held: bool) {
+    let path = path.display().to_string();
+    match result {
+        // 書けなかったファイルが書けて、残りもなければ元に戻す
+        Ok(()) => {
+            let recovered = health
+                .failure
+                .as_ref()
+                .is_some_and(|failure| failure.path == path || !health.held);
+            if recovered && health.dirty.is_empty() {
+                println!("[storage] writes recovered: {}", path);
+                health.failure = None;
+                health.held = false;
+                health.next_retry = None;
+                health.changed = true;
+            }
+        }
+        Err(reason) => {
+            let same = health
+                .failure
+                .as_ref()
+                .is_some_and(|failure| failure.path == path && &failure.reason == reason);
+            if !same {
+                eprintln!("Failed to save {}: {}", path, reason);
+                let since = health
+                    .failure
+                    .as_ref()
+                    .map_or_else(unix_timestamp, |failure| failure.since);
+                health.failure = Some(StorageFailure {
+                    path,
+                    reason: reason.clone(),
+                    since,
+                    pending: 0,
+                });
+                health.held = held;
+                health.changed = true;
+            }
+            if health.next_retry.is_none() {
+                health.next_retry = Some(Instant::now() + RETRY_INTERVAL);
+            }
+        }
+    }
+}
+
+// 書けない状態なら保存先と理由を返す
+pub fn status() -> Option<StorageFailure> {
+    let health = HEALTH.lock();
+    health.failure.clone().map(|failure| StorageFailure {
+        pending: health.dirty.len(),
+        ..failure
+    })
+}
+
+// 書けなくなった・書けるようになったときだけ true（ゲームループが画面に知らせる）
+pub fn take_changed() -> bool {
+    std::mem::take(&mut HEALTH.lock().changed)
+}
+
+// 新しい書き込みがなくても、書けない間は間隔をあけて残した分を試し直す
+pub fn retry_if_due() {
+    let jobs = {
+        let mut health = HEALTH.lock();
+        if health.failure.is_none() {
+            return;
+        }
+        take_due(&mut health)
+    };
+    for job in jobs {
+        dispatch(job);
+    }
+}
+
+type Reply = Sender<Result<(), String>>;
+
+enum Message {
+    Write(Box<SaveJob>, Option<Reply>),
+    Flush(Sender<()>),
+}
+
+static WRITER: Mutex<Option<Sender<Message>>> = const_mutex(None);
+
+// 初めて使うときに書き込み用のスレッドを立てる。立てられなければ呼び出し元に返す
+fn send(message: Message) -> Result<(), Message> {
+    let mut writer = WRITER.lock();
+    if writer.is_none() {
+        let (sender, receiver) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("save-writer".to_string())
+            .spawn(move || run(receiver));
+        if spawned.is_ok() {
+            *writer = Some(sender);
+        }
+    }
+    match writer.as_ref() {
+        Some(sender) => sender.send(message).map_err(|err| err.0),
+        None => Err(message),
+    }
+}
+
+// 書き込みを積んで待たずに戻る（ゲームループの自動セーブ用）
+pub fn submit(job: SaveJob) {
+    for job in hold(job) {
+        dispatch(job);
+    }
+}
+
+fn dispatch(job: SaveJob) {
+    if let Err(Message::Write(job, _)) = send(Message::Write(Box::new(job), None)) {
+        let result = job.write().map_err(|e| e.to_string());
+        record(*job, &result);
+    }
+}
+
+// 積んである分も含めて書き終わるまで待ち、この書き込みの結果を返す
+pub fn write(job: SaveJob) -> Result<(), String> {
+    let (reply, done) = mpsc::channel();
+    match send(Message::Write(Box::new(job), Some(reply))) {
+        Ok(()) => done
+            .recv()
+            .unwrap_or_else(|_| Err("Save writer stopped".to_string())),
+        Err(Message::Write(job, _)) => {
+            let result = job.write().map_err(|e| e.to_string());
+            record(*job, &result);
+            result
+        }
+        Err(Message::Flush(_)) => unreachable!(),
+    }
+}
+
+// 積んである書き込みが終わるまで待つ（読み直す前と終了時）。メモリに残した分も間隔を待たずに試す
+pub fn flush() {
+    let dirty = std::mem::take(&mut HEALTH.lock().dirty);
+    for job in dirty {
+        dispatch(job);
+    }
+    let (reply, done) = mpsc::channel();
+    if send(Message::Flush(reply)).is_ok() {
+        let _ = done.recv();
+    }
+}
+
+fn run(receiver: Receiver<Message>) {
+    while let Ok(first) = receiver.recv() {
+        let mut pending: Vec<(SaveJob, Vec<Reply>)> = Vec::new();
+        let mut flushes = Vec::new();
+        for message in std::iter::once(first).chain(receiver.try_iter()) {
+            match message {
+                // 同じファイルへの古い書き込みは最新の内容に置き換える（順番は最初の位置のまま）
+                Message::Write(job, reply) => {
+                    match pending
+                        .iter_mut()
+                        .find(|(queued, _)| queued.path == job.path)
+                    {
+                        Some((queued, replies)) => {
+                            *queued = *job;
+                            replies.extend(reply);
+                        }
+                        None => pending.push((*job, reply.into_iter().collect())),
+                    }
+                }
+                Message::Flush(reply) => flushes.push(reply),
+            }
+        }
+        for (job, replies) in pending {
+            let result = job.write().map_err(|e| e.to_string());
+            for reply in replies {
+                let _ = reply.send(result.clone());
+            }
+            record(job, &result);
+        }
+        for reply in flushes {
+            let _ = reply.send(());
+        }
+    }
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// 1 が最も新しい世代
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    suffixed(path, &format!("bak{}", generation))
+}
+
+fn backup_due(path: &Path) -> bool {
+    let newest = fs::metadata(backup_path(path, 1)).and_then(|metadata| metadata.modified());
+    match newest {
+        Ok(modified) => SystemTime::now()
+            .duration_since(modified)
+            .map_or(true, |age| age >= BACKUP_INTERVAL),
+        Err(_) => true,
+    }
+}
+
+// 古い世代から順にずらし、今のファイルを 1 世代目として残す
+fn rotate(path: &Path) {
+    for generation in (1..BACKUPS).rev() {
+        let _ = fs::rename(
+            backup_path(path, generation),
+            backup_path(path, generation + 1),
+        );
+    }
+    let _ = fs::copy(path, backup_path(path, 1));
+}
+
+// 一時ファイルに書いて同期してから置き換える（書き込みの途中で落ちても元のファイルは壊れない）
+fn write_atomic(path: &Path, bytes: &[u8], rotate_backups: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = suffixed(path, "tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    if rotate_backups && path.exists() {
+        rotate(path);
+    }
+    fs::rename(&temp, path)
+}
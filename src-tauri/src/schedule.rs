@@ -0,0 +1,114 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, Timelike};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+// 曜日は ISO 形式（1 = 月曜 … 7 = 日曜）、時刻は 0:00 からの分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    pub input_days: Vec<u32>,
+    pub input_start_minute: u32,
+    pub input_end_minute: u32,
+    pub widget_days: Vec<u32>,
+    // 1 日のまとめを通知する時刻（勤務時間の設定とは独立して動く）
+    #[serde(default = "default_recap_enabled")]
+    pub recap_enabled: bool,
+    #[serde(default = "default_recap_minute")]
+    pub recap_minute: u32,
+}
+
+fn default_recap_enabled() -> bool {
+    true
+}
+
+fn default_recap_minute() -> u32 {
+    18 * 60
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            input_days: vec![1, 2, 3, 4, 5],
+            input_start_minute: 9 * 60,
+            input_end_minute: 18 * 60,
+            widget_days: vec![1, 2, 3, 4, 5],
+            recap_enabled: default_recap_enabled(),
+            recap_minute: default_recap_minute(),
+        }
+    }
+}
+
+impl ScheduleConfig {
+    pub fn input_allowed(&self, now: &DateTime<Local>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        if !self
+            .input_days
+            .contains(&now.weekday().number_from_monday())
+        {
+            return false;
+        }
+        let minute = now.hour() * 60 + now.minute();
+        if self.input_start_minute <= self.input_end_minute {
+            minute >= self.input_start_minute && minute < self.input_end_minute
+        } else {
+            // 日付をまたぐ時間帯（例: 22:00〜6:00）
+            minute >= self.input_start_minute || minute < self.input_end_minute
+        }
+    }
+
+    pub fn widget_visible(&self, now: &DateTime<Local>) -> bool {
+        !self.enabled
+            || self
+                .widget_days
+                .contains(&now.weekday().number_from_monday())
+    }
+}
+
+// ローカル時刻の 0 時の UNIX 時刻（夏時間で重なる場合は早いほう）
+pub fn local_midnight(date: NaiveDate) -> Option<i64> {
+    date.and_hms_opt(0, 0, 0)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.timestamp())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ScheduleStatus {
+    pub input_allowed: bool,
+    pub widget_visible: bool,
+}
+
+pub struct Scheduler {
+    config: Mutex<ScheduleConfig>,
+}
+
+impl Scheduler {
+    pub fn new(config: ScheduleConfig) -> Self {
+        Self {
+            config: Mutex::new(config),
+        }
+    }
+
+    pub fn set_config(&self, config: ScheduleConfig) {
+        *self.config.lock() = config;
+    }
+
+    pub fn status(&self) -> ScheduleStatus {
+        let now = Local::now();
+        let config = self.config.lock();
+        ScheduleStatus {
+            input_allowed: config.input_allowed(&now),
+            widget_visible: config.widget_visible(&now),
+        }
+    }
+
+    // 今日のまとめの時刻を過ぎているか
+    pub fn recap_due(&self, now: &DateTime<Local>) -> bool {
+        let config = self.config.lock();
+        config.recap_enabled
+            && now.hour() * 60 + now.minute() >= config.recap_minute.min(24 * 60 - 1)
+    }
+}
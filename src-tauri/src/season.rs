@@ -0,0 +1,111 @@
+use crate::prestige;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// サーバーが確定した、終わったシーズンの成績（プロファイルの seasons）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+pub struct SeasonResult {
+    pub season: u32,
+    pub stage: u32,
+    #[ts(type = "number")]
+    pub coins: u64,
+    pub rank: usize,
+    pub players: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonStanding {
+    pub rank: usize,
+    pub player_id: String,
+    pub player_name: String,
+    pub stage: u32,
+    pub coins: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousSeason {
+    pub season: u32,
+    pub ended_at: i64,
+    pub players: usize,
+    pub top: Vec<SeasonStanding>,
+}
+
+// /api/season の応答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonInfo {
+    pub season: u32,
+    pub started_at: i64,
+    #[serde(default)]
+    pub ends_at: Option<i64>,
+    #[serde(default)]
+    pub previous: Option<PreviousSeason>,
+}
+
+// 手元で最後に見たシーズンと、報酬を受け取り済みの最後のシーズン（0 ならまだない）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct SeasonTracker {
+    pub current: u32,
+    pub rewarded: u32,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct SeasonReward {
+    pub season: u32,
+    pub rank: usize,
+    pub players: usize,
+    #[ts(type = "number")]
+    pub points: u64,
+}
+
+// 同期で切り替えに気付いたときに "season-rollover" で送る
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct SeasonRollover {
+    pub season: u32,
+    // 周回をやり直したか（初めてシーズンを見たときや報酬だけのときは false）
+    pub reset: bool,
+    pub rewards: Vec<SeasonReward>,
+    #[ts(type = "number")]
+    pub points: u64,
+}
+
+// 順位による上乗せ（1 位・3 位以内・10 位以内）
+fn rank_bonus(rank: usize) -> u64 {
+    match rank {
+        1 => 5,
+        2..=3 => 3,
+        4..=10 => 1,
+        _ => 0,
+    }
+}
+
+// 転生と同じく到達ステージからポイントを計算し、順位の分を足す
+pub fn reward_points(result: &SeasonResult) -> u64 {
+    prestige::points_for_stage(result.stage) + rank_bonus(result.rank)
+}
+
+impl SeasonTracker {
+    // まだ受け取っていない成績の報酬（同じシーズンを二度受け取らない）
+    pub fn claim(&mut self, results: &[SeasonResult]) -> Vec<SeasonReward> {
+        let rewards: Vec<SeasonReward> = results
+            .iter()
+            .filter(|result| result.season > self.rewarded)
+            .map(|result| SeasonReward {
+                season: result.season,
+                rank: result.rank,
+                players: result.players,
+                points: reward_points(result),
+            })
+            .collect();
+        if let Some(latest) = rewards.iter().map(|reward| reward.season).max() {
+            self.rewarded = latest;
+        }
+        rewards
+    }
+
+    // サーバーのシーズンを記録し、前に見たシーズンから進んでいれば true
+    pub fn advance(&mut self, season: u32) -> bool {
+        let rolled = self.current != 0 && season > self.current;
+        self.current = self.current.max(season);
+        rolled
+    }
+}
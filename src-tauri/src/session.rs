@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+// この時間入力がなければ離席とみなしてセッションを終了
+pub const AFK_THRESHOLD_SECS: i64 = 300;
+const MAX_SESSION_HISTORY: usize = 100;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SessionSummary {
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub keystrokes: u32,
+    pub clicks: u32,
+    pub coins_earned: u64,
+    pub stages_cleared: u32,
+}
+
+impl SessionSummary {
+    fn new(now: i64) -> Self {
+        Self {
+            started_at: now,
+            ended_at: None,
+            keystrokes: 0,
+            clicks: 0,
+            coins_earned: 0,
+            stages_cleared: 0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct SessionTracker {
+    pub current: Option<SessionSummary>,
+    pub history: Vec<SessionSummary>,
+    #[serde(default)]
+    last_input_at: i64,
+}
+
+impl SessionTracker {
+    pub fn record_input(&mut self, now: i64, clicks: u32, keystrokes: u32) {
+        if clicks == 0 && keystrokes == 0 {
+            return;
+        }
+        self.last_input_at = now;
+        let session = self.current.get_or_insert_with(|| SessionSummary::new(now));
+        session.clicks += clicks;
+        session.keystrokes += keystrokes;
+    }
+
+    pub fn record_coins(&mut self, amount: u64) {
+        if let Some(session) = self.current.as_mut() {
+            session.coins_earned = session.coins_earned.saturating_add(amount);
+        }
+    }
+
+    pub fn record_stage_clear(&mut self) {
+        if let Some(session) = self.current.as_mut() {
+            session.stages_cleared += 1;
+        }
+    }
+
+    // 離席判定。セッションを閉じた場合は true
+    pub fn check_afk(&mut self, now: i64) -> bool {
+        if self.current.is_some() && now - self.last_input_at >= AFK_THRESHOLD_SECS {
+            self.end(self.last_input_at);
+            return true;
+        }
+        false
+    }
+
+    pub fn end(&mut self, now: i64) {
+        if let Some(mut session) = self.current.take() {
+            session.ended_at = Some(now.max(session.started_at));
+            self.history.push(session);
+            if self.history.len() > MAX_SESSION_HISTORY {
+                let overflow = self.history.len() - MAX_SESSION_HISTORY;
+                self.history.drain(0..overflow);
+            }
+        }
+    }
+}
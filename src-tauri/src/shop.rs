@@ -0,0 +1,139 @@
+use crate::error::GameError;
+use crate::prestige::PrestigeState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferKind {
+    Cosmetic,
+    Artifact,
+    Unlock,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ShopOffer {
+    pub item: String,
+    pub kind: OfferKind,
+    // 転生ポイント
+    pub price: u64,
+}
+
+// /api/shop の応答（品揃えはサーバーが決め、expires_at で入れ替わる）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ShopStock {
+    pub rotation: i64,
+    pub refreshed_at: i64,
+    pub expires_at: i64,
+    pub offers: Vec<ShopOffer>,
+}
+
+// 持っている間ずっと効く品（ID, 攻撃％, HP％, コイン％）。知らない ID は効果なしで持つだけ
+const ARTIFACTS: [(&str, f32, f32, f64); 3] = [
+    ("war_drum", 10.0, 0.0, 0.0),
+    ("iron_bulwark", 0.0, 10.0, 0.0),
+    ("lucky_coin", 0.0, 0.0, 15.0),
+];
+// 自動購入を始めるときのコインが要らなくなる
+pub const AUTO_BUY_PASS: &str = "auto_buy_pass";
+
+// 持っている品による倍率（攻撃, HP, コイン）
+pub fn artifact_multipliers(owned: &[String]) -> (f32, f32, f64) {
+    ARTIFACTS
+        .iter()
+        .filter(|(id, ..)| owned.iter().any(|item| item == id))
+        .fold((1.0, 1.0, 1.0), |(attack, hp, coin), (_, a, h, c)| {
+            (attack + a / 100.0, hp + h / 100.0, coin + c / 100.0)
+        })
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct OfferStatus {
+    #[serde(flatten)]
+    pub offer: ShopOffer,
+    pub owned: bool,
+    pub affordable: bool,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ShopStatus {
+    // まだ取得していなければ None
+    pub expires_at: Option<i64>,
+    // 期限が切れた品揃え（取り直すまで買えない）
+    pub expired: bool,
+    pub offers: Vec<OfferStatus>,
+    pub points: u64,
+    pub owned: Vec<String>,
+}
+
+// 最後に取得した品揃え。オフラインでも期限までは買える
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ShopCache {
+    stock: Option<ShopStock>,
+}
+
+impl ShopCache {
+    pub fn store(&mut self, stock: ShopStock) {
+        self.stock = Some(stock);
+    }
+
+    pub fn is_fresh(&self, now: i64) -> bool {
+        self.stock
+            .as_ref()
+            .is_some_and(|stock| now < stock.expires_at)
+    }
+
+    pub fn status(&self, now: i64, prestige: &PrestigeState) -> ShopStatus {
+        let offers = self
+            .stock
+            .iter()
+            .flat_map(|stock| &stock.offers)
+            .map(|offer| OfferStatus {
+                offer: offer.clone(),
+                owned: prestige.shop_items.contains(&offer.item),
+                affordable: prestige.points >= offer.price,
+            })
+            .collect();
+        ShopStatus {
+            expires_at: self.stock.as_ref().map(|stock| stock.expires_at),
+            expired: !self.is_fresh(now),
+            offers,
+            points: prestige.points,
+            owned: prestige.shop_items.clone(),
+        }
+    }
+
+    // 今並んでいる品を転生ポイントで買う（同じ品は 1 つまで）
+    pub fn buy(
+        &self,
+        item: &str,
+        now: i64,
+        prestige: &mut PrestigeState,
+    ) -> Result<ShopOffer, GameError> {
+        let stock = self
+            .stock
+            .as_ref()
+            .filter(|_| self.is_fresh(now))
+            .ok_or(GameError::ShopStockExpired)?;
+        let offer = stock
+            .offers
+            .iter()
+            .find(|offer| offer.item == item)
+            .ok_or_else(|| GameError::ShopOfferNotFound {
+                item: item.to_string(),
+            })?;
+        if prestige.shop_items.contains(&offer.item) {
+            return Err(GameError::ShopItemOwned {
+                item: item.to_string(),
+            });
+        }
+        if prestige.points < offer.price {
+            return Err(GameError::NotEnoughPrestigePoints {
+                needed: offer.price,
+                have: prestige.points,
+            });
+        }
+        prestige.points -= offer.price;
+        prestige.shop_items.push(offer.item.clone());
+        Ok(offer.clone())
+    }
+}
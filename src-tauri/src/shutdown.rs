@@ -0,0 +1,36 @@
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// 終了処理の受け渡し（終了要求 → ゲームループ停止の通知）
+#[derive(Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    loop_stopped: Mutex<bool>,
+    stopped: Condvar,
+}
+
+impl Shutdown {
+    // 最初の呼び出しだけ true（終了処理の二重実行を防ぐ）
+    pub fn request(&self) -> bool {
+        !self.requested.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn mark_loop_stopped(&self) {
+        *self.loop_stopped.lock() = true;
+        self.stopped.notify_all();
+    }
+
+    // ゲームループが止まるまで待つ（止まらなければ timeout で諦める）
+    pub fn wait_for_loop(&self, timeout: Duration) -> bool {
+        let mut stopped = self.loop_stopped.lock();
+        if !*stopped {
+            self.stopped.wait_for(&mut stopped, timeout);
+        }
+        *stopped
+    }
+}
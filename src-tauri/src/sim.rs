@@ -0,0 +1,562 @@
+// ヘッドレスのシミュレーター: Tauri を使わずに一定の入力ペースで GameState を N 時間進め、
+// ステージごとのクリア時間・獲得コイン・ユニット数を表示する（ステージ曲線の調整用）
+// --sweep でバランスの倍率の組み合わせごとに同じ条件で走らせ、CSV で比べられる
+use crate::game::{GameState, TickInput, STEP_SECS};
+use crate::rules::{Difficulty, GameRules};
+use parking_lot::Mutex;
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// STEP_SECS（1/60 秒）の刻みで 1 秒分
+const TICKS_PER_SEC: u64 = 60;
+// アプリと同じく 1 分ごとに強力ユニット
+const REINFORCEMENT_TICKS: u64 = TICKS_PER_SEC * 60;
+// --until-stage を指定したときの既定の上限時間
+const UNTIL_STAGE_HOURS: f64 = 24.0;
+// --sweep で変えられる倍率（GameRules の Custom と同じ名前と範囲）
+const SWEEP_PARAMS: [&str; 5] = [
+    "enemy_scaling",
+    "spawn_interval",
+    "spawn_acceleration",
+    "coin_multiplier",
+    "base_hp",
+];
+
+struct Options {
+    // 指定がなければ 1 時間（--until-stage があれば UNTIL_STAGE_HOURS まで）
+    hours: Option<f64>,
+    // このステージをクリアしたら止める
+    until_stage: Option<u32>,
+    // 倍率ごとに試す値（組み合わせをすべて走らせる）
+    sweep: Vec<(&'static str, Vec<f32>)>,
+    clicks_per_min: f64,
+    types_per_min: f64,
+    seed: u64,
+    buy_upgrades: bool,
+    csv: Option<String>,
+}
+
+const USAGE: &str = "Usage: sim [--hours H] [--until-stage N] [--cpm N] [--kpm N] [--seed N]
+           [--upgrades cheapest|none] [--sweep NAME=V1,V2,...]... [--csv PATH]
+
+  --hours        simulated hours to run (default 1, or 24 with --until-stage; fractions allowed)
+  --until-stage  stop once stage N is cleared
+  --cpm       mouse clicks per minute (default 30)
+  --kpm       key presses per minute (default 120)
+  --seed      RNG seed, the same options give the same run (default 0)
+  --upgrades  buy the cheapest upgrade whenever affordable, or never (default cheapest)
+  --sweep     run every combination of the given balance factors (repeatable);
+              NAME is one of enemy_scaling, spawn_interval, spawn_acceleration,
+              coin_multiplier, base_hp and values are between 0.1 and 10
+  --csv       also write per-stage rows as CSV to PATH (- for stdout); with --sweep
+              each row starts with the factors and ends with the elapsed time and coins so far
+
+Starts from a fresh game and never touches saves or settings.";
+
+// NAME=V1,V2,... を読む
+fn parse_sweep(value: &str) -> Result<(&'static str, Vec<f32>), String> {
+    let (name, values) = value
+        .split_once('=')
+        .ok_or_else(|| format!("--sweep expects NAME=V1,V2,..., got {}", value))?;
+    let name = SWEEP_PARAMS
+        .iter()
+        .find(|param| **param == name.trim())
+        .copied()
+        .ok_or_else(|| {
+            format!(
+                "--sweep expects one of {}, got {}",
+                SWEEP_PARAMS.join(", "),
+                name
+            )
+        })?;
+    let values = values
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| format!("--sweep {} expects numbers, got {}", name, v))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((name, values))
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        hours: None,
+        until_stage: None,
+        sweep: Vec::new(),
+        clicks_per_min: 30.0,
+        types_per_min: 120.0,
+        seed: 0,
+        buy_upgrades: true,
+        csv: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n\n{}", flag, USAGE))?;
+        let number = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or_else(|| format!("{} expects a non-negative number, got {}", flag, value))
+        };
+        match flag.as_str() {
+            "--hours" => options.hours = Some(number()?),
+            "--until-stage" => {
+                options.until_stage = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|stage| *stage > 0)
+                        .ok_or_else(|| format!("{} expects a stage number, got {}", flag, value))?,
+                )
+            }
+            "--sweep" => {
+                let (name, values) = parse_sweep(&value)?;
+                options.sweep.retain(|(swept, _)| *swept != name);
+                options.sweep.push((name, values));
+            }
+            "--cpm" => options.clicks_per_min = number()?,
+            "--kpm" => options.types_per_min = number()?,
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("{} expects a number, got {}", flag, value))?
+            }
+            "--upgrades" => {
+                options.buy_upgrades = match value.as_str() {
+                    "cheapest" => true,
+                    "none" => false,
+                    _ => {
+                        return Err(format!(
+                            "--upgrades expects cheapest or none, got {}",
+                            value
+                        ))
+                    }
+                }
+            }
+            "--csv" => options.csv = Some(value),
+            _ => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+// 1 分あたりの回数を回ごとに配る（端数は積み上げて、どの回でも合計がずれない）
+fn per_tick(per_min: f64, tick: u64) -> u32 {
+    let per_tick = per_min / 60.0 * STEP_SECS as f64;
+    ((tick + 1) as f64 * per_tick).floor() as u32 - (tick as f64 * per_tick).floor() as u32
+}
+
+// 1 ステージ分の集計
+struct StageStats {
+    stage: u32,
+    started_tick: u64,
+    seconds: f64,
+    coins_earned: u64,
+    coins_spent: u64,
+    upgrades_bought: u32,
+    peak_units: usize,
+    units_at_end: usize,
+    defeats: u32,
+    cleared: bool,
+}
+
+impl StageStats {
+    fn new(stage: u32, started_tick: u64) -> Self {
+        Self {
+            stage,
+            started_tick,
+            seconds: 0.0,
+            coins_earned: 0,
+            coins_spent: 0,
+            upgrades_bought: 0,
+            peak_units: 0,
+            units_at_end: 0,
+            defeats: 0,
+            cleared: false,
+        }
+    }
+
+    fn close(&mut self, tick: u64, units: usize, cleared: bool) {
+        self.seconds = (tick - self.started_tick) as f64 * STEP_SECS as f64;
+        self.units_at_end = units;
+        self.cleared = cleared;
+    }
+}
+
+struct Recorder {
+    stages: Vec<StageStats>,
+    last_coins: u64,
+    last_defeats: u32,
+}
+
+impl Recorder {
+    fn new(game: &GameState) -> Self {
+        Self {
+            stages: vec![StageStats::new(game.stage, 0)],
+            last_coins: game.coins,
+            last_defeats: game.defeats,
+        }
+    }
+
+    fn current(&mut self) -> &mut StageStats {
+        self.stages.last_mut().expect("at least one stage")
+    }
+
+    // tick 回進めた直後の状態を見る
+    fn observe(&mut self, game: &GameState, tick: u64) {
+        let units = game.player_units.len();
+        let earned = game.coins.saturating_sub(self.last_coins);
+        let defeats = game.defeats.saturating_sub(self.last_defeats);
+        self.last_coins = game.coins;
+        self.last_defeats = game.defeats;
+
+        let current = self.current();
+        current.coins_earned += earned;
+        current.defeats += defeats;
+        current.peak_units = current.peak_units.max(units);
+        if game.stage != current.stage {
+            current.close(tick, units, true);
+            self.stages.push(StageStats::new(game.stage, tick));
+        }
+    }
+
+    fn record_purchase(&mut self, cost: u64, coins_after: u64) {
+        let current = self.current();
+        current.coins_spent += cost;
+        current.upgrades_bought += 1;
+        self.last_coins = coins_after;
+    }
+}
+
+impl Options {
+    fn hours(&self) -> f64 {
+        self.hours.unwrap_or(if self.until_stage.is_some() {
+            UNTIL_STAGE_HOURS
+        } else {
+            1.0
+        })
+    }
+}
+
+fn simulate(options: &Options, rules: &GameRules) -> (GameState, Vec<StageStats>) {
+    let mut game = GameState::headless();
+    game.seed_rng(options.seed);
+    if *rules != GameRules::default() {
+        // 検証は呼び出し側で済ませている
+        let _ = game.set_difficulty(rules.clone(), true);
+    }
+    let mut recorder = Recorder::new(&game);
+    let total_secs = (options.hours() * 3600.0).round() as u64;
+    let mut seconds_run = total_secs;
+
+    // 1 秒ずつ進め、その合間に強化を買う
+    for second in 0..total_secs {
+        if options.until_stage.is_some_and(|stage| game.stage > stage) {
+            seconds_run = second;
+            break;
+        }
+        let first_tick = second * TICKS_PER_SEC;
+        game.run_ticks(TICKS_PER_SEC, STEP_SECS, |game, offset| {
+            let tick = first_tick + offset;
+            if tick > 0 {
+                recorder.observe(game, tick);
+            }
+            TickInput {
+                clicks: per_tick(options.clicks_per_min, tick),
+                types: per_tick(options.types_per_min, tick),
+                reinforcement: tick > 0 && tick.is_multiple_of(REINFORCEMENT_TICKS),
+                ..TickInput::default()
+            }
+        });
+        let tick = first_tick + TICKS_PER_SEC;
+        recorder.observe(&game, tick);
+        if options.buy_upgrades {
+            while let Ok((_, _, cost)) = game.purchase_cheapest_upgrade() {
+                recorder.record_purchase(cost, game.coins);
+            }
+        }
+    }
+
+    let end_tick = seconds_run * TICKS_PER_SEC;
+    let units = game.player_units.len();
+    recorder.current().close(end_tick, units, false);
+    // 目標の先で始まったばかりのステージは数えない
+    if let Some(until) = options.until_stage {
+        recorder.stages.retain(|stats| stats.stage <= until);
+    }
+    (game, recorder.stages)
+}
+
+const CSV_HEADER: &str = "stage,cleared,seconds,coins_earned,coins_spent,upgrades_bought,peak_units,units_at_end,defeats";
+
+fn csv_row(stats: &StageStats) -> String {
+    format!(
+        "{},{},{:.1},{},{},{},{},{},{}",
+        stats.stage,
+        stats.cleared,
+        stats.seconds,
+        stats.coins_earned,
+        stats.coins_spent,
+        stats.upgrades_bought,
+        stats.peak_units,
+        stats.units_at_end,
+        stats.defeats
+    )
+}
+
+fn print_report(game: &GameState, stages: &[StageStats]) {
+    println!(
+        "{:>6} {:>10} {:>12} {:>12} {:>9} {:>10} {:>9} {:>8}",
+        "stage", "clear s", "coins", "spent", "upgrades", "peak units", "units", "defeats"
+    );
+    for stats in stages {
+        let seconds = if stats.cleared {
+            format!("{:.1}", stats.seconds)
+        } else {
+            format!("({:.1})", stats.seconds)
+        };
+        println!(
+            "{:>6} {:>10} {:>12} {:>12} {:>9} {:>10} {:>9} {:>8}",
+            stats.stage,
+            seconds,
+            stats.coins_earned,
+            stats.coins_spent,
+            stats.upgrades_bought,
+            stats.peak_units,
+            stats.units_at_end,
+            stats.defeats
+        );
+    }
+    println!(
+        "Reached stage {} with {} coins, {} player units and {} enemy units.",
+        game.stage,
+        game.coins,
+        game.player_units.len(),
+        game.enemy_units.len()
+    );
+}
+
+fn write_csv(path: &str, stages: &[StageStats]) -> std::io::Result<()> {
+    let mut out = String::from(CSV_HEADER);
+    for stats in stages {
+        out.push('\n');
+        out.push_str(&csv_row(stats));
+    }
+    out.push('\n');
+    write_out(path, &out)
+}
+
+fn write_out(path: &str, out: &str) -> std::io::Result<()> {
+    if path == "-" {
+        std::io::stdout().write_all(out.as_bytes())
+    } else {
+        fs::write(path, out)
+    }
+}
+
+// 倍率の組み合わせ 1 つ分の結果
+struct SweepRun {
+    rules: GameRules,
+    reached_stage: u32,
+    stages: Vec<StageStats>,
+}
+
+fn rule_mut<'a>(rules: &'a mut GameRules, name: &str) -> &'a mut f32 {
+    match name {
+        "enemy_scaling" => &mut rules.enemy_scaling,
+        "spawn_interval" => &mut rules.spawn_interval,
+        "spawn_acceleration" => &mut rules.spawn_acceleration,
+        "coin_multiplier" => &mut rules.coin_multiplier,
+        _ => &mut rules.base_hp,
+    }
+}
+
+fn rule_values(rules: &GameRules) -> [f32; 5] {
+    [
+        rules.enemy_scaling,
+        rules.spawn_interval,
+        rules.spawn_acceleration,
+        rules.coin_multiplier,
+        rules.base_hp,
+    ]
+}
+
+// 指定した値の組み合わせをすべて作る（指定のない倍率は 1.0）
+fn sweep_grid(sweep: &[(&'static str, Vec<f32>)]) -> Result<Vec<GameRules>, String> {
+    let mut grid = vec![GameRules::preset(Difficulty::Custom)];
+    for (name, values) in sweep {
+        grid = grid
+            .iter()
+            .flat_map(|rules| {
+                values.iter().map(move |value| {
+                    let mut rules = rules.clone();
+                    *rule_mut(&mut rules, name) = *value;
+                    rules
+                })
+            })
+            .collect();
+    }
+    grid.into_iter()
+        .map(|rules| rules.validated().map_err(|e| e.to_string()))
+        .collect()
+}
+
+// 組み合わせごとの実行は互いに独立なので、コア数ぶんのスレッドで分ける
+fn run_sweep(options: &Options, grid: Vec<GameRules>) -> Vec<SweepRun> {
+    let next = AtomicUsize::new(0);
+    let runs: Mutex<Vec<(usize, SweepRun)>> = Mutex::new(Vec::with_capacity(grid.len()));
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(grid.len());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(rules) = grid.get(index) else {
+                    break;
+                };
+                let (game, stages) = simulate(options, rules);
+                eprintln!(
+                    "  [{}/{}] reached stage {}",
+                    index + 1,
+                    grid.len(),
+                    game.stage
+                );
+                let run = SweepRun {
+                    rules: rules.clone(),
+                    reached_stage: game.stage,
+                    stages,
+                };
+                runs.lock().push((index, run));
+            });
+        }
+    });
+    let mut runs = runs.into_inner();
+    runs.sort_by_key(|(index, _)| *index);
+    runs.into_iter().map(|(_, run)| run).collect()
+}
+
+fn print_sweep(runs: &[SweepRun]) {
+    println!(
+        "{:>8} {:>8} {:>8} {:>8} {:>8} {:>6} {:>10} {:>14}",
+        "enemy", "spawn", "accel", "coin", "base hp", "stage", "hours", "coins"
+    );
+    for run in runs {
+        let [enemy, spawn, accel, coin, base_hp] = rule_values(&run.rules);
+        let seconds: f64 = run.stages.iter().map(|stats| stats.seconds).sum();
+        let coins: u64 = run.stages.iter().map(|stats| stats.coins_earned).sum();
+        println!(
+            "{:>8} {:>8} {:>8} {:>8} {:>8} {:>6} {:>10.2} {:>14}",
+            enemy,
+            spawn,
+            accel,
+            coin,
+            base_hp,
+            run.reached_stage,
+            seconds / 3600.0,
+            coins
+        );
+    }
+}
+
+// 各行の先頭に倍率、末尾にそのステージまでの経過秒数と獲得コインの累計（コインの曲線）
+fn write_sweep_csv(path: &str, runs: &[SweepRun]) -> std::io::Result<()> {
+    let mut out = format!(
+        "{},{},elapsed,coins_total",
+        SWEEP_PARAMS.join(","),
+        CSV_HEADER
+    );
+    for run in runs {
+        let factors = rule_values(&run.rules)
+            .map(|value| value.to_string())
+            .join(",");
+        let mut elapsed = 0.0;
+        let mut coins_total = 0;
+        for stats in &run.stages {
+            elapsed += stats.seconds;
+            coins_total += stats.coins_earned;
+            out.push_str(&format!(
+                "\n{},{},{:.1},{}",
+                factors,
+                csv_row(stats),
+                elapsed,
+                coins_total
+            ));
+        }
+    }
+    out.push('\n');
+    write_out(path, &out)
+}
+
+fn sweep_main(options: &Options) {
+    let grid = match sweep_grid(&options.sweep) {
+        Ok(grid) => grid,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    eprintln!(
+        "Sweeping {} combinations for up to {} h each at {} clicks/min and {} keys/min (seed {})...",
+        grid.len(),
+        options.hours(),
+        options.clicks_per_min,
+        options.types_per_min,
+        options.seed
+    );
+    let runs = run_sweep(options, grid);
+    if options.csv.as_deref() != Some("-") {
+        print_sweep(&runs);
+    }
+    if let Some(path) = &options.csv {
+        if let Err(e) = write_sweep_csv(path, &runs) {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if !options.sweep.is_empty() {
+        sweep_main(&options);
+        return;
+    }
+
+    eprintln!(
+        "Simulating {} h at {} clicks/min and {} keys/min (seed {})...",
+        options.hours(),
+        options.clicks_per_min,
+        options.types_per_min,
+        options.seed
+    );
+    let (game, stages) = simulate(&options, &GameRules::default());
+    // CSV を標準出力に出すときは表を混ぜない
+    if options.csv.as_deref() != Some("-") {
+        print_report(&game, &stages);
+    }
+    if let Some(path) = &options.csv {
+        if let Err(e) = write_csv(path, &stages) {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
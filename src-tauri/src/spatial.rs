@@ -0,0 +1,226 @@
+use crate::game::Unit;
+use std::collections::HashMap;
+
+// 位置でソートしたユニットの索引（最寄りの検索と範囲内の数え上げを二分探索で行う）
+pub struct PositionIndex {
+    // (位置, ユニット一覧での添字)
+    entries: Vec<(f32, usize)>,
+    // テストで検索が見た要素の数を数える
+    #[cfg(test)]
+    probes: std::cell::Cell<usize>,
+}
+
+impl PositionIndex {
+    pub fn new(units: &[Unit], include: impl Fn(&Unit) -> bool) -> Self {
+        let mut entries: Vec<(f32, usize)> = units
+            .iter()
+            .enumerate()
+            .filter(|(_, unit)| include(unit))
+            .map(|(index, unit)| (unit.position, index))
+            .collect();
+        entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            entries,
+            #[cfg(test)]
+            probes: std::cell::Cell::new(0),
+        }
+    }
+
+    #[cfg(test)]
+    fn probe(&self) {
+        self.probes.set(self.probes.get() + 1);
+    }
+
+    #[cfg(not(test))]
+    fn probe(&self) {}
+
+    fn insert(&mut self, position: f32, index: usize) {
+        let at = self.lower_bound(position);
+        self.entries.insert(at, (position, index));
+    }
+
+    fn lower_bound(&self, position: f32) -> usize {
+        self.entries.partition_point(|&(p, _)| {
+            self.probe();
+            p < position
+        })
+    }
+
+    fn nearest_entry(&self, position: f32) -> Option<(f32, usize)> {
+        let split = self.lower_bound(position);
+        let before = split.checked_sub(1).map(|i| self.entries[i]);
+        let after = self.entries.get(split).copied();
+        match (before, after) {
+            (Some(b), Some(a)) if position - b.0 <= a.0 - position => Some(b),
+            (_, Some(a)) => Some(a),
+            (b, None) => b,
+        }
+    }
+
+    // 最も近いユニットの添字
+    pub fn nearest(&self, position: f32) -> Option<usize> {
+        self.nearest_entry(position).map(|(_, index)| index)
+    }
+
+    pub fn nearest_within(&self, position: f32, radius: f32) -> Option<usize> {
+        self.nearest_entry(position)
+            .filter(|&(p, _)| (p - position).abs() <= radius)
+            .map(|(_, index)| index)
+    }
+
+    // 範囲内のユニットの添字
+    pub fn within(&self, position: f32, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let start = self.lower_bound(position - radius);
+        self.entries[start..]
+            .iter()
+            .take_while(move |&&(p, _)| {
+                self.probe();
+                p <= position + radius
+            })
+            .map(|&(_, index)| index)
+    }
+
+    pub fn count_within(&self, position: f32, radius: f32) -> usize {
+        let start = self.lower_bound(position - radius);
+        let end = self.entries.partition_point(|&(p, _)| {
+            self.probe();
+            p <= position + radius
+        });
+        end.saturating_sub(start)
+    }
+}
+
+// レーンごとの位置索引（同じレーンのユニットだけが互いを狙う）
+pub struct LaneIndex {
+    lanes: Vec<PositionIndex>,
+}
+
+impl LaneIndex {
+    // レーン数より大きいレーン番号のユニットは最後のレーンに入れる
+    pub fn new(units: &[Unit], lane_count: u8, include: impl Fn(&Unit) -> bool) -> Self {
+        let last = lane_count.max(1) - 1;
+        Self {
+            lanes: (0..=last)
+                .map(|lane| {
+                    PositionIndex::new(units, |unit| unit.lane.min(last) == lane && include(unit))
+                })
+                .collect(),
+        }
+    }
+
+    // どのレーンからも狙えるユニット（ボスなど）を加える
+    pub fn insert_everywhere(&mut self, units: &[Unit], index: usize) {
+        for lane in &mut self.lanes {
+            lane.insert(units[index].position, index);
+        }
+    }
+
+    pub fn lane(&self, lane: u8) -> &PositionIndex {
+        &self.lanes[(lane as usize).min(self.lanes.len() - 1)]
+    }
+}
+
+// ユニットID → 一覧での添字
+pub fn slots(units: &[Unit]) -> HashMap<u32, usize> {
+    units
+        .iter()
+        .enumerate()
+        .map(|(index, unit)| (unit.id, index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::UnitAbility;
+    use crate::coop::CoopPlayer;
+    use crate::enemy_kind::EnemyKind;
+    use crate::game::UnitType;
+
+    fn unit_at(id: u32, position: f32) -> Unit {
+        Unit {
+            id,
+            unit_type: UnitType::Small,
+            position,
+            hp: 10.0,
+            max_hp: 10.0,
+            attack: 5.0,
+            speed: 100.0,
+            is_player: true,
+            target_id: None,
+            knockback_velocity: 0.0,
+            knockback_time: 0.0,
+            knockback_total: 0.0,
+            attack_cooldown: 0.0,
+            invulnerable_time: 0.0,
+            stealthed: false,
+            is_flying: false,
+            ability: UnitAbility::Melee,
+            ability_cooldown: 0.0,
+            rallying: false,
+            lane: 0,
+            owner: CoopPlayer::A,
+            kind: EnemyKind::Standard,
+            order: None,
+        }
+    }
+
+    #[test]
+    fn nearest_matches_linear_scan() {
+        let units: Vec<Unit> = (0..200)
+            .map(|i| unit_at(i, (i * 37 % 1000) as f32))
+            .collect();
+        let index = PositionIndex::new(&units, |_| true);
+        for probe in (0..1000).step_by(7) {
+            let probe = probe as f32 + 0.5;
+            let expected = units
+                .iter()
+                .map(|u| (u.position - probe).abs())
+                .fold(f32::MAX, f32::min);
+            let found = index.nearest(probe).unwrap();
+            assert_eq!((units[found].position - probe).abs(), expected);
+        }
+    }
+
+    // 2400 体の索引でも 1 回の検索で見るのは二分探索の分だけ（全件の走査にならない）
+    #[test]
+    fn queries_do_not_scan_every_unit() {
+        let units: Vec<Unit> = (0..2400)
+            .map(|i| unit_at(i, (i * 37 % 1000) as f32 + (i / 1000) as f32 * 0.1))
+            .collect();
+        let index = PositionIndex::new(&units, |_| true);
+        let per_search = (units.len() as f32).log2().ceil() as usize + 1;
+        for unit in &units {
+            index.probes.set(0);
+            index.nearest(unit.position);
+            index.count_within(unit.position, 1.0);
+            assert!(index.probes.get() <= 3 * per_search);
+        }
+        index.probes.set(0);
+        let nearby = index.within(500.0, 2.0).count();
+        assert!(index.probes.get() <= per_search + nearby + 1);
+    }
+
+    #[test]
+    fn filtered_and_ranged_queries() {
+        let units = vec![unit_at(1, 100.0), unit_at(2, 150.0), unit_at(3, 400.0)];
+        let index = PositionIndex::new(&units, |u| u.id != 2);
+        assert_eq!(index.nearest(160.0), Some(0));
+        assert_eq!(index.nearest_within(250.0, 100.0), None);
+        assert_eq!(index.count_within(120.0, 300.0), 2);
+        assert_eq!(index.within(120.0, 300.0).collect::<Vec<_>>(), vec![0, 2]);
+        assert!(PositionIndex::new(&units, |_| false).nearest(0.0).is_none());
+    }
+
+    #[test]
+    fn lanes_only_see_their_own_units() {
+        let mut units = vec![unit_at(1, 100.0), unit_at(2, 500.0), unit_at(3, 900.0)];
+        units[1].lane = 1;
+        units[2].lane = 7;
+        let mut index = LaneIndex::new(&units, 2, |_| true);
+        assert_eq!(index.lane(0).nearest(600.0), Some(0));
+        assert_eq!(index.lane(1).count_within(500.0, 1000.0), 2);
+        index.insert_everywhere(&units, 1);
+        assert_eq!(index.lane(0).nearest(600.0), Some(1));
+    }
+}
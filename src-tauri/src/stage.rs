@@ -0,0 +1,176 @@
+use crate::boss::{self, BossPreview};
+use crate::enemy_kind::{self, EnemyKind};
+use crate::game::UnitType;
+use crate::personality::EnemyPersonality;
+use chrono::Timelike;
+use serde::Serialize;
+
+// 敵の出現テーブル（ユニット種別, 出現率, 基本体力, 基本攻撃力, 移動速度）
+const ENEMY_ROSTER: [(UnitType, f32, f32, f32, f32); 3] = [
+    (UnitType::Small, 0.7, 15.0, 4.0, 90.0),
+    (UnitType::Medium, 0.15, 40.0, 12.0, 70.0),
+    (UnitType::Large, 0.15, 120.0, 40.0, 50.0),
+];
+
+// 敵の性格は PERSONALITY_BAND_LENGTH ステージごとにこの順で切り替わる
+const PERSONALITY_BAND_LENGTH: u32 = 10;
+const PERSONALITY_ROTATION: [EnemyPersonality; 3] = [
+    EnemyPersonality::Rush,
+    EnemyPersonality::Siege,
+    EnemyPersonality::Attrition,
+];
+
+#[derive(Clone, Serialize, Debug)]
+pub struct EnemyPreview {
+    pub unit_type: UnitType,
+    pub spawn_chance: f32,
+    pub hp: f32,
+    pub attack: f32,
+    pub speed: f32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct EnemyKindPreview {
+    pub kind: EnemyKind,
+    pub spawn_chance: f32,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct StagePreview {
+    pub stage: u32,
+    pub enemy_base_hp: f32,
+    pub stat_multiplier: f32,
+    pub spawn_interval: f32,
+    pub spawn_burst: u32,
+    pub personality: EnemyPersonality,
+    pub flying_chance: f32,
+    pub lanes: u8,
+    pub enemies: Vec<EnemyPreview>,
+    // 大きさとは別に混ざる敵の性質
+    pub enemy_kinds: Vec<EnemyKindPreview>,
+    pub modifiers: Vec<String>,
+    pub boss: Option<BossPreview>,
+}
+
+// 1000ステージ想定でなだらかに難易度上昇（対数的スケーリング）
+pub fn stat_multiplier(stage: u32) -> f32 {
+    1.0 + (stage as f32 - 1.0) * 0.05 + ((stage as f32).ln() / 10.0) * 0.3
+}
+
+pub fn personality(stage: u32) -> EnemyPersonality {
+    let band = (stage.max(1) - 1) / PERSONALITY_BAND_LENGTH;
+    PERSONALITY_ROTATION[band as usize % PERSONALITY_ROTATION.len()]
+}
+
+// 敵のスポーン間隔（なだらかに速度上昇、1000ステージ想定）に性格による補正を掛けたもの
+pub fn spawn_interval(stage: u32) -> f32 {
+    spawn_interval_curve(stage, 1.0)
+}
+
+// acceleration はステージごとに間隔が縮む速さの倍率（難易度で変わる）
+pub fn spawn_interval_curve(stage: u32, acceleration: f32) -> f32 {
+    let base = (3.0 - (stage as f32 * 0.002 * acceleration).min(2.0)).max(1.0);
+    base * personality(stage).spawn_interval_multiplier()
+}
+
+pub fn enemy_base_hp(stage: u32) -> f32 {
+    500.0 * (1.0 + (stage as f32 - 1.0) * 0.5)
+}
+
+// このステージから小型・中型の敵の一部が飛行ユニットになる
+const FLYING_FROM_STAGE: u32 = 15;
+const MAX_FLYING_CHANCE: f32 = 0.3;
+
+pub fn flying_chance(stage: u32) -> f32 {
+    if stage < FLYING_FROM_STAGE {
+        return 0.0;
+    }
+    (0.1 + (stage - FLYING_FROM_STAGE) as f32 * 0.002).min(MAX_FLYING_CHANCE)
+}
+
+// LANE_BAND_LENGTH ステージごとにレーンが 1 本ずつ増える
+pub const MAX_LANES: u8 = 3;
+const LANE_BAND_LENGTH: u32 = 25;
+
+pub fn lane_count(stage: u32) -> u8 {
+    (1 + (stage.max(1) - 1) / LANE_BAND_LENGTH).min(MAX_LANES as u32) as u8
+}
+
+// 夜間（ローカル時刻 20時〜6時）は隠密状態の敵が出現する
+const NIGHT_STEALTH_CHANCE: f32 = 0.25;
+
+pub fn is_night() -> bool {
+    let hour = chrono::Local::now().hour();
+    !(6..20).contains(&hour)
+}
+
+pub fn stealth_chance() -> f32 {
+    if is_night() {
+        NIGHT_STEALTH_CHANCE
+    } else {
+        0.0
+    }
+}
+
+// roll は 0.0〜1.0 の乱数
+pub fn pick_enemy(roll: f32) -> (UnitType, f32, f32, f32) {
+    let mut acc = 0.0;
+    for &(unit_type, chance, hp, attack, speed) in &ENEMY_ROSTER {
+        acc += chance;
+        if roll < acc {
+            return (unit_type, hp, attack, speed);
+        }
+    }
+    let (unit_type, _, hp, attack, speed) = ENEMY_ROSTER[ENEMY_ROSTER.len() - 1];
+    (unit_type, hp, attack, speed)
+}
+
+// 稼ぎ中は小型の弱い敵だけを出す
+pub fn farming_enemy(stage: u32) -> (f32, (UnitType, f32, f32, f32)) {
+    (stat_multiplier(stage) * 0.5, pick_enemy(0.0))
+}
+
+pub fn preview(stage: u32) -> StagePreview {
+    let multiplier = stat_multiplier(stage);
+    StagePreview {
+        stage,
+        enemy_base_hp: enemy_base_hp(stage),
+        stat_multiplier: multiplier,
+        spawn_interval: spawn_interval(stage),
+        spawn_burst: personality(stage).spawn_burst(),
+        personality: personality(stage),
+        flying_chance: flying_chance(stage),
+        lanes: lane_count(stage),
+        enemies: ENEMY_ROSTER
+            .iter()
+            .map(|&(unit_type, chance, hp, attack, speed)| EnemyPreview {
+                unit_type,
+                spawn_chance: chance,
+                hp: hp * multiplier,
+                attack: attack * multiplier,
+                speed,
+            })
+            .collect(),
+        enemy_kinds: enemy_kind::spawn_weights(stage)
+            .into_iter()
+            .filter(|&(_, chance)| chance > 0.0)
+            .map(|(kind, spawn_chance)| EnemyKindPreview { kind, spawn_chance })
+            .collect(),
+        modifiers: modifiers(stage),
+        boss: boss::preview(stage),
+    }
+}
+
+fn modifiers(stage: u32) -> Vec<String> {
+    let mut modifiers = Vec::new();
+    if is_night() {
+        modifiers.push("night_stealth".to_string());
+    }
+    if flying_chance(stage) > 0.0 {
+        modifiers.push("flying".to_string());
+    }
+    if boss::is_boss_stage(stage) {
+        modifiers.push("boss".to_string());
+    }
+    modifiers
+}
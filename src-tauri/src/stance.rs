@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// 防御態勢で待機する位置（前線基地があればそこまで出る）
+const DEFENSIVE_LINE: f32 = 250.0;
+// 待機位置に着いたとみなす距離
+pub const HOLD_TOLERANCE: f32 = 5.0;
+
+// 狙う敵がいないときの味方の動き
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, TS)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Stance {
+    // 敵基地まで進軍して攻撃する
+    #[default]
+    Aggressive,
+    // 自陣寄りの防衛線まで下がって待つ
+    Defensive,
+    // 指定した位置で待つ
+    Hold {
+        position: f32,
+    },
+}
+
+impl Stance {
+    // 待機する位置。進軍する態勢なら None
+    pub fn hold_position(self, barracks: Option<f32>) -> Option<f32> {
+        match self {
+            Self::Aggressive => None,
+            Self::Defensive => Some(barracks.map_or(DEFENSIVE_LINE, |b| b.max(DEFENSIVE_LINE))),
+            Self::Hold { position } => Some(position),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::profile;
+use crate::save_writer::{self, SaveJob};
+use crate::schedule;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use ts_rs::TS;
+
+// 1 分ごとのサンプルを 1 週間分残す
+const MAX_SAMPLES: usize = 7 * 24 * 60;
+
+// ゲームループの 1 回分に起きたこと（GameState が数え、ループが取り出す）
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Activity {
+    pub coins_earned: u64,
+    pub units_spawned: u32,
+    pub enemies_killed: u32,
+    pub actions: u32,
+    pub keys: u32,
+    pub stages_cleared: u32,
+}
+
+// 1 分間の集計。minute は UNIX 時刻を 60 で割ったもの
+#[derive(Clone, Copy, Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct StatsSample {
+    pub minute: i64,
+    pub coins_earned: u64,
+    pub units_spawned: u32,
+    pub enemies_killed: u32,
+    // その 1 分間のクリック・打鍵数（= APM）
+    pub actions: u32,
+    // うち打鍵数
+    #[serde(default)]
+    pub keys: u32,
+    #[serde(default)]
+    pub stages_cleared: u32,
+}
+
+impl StatsSample {
+    fn add(&mut self, activity: Activity) {
+        self.coins_earned = self.coins_earned.saturating_add(activity.coins_earned);
+        self.units_spawned = self.units_spawned.saturating_add(activity.units_spawned);
+        self.enemies_killed = self.enemies_killed.saturating_add(activity.enemies_killed);
+        self.actions = self.actions.saturating_add(activity.actions);
+        self.keys = self.keys.saturating_add(activity.keys);
+        self.stages_cleared = self.stages_cleared.saturating_add(activity.stages_cleared);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.coins_earned == 0
+            && self.units_spawned == 0
+            && self.enemies_killed == 0
+            && self.actions == 0
+            && self.stages_cleared == 0
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsRange {
+    Hour,
+    Today,
+    Week,
+}
+
+impl StatsRange {
+    // 範囲の始まり（分単位）。「今日」はローカル時刻の 0 時から
+    fn start_minute(self, now: i64) -> i64 {
+        let start = match self {
+            StatsRange::Hour => now - 60 * 60,
+            StatsRange::Today => Local
+                .timestamp_opt(now, 0)
+                .single()
+                .and_then(|time| schedule::local_midnight(time.date_naive()))
+                .unwrap_or(now - 24 * 60 * 60),
+            StatsRange::Week => now - 7 * 24 * 60 * 60,
+        };
+        start.div_euclid(60)
+    }
+}
+
+#[derive(Clone, Serialize, Debug, Default, TS)]
+pub struct StatsTotals {
+    #[ts(type = "number")]
+    pub coins_earned: u64,
+    #[ts(type = "number")]
+    pub units_spawned: u64,
+    #[ts(type = "number")]
+    pub enemies_killed: u64,
+    #[ts(type = "number")]
+    pub actions: u64,
+    #[ts(type = "number")]
+    pub keys: u64,
+    #[ts(type = "number")]
+    pub stages_cleared: u64,
+    // 何か起きた分だけで割った平均
+    pub active_minutes: u32,
+    pub average_apm: f32,
+    pub peak_apm: u32,
+}
+
+impl StatsTotals {
+    fn from_samples<'a>(samples: impl Iterator<Item = &'a StatsSample>) -> Self {
+        let mut totals = Self::default();
+        for sample in samples.filter(|sample| !sample.is_empty()) {
+            totals.coins_earned += sample.coins_earned;
+            totals.units_spawned += sample.units_spawned as u64;
+            totals.enemies_killed += sample.enemies_killed as u64;
+            totals.actions += sample.actions as u64;
+            totals.keys += sample.keys as u64;
+            totals.stages_cleared += sample.stages_cleared as u64;
+            totals.active_minutes += 1;
+            totals.peak_apm = totals.peak_apm.max(sample.actions);
+        }
+        if totals.active_minutes > 0 {
+            totals.average_apm = totals.actions as f32 / totals.active_minutes as f32;
+        }
+        totals
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct StatsSummary {
+    pub current: StatsSample,
+    pub last_hour: StatsTotals,
+    pub today: StatsTotals,
+    pub week: StatsTotals,
+}
+
+fn stats_file_path() -> Option<PathBuf> {
+    profile::data_dir().map(|dir| dir.join("stats_history.json"))
+}
+
+// 締めた分のサンプルのリングバッファと集計中の 1 分
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct StatsHistory {
+    samples: VecDeque<StatsSample>,
+    #[serde(skip)]
+    current: StatsSample,
+    // 日ごとのまとめを通知した最後の日（"2026-10-16"）
+    #[serde(default)]
+    pub recap_sent_on: Option<String>,
+}
+
+impl StatsHistory {
+    pub fn load() -> Self {
+        let mut history: Self = stats_file_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        // 先に確保しておき、毎分の追加で再確保しない
+        history
+            .samples
+            .reserve(MAX_SAMPLES.saturating_sub(history.samples.len()));
+        history
+    }
+
+    // 書けないときは save_writer がメモリに残して試し直す
+    pub fn save(&self) {
+        if let Some(path) = stats_file_path() {
+            if let Ok(json) = serde_json::to_vec(self) {
+                save_writer::submit(SaveJob::new(path, json));
+            }
+        }
+    }
+
+    // 分が変わったら集計中の分を締める。締めたら true（保存の合図）
+    pub fn record(&mut self, now: i64, activity: Activity) -> bool {
+        let minute = now.div_euclid(60);
+        let mut rolled = false;
+        if minute != self.current.minute {
+            rolled = self.close_current();
+            self.current = StatsSample {
+                minute,
+                ..StatsSample::default()
+            };
+        }
+        self.current.add(activity);
+        rolled
+    }
+
+    // 何も起きなかった分は残さない（グラフ側で 0 として扱う）
+    fn close_current(&mut self) -> bool {
+        if self.current.is_empty() {
+            return false;
+        }
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(self.current);
+        true
+    }
+
+    // 終了時・プロファイル切り替え時に集計中の分も締める
+    pub fn flush(&mut self) {
+        self.close_current();
+        self.current = StatsSample::default();
+    }
+
+    fn in_range(&self, range: StatsRange, now: i64) -> impl Iterator<Item = &StatsSample> {
+        let start = range.start_minute(now);
+        self.samples
+            .iter()
+            .chain(std::iter::once(&self.current))
+            .filter(move |sample| sample.minute >= start)
+    }
+
+    // [start, end) の UNIX 時刻の範囲の合計
+    pub fn totals_between(&self, start: i64, end: i64) -> StatsTotals {
+        let (start, end) = (start.div_euclid(60), end.div_euclid(60));
+        StatsTotals::from_samples(
+            self.samples
+                .iter()
+                .chain(std::iter::once(&self.current))
+                .filter(|sample| sample.minute >= start && sample.minute < end),
+        )
+    }
+
+    pub fn series(&self, range: StatsRange, now: i64) -> Vec<StatsSample> {
+        self.in_range(range, now)
+            .filter(|sample| !sample.is_empty())
+            .copied()
+            .collect()
+    }
+
+    pub fn summary(&self, now: i64) -> StatsSummary {
+        StatsSummary {
+            current: self.current,
+            last_hour: StatsTotals::from_samples(self.in_range(StatsRange::Hour, now)),
+            today: StatsTotals::from_samples(self.in_range(StatsRange::Today, now)),
+            week: StatsTotals::from_samples(self.in_range(StatsRange::Week, now)),
+        }
+    }
+}
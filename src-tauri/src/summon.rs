@@ -0,0 +1,109 @@
+use crate::error::GameError;
+use crate::game::UnitType;
+use crate::stage;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// コインで呼び出せるユニット（ユニット種別, 基本コスト, クールダウン秒）
+const SUMMONS: [(UnitType, u64, f32); 4] = [
+    (UnitType::Small, 25, 5.0),
+    (UnitType::Medium, 80, 10.0),
+    (UnitType::Large, 250, 20.0),
+    (UnitType::Hero, 1000, 60.0),
+];
+// 同じステージで呼ぶたびにコストが上がる
+const COST_GROWTH: f64 = 1.5;
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct SummonStatus {
+    pub unit_type: UnitType,
+    #[ts(type = "number")]
+    pub cost: u64,
+    pub purchases: u32,
+    pub cooldown_remaining: f32,
+    pub cooldown_total: f32,
+}
+
+// 購入回数と残りのクールダウン（SUMMONS と同じ順）
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Summons {
+    purchases: [u32; SUMMONS.len()],
+    cooldowns: [f32; SUMMONS.len()],
+}
+
+impl Summons {
+    fn slot(unit_type: UnitType) -> usize {
+        SUMMONS
+            .iter()
+            .position(|&(kind, _, _)| kind == unit_type)
+            .unwrap_or(0)
+    }
+
+    // 後のステージほど敵と報酬に合わせて高くなる
+    fn cost_at(&self, slot: usize, stage: u32) -> u64 {
+        let (_, base, _) = SUMMONS[slot];
+        (base as f64
+            * stage::stat_multiplier(stage) as f64
+            * COST_GROWTH.powi(self.purchases[slot].min(64) as i32))
+        .min(u64::MAX as f64) as u64
+    }
+
+    // 呼び出せるならそのコスト
+    pub fn check(&self, unit_type: UnitType, stage: u32, coins: u64) -> Result<u64, GameError> {
+        let slot = Self::slot(unit_type);
+        if self.cooldowns[slot] > 0.0 {
+            return Err(GameError::SummonOnCooldown {
+                remaining_secs: self.cooldowns[slot],
+            });
+        }
+        let cost = self.cost_at(slot, stage);
+        if coins < cost {
+            return Err(GameError::NotEnoughCoins {
+                needed: cost,
+                have: coins,
+            });
+        }
+        Ok(cost)
+    }
+
+    pub fn record(&mut self, unit_type: UnitType) {
+        let slot = Self::slot(unit_type);
+        self.purchases[slot] = self.purchases[slot].saturating_add(1);
+        self.cooldowns[slot] = SUMMONS[slot].2;
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - delta).max(0.0);
+        }
+    }
+
+    // ステージが変わるとコストは元に戻る（クールダウンはそのまま）
+    pub fn new_stage(&mut self) {
+        self.purchases = Default::default();
+    }
+
+    // 読み込んだ値が壊れていてもすぐ呼び出せる状態に戻す
+    pub fn repair(&mut self) {
+        for (cooldown, &(_, _, total)) in self.cooldowns.iter_mut().zip(&SUMMONS) {
+            if !cooldown.is_finite() {
+                *cooldown = 0.0;
+            }
+            *cooldown = cooldown.clamp(0.0, total);
+        }
+    }
+
+    pub fn status(&self, stage: u32) -> Vec<SummonStatus> {
+        SUMMONS
+            .iter()
+            .enumerate()
+            .map(|(slot, &(unit_type, _, total))| SummonStatus {
+                unit_type,
+                cost: self.cost_at(slot, stage),
+                purchases: self.purchases[slot],
+                cooldown_remaining: self.cooldowns[slot],
+                cooldown_total: total,
+            })
+            .collect()
+    }
+}
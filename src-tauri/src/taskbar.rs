@@ -0,0 +1,111 @@
+use crate::game::GameState;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+// 自軍の基地の体力がこれを下回ったら危険として表示する
+const DANGER_RATIO: f32 = 0.3;
+
+// タスクバー・Dock に出す内容。値が変わったときだけ OS の API を呼ぶ
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Indicator {
+    // 0〜100。None ならプログレスバーを消す
+    progress: Option<u64>,
+    danger: bool,
+    unopened_chests: usize,
+}
+
+impl Indicator {
+    // 基地が危ないときは基地の体力、ボス戦中はボスの残り体力
+    pub fn from_game(game: &GameState) -> Self {
+        let percent = |hp: f32, max: f32| {
+            if max > 0.0 {
+                ((hp / max).clamp(0.0, 1.0) * 100.0).round() as u64
+            } else {
+                0
+            }
+        };
+        let base_ratio = if game.max_player_base_hp > 0.0 {
+            game.player_base_hp / game.max_player_base_hp
+        } else {
+            1.0
+        };
+        let danger = base_ratio < DANGER_RATIO;
+        let progress = if danger {
+            Some(percent(game.player_base_hp, game.max_player_base_hp))
+        } else {
+            game.boss_status().map(|boss| percent(boss.hp, boss.max_hp))
+        };
+        Self {
+            progress,
+            danger,
+            unopened_chests: game.chests.chests.len(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Taskbar {
+    last: Option<Indicator>,
+}
+
+impl Taskbar {
+    // ゲームループから 1 秒ごとに呼ぶ
+    pub fn refresh(&mut self, app: &AppHandle, indicator: Indicator) {
+        if self.last == Some(indicator) {
+            return;
+        }
+        let Some(window) = app.get_webview_window("main") else {
+            return;
+        };
+        let state = match indicator.progress {
+            Some(progress) => ProgressBarState {
+                status: Some(if indicator.danger {
+                    ProgressBarStatus::Error
+                } else {
+                    ProgressBarStatus::Normal
+                }),
+                progress: Some(progress),
+            },
+            None => ProgressBarState {
+                status: Some(ProgressBarStatus::None),
+                progress: None,
+            },
+        };
+        let _ = window.set_progress_bar(state);
+        if self.last.map(|last| last.unopened_chests) != Some(indicator.unopened_chests) {
+            set_chest_badge(&window, indicator.unopened_chests);
+        }
+        self.last = Some(indicator);
+    }
+}
+
+// Windows にはバッジの数字がないので、未開封の宝箱があれば重ねアイコンの点を出す
+#[cfg(target_os = "windows")]
+fn set_chest_badge(window: &tauri::WebviewWindow, unopened: usize) {
+    let icon = (unopened > 0).then(|| tauri::image::Image::new_owned(badge_dot(), 16, 16));
+    let _ = window.set_overlay_icon(icon);
+}
+
+#[cfg(not(target_os = "windows"))]
+fn set_chest_badge(window: &tauri::WebviewWindow, unopened: usize) {
+    let count = (unopened > 0).then_some(unopened as i64);
+    let _ = window.set_badge_count(count);
+}
+
+// 16x16 の赤い丸（RGBA）
+#[cfg(target_os = "windows")]
+fn badge_dot() -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(16 * 16 * 4);
+    for y in 0..16 {
+        for x in 0..16 {
+            let (dx, dy) = (x as f32 - 7.5, y as f32 - 7.5);
+            let inside = dx * dx + dy * dy <= 7.5 * 7.5;
+            rgba.extend_from_slice(if inside {
+                &[220, 40, 40, 255]
+            } else {
+                &[0, 0, 0, 0]
+            });
+        }
+    }
+    rgba
+}
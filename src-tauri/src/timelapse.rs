@@ -0,0 +1,292 @@
+use crate::game::{unix_timestamp, GameState, Unit, UnitType, FIELD_LENGTH, STEP_SECS};
+use crate::profile;
+use crate::replay::Replay;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+
+pub const DEFAULT_MINUTES: u32 = 5;
+pub const MAX_MINUTES: u32 = 60;
+const WIDTH: usize = 480;
+const HEIGHT: usize = 160;
+// 長い記録でもファイルが大きくなりすぎないよう間引く
+const MAX_FRAMES: u64 = 240;
+// 再生時間の目安（1/100 秒単位で 1 コマ 2〜50）
+const PLAYBACK_CS: u64 = 1500;
+const BASE_WIDTH: usize = 16;
+const GROUND_Y: usize = HEIGHT - 16;
+const FIELD_TOP: usize = 24;
+
+// 0 背景 / 1 地面 / 2 味方 / 3 英雄 / 4 敵 / 5 飛行する敵 / 6 前線 / 7 経過 / 8 基地の削れた分
+const PALETTE: [[u8; 3]; 16] = [
+    [18, 22, 38],
+    [70, 74, 90],
+    [80, 150, 255],
+    [170, 220, 255],
+    [240, 80, 80],
+    [255, 160, 110],
+    [250, 210, 60],
+    [90, 200, 120],
+    [60, 40, 48],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+];
+const BACKGROUND: u8 = 0;
+const GROUND: u8 = 1;
+const PLAYER: u8 = 2;
+const HERO: u8 = 3;
+const ENEMY: u8 = 4;
+const FLYING: u8 = 5;
+const FRONT_LINE: u8 = 6;
+const PROGRESS: u8 = 7;
+const DAMAGED: u8 = 8;
+
+#[derive(Clone, Serialize, Debug)]
+pub struct TimelapseExport {
+    pub path: String,
+    pub frames: u32,
+    // 書き出した区間の長さ（ゲーム内の秒数）
+    pub seconds: f32,
+    pub bytes: u64,
+}
+
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            pixels: vec![BACKGROUND; WIDTH * HEIGHT],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for row in y.min(HEIGHT)..(y + height).min(HEIGHT) {
+            let start = row * WIDTH;
+            self.pixels[start + x.min(WIDTH)..start + (x + width).min(WIDTH)].fill(color);
+        }
+    }
+}
+
+fn field_x(position: f32) -> usize {
+    let span = (WIDTH - 2 * BASE_WIDTH) as f32;
+    BASE_WIDTH + ((position / FIELD_LENGTH).clamp(0.0, 1.0) * span) as usize
+}
+
+fn draw_base(canvas: &mut Canvas, x: usize, hp: f32, max_hp: f32, color: u8) {
+    let full = GROUND_Y - FIELD_TOP;
+    let ratio = if max_hp > 0.0 {
+        (hp / max_hp).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let height = (full as f32 * ratio) as usize;
+    canvas.fill(x, FIELD_TOP, BASE_WIDTH - 4, full - height, DAMAGED);
+    canvas.fill(x, GROUND_Y - height, BASE_WIDTH - 4, height, color);
+}
+
+fn draw_unit(canvas: &mut Canvas, unit: &Unit, lanes: usize) {
+    let size = match unit.unit_type {
+        UnitType::Small => 2,
+        UnitType::Medium => 3,
+        UnitType::Large => 5,
+        UnitType::Hero => 6,
+    };
+    let color = match (unit.is_player, unit.unit_type, unit.is_flying) {
+        (true, UnitType::Hero, _) => HERO,
+        (true, _, _) => PLAYER,
+        (false, _, true) => FLYING,
+        (false, _, false) => ENEMY,
+    };
+    let lane_height = (GROUND_Y - FIELD_TOP) / lanes.max(1);
+    let lane = (unit.lane as usize).min(lanes.saturating_sub(1));
+    // 同じ位置に重なったユニットが潰れないよう ID で少しずらす
+    let jitter = (unit.id as usize % 5) * lane_height / 8;
+    let y = FIELD_TOP + lane * lane_height + lane_height / 4 + jitter;
+    canvas.fill(
+        field_x(unit.position).saturating_sub(size / 2),
+        y,
+        size,
+        size,
+        color,
+    );
+}
+
+// 1 コマ分の戦場（左が味方の基地、右が敵の基地。上端の帯は書き出した区間の経過）
+fn draw(game: &GameState, progress: f32) -> Vec<u8> {
+    let mut canvas = Canvas::new();
+    canvas.fill(0, 0, (WIDTH as f32 * progress) as usize, 4, PROGRESS);
+    canvas.fill(0, GROUND_Y, WIDTH, 2, GROUND);
+    draw_base(
+        &mut canvas,
+        2,
+        game.player_base_hp,
+        game.max_player_base_hp,
+        PLAYER,
+    );
+    draw_base(
+        &mut canvas,
+        WIDTH - BASE_WIDTH + 2,
+        game.enemy_base_hp,
+        game.max_enemy_base_hp,
+        ENEMY,
+    );
+    let front = field_x(game.front_line() * FIELD_LENGTH);
+    canvas.fill(
+        front,
+        FIELD_TOP - 6,
+        1,
+        GROUND_Y - FIELD_TOP + 6,
+        FRONT_LINE,
+    );
+    let lanes = game.lane_count() as usize;
+    for unit in game.enemy_units.iter().chain(&game.player_units) {
+        draw_unit(&mut canvas, unit, lanes);
+    }
+    canvas.pixels
+}
+
+// GIF の LZW 圧縮（コードは下位ビットから詰める）
+fn lzw(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut code_size = min_code_size + 1;
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0u32;
+    let mut emit = |code: u16, size: u8, out: &mut Vec<u8>| {
+        bits |= (code as u32) << bit_count;
+        bit_count += size as u32;
+        while bit_count >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    emit(clear, code_size, &mut out);
+    let mut current: Option<u16> = None;
+    for &pixel in pixels {
+        let Some(prefix) = current else {
+            current = Some(pixel as u16);
+            continue;
+        };
+        if let Some(&code) = codes.get(&(prefix, pixel)) {
+            current = Some(code);
+            continue;
+        }
+        emit(prefix, code_size, &mut out);
+        if next < 4096 {
+            if next == 1 << code_size {
+                code_size += 1;
+            }
+            codes.insert((prefix, pixel), next);
+            next += 1;
+        } else {
+            // 辞書が一杯になったら作り直す
+            emit(clear, code_size, &mut out);
+            codes.clear();
+            next = end + 1;
+            code_size = min_code_size + 1;
+        }
+        current = Some(pixel as u16);
+    }
+    if let Some(prefix) = current {
+        emit(prefix, code_size, &mut out);
+    }
+    emit(end, code_size, &mut out);
+    if bit_count > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+struct Gif {
+    bytes: Vec<u8>,
+}
+
+impl Gif {
+    // 16 色のパレットで、繰り返し再生する
+    fn new() -> Self {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend_from_slice(&(WIDTH as u16).to_le_bytes());
+        bytes.extend_from_slice(&(HEIGHT as u16).to_le_bytes());
+        bytes.extend_from_slice(&[0xF3, 0, 0]);
+        for color in PALETTE {
+            bytes.extend_from_slice(&color);
+        }
+        bytes.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        bytes.extend_from_slice(b"NETSCAPE2.0");
+        bytes.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+        Self { bytes }
+    }
+
+    fn frame(&mut self, pixels: &[u8], delay_cs: u16) {
+        self.bytes.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        self.bytes.extend_from_slice(&delay_cs.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&[0x00, 0x00, 0x2C, 0, 0, 0, 0]);
+        self.bytes.extend_from_slice(&(WIDTH as u16).to_le_bytes());
+        self.bytes.extend_from_slice(&(HEIGHT as u16).to_le_bytes());
+        self.bytes.extend_from_slice(&[0x00, 4]);
+        for block in lzw(pixels, 4).chunks(255) {
+            self.bytes.push(block.len() as u8);
+            self.bytes.extend_from_slice(block);
+        }
+        self.bytes.push(0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.push(0x3B);
+        self.bytes
+    }
+}
+
+// 記録の最後の minutes 分を再計算してアニメーション GIF にする。コマ数と区間の秒数も返す
+pub fn render(replay: &Replay, minutes: u32) -> (Vec<u8>, u32, f32) {
+    let minutes = minutes.clamp(1, MAX_MINUTES);
+    let window = ((minutes as f32 * 60.0 / STEP_SECS) as u64).min(replay.ticks);
+    let from = replay.ticks - window;
+    let interval = window.div_ceil(MAX_FRAMES).max(1);
+    let expected = window.div_ceil(interval).max(1);
+    let delay_cs = (PLAYBACK_CS / expected).clamp(2, 50) as u16;
+
+    let mut gif = Gif::new();
+    let mut frames = 0u32;
+    let last = replay.run_with(|tick, game| {
+        if tick > from && (tick - from).is_multiple_of(interval) {
+            gif.frame(&draw(game, (tick - from) as f32 / window as f32), delay_cs);
+            frames += 1;
+        }
+    });
+    if frames == 0 {
+        gif.frame(&draw(&last, 1.0), delay_cs);
+        frames = 1;
+    }
+    (gif.finish(), frames, window as f32 * STEP_SECS)
+}
+
+// データフォルダの timelapse に書き出す
+pub fn export(replay: &Replay, minutes: u32) -> Result<TimelapseExport, String> {
+    let (gif, frames, seconds) = render(replay, minutes);
+    let dir = profile::data_dir()
+        .ok_or("Failed to resolve data directory")?
+        .join("timelapse");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("timelapse-{}.gif", unix_timestamp()));
+    fs::write(&path, &gif).map_err(|e| e.to_string())?;
+    Ok(TimelapseExport {
+        path: path.to_string_lossy().into_owned(),
+        frames,
+        seconds,
+        bytes: gif.len() as u64,
+    })
+}
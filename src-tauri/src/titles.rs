@@ -0,0 +1,101 @@
+use crate::achievements::{self, Achievements};
+use crate::error::GameError;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleSource {
+    Achievement,
+    Raid,
+}
+
+// レイドの報酬ランクで得られる称号 (id, 名前, 必要なランク)
+const RAID_TITLES: [(&str, &str, &[&str]); 3] = [
+    ("raider", "Raider", &["legendary", "epic", "rare", "common"]),
+    ("raid_elite", "Raid Elite", &["legendary", "epic"]),
+    ("boss_slayer", "Boss Slayer", &["legendary"]),
+];
+
+#[derive(Clone, Serialize, Debug)]
+pub struct TitleStatus {
+    pub id: String,
+    pub name: String,
+    pub source: TitleSource,
+    pub earned: bool,
+    pub active: bool,
+}
+
+// 獲得した称号（実績の ID をそのまま称号の ID に使う）。サーバーと同期し、一覧に表示される
+#[derive(Clone, Serialize, Deserialize, Default, Debug, TS)]
+pub struct Titles {
+    #[serde(default)]
+    pub earned: Vec<String>,
+    #[serde(default)]
+    pub active: Option<String>,
+}
+
+impl Titles {
+    fn earn(&mut self, id: &str) {
+        if !self.is_earned(id) {
+            self.earned.push(id.to_string());
+        }
+    }
+
+    pub fn is_earned(&self, id: &str) -> bool {
+        self.earned.iter().any(|earned| earned == id)
+    }
+
+    pub fn record_achievements(&mut self, achievements: &Achievements) {
+        for unlocked in &achievements.unlocked {
+            self.earn(&unlocked.id);
+        }
+    }
+
+    pub fn record_raid(&mut self, tier: &str) {
+        for (id, _, tiers) in RAID_TITLES {
+            if tiers.contains(&tier) {
+                self.earn(id);
+            }
+        }
+    }
+
+    // 別の端末で獲得した称号を合わせる（称号は失われない）
+    pub fn absorb(&mut self, other: &Titles) {
+        for id in &other.earned {
+            self.earn(id);
+        }
+        if other.active.is_some() {
+            self.active = other.active.clone();
+        }
+    }
+
+    // None で称号を外す
+    pub fn set_active(&mut self, id: Option<String>) -> Result<(), GameError> {
+        if let Some(id) = &id {
+            if !self.is_earned(id) {
+                return Err(GameError::TitleNotEarned { id: id.clone() });
+            }
+        }
+        self.active = id;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<TitleStatus> {
+        let achievement_titles =
+            achievements::titles().map(|(id, name)| (id, name, TitleSource::Achievement));
+        let raid_titles = RAID_TITLES
+            .iter()
+            .map(|&(id, name, _)| (id, name, TitleSource::Raid));
+        achievement_titles
+            .chain(raid_titles)
+            .map(|(id, name, source)| TitleStatus {
+                id: id.to_string(),
+                name: name.to_string(),
+                source,
+                earned: self.is_earned(id),
+                active: self.active.as_deref() == Some(id),
+            })
+            .collect()
+    }
+}
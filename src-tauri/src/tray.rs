@@ -0,0 +1,185 @@
+use crate::game::GameState;
+use crate::i18n;
+use crate::loop_control::LoopControl;
+use crate::multiplayer::MultiplayerApi;
+use crate::numbers;
+use crate::widget::WidgetMode;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Wry};
+
+const TRAY_ID: &str = "main";
+
+// 後から表示を変えるメニュー項目と、最後に設定したツールチップ
+pub struct Tray {
+    open: MenuItem<Wry>,
+    pause: CheckMenuItem<Wry>,
+    widget: CheckMenuItem<Wry>,
+    sync: MenuItem<Wry>,
+    quit: MenuItem<Wry>,
+    tooltip: Mutex<String>,
+}
+
+pub fn build(app: &AppHandle) -> tauri::Result<()> {
+    let open = MenuItem::with_id(app, "open", i18n::text("tray.open"), true, None::<&str>)?;
+    let pause = CheckMenuItem::with_id(
+        app,
+        "pause",
+        i18n::text("tray.pause"),
+        true,
+        false,
+        None::<&str>,
+    )?;
+    let widget = CheckMenuItem::with_id(
+        app,
+        "widget",
+        i18n::text("tray.widget"),
+        true,
+        true,
+        None::<&str>,
+    )?;
+    let sync = MenuItem::with_id(app, "sync", i18n::text("tray.sync"), true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", i18n::text("tray.quit"), true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let menu = Menu::with_items(app, &[&open, &pause, &widget, &sync, &separator, &quit])?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Clicker Clicker Clicker")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            // 左クリックでメインウィンドウを開く（メニューは右クリック）
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                open_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    app.manage(Tray {
+        open,
+        pause,
+        widget,
+        sync,
+        quit,
+        tooltip: Mutex::new(String::new()),
+    });
+    Ok(())
+}
+
+// 言語を切り替えたらメニューの文言を付け直す（ツールチップは次の refresh で変わる）
+pub fn relabel(app: &AppHandle) {
+    let Some(tray) = app.try_state::<Tray>() else {
+        return;
+    };
+    let _ = tray.open.set_text(i18n::text("tray.open"));
+    let _ = tray.pause.set_text(i18n::text("tray.pause"));
+    let _ = tray.widget.set_text(i18n::text("tray.widget"));
+    let _ = tray.sync.set_text(i18n::text("tray.sync"));
+    let _ = tray.quit.set_text(i18n::text("tray.quit"));
+    refresh(app);
+}
+
+pub fn open_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        "open" => open_main_window(app),
+        "pause" => {
+            if let Some(loop_control) = app.try_state::<Arc<LoopControl>>() {
+                if loop_control.is_paused() {
+                    loop_control.resume();
+                } else {
+                    loop_control.pause();
+                }
+            }
+        }
+        "widget" => {
+            if let Some(widget_mode) = app.try_state::<Arc<WidgetMode>>() {
+                // 実際の表示切り替えはゲームループのスケジュール判定でまとめて行う
+                widget_mode.set_hidden(!widget_mode.is_hidden());
+            }
+        }
+        "sync" => {
+            let (Some(mp_client), Some(game_state)) = (
+                app.try_state::<Arc<dyn MultiplayerApi>>(),
+                app.try_state::<Arc<Mutex<GameState>>>(),
+            ) else {
+                return;
+            };
+            let app = app.clone();
+            let mp_client = Arc::clone(&mp_client);
+            let game_state = Arc::clone(&game_state);
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = crate::sync_now(&app, mp_client.as_ref(), &game_state).await {
+                    println!("[tray] {}", err);
+                }
+            });
+        }
+        "quit" => crate::shutdown(app.clone()),
+        _ => {}
+    }
+    refresh(app);
+}
+
+// ツールチップとチェック状態を現在の状態に合わせる（ゲームループから 1 秒ごとに呼ぶ）
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.try_state::<Tray>() else {
+        return;
+    };
+    if let Some(loop_control) = app.try_state::<Arc<LoopControl>>() {
+        let paused = loop_control.is_paused();
+        if tray
+            .pause
+            .is_checked()
+            .is_ok_and(|checked| checked != paused)
+        {
+            let _ = tray.pause.set_checked(paused);
+        }
+    }
+    if let Some(widget_mode) = app.try_state::<Arc<WidgetMode>>() {
+        let shown = !widget_mode.is_hidden();
+        if tray
+            .widget
+            .is_checked()
+            .is_ok_and(|checked| checked != shown)
+        {
+            let _ = tray.widget.set_checked(shown);
+        }
+    }
+    if let Some(game_state) = app.try_state::<Arc<Mutex<GameState>>>() {
+        let tooltip = {
+            let game = game_state.lock();
+            i18n::format(
+                "tray.tooltip",
+                &[
+                    ("stage", &game.stage),
+                    ("coins", &numbers::format_compact(game.coins)),
+                ],
+            )
+        };
+        let mut last = tray.tooltip.lock();
+        if *last != tooltip {
+            if let Some(icon) = app.tray_by_id(TRAY_ID) {
+                let _ = icon.set_tooltip(Some(&tooltip));
+            }
+            *last = tooltip;
+        }
+    }
+}
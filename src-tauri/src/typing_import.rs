@@ -0,0 +1,275 @@
+use crate::error::GameError;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+// 読み込むファイルの大きさと行数の上限
+pub const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_ROWS: usize = 200_000;
+// 換算する打鍵数の上限（これを超える分は切り捨てる）
+pub const MAX_IMPORTED_KEYSTROKES: u64 = 5_000_000;
+pub const KEYSTROKES_PER_COIN: u64 = 100;
+// 1 回のテストとしてありえない記録は数えない
+const MAX_WPM: f64 = 300.0;
+const MAX_TEST_SECS: f64 = 60.0 * 60.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TypingSource {
+    MonkeyType,
+    TypingIo,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct TypingHistory {
+    pub source: TypingSource,
+    pub tests: u64,
+    pub keystrokes: u64,
+    pub skipped_rows: u64,
+}
+
+// 取り込み済みの記録（セーブごとに 1 回だけ）
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TypingImport {
+    pub source: TypingSource,
+    pub imported_at: i64,
+    pub keystrokes: u64,
+    pub coins: u64,
+}
+
+// 見出し行の列名で形式を判別する
+enum Columns {
+    // charStats は "正解;誤り;余分;打ち漏らし"
+    MonkeyType {
+        char_stats: Option<usize>,
+        wpm: usize,
+        duration: usize,
+    },
+    // 文字数の列があればそれを、なければ WPM と時間から打鍵数を出す
+    TypingIo {
+        chars: Option<usize>,
+        wpm: Option<usize>,
+        duration: Option<usize>,
+    },
+}
+
+pub fn read_file(path: &Path) -> Result<TypingHistory, GameError> {
+    let file = std::fs::File::open(path).map_err(GameError::storage)?;
+    let size = file.metadata().map_err(GameError::storage)?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(GameError::TypingHistoryTooLarge {
+            max_bytes: MAX_FILE_BYTES,
+        });
+    }
+    let mut text = String::new();
+    file.take(MAX_FILE_BYTES)
+        .read_to_string(&mut text)
+        .map_err(|_| GameError::TypingHistoryUnsupported)?;
+    parse(&text)
+}
+
+pub fn parse(text: &str) -> Result<TypingHistory, GameError> {
+    let mut lines = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty());
+    let header = split_row(lines.next().ok_or(GameError::TypingHistoryUnsupported)?);
+    let find = |names: &[&str]| {
+        header
+            .iter()
+            .position(|column| names.iter().any(|name| column.eq_ignore_ascii_case(name)))
+    };
+    let (source, columns) = match (find(&["charStats"]), find(&["testDuration"])) {
+        (char_stats, Some(duration)) => (
+            TypingSource::MonkeyType,
+            Columns::MonkeyType {
+                char_stats,
+                wpm: find(&["wpm"]).ok_or(GameError::TypingHistoryUnsupported)?,
+                duration,
+            },
+        ),
+        _ => {
+            let columns = Columns::TypingIo {
+                chars: find(&["keystrokes", "characters", "chars"]),
+                wpm: find(&["wpm", "speed"]),
+                duration: find(&["duration", "time", "seconds"]),
+            };
+            if let Columns::TypingIo {
+                chars: None,
+                wpm,
+                duration,
+            } = &columns
+            {
+                if wpm.is_none() || duration.is_none() {
+                    return Err(GameError::TypingHistoryUnsupported);
+                }
+            }
+            (TypingSource::TypingIo, columns)
+        }
+    };
+
+    let mut history = TypingHistory {
+        source,
+        tests: 0,
+        keystrokes: 0,
+        skipped_rows: 0,
+    };
+    for line in lines.take(MAX_ROWS) {
+        let row = split_row(line);
+        match row_keystrokes(&columns, &row) {
+            Some(keystrokes) => {
+                history.tests += 1;
+                history.keystrokes = history.keystrokes.saturating_add(keystrokes);
+            }
+            None => history.skipped_rows += 1,
+        }
+    }
+    if history.tests == 0 {
+        return Err(GameError::TypingHistoryUnsupported);
+    }
+    Ok(history)
+}
+
+fn row_keystrokes(columns: &Columns, row: &[String]) -> Option<u64> {
+    let number = |index: usize| -> Option<f64> {
+        row.get(index)?
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite() && *value >= 0.0)
+    };
+    // WPM は 1 語 5 打鍵
+    let from_speed = |wpm: f64, secs: f64| {
+        (wpm <= MAX_WPM && secs <= MAX_TEST_SECS).then(|| (wpm * 5.0 * secs / 60.0).round() as u64)
+    };
+    match *columns {
+        Columns::MonkeyType {
+            char_stats,
+            wpm,
+            duration,
+        } => {
+            let (wpm, secs) = (number(wpm)?, number(duration)?);
+            let limit = from_speed(MAX_WPM, secs)?;
+            let typed = char_stats.and_then(|index| {
+                let parts: Vec<u64> = row
+                    .get(index)?
+                    .split(';')
+                    .map(|part| part.trim().parse().ok())
+                    .collect::<Option<_>>()?;
+                // 打ち漏らしは打鍵していないので数えない
+                Some(parts.iter().take(3).sum::<u64>())
+            });
+            match typed {
+                Some(typed) if typed <= limit => Some(typed),
+                Some(_) => None,
+                None => from_speed(wpm, secs),
+            }
+        }
+        Columns::TypingIo {
+            chars,
+            wpm,
+            duration,
+        } => {
+            let speed = wpm
+                .zip(duration)
+                .and_then(|(wpm, duration)| from_speed(number(wpm)?, number(duration)?));
+            match chars.and_then(number) {
+                Some(chars) => {
+                    // 時間の列があれば、その時間で打てない数は弾く
+                    let limit = duration
+                        .and_then(number)
+                        .and_then(|secs| from_speed(MAX_WPM, secs))
+                        .unwrap_or(u64::MAX);
+                    let chars = chars.round() as u64;
+                    (chars <= limit).then_some(chars)
+                }
+                None => speed,
+            }
+        }
+    }
+}
+
+// 引用符で囲まれた列（"" は引用符 1 文字）に対応した 1 行の分割
+fn split_row(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_split_on_commas_outside_quotes() {
+        assert_eq!(
+            split_row("a, \"b,c\" ,\"say \"\"hi\"\"\",,d\r"),
+            ["a", "b,c", "say \"hi\"", "", "d"]
+        );
+        assert_eq!(split_row(""), [""]);
+    }
+
+    // 空行は読み飛ばし、数えられない行は件数だけ残す
+    #[test]
+    fn monkeytype_history_counts_typed_characters() {
+        let csv = "\u{feff}_id,wpm,acc,testDuration,charStats\n\
+                   \n\
+                   1,60,98,30,\"140;2;1;3\"\n\
+                   2,60,98,30,\n\
+                   \n\
+                   3,abc,98,30,\n\
+                   4,60,98,30,\"99999;0;0;0\"\n\
+                   5,60,98\n";
+        let history = parse(csv).unwrap();
+        assert_eq!(history.source, TypingSource::MonkeyType);
+        assert_eq!(history.tests, 2);
+        // 打ち漏らしを除いた 143 と、charStats がない行の WPM からの 150
+        assert_eq!(history.keystrokes, 143 + 150);
+        assert_eq!(history.skipped_rows, 3);
+    }
+
+    #[test]
+    fn typing_io_history_uses_characters_or_speed() {
+        let history =
+            parse("date,chars,wpm,duration\nmon,250,60,60\ntue,,60,60\nwed,1000000,60,60\n")
+                .unwrap();
+        assert_eq!(history.source, TypingSource::TypingIo);
+        assert_eq!((history.tests, history.keystrokes), (2, 250 + 300));
+        assert_eq!(history.skipped_rows, 1);
+
+        let history = parse("Speed,Time\n40,30\n-5,30\n").unwrap();
+        assert_eq!((history.tests, history.keystrokes), (1, 100));
+        assert_eq!(history.skipped_rows, 1);
+    }
+
+    #[test]
+    fn unknown_or_empty_files_are_unsupported() {
+        for csv in [
+            "",
+            "\n\n",
+            "name,score\nalice,10\n",
+            "wpm,duration\n",
+            "wpm,duration\nx,y\n",
+        ] {
+            assert_eq!(
+                parse(csv).unwrap_err(),
+                GameError::TypingHistoryUnsupported,
+                "{:?}",
+                csv
+            );
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::ability::UnitAbility;
+use crate::game::Unit;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+// 全ユニットを含むスナップショットを送る間隔（それ以外のフレームは差分だけ）
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+// これ未満の位置・HP の変化は送らない
+const CHANGE_EPSILON: f32 = 0.01;
+// 能力ゲージの刻み数（刻みが変わったときだけ送る）
+const COOLDOWN_STEPS: f32 = 20.0;
+
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct UnitMove {
+    pub id: u32,
+    pub position: f32,
+    pub hp: f32,
+}
+
+#[derive(Default)]
+pub struct UnitDiff {
+    pub spawned: Vec<Unit>,
+    pub removed: Vec<u32>,
+    pub moved: Vec<UnitMove>,
+}
+
+// フロントエンドに送ったユニットの状態を覚えておき、フレーム間の差分を作る
+#[derive(Default)]
+pub struct UnitDiffTracker {
+    // id -> (位置, HP)
+    known: HashMap<u32, (f32, f32)>,
+    last_snapshot: Option<Instant>,
+}
+
+impl UnitDiffTracker {
+    pub fn diff<'a>(&mut self, units: impl Iterator<Item = &'a Unit>) -> UnitDiff {
+        let mut diff = UnitDiff::default();
+        let mut current = HashMap::with_capacity(self.known.len());
+        for unit in units {
+            match self.known.get(&unit.id) {
+                None => diff.spawned.push(unit.clone()),
+                Some(&(position, hp))
+                    if (position - unit.position).abs() >= CHANGE_EPSILON
+                        || (hp - unit.hp).abs() >= CHANGE_EPSILON =>
+                {
+                    diff.moved.push(UnitMove {
+                        id: unit.id,
+                        position: unit.position,
+                        hp: unit.hp,
+                    });
+                }
+                Some(_) => {}
+            }
+            current.insert(unit.id, (unit.position, unit.hp));
+        }
+        diff.removed = self
+            .known
+            .keys()
+            .filter(|id| !current.contains_key(id))
+            .copied()
+            .collect();
+        self.known = current;
+        diff
+    }
+
+    // スナップショットを送る時刻なら true
+    pub fn snapshot_due(&mut self) -> bool {
+        if self
+            .last_snapshot
+            .is_some_and(|t| t.elapsed() < SNAPSHOT_INTERVAL)
+        {
+            return false;
+        }
+        self.last_snapshot = Some(Instant::now());
+        true
+    }
+
+    // 次のフレームで全体を送り直す（放置明けなど）
+    pub fn force_snapshot(&mut self) {
+        self.last_snapshot = None;
+    }
+}
+
+// 発動型の能力を持つユニットの準備状況（ready は 0.0〜1.0、1.0 で発動可能）
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct AbilityCooldown {
+    pub id: u32,
+    pub ability: UnitAbility,
+    pub ready: f32,
+}
+
+// ユニット一覧とは別に、能力ゲージの変化だけを送る
+#[derive(Default)]
+pub struct CooldownTracker {
+    // id -> 送った刻み
+    known: HashMap<u32, f32>,
+}
+
+impl CooldownTracker {
+    pub fn diff<'a>(&mut self, units: impl Iterator<Item = &'a Unit>) -> Vec<AbilityCooldown> {
+        let mut changed = Vec::new();
+        let mut current = HashMap::with_capacity(self.known.len());
+        for unit in units {
+            let Some(ready) = unit.ability_readiness() else {
+                continue;
+            };
+            let ready = (ready * COOLDOWN_STEPS).floor() / COOLDOWN_STEPS;
+            if self.known.get(&unit.id) != Some(&ready) {
+                changed.push(AbilityCooldown {
+                    id: unit.id,
+                    ability: unit.ability,
+                    ready,
+                });
+            }
+            current.insert(unit.id, ready);
+        }
+        self.known = current;
+        changed
+    }
+
+    // 次の diff で全ユニット分を送り直す
+    pub fn reset(&mut self) {
+        self.known.clear();
+    }
+}
@@ -0,0 +1,251 @@
+use crate::game;
+use crate::i18n;
+use crate::profile;
+use parking_lot::{const_mutex, Mutex};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Once;
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+// この時間ゲームループから生存通知がなければ止まったとみなす（放置中でも約1秒ごとに回る）
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+// 再開の間隔と、RESTART_WINDOW の間に再開する回数の上限（落ち続けるなら諦める）
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const RESTART_WINDOW: Duration = Duration::from_secs(10 * 60);
+const MAX_RESTARTS: usize = 3;
+// クラッシュログに残す件数
+const MAX_INCIDENTS: usize = 100;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    GameLoop,
+    InputHook,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum Failure {
+    Panicked,
+    // 生存通知が途絶えた（スレッドは止められないので再開はしない）
+    Stalled,
+    // panic せずに終わった
+    Stopped,
+}
+
+// クラッシュログの 1 件。subsystem-down イベントでもそのまま送る
+#[derive(Clone, Serialize, Deserialize, Debug, TS)]
+pub struct Incident {
+    pub subsystem: Subsystem,
+    pub failure: Failure,
+    pub message: String,
+    #[ts(type = "number")]
+    pub at: i64,
+    // 最後のセーブから再開したか
+    pub restarted: bool,
+}
+
+// panic フックで拾った最後のメッセージ（発生場所つき）
+static LAST_PANIC: Mutex<Option<String>> = const_mutex(None);
+static PANIC_HOOK: Once = Once::new();
+
+// 既定のフックの出力は残したまま、メッセージを控える
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let message = match info.location() {
+                Some(location) => format!(
+                    "{} ({}:{})",
+                    payload_message(info.payload()),
+                    location.file(),
+                    location.line()
+                ),
+                None => payload_message(info.payload()),
+            };
+            *LAST_PANIC.lock() = Some(message);
+            default_hook(info);
+        }));
+    });
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic".to_string()
+    }
+}
+
+fn crash_log_path() -> Option<PathBuf> {
+    profile::data_dir().map(|dir| dir.join("crash_log.json"))
+}
+
+fn load_crash_log() -> Vec<Incident> {
+    crash_log_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_crash_log(incidents: &[Incident]) {
+    if let Some(path) = crash_log_path() {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(incidents) {
+            let _ = fs::write(path, json);
+        }
+    }
+}
+
+// ゲームループと入力フックの見張り。panic・終了したスレッドは最後のセーブから再開し、
+// 生存通知が途絶えたゲームループは報告する。どちらもクラッシュログに残す
+pub struct Watchdog {
+    started: Instant,
+    // ゲームループが最後に生存通知した時刻（started からのミリ秒）
+    last_beat: AtomicU64,
+    running: AtomicBool,
+    stall_reported: AtomicBool,
+    restarts: Mutex<Vec<(Subsystem, Instant)>>,
+    incidents: Mutex<Vec<Incident>>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        install_panic_hook();
+        Self {
+            started: Instant::now(),
+            last_beat: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+            stall_reported: AtomicBool::new(false),
+            restarts: Mutex::new(Vec::new()),
+            incidents: Mutex::new(load_crash_log()),
+        }
+    }
+
+    // ゲームループの 1 周ごとに呼ぶ
+    pub fn beat(&self) {
+        let now = self.started.elapsed().as_millis() as u64;
+        self.last_beat.store(now, Ordering::Relaxed);
+    }
+
+    fn since_beat(&self) -> Duration {
+        let last = Duration::from_millis(self.last_beat.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last)
+    }
+
+    pub fn incidents(&self) -> Vec<Incident> {
+        self.incidents.lock().clone()
+    }
+
+    fn record(
+        &self,
+        subsystem: Subsystem,
+        failure: Failure,
+        message: String,
+        restarted: bool,
+    ) -> Incident {
+        let incident = Incident {
+            subsystem,
+            failure,
+            message,
+            at: game::unix_timestamp(),
+            restarted,
+        };
+        eprintln!(
+            "[watchdog] {:?} {:?}: {}",
+            incident.subsystem, incident.failure, incident.message
+        );
+        let mut incidents = self.incidents.lock();
+        incidents.push(incident.clone());
+        let overflow = incidents.len().saturating_sub(MAX_INCIDENTS);
+        incidents.drain(..overflow);
+        save_crash_log(&incidents);
+        incident
+    }
+
+    // 直近の再開が上限に達していなければ再開を数えて true
+    fn allow_restart(&self, subsystem: Subsystem) -> bool {
+        let mut restarts = self.restarts.lock();
+        restarts.retain(|(_, at)| at.elapsed() < RESTART_WINDOW);
+        let recent = restarts.iter().filter(|(s, _)| *s == subsystem).count();
+        if recent >= MAX_RESTARTS {
+            return false;
+        }
+        restarts.push((subsystem, Instant::now()));
+        true
+    }
+
+    // run を動かし続ける。Ok で戻れば正常終了、Err か panic なら記録して on_down を呼び、
+    // 再開できるなら少し待ってから run をやり直す（状態の復元は on_down で行う）
+    pub fn supervise(
+        &self,
+        subsystem: Subsystem,
+        mut run: impl FnMut() -> Result<(), String>,
+        mut on_down: impl FnMut(&Incident),
+    ) {
+        loop {
+            let tracked = subsystem == Subsystem::GameLoop;
+            if tracked {
+                self.beat();
+                self.running.store(true, Ordering::Relaxed);
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(&mut run));
+            if tracked {
+                self.running.store(false, Ordering::Relaxed);
+            }
+            let (failure, message) = match result {
+                Ok(Ok(())) => return,
+                Ok(Err(reason)) => (Failure::Stopped, reason),
+                Err(payload) => (
+                    Failure::Panicked,
+                    LAST_PANIC
+                        .lock()
+                        .take()
+                        .unwrap_or_else(|| payload_message(payload.as_ref())),
+                ),
+            };
+            let restarted = self.allow_restart(subsystem);
+            let incident = self.record(subsystem, failure, message, restarted);
+            on_down(&incident);
+            if !restarted {
+                return;
+            }
+            std::thread::sleep(RESTART_DELAY);
+        }
+    }
+
+    // ゲームループの生存通知を見張る（keep_watching が false になったら終わる）。
+    // 止まったら 1 回だけ報告し、通知が戻れば次の停止をまた報告する
+    pub fn watch(&self, keep_watching: impl Fn() -> bool, on_down: impl Fn(&Incident)) {
+        while keep_watching() {
+            std::thread::sleep(WATCH_INTERVAL);
+            if !keep_watching() || !self.running.load(Ordering::Relaxed) {
+                continue;
+            }
+            let silent = self.since_beat();
+            if silent < STALL_TIMEOUT {
+                self.stall_reported.store(false, Ordering::Relaxed);
+            } else if !self.stall_reported.swap(true, Ordering::Relaxed) {
+                let message = i18n::format("watchdog.stalled", &[("secs", &silent.as_secs())]);
+                let incident = self.record(Subsystem::GameLoop, Failure::Stalled, message, false);
+                on_down(&incident);
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use crate::game::{GameState, Unit, UnitType, FIELD_LENGTH};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+use ts_rs::TS;
+
+// ウィジェットの高さ（物理ピクセル）。コンパクト表示ではコインとステージの 1 行だけ
+pub const WIDGET_HEIGHT: u32 = 80;
+pub const COMPACT_HEIGHT: u32 = 28;
+// 完全に透明にすると見失うので下限を設ける
+pub const MIN_OPACITY: f32 = 0.1;
+pub const MAX_OPACITY: f32 = 1.0;
+pub const MIN_WIDTH_PERCENT: u32 = 10;
+pub const MAX_WIDTH_PERCENT: u32 = 100;
+// ウィジェットへ widget-update を送る頻度（fps）
+pub const DEFAULT_FPS: u32 = 10;
+const MIN_FPS: u32 = 1;
+const MAX_FPS: u32 = 30;
+
+// モニター内での横方向の配置（Full は幅の割合を無視して全幅）
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WidgetAlignment {
+    Left,
+    Center,
+    Right,
+    #[default]
+    Full,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// モニターの下端から y_offset だけ上に、指定の幅と配置で置く
+pub fn placement(
+    monitor: &MonitorInfo,
+    width_percent: u32,
+    alignment: WidgetAlignment,
+    y_offset: i32,
+    compact: bool,
+) -> Placement {
+    let height = if compact {
+        COMPACT_HEIGHT
+    } else {
+        WIDGET_HEIGHT
+    };
+    let width = match alignment {
+        WidgetAlignment::Full => monitor.width,
+        _ => {
+            let percent = width_percent.clamp(MIN_WIDTH_PERCENT, MAX_WIDTH_PERCENT);
+            (monitor.width as u64 * percent as u64 / 100) as u32
+        }
+    };
+    let x = match alignment {
+        WidgetAlignment::Left | WidgetAlignment::Full => monitor.x,
+        WidgetAlignment::Center => monitor.x + ((monitor.width - width) / 2) as i32,
+        WidgetAlignment::Right => monitor.x + (monitor.width - width) as i32,
+    };
+    Placement {
+        x,
+        y: monitor.y + monitor.height as i32 - height as i32 - y_offset,
+        width,
+        height,
+    }
+}
+
+// 名前で選んだモニター。見つからなければ None（呼び出し側で現在のモニターか主モニターを使う）
+pub fn find_monitor<'a>(
+    monitors: &'a [MonitorInfo],
+    name: Option<&str>,
+) -> Option<&'a MonitorInfo> {
+    let name = name?;
+    monitors
+        .iter()
+        .find(|monitor| monitor.name.as_deref() == Some(name))
+}
+
+pub fn clamp_fps(fps: u32) -> u32 {
+    fps.clamp(MIN_FPS, MAX_FPS)
+}
+
+// ウィジェットの描画に要る分だけのユニット（位置は戦場の長さを 0〜65535 に、体力は ％）
+#[derive(Clone, Copy, Serialize, Debug, TS)]
+pub struct WidgetUnit {
+    pub x: u16,
+    // 0: 小型, 1: 中型, 2: 大型, 3: 英雄
+    pub kind: u8,
+    pub hp: u8,
+    pub lane: u8,
+}
+
+impl WidgetUnit {
+    fn new(unit: &Unit) -> Self {
+        let x = (unit.position / FIELD_LENGTH).clamp(0.0, 1.0) * u16::MAX as f32;
+        Self {
+            x: if x.is_finite() { x.round() as u16 } else { 0 },
+            kind: match unit.unit_type {
+                UnitType::Small => 0,
+                UnitType::Medium => 1,
+                UnitType::Large => 2,
+                UnitType::Hero => 3,
+            },
+            hp: percent(unit.hp, unit.max_hp),
+            lane: unit.lane,
+        }
+    }
+}
+
+fn percent(hp: f32, max_hp: f32) -> u8 {
+    if max_hp > 0.0 && hp.is_finite() {
+        (hp / max_hp * 100.0).round().clamp(0.0, 100.0) as u8
+    } else {
+        0
+    }
+}
+
+// 常に手前にあるウィジェット向けの軽い状態（ノックバックなどの内部の値は含めない）
+#[derive(Clone, Serialize, Debug, TS)]
+pub struct WidgetFrame {
+    pub stage: u32,
+    pub allies: u32,
+    pub enemies: u32,
+    pub player_base: u8,
+    pub enemy_base: u8,
+    pub boss: bool,
+    // 控えめモードでは false（画面側で演出を出さない）
+    pub animations: bool,
+    pub player_units: Vec<WidgetUnit>,
+    pub enemy_units: Vec<WidgetUnit>,
+}
+
+impl WidgetFrame {
+    pub fn new(game: &GameState, animations: bool) -> Self {
+        Self {
+            stage: game.stage,
+            allies: game.player_units.len() as u32,
+            enemies: game.enemy_units.len() as u32,
+            player_base: percent(game.player_base_hp, game.max_player_base_hp),
+            enemy_base: percent(game.enemy_base_hp, game.max_enemy_base_hp),
+            boss: game.boss.is_some(),
+            animations,
+            player_units: game.player_units.iter().map(WidgetUnit::new).collect(),
+            enemy_units: game.enemy_units.iter().map(WidgetUnit::new).collect(),
+        }
+    }
+}
+
+pub fn clamp_opacity(opacity: f32) -> f32 {
+    if opacity.is_finite() {
+        opacity.clamp(MIN_OPACITY, MAX_OPACITY)
+    } else {
+        MAX_OPACITY
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Debug, TS)]
+pub struct WidgetStyle {
+    pub opacity: f32,
+    pub compact: bool,
+}
+
+// ウィジェットを操作できる状態か（false ならクリックを背面のウィンドウへ透過する）、
+// トレイから非表示にされているか、不透明度（f32 のビット列）とコンパクト表示か、
+// widget-update を送る頻度
+#[derive(Default)]
+pub struct WidgetMode {
+    interactive: AtomicBool,
+    hidden: AtomicBool,
+    opacity: AtomicU32,
+    compact: AtomicBool,
+    fps: AtomicU32,
+}
+
+impl WidgetMode {
+    pub fn new(interactive: bool, opacity: f32, compact: bool, fps: u32) -> Self {
+        Self {
+            interactive: AtomicBool::new(interactive),
+            hidden: AtomicBool::new(false),
+            opacity: AtomicU32::new(clamp_opacity(opacity).to_bits()),
+            compact: AtomicBool::new(compact),
+            fps: AtomicU32::new(clamp_fps(fps)),
+        }
+    }
+
+    pub fn set_fps(&self, fps: u32) {
+        self.fps.store(clamp_fps(fps), Ordering::Relaxed);
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / clamp_fps(self.fps.load(Ordering::Relaxed))
+    }
+
+    pub fn style(&self) -> WidgetStyle {
+        WidgetStyle {
+            opacity: f32::from_bits(self.opacity.load(Ordering::Relaxed)),
+            compact: self.is_compact(),
+        }
+    }
+
+    // 丸めた値を返す
+    pub fn set_opacity(&self, opacity: f32) -> f32 {
+        let opacity = clamp_opacity(opacity);
+        self.opacity.store(opacity.to_bits(), Ordering::Relaxed);
+        opacity
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.compact.load(Ordering::Relaxed)
+    }
+
+    pub fn set_compact(&self, compact: bool) {
+        self.compact.store(compact, Ordering::Relaxed);
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.load(Ordering::Relaxed)
+    }
+
+    pub fn set_hidden(&self, hidden: bool) {
+        self.hidden.store(hidden, Ordering::Relaxed);
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive.load(Ordering::Relaxed)
+    }
+
+    pub fn set_interactive(&self, interactive: bool) {
+        self.interactive.store(interactive, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,247 @@
+// src-tauri/src/event_catalog.rs のペイロードの型から生成する（手で編集せず UPDATE_EVENT_TYPES=1 cargo test events で書き直す）
+
+export type AchievementStatus = { id: string, name: string, description: string, metric: Metric, threshold: number, progress: number, unlocked_at: number | null, };
+
+export type ArmySnapshot = { player_id: string, player_name: string, upgrades: Upgrades, prestige_attack: number, prestige_hp: number, created_at: number, };
+
+export type BackendAttempt = { backend: string, reason: string, };
+
+export type BattleEvent = { "kind": "spawn", time: number, id: number, } | { "kind": "defeated", time: number, id: number, by: number, } | { "kind": "base_destroyed", time: number, attacker: boolean, };
+
+export type BattleFrame = { time: number, attacker_base_hp: number, defender_base_hp: number, units: Array<FrameUnit>, };
+
+export type BattleReport = { stage: number, duration_secs: number, damage_by_type: DamageByType, mvp: UnitContribution | null, coins_earned: number, enemies_defeated: number, units_lost: number, finished_at: number, };
+
+export type BattleResult = { attacker: ArmySnapshot, defender: ArmySnapshot, winner: BattleWinner, duration_secs: number, attacker_base_hp: number, defender_base_hp: number, frames: Array<BattleFrame>, events: Array<BattleEvent>, };
+
+export type BattleWinner = "attacker" | "defender" | "draw";
+
+export type BossStatus = { name: string, hp: number, max_hp: number, phase: number, };
+
+export type ComboMilestone = { combo: number, multiplier: number, };
+
+export type CommandHelp = { name: string, usage: string, description: string, examples: Array<string>, };
+
+export type CommandOutcome = { command: string, message: string, help: Array<CommandHelp>, };
+
+export type CompactWidgetUpdate = { coins_display: string, stage: number, player_base: number, enemy_base: number, front_line: number, farming: boolean, boss: boolean, combo: number, animations: boolean, };
+
+export type CoopPlayer = "a" | "b";
+
+export type CoopStatus = { enabled: boolean, coins_a: number, coins_b: number, kills_a: number, kills_b: number, };
+
+export type Cosmetics = { unlocked: Array<string>, avatar: string | null, skin: string | null, };
+
+export type DailyRecap = { date: string, today: StatsTotals, yesterday: StatsTotals, coins_change_percent: number | null, };
+
+export type DamageByType = { small: number, medium: number, large: number, hero: number, };
+
+export type EnemyKind = "standard" | "armored" | "fast" | "swarm";
+
+export type Failure = "panicked" | "stalled" | "stopped";
+
+export type FinalBlow = { stage: number, attacker_id: number, attacker_type: UnitType, attacker_ability: UnitAbility, attacker_position: number, defender: string, defender_max_hp: number, damage: number, };
+
+export type FocusBlock = { started_at: number, duration_secs: number, ended_at: number | null, completed: boolean, keystrokes: number, bonus_units: number, distractions: number, };
+
+export type ForwardBarracks = { position: number, };
+
+export type FrameUnit = { id: number, unit_type: UnitType, attacker: boolean, position: number, hp: number, };
+
+export type GameError = { message: string, } & ({ "code": "not_enough_coins", needed: number, have: number, } | { "code": "not_enough_prestige_points", needed: number, have: number, } | { "code": "invalid_upgrade", upgrade_type: string, } | { "code": "unknown_command", name: string, } | { "code": "invalid_command_arguments", usage: string, } | { "code": "auto_buy_entry_not_found", index: number, } | { "code": "nothing_to_respec" } | { "code": "invalid_prestige_upgrade", kind: string, } | { "code": "stage_locked" } | { "code": "prestige_locked", min_stage: number, } | { "code": "chest_not_found", chest_id: number, } | { "code": "barracks_already_built" } | { "code": "invalid_barracks_position" } | { "code": "position_not_captured" } | { "code": "invalid_rally_point" } | { "code": "invalid_stance" } | { "code": "invalid_order" } | { "code": "no_units_ordered" } | { "code": "focus_already_running" } | { "code": "invalid_focus_duration" } | { "code": "invalid_save_slot", slot: number, } | { "code": "save_slot_empty", slot: number, } | { "code": "checkpoint_not_found", id: string, } | { "code": "save_locked" } | { "code": "save_not_passphrase_protected" } | { "code": "wrong_passphrase" } | { "code": "save_tampered" } | { "code": "invalid_profile_name" } | { "code": "invalid_macro_name" } | { "code": "macro_not_found", name: string, } | { "code": "macro_already_recording" } | { "code": "macro_not_recording" } | { "code": "empty_macro" } | { "code": "replay_not_recording" } | { "code": "invalid_game_rules", min: number, max: number, } | { "code": "difficulty_needs_reset" } | { "code": "no_local_network" } | { "code": "remote_play_port_unavailable", port: number, } | { "code": "widget_unavailable" } | { "code": "invalid_hotkey", hotkey: string, } | { "code": "invalid_friend" } | { "code": "too_many_friends", max: number, } | { "code": "sync_conflict_pending" } | { "code": "no_sync_conflict" } | { "code": "title_not_earned", id: string, } | { "code": "unknown_cosmetic", id: string, } | { "code": "cosmetic_locked", id: string, } | { "code": "save_code_corrupt" } | { "code": "save_code_too_new", version: number, supported: number, } | { "code": "save_code_checksum_mismatch" } | { "code": "quest_not_found", quest_id: string, } | { "code": "quest_not_complete", quest_id: string, } | { "code": "quest_already_claimed", quest_id: string, } | { "code": "summon_on_cooldown", remaining_secs: number, } | { "code": "item_on_cooldown", item: string, remaining_secs: number, } | { "code": "no_items_left", item: string, } | { "code": "item_stack_full", item: string, max: number, } | { "code": "shop_stock_expired" } | { "code": "shop_offer_not_found", item: string, } | { "code": "shop_item_owned", item: string, } | { "code": "not_enough_energy", needed: number, have: number, } | { "code": "energy_mode_off" } | { "code": "not_energy_summonable", unit_type: string, } | { "code": "typing_history_unsupported" } | { "code": "typing_history_too_large", max_bytes: number, } | { "code": "typing_history_already_imported" } | { "code": "storage", message: string, } | { "code": "multiplayer", error: MultiplayerError, retryable: boolean, } | { "code": "internal", message: string, });
+
+export type GameErrorFields = { "code": "not_enough_coins", needed: number, have: number, } | { "code": "not_enough_prestige_points", needed: number, have: number, } | { "code": "invalid_upgrade", upgrade_type: string, } | { "code": "unknown_command", name: string, } | { "code": "invalid_command_arguments", usage: string, } | { "code": "auto_buy_entry_not_found", index: number, } | { "code": "nothing_to_respec" } | { "code": "invalid_prestige_upgrade", kind: string, } | { "code": "stage_locked" } | { "code": "prestige_locked", min_stage: number, } | { "code": "chest_not_found", chest_id: number, } | { "code": "barracks_already_built" } | { "code": "invalid_barracks_position" } | { "code": "position_not_captured" } | { "code": "invalid_rally_point" } | { "code": "invalid_stance" } | { "code": "invalid_order" } | { "code": "no_units_ordered" } | { "code": "focus_already_running" } | { "code": "invalid_focus_duration" } | { "code": "invalid_save_slot", slot: number, } | { "code": "save_slot_empty", slot: number, } | { "code": "checkpoint_not_found", id: string, } | { "code": "save_locked" } | { "code": "save_not_passphrase_protected" } | { "code": "wrong_passphrase" } | { "code": "save_tampered" } | { "code": "invalid_profile_name" } | { "code": "invalid_macro_name" } | { "code": "macro_not_found", name: string, } | { "code": "macro_already_recording" } | { "code": "macro_not_recording" } | { "code": "empty_macro" } | { "code": "replay_not_recording" } | { "code": "invalid_game_rules", min: number, max: number, } | { "code": "difficulty_needs_reset" } | { "code": "no_local_network" } | { "code": "remote_play_port_unavailable", port: number, } | { "code": "widget_unavailable" } | { "code": "invalid_hotkey", hotkey: string, } | { "code": "invalid_friend" } | { "code": "too_many_friends", max: number, } | { "code": "sync_conflict_pending" } | { "code": "no_sync_conflict" } | { "code": "title_not_earned", id: string, } | { "code": "unknown_cosmetic", id: string, } | { "code": "cosmetic_locked", id: string, } | { "code": "save_code_corrupt" } | { "code": "save_code_too_new", version: number, supported: number, } | { "code": "save_code_checksum_mismatch" } | { "code": "quest_not_found", quest_id: string, } | { "code": "quest_not_complete", quest_id: string, } | { "code": "quest_already_claimed", quest_id: string, } | { "code": "summon_on_cooldown", remaining_secs: number, } | { "code": "item_on_cooldown", item: string, remaining_secs: number, } | { "code": "no_items_left", item: string, } | { "code": "item_stack_full", item: string, max: number, } | { "code": "shop_stock_expired" } | { "code": "shop_offer_not_found", item: string, } | { "code": "shop_item_owned", item: string, } | { "code": "not_enough_energy", needed: number, have: number, } | { "code": "energy_mode_off" } | { "code": "not_energy_summonable", unit_type: string, } | { "code": "typing_history_unsupported" } | { "code": "typing_history_too_large", max_bytes: number, } | { "code": "typing_history_already_imported" } | { "code": "storage", message: string, } | { "code": "multiplayer", error: MultiplayerError, retryable: boolean, } | { "code": "internal", message: string, };
+
+export type GameStateUpdate = { player_units: Array<Unit> | null, enemy_units: Array<Unit> | null, player_base_hp: number, enemy_base_hp: number, coins: number, coins_display: string, coins_grouped: string, stage: number, click_count: number, type_count: number, upgrades: Upgrades, farming: boolean, front_line: number, lanes: number, morale: number, barracks: ForwardBarracks | null, rally: RallyPoint | null, stance: Stance, boss: BossStatus | null, combo: number, combo_multiplier: number, unopened_chests: number, summons: Array<SummonStatus>, items: Array<ItemStatus>, invasion: InvasionStatus, time_scale: number, input_heat: Array<number>, coop: CoopStatus | null, energy: number, animations: boolean, };
+
+export type HotkeyAction = "toggle_pause" | "reset_stage" | "toggle_widget" | "buy_cheapest_upgrade" | "toggle_low_distraction";
+
+export type HotkeyActionFeedback = { action: HotkeyAction, ok: boolean, message: string, };
+
+export type IdleUpdate = { coins: number | null, stage: number | null, player_base_hp: number | null, enemy_base_hp: number | null, player_units: number | null, enemy_units: number | null, };
+
+export type Incident = { subsystem: Subsystem, failure: Failure, message: string, at: number, restarted: boolean, };
+
+export type InputBackendKind = "auto" | "native" | "evdev" | "disabled";
+
+export type InputBackendStatus = { requested: InputBackendKind, active: string | null, capabilities: InputCapabilities, warning: string | null, failed: Array<BackendAttempt>, restart_required: boolean, };
+
+export type InputCapabilities = { keyboard: boolean, mouse_buttons: boolean, wheel: boolean, mouse_move: boolean, };
+
+export type InvasionEvent = { "phase": "warning", stage: number, enemies: number, arrives_in: number, } | { "phase": "started", stage: number, enemies: number, } | { "phase": "repelled", stage: number, coins: number, } | { "phase": "failed", stage: number, };
+
+export type InvasionStatus = { arrives_in: number | null, remaining: number, repelled: number, };
+
+export type ItemKind = "airstrike" | "freeze_bomb" | "repair_kit";
+
+export type ItemStatus = { kind: ItemKind, count: number, price: number, cooldown_remaining: number, cooldown_total: number, active_remaining: number, };
+
+export type LoopStatus = { paused: boolean, tick_rate: number, idle: boolean, low_distraction: boolean, };
+
+export type Metric = "clicks" | "keystrokes" | "enemies_defeated" | "stages_cleared" | "upgrades_purchased";
+
+export type MultiplayerError = { "kind": "not_configured" } | { "kind": "not_registered" } | { "kind": "timeout" } | { "kind": "network", message: string, } | { "kind": "client_error", status: number, } | { "kind": "server_error", status: number, } | { "kind": "schema", message: string, } | { "kind": "maintenance", message: string, retry_after_secs: number, } | { "kind": "read_only" } | { "kind": "rate_limited", retry_after_secs: number, };
+
+export type Notification = { category: NotificationCategory, severity: Severity, title: string, body: string, at: number, };
+
+export type NotificationCategory = "stage_clear" | "base_danger" | "achievement" | "auto_buy" | "sync" | "invasion";
+
+export type OfflineProgress = { seconds: number, enemies_defeated: number, coins: number, };
+
+export type Order = { "type": "move", position: number, } | { "type": "attack", target_id: number, };
+
+export type PlayerProfile = { player_id: string, player_name: string, progress: PlayerProgressData, last_update: number, guild_id: string | null, season: number, seasons: Array<SeasonResult>, };
+
+export type PlayerProgressData = { stage: number, coins: number, upgrades: Upgrades, max_player_base_hp: number, max_enemy_base_hp: number, prestige: PrestigeState | null, titles: Titles | null, cosmetics: Cosmetics | null, };
+
+export type Politeness = "polite" | "assertive";
+
+export type PrestigeState = { points: number, total_points_earned: number, prestige_count: number, highest_stage: number, best_stage: number, upgrades: PrestigeUpgrades, shop_items: Array<string>, };
+
+export type PrestigeUpgrades = { attack: number, hp: number, coin: number, };
+
+export type Quest = { id: string, period: QuestPeriod, metric: QuestMetric, description: string, target: number, progress: number, reward: number, claimed: boolean, };
+
+export type QuestMetric = "small_units" | "medium_units" | "stages_cleared" | "keystrokes" | "clicks" | "enemies_defeated";
+
+export type QuestPeriod = "daily" | "weekly";
+
+export type RaidBossStatus = { raid_id: string, name: string, generation: number, hp: number, max_hp: number, contributors: number, pending_rewards: Array<string>, };
+
+export type RallyPoint = { position: number, threshold: number, };
+
+export type SeasonResult = { season: number, stage: number, coins: number, rank: number, players: number, };
+
+export type SeasonReward = { season: number, rank: number, players: number, points: number, };
+
+export type SeasonRollover = { season: number, reset: boolean, rewards: Array<SeasonReward>, points: number, };
+
+export type SequenceEvent = { stage: number, phase: SequencePhase, duration_secs: number, time_scale: number, };
+
+export type SequencePhase = "start" | "end";
+
+export type Severity = "info" | "success" | "warning" | "error";
+
+export type Stance = { "kind": "aggressive" } | { "kind": "defensive" } | { "kind": "hold", position: number, };
+
+export type StateSummary = { text: string, sentences: Array<string>, politeness: Politeness, locale: string, };
+
+export type StatsTotals = { coins_earned: number, units_spawned: number, enemies_killed: number, actions: number, keys: number, stages_cleared: number, active_minutes: number, average_apm: number, peak_apm: number, };
+
+export type Subsystem = "game_loop" | "input_hook";
+
+export type SummonStatus = { unit_type: UnitType, cost: number, purchases: number, cooldown_remaining: number, cooldown_total: number, };
+
+export type SyncConflict = { local: PlayerProgressData, remote: PlayerProgressData, merged: PlayerProgressData, remote_update: number, remote_age_secs: number, };
+
+export type SyncStatus = { enabled: boolean, connected: boolean, pending: boolean, last_success: number | null, last_attempt: number | null, error: GameErrorFields | null, consecutive_failures: number, next_attempt: number | null, };
+
+export type Titles = { earned: Array<string>, active: string | null, };
+
+export type Unit = { id: number, unit_type: UnitType, position: number, hp: number, max_hp: number, attack: number, speed: number, is_player: boolean, target_id: number | null, knockback_velocity: number, knockback_time: number, knockback_total: number, attack_cooldown: number, invulnerable_time: number, stealthed: boolean, is_flying: boolean, ability: UnitAbility, ability_cooldown: number, rallying: boolean, lane: number, owner: CoopPlayer, kind: EnemyKind, order: UnitOrder | null, };
+
+export type UnitAbility = "melee" | "ranged" | "healer" | "tank";
+
+export type UnitContribution = { unit_id: number, unit_type: UnitType, damage: number, kills: number, };
+
+export type UnitOrder = { order: Order, remaining: number, };
+
+export type UnitType = "Small" | "Medium" | "Large" | "Hero";
+
+export type Upgrades = { small_attack: number, medium_attack: number, large_attack: number, small_hp: number, medium_hp: number, large_hp: number, small_speed: number, medium_speed: number, large_speed: number, coin_rate: number, base_hp: number, heal_radius: number, heal_rate: number, };
+
+export type WidgetFrame = { stage: number, allies: number, enemies: number, player_base: number, enemy_base: number, boss: boolean, animations: boolean, player_units: Array<WidgetUnit>, enemy_units: Array<WidgetUnit>, };
+
+export type WidgetStyle = { opacity: number, compact: boolean, };
+
+export type WidgetUnit = { x: number, kind: number, hp: number, lane: number, };
+
+export const EVENTS = {
+  GameUpdate: "game-update",
+  GameIdleUpdate: "game-idle-update",
+  GameHeartbeat: "game-heartbeat",
+  IdleChanged: "idle-changed",
+  LowDistractionChanged: "low-distraction-changed",
+  OfflineProgress: "offline-progress",
+  LocaleChanged: "locale-changed",
+  PresentationMode: "presentation-mode",
+  HotkeyAction: "hotkey-action",
+  InputBackendChanged: "input-backend-changed",
+  CommandExecuted: "command-executed",
+  CommandFailed: "command-failed",
+  SubsystemDown: "subsystem-down",
+  StorageError: "storage-error",
+  UnitSpawned: "unit-spawned",
+  UnitRemoved: "unit-removed",
+  UnitMoved: "unit-moved",
+  AbilityCooldowns: "ability-cooldowns",
+  StageReport: "stage-report",
+  Invasion: "invasion",
+  ComboMilestone: "combo-milestone",
+  FinalBlow: "final-blow",
+  StageClearSequence: "stage-clear-sequence",
+  PvpBattleResult: "pvp-battle-result",
+  WidgetUpdate: "widget-update",
+  WidgetCompactUpdate: "widget-compact-update",
+  WidgetStyle: "widget-style",
+  WidgetInteractive: "widget-interactive",
+  MpRemoteUpdate: "mp-remote-update",
+  MpSyncConflict: "mp-sync-conflict",
+  MpSyncStatus: "mp-sync-status",
+  MpMaintenance: "mp-maintenance",
+  FriendsUpdate: "friends-update",
+  RaidUpdate: "raid-update",
+  SeasonRollover: "season-rollover",
+  A11ySummary: "a11y-summary",
+  AchievementUnlocked: "achievement-unlocked",
+  QuestCompleted: "quest-completed",
+  FocusCompleted: "focus-completed",
+  Notification: "notification",
+  DailyRecap: "daily-recap",
+} as const;
+
+export type EventName = (typeof EVENTS)[keyof typeof EVENTS];
+
+export interface EventPayloads {
+  "game-update": GameStateUpdate;
+  "game-idle-update": IdleUpdate;
+  "game-heartbeat": LoopStatus;
+  "idle-changed": LoopStatus;
+  "low-distraction-changed": LoopStatus;
+  "offline-progress": OfflineProgress;
+  "locale-changed": string;
+  "presentation-mode": boolean;
+  "hotkey-action": HotkeyActionFeedback;
+  "input-backend-changed": InputBackendStatus;
+  "command-executed": CommandOutcome;
+  "command-failed": GameError;
+  "subsystem-down": Incident;
+  "storage-error": StorageFailure | null;
+  "unit-spawned": Array<Unit>;
+  "unit-removed": Array<number>;
+  "unit-moved": Array<UnitMove>;
+  "ability-cooldowns": Array<AbilityCooldown>;
+  "stage-report": BattleReport;
+  "invasion": InvasionEvent;
+  "combo-milestone": ComboMilestone;
+  "final-blow": FinalBlow;
+  "stage-clear-sequence": SequenceEvent;
+  "pvp-battle-result": BattleResult;
+  "widget-update": WidgetFrame;
+  "widget-compact-update": CompactWidgetUpdate;
+  "widget-style": WidgetStyle;
+  "widget-interactive": boolean;
+  "mp-remote-update": PlayerProfile;
+  "mp-sync-conflict": SyncConflict;
+  "mp-sync-status": SyncStatus;
+  "mp-maintenance": MaintenanceNotice | null;
+  "friends-update": Array<FriendProgress>;
+  "raid-update": RaidBossStatus;
+  "season-rollover": SeasonRollover;
+  "a11y-summary": StateSummary;
+  "achievement-unlocked": AchievementStatus;
+  "quest-completed": Quest;
+  "focus-completed": FocusBlock;
+  "notification": Notification;
+  "daily-recap": DailyRecap;
+}